@@ -0,0 +1,66 @@
+//! The dedicated server. It runs the world without a window, and players join it with `--connect <address>`.
+//!
+//! Run it with `--help` for the options, which are the same as the ones of the game about the world. Type `help` into
+//! the console for the admin commands.
+
+use std::time::Duration;
+
+use bevy::{
+    app::{AppExit, ScheduleRunnerPlugin},
+    log::LogPlugin,
+    prelude::*,
+};
+use clap::Parser;
+use voxel_engine::{
+    cli::WorldArgs,
+    console::{ConsoleCommand, RegisterConsoleCommand, StdinConsolePlugin},
+    voxel::{
+        net::{NetworkMode, DEFAULT_PORT},
+        VoxelDedicatedServerPlugin,
+    },
+};
+
+/// How many times per second the server updates. Block ticks run at their own fixed rate on top of this.
+const UPDATES_PER_SECOND: f64 = 60.0;
+
+/// The dedicated server of the voxel game. The options about new worlds only apply while there's no world saved in the
+/// world directory yet.
+#[derive(Parser, Debug)]
+struct Cli {
+    /// The port players join on.
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+    #[command(flatten)]
+    world_args: WorldArgs,
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    App::new()
+        // The voxel plugin decides what to add based on the network mode, so this has to be inserted first.
+        .insert_resource(NetworkMode::Host { port: cli.port })
+        .insert_resource(cli.world_args.world_dir())
+        .insert_resource(cli.world_args.preset())
+        .insert_resource(cli.world_args.mesher())
+        .insert_resource(cli.world_args.voxels())
+        .add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                1.0 / UPDATES_PER_SECOND,
+            ))),
+            LogPlugin::default(),
+            StdinConsolePlugin,
+            VoxelDedicatedServerPlugin::new(cli.world_args.voxel_config()),
+        ))
+        .register_console_command("stop", "Saves the world and stops the server")
+        .add_systems(Update, stop_server)
+        .run();
+}
+
+/// Stops the server on the `stop` console command. The world is saved on exit.
+fn stop_server(mut commands: EventReader<ConsoleCommand>, mut exit: EventWriter<AppExit>) {
+    if commands.read().any(|command| command.name == "stop") {
+        info!("Stopping the server");
+        exit.send(AppExit);
+    }
+}
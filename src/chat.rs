@@ -0,0 +1,327 @@
+use std::collections::VecDeque;
+
+use bevy::{input::InputSystem, prelude::*};
+use bevy_egui::{EguiPlugin, EguiSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    console::{ConsoleCommand, ConsoleCommands},
+    voxel::net::{NetworkMode, PlayerName},
+};
+
+/// Longer messages are cut off, in characters.
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 256;
+/// How many lines the chat keeps. Older lines are forgotten.
+const MAX_CHAT_LINES: usize = 100;
+/// How many lines are shown at once.
+const VISIBLE_CHAT_LINES: usize = 10;
+/// How long a new line stays visible while the chat is closed, in seconds.
+const CHAT_LINE_LIFETIME: f64 = 10.0;
+
+/// This plugin adds the chat, which shows [ChatLine]s in the bottom left corner and lets the player type messages.
+///
+/// Typed messages are sent as [OutgoingChatMessage]s, which the server turns into [ChatLine]s for every player. When
+/// playing offline or hosting, this instance is the server, so they're shown right away.
+///
+/// Lines starting with `/` run a [ConsoleCommand] instead, like `/help` or `/seed`.
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        app.init_resource::<ChatLog>()
+            .init_resource::<ChatDraft>()
+            .init_resource::<ConsoleCommands>()
+            .add_state::<ChatState>()
+            .add_event::<ChatLine>()
+            .add_event::<OutgoingChatMessage>()
+            .add_event::<ConsoleCommand>()
+            .add_systems(
+                PreUpdate,
+                systems::block_game_input
+                    .after(InputSystem)
+                    .after(EguiSet::ProcessInput)
+                    .run_if(in_state(ChatState::Open)),
+            )
+            .add_systems(
+                Update,
+                (
+                    systems::open_chat.run_if(in_state(ChatState::Closed)),
+                    systems::chat_window,
+                    systems::log_chat_lines,
+                )
+                    .chain(),
+            )
+            .add_systems(OnEnter(ChatState::Open), systems::focus_draft);
+
+        // Clients get their own messages back from the server.
+        let network_mode = app.world.get_resource::<NetworkMode>();
+        if !matches!(network_mode, Some(NetworkMode::Client { .. })) {
+            app.add_systems(Update, systems::post_local_messages);
+        }
+    }
+}
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(crate) enum ChatState {
+    Open,
+    #[default]
+    Closed,
+}
+
+/// A line in the chat, sent by the server to every player.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChatLine {
+    /// The name of the player who sent it, or [None] for messages of the server itself.
+    pub sender: Option<String>,
+    /// The message. `*text*` is highlighted, and `_text_` is italic.
+    pub text: String,
+}
+
+impl ChatLine {
+    /// Cleans up a message before it's shown to everyone. Returns [None] if nothing is left of it.
+    pub fn new(sender: Option<String>, text: &str) -> Option<Self> {
+        let text: String = text
+            .trim()
+            .chars()
+            .filter(|char| !char.is_control())
+            .take(MAX_CHAT_MESSAGE_LENGTH)
+            .collect();
+
+        (!text.is_empty()).then_some(Self { sender, text })
+    }
+}
+
+/// A message the local player typed, to be sent to the server.
+#[derive(Event, Debug, Clone)]
+pub struct OutgoingChatMessage(pub String);
+
+/// The received lines, with the time they were received, oldest first.
+#[derive(Resource, Default, Debug)]
+struct ChatLog(VecDeque<(f64, ChatLine)>);
+
+impl ChatLog {
+    fn push(&mut self, received_at: f64, line: ChatLine) {
+        self.0.push_back((received_at, line));
+
+        while self.0.len() > MAX_CHAT_LINES {
+            self.0.pop_front();
+        }
+    }
+}
+
+/// What the player is typing.
+#[derive(Resource, Default, Debug)]
+struct ChatDraft {
+    text: String,
+    /// Set when the chat opens, so the text field takes the keyboard focus.
+    needs_focus: bool,
+}
+
+mod systems {
+    use bevy_egui::{
+        egui::{self, text::LayoutJob, Align, Align2, Color32, FontSelection, RichText},
+        EguiContexts,
+    };
+
+    use crate::input::{ActionInput, InputAction};
+
+    use super::*;
+
+    /// The game shouldn't react to what's typed into the chat. The chat itself reads the keyboard events instead.
+    pub(super) fn block_game_input(
+        mut keys: ResMut<Input<KeyCode>>,
+        mut mouse_buttons: ResMut<Input<MouseButton>>,
+    ) {
+        keys.reset_all();
+        mouse_buttons.reset_all();
+    }
+
+    pub(super) fn open_chat(
+        input: ActionInput,
+        mut draft: ResMut<ChatDraft>,
+        mut next_state: ResMut<NextState<ChatState>>,
+    ) {
+        if input.just_pressed(InputAction::OpenChat) {
+            draft.text.clear();
+            next_state.set(ChatState::Open);
+        } else if input.just_pressed(InputAction::OpenChatCommand) {
+            draft.text = "/".to_string();
+            next_state.set(ChatState::Open);
+        }
+    }
+
+    pub(super) fn focus_draft(mut draft: ResMut<ChatDraft>) {
+        draft.needs_focus = true;
+    }
+
+    pub(super) fn chat_window(
+        mut contexts: EguiContexts,
+        time: Res<Time>,
+        chat_state: Res<State<ChatState>>,
+        mut next_state: ResMut<NextState<ChatState>>,
+        mut chat_log: ResMut<ChatLog>,
+        mut draft: ResMut<ChatDraft>,
+        mut outgoing: EventWriter<OutgoingChatMessage>,
+        mut console_commands: EventWriter<ConsoleCommand>,
+        registered_commands: Res<ConsoleCommands>,
+    ) {
+        let now = time.elapsed_seconds_f64();
+        let open = *chat_state.get() == ChatState::Open;
+
+        let visible_lines: Vec<&ChatLine> = chat_log
+            .0
+            .iter()
+            .rev()
+            .take(VISIBLE_CHAT_LINES)
+            .filter(|(received_at, _)| open || now - received_at < CHAT_LINE_LIFETIME)
+            .map(|(_, line)| line)
+            .collect();
+        if !open && visible_lines.is_empty() {
+            return;
+        }
+
+        let mut submitted = None;
+
+        egui::Area::new("chat")
+            .anchor(Align2::LEFT_BOTTOM, [8.0, -8.0])
+            .show(contexts.ctx_mut(), |ui| {
+                egui::Frame::none()
+                    .fill(Color32::from_black_alpha(128))
+                    .inner_margin(4.0)
+                    .show(ui, |ui| {
+                        ui.set_width(400.0);
+
+                        for line in visible_lines.into_iter().rev() {
+                            ui.label(format_line(line, ui.style()));
+                        }
+
+                        if !open {
+                            return;
+                        }
+
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut draft.text)
+                                .char_limit(MAX_CHAT_MESSAGE_LENGTH)
+                                .desired_width(f32::INFINITY),
+                        );
+
+                        if draft.needs_focus {
+                            draft.needs_focus = false;
+                            response.request_focus();
+                        } else if response.lost_focus() {
+                            if ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                                submitted = Some(std::mem::take(&mut draft.text));
+                            }
+                            next_state.set(ChatState::Closed);
+                        }
+                    });
+            });
+
+        let Some(text) = submitted else {
+            return;
+        };
+
+        let Some(command_line) = text.strip_prefix('/') else {
+            outgoing.send(OutgoingChatMessage(text));
+            return;
+        };
+
+        let mut system_line = |text: String| {
+            if let Some(line) = ChatLine::new(None, &text) {
+                chat_log.push(now, line);
+            }
+        };
+
+        let Some(command) = ConsoleCommand::parse(command_line) else {
+            return;
+        };
+
+        if command.name == "help" {
+            for (name, description) in registered_commands.iter() {
+                system_line(format!("/{name}: {description}"));
+            }
+        } else if registered_commands.contains(&command.name) {
+            system_line(format!("/{command_line}"));
+            console_commands.send(command);
+        } else {
+            system_line(format!(
+                "Unknown command /{}, type /help for a list of commands",
+                command.name
+            ));
+        }
+    }
+
+    /// Adds the received lines to the chat.
+    pub(super) fn log_chat_lines(
+        time: Res<Time>,
+        mut chat_lines: EventReader<ChatLine>,
+        mut chat_log: ResMut<ChatLog>,
+    ) {
+        for line in chat_lines.read() {
+            chat_log.push(time.elapsed_seconds_f64(), line.clone());
+        }
+    }
+
+    /// Without a remote server, the messages of the player are shown right away.
+    pub(super) fn post_local_messages(
+        mut outgoing: EventReader<OutgoingChatMessage>,
+        mut chat_lines: EventWriter<ChatLine>,
+        player_name: Option<Res<PlayerName>>,
+    ) {
+        let sender = player_name.map_or_else(|| PlayerName::default().0, |name| name.0.clone());
+
+        for OutgoingChatMessage(text) in outgoing.read() {
+            if let Some(line) = ChatLine::new(Some(sender.clone()), text) {
+                chat_lines.send(line);
+            }
+        }
+    }
+
+    /// Lays out a chat line, with the name of the sender in front. `*text*` is highlighted and `_text_` is italic.
+    ///
+    /// Server messages are yellow, and have no sender.
+    fn format_line(line: &ChatLine, style: &egui::Style) -> LayoutJob {
+        let mut job = LayoutJob::default();
+        let mut append =
+            |text: RichText| text.append_to(&mut job, style, FontSelection::Default, Align::Center);
+
+        let text_color = match &line.sender {
+            Some(sender) => {
+                append(RichText::new(format!("<{sender}> ")).color(Color32::LIGHT_BLUE));
+                Color32::WHITE
+            }
+            None => Color32::YELLOW,
+        };
+
+        let mut highlighted = false;
+        let mut italic = false;
+        let mut segment = String::new();
+        for char in line.text.chars() {
+            if char != '*' && char != '_' {
+                segment.push(char);
+                continue;
+            }
+
+            let mut text = RichText::new(std::mem::take(&mut segment)).color(text_color);
+            if highlighted {
+                text = text.color(Color32::GOLD);
+            }
+            if italic {
+                text = text.italics();
+            }
+            append(text);
+
+            match char {
+                '*' => highlighted = !highlighted,
+                _ => italic = !italic,
+            }
+        }
+        append(RichText::new(segment).color(text_color));
+
+        job
+    }
+}
@@ -0,0 +1,81 @@
+//! Command line arguments shared by the game and the dedicated server.
+
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::voxel::{
+    color::VoxelMode, mesher::TerrainMesher, persistence::WorldDir, preset::TerrainPreset,
+    VoxelConfig, MAX_CHUNK_WIDTH,
+};
+
+/// The options about the world. The ones about new worlds only apply while there's no world saved in the world
+/// directory yet.
+#[derive(Args, Debug)]
+pub struct WorldArgs {
+    /// The directory the world is saved in, and loaded from.
+    #[arg(long, value_name = "PATH")]
+    pub world: Option<PathBuf>,
+    /// The seed of a new world, or a random one.
+    #[arg(long)]
+    pub seed: Option<u32>,
+    /// The terrain of a new world.
+    #[arg(long, value_parser = TerrainPreset::from_name)]
+    pub preset: Option<TerrainPreset>,
+    /// Generates a new world flat, like `--preset flat`.
+    #[arg(long, conflicts_with = "preset")]
+    pub flat: bool,
+    /// How the terrain of a new world is meshed.
+    #[arg(long, value_parser = TerrainMesher::from_name)]
+    pub mesher: Option<TerrainMesher>,
+    /// What the voxels of a new world hold.
+    #[arg(long, value_parser = VoxelMode::from_name)]
+    pub voxels: Option<VoxelMode>,
+    /// How many voxels wide, high and deep a chunk is.
+    #[arg(long, value_name = "VOXELS", value_parser = parse_chunk_width)]
+    pub chunk_width: Option<u8>,
+}
+
+impl WorldArgs {
+    pub fn world_dir(&self) -> WorldDir {
+        self.world.clone().map_or_else(WorldDir::default, WorldDir)
+    }
+
+    pub fn preset(&self) -> TerrainPreset {
+        if self.flat {
+            TerrainPreset::Flat
+        } else {
+            self.preset.unwrap_or_default()
+        }
+    }
+
+    pub fn mesher(&self) -> TerrainMesher {
+        self.mesher.unwrap_or_default()
+    }
+
+    pub fn voxels(&self) -> VoxelMode {
+        self.voxels.unwrap_or_default()
+    }
+
+    /// The default [VoxelConfig], with the chunk width and seed of the arguments.
+    pub fn voxel_config(&self) -> VoxelConfig {
+        let default = VoxelConfig::default();
+        VoxelConfig {
+            chunk_width: self.chunk_width.unwrap_or(default.chunk_width),
+            seed: self.seed,
+            ..default
+        }
+    }
+}
+
+/// A chunk width has to be a power of two, and at most [MAX_CHUNK_WIDTH].
+fn parse_chunk_width(width: &str) -> Result<u8, String> {
+    let width: u8 = width.parse().map_err(|err| format!("{err}"))?;
+    if !width.is_power_of_two() || width > MAX_CHUNK_WIDTH {
+        return Err(format!(
+            "expected a power of two of at most {MAX_CHUNK_WIDTH}"
+        ));
+    }
+
+    Ok(width)
+}
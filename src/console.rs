@@ -0,0 +1,142 @@
+use std::{
+    collections::BTreeMap,
+    io::BufRead,
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+};
+
+use bevy::prelude::*;
+
+/// This plugin reads [ConsoleCommand]s from the standard input, one per line. It's meant for the dedicated server,
+/// which has no window to type into.
+///
+/// Only commands registered with [RegisterConsoleCommand::register_console_command] are sent. `help` lists them.
+pub struct StdinConsolePlugin;
+
+impl Plugin for StdinConsolePlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = mpsc::channel();
+
+        // Reading the standard input blocks, so it gets its own thread.
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        app.init_resource::<ConsoleCommands>()
+            .add_event::<ConsoleCommand>()
+            .insert_resource(StdinLines(Mutex::new(receiver)))
+            .add_systems(PreUpdate, systems::read_stdin_commands);
+    }
+}
+
+/// A command typed into a console, like `kick 1234`. The first word is the name of the command, the rest are its
+/// arguments.
+///
+/// Every plugin handles its own commands, by reading these events and checking the name.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleCommand {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl ConsoleCommand {
+    /// Splits a line into a command. Returns [None] for blank lines.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace().map(str::to_string);
+
+        Some(Self {
+            name: words.next()?.to_lowercase(),
+            args: words.collect(),
+        })
+    }
+}
+
+/// The registered console commands, and what they do.
+#[derive(Resource, Default, Debug)]
+pub struct ConsoleCommands(BTreeMap<&'static str, &'static str>);
+
+impl ConsoleCommands {
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// Every command name, with its description, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &'static str)> + '_ {
+        self.0
+            .iter()
+            .map(|(name, description)| (*name, *description))
+    }
+}
+
+pub trait RegisterConsoleCommand {
+    /// Registers a [ConsoleCommand], so consoles accept it and list it in `help`.
+    fn register_console_command(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+    ) -> &mut Self;
+}
+
+impl RegisterConsoleCommand for App {
+    fn register_console_command(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+    ) -> &mut Self {
+        self.add_event::<ConsoleCommand>()
+            .init_resource::<ConsoleCommands>()
+            .world
+            .resource_mut::<ConsoleCommands>()
+            .0
+            .insert(name, description);
+
+        self
+    }
+}
+
+/// The lines read from the standard input. The [Receiver] isn't [Sync], hence the [Mutex].
+#[derive(Resource)]
+struct StdinLines(Mutex<Receiver<String>>);
+
+mod systems {
+    use super::*;
+
+    pub(super) fn read_stdin_commands(
+        stdin_lines: Res<StdinLines>,
+        console_commands: Res<ConsoleCommands>,
+        mut commands: EventWriter<ConsoleCommand>,
+    ) {
+        let Ok(lines) = stdin_lines.0.lock() else {
+            return;
+        };
+
+        for line in lines.try_iter() {
+            let Some(command) = ConsoleCommand::parse(&line) else {
+                continue;
+            };
+
+            if command.name == "help" {
+                for (name, description) in console_commands.iter() {
+                    info!("{name}: {description}");
+                }
+            } else if console_commands.contains(&command.name) {
+                commands.send(command);
+            } else {
+                warn!(
+                    "Unknown command `{}`, type `help` for a list of commands",
+                    command.name
+                );
+            }
+        }
+    }
+}
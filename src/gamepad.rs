@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+
+use crate::settings::SettingsMenuState;
+
+/// The camera pitch is clamped to this (in radians), so it can't flip over. Matches the flycam.
+const MAX_PITCH: f32 = 1.54;
+
+/// This plugin lets the [FlyCam](bevy_flycam::FlyCam) be controlled with a gamepad.
+///
+/// Keyboard and mouse movement is still handled by the flycam itself.
+pub struct GamepadCameraPlugin;
+
+impl Plugin for GamepadCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            systems::gamepad_fly_camera.run_if(in_state(SettingsMenuState::Closed)),
+        );
+    }
+}
+
+mod systems {
+    use bevy::prelude::*;
+    use bevy_flycam::{FlyCam, MovementSettings};
+
+    use crate::{
+        input::{ActionInput, InputAction, InputAxis},
+        settings::ControlSettings,
+        voxel::{game_mode::GameMode, noclip::Noclip},
+    };
+
+    use super::MAX_PITCH;
+
+    pub(super) fn gamepad_fly_camera(
+        input: ActionInput,
+        settings: Res<ControlSettings>,
+        game_mode: Res<GameMode>,
+        noclip: Res<Noclip>,
+        movement_settings: Res<MovementSettings>,
+        time: Res<Time>,
+        mut camera_query: Query<&mut Transform, With<FlyCam>>,
+    ) {
+        let look = Vec2::new(
+            input.axis(InputAxis::LookRight),
+            input.axis(InputAxis::LookUp),
+        );
+        let movement = Vec2::new(
+            input.axis(InputAxis::MoveRight),
+            input.axis(InputAxis::MoveForward),
+        );
+        // Only creative players fly, unless they noclip.
+        let vertical = if noclip.flies(&game_mode) {
+            input.pressed(InputAction::Ascend) as i32 as f32
+                - input.pressed(InputAction::Descend) as i32 as f32
+        } else {
+            0.0
+        };
+
+        let look_speed = settings.gamepad_look_sensitivity.to_radians() * time.delta_seconds();
+        let invert_y = if settings.invert_gamepad_look_y {
+            -1.0
+        } else {
+            1.0
+        };
+
+        for mut transform in &mut camera_query {
+            if look != Vec2::ZERO {
+                let (mut yaw, mut pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+                yaw -= look.x * look_speed;
+                pitch = (pitch + look.y * look_speed * invert_y).clamp(-MAX_PITCH, MAX_PITCH);
+
+                // Order is important to prevent unintended roll
+                transform.rotation =
+                    Quat::from_axis_angle(Vec3::Y, yaw) * Quat::from_axis_angle(Vec3::X, pitch);
+            }
+
+            let local_z = transform.local_z();
+            let forward = -Vec3::new(local_z.x, 0.0, local_z.z).normalize_or_zero();
+            let right = Vec3::new(local_z.z, 0.0, -local_z.x).normalize_or_zero();
+
+            // Sticks are analog, so the velocity is only clamped, not normalized.
+            let velocity = (forward * movement.y + right * movement.x + Vec3::Y * vertical)
+                .clamp_length_max(1.0);
+
+            transform.translation += velocity * movement_settings.speed * time.delta_seconds();
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use std::{fs, path::Path};
+
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+/// Where the [InputMap] is persisted, relative to the working directory.
+const INPUT_MAP_PATH: &str = "keybindings.ron";
+
+/// This plugin loads the [InputMap], which maps [InputAction]s to the keys and buttons that trigger them.
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputMap::load(INPUT_MAP_PATH));
+    }
+}
+
+/// Everything the player can do, that can be bound to an input.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum InputAction {
+    /// Toggles flying through the terrain, whatever the [GameMode](crate::voxel::game_mode::GameMode).
+    ToggleNoclip,
+    ToggleSettingsMenu,
+    /// Grabs or releases the mouse cursor, which the flycam looks around with.
+    ToggleCursorGrab,
+    ToggleMultiplayerMenu,
+    ToggleChunkBorders,
+    ToggleVoxelGrid,
+    ToggleChunkStateHeatmap,
+    /// Toggles the global wireframe.
+    ToggleWireframe,
+    /// Toggles the wireframe of just the chunk the camera is inside.
+    ToggleChunkWireframe,
+    ToggleChunkInspector,
+    ToggleRenderDistanceGizmo,
+    /// Toggles drawing the vertex normals of the targeted chunk's mesh.
+    ToggleMeshNormals,
+    /// Toggles outlining the chunks waiting in the load and unload queues.
+    ToggleChunkQueueGizmo,
+    /// Toggles outlining voxels when they are ticked.
+    ToggleBlockTickGizmo,
+    /// Toggles the falling sand simulation.
+    ToggleFallingSand,
+    /// Skips to the next sunrise, noon, sunset or midnight.
+    SkipTimeOfDay,
+    /// Cycles how fast the time of day passes.
+    CycleTimeSpeed,
+    /// Switches to the next weather.
+    CycleWeather,
+    /// Toggles breaking and placing single micro voxels instead of whole voxels.
+    ToggleMicroEditing,
+    ToggleInventory,
+    BreakBlock,
+    PlaceBlock,
+    /// Selects a specific hotbar slot. Slots are zero-indexed.
+    HotbarSlot(u8),
+    HotbarNext,
+    HotbarPrevious,
+    /// Moves the camera up. Keyboard movement is handled by the flycam, so this is only used by gamepads.
+    Ascend,
+    /// Moves the camera down. Keyboard movement is handled by the flycam, so this is only used by gamepads.
+    Descend,
+    /// Jumps, while flying is turned off by the [GameMode](crate::voxel::game_mode::GameMode).
+    Jump,
+    OpenChat,
+    /// Opens the chat with a `/` already typed, to run a console command.
+    OpenChatCommand,
+    TakeScreenshot,
+}
+
+/// Analog inputs, like gamepad sticks. These range from -1.0 to 1.0.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum InputAxis {
+    MoveRight,
+    MoveForward,
+    LookRight,
+    LookUp,
+}
+
+/// A single key or button that an [InputAction] can be bound to.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// A button on any connected gamepad.
+    Gamepad(GamepadButtonType),
+}
+
+impl InputBinding {
+    fn is_gamepad(&self) -> bool {
+        matches!(self, InputBinding::Gamepad(_))
+    }
+}
+
+/// Resource holding the bindings of every [InputAction] and [InputAxis].
+///
+/// Systems shouldn't read this directly, but use [ActionInput] instead.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub(crate) struct InputMap {
+    bindings: HashMap<InputAction, Vec<InputBinding>>,
+    /// The gamepad axes bound to every [InputAxis].
+    axes: HashMap<InputAxis, Vec<GamepadAxisType>>,
+}
+
+impl InputMap {
+    /// Loads the input map from `path`.
+    ///
+    /// Actions missing from the file keep their default bindings. Actions bound to only keys and mouse buttons in the
+    /// file keep their default gamepad buttons, and the other way around, so files written before gamepads were
+    /// supported still get them. If the file doesn't exist, it's created with the default bindings so they can be
+    /// edited.
+    fn load(path: impl AsRef<Path>) -> Self {
+        let mut input_map = Self::default();
+
+        let Ok(contents) = fs::read_to_string(path.as_ref()) else {
+            input_map.save(path);
+            return input_map;
+        };
+
+        match ron::from_str::<InputMap>(&contents) {
+            Ok(loaded) => {
+                for (action, loaded_bindings) in loaded.bindings {
+                    let bindings = input_map.bindings.entry(action).or_default();
+                    // Bindings of a kind the file has for the action replace the defaults of that kind. Actions
+                    // without any bindings in the file are unbound.
+                    bindings.retain(|default| {
+                        !loaded_bindings.is_empty()
+                            && !loaded_bindings
+                                .iter()
+                                .any(|binding| binding.is_gamepad() == default.is_gamepad())
+                    });
+                    bindings.extend(loaded_bindings);
+                }
+                input_map.axes.extend(loaded.axes);
+            }
+            Err(err) => warn!("Failed to parse {}: {err}", path.as_ref().display()),
+        }
+
+        input_map
+    }
+
+    fn save(&self, path: impl AsRef<Path>) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                error!("Failed to serialize input map: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = fs::write(path.as_ref(), contents) {
+            error!("Failed to write {}: {err}", path.as_ref().display());
+        }
+    }
+
+    pub(crate) fn bindings(&self, action: InputAction) -> &[InputBinding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn axes(&self, axis: InputAxis) -> &[GamepadAxisType] {
+        self.axes.get(&axis).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let mut bindings = HashMap::from([
+            (
+                InputAction::ToggleNoclip,
+                vec![InputBinding::Key(KeyCode::X)],
+            ),
+            (
+                InputAction::ToggleSettingsMenu,
+                vec![
+                    InputBinding::Key(KeyCode::Escape),
+                    InputBinding::Gamepad(GamepadButtonType::Start),
+                ],
+            ),
+            (
+                InputAction::ToggleCursorGrab,
+                vec![InputBinding::Key(KeyCode::AltLeft)],
+            ),
+            (
+                InputAction::ToggleMultiplayerMenu,
+                vec![InputBinding::Key(KeyCode::M)],
+            ),
+            (
+                InputAction::ToggleChunkBorders,
+                vec![
+                    InputBinding::Key(KeyCode::B),
+                    InputBinding::Gamepad(GamepadButtonType::Select),
+                ],
+            ),
+            (
+                InputAction::ToggleVoxelGrid,
+                vec![InputBinding::Key(KeyCode::G)],
+            ),
+            (
+                InputAction::ToggleChunkStateHeatmap,
+                vec![InputBinding::Key(KeyCode::H)],
+            ),
+            (
+                InputAction::ToggleWireframe,
+                vec![InputBinding::Key(KeyCode::F)],
+            ),
+            (
+                InputAction::ToggleChunkWireframe,
+                vec![InputBinding::Key(KeyCode::V)],
+            ),
+            (
+                InputAction::ToggleChunkInspector,
+                vec![InputBinding::Key(KeyCode::I)],
+            ),
+            (
+                InputAction::ToggleRenderDistanceGizmo,
+                vec![InputBinding::Key(KeyCode::R)],
+            ),
+            (
+                InputAction::ToggleMeshNormals,
+                vec![InputBinding::Key(KeyCode::N)],
+            ),
+            (
+                InputAction::ToggleChunkQueueGizmo,
+                vec![InputBinding::Key(KeyCode::L)],
+            ),
+            (
+                InputAction::ToggleBlockTickGizmo,
+                vec![InputBinding::Key(KeyCode::T)],
+            ),
+            (
+                InputAction::ToggleFallingSand,
+                vec![InputBinding::Key(KeyCode::K)],
+            ),
+            (
+                InputAction::SkipTimeOfDay,
+                vec![InputBinding::Key(KeyCode::Period)],
+            ),
+            (
+                InputAction::CycleTimeSpeed,
+                vec![InputBinding::Key(KeyCode::Comma)],
+            ),
+            (
+                InputAction::CycleWeather,
+                vec![InputBinding::Key(KeyCode::Y)],
+            ),
+            (
+                InputAction::ToggleMicroEditing,
+                vec![InputBinding::Key(KeyCode::U)],
+            ),
+            (
+                InputAction::ToggleInventory,
+                vec![
+                    InputBinding::Key(KeyCode::Tab),
+                    InputBinding::Gamepad(GamepadButtonType::North),
+                ],
+            ),
+            (
+                InputAction::BreakBlock,
+                vec![
+                    InputBinding::Mouse(MouseButton::Left),
+                    InputBinding::Gamepad(GamepadButtonType::RightTrigger2),
+                ],
+            ),
+            (
+                InputAction::PlaceBlock,
+                vec![
+                    InputBinding::Mouse(MouseButton::Right),
+                    InputBinding::Gamepad(GamepadButtonType::LeftTrigger2),
+                ],
+            ),
+            (
+                InputAction::HotbarNext,
+                vec![
+                    InputBinding::Key(KeyCode::E),
+                    InputBinding::Gamepad(GamepadButtonType::RightTrigger),
+                ],
+            ),
+            (
+                InputAction::HotbarPrevious,
+                vec![
+                    InputBinding::Key(KeyCode::Q),
+                    InputBinding::Gamepad(GamepadButtonType::LeftTrigger),
+                ],
+            ),
+            (
+                InputAction::Ascend,
+                vec![InputBinding::Gamepad(GamepadButtonType::South)],
+            ),
+            (
+                InputAction::Descend,
+                vec![InputBinding::Gamepad(GamepadButtonType::East)],
+            ),
+            (
+                InputAction::Jump,
+                vec![
+                    InputBinding::Key(KeyCode::Space),
+                    InputBinding::Gamepad(GamepadButtonType::South),
+                ],
+            ),
+            (
+                InputAction::OpenChat,
+                vec![InputBinding::Key(KeyCode::Return)],
+            ),
+            (
+                InputAction::OpenChatCommand,
+                vec![InputBinding::Key(KeyCode::Slash)],
+            ),
+            (
+                InputAction::TakeScreenshot,
+                vec![InputBinding::Key(KeyCode::F2)],
+            ),
+        ]);
+
+        let number_keys = [
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+        ];
+
+        for (slot, key) in number_keys.into_iter().enumerate() {
+            bindings.insert(
+                InputAction::HotbarSlot(slot as u8),
+                vec![InputBinding::Key(key)],
+            );
+        }
+
+        let axes = HashMap::from([
+            (InputAxis::MoveRight, vec![GamepadAxisType::LeftStickX]),
+            (InputAxis::MoveForward, vec![GamepadAxisType::LeftStickY]),
+            (InputAxis::LookRight, vec![GamepadAxisType::RightStickX]),
+            (InputAxis::LookUp, vec![GamepadAxisType::RightStickY]),
+        ]);
+
+        Self { bindings, axes }
+    }
+}
+
+/// System param for reading the state of [InputAction]s and [InputAxis]es, instead of specific keys.
+#[derive(SystemParam)]
+pub(crate) struct ActionInput<'w> {
+    input_map: Res<'w, InputMap>,
+    keys: Res<'w, Input<KeyCode>>,
+    mouse_buttons: Res<'w, Input<MouseButton>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl ActionInput<'_> {
+    /// Returns true if any of the bindings of the action are pressed.
+    pub(crate) fn pressed(&self, action: InputAction) -> bool {
+        self.input_map
+            .bindings(action)
+            .iter()
+            .any(|binding| match binding {
+                InputBinding::Key(key) => self.keys.pressed(*key),
+                InputBinding::Mouse(button) => self.mouse_buttons.pressed(*button),
+                InputBinding::Gamepad(button_type) => self.gamepads.iter().any(|gamepad| {
+                    self.gamepad_buttons
+                        .pressed(GamepadButton::new(gamepad, *button_type))
+                }),
+            })
+    }
+
+    /// Returns true if any of the bindings of the action were pressed this frame.
+    pub(crate) fn just_pressed(&self, action: InputAction) -> bool {
+        self.input_map
+            .bindings(action)
+            .iter()
+            .any(|binding| match binding {
+                InputBinding::Key(key) => self.keys.just_pressed(*key),
+                InputBinding::Mouse(button) => self.mouse_buttons.just_pressed(*button),
+                InputBinding::Gamepad(button_type) => self.gamepads.iter().any(|gamepad| {
+                    self.gamepad_buttons
+                        .just_pressed(GamepadButton::new(gamepad, *button_type))
+                }),
+            })
+    }
+
+    /// Returns the combined value of every binding of the axis on every connected gamepad, clamped to -1.0..=1.0.
+    ///
+    /// Deadzones are applied by bevy, through the `GamepadSettings` resource.
+    pub(crate) fn axis(&self, axis: InputAxis) -> f32 {
+        let value: f32 = self
+            .input_map
+            .axes(axis)
+            .iter()
+            .flat_map(|axis_type| {
+                self.gamepads.iter().filter_map(|gamepad| {
+                    self.gamepad_axes.get(GamepadAxis::new(gamepad, *axis_type))
+                })
+            })
+            .sum();
+
+        value.clamp(-1.0, 1.0)
+    }
+}
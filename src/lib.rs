@@ -0,0 +1,16 @@
+//! A voxel game built on Bevy. The game itself is started from `main.rs`, and the dedicated server from
+//! `bin/server.rs`. Both are put together from the plugins in here.
+
+// Bevy systems routinely take more parameters than clippy's default limit.
+#![allow(clippy::too_many_arguments)]
+
+pub mod chat;
+pub mod cli;
+pub mod console;
+pub mod gamepad;
+pub mod input;
+pub mod multiplayer;
+pub mod screenshot;
+pub mod settings;
+pub mod sky;
+pub mod voxel;
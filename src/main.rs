@@ -1,4 +1,4 @@
-mod voxel;
+use std::path::PathBuf;
 
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
@@ -10,10 +10,119 @@ use bevy::{
     },
 };
 use bevy_flycam::{FlyCam, NoCameraPlayerPlugin};
-use voxel::{load::RenderDistance, VoxelPlugin};
+use clap::Parser;
+use voxel_engine::{
+    chat::ChatPlugin,
+    cli::WorldArgs,
+    gamepad::GamepadCameraPlugin,
+    input::InputMapPlugin,
+    multiplayer::MultiplayerMenuPlugin,
+    screenshot::ScreenshotPlugin,
+    settings::{GameSettings, RenderDistanceOverride, SettingsPlugin},
+    sky::SkyPlugin,
+    voxel::{
+        bench::{
+            bench_world_dir, VoxelBenchPlugin, BENCH_RENDER_DISTANCE, BENCH_SEED,
+            DEFAULT_BENCH_REPORT,
+        },
+        load::RenderDistance,
+        net::{NetworkMode, PlayerName, DEFAULT_PORT},
+        persistence::WorldDir,
+        replay::PlayReplay,
+        VoxelConfig, VoxelPlugin,
+    },
+};
+
+/// A voxel game. The options about new worlds only apply while there's no world saved in the world directory yet.
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Hosts a game other players can join, on the port, or the default one.
+    #[arg(long, value_name = "PORT", num_args = 0..=1)]
+    host: Option<Option<u16>>,
+    /// Joins the server at the address, like `localhost` or `192.168.0.2:5000`.
+    #[arg(long, value_name = "ADDRESS", conflicts_with = "host")]
+    connect: Option<String>,
+    /// The name other players see in the chat.
+    #[arg(long)]
+    name: Option<String>,
+    #[command(flatten)]
+    world_args: WorldArgs,
+    /// How many chunks around the camera are loaded, instead of the render distance of the settings. The settings keep
+    /// their own.
+    #[arg(long, value_name = "CHUNKS")]
+    render_distance: Option<u32>,
+    /// Hides the inspector windows, and doesn't log the frame rate.
+    #[arg(long)]
+    no_debug_tools: bool,
+    /// Plays the replay with this name from the replays directory as soon as the game starts.
+    #[arg(long, value_name = "NAME")]
+    replay: Option<String>,
+    /// Flies along a fixed path over a fresh world with a fixed seed, writes a report of the performance to
+    /// REPORT.json and REPORT.csv, and exits.
+    #[arg(
+        long,
+        value_name = "REPORT",
+        num_args = 0..=1,
+        conflicts_with_all = ["host", "connect", "world", "seed", "replay"]
+    )]
+    bench: Option<Option<PathBuf>>,
+}
+
+impl Cli {
+    fn network_mode(&self) -> NetworkMode {
+        if let Some(port) = self.host {
+            NetworkMode::Host {
+                port: port.unwrap_or(DEFAULT_PORT),
+            }
+        } else if let Some(addr) = &self.connect {
+            NetworkMode::connect(addr)
+        } else {
+            NetworkMode::Offline
+        }
+    }
+
+    fn world_dir(&self) -> WorldDir {
+        if self.bench.is_some() {
+            bench_world_dir()
+        } else {
+            self.world_args.world_dir()
+        }
+    }
+
+    fn voxel_config(&self) -> VoxelConfig {
+        let config = self.world_args.voxel_config();
+        VoxelConfig {
+            seed: if self.bench.is_some() {
+                Some(BENCH_SEED)
+            } else {
+                config.seed
+            },
+            debug_tools: !self.no_debug_tools,
+            ..config
+        }
+    }
+}
 
 fn main() {
-    App::new()
+    let cli = Cli::parse();
+    let mut app = App::new();
+    if let Some(name) = cli.replay.clone() {
+        // Picked up by the voxel plugin, like the resources below.
+        app.insert_resource(PlayReplay(name));
+    }
+
+    app
+        // The voxel plugin decides what to add based on the network mode, so this has to be inserted first.
+        .insert_resource(cli.network_mode())
+        .insert_resource(
+            cli.name
+                .clone()
+                .map_or_else(PlayerName::default, PlayerName),
+        )
+        .insert_resource(cli.world_dir())
+        .insert_resource(cli.world_args.preset())
+        .insert_resource(cli.world_args.mesher())
+        .insert_resource(cli.world_args.voxels())
         .add_plugins((
             DefaultPlugins.set(RenderPlugin {
                 render_creation: RenderCreation::Automatic(WgpuSettings {
@@ -24,9 +133,15 @@ fn main() {
             }),
             WireframePlugin,
             FrameTimeDiagnosticsPlugin,
-            LogDiagnosticsPlugin::default(),
             NoCameraPlayerPlugin,
-            VoxelPlugin,
+            InputMapPlugin,
+            VoxelPlugin::new(cli.voxel_config()),
+            SettingsPlugin,
+            GamepadCameraPlugin,
+            SkyPlugin,
+            ChatPlugin,
+            MultiplayerMenuPlugin,
+            ScreenshotPlugin,
         ))
         .insert_resource(WireframeConfig {
             // The global wireframe config enables drawing of wireframes on every mesh,
@@ -37,17 +152,42 @@ fn main() {
             // Can be changed per mesh using the `WireframeColor` component.
             default_color: Color::WHITE,
         })
-        .add_systems(Startup, setup_cam)
-        .run();
+        .add_systems(Startup, setup_cam);
+
+    if !cli.no_debug_tools {
+        app.add_plugins(LogDiagnosticsPlugin::default());
+    }
+    if let Some(report_path) = cli.bench.clone() {
+        app.add_plugins(VoxelBenchPlugin {
+            report_path: report_path.unwrap_or_else(|| PathBuf::from(DEFAULT_BENCH_REPORT)),
+        });
+    }
+    let render_distance = cli
+        .render_distance
+        .or(cli.bench.is_some().then_some(BENCH_RENDER_DISTANCE));
+    app.insert_resource(RenderDistanceOverride(render_distance));
+
+    app.run();
 }
 
-fn setup_cam(mut commands: Commands) {
+fn setup_cam(
+    mut commands: Commands,
+    settings: Res<GameSettings>,
+    render_distance_override: Res<RenderDistanceOverride>,
+) {
     commands.spawn((
         Camera3dBundle {
             transform: Transform::from_xyz(0.0, 0.0, 0.0),
+            projection: Projection::Perspective(PerspectiveProjection {
+                fov: settings.fov.to_radians(),
+                ..default()
+            }),
             ..default()
         },
         FlyCam,
-        RenderDistance::new(5, 2),
+        RenderDistance::new(
+            render_distance_override.render_distance(&settings),
+            settings.unload_margin,
+        ),
     ));
 }
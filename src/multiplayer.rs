@@ -0,0 +1,147 @@
+use std::{net::SocketAddr, process::Command};
+
+use bevy::{app::AppExit, prelude::*};
+use bevy_egui::EguiPlugin;
+
+use crate::voxel::net::{LanServerBrowser, LanServerBrowserPlugin, NetworkMode, PlayerName};
+
+/// This plugin adds the multiplayer menu, which lists the servers on the local network.
+///
+/// The network mode is picked when the game starts, so joining a server starts the game again, connected to it.
+pub struct MultiplayerMenuPlugin;
+
+impl Plugin for MultiplayerMenuPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        app.add_plugins(LanServerBrowserPlugin)
+            .add_state::<MultiplayerMenuState>()
+            .add_systems(
+                Update,
+                (
+                    systems::toggle_multiplayer_menu,
+                    systems::multiplayer_menu.run_if(in_state(MultiplayerMenuState::Open)),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                OnEnter(MultiplayerMenuState::Open),
+                systems::start_searching,
+            )
+            .add_systems(OnExit(MultiplayerMenuState::Open), systems::stop_searching);
+    }
+}
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(crate) enum MultiplayerMenuState {
+    Open,
+    #[default]
+    Closed,
+}
+
+/// Starts the game again, connected to the server at `server_addr`, and quits this one.
+fn join_server(server_addr: SocketAddr, player_name: &str, exit: &mut EventWriter<AppExit>) {
+    let result = std::env::current_exe().and_then(|exe| {
+        Command::new(exe)
+            .args(["--connect", &server_addr.to_string(), "--name", player_name])
+            .spawn()
+    });
+
+    match result {
+        Ok(_) => exit.send(AppExit),
+        Err(err) => error!("Couldn't start the game to join {server_addr}: {err}"),
+    }
+}
+
+mod systems {
+    use bevy_egui::{egui, EguiContexts};
+
+    use crate::input::{ActionInput, InputAction};
+
+    use super::*;
+
+    pub(super) fn toggle_multiplayer_menu(
+        input: ActionInput,
+        mut next_state: ResMut<NextState<MultiplayerMenuState>>,
+        cur_state: Res<State<MultiplayerMenuState>>,
+    ) {
+        if input.just_pressed(InputAction::ToggleMultiplayerMenu) {
+            next_state.set(match **cur_state {
+                MultiplayerMenuState::Open => MultiplayerMenuState::Closed,
+                MultiplayerMenuState::Closed => MultiplayerMenuState::Open,
+            })
+        }
+    }
+
+    pub(super) fn start_searching(mut browser: ResMut<LanServerBrowser>) {
+        browser.set_searching(true);
+    }
+
+    pub(super) fn stop_searching(mut browser: ResMut<LanServerBrowser>) {
+        browser.set_searching(false);
+    }
+
+    pub(super) fn multiplayer_menu(
+        mut contexts: EguiContexts,
+        browser: Res<LanServerBrowser>,
+        network_mode: Res<NetworkMode>,
+        player_name: Option<Res<PlayerName>>,
+        mut next_state: ResMut<NextState<MultiplayerMenuState>>,
+        mut exit: EventWriter<AppExit>,
+    ) {
+        let player_name =
+            player_name.map_or_else(|| PlayerName::default().0, |name| name.0.clone());
+
+        egui::Window::new("Multiplayer")
+            .collapsible(false)
+            .resizable(false)
+            .show(contexts.ctx_mut(), |ui| {
+                match *network_mode {
+                    NetworkMode::Offline => ui.label("Playing offline"),
+                    NetworkMode::Host { port } => ui.label(format!("Hosting on port {port}")),
+                    NetworkMode::Client { server_addr } => {
+                        ui.label(format!("Connected to {server_addr}"))
+                    }
+                };
+                ui.label(format!("Playing as {player_name}"));
+
+                ui.separator();
+
+                let servers = browser.servers();
+                if servers.is_empty() {
+                    ui.label("Searching for servers on the local network...");
+                }
+
+                egui::Grid::new("lan_servers")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for server in servers {
+                            ui.label(&server.info.name);
+                            ui.label(server.addr.to_string());
+                            ui.label(format!(
+                                "{}/{} players",
+                                server.info.players, server.info.max_players
+                            ));
+
+                            let joined = matches!(
+                                *network_mode,
+                                NetworkMode::Client { server_addr } if server_addr == server.addr
+                            );
+                            if ui.add_enabled(!joined, egui::Button::new("Join")).clicked() {
+                                join_server(server.addr, &player_name, &mut exit);
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+
+                if ui.button("Close").clicked() {
+                    next_state.set(MultiplayerMenuState::Closed);
+                }
+            });
+    }
+}
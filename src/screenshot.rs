@@ -0,0 +1,448 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    prelude::*,
+    render::{camera::RenderTarget, Extract, ExtractSchedule, Render, RenderApp, RenderSet},
+};
+use bevy_egui::EguiPlugin;
+
+use crate::settings::GameSettings;
+
+/// Where screenshots are saved, relative to the working directory.
+const SCREENSHOT_DIR: &str = "screenshots";
+/// How long the confirmation stays on screen after a screenshot is saved, in seconds.
+const TOAST_DURATION: f64 = 3.0;
+/// The widest and highest a supersampled screenshot can be, in pixels. Every adapter supports textures this big.
+const MAX_SCREENSHOT_SIZE: u32 = 8192;
+
+/// This plugin saves a screenshot of the current frame to a timestamped PNG in [SCREENSHOT_DIR] whenever
+/// [InputAction::TakeScreenshot](crate::input::InputAction::TakeScreenshot) is pressed, and briefly shows where it was
+/// saved.
+///
+/// With a [GameSettings::screenshot_scale] above 1, the player camera renders a single frame to an image that many times
+/// the size of the window instead, which is read back and saved. The window shows nothing for that frame.
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        let (sender, receiver) = mpsc::channel();
+
+        app.insert_resource(ScreenshotChannel {
+            sender: sender.clone(),
+            receiver: Mutex::new(receiver),
+        })
+        .init_resource::<ScreenshotToast>()
+        .add_systems(
+            Update,
+            (
+                systems::take_screenshot,
+                systems::receive_screenshot_progress,
+                systems::show_toast,
+            )
+                .chain(),
+        );
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(RenderScreenshotSender(sender))
+            .add_systems(ExtractSchedule, systems::extract_capture)
+            .add_systems(
+                Render,
+                systems::read_back_capture.in_set(RenderSet::Cleanup),
+            );
+    }
+}
+
+/// How far a screenshot got, sent back from wherever it's taken and saved.
+enum ScreenshotProgress {
+    /// The frame of a supersampled screenshot was read back, so the camera can render to the window again.
+    Captured,
+    Saved(PathBuf),
+    Failed(String),
+}
+
+#[derive(Resource)]
+struct ScreenshotChannel {
+    sender: Sender<ScreenshotProgress>,
+    receiver: Mutex<Receiver<ScreenshotProgress>>,
+}
+
+/// The render world's end of the [ScreenshotChannel].
+#[derive(Resource)]
+struct RenderScreenshotSender(Sender<ScreenshotProgress>);
+
+/// A supersampled screenshot being taken. The player camera renders to `image` until it's read back.
+#[derive(Resource)]
+struct SupersampledCapture {
+    image: Handle<Image>,
+    path: PathBuf,
+    /// Where the camera rendered to before, to go back to once the frame is captured.
+    previous_target: RenderTarget,
+}
+
+/// What the render world needs to read back a [SupersampledCapture].
+#[derive(Resource)]
+struct ExtractedCapture {
+    image: AssetId<Image>,
+    path: PathBuf,
+}
+
+/// The confirmation shown after a screenshot, with the time it was shown at.
+#[derive(Resource, Default)]
+struct ScreenshotToast(Option<(f64, String)>);
+
+/// A path in [SCREENSHOT_DIR] named after the current time, which no screenshot is saved at yet.
+fn next_screenshot_path() -> io::Result<PathBuf> {
+    fs::create_dir_all(SCREENSHOT_DIR)?;
+
+    let timestamp = timestamp();
+    let mut path = Path::new(SCREENSHOT_DIR).join(format!("{timestamp}.png"));
+    let mut count = 1;
+    while path.exists() {
+        count += 1;
+        path = Path::new(SCREENSHOT_DIR).join(format!("{timestamp}_{count}.png"));
+    }
+
+    Ok(path)
+}
+
+/// The current time in UTC, like `2024-01-31_13-45-07`.
+fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, seconds_of_day) = (seconds / 86_400, seconds % 86_400);
+
+    // The civil date of a day since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}",
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+fn save_screenshot(image: Image, path: &Path) -> Result<(), String> {
+    let image = image.try_into_dynamic().map_err(|err| err.to_string())?;
+    // The alpha channel holds brightness instead of opacity with HDR, so it's dropped.
+    image.to_rgb8().save(path).map_err(|err| err.to_string())
+}
+
+/// Saves the screenshot, and reports whether it was saved.
+fn save_and_report(image: Image, path: PathBuf, sender: &Sender<ScreenshotProgress>) {
+    let progress = match save_screenshot(image, &path) {
+        Ok(()) => ScreenshotProgress::Saved(path),
+        Err(err) => ScreenshotProgress::Failed(format!(
+            "Failed to save screenshot {}: {err}",
+            path.display()
+        )),
+    };
+    // The receiver only goes away when the app exits.
+    let _ = sender.send(progress);
+}
+
+mod systems {
+    use bevy::{
+        render::{
+            render_asset::RenderAssets,
+            render_resource::{
+                BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d,
+                ImageCopyBuffer, ImageDataLayout, MapMode, TextureDescriptor, TextureDimension,
+                TextureFormat, TextureUsages,
+            },
+            renderer::{RenderDevice, RenderQueue},
+            view::screenshot::ScreenshotManager,
+        },
+        tasks::IoTaskPool,
+        window::PrimaryWindow,
+    };
+    use bevy_egui::{
+        egui::{self, Align2, Color32},
+        EguiContexts,
+    };
+    use bevy_flycam::FlyCam;
+
+    use crate::input::{ActionInput, InputAction};
+
+    use super::*;
+
+    pub(super) fn take_screenshot(
+        mut commands: Commands,
+        input: ActionInput,
+        settings: Res<GameSettings>,
+        channel: Res<ScreenshotChannel>,
+        capture: Option<Res<SupersampledCapture>>,
+        mut screenshot_manager: ResMut<ScreenshotManager>,
+        mut images: ResMut<Assets<Image>>,
+        window_query: Query<(Entity, &Window), With<PrimaryWindow>>,
+        mut camera_query: Query<&mut Camera, With<FlyCam>>,
+    ) {
+        if !input.just_pressed(InputAction::TakeScreenshot) || capture.is_some() {
+            return;
+        }
+        let Ok((window_entity, window)) = window_query.get_single() else {
+            return;
+        };
+
+        let path = match next_screenshot_path() {
+            Ok(path) => path,
+            Err(err) => {
+                let _ = channel.sender.send(ScreenshotProgress::Failed(format!(
+                    "Failed to create {SCREENSHOT_DIR}: {err}"
+                )));
+                return;
+            }
+        };
+
+        let largest_side = window.physical_width().max(window.physical_height()).max(1);
+        let scale = settings
+            .screenshot_scale
+            .min(MAX_SCREENSHOT_SIZE / largest_side);
+        let Ok(mut camera) = camera_query.get_single_mut() else {
+            return;
+        };
+
+        if scale <= 1 {
+            let sender = channel.sender.clone();
+            // Only fails when a screenshot of this frame is already being taken.
+            let _ = screenshot_manager.take_screenshot(window_entity, move |image| {
+                save_and_report(image, path, &sender);
+            });
+            return;
+        }
+
+        let size = Extent3d {
+            width: window.physical_width() * scale,
+            height: window.physical_height() * scale,
+            depth_or_array_layers: 1,
+        };
+        let mut image = Image {
+            texture_descriptor: TextureDescriptor {
+                label: Some("supersampled_screenshot"),
+                size,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                mip_level_count: 1,
+                sample_count: 1,
+                usage: TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_DST
+                    | TextureUsages::COPY_SRC
+                    | TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+            ..default()
+        };
+        image.resize(size);
+
+        let image = images.add(image);
+        let previous_target =
+            std::mem::replace(&mut camera.target, RenderTarget::Image(image.clone()));
+        commands.insert_resource(SupersampledCapture {
+            image,
+            path,
+            previous_target,
+        });
+    }
+
+    pub(super) fn receive_screenshot_progress(
+        mut commands: Commands,
+        time: Res<Time>,
+        channel: Res<ScreenshotChannel>,
+        capture: Option<Res<SupersampledCapture>>,
+        mut toast: ResMut<ScreenshotToast>,
+        mut camera_query: Query<&mut Camera, With<FlyCam>>,
+    ) {
+        let mut capture = capture.as_deref();
+        let receiver = channel
+            .receiver
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        for progress in receiver.try_iter() {
+            let text = match progress {
+                ScreenshotProgress::Captured => None,
+                ScreenshotProgress::Saved(path) => {
+                    info!("Saved screenshot to {}", path.display());
+                    Some(format!("Saved screenshot to {}", path.display()))
+                }
+                ScreenshotProgress::Failed(err) => {
+                    error!("{err}");
+                    Some("Failed to save the screenshot".to_string())
+                }
+            };
+            if let Some(text) = text {
+                toast.0 = Some((time.elapsed_seconds_f64(), text));
+            }
+
+            // Whatever happened to a supersampled screenshot, its frame is done.
+            if let Some(capture) = capture.take() {
+                if let Ok(mut camera) = camera_query.get_single_mut() {
+                    camera.target = capture.previous_target.clone();
+                }
+                commands.remove_resource::<SupersampledCapture>();
+            }
+        }
+    }
+
+    pub(super) fn show_toast(
+        mut contexts: EguiContexts,
+        time: Res<Time>,
+        mut toast: ResMut<ScreenshotToast>,
+    ) {
+        let Some((shown_at, text)) = &toast.0 else {
+            return;
+        };
+        if time.elapsed_seconds_f64() - shown_at > TOAST_DURATION {
+            toast.0 = None;
+            return;
+        }
+
+        egui::Area::new("screenshot_toast")
+            .anchor(Align2::CENTER_TOP, [0.0, 16.0])
+            .show(contexts.ctx_mut(), |ui| {
+                egui::Frame::none()
+                    .fill(Color32::from_black_alpha(160))
+                    .inner_margin(6.0)
+                    .show(ui, |ui| {
+                        ui.label(text.as_str());
+                    });
+            });
+    }
+
+    pub(super) fn extract_capture(
+        mut commands: Commands,
+        capture: Extract<Option<Res<SupersampledCapture>>>,
+    ) {
+        match capture.as_ref() {
+            Some(capture) => commands.insert_resource(ExtractedCapture {
+                image: capture.image.id(),
+                path: capture.path.clone(),
+            }),
+            None => commands.remove_resource::<ExtractedCapture>(),
+        }
+    }
+
+    /// Copies the frame of a supersampled screenshot to the CPU once it's rendered, and saves it in the background.
+    pub(super) fn read_back_capture(
+        capture: Option<Res<ExtractedCapture>>,
+        images: Res<RenderAssets<Image>>,
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        sender: Res<RenderScreenshotSender>,
+        mut read_back: Local<Option<AssetId<Image>>>,
+    ) {
+        let Some(capture) = capture else {
+            return;
+        };
+        // The capture stays extracted for a frame or two after it's read back.
+        if *read_back == Some(capture.image) {
+            return;
+        }
+        // The image is only rendered to once it's on the GPU.
+        let Some(gpu_image) = images.get(capture.image) else {
+            return;
+        };
+        *read_back = Some(capture.image);
+
+        let width = gpu_image.size.x as u32;
+        let height = gpu_image.size.y as u32;
+        let row_bytes = width as usize * 4;
+        let padded_row_bytes = RenderDevice::align_copy_bytes_per_row(row_bytes);
+
+        let buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("supersampled_screenshot_buffer"),
+            size: (padded_row_bytes * height as usize) as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("supersampled_screenshot"),
+        });
+        encoder.copy_texture_to_buffer(
+            gpu_image.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes as u32),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        render_queue.submit([encoder.finish()]);
+
+        // The buffer is mapped once the GPU is done with the copy, which is polled for every frame.
+        let path = capture.path.clone();
+        let sender = sender.0.clone();
+        let mapped_buffer = buffer.clone();
+        render_device.map_buffer(&buffer.slice(..), MapMode::Read, move |result| {
+            if let Err(err) = result {
+                let _ = sender.send(ScreenshotProgress::Failed(format!(
+                    "Failed to read back the supersampled screenshot: {err}"
+                )));
+                return;
+            }
+
+            IoTaskPool::get()
+                .spawn(async move {
+                    // Rows are padded to the copy alignment on the GPU.
+                    let data: Vec<u8> = mapped_buffer
+                        .slice(..)
+                        .get_mapped_range()
+                        .chunks(padded_row_bytes)
+                        .flat_map(|row| &row[..row_bytes])
+                        .copied()
+                        .collect();
+                    mapped_buffer.unmap();
+                    let _ = sender.send(ScreenshotProgress::Captured);
+
+                    let image = Image::new(
+                        Extent3d {
+                            width,
+                            height,
+                            depth_or_array_layers: 1,
+                        },
+                        TextureDimension::D2,
+                        data,
+                        TextureFormat::Rgba8UnormSrgb,
+                    );
+                    save_and_report(image, path, &sender);
+                })
+                .detach();
+        });
+    }
+}
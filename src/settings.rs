@@ -0,0 +1,506 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use bevy_egui::EguiPlugin;
+use bevy_flycam::KeyBindings;
+use serde::{Deserialize, Serialize};
+
+use crate::voxel::VoxelConfig;
+
+/// Where the [SettingsFile] is persisted, relative to the working directory.
+const SETTINGS_PATH: &str = "settings.ron";
+/// How fast the mouse turns the camera at a [ControlSettings::mouse_sensitivity] of 1, in radians per pixel.
+const DEFAULT_MOUSE_SENSITIVITY: f32 = 0.00012;
+
+/// This plugin is responsible for the in-game settings menu, and applying the settings to the game. The settings are
+/// loaded from the [SettingsFile] at startup, and written back to it whenever they change.
+///
+/// The [AudioSettings] are applied by the audio systems themselves. Key bindings are kept in a file of their own, see
+/// [InputMap](crate::input::InputMap).
+///
+/// The mouse cursor is released while the menu is open, and grabbed again when it closes. Otherwise it's grabbed and
+/// released with [InputAction::ToggleCursorGrab](crate::input::InputAction::ToggleCursorGrab), instead of the
+/// flycam's own key, which would be the same as the one opening the menu.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        let settings = SettingsFile::load(SETTINGS_PATH);
+
+        app.insert_resource(settings.graphics)
+            .insert_resource(settings.controls)
+            .insert_resource(settings.audio)
+            .insert_resource(settings.streaming)
+            .init_resource::<RenderDistanceOverride>()
+            .register_type::<GameSettings>()
+            .register_type::<ControlSettings>()
+            .register_type::<AudioSettings>()
+            .register_type::<StreamingSettings>()
+            .insert_resource(KeyBindings {
+                toggle_grab_cursor: KeyCode::Unlabeled,
+                ..default()
+            })
+            .add_state::<SettingsMenuState>()
+            .add_systems(OnEnter(SettingsMenuState::Open), systems::release_cursor)
+            .add_systems(OnExit(SettingsMenuState::Open), systems::grab_cursor)
+            .add_systems(
+                Update,
+                (
+                    systems::toggle_settings_menu,
+                    systems::toggle_cursor_grab.run_if(in_state(SettingsMenuState::Closed)),
+                    systems::toggle_wireframe,
+                    systems::settings_menu.run_if(in_state(SettingsMenuState::Open)),
+                    (
+                        systems::apply_render_distance,
+                        systems::apply_fov,
+                        systems::apply_vsync,
+                        systems::apply_wireframe,
+                    )
+                        .run_if(resource_changed::<GameSettings>()),
+                    systems::apply_controls.run_if(resource_changed::<ControlSettings>()),
+                    systems::apply_streaming.run_if(resource_changed::<StreamingSettings>()),
+                    systems::save_changed_settings,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(crate) enum SettingsMenuState {
+    Open,
+    #[default]
+    Closed,
+}
+
+/// The user facing graphics settings of the game. These are applied live whenever the resource changes.
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct GameSettings {
+    /// Render distance (in chunks) of the player camera.
+    pub render_distance: u32,
+    /// How many chunks outside of the render distance a chunk has to be before it's unloaded.
+    pub unload_margin: u32,
+    /// Vertical field of view in degrees.
+    pub fov: f32,
+    pub(crate) vsync: bool,
+    pub(crate) wireframe: bool,
+    /// How many times the size of the window screenshots are taken at. Anything above 1 supersamples them.
+    pub(crate) screenshot_scale: u32,
+}
+
+/// A render distance used instead of the one of the [GameSettings] for this run, like the one given on the command
+/// line. Unlike the settings, it's never saved.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct RenderDistanceOverride(pub Option<u32>);
+
+impl RenderDistanceOverride {
+    /// The render distance of the camera with the given settings.
+    pub fn render_distance(&self, settings: &GameSettings) -> u32 {
+        self.0.unwrap_or(settings.render_distance)
+    }
+}
+
+/// How the camera is controlled with the mouse and a gamepad.
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct ControlSettings {
+    /// How fast the mouse turns the camera, where 1 is the flycam's default.
+    pub(crate) mouse_sensitivity: f32,
+    /// How fast the camera flies, in voxels per second.
+    pub(crate) fly_speed: f32,
+    /// How fast the camera turns with the right gamepad stick fully deflected, in degrees per second.
+    pub(crate) gamepad_look_sensitivity: f32,
+    pub(crate) invert_gamepad_look_y: bool,
+}
+
+/// How loud the sounds of the game are, for every [AudioBus]. Every volume goes from 0.0 to 1.0.
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct AudioSettings {
+    /// Scales every other volume.
+    pub(crate) master: f32,
+    pub(crate) music: f32,
+    pub(crate) effects: f32,
+    pub(crate) ambience: f32,
+}
+
+/// What kind of sound something is, which decides which volume of the [AudioSettings] it's played at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AudioBus {
+    Music,
+    /// The sounds of things happening in the world, like blocks breaking.
+    Effects,
+    /// Sounds that set the mood of a place, like wind or a cave drone.
+    Ambience,
+}
+
+impl AudioSettings {
+    /// How loud sounds on the bus are played, with the master volume applied.
+    pub(crate) fn volume(&self, bus: AudioBus) -> f32 {
+        let bus_volume = match bus {
+            AudioBus::Music => self.music,
+            AudioBus::Effects => self.effects,
+            AudioBus::Ambience => self.ambience,
+        };
+        self.master * bus_volume
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            music: 0.5,
+            effects: 1.0,
+            ambience: 0.8,
+        }
+    }
+}
+
+/// How much of the world streams in every frame. Higher budgets fill in the world faster, at the cost of longer frames
+/// while they do.
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct StreamingSettings {
+    /// How many chunks the camera queues up to load every frame, or [None] for all of the missing ones.
+    pub(crate) chunk_loads_per_frame: Option<u32>,
+    /// How many whole chunks a hosted game sends to every client per frame, see
+    /// [VoxelConfig::chunk_sends_per_frame].
+    pub(crate) chunk_sends_per_frame: usize,
+}
+
+impl Default for StreamingSettings {
+    fn default() -> Self {
+        Self {
+            chunk_loads_per_frame: None,
+            chunk_sends_per_frame: VoxelConfig::default().chunk_sends_per_frame,
+        }
+    }
+}
+
+/// Every setting, in the sections they're saved in. Sections and settings missing from the file keep their defaults,
+/// so files from older versions of the game still load.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+struct SettingsFile {
+    graphics: GameSettings,
+    controls: ControlSettings,
+    audio: AudioSettings,
+    streaming: StreamingSettings,
+}
+
+impl SettingsFile {
+    /// Loads the settings from `path`, writing the defaults to it if it doesn't exist yet, so they can be edited. Falls
+    /// back to the defaults if the file is invalid.
+    fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let Ok(contents) = fs::read_to_string(path) else {
+            let settings = Self::default();
+            settings.save(path);
+            return settings;
+        };
+
+        ron::from_str(&contents).unwrap_or_else(|err| {
+            warn!("Failed to parse {}: {err}", path.display());
+            Self::default()
+        })
+    }
+
+    fn save(&self, path: impl AsRef<Path>) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                error!("Failed to serialize settings: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = fs::write(path.as_ref(), contents) {
+            error!("Failed to write {}: {err}", path.as_ref().display());
+        }
+    }
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            render_distance: VoxelConfig::default().render_distance,
+            unload_margin: 2,
+            fov: 45.0,
+            vsync: true,
+            wireframe: true,
+            screenshot_scale: 1,
+        }
+    }
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        Self {
+            mouse_sensitivity: 1.0,
+            fly_speed: 12.0,
+            gamepad_look_sensitivity: 120.0,
+            invert_gamepad_look_y: false,
+        }
+    }
+}
+
+mod systems {
+    use bevy::{
+        pbr::wireframe::WireframeConfig,
+        prelude::*,
+        window::{CursorGrabMode, PresentMode, PrimaryWindow},
+    };
+    use bevy_egui::{egui, EguiContexts};
+    use bevy_flycam::{FlyCam, MovementSettings};
+
+    use crate::{
+        input::{ActionInput, InputAction},
+        voxel::{load::RenderDistance, VoxelConfig},
+    };
+
+    use super::{
+        AudioSettings, ControlSettings, GameSettings, RenderDistanceOverride, SettingsFile,
+        SettingsMenuState, StreamingSettings, DEFAULT_MOUSE_SENSITIVITY, SETTINGS_PATH,
+    };
+
+    pub(super) fn toggle_settings_menu(
+        input: ActionInput,
+        mut next_state: ResMut<NextState<SettingsMenuState>>,
+        cur_state: Res<State<SettingsMenuState>>,
+    ) {
+        if input.just_pressed(InputAction::ToggleSettingsMenu) {
+            next_state.set(match **cur_state {
+                SettingsMenuState::Open => SettingsMenuState::Closed,
+                SettingsMenuState::Closed => SettingsMenuState::Open,
+            })
+        }
+    }
+
+    pub(super) fn toggle_cursor_grab(
+        input: ActionInput,
+        mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    ) {
+        if !input.just_pressed(InputAction::ToggleCursorGrab) {
+            return;
+        }
+
+        if let Ok(mut window) = window_query.get_single_mut() {
+            let grabbed = window.cursor.grab_mode != CursorGrabMode::None;
+            set_cursor_grab(&mut window, !grabbed);
+        }
+    }
+
+    pub(super) fn release_cursor(mut window_query: Query<&mut Window, With<PrimaryWindow>>) {
+        if let Ok(mut window) = window_query.get_single_mut() {
+            set_cursor_grab(&mut window, false);
+        }
+    }
+
+    pub(super) fn grab_cursor(mut window_query: Query<&mut Window, With<PrimaryWindow>>) {
+        if let Ok(mut window) = window_query.get_single_mut() {
+            set_cursor_grab(&mut window, true);
+        }
+    }
+
+    /// Grabs and hides the cursor the same way the flycam does, or releases and shows it.
+    fn set_cursor_grab(window: &mut Window, grab: bool) {
+        window.cursor.grab_mode = if grab {
+            CursorGrabMode::Confined
+        } else {
+            CursorGrabMode::None
+        };
+        window.cursor.visible = !grab;
+    }
+
+    pub(super) fn toggle_wireframe(input: ActionInput, mut settings: ResMut<GameSettings>) {
+        if input.just_pressed(InputAction::ToggleWireframe) {
+            settings.wireframe = !settings.wireframe;
+        }
+    }
+
+    pub(super) fn settings_menu(
+        mut contexts: EguiContexts,
+        mut settings: ResMut<GameSettings>,
+        mut controls: ResMut<ControlSettings>,
+        mut audio_settings: ResMut<AudioSettings>,
+        mut next_state: ResMut<NextState<SettingsMenuState>>,
+    ) {
+        // Edit a copy, so change detection only triggers when something actually changed.
+        let mut edited = settings.clone();
+        let mut edited_controls = controls.clone();
+        let mut edited_audio = audio_settings.clone();
+
+        egui::Window::new("Settings")
+            .collapsible(false)
+            .resizable(false)
+            .show(contexts.ctx_mut(), |ui| {
+                ui.add(
+                    egui::Slider::new(&mut edited.render_distance, 1..=32).text("Render distance"),
+                );
+                ui.add(egui::Slider::new(&mut edited.unload_margin, 0..=8).text("Unload margin"));
+                ui.add(egui::Slider::new(&mut edited.fov, 30.0..=120.0).text("FOV"));
+                ui.checkbox(&mut edited.vsync, "VSync");
+                ui.checkbox(&mut edited.wireframe, "Wireframe");
+                ui.add(
+                    egui::Slider::new(&mut edited.screenshot_scale, 1..=4).text("Screenshot scale"),
+                );
+
+                ui.separator();
+
+                ui.add(
+                    egui::Slider::new(&mut edited_controls.mouse_sensitivity, 0.1..=4.0)
+                        .text("Mouse sensitivity"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut edited_controls.fly_speed, 2.0..=64.0).text("Fly speed"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut edited_controls.gamepad_look_sensitivity, 30.0..=360.0)
+                        .text("Gamepad look sensitivity"),
+                );
+                ui.checkbox(
+                    &mut edited_controls.invert_gamepad_look_y,
+                    "Invert gamepad look Y",
+                );
+
+                ui.separator();
+
+                ui.add(
+                    egui::Slider::new(&mut edited_audio.master, 0.0..=1.0).text("Master volume"),
+                );
+                ui.add(egui::Slider::new(&mut edited_audio.music, 0.0..=1.0).text("Music volume"));
+                ui.add(
+                    egui::Slider::new(&mut edited_audio.effects, 0.0..=1.0).text("Effects volume"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut edited_audio.ambience, 0.0..=1.0)
+                        .text("Ambience volume"),
+                );
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("Reset to defaults").clicked() {
+                        edited = GameSettings::default();
+                        edited_controls = ControlSettings::default();
+                        edited_audio = AudioSettings::default();
+                    }
+                    if ui.button("Close").clicked() {
+                        next_state.set(SettingsMenuState::Closed);
+                    }
+                });
+            });
+
+        if edited != *settings {
+            *settings = edited;
+        }
+        if edited_controls != *controls {
+            *controls = edited_controls;
+        }
+        if edited_audio != *audio_settings {
+            *audio_settings = edited_audio;
+        }
+    }
+
+    /// Writes the settings back to the [SettingsFile] once they changed. Changes made before the first frame, like
+    /// from the command line, aren't saved. Sliders change the settings every frame while they're dragged, so the
+    /// file is only written once the settings menu is closed.
+    pub(super) fn save_changed_settings(
+        settings: Res<GameSettings>,
+        controls: Res<ControlSettings>,
+        audio_settings: Res<AudioSettings>,
+        streaming: Res<StreamingSettings>,
+        menu_state: Res<State<SettingsMenuState>>,
+        mut unsaved: Local<bool>,
+    ) {
+        *unsaved |= (settings.is_changed() && !settings.is_added())
+            || (controls.is_changed() && !controls.is_added())
+            || (audio_settings.is_changed() && !audio_settings.is_added())
+            || (streaming.is_changed() && !streaming.is_added());
+        if !*unsaved || *menu_state.get() == SettingsMenuState::Open {
+            return;
+        }
+
+        SettingsFile {
+            graphics: settings.clone(),
+            controls: controls.clone(),
+            audio: audio_settings.clone(),
+            streaming: streaming.clone(),
+        }
+        .save(SETTINGS_PATH);
+        *unsaved = false;
+    }
+
+    pub(super) fn apply_render_distance(
+        settings: Res<GameSettings>,
+        render_distance_override: Res<RenderDistanceOverride>,
+        mut render_dist_query: Query<&mut RenderDistance, With<FlyCam>>,
+    ) {
+        for mut render_distance in &mut render_dist_query {
+            render_distance.val = render_distance_override.render_distance(&settings);
+            render_distance.unload_margin = settings.unload_margin;
+        }
+    }
+
+    pub(super) fn apply_fov(
+        settings: Res<GameSettings>,
+        mut projection_query: Query<&mut Projection, With<FlyCam>>,
+    ) {
+        for mut projection in &mut projection_query {
+            if let Projection::Perspective(perspective) = projection.as_mut() {
+                perspective.fov = settings.fov.to_radians();
+            }
+        }
+    }
+
+    /// The flycam turns and flies with its own [MovementSettings], and the gamepad reads the [ControlSettings] itself.
+    pub(super) fn apply_controls(
+        controls: Res<ControlSettings>,
+        movement_settings: Option<ResMut<MovementSettings>>,
+    ) {
+        if let Some(mut movement_settings) = movement_settings {
+            movement_settings.sensitivity = controls.mouse_sensitivity * DEFAULT_MOUSE_SENSITIVITY;
+            movement_settings.speed = controls.fly_speed;
+        }
+    }
+
+    /// The camera queues up its chunks with the budget of its [RenderDistance].
+    pub(super) fn apply_streaming(
+        streaming: Res<StreamingSettings>,
+        voxel_config: Option<ResMut<VoxelConfig>>,
+        mut render_dist_query: Query<&mut RenderDistance, With<FlyCam>>,
+    ) {
+        for mut render_distance in &mut render_dist_query {
+            render_distance.budget = streaming.chunk_loads_per_frame;
+        }
+        if let Some(mut voxel_config) = voxel_config {
+            voxel_config.chunk_sends_per_frame = streaming.chunk_sends_per_frame;
+        }
+    }
+
+    pub(super) fn apply_vsync(
+        settings: Res<GameSettings>,
+        mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    ) {
+        for mut window in &mut window_query {
+            window.present_mode = if settings.vsync {
+                PresentMode::AutoVsync
+            } else {
+                PresentMode::AutoNoVsync
+            };
+        }
+    }
+
+    pub(super) fn apply_wireframe(
+        settings: Res<GameSettings>,
+        mut wireframe_config: ResMut<WireframeConfig>,
+    ) {
+        wireframe_config.global = settings.wireframe;
+    }
+}
@@ -0,0 +1,73 @@
+// The shader of `SkyMaterial`. The sky dome is drawn around the camera, behind everything else, with a gradient from
+// the horizon to the zenith, the sun and moon as discs, and stars at night.
+
+#import bevy_pbr::mesh_view_bindings::view
+
+struct SkyMaterial {
+    zenith_color: vec4<f32>,
+    horizon_color: vec4<f32>,
+    sun_color: vec4<f32>,
+    moon_color: vec4<f32>,
+    sun_direction: vec3<f32>,
+    // How far the sky has turned since midnight, in radians. The stars turn with it.
+    sky_angle: f32,
+    star_visibility: f32,
+};
+
+@group(1) @binding(0) var<uniform> material: SkyMaterial;
+
+const SUN_SIZE: f32 = 0.9995;
+const MOON_SIZE: f32 = 0.9997;
+const STAR_DENSITY: f32 = 300.0;
+const STAR_THRESHOLD: f32 = 0.998;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) direction: vec3<f32>,
+};
+
+@vertex
+fn vertex(in: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    // The dome is centered on the camera wherever the mesh is.
+    out.position = view.view_proj * vec4<f32>(view.world_position + in.position, 1.0);
+    // A depth of 0.0 is infinitely far away with the reversed depth, so the dome is behind everything.
+    out.position.z = 0.0;
+    out.direction = in.position;
+    return out;
+}
+
+fn hash(p: vec3<f32>) -> f32 {
+    return fract(sin(dot(p, vec3<f32>(12.9898, 78.233, 37.719))) * 43758.5453);
+}
+
+// A disc around `direction`, fading out at its edge. `size` is the cosine of its radius.
+fn disc(view_direction: vec3<f32>, direction: vec3<f32>, size: f32) -> f32 {
+    return smoothstep(size, mix(size, 1.0, 0.3), dot(view_direction, direction));
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = normalize(in.direction);
+
+    let height = clamp(direction.y, 0.0, 1.0);
+    var color = mix(material.horizon_color.rgb, material.zenith_color.rgb, sqrt(height));
+
+    // The stars are fixed to the sky, which turns around the same axis as the sun.
+    let c = cos(-material.sky_angle);
+    let s = sin(-material.sky_angle);
+    let star_direction = vec3<f32>(c * direction.x - s * direction.y, s * direction.x + c * direction.y, direction.z);
+    let star = step(STAR_THRESHOLD, hash(floor(star_direction * STAR_DENSITY)));
+    color += vec3<f32>(star * material.star_visibility * smoothstep(0.0, 0.2, direction.y));
+
+    let sun = material.sun_direction;
+    let glow = pow(max(dot(direction, sun), 0.0), 64.0) * 0.4;
+    color += material.sun_color.rgb * (disc(direction, sun, SUN_SIZE) * 4.0 + glow);
+    color = mix(color, material.moon_color.rgb, disc(direction, -sun, MOON_SIZE) * material.moon_color.a);
+
+    return vec4<f32>(color, 1.0);
+}
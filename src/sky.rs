@@ -0,0 +1,295 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    asset::load_internal_asset,
+    pbr::{MaterialPipeline, MaterialPipelineKey, NotShadowCaster},
+    prelude::*,
+    reflect::TypePath,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_resource::{
+            AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+        },
+        view::NoFrustumCulling,
+    },
+};
+
+use crate::voxel::weather::Weather;
+
+/// How long a full day takes at normal speed, in seconds.
+const DAY_LENGTH_SECONDS: f32 = 600.0;
+/// The [TimeOfDay] the game starts at, a bit after sunrise.
+const START_TIME_OF_DAY: f32 = 0.3;
+/// The speeds [InputAction::CycleTimeSpeed](crate::input::InputAction::CycleTimeSpeed) cycles through.
+const TIME_SPEEDS: [f32; 4] = [1.0, 10.0, 100.0, 0.0];
+const MAX_SUN_ILLUMINANCE: f32 = 10_000.0;
+const DAY_AMBIENT_BRIGHTNESS: f32 = 0.3;
+const NIGHT_AMBIENT_BRIGHTNESS: f32 = 0.02;
+const DAY_SKY_COLOR: Color = Color::rgb(0.5, 0.7, 1.0);
+const NIGHT_SKY_COLOR: Color = Color::rgb(0.01, 0.01, 0.05);
+const SUNSET_COLOR: Color = Color::rgb(1.0, 0.5, 0.2);
+const DAY_ZENITH_COLOR: Color = Color::rgb(0.2, 0.4, 0.9);
+const NIGHT_ZENITH_COLOR: Color = Color::rgb(0.0, 0.0, 0.02);
+const SUN_COLOR: Color = Color::rgb(1.0, 0.95, 0.8);
+const MOON_COLOR: Color = Color::rgb(0.8, 0.85, 0.9);
+
+const SKY_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x736b_795f_646f_6d65_5f73_6861_6465_7200);
+
+/// This plugin is responsible for the day/night cycle. It moves the sun with the [TimeOfDay],
+/// and changes the sun light, ambient light and sky to match. Bad [Weather] darkens all of them.
+///
+/// The sky is a dome drawn with the [SkyMaterial], with the sun and the moon opposite of it in the direction of the
+/// sun light. The clear color is kept at the color of the horizon, since the fog takes its color from it.
+pub struct SkyPlugin;
+
+impl Plugin for SkyPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            SKY_SHADER_HANDLE,
+            "shaders/sky.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_plugins(MaterialPlugin::<SkyMaterial> {
+            prepass_enabled: false,
+            ..default()
+        })
+        .init_resource::<TimeOfDay>()
+        .register_type::<TimeOfDay>()
+        .add_systems(Startup, (systems::setup_sun, systems::setup_sky_dome))
+        .add_systems(
+            Update,
+            (
+                systems::control_time_of_day,
+                systems::advance_time_of_day,
+                systems::update_sky,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// The current time of the day, as a fraction of a full day. 0.0 is midnight, 0.25 is sunrise, 0.5 is noon
+/// and 0.75 is sunset.
+#[derive(Resource, Reflect, Debug, Clone, Copy)]
+pub(crate) struct TimeOfDay {
+    time: f32,
+    /// How many times faster than normal the time passes. 0.0 stops the time.
+    pub(crate) speed: f32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            time: START_TIME_OF_DAY,
+            speed: TIME_SPEEDS[0],
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// Sets the time of the day. Values outside of 0.0..1.0 wrap around to the previous or next day.
+    pub(crate) fn set_time(&mut self, time: f32) {
+        self.time = time.rem_euclid(1.0);
+    }
+
+    /// The direction from the world towards the sun.
+    pub(crate) fn sun_direction(&self) -> Vec3 {
+        let angle = (self.time - 0.25) * TAU;
+        // Tilt the path of the sun a bit, so it isn't straight overhead at noon.
+        Vec3::new(angle.cos(), angle.sin(), 0.3).normalize()
+    }
+
+    /// How bright the day is, from 0.0 at night to 1.0 during the day. This fades while the sun is close to the
+    /// horizon.
+    pub(crate) fn daylight(&self) -> f32 {
+        ((self.sun_direction().y + 0.1) / 0.3).clamp(0.0, 1.0)
+    }
+
+    /// How far the sky has turned since midnight, in radians.
+    fn sky_angle(&self) -> f32 {
+        self.time * TAU
+    }
+}
+
+/// Marker component for the directional light of the sun.
+#[derive(Component)]
+struct Sun;
+
+/// The material of the sky dome, updated with the [TimeOfDay] and [Weather].
+#[derive(Asset, AsBindGroup, TypePath, Debug, Clone, Default)]
+pub(crate) struct SkyMaterial {
+    #[uniform(0)]
+    zenith_color: Color,
+    /// Also the clear color.
+    #[uniform(0)]
+    horizon_color: Color,
+    #[uniform(0)]
+    sun_color: Color,
+    /// The alpha is how visible the moon is.
+    #[uniform(0)]
+    moon_color: Color,
+    #[uniform(0)]
+    sun_direction: Vec3,
+    #[uniform(0)]
+    sky_angle: f32,
+    #[uniform(0)]
+    star_visibility: f32,
+}
+
+impl Material for SkyMaterial {
+    fn vertex_shader() -> ShaderRef {
+        SKY_SHADER_HANDLE.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        SKY_SHADER_HANDLE.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // The dome is seen from the inside.
+        descriptor.primitive.cull_mode = None;
+        if let Some(depth_stencil) = &mut descriptor.depth_stencil {
+            depth_stencil.depth_write_enabled = false;
+        }
+        descriptor.vertex.buffers =
+            vec![layout.get_layout(&[Mesh::ATTRIBUTE_POSITION.at_shader_location(0)])?];
+
+        Ok(())
+    }
+}
+
+/// Mixes `a` and `b`, `t` being how much of `b` is used.
+fn mix_colors(a: Color, b: Color, t: f32) -> Color {
+    let [ar, ag, ab, aa] = a.as_rgba_f32();
+    let [br, bg, bb, ba] = b.as_rgba_f32();
+
+    Color::rgba(
+        ar + (br - ar) * t,
+        ag + (bg - ag) * t,
+        ab + (bb - ab) * t,
+        aa + (ba - aa) * t,
+    )
+}
+
+mod systems {
+    use crate::input::{ActionInput, InputAction};
+
+    use super::*;
+
+    pub(super) fn setup_sun(mut commands: Commands) {
+        commands.spawn((
+            DirectionalLightBundle {
+                directional_light: DirectionalLight {
+                    illuminance: MAX_SUN_ILLUMINANCE,
+                    ..default()
+                },
+                ..default()
+            },
+            Sun,
+        ));
+    }
+
+    pub(super) fn setup_sky_dome(
+        mut commands: Commands,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<SkyMaterial>>,
+    ) {
+        commands.spawn((
+            MaterialMeshBundle {
+                mesh: meshes.add(
+                    shape::UVSphere {
+                        radius: 10.0,
+                        sectors: 32,
+                        stacks: 16,
+                    }
+                    .into(),
+                ),
+                material: materials.add(SkyMaterial::default()),
+                ..default()
+            },
+            // The shader keeps the dome around the camera, wherever its transform is.
+            NoFrustumCulling,
+            NotShadowCaster,
+        ));
+    }
+
+    /// Skips to the next quarter of the day, or cycles through the [TIME_SPEEDS].
+    pub(super) fn control_time_of_day(input: ActionInput, mut time_of_day: ResMut<TimeOfDay>) {
+        if input.just_pressed(InputAction::SkipTimeOfDay) {
+            let next_quarter = ((time_of_day.time * 4.0).floor() + 1.0) / 4.0;
+            time_of_day.set_time(next_quarter);
+        }
+
+        if input.just_pressed(InputAction::CycleTimeSpeed) {
+            let current = TIME_SPEEDS
+                .iter()
+                .position(|speed| *speed == time_of_day.speed)
+                .unwrap_or(0);
+            time_of_day.speed = TIME_SPEEDS[(current + 1) % TIME_SPEEDS.len()];
+            info!("Time speed: {}x", time_of_day.speed);
+        }
+    }
+
+    pub(super) fn advance_time_of_day(time: Res<Time>, mut time_of_day: ResMut<TimeOfDay>) {
+        if time_of_day.speed == 0.0 {
+            return;
+        }
+
+        let time = time_of_day.time + time.delta_seconds() * time_of_day.speed / DAY_LENGTH_SECONDS;
+        time_of_day.set_time(time);
+    }
+
+    pub(super) fn update_sky(
+        time_of_day: Res<TimeOfDay>,
+        weather: Res<State<Weather>>,
+        mut sun_query: Query<(&mut Transform, &mut DirectionalLight), With<Sun>>,
+        mut ambient_light: ResMut<AmbientLight>,
+        mut clear_color: ResMut<ClearColor>,
+        sky_query: Query<&Handle<SkyMaterial>>,
+        mut sky_materials: ResMut<Assets<SkyMaterial>>,
+    ) {
+        if !time_of_day.is_changed() && !weather.is_changed() {
+            return;
+        }
+
+        let sun_direction = time_of_day.sun_direction();
+        let daylight = time_of_day.daylight() * (1.0 - weather.get().overcast());
+        // How close the sun is to the horizon, used to tint the light and sky during sunrise and sunset.
+        let sunset = 1.0 - (sun_direction.y.abs() / 0.3).min(1.0);
+
+        for (mut transform, mut light) in &mut sun_query {
+            *transform = Transform::IDENTITY.looking_to(-sun_direction, Vec3::Y);
+            light.illuminance = MAX_SUN_ILLUMINANCE * daylight;
+            light.color = mix_colors(Color::WHITE, SUNSET_COLOR, sunset);
+        }
+
+        ambient_light.brightness = NIGHT_AMBIENT_BRIGHTNESS
+            + (DAY_AMBIENT_BRIGHTNESS - NIGHT_AMBIENT_BRIGHTNESS) * daylight;
+
+        let sky_color = mix_colors(NIGHT_SKY_COLOR, DAY_SKY_COLOR, daylight);
+        clear_color.0 = mix_colors(sky_color, SUNSET_COLOR, sunset * daylight * 0.5);
+
+        let clear_sky = 1.0 - weather.get().overcast();
+        for handle in &sky_query {
+            let Some(material) = sky_materials.get_mut(handle) else {
+                continue;
+            };
+
+            material.zenith_color = mix_colors(NIGHT_ZENITH_COLOR, DAY_ZENITH_COLOR, daylight);
+            material.horizon_color = clear_color.0;
+            material.sun_color = mix_colors(SUN_COLOR, SUNSET_COLOR, sunset) * clear_sky;
+            material.moon_color = MOON_COLOR.with_a((1.0 - time_of_day.daylight()) * clear_sky);
+            material.sun_direction = sun_direction;
+            material.sky_angle = time_of_day.sky_angle();
+            material.star_visibility = (1.0 - time_of_day.daylight()) * clear_sky;
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+
+use crate::settings::AudioBus;
+
+use super::{Mixed, Sounds, Surroundings};
+
+/// How many solid voxels have to be above the camera for the cave drone to play at full volume.
+const CAVE_DEPTH: f32 = 12.0;
+/// The height the wind starts to blow at, and the height it's loudest at, in voxels.
+const WIND_HEIGHTS: (f32, f32) = (40.0, 100.0);
+/// How much of the difference to its target volume an ambience catches up on every second.
+const AMBIENCE_FADE_RATE: f32 = 0.5;
+
+/// This plugin plays looping ambience that fits where the camera is: a drone in caves deep underground, wind high up
+/// in the air, and the rumble of the water while under water.
+pub(super) struct AmbiencePlugin;
+
+impl Plugin for AmbiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, systems::spawn_ambience)
+            .add_systems(Update, systems::fade_ambience);
+    }
+}
+
+/// A looping ambient sound, which fades its [Mixed] volume in and out with the surroundings of the camera.
+#[derive(Component, Debug)]
+struct Ambience {
+    kind: AmbienceKind,
+    /// How far the ambience is faded in, from 0 to 1.
+    level: f32,
+    /// The volume the sound event was picked with, at full level.
+    volume: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AmbienceKind {
+    Cave,
+    Wind,
+    Underwater,
+}
+
+impl AmbienceKind {
+    const ALL: [AmbienceKind; 3] = [
+        AmbienceKind::Cave,
+        AmbienceKind::Wind,
+        AmbienceKind::Underwater,
+    ];
+
+    /// The sound event the ambience plays.
+    fn event_name(&self) -> &'static str {
+        match self {
+            AmbienceKind::Cave => "ambience.cave",
+            AmbienceKind::Wind => "ambience.wind",
+            AmbienceKind::Underwater => "ambience.underwater",
+        }
+    }
+}
+
+impl AmbienceKind {
+    /// How loud the ambience should be in the surroundings of the camera, from 0 to 1.
+    fn volume(&self, surroundings: &Surroundings) -> f32 {
+        match self {
+            AmbienceKind::Cave => {
+                surroundings.cover * (surroundings.depth as f32 / CAVE_DEPTH).min(1.0)
+            }
+            AmbienceKind::Wind => {
+                let (start, loudest) = WIND_HEIGHTS;
+                (1.0 - surroundings.cover)
+                    * ((surroundings.height - start) / (loudest - start)).clamp(0.0, 1.0)
+            }
+            AmbienceKind::Underwater => {
+                if surroundings.underwater {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+mod systems {
+    use super::*;
+
+    pub(super) fn spawn_ambience(mut commands: Commands, sounds: Sounds) {
+        for kind in AmbienceKind::ALL {
+            let Some((bundle, volume)) = sounds.pick(kind.event_name(), PlaybackSettings::LOOP)
+            else {
+                continue;
+            };
+
+            commands.spawn((
+                bundle,
+                Ambience {
+                    kind,
+                    level: 0.0,
+                    volume,
+                },
+                Mixed {
+                    bus: AudioBus::Ambience,
+                    volume: 0.0,
+                    // The underwater ambience is what the muffled world sounds like.
+                    muffled: kind != AmbienceKind::Underwater,
+                },
+            ));
+        }
+    }
+
+    pub(super) fn fade_ambience(
+        time: Res<Time>,
+        surroundings: Res<Surroundings>,
+        mut ambience_query: Query<(&mut Ambience, &mut Mixed)>,
+    ) {
+        let fade = (AMBIENCE_FADE_RATE * time.delta_seconds()).min(1.0);
+        for (mut ambience, mut mixed) in &mut ambience_query {
+            let target = ambience.kind.volume(&surroundings);
+            ambience.level += (target - ambience.level) * fade;
+            mixed.volume = ambience.level * ambience.volume;
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+use crate::{
+    settings::AudioBus,
+    voxel::{edit::VoxelChanged, Voxel},
+};
+
+use super::Sounds;
+
+/// This plugin plays the sound of a block where it's broken or placed, from the [BlockSoundSet] of the block.
+pub(super) struct BlockSoundPlugin;
+
+impl Plugin for BlockSoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<VoxelChanged>()
+            .add_systems(Update, systems::play_block_sounds);
+    }
+}
+
+/// The sounds a kind of block makes, set for every block in the block registry. Blocks made of the same material share
+/// one.
+///
+/// The sounds of a set are the sound events `block.<set>.<sound>`, like `block.stone.break`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(in crate::voxel) enum BlockSoundSet {
+    Stone,
+    Dirt,
+    Grass,
+    Sand,
+    Gravel,
+    Snow,
+    Wood,
+    Leaves,
+    Liquid,
+}
+
+impl BlockSoundSet {
+    pub(super) const ALL: [BlockSoundSet; 9] = [
+        BlockSoundSet::Stone,
+        BlockSoundSet::Dirt,
+        BlockSoundSet::Grass,
+        BlockSoundSet::Sand,
+        BlockSoundSet::Gravel,
+        BlockSoundSet::Snow,
+        BlockSoundSet::Wood,
+        BlockSoundSet::Leaves,
+        BlockSoundSet::Liquid,
+    ];
+
+    pub(super) fn name(&self) -> &'static str {
+        match self {
+            BlockSoundSet::Stone => "stone",
+            BlockSoundSet::Dirt => "dirt",
+            BlockSoundSet::Grass => "grass",
+            BlockSoundSet::Sand => "sand",
+            BlockSoundSet::Gravel => "gravel",
+            BlockSoundSet::Snow => "snow",
+            BlockSoundSet::Wood => "wood",
+            BlockSoundSet::Leaves => "leaves",
+            BlockSoundSet::Liquid => "liquid",
+        }
+    }
+
+    /// The name of the sound event of one of the sounds of the set.
+    pub(super) fn event_name(&self, sound: BlockSound) -> String {
+        format!("block.{}.{}", self.name(), sound.name())
+    }
+}
+
+/// What happened to a block to make it sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum BlockSound {
+    Break,
+    Place,
+    /// The player walked on the block, see [FootstepPlugin](super::footstep::FootstepPlugin).
+    Step,
+}
+
+impl BlockSound {
+    pub(super) fn name(&self) -> &'static str {
+        match self {
+            BlockSound::Break => "break",
+            BlockSound::Place => "place",
+            BlockSound::Step => "step",
+        }
+    }
+}
+
+/// The sound a change of a voxel makes, and the block that makes it. Breaking a block sounds like the block that was
+/// there, and placing one sounds like the new block. Changes that keep the kind of block, like a fluid level, don't
+/// make a sound.
+fn change_sound(previous: Voxel, voxel: Voxel) -> Option<(BlockSound, Voxel)> {
+    if previous.id() == voxel.id() {
+        None
+    } else if voxel == Voxel::AIR {
+        Some((BlockSound::Break, previous))
+    } else {
+        Some((BlockSound::Place, voxel))
+    }
+}
+
+mod systems {
+    use super::*;
+
+    pub(super) fn play_block_sounds(
+        mut commands: Commands,
+        mut changes: EventReader<VoxelChanged>,
+        sounds: Sounds,
+    ) {
+        for change in changes.read() {
+            let Some((sound, voxel)) = change_sound(change.previous, change.voxel) else {
+                continue;
+            };
+            let Some(sound_set) = voxel.definition().sounds else {
+                continue;
+            };
+
+            sounds.play_at(
+                &mut commands,
+                &sound_set.event_name(sound),
+                change.voxel_pos.as_vec3(),
+                AudioBus::Effects,
+            );
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::common_conditions::on_timer, utils::HashMap};
+
+use crate::{settings::AudioBus, voxel::world::VoxelWorld};
+
+use super::{Mixed, Sounds};
+
+/// How often the voxels around the listener are searched for ones playing an ambient sound.
+const SCAN_INTERVAL: Duration = Duration::from_millis(500);
+/// How far around the listener voxels play their ambient sound, in voxels.
+const SCAN_RADIUS: i32 = 12;
+/// How many ambient block sounds play at once. The ones closest to the listener win.
+const MAX_BLOCK_EMITTERS: usize = 8;
+/// How close two voxels playing the same ambient sound can be, in voxels. Closer ones are left out, so a single lake
+/// doesn't take up every emitter.
+const EMITTER_SPACING: f32 = 4.0;
+
+/// This plugin plays the [ambient sounds](crate::voxel::registry::BlockDefinition::ambient_sound) of the blocks around
+/// the listener on a loop, like water, lava and fire. Only a few of the closest blocks play theirs at once.
+pub(super) struct BlockAmbiencePlugin;
+
+impl Plugin for BlockAmbiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            systems::update_block_emitters.run_if(on_timer(SCAN_INTERVAL)),
+        );
+    }
+}
+
+/// A looping sound played from a voxel.
+#[derive(Component, Debug)]
+struct BlockEmitter {
+    /// The world voxel position of the voxel.
+    voxel_pos: IVec3,
+    sound: &'static str,
+}
+
+/// Picks the voxels to play ambient sounds from, closest to `center` first. Only voxels at the surface of their block
+/// play, as the ones below are covered by it.
+fn pick_emitters(voxel_world: &VoxelWorld, center: IVec3) -> Vec<(IVec3, &'static str)> {
+    let radius = IVec3::splat(SCAN_RADIUS);
+    let mut candidates: Vec<(IVec3, &'static str)> = voxel_world
+        .iter_region(center - radius, center + radius)
+        .filter_map(|(voxel_pos, voxel)| {
+            let sound = voxel.definition().ambient_sound?;
+            let covered = voxel_world
+                .get_block(voxel_pos + IVec3::Y)
+                .is_some_and(|above| above.id() == voxel.id());
+            (!covered).then_some((voxel_pos, sound))
+        })
+        .collect();
+    candidates.sort_by_key(|(voxel_pos, _)| (*voxel_pos - center).length_squared());
+
+    let mut emitters: Vec<(IVec3, &'static str)> = Vec::new();
+    for (voxel_pos, sound) in candidates {
+        if emitters.len() >= MAX_BLOCK_EMITTERS {
+            break;
+        }
+
+        let crowded = emitters.iter().any(|(other_pos, other_sound)| {
+            *other_sound == sound
+                && voxel_pos.as_vec3().distance(other_pos.as_vec3()) < EMITTER_SPACING
+        });
+        if !crowded {
+            emitters.push((voxel_pos, sound));
+        }
+    }
+
+    emitters
+}
+
+mod systems {
+    use super::*;
+
+    /// Keeps emitters playing for the picked voxels, starting the ones that are new and stopping the ones that aren't
+    /// picked anymore. The camera is the listener.
+    pub(super) fn update_block_emitters(
+        mut commands: Commands,
+        sounds: Sounds,
+        voxel_world: VoxelWorld,
+        camera_query: Query<&Transform, With<Camera3d>>,
+        emitter_query: Query<(Entity, &BlockEmitter)>,
+    ) {
+        let Ok(transform) = camera_query.get_single() else {
+            return;
+        };
+
+        let mut picked: HashMap<IVec3, &'static str> =
+            pick_emitters(&voxel_world, transform.translation.round().as_ivec3())
+                .into_iter()
+                .collect();
+
+        for (entity, emitter) in &emitter_query {
+            // Emitters that are still picked keep playing, instead of starting over.
+            if picked.get(&emitter.voxel_pos) == Some(&emitter.sound) {
+                picked.remove(&emitter.voxel_pos);
+            } else {
+                commands.entity(entity).despawn();
+            }
+        }
+
+        for (voxel_pos, sound) in picked {
+            let settings = PlaybackSettings {
+                spatial: true,
+                ..PlaybackSettings::LOOP
+            };
+            let Some((bundle, volume)) = sounds.pick(&format!("block.ambient.{sound}"), settings)
+            else {
+                continue;
+            };
+
+            commands.spawn((
+                bundle,
+                TransformBundle::from_transform(Transform::from_translation(voxel_pos.as_vec3())),
+                BlockEmitter { voxel_pos, sound },
+                Mixed {
+                    bus: AudioBus::Ambience,
+                    volume,
+                    muffled: true,
+                },
+            ));
+        }
+    }
+}
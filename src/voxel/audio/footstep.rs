@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+use crate::{
+    settings::AudioBus,
+    voxel::{
+        physics::{TerrainCollider, Velocity},
+        world::VoxelWorld,
+    },
+};
+
+use super::{block::BlockSound, Sounds};
+
+/// How far the player walks between footsteps, in voxels. Walking faster makes the steps follow each other faster.
+const STRIDE_LENGTH: f32 = 1.7;
+
+/// This plugin plays footsteps while the player walks, sounding like the block they walk on.
+pub(super) struct FootstepPlugin;
+
+impl Plugin for FootstepPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, systems::play_footsteps);
+    }
+}
+
+/// How far the player walked since their last footstep.
+#[derive(Default)]
+struct Stride {
+    /// Where the player was last frame.
+    previous: Option<Vec3>,
+    walked: f32,
+}
+
+mod systems {
+    use super::*;
+
+    /// Only players that walk, rather than fly, make footsteps. They walk while they're on the ground, which is
+    /// whenever they're not moving up or down. The camera is the player.
+    pub(super) fn play_footsteps(
+        mut commands: Commands,
+        mut stride: Local<Stride>,
+        sounds: Sounds,
+        voxel_world: VoxelWorld,
+        player_query: Query<(&Transform, &Velocity, &TerrainCollider), With<Camera3d>>,
+    ) {
+        let Ok((transform, velocity, collider)) = player_query.get_single() else {
+            stride.previous = None;
+            return;
+        };
+
+        let position = transform.translation;
+        let previous = stride.previous.replace(position).unwrap_or(position);
+        if velocity.0.y != 0.0 {
+            return;
+        }
+
+        stride.walked += (position - previous).xz().length();
+        if stride.walked < STRIDE_LENGTH {
+            return;
+        }
+        stride.walked = 0.0;
+
+        // The voxel whose top the player stands on.
+        let ground = (position - Vec3::Y * (collider.height + 0.5))
+            .round()
+            .as_ivec3();
+        let Some(sound_set) = voxel_world
+            .get_block(ground)
+            .and_then(|voxel| voxel.definition().sounds)
+        else {
+            return;
+        };
+
+        sounds.play_at(
+            &mut commands,
+            &sound_set.event_name(BlockSound::Step),
+            ground.as_vec3() + Vec3::Y * 0.5,
+            AudioBus::Effects,
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! Sounds of the world, heard from where they happen.
+//!
+//! Sounds are played by the name of their event in the [SoundRegistry], which says what files to play for it. The
+//! default files are in `sounds/` in the assets folder. Files that are missing are logged once, and stay silent.
+
+mod ambience;
+mod block;
+mod block_ambience;
+mod footstep;
+mod music;
+mod registry;
+mod simulation;
+mod weather;
+
+use bevy::{
+    audio::{PlaybackMode, SpatialScale, Volume},
+    ecs::system::{EntityCommands, SystemParam},
+    prelude::*,
+    transform::TransformSystem,
+};
+
+use crate::settings::{AudioBus, AudioSettings};
+
+use super::{underwater::CameraMedium, world::VoxelWorld};
+
+pub(super) use self::block::BlockSoundSet;
+
+use self::{
+    ambience::AmbiencePlugin, block::BlockSoundPlugin, block_ambience::BlockAmbiencePlugin,
+    footstep::FootstepPlugin, music::MusicPlugin, registry::SoundRegistry,
+    simulation::SimulationSoundPlugin, weather::WeatherSoundPlugin,
+};
+
+/// Where the [SoundRegistry] is loaded from, relative to the working directory.
+const SOUNDS_PATH: &str = "sounds.ron";
+
+/// How far apart the ears of the listener are, in voxels.
+const EAR_GAP: f32 = 0.3;
+/// How many voxels make up a unit of distance for spatial sounds. Spatial sounds get quieter with the square of the
+/// distance in these units, past the first one.
+const AUDIO_DISTANCE_UNIT: f32 = 4.0;
+/// How far above the camera the sky is looked for, in voxels. Anything solid in between covers the camera.
+const SKY_SCAN_HEIGHT: i32 = 32;
+/// How far around the camera, in voxels, the other columns looking for the sky are.
+const SKY_SCAN_SPREAD: i32 = 4;
+/// How loud muffled sounds are while the camera is under water.
+const UNDERWATER_VOLUME: f32 = 0.3;
+/// How long muffling takes to fade in or out when the camera goes in or out of the water, in seconds.
+const MUFFLE_FADE_TIME: f32 = 0.3;
+
+/// This plugin plays the sounds of the game. Sounds of things happening in the world are spatial, and the camera, which
+/// is the player, hears them. Ambience and music aren't, they play all around the camera.
+///
+/// Every sound is [Mixed] into one of the [AudioBus]es, and played at its volume from the [AudioSettings]. Sounds of
+/// the world are muffled while the camera is under water. The sinks Bevy plays sounds with can't be filtered once
+/// they're playing, so they're turned down instead.
+pub(super) struct VoxelAudioPlugin;
+
+impl Plugin for VoxelAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SoundRegistry::load(SOUNDS_PATH))
+            .init_resource::<Surroundings>()
+            .init_resource::<Muffling>()
+            .init_resource::<AudioSettings>()
+            .insert_resource(SpatialScale::new(1.0 / AUDIO_DISTANCE_UNIT))
+            .add_plugins((
+                BlockSoundPlugin,
+                FootstepPlugin,
+                AmbiencePlugin,
+                MusicPlugin,
+                SimulationSoundPlugin,
+                BlockAmbiencePlugin,
+                WeatherSoundPlugin,
+            ))
+            .add_systems(
+                Update,
+                (
+                    systems::add_listener,
+                    (systems::update_surroundings, systems::fade_muffling).chain(),
+                ),
+            )
+            // Before the sounds are started, which happens after the transforms are propagated.
+            .add_systems(
+                PostUpdate,
+                systems::mix_volumes.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// What's around the camera, which decides what the ambience and music sound like.
+#[derive(Resource, Default, Debug)]
+struct Surroundings {
+    /// How much of the sky around the camera is covered, from 0 for open sky to 1.
+    cover: f32,
+    /// How many solid voxels are right above the camera, up to [SKY_SCAN_HEIGHT].
+    depth: i32,
+    /// The height of the camera.
+    height: f32,
+    /// Whether the camera is under water, see [CameraMedium].
+    underwater: bool,
+}
+
+impl Surroundings {
+    /// Looks for the sky above the camera, and a few columns around it. Columns in unloaded chunks count as open.
+    fn around(voxel_world: &VoxelWorld, position: Vec3, medium: CameraMedium) -> Self {
+        let voxel_pos = position.round().as_ivec3();
+        let solid_above = |column: IVec3| {
+            (1..=SKY_SCAN_HEIGHT)
+                .filter(|dy| {
+                    voxel_world
+                        .get_block(column + IVec3::Y * *dy)
+                        .is_some_and(|voxel| voxel.is_solid())
+                })
+                .count() as i32
+        };
+
+        let columns = [
+            IVec3::ZERO,
+            IVec3::X * SKY_SCAN_SPREAD,
+            IVec3::NEG_X * SKY_SCAN_SPREAD,
+            IVec3::Z * SKY_SCAN_SPREAD,
+            IVec3::NEG_Z * SKY_SCAN_SPREAD,
+        ];
+        let covered = columns
+            .iter()
+            .filter(|offset| solid_above(voxel_pos + **offset) > 0)
+            .count();
+
+        Self {
+            cover: covered as f32 / columns.len() as f32,
+            depth: solid_above(voxel_pos),
+            height: position.y,
+            underwater: medium == CameraMedium::Water,
+        }
+    }
+}
+
+/// How muffled the sounds of the world are, from 0 to 1. It fades in while the camera is under water.
+#[derive(Resource, Default, Debug)]
+struct Muffling(f32);
+
+/// The [AudioBus] a sound is played on, and how loud it is before the volume of the bus is applied. Sounds with this
+/// ignore the volume of their [PlaybackSettings], set this instead.
+#[derive(Component, Debug)]
+struct Mixed {
+    bus: AudioBus,
+    /// From 0 to 1.
+    volume: f32,
+    /// Whether the sound is heard muffled under water. Sounds of the world are, music isn't.
+    muffled: bool,
+}
+
+/// System param for playing the sound events of the [SoundRegistry].
+#[derive(SystemParam)]
+struct Sounds<'w> {
+    asset_server: Res<'w, AssetServer>,
+    registry: Res<'w, SoundRegistry>,
+}
+
+impl Sounds<'_> {
+    /// Picks one of the sounds of the named event, and how loud to play it. The bundle plays it with `settings`, at the
+    /// pitch picked. [None] if there's no such event.
+    fn pick(&self, name: &str, settings: PlaybackSettings) -> Option<(AudioBundle, f32)> {
+        let sound = self.registry.pick(name)?;
+        let bundle = AudioBundle {
+            source: self.asset_server.load(sound.path),
+            settings: PlaybackSettings {
+                speed: sound.speed,
+                ..settings
+            },
+        };
+
+        Some((bundle, sound.volume))
+    }
+
+    /// Plays the named event once at a position in the world, from an entity that's despawned once the sound is done.
+    fn play_at<'w, 's, 'a>(
+        &self,
+        commands: &'a mut Commands<'w, 's>,
+        name: &str,
+        position: Vec3,
+        bus: AudioBus,
+    ) -> Option<EntityCommands<'w, 's, 'a>> {
+        let settings = PlaybackSettings {
+            mode: PlaybackMode::Despawn,
+            spatial: true,
+            ..default()
+        };
+        let (bundle, volume) = self.pick(name, settings)?;
+
+        Some(commands.spawn((
+            bundle,
+            TransformBundle::from_transform(Transform::from_translation(position)),
+            Mixed {
+                bus,
+                volume,
+                muffled: true,
+            },
+        )))
+    }
+}
+
+mod systems {
+    use super::*;
+
+    /// The camera is the player.
+    pub(super) fn add_listener(
+        mut commands: Commands,
+        camera_query: Query<Entity, (With<Camera3d>, Without<SpatialListener>)>,
+    ) {
+        for entity in &camera_query {
+            commands
+                .entity(entity)
+                .insert(SpatialListener::new(EAR_GAP));
+        }
+    }
+
+    /// The camera is the player.
+    pub(super) fn update_surroundings(
+        mut surroundings: ResMut<Surroundings>,
+        voxel_world: VoxelWorld,
+        camera_medium: Option<Res<CameraMedium>>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+    ) {
+        if let Ok(transform) = camera_query.get_single() {
+            let medium = camera_medium.map_or(CameraMedium::Air, |medium| *medium);
+            *surroundings = Surroundings::around(&voxel_world, transform.translation, medium);
+        }
+    }
+
+    pub(super) fn fade_muffling(
+        time: Res<Time>,
+        surroundings: Res<Surroundings>,
+        mut muffling: ResMut<Muffling>,
+    ) {
+        let step = time.delta_seconds() / MUFFLE_FADE_TIME;
+        muffling.0 = if surroundings.underwater {
+            (muffling.0 + step).min(1.0)
+        } else {
+            (muffling.0 - step).max(0.0)
+        };
+    }
+
+    /// Sounds that haven't started yet start at the mixed volume, and the ones playing are changed to it.
+    pub(super) fn mix_volumes(
+        audio_settings: Res<AudioSettings>,
+        muffling: Res<Muffling>,
+        mut sound_query: Query<(
+            &Mixed,
+            &mut PlaybackSettings,
+            Option<&AudioSink>,
+            Option<&SpatialAudioSink>,
+        )>,
+    ) {
+        for (mixed, mut settings, sink, spatial_sink) in &mut sound_query {
+            let mut volume = mixed.volume * audio_settings.volume(mixed.bus);
+            if mixed.muffled {
+                volume *= 1.0 - muffling.0 * (1.0 - UNDERWATER_VOLUME);
+            }
+            settings.volume = Volume::new_relative(volume);
+
+            if let Some(sink) = sink {
+                sink.set_volume(volume);
+            }
+            if let Some(sink) = spatial_sink {
+                sink.set_volume(volume);
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    multiplayer::MultiplayerMenuState,
+    settings::{AudioBus, SettingsMenuState},
+    sky::TimeOfDay,
+};
+
+use super::{Mixed, Sounds, Surroundings};
+
+/// Where the [MusicPlaylist] is loaded from, relative to the working directory.
+const PLAYLIST_PATH: &str = "music.ron";
+/// How many solid voxels have to be above the camera for the underground music to play.
+const UNDERGROUND_DEPTH: i32 = 8;
+
+/// This plugin plays music that fits what the player is doing, from the sound events in the [MusicPlaylist]. When
+/// the [MusicContext] changes, the track playing fades out while a track of the new context fades in.
+pub(super) struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MusicPlaylist::load(PLAYLIST_PATH))
+            .add_systems(Update, systems::play_music);
+    }
+}
+
+/// What the player is doing, which decides what music plays.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum MusicContext {
+    /// A menu is open.
+    Menu,
+    SurfaceDay,
+    SurfaceNight,
+    /// The camera is deep below solid voxels, like in a cave.
+    Underground,
+}
+
+impl MusicContext {
+    fn current(menu_open: bool, surroundings: &Surroundings, daylight: f32) -> Self {
+        if menu_open {
+            MusicContext::Menu
+        } else if surroundings.cover >= 1.0 && surroundings.depth >= UNDERGROUND_DEPTH {
+            MusicContext::Underground
+        } else if daylight >= 0.5 {
+            MusicContext::SurfaceDay
+        } else {
+            MusicContext::SurfaceNight
+        }
+    }
+}
+
+/// The music of every [MusicContext]. It's loaded from [PLAYLIST_PATH], which is written with the default playlist if
+/// it doesn't exist yet. The tracks themselves are the sounds of the events, so they're added in the sound registry.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct MusicPlaylist {
+    /// How long a track takes to fade in or out, in seconds.
+    crossfade: f32,
+    pools: Vec<TrackPool>,
+}
+
+/// The sound event a track is picked from while in a [MusicContext].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TrackPool {
+    context: MusicContext,
+    /// The name of the sound event, like `music.day`.
+    sound: String,
+}
+
+impl MusicPlaylist {
+    fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|err| {
+                warn!("Failed to parse {}: {err}", path.display());
+                Self::default()
+            }),
+            Err(_) => {
+                let playlist = Self::default();
+                playlist.save(path);
+                playlist
+            }
+        }
+    }
+
+    fn save(&self, path: &Path) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                error!("Failed to serialize the music playlist: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = fs::write(path, contents) {
+            error!("Failed to write {}: {err}", path.display());
+        }
+    }
+
+    /// The first pool of the context.
+    fn pool(&self, context: MusicContext) -> Option<&TrackPool> {
+        self.pools.iter().find(|pool| pool.context == context)
+    }
+}
+
+impl Default for MusicPlaylist {
+    fn default() -> Self {
+        let pool = |context, sound: &str| TrackPool {
+            context,
+            sound: sound.to_string(),
+        };
+
+        Self {
+            crossfade: 4.0,
+            pools: vec![
+                pool(MusicContext::Menu, "music.menu"),
+                pool(MusicContext::SurfaceDay, "music.day"),
+                pool(MusicContext::SurfaceNight, "music.night"),
+                pool(MusicContext::Underground, "music.underground"),
+            ],
+        }
+    }
+}
+
+/// A track that's playing. Only the current one is faded in, the others fade out and are despawned.
+#[derive(Component, Debug)]
+struct MusicTrack {
+    context: MusicContext,
+    current: bool,
+    /// How far the track is faded in, from 0 to 1.
+    fade: f32,
+    /// The volume the track was picked with, when faded in.
+    volume: f32,
+}
+
+mod systems {
+    use super::*;
+
+    pub(super) fn play_music(
+        mut commands: Commands,
+        time: Res<Time>,
+        sounds: Sounds,
+        playlist: Res<MusicPlaylist>,
+        surroundings: Res<Surroundings>,
+        time_of_day: Option<Res<TimeOfDay>>,
+        settings_menu: Option<Res<State<SettingsMenuState>>>,
+        multiplayer_menu: Option<Res<State<MultiplayerMenuState>>>,
+        mut track_query: Query<(Entity, &mut MusicTrack, &mut Mixed, Option<&AudioSink>)>,
+    ) {
+        let menu_open = settings_menu.is_some_and(|state| **state == SettingsMenuState::Open)
+            || multiplayer_menu.is_some_and(|state| **state == MultiplayerMenuState::Open);
+        let daylight = time_of_day.map_or(1.0, |time_of_day| time_of_day.daylight());
+        let context = MusicContext::current(menu_open, &surroundings, daylight);
+        let fade_step = time.delta_seconds() / playlist.crossfade.max(f32::EPSILON);
+
+        let mut playing = false;
+        for (entity, mut track, mut mixed, sink) in &mut track_query {
+            // Tracks that played to the end are done, and another one is picked below.
+            if sink.is_some_and(|sink| sink.empty()) {
+                commands.entity(entity).despawn();
+                continue;
+            }
+
+            if track.context != context {
+                track.current = false;
+            }
+            track.fade = if track.current {
+                (track.fade + fade_step).min(1.0)
+            } else {
+                track.fade - fade_step
+            };
+            if track.fade <= 0.0 && !track.current {
+                commands.entity(entity).despawn();
+                continue;
+            }
+
+            playing |= track.current;
+            mixed.volume = track.fade * track.volume;
+        }
+
+        if playing {
+            return;
+        }
+        let Some((bundle, volume)) = playlist
+            .pool(context)
+            .and_then(|pool| sounds.pick(&pool.sound, PlaybackSettings::ONCE))
+        else {
+            return;
+        };
+
+        commands.spawn((
+            bundle,
+            Mixed {
+                bus: AudioBus::Music,
+                volume: 0.0,
+                muffled: false,
+            },
+            MusicTrack {
+                context,
+                current: true,
+                fade: 0.0,
+                volume,
+            },
+        ));
+    }
+}
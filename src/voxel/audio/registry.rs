@@ -0,0 +1,192 @@
+use std::{collections::BTreeMap, fs, ops::RangeInclusive, path::Path};
+
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use super::block::{BlockSound, BlockSoundSet};
+
+/// Every sound event there is. They're loaded from [SOUNDS_PATH](super::SOUNDS_PATH), which is written with the
+/// default events if it doesn't exist yet, so sounds can be swapped without changing the game.
+///
+/// Sounds are played by the name of their event, like `block.stone.break`. Every time an event is played, one of its
+/// files is picked at random, and played at a random volume and pitch from its ranges.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+pub(super) struct SoundRegistry {
+    events: BTreeMap<String, SoundEvent>,
+}
+
+/// The sounds played for an event, and how.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SoundEvent {
+    sounds: Vec<WeightedSound>,
+    /// How loud the sounds are played, from 0 to 1.
+    volume: RangeInclusive<f32>,
+    /// How fast the sounds are played, which raises or lowers their pitch. 1 is the speed of the file.
+    pitch: RangeInclusive<f32>,
+}
+
+/// A sound file of a [SoundEvent], and how likely it is to be picked compared to the others.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct WeightedSound {
+    /// The asset path of the file.
+    path: String,
+    weight: u32,
+}
+
+/// A sound picked from a [SoundEvent], ready to be played.
+pub(super) struct PickedSound {
+    pub(super) path: String,
+    pub(super) volume: f32,
+    pub(super) speed: f32,
+}
+
+impl SoundRegistry {
+    pub(super) fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|err| {
+                warn!("Failed to parse {}: {err}", path.display());
+                Self::default()
+            }),
+            Err(_) => {
+                let registry = Self::default();
+                registry.save(path);
+                registry
+            }
+        }
+    }
+
+    fn save(&self, path: &Path) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                error!("Failed to serialize the sound events: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = fs::write(path, contents) {
+            error!("Failed to write {}: {err}", path.display());
+        }
+    }
+
+    /// Picks one of the sounds of the event with the given name. [None] if there's no such event, or it has no sounds.
+    pub(super) fn pick(&self, name: &str) -> Option<PickedSound> {
+        let Some(event) = self.events.get(name) else {
+            debug!("No sound event named `{name}`");
+            return None;
+        };
+
+        let mut rng = rand::thread_rng();
+        let sound = event
+            .sounds
+            .choose_weighted(&mut rng, |sound| sound.weight)
+            .ok()?;
+
+        Some(PickedSound {
+            path: sound.path.clone(),
+            volume: random_in(&mut rng, &event.volume),
+            speed: random_in(&mut rng, &event.pitch),
+        })
+    }
+}
+
+/// A random value in the range. Ranges with the end before the start give the start.
+fn random_in(rng: &mut impl Rng, range: &RangeInclusive<f32>) -> f32 {
+    if range.start() < range.end() {
+        rng.gen_range(range.clone())
+    } else {
+        *range.start()
+    }
+}
+
+impl Default for SoundRegistry {
+    fn default() -> Self {
+        let event = |paths: &[String], volume, pitch| SoundEvent {
+            sounds: paths
+                .iter()
+                .map(|path| WeightedSound {
+                    path: path.clone(),
+                    weight: 1,
+                })
+                .collect(),
+            volume,
+            pitch,
+        };
+        let file = |path: &str| vec![format!("sounds/{path}.ogg")];
+
+        let mut events = BTreeMap::new();
+
+        for set in BlockSoundSet::ALL {
+            for (sound, volume, pitch) in [
+                (BlockSound::Break, 0.8..=0.8, 0.9..=1.1),
+                (BlockSound::Place, 0.8..=0.8, 0.9..=1.1),
+                (BlockSound::Step, 0.4..=0.4, 0.95..=1.05),
+            ] {
+                let path = format!("block/{}/{}", set.name(), sound.name());
+                events.insert(set.event_name(sound), event(&file(&path), volume, pitch));
+            }
+        }
+
+        for (name, volume) in [("water", 0.6), ("lava", 0.6), ("fire", 0.6)] {
+            events.insert(
+                format!("block.ambient.{name}"),
+                event(
+                    &file(&format!("block/ambient/{name}")),
+                    volume..=volume,
+                    1.0..=1.0,
+                ),
+            );
+        }
+
+        for (name, volume, pitch) in [
+            ("explosion", 1.0..=1.0, 0.8..=1.0),
+            ("water_flow", 0.3..=0.3, 0.9..=1.1),
+            ("lava_flow", 0.3..=0.3, 0.9..=1.1),
+            ("fire", 0.5..=0.5, 0.9..=1.1),
+        ] {
+            events.insert(
+                format!("world.{name}"),
+                event(&file(&format!("world/{name}")), volume, pitch),
+            );
+        }
+
+        for name in ["cave", "wind", "underwater"] {
+            events.insert(
+                format!("ambience.{name}"),
+                event(&file(&format!("ambience/{name}")), 1.0..=1.0, 1.0..=1.0),
+            );
+        }
+
+        events.insert(
+            "weather.rain".to_string(),
+            event(&file("weather/rain"), 1.0..=1.0, 1.0..=1.0),
+        );
+        let thunder: Vec<String> = (1..=3)
+            .map(|i| format!("sounds/weather/thunder_{i}.ogg"))
+            .collect();
+        events.insert(
+            "weather.thunder".to_string(),
+            event(&thunder, 0.7..=1.0, 0.8..=1.1),
+        );
+
+        for (name, tracks, volume) in [
+            ("menu", &["menu"][..], 0.8),
+            ("day", &["day_1", "day_2", "day_3"], 0.6),
+            ("night", &["night_1", "night_2"], 0.5),
+            ("underground", &["underground_1", "underground_2"], 0.5),
+        ] {
+            let paths: Vec<String> = tracks
+                .iter()
+                .map(|track| format!("sounds/music/{track}.ogg"))
+                .collect();
+            events.insert(
+                format!("music.{name}"),
+                event(&paths, volume..=volume, 1.0..=1.0),
+            );
+        }
+
+        Self { events }
+    }
+}
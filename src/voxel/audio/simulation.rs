@@ -0,0 +1,119 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    settings::AudioBus,
+    voxel::{explosion::Explosion, fire::FireBurning, fluid::FluidFlowed},
+};
+
+use super::Sounds;
+
+/// This plugin makes the world simulation heard: explosions, flowing fluids and burning fire play where they happen.
+///
+/// Every [EmitterCategory] is only heard up to a distance, and only plays so many sounds at once, so a flood or a
+/// forest fire doesn't drown out everything else.
+pub(super) struct SimulationSoundPlugin;
+
+impl Plugin for SimulationSoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Explosion>()
+            .add_event::<FluidFlowed>()
+            .add_event::<FireBurning>()
+            .add_systems(Update, systems::play_simulation_sounds);
+    }
+}
+
+/// What kind of simulation event a sound is played for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum EmitterCategory {
+    Explosion,
+    Fluid,
+    Fire,
+}
+
+impl EmitterCategory {
+    /// How many sounds of the category can play at once. Further ones are dropped until one is done.
+    fn budget(&self) -> usize {
+        match self {
+            EmitterCategory::Explosion => 4,
+            EmitterCategory::Fluid => 6,
+            EmitterCategory::Fire => 6,
+        }
+    }
+
+    /// How far from the camera sounds of the category are still played, in voxels.
+    fn range(&self) -> f32 {
+        match self {
+            EmitterCategory::Explosion => 128.0,
+            EmitterCategory::Fluid => 24.0,
+            EmitterCategory::Fire => 32.0,
+        }
+    }
+}
+
+/// Marker component for the sound of a simulation event, holding what kind of event it's for.
+#[derive(Component, Debug)]
+struct SoundEmitter(EmitterCategory);
+
+mod systems {
+    use super::*;
+
+    /// The camera is the player.
+    pub(super) fn play_simulation_sounds(
+        mut commands: Commands,
+        sounds: Sounds,
+        mut explosions: EventReader<Explosion>,
+        mut flows: EventReader<FluidFlowed>,
+        mut burning: EventReader<FireBurning>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+        emitter_query: Query<&SoundEmitter>,
+    ) {
+        let Ok(camera_transform) = camera_query.get_single() else {
+            return;
+        };
+
+        let events = explosions
+            .read()
+            .map(|explosion| {
+                (
+                    EmitterCategory::Explosion,
+                    explosion.center,
+                    "world.explosion".to_string(),
+                )
+            })
+            .chain(flows.read().map(|flow| {
+                (
+                    EmitterCategory::Fluid,
+                    flow.voxel_pos.as_vec3(),
+                    format!("world.{}_flow", flow.fluid.name()),
+                )
+            }))
+            .chain(burning.read().map(|fire| {
+                (
+                    EmitterCategory::Fire,
+                    fire.voxel_pos.as_vec3(),
+                    "world.fire".to_string(),
+                )
+            }));
+
+        let mut playing: HashMap<EmitterCategory, usize> = HashMap::new();
+        for SoundEmitter(category) in &emitter_query {
+            *playing.entry(*category).or_default() += 1;
+        }
+
+        for (category, position, name) in events {
+            let count = playing.entry(category).or_default();
+            if *count >= category.budget()
+                || position.distance(camera_transform.translation) > category.range()
+            {
+                continue;
+            }
+
+            if let Some(mut emitter) =
+                sounds.play_at(&mut commands, &name, position, AudioBus::Effects)
+            {
+                emitter.insert(SoundEmitter(category));
+                *count += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{settings::AudioBus, voxel::weather::Weather};
+
+use super::{Mixed, Sounds, Surroundings};
+
+/// How much of the difference to the rain intensity the rain sound catches up on every second.
+const RAIN_FADE_RATE: f32 = 0.3;
+/// How loud the weather is with the sky around the camera fully covered, compared to out in the open.
+const COVERED_VOLUME: f32 = 0.3;
+/// How many solid voxels have to be above the camera for the weather to be silent.
+const SILENT_DEPTH: i32 = 8;
+/// How long it takes between thunder claps while it rains, in seconds.
+const THUNDER_INTERVAL: Range<f32> = 15.0..45.0;
+/// How far from the camera thunder is heard from, in voxels. It's within the first
+/// [audio distance unit](super::AUDIO_DISTANCE_UNIT), so it comes from a direction without getting quieter.
+const THUNDER_DISTANCE: f32 = 3.5;
+
+/// This plugin plays the sound of the rain, as loud as it rains, and thunder now and then while it does. Both get
+/// quieter under cover, and can't be heard deep underground.
+pub(super) struct WeatherSoundPlugin;
+
+impl Plugin for WeatherSoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ThunderTimer>()
+            .add_systems(Startup, systems::spawn_rain_sound)
+            .add_systems(
+                Update,
+                (systems::fade_rain_sound, systems::play_thunder)
+                    .run_if(resource_exists::<State<Weather>>()),
+            );
+    }
+}
+
+/// The looping sound of the rain, which fades its [Mixed] volume in and out with the rain.
+#[derive(Component, Debug)]
+struct RainSound {
+    /// How hard it sounds like it rains, from 0 to 1. It follows [Weather::rain_intensity].
+    intensity: f32,
+    /// The volume the sound event was picked with, in a downpour out in the open.
+    volume: f32,
+}
+
+/// Counts down until the next thunder clap.
+#[derive(Resource)]
+struct ThunderTimer(Timer);
+
+impl Default for ThunderTimer {
+    fn default() -> Self {
+        Self(random_thunder_timer())
+    }
+}
+
+fn random_thunder_timer() -> Timer {
+    Timer::from_seconds(
+        rand::thread_rng().gen_range(THUNDER_INTERVAL),
+        TimerMode::Once,
+    )
+}
+
+/// How much of the weather can be heard in the surroundings of the camera, from 0 to 1.
+fn exposure(surroundings: &Surroundings) -> f32 {
+    let covered = 1.0 - surroundings.cover * (1.0 - COVERED_VOLUME);
+    let buried = (1.0 - surroundings.depth as f32 / SILENT_DEPTH as f32).max(0.0);
+    covered * buried
+}
+
+mod systems {
+    use super::*;
+
+    pub(super) fn spawn_rain_sound(mut commands: Commands, sounds: Sounds) {
+        let Some((bundle, volume)) = sounds.pick("weather.rain", PlaybackSettings::LOOP) else {
+            return;
+        };
+
+        commands.spawn((
+            bundle,
+            RainSound {
+                intensity: 0.0,
+                volume,
+            },
+            Mixed {
+                bus: AudioBus::Ambience,
+                volume: 0.0,
+                muffled: true,
+            },
+        ));
+    }
+
+    pub(super) fn fade_rain_sound(
+        time: Res<Time>,
+        weather: Res<State<Weather>>,
+        surroundings: Res<Surroundings>,
+        mut rain_query: Query<(&mut RainSound, &mut Mixed)>,
+    ) {
+        let fade = (RAIN_FADE_RATE * time.delta_seconds()).min(1.0);
+        let target = weather.rain_intensity();
+        for (mut rain, mut mixed) in &mut rain_query {
+            rain.intensity += (target - rain.intensity) * fade;
+            mixed.volume = rain.intensity * rain.volume * exposure(&surroundings);
+        }
+    }
+
+    /// Plays thunder from a random direction in the sky while it rains. The camera is the listener.
+    pub(super) fn play_thunder(
+        mut commands: Commands,
+        time: Res<Time>,
+        sounds: Sounds,
+        weather: Res<State<Weather>>,
+        surroundings: Res<Surroundings>,
+        mut timer: ResMut<ThunderTimer>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+    ) {
+        if weather.rain_intensity() <= 0.0 || !timer.0.tick(time.delta()).finished() {
+            return;
+        }
+        timer.0 = random_thunder_timer();
+
+        let Ok(camera_transform) = camera_query.get_single() else {
+            return;
+        };
+        let settings = PlaybackSettings {
+            spatial: true,
+            ..PlaybackSettings::DESPAWN
+        };
+        let Some((bundle, volume)) = sounds.pick("weather.thunder", settings) else {
+            return;
+        };
+
+        let mut rng = rand::thread_rng();
+        let direction = Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(0.2..1.0),
+            rng.gen_range(-1.0..1.0),
+        )
+        .normalize();
+        let position = camera_transform.translation + direction * THUNDER_DISTANCE;
+
+        commands.spawn((
+            bundle,
+            TransformBundle::from_transform(Transform::from_translation(position)),
+            Mixed {
+                bus: AudioBus::Ambience,
+                volume: volume * exposure(&surroundings),
+                muffled: true,
+            },
+        ));
+    }
+}
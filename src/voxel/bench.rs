@@ -0,0 +1,277 @@
+use std::{fs, io, path::PathBuf};
+
+use bevy::{app::AppExit, prelude::*, transform::TransformSystem};
+use serde::Serialize;
+
+use super::{
+    diagnostics::VoxelPipelineStats, generation::VoxelChunkWidth, load::ChunkLoadQueue,
+    persistence::WorldDir, render::ChunkRenderQueue,
+};
+
+/// The seed of the world every benchmark flies over.
+pub const BENCH_SEED: u32 = 1337;
+/// The render distance benchmarks run at, unless another one is given.
+pub const BENCH_RENDER_DISTANCE: u32 = 8;
+/// Where the report is written by default, without an extension.
+pub const DEFAULT_BENCH_REPORT: &str = "bench_report";
+/// The directory in the temporary directory the benchmark world is generated in.
+const BENCH_WORLD_DIR: &str = "voxel_bench_world";
+/// The corners of the path the camera flies along, in world voxel positions.
+const BENCH_PATH: [Vec3; 5] = [
+    Vec3::new(0.0, 80.0, 0.0),
+    Vec3::new(384.0, 80.0, 0.0),
+    Vec3::new(384.0, 60.0, 384.0),
+    Vec3::new(0.0, 100.0, 384.0),
+    Vec3::new(0.0, 80.0, 0.0),
+];
+/// How fast the camera flies along the [BENCH_PATH], in voxels per second.
+const BENCH_SPEED: f32 = 24.0;
+/// How far the camera looks down while flying, in voxels per voxel forward.
+const BENCH_LOOK_DOWN: f32 = 0.3;
+
+/// This plugin benchmarks the game. It flies the camera along [BENCH_PATH] at [BENCH_SPEED], recording the frame time,
+/// how many chunks were generated and meshed, and how long the queues were every frame. Once the path is done, the
+/// game exits and the report is written: a summary to `<report>.json`, and every frame to `<report>.csv`. Closing the
+/// game early still writes the frames so far.
+///
+/// The camera is where it should be at the time since the start, not a fixed step every frame, so every run flies the
+/// same path in the same time and loads the same chunks along the way. The world should be generated from [BENCH_SEED]
+/// in a fresh [bench_world_dir], so runs on different commits can be compared.
+pub struct VoxelBenchPlugin {
+    /// Where the report is written, without an extension.
+    pub report_path: PathBuf,
+}
+
+impl Plugin for VoxelBenchPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BenchRecording {
+            report_path: self.report_path.clone(),
+            started_at: None,
+            frames: Vec::new(),
+        })
+        .add_systems(
+            PostUpdate,
+            (systems::fly_bench_path, systems::record_bench_frame)
+                .chain()
+                .before(TransformSystem::TransformPropagate),
+        )
+        .add_systems(Last, systems::write_bench_report);
+    }
+}
+
+/// A world directory that nothing was saved in yet, so the benchmark never continues a saved world, or saves over one.
+pub fn bench_world_dir() -> WorldDir {
+    let dir = std::env::temp_dir().join(BENCH_WORLD_DIR);
+    match fs::remove_dir_all(&dir) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            warn!("Failed to delete {}: {err}", dir.display());
+        }
+        _ => {}
+    }
+
+    WorldDir(dir)
+}
+
+#[derive(Resource, Debug)]
+struct BenchRecording {
+    report_path: PathBuf,
+    /// When the camera started flying, in seconds since the app started.
+    started_at: Option<f64>,
+    frames: Vec<BenchFrame>,
+}
+
+/// What happened in a single frame of the benchmark.
+#[derive(Debug, Clone, Copy)]
+struct BenchFrame {
+    /// Seconds since the benchmark started.
+    time: f64,
+    frame_time_ms: f64,
+    chunks_generated: u32,
+    meshes_built: u32,
+    mesh_time_ms: f64,
+    load_queue_len: usize,
+    render_queue_len: usize,
+}
+
+/// The summary of a benchmark, written as JSON.
+#[derive(Serialize, Debug)]
+struct BenchSummary {
+    seed: u32,
+    chunk_width: u8,
+    duration_secs: f64,
+    frames: usize,
+    average_fps: f64,
+    average_frame_time_ms: f64,
+    median_frame_time_ms: f64,
+    p95_frame_time_ms: f64,
+    p99_frame_time_ms: f64,
+    max_frame_time_ms: f64,
+    chunks_generated: u64,
+    chunks_generated_per_sec: f64,
+    meshes_built: u64,
+    meshes_built_per_sec: f64,
+    average_mesh_time_ms: f64,
+}
+
+impl BenchSummary {
+    fn new(frames: &[BenchFrame], chunk_width: u8) -> Self {
+        let duration_secs = frames.last().map_or(0.0, |frame| frame.time);
+        let mut frame_times: Vec<f64> = frames.iter().map(|frame| frame.frame_time_ms).collect();
+        frame_times.sort_by(f64::total_cmp);
+        let percentile = |percent: f64| {
+            let index = ((frame_times.len() as f64 - 1.0) * percent)
+                .round()
+                .max(0.0) as usize;
+            frame_times.get(index).copied().unwrap_or(0.0)
+        };
+
+        let total_frame_time_ms: f64 = frame_times.iter().sum();
+        let chunks_generated: u64 = frames
+            .iter()
+            .map(|frame| frame.chunks_generated as u64)
+            .sum();
+        let meshes_built: u64 = frames.iter().map(|frame| frame.meshes_built as u64).sum();
+        let mesh_time_ms: f64 = frames.iter().map(|frame| frame.mesh_time_ms).sum();
+        let per_sec = |count: u64| {
+            if duration_secs > 0.0 {
+                count as f64 / duration_secs
+            } else {
+                0.0
+            }
+        };
+
+        Self {
+            seed: BENCH_SEED,
+            chunk_width,
+            duration_secs,
+            frames: frames.len(),
+            average_fps: per_sec(frames.len() as u64),
+            average_frame_time_ms: total_frame_time_ms / frame_times.len().max(1) as f64,
+            median_frame_time_ms: percentile(0.5),
+            p95_frame_time_ms: percentile(0.95),
+            p99_frame_time_ms: percentile(0.99),
+            max_frame_time_ms: frame_times.last().copied().unwrap_or(0.0),
+            chunks_generated,
+            chunks_generated_per_sec: per_sec(chunks_generated),
+            meshes_built,
+            meshes_built_per_sec: per_sec(meshes_built),
+            average_mesh_time_ms: mesh_time_ms / meshes_built.max(1) as f64,
+        }
+    }
+}
+
+/// Where the camera is on the [BENCH_PATH] after flying for `distance` voxels, and where it's heading. [None] once
+/// it's past the end.
+fn point_on_path(mut distance: f32) -> Option<(Vec3, Vec3)> {
+    for segment in BENCH_PATH.windows(2) {
+        let (start, end) = (segment[0], segment[1]);
+        let length = start.distance(end);
+        if distance <= length {
+            let direction = (end - start) / length;
+            return Some((start + direction * distance, direction));
+        }
+        distance -= length;
+    }
+
+    None
+}
+
+fn write_report(recording: &BenchRecording, chunk_width: u8) -> io::Result<(PathBuf, PathBuf)> {
+    let json_path = recording.report_path.with_extension("json");
+    let csv_path = recording.report_path.with_extension("csv");
+    if let Some(dir) = json_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+
+    let summary = BenchSummary::new(&recording.frames, chunk_width);
+    let json = serde_json::to_string_pretty(&summary)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    fs::write(&json_path, json)?;
+
+    let mut csv = String::from(
+        "time,frame_time_ms,chunks_generated,meshes_built,mesh_time_ms,load_queue_len,render_queue_len\n",
+    );
+    for frame in &recording.frames {
+        csv.push_str(&format!(
+            "{:.4},{:.3},{},{},{:.3},{},{}\n",
+            frame.time,
+            frame.frame_time_ms,
+            frame.chunks_generated,
+            frame.meshes_built,
+            frame.mesh_time_ms,
+            frame.load_queue_len,
+            frame.render_queue_len
+        ));
+    }
+    fs::write(&csv_path, csv)?;
+
+    Ok((json_path, csv_path))
+}
+
+mod systems {
+    use super::*;
+
+    /// Moves the camera to where it should be on the path by now, and exits once the path is done.
+    pub(super) fn fly_bench_path(
+        time: Res<Time<Real>>,
+        mut recording: ResMut<BenchRecording>,
+        mut exit: EventWriter<AppExit>,
+        mut camera_query: Query<&mut Transform, With<Camera3d>>,
+    ) {
+        let Ok(mut camera_transform) = camera_query.get_single_mut() else {
+            return;
+        };
+
+        let now = time.elapsed_seconds_f64();
+        let elapsed = now - *recording.started_at.get_or_insert(now);
+        let Some((position, direction)) = point_on_path(elapsed as f32 * BENCH_SPEED) else {
+            exit.send(AppExit);
+            return;
+        };
+
+        *camera_transform = Transform::from_translation(position)
+            .looking_to(direction - Vec3::Y * BENCH_LOOK_DOWN, Vec3::Y);
+    }
+
+    pub(super) fn record_bench_frame(
+        time: Res<Time<Real>>,
+        stats: Res<VoxelPipelineStats>,
+        chunk_load_queue: Option<Res<ChunkLoadQueue>>,
+        chunk_render_queue: Option<Res<ChunkRenderQueue>>,
+        mut recording: ResMut<BenchRecording>,
+    ) {
+        let Some(started_at) = recording.started_at else {
+            return;
+        };
+
+        let frame = BenchFrame {
+            time: time.elapsed_seconds_f64() - started_at,
+            frame_time_ms: time.delta_seconds_f64() * 1000.0,
+            chunks_generated: stats.chunks_generated,
+            meshes_built: stats.meshes_built,
+            mesh_time_ms: stats.mesh_time.as_secs_f64() * 1000.0,
+            load_queue_len: chunk_load_queue.map_or(0, |queue| queue.load_len()),
+            render_queue_len: chunk_render_queue.map_or(0, |queue| queue.len()),
+        };
+        recording.frames.push(frame);
+    }
+
+    pub(super) fn write_bench_report(
+        exit: EventReader<AppExit>,
+        recording: Res<BenchRecording>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        if exit.is_empty() {
+            return;
+        }
+
+        match write_report(&recording, chunk_width.0) {
+            Ok((json_path, csv_path)) => info!(
+                "Wrote the benchmark report to {} and {}",
+                json_path.display(),
+                csv_path.display()
+            ),
+            Err(err) => error!("Failed to write the benchmark report: {err}"),
+        }
+    }
+}
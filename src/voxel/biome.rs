@@ -0,0 +1,156 @@
+use noise::{NoiseFn, Simplex};
+use serde::{Deserialize, Serialize};
+
+use super::Voxel;
+
+/// How quickly the climate changes over the world. Lower makes larger biomes.
+const CLIMATE_FREQUENCY: f64 = 0.002;
+/// How far around a column the biomes are blended, in voxels, unless the world says otherwise.
+pub(super) const DEFAULT_BLEND_RADIUS: u32 = 16;
+
+/// The kind of landscape at a column of the world, picked by its climate.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum Biome {
+    Plains,
+    /// Hot and dry. Flat sand dunes.
+    Desert,
+    /// Cold. High and rugged.
+    Highlands,
+}
+
+/// What a [Biome] changes about the terrain. Everything but the voxels is blended with the biomes around it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct BiomeSettings {
+    /// How far the surface is raised, in voxels.
+    pub(super) height: f64,
+    /// How much of the mountains the terrain noise raises is kept.
+    pub(super) amplitude: f64,
+    /// The voxel on top of the surface.
+    pub(super) surface: Voxel,
+    /// The voxels below the top one, down to `filler_depth`.
+    pub(super) filler: Voxel,
+    /// How many voxels deep the surface is, counting the top one.
+    pub(super) filler_depth: f64,
+}
+
+impl Biome {
+    pub(super) fn settings(self) -> BiomeSettings {
+        match self {
+            Biome::Plains => BiomeSettings {
+                height: 0.0,
+                amplitude: 0.6,
+                surface: Voxel::GRASS,
+                filler: Voxel::DIRT,
+                filler_depth: 4.0,
+            },
+            Biome::Desert => BiomeSettings {
+                height: 2.0,
+                amplitude: 0.25,
+                surface: Voxel::SAND,
+                filler: Voxel::SAND,
+                filler_depth: 5.0,
+            },
+            Biome::Highlands => BiomeSettings {
+                height: 14.0,
+                amplitude: 1.5,
+                surface: Voxel::GRASS,
+                filler: Voxel::DIRT,
+                filler_depth: 2.0,
+            },
+        }
+    }
+}
+
+/// The biomes around a column, weighted by how close they are, so the terrain doesn't jump at the border between two.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct BiomeBlend {
+    /// The biome of the column itself, which picks the voxels of the surface.
+    pub(super) biome: Biome,
+    pub(super) height: f64,
+    pub(super) amplitude: f64,
+    pub(super) filler_depth: f64,
+}
+
+/// Picks the [Biome] of every column from its temperature and humidity, which are two large noises.
+pub(super) struct BiomeMap {
+    temperature: Simplex,
+    humidity: Simplex,
+    /// How far around a column the biomes are blended, in voxels. 0 turns blending off.
+    blend_radius: u32,
+}
+
+impl BiomeMap {
+    pub(super) fn new(seed: u32, blend_radius: u32) -> Self {
+        Self {
+            temperature: Simplex::new(seed ^ 0x7e3a_1c59),
+            humidity: Simplex::new(seed ^ 0x51d2_e88b),
+            blend_radius,
+        }
+    }
+
+    pub(super) fn blend_radius(&self) -> u32 {
+        self.blend_radius
+    }
+
+    pub(super) fn biome_at(&self, x: i32, z: i32) -> Biome {
+        let point = [x as f64 * CLIMATE_FREQUENCY, z as f64 * CLIMATE_FREQUENCY];
+        let temperature = self.temperature.get(point);
+        let humidity = self.humidity.get(point);
+
+        if temperature < -0.3 {
+            Biome::Highlands
+        } else if temperature > 0.2 && humidity < 0.0 {
+            Biome::Desert
+        } else {
+            Biome::Plains
+        }
+    }
+
+    /// Blends the settings of the biomes within the blend radius of the column. Rather than every column around it, a
+    /// grid of them is sampled, weighted by a smooth falloff from the middle.
+    pub(super) fn blend(&self, x: i32, z: i32) -> BiomeBlend {
+        let biome = self.biome_at(x, z);
+        let radius = self.blend_radius as i32;
+        if radius == 0 {
+            let settings = biome.settings();
+            return BiomeBlend {
+                biome,
+                height: settings.height,
+                amplitude: settings.amplitude,
+                filler_depth: settings.filler_depth,
+            };
+        }
+
+        let step = (radius / 2).max(1);
+        let steps = radius / step;
+
+        let mut total_weight = 0.0;
+        let mut blend = BiomeBlend {
+            biome,
+            height: 0.0,
+            amplitude: 0.0,
+            filler_depth: 0.0,
+        };
+        for step_z in -steps..=steps {
+            for step_x in -steps..=steps {
+                let (dx, dz) = (step_x * step, step_z * step);
+                let distance_squared = (dx * dx + dz * dz) as f64 / (radius * radius) as f64;
+                if distance_squared >= 1.0 {
+                    continue;
+                }
+
+                let weight = (1.0 - distance_squared).powi(2);
+                let settings = self.biome_at(x + dx, z + dz).settings();
+                blend.height += settings.height * weight;
+                blend.amplitude += settings.amplitude * weight;
+                blend.filler_depth += settings.filler_depth * weight;
+                total_weight += weight;
+            }
+        }
+
+        blend.height /= total_weight;
+        blend.amplitude /= total_weight;
+        blend.filler_depth /= total_weight;
+        blend
+    }
+}
@@ -0,0 +1,254 @@
+use bevy::{
+    asset::load_internal_asset,
+    pbr::{MaterialPipeline, MaterialPipelineKey, PREPASS_SHADER_HANDLE},
+    prelude::*,
+    reflect::TypePath,
+    render::{
+        mesh::{MeshVertexAttribute, MeshVertexBufferLayout},
+        render_asset::RenderAssets,
+        render_resource::{
+            AsBindGroup, AsBindGroupShaderType, Face, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError, VertexFormat,
+        },
+    },
+};
+
+pub(super) const CHUNK_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x766f_7865_6c5f_6368_756e_6b5f_7368_6472);
+const CHUNK_PREPASS_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x766f_7865_6c5f_6368_756e_6b5f_7072_6570);
+const CHUNK_VERTEX_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x766f_7865_6c5f_6368_756e_6b5f_7665_7274);
+
+/// How much ambient light reaches a vertex of a chunk mesh, from 0.0 in a corner surrounded by voxels to 1.0 in the open.
+pub(super) const ATTRIBUTE_OCCLUSION: MeshVertexAttribute = MeshVertexAttribute::new(
+    "Voxel_Occlusion",
+    0x766f_7865_6c00_0001,
+    VertexFormat::Float32,
+);
+/// The layer of the [ChunkMaterial::array_texture] the face of a vertex is drawn with, or [NO_TEXTURE_LAYER].
+pub(super) const ATTRIBUTE_TEXTURE_LAYER: MeshVertexAttribute = MeshVertexAttribute::new(
+    "Voxel_TextureLayer",
+    0x766f_7865_6c00_0002,
+    VertexFormat::Uint32,
+);
+/// The [ATTRIBUTE_TEXTURE_LAYER] of faces that only have a color.
+pub(super) const NO_TEXTURE_LAYER: u32 = u32::MAX;
+/// A whole vertex of a chunk mesh in two words, see [pack_vertex]. Meshes with packed vertices have no other
+/// attributes.
+pub(super) const ATTRIBUTE_PACKED_VERTEX: MeshVertexAttribute = MeshVertexAttribute::new(
+    "Voxel_PackedVertex",
+    0x766f_7865_6c00_0003,
+    VertexFormat::Uint32x2,
+);
+/// The texture layer of packed vertices that only have a color. Packed vertices can't use the layers from this one up.
+const PACKED_NO_TEXTURE_LAYER: u32 = 0x3ff;
+
+const FLAGS_UNLIT: u32 = 1;
+const FLAGS_DOUBLE_SIDED: u32 = 2;
+const FLAGS_TEXTURED: u32 = 4;
+
+/// This plugin adds the [ChunkMaterial] chunks are drawn with, and its shader.
+pub(super) struct ChunkMaterialPlugin;
+
+impl Plugin for ChunkMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            CHUNK_VERTEX_SHADER_HANDLE,
+            "shaders/chunk_vertex.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            CHUNK_SHADER_HANDLE,
+            "shaders/chunk.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            CHUNK_PREPASS_SHADER_HANDLE,
+            "shaders/chunk_prepass.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_plugins(MaterialPlugin::<ChunkMaterial>::default());
+    }
+}
+
+/// The material of chunk meshes. Unlike a [StandardMaterial], it reads the voxel data the mesher writes into every
+/// vertex: the color of the voxel, its [ATTRIBUTE_OCCLUSION] and its [ATTRIBUTE_TEXTURE_LAYER]. Chunks are lit like
+/// any other mesh, and fade into the [FogSettings] of the camera.
+#[derive(Asset, AsBindGroup, TypePath, Debug, Clone)]
+#[bind_group_data(ChunkMaterialKey)]
+#[uniform(0, ChunkMaterialUniform)]
+pub(super) struct ChunkMaterial {
+    /// Multiplied with the color of every voxel.
+    pub(super) base_color: Color,
+    /// Unlit chunks ignore lights and shadows, for voxels that glow.
+    pub(super) unlit: bool,
+    /// Which faces aren't drawn. [None] draws both sides of every face, like the surface of water seen from below.
+    pub(super) cull_mode: Option<Face>,
+    /// With [AlphaMode::Mask], fragments below the cutoff are discarded in the main pass. The shadows of masked
+    /// chunks are solid, since the shadow pass doesn't read colors or textures.
+    pub(super) alpha_mode: AlphaMode,
+    /// The textures of the voxels, one per layer. Without it, voxels only have their color.
+    #[texture(1, dimension = "2d_array")]
+    #[sampler(2)]
+    pub(super) array_texture: Option<Handle<Image>>,
+}
+
+impl Default for ChunkMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: Color::WHITE,
+            unlit: false,
+            cull_mode: Some(Face::Back),
+            alpha_mode: AlphaMode::Opaque,
+            array_texture: None,
+        }
+    }
+}
+
+/// The uniform of a [ChunkMaterial], as the shader sees it.
+pub(super) use uniform::ChunkMaterialUniform;
+
+mod uniform {
+    // Deriving ShaderType generates trait checks which are never called.
+    #![allow(dead_code)]
+
+    use bevy::{math::Vec4, render::render_resource::ShaderType};
+
+    #[derive(Clone, Default, ShaderType)]
+    pub(in crate::voxel) struct ChunkMaterialUniform {
+        pub(super) base_color: Vec4,
+        pub(super) flags: u32,
+        pub(super) alpha_cutoff: f32,
+    }
+}
+
+impl AsBindGroupShaderType<ChunkMaterialUniform> for ChunkMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> ChunkMaterialUniform {
+        let mut flags = 0;
+        if self.unlit {
+            flags |= FLAGS_UNLIT;
+        }
+        if self.cull_mode.is_none() {
+            flags |= FLAGS_DOUBLE_SIDED;
+        }
+        if self.array_texture.is_some() {
+            flags |= FLAGS_TEXTURED;
+        }
+
+        let alpha_cutoff = match self.alpha_mode {
+            AlphaMode::Mask(cutoff) => cutoff,
+            _ => 0.0,
+        };
+
+        ChunkMaterialUniform {
+            base_color: self.base_color.as_linear_rgba_f32().into(),
+            flags,
+            alpha_cutoff,
+        }
+    }
+}
+
+/// What a [ChunkMaterial] needs its own pipeline for.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct ChunkMaterialKey {
+    cull_mode: Option<Face>,
+}
+
+impl From<&ChunkMaterial> for ChunkMaterialKey {
+    fn from(material: &ChunkMaterial) -> Self {
+        Self {
+            cull_mode: material.cull_mode,
+        }
+    }
+}
+
+impl Material for ChunkMaterial {
+    fn vertex_shader() -> ShaderRef {
+        CHUNK_SHADER_HANDLE.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        CHUNK_SHADER_HANDLE.into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        descriptor.primitive.cull_mode = key.bind_group_data.cull_mode;
+
+        if layout.contains(ATTRIBUTE_PACKED_VERTEX) {
+            // The default prepass shader can't read packed vertices, so they get their own.
+            if descriptor.vertex.shader == PREPASS_SHADER_HANDLE {
+                descriptor.vertex.shader = CHUNK_PREPASS_SHADER_HANDLE;
+            } else if descriptor.vertex.shader != CHUNK_SHADER_HANDLE {
+                return Ok(());
+            }
+
+            descriptor.vertex.shader_defs.push("PACKED_VERTICES".into());
+            if let Some(fragment) = &mut descriptor.fragment {
+                fragment.shader_defs.push("PACKED_VERTICES".into());
+            }
+            descriptor.vertex.buffers =
+                vec![layout.get_layout(&[ATTRIBUTE_PACKED_VERTEX.at_shader_location(0)])?];
+
+            return Ok(());
+        }
+
+        // The prepasses, like the one for shadows, use the default shaders, which only need the usual attributes.
+        if descriptor.vertex.shader != CHUNK_SHADER_HANDLE {
+            return Ok(());
+        }
+
+        descriptor.vertex.buffers = vec![layout.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(2),
+            Mesh::ATTRIBUTE_COLOR.at_shader_location(3),
+            ATTRIBUTE_OCCLUSION.at_shader_location(4),
+            ATTRIBUTE_TEXTURE_LAYER.at_shader_location(5),
+        ])?];
+
+        Ok(())
+    }
+}
+
+/// Packs a vertex of a chunk mesh into the two words of an [ATTRIBUTE_PACKED_VERTEX], which the chunk shader unpacks.
+///
+/// The first word holds the position, local to the chunk, with the height in sixteenths of a voxel, the id of the face
+/// normal and the occlusion. The second word holds the color, with 6 bits per channel and 4 bits of alpha, and the
+/// texture layer. Texture coordinates follow from the position and normal.
+pub(super) fn pack_vertex(
+    position: Vec3,
+    normal_id: u32,
+    occlusion: f32,
+    color: Color,
+    texture_layer: u32,
+) -> [u32; 2] {
+    let corner = position + 0.5;
+    let x = corner.x.round() as u32 & 0x7f;
+    let z = corner.z.round() as u32 & 0x7f;
+    let y = (corner.y * 16.0).round() as u32 & 0x7ff;
+    let occlusion = (occlusion * 3.0).round() as u32 & 0x3;
+
+    let [r, g, b, a] = color.as_rgba_f32();
+    let [r, g, b] = [r, g, b].map(|channel| (channel.clamp(0.0, 1.0) * 63.0).round() as u32);
+    let a = (a.clamp(0.0, 1.0) * 15.0).round() as u32;
+    let texture_layer = texture_layer.min(PACKED_NO_TEXTURE_LAYER);
+
+    [
+        x | z << 7 | y << 14 | normal_id << 25 | occlusion << 28,
+        r | g << 6 | b << 12 | a << 18 | texture_layer << 22,
+    ]
+}
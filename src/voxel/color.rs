@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{data::VoxelData, generation::ChunkMeshSection, Voxel};
+
+/// What the voxels of a world hold. It's saved with the world like the
+/// [TerrainMesher](super::mesher::TerrainMesher), and sent to clients joining it.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoxelMode {
+    /// Every voxel is a kind of block from the registry, like stone or water.
+    #[default]
+    Blocks,
+    /// Solid voxels hold any color instead of a kind of block, for building voxel art. The terrain is generated as
+    /// usual, with every opaque block turned into a [colored voxel](Voxel::from_color) of its color, and players place
+    /// the color they picked rather than the block in their hotbar.
+    ///
+    /// Fluids, plants and other blocks that aren't opaque stay blocks, so they still flow, burn and grow.
+    Colors,
+}
+
+impl VoxelMode {
+    const ALL: [VoxelMode; 2] = [VoxelMode::Blocks, VoxelMode::Colors];
+
+    /// The mode with the given name, see [VoxelMode::name]. The error lists the names there are.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|mode| mode.name()).collect();
+                format!(
+                    "Unknown voxel mode {name:?}, expected one of {}",
+                    names.join(", ")
+                )
+            })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VoxelMode::Blocks => "blocks",
+            VoxelMode::Colors => "colors",
+        }
+    }
+
+    /// Turns a generated voxel into what it is in this mode.
+    pub(super) fn generated_voxel(&self, voxel: Voxel) -> Voxel {
+        match self {
+            VoxelMode::Colors if voxel.mesh_section() == Some(ChunkMeshSection::Opaque) => {
+                Voxel::from_color(voxel.color())
+            }
+            _ => voxel,
+        }
+    }
+}
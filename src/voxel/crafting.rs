@@ -0,0 +1,344 @@
+use std::{fs, path::Path};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use super::inventory::{Inventory, InventoryScreenState, Item, ItemStack};
+
+/// Where the [RecipeRegistry] is loaded from, relative to the working directory.
+const RECIPES_PATH: &str = "recipes.ron";
+/// How many slots wide and high the [CraftingGrid] is.
+const CRAFTING_GRID_WIDTH: usize = 3;
+
+/// This plugin adds crafting to the inventory screen. Items put in the [CraftingGrid] are turned into the result of
+/// the recipe they match, from the [RecipeRegistry].
+///
+/// Items left in the grid go back into the [Inventory] when the inventory screen is closed, or are dropped if they
+/// don't fit.
+pub(super) struct VoxelCraftingPlugin;
+
+impl Plugin for VoxelCraftingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RecipeRegistry::load(RECIPES_PATH))
+            .init_resource::<CraftingGrid>()
+            .add_systems(
+                Update,
+                systems::crafting_screen.run_if(in_state(InventoryScreenState::Open)),
+            )
+            .add_systems(
+                OnExit(InventoryScreenState::Open),
+                systems::empty_crafting_grid,
+            );
+    }
+}
+
+/// Every recipe there is. They're loaded from [RECIPES_PATH], which is written with the default recipes if it doesn't
+/// exist yet, so recipes can be added without changing the game.
+///
+/// Items are referred to by their [name](Item::name). Recipes with items that don't exist are left out.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+pub(super) struct RecipeRegistry {
+    recipes: Vec<Recipe>,
+}
+
+/// A way to turn items in the [CraftingGrid] into another item.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) enum Recipe {
+    /// The ingredients have to be laid out in the pattern, anywhere in the grid. Every character of the pattern is the
+    /// ingredient it's mapped to in the key, and spaces are empty slots.
+    Shaped {
+        pattern: Vec<String>,
+        key: HashMap<char, String>,
+        result: RecipeResult,
+    },
+    /// The ingredients can be anywhere in the grid, in any order.
+    Shapeless {
+        ingredients: Vec<String>,
+        result: RecipeResult,
+    },
+}
+
+/// What a [Recipe] makes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) struct RecipeResult {
+    item: String,
+    count: u16,
+}
+
+/// The items in the crafting grid of the inventory screen, row by row.
+#[derive(Resource, Default, Debug)]
+pub(super) struct CraftingGrid {
+    slots: [Option<ItemStack>; CRAFTING_GRID_WIDTH * CRAFTING_GRID_WIDTH],
+}
+
+impl RecipeRegistry {
+    fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let mut registry = match fs::read_to_string(path) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|err| {
+                warn!("Failed to parse {}: {err}", path.display());
+                Self::default()
+            }),
+            Err(_) => {
+                let registry = Self::default();
+                registry.save(path);
+                registry
+            }
+        };
+
+        registry.recipes.retain(|recipe| {
+            let unknown: Vec<&String> = recipe
+                .item_names()
+                .filter(|name| Item::from_name(name).is_none())
+                .collect();
+            if !unknown.is_empty() {
+                warn!("Leaving out a recipe with unknown items {unknown:?}");
+            }
+            unknown.is_empty()
+        });
+
+        registry
+    }
+
+    fn save(&self, path: &Path) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                error!("Failed to serialize the recipes: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = fs::write(path, contents) {
+            error!("Failed to write {}: {err}", path.display());
+        }
+    }
+
+    /// The first recipe the items in the grid match, if any.
+    fn find(&self, grid: &CraftingGrid) -> Option<&Recipe> {
+        self.recipes.iter().find(|recipe| recipe.matches(grid))
+    }
+}
+
+impl Default for RecipeRegistry {
+    fn default() -> Self {
+        let result = |item: &str, count| RecipeResult {
+            item: item.to_string(),
+            count,
+        };
+
+        // Every tool is made of its material, on a wooden handle.
+        let tools = [
+            ("wooden", "wood"),
+            ("stone", "stone"),
+            ("obsidian", "obsidian"),
+        ]
+        .into_iter()
+        .flat_map(|(tier, material)| {
+            let key = HashMap::from([('M', material.into()), ('W', "wood".into())]);
+            [
+                ("pickaxe", vec!["MMM".into(), " W ".into(), " W ".into()]),
+                ("shovel", vec!["M".into(), "W".into(), "W".into()]),
+                ("axe", vec!["MM".into(), "MW".into(), " W".into()]),
+            ]
+            .map(|(kind, pattern)| Recipe::Shaped {
+                pattern,
+                key: key.clone(),
+                result: result(&format!("{tier}_{kind}"), 1),
+            })
+        });
+
+        Self {
+            recipes: vec![
+                Recipe::Shaped {
+                    pattern: vec!["SGS".into(), "GSG".into(), "SGS".into()],
+                    key: HashMap::from([('S', "sand".into()), ('G', "gravel".into())]),
+                    result: result("tnt", 1),
+                },
+                Recipe::Shaped {
+                    pattern: vec!["SSS".into()],
+                    key: HashMap::from([('S', "snow".into())]),
+                    result: result("snow_layer", 6),
+                },
+                Recipe::Shapeless {
+                    ingredients: vec!["dirt".into(), "leaves".into()],
+                    result: result("grass", 1),
+                },
+                Recipe::Shapeless {
+                    ingredients: vec!["stone".into()],
+                    result: result("gravel", 1),
+                },
+            ]
+            .into_iter()
+            .chain(tools)
+            .collect(),
+        }
+    }
+}
+
+impl Recipe {
+    fn result(&self) -> &RecipeResult {
+        match self {
+            Recipe::Shaped { result, .. } | Recipe::Shapeless { result, .. } => result,
+        }
+    }
+
+    /// The names of every item the recipe uses or makes.
+    fn item_names(&self) -> impl Iterator<Item = &String> {
+        let ingredients: Vec<&String> = match self {
+            Recipe::Shaped { key, .. } => key.values().collect(),
+            Recipe::Shapeless { ingredients, .. } => ingredients.iter().collect(),
+        };
+
+        ingredients.into_iter().chain([&self.result().item])
+    }
+
+    /// The stack the recipe makes. Recipes in the [RecipeRegistry] only have items that exist, so this is only `None`
+    /// for recipes that were left out of it.
+    fn result_stack(&self) -> Option<ItemStack> {
+        let result = self.result();
+        Item::from_name(&result.item).map(|item| ItemStack::new(item, result.count))
+    }
+
+    fn matches(&self, grid: &CraftingGrid) -> bool {
+        let name = |slot: usize| grid.slots[slot].map(|stack| stack.item.name());
+
+        match self {
+            Recipe::Shaped { pattern, key, .. } => {
+                let Some(((left, top), (right, bottom))) = grid.bounds() else {
+                    return false;
+                };
+                let width = right - left + 1;
+                if pattern.len() != bottom - top + 1
+                    || pattern.iter().map(|row| row.chars().count()).max() != Some(width)
+                {
+                    return false;
+                }
+
+                pattern.iter().enumerate().all(|(y, row)| {
+                    (0..width).all(|x| {
+                        let ingredient = row
+                            .chars()
+                            .nth(x)
+                            .and_then(|symbol| key.get(&symbol))
+                            .map(String::as_str);
+                        name((top + y) * CRAFTING_GRID_WIDTH + left + x) == ingredient
+                    })
+                })
+            }
+            Recipe::Shapeless { ingredients, .. } => {
+                let mut placed: Vec<&str> = (0..grid.slots.len()).filter_map(name).collect();
+                let mut needed: Vec<&str> = ingredients.iter().map(String::as_str).collect();
+                placed.sort_unstable();
+                needed.sort_unstable();
+                placed == needed
+            }
+        }
+    }
+}
+
+impl CraftingGrid {
+    /// The column and row of the top left and bottom right corners of the slots that aren't empty, if any.
+    fn bounds(&self) -> Option<((usize, usize), (usize, usize))> {
+        let filled: Vec<(usize, usize)> = (0..self.slots.len())
+            .filter(|slot| self.slots[*slot].is_some())
+            .map(|slot| (slot % CRAFTING_GRID_WIDTH, slot / CRAFTING_GRID_WIDTH))
+            .collect();
+
+        let corner = |pick: fn(usize, usize) -> usize| {
+            filled
+                .iter()
+                .copied()
+                .reduce(|(x1, y1), (x2, y2)| (pick(x1, x2), pick(y1, y2)))
+        };
+        Some((corner(usize::min)?, corner(usize::max)?))
+    }
+
+    /// Uses up one item of every slot, for crafting.
+    fn consume(&mut self) {
+        for slot in &mut self.slots {
+            if let Some(stack) = slot {
+                stack.count -= 1;
+                if stack.count == 0 {
+                    *slot = None;
+                }
+            }
+        }
+    }
+}
+
+mod systems {
+    use bevy_egui::{egui, EguiContexts};
+
+    use crate::voxel::{inventory::slot_button, item_drop::DropItem};
+
+    use super::*;
+
+    /// Shows the crafting grid next to the inventory, and the result of the recipe it matches. Clicking the result
+    /// crafts it, and picks it up.
+    pub(super) fn crafting_screen(
+        mut contexts: EguiContexts,
+        recipes: Res<RecipeRegistry>,
+        mut grid: ResMut<CraftingGrid>,
+        mut inventory: ResMut<Inventory>,
+    ) {
+        egui::Window::new("Crafting")
+            .collapsible(false)
+            .resizable(false)
+            .show(contexts.ctx_mut(), |ui| {
+                ui.horizontal(|ui| {
+                    egui::Grid::new("crafting_grid").show(ui, |ui| {
+                        for (slot, stack) in grid.slots.iter_mut().enumerate() {
+                            let response = slot_button(ui, *stack);
+                            if response.clicked() {
+                                inventory.click_stack(stack, false);
+                            } else if response.secondary_clicked() {
+                                inventory.click_stack(stack, true);
+                            }
+
+                            if (slot + 1) % CRAFTING_GRID_WIDTH == 0 {
+                                ui.end_row();
+                            }
+                        }
+                    });
+
+                    ui.label("=");
+
+                    let result = recipes.find(&grid).and_then(Recipe::result_stack);
+                    if slot_button(ui, result).clicked() {
+                        if let Some(result) = result {
+                            if inventory.pick_up(result) {
+                                grid.consume();
+                            }
+                        }
+                    }
+                });
+            });
+    }
+
+    /// Puts the items left in the crafting grid back into the [Inventory], and drops the ones that don't fit in front
+    /// of the camera.
+    pub(super) fn empty_crafting_grid(
+        mut grid: ResMut<CraftingGrid>,
+        mut inventory: ResMut<Inventory>,
+        mut drops: EventWriter<DropItem>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+    ) {
+        let drop_position = camera_query.get_single().map_or(Vec3::ZERO, |transform| {
+            transform.translation + transform.forward()
+        });
+
+        for stack in grid.slots.iter_mut().filter_map(Option::take) {
+            let left = inventory.add(stack);
+            if left > 0 {
+                drops.send(DropItem {
+                    position: drop_position,
+                    stack: ItemStack {
+                        count: left,
+                        ..stack
+                    },
+                });
+            }
+        }
+    }
+}
@@ -9,7 +9,9 @@ pub(super) const DIRECT_CUBE_NEIGHBOURS: [IVec3; 6] = [
     IVec3 { x: 0, y: 0, z: 1 },
 ];
 
-pub(super) enum CubeFace {
+/// A face of a voxel, named by the direction it faces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
     Top,
     Bottom,
     Left,
@@ -60,6 +62,15 @@ impl CubeFace {
             .collect()
     }
 
+    /// The texture coordinate of a vertex of the face. Textures on the sides of a voxel are upright.
+    pub(super) fn uv(&self, vertex: Vec3) -> [f32; 2] {
+        match self {
+            CubeFace::Top | CubeFace::Bottom => [vertex.x + 0.5, vertex.z + 0.5],
+            CubeFace::Left | CubeFace::Right => [vertex.z + 0.5, 0.5 - vertex.y],
+            CubeFace::Front | CubeFace::Back => [vertex.x + 0.5, 0.5 - vertex.y],
+        }
+    }
+
     pub(super) fn vertices(&self) -> Vec<Vec3> {
         match self {
             CubeFace::Top => vec![
@@ -138,3 +149,13 @@ impl CubeCorner {
         }
     }
 }
+
+/// The ambient occlusion of a vertex of a face, from the three voxels in front of the face that touch the vertex.
+/// The two voxels on the sides of the vertex fully occlude it together, even without the corner voxel.
+pub(super) fn vertex_occlusion(side1: bool, side2: bool, corner: bool) -> f32 {
+    if side1 && side2 {
+        return 0.0;
+    }
+
+    (3 - side1 as u8 - side2 as u8 - corner as u8) as f32 / 3.0
+}
@@ -0,0 +1,252 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub use super::generation::ChunkMeshSection;
+use super::{
+    density::{fills, DensityField, SURFACE_DENSITY},
+    fluid::fluid_height,
+    generation::{LocalVoxelPosition, VoxelChunkWidth},
+    weather::snow_layer_height,
+    Voxel,
+};
+
+/// How many steps of [DensityVoxel::density] there are on either side of the surface.
+const DENSITY_STEPS: f32 = 127.0;
+
+/// What chunks need to know about the voxels they hold, to store, mesh and light them.
+///
+/// The chunks of the engine hold [Voxel]s, but chunks are generic over this, so games with richer per-voxel data can
+/// store their own voxel type, and mesh it with a [VoxelChunkRenderingPlugin](super::render::VoxelChunkRenderingPlugin)
+/// of that type. The simulation, like fluids and fire, is specific to [Voxel].
+pub trait VoxelData: Copy + PartialEq + Send + Sync + 'static {
+    /// Solid voxels can be stood on and targeted.
+    fn is_solid(&self) -> bool;
+
+    /// Opaque voxels hide the faces of the voxels next to them. Solid voxels are opaque by default.
+    fn is_opaque(&self) -> bool {
+        self.is_solid()
+    }
+
+    /// Which part of the chunk mesh the voxel is drawn in. [None] means the voxel isn't drawn at all.
+    fn mesh_section(&self) -> Option<ChunkMeshSection>;
+
+    /// The color of the face of the voxel pointing in the `normal` direction.
+    fn face_color(&self, normal: IVec3) -> Color;
+
+    /// The layer of the chunk array texture the face of the voxel pointing in the `normal` direction is drawn with,
+    /// multiplied with its [face_color](Self::face_color). [None] draws the face with only its color.
+    fn texture_layer(&self, _normal: IVec3) -> Option<u32> {
+        None
+    }
+
+    /// How high the top of the voxel is drawn, from 0.0 to 1.0, given the voxel above it if it's loaded. Lowered tops
+    /// are never hidden by the voxel above them.
+    fn top_height(&self, _above: Option<Self>) -> f32 {
+        1.0
+    }
+
+    /// Whether the face between two voxels is hidden, because they're the same kind of voxel, like two water voxels
+    /// of different levels.
+    fn is_same_kind(&self, other: &Self) -> bool {
+        self == other
+    }
+
+    /// How much light the voxel emits, from 0 to 15.
+    fn light_emission(&self) -> u8 {
+        0
+    }
+
+    /// How filled the voxel is for smooth terrain, from 0.0 (empty) to 1.0 (filled), with the surface at 0.5. [None]
+    /// derives it from the voxels around it, see [DensityField](super::density::DensityField).
+    fn density(&self) -> Option<f32> {
+        None
+    }
+}
+
+impl VoxelData for Voxel {
+    fn is_solid(&self) -> bool {
+        self.definition().solid
+    }
+
+    /// Cutout voxels, like leaves, can be seen through, so they don't hide the faces next to them. Neither do micro
+    /// blocks, which are only partly filled.
+    fn is_opaque(&self) -> bool {
+        self.is_solid() && !matches!(self.mesh_section(), Some(ChunkMeshSection::Cutout) | None)
+    }
+
+    fn mesh_section(&self) -> Option<ChunkMeshSection> {
+        self.definition().mesh_section
+    }
+
+    fn face_color(&self, _normal: IVec3) -> Color {
+        self.color()
+    }
+
+    /// The surface of fluids is lowered based on their level, see [fluid_height], and the surface of snow layers
+    /// based on how many layers they have, see [snow_layer_height].
+    fn top_height(&self, above: Option<Self>) -> f32 {
+        if self.is_fluid() {
+            fluid_height(self.state, above.is_some_and(|above| above.id == self.id))
+        } else if self.id == Voxel::SNOW_LAYER.id {
+            snow_layer_height(self.state)
+        } else {
+            1.0
+        }
+    }
+
+    fn is_same_kind(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+
+    fn light_emission(&self) -> u8 {
+        self.definition().light_emission
+    }
+}
+
+/// A voxel stored as a density and a material, for smooth terrain that can be sculpted finer than whole voxels.
+///
+/// Chunks store these next to their [Voxel]s with [VoxelConfig::density_chunks](super::VoxelConfig::density_chunks),
+/// for the smooth meshers to draw. A voxel is filled when its density is above the surface, and then drawn as its
+/// material. An empty voxel keeps its material if that doesn't fill smooth terrain, like water, and is air otherwise.
+/// The state of the material, like the level of a fluid, isn't kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DensityVoxel {
+    /// From -127 (empty) to 127 (filled), with the surface at 0.
+    density: i8,
+    /// The id of the [Voxel] the voxel is made of.
+    material: u16,
+}
+
+impl DensityVoxel {
+    pub const EMPTY: Self = Self {
+        density: -(DENSITY_STEPS as i8),
+        material: 0,
+    };
+
+    /// A voxel of the given material, and density from 0.0 to 1.0.
+    pub fn new(density: f32, material: Voxel) -> Self {
+        Self {
+            density: quantize_density(density),
+            material: material.id(),
+        }
+    }
+
+    /// The voxel closest to `voxel`: fully filled if the voxel fills smooth terrain, and fully empty otherwise.
+    pub fn from_voxel(voxel: Voxel) -> Self {
+        Self::new(if fills(&voxel) { 1.0 } else { 0.0 }, voxel)
+    }
+
+    /// Converts all the voxels of a chunk `chunk_width` voxels wide, in the order the chunk holds them. Their densities
+    /// are derived like those of a [DensityField], from the voxels around them, so smooth terrain looks the same either
+    /// way until it's sculpted. Voxels on the border of the chunk only look at the voxels inside it, since the
+    /// neighbouring chunks might not be loaded.
+    pub fn from_chunk_voxels(voxels: &[Voxel], chunk_width: u8) -> Vec<Self> {
+        let chunk_width = VoxelChunkWidth(chunk_width);
+        let max = IVec3::splat(chunk_width.0 as i32 - 1);
+        let voxel_at = |position: IVec3| {
+            let position = LocalVoxelPosition::from_ivec3(position.clamp(IVec3::ZERO, max));
+            voxels.get(position.to_index(&chunk_width)).copied()
+        };
+        let field = DensityField::new(chunk_width.0 as i32, voxel_at);
+
+        voxels
+            .iter()
+            .enumerate()
+            .map(|(i, voxel)| {
+                let position = LocalVoxelPosition::from_index(i, &chunk_width).as_ivec3();
+                Self::from_voxel(*voxel).with_density(field.density(position))
+            })
+            .collect()
+    }
+
+    /// The [Voxel] this voxel is drawn and simulated as.
+    pub fn to_voxel(&self) -> Voxel {
+        let material = self.material();
+        if self.is_filled() || !fills(&material) {
+            material
+        } else {
+            Voxel::AIR
+        }
+    }
+
+    /// How filled the voxel is, from 0.0 to 1.0.
+    pub fn density(&self) -> f32 {
+        SURFACE_DENSITY + self.density as f32 / DENSITY_STEPS * SURFACE_DENSITY
+    }
+
+    pub fn material(&self) -> Voxel {
+        Voxel::new(self.material)
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.density > 0
+    }
+
+    /// Changes the density of the voxel, but keeps it on the same side of the surface. This smooths the surface
+    /// without changing which voxels are filled.
+    pub fn with_density(self, density: f32) -> Self {
+        let density = quantize_density(density);
+        Self {
+            density: if self.is_filled() {
+                density.max(1)
+            } else {
+                density.min(0)
+            },
+            ..self
+        }
+    }
+}
+
+fn quantize_density(density: f32) -> i8 {
+    ((density - SURFACE_DENSITY) / SURFACE_DENSITY * DENSITY_STEPS)
+        .round()
+        .clamp(-DENSITY_STEPS, DENSITY_STEPS) as i8
+}
+
+impl From<Voxel> for DensityVoxel {
+    fn from(voxel: Voxel) -> Self {
+        Self::from_voxel(voxel)
+    }
+}
+
+impl From<DensityVoxel> for Voxel {
+    fn from(voxel: DensityVoxel) -> Self {
+        voxel.to_voxel()
+    }
+}
+
+/// Density voxels are drawn and lit as the [Voxel] they stand for, see [DensityVoxel::to_voxel].
+impl VoxelData for DensityVoxel {
+    fn is_solid(&self) -> bool {
+        self.to_voxel().is_solid()
+    }
+
+    fn is_opaque(&self) -> bool {
+        VoxelData::is_opaque(&self.to_voxel())
+    }
+
+    fn mesh_section(&self) -> Option<ChunkMeshSection> {
+        self.to_voxel().mesh_section()
+    }
+
+    fn face_color(&self, normal: IVec3) -> Color {
+        self.to_voxel().face_color(normal)
+    }
+
+    fn top_height(&self, above: Option<Self>) -> f32 {
+        self.to_voxel()
+            .top_height(above.map(|above| above.to_voxel()))
+    }
+
+    fn is_same_kind(&self, other: &Self) -> bool {
+        self.to_voxel().is_same_kind(&other.to_voxel())
+    }
+
+    fn light_emission(&self) -> u8 {
+        self.to_voxel().light_emission()
+    }
+
+    fn density(&self) -> Option<f32> {
+        Some(DensityVoxel::density(self))
+    }
+}
@@ -0,0 +1,252 @@
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+
+use super::{
+    chunk_material::{ATTRIBUTE_OCCLUSION, ATTRIBUTE_TEXTURE_LAYER, NO_TEXTURE_LAYER},
+    data::VoxelData,
+    generation::ChunkMeshSection,
+    world::box_positions,
+};
+
+/// The density at which the surface of smooth terrain lies, between empty (0.0) and filled (1.0).
+pub(super) const SURFACE_DENSITY: f32 = 0.5;
+/// How close the surface can get to a corner of a cell, as a fraction of the edge. Keeps the triangles of thin walls
+/// from collapsing.
+const MIN_EDGE_FRACTION: f32 = 0.1;
+/// How many voxels around the chunk the densities are known for. Smooth meshers look at the cells just before the
+/// chunk, and the normals of their corners need the densities around those.
+const DENSITY_MARGIN: i32 = 2;
+/// How many voxels around the chunk are looked up. Every density needs the voxels around it.
+const FILLED_MARGIN: i32 = DENSITY_MARGIN + 1;
+
+/// The corners of a cell of a smooth mesher, which runs between the centers of 8 voxels. Corner `i` is offset by bit 0
+/// of `i` on x, bit 1 on y and bit 2 on z.
+pub(super) const CELL_CORNERS: [IVec3; 8] = [
+    IVec3::new(0, 0, 0),
+    IVec3::new(1, 0, 0),
+    IVec3::new(0, 1, 0),
+    IVec3::new(1, 1, 0),
+    IVec3::new(0, 0, 1),
+    IVec3::new(1, 0, 1),
+    IVec3::new(0, 1, 1),
+    IVec3::new(1, 1, 1),
+];
+
+/// The edges of a cell, by the [CELL_CORNERS] they connect. First the edges along x, then y, then z.
+pub(super) const CELL_EDGES: [(usize, usize); 12] = [
+    (0, 1),
+    (2, 3),
+    (4, 5),
+    (6, 7),
+    (0, 2),
+    (1, 3),
+    (4, 6),
+    (5, 7),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+/// The densities of smooth terrain, taken from the [density](VoxelData::density) of the voxels, or derived from which
+/// voxels are filled for voxels that don't have one.
+///
+/// A derived density is the share of filled voxels in the 3x3x3 voxels around it, so the surface is rounded off
+/// wherever the voxels around it step up or down. It only moves the surface though; whether the surface goes in
+/// front of or behind a voxel still only depends on whether that voxel is filled, so smooth terrain never leaves out
+/// voxels or adds voxels that aren't there.
+pub(super) struct DensityField {
+    width: i32,
+    /// Whether the voxels of the chunk and the [FILLED_MARGIN] around it are filled.
+    filled: Vec<bool>,
+    /// The densities of the voxels of the chunk and the [DENSITY_MARGIN] around it.
+    densities: Vec<f32>,
+}
+
+impl DensityField {
+    /// Builds the field of a chunk `width` voxels wide. `voxel_at` is called once for every voxel of the chunk and the
+    /// few voxels around it on every side, with its position relative to the chunk. Voxels that aren't loaded are
+    /// empty.
+    pub(super) fn new<V: VoxelData>(width: i32, voxel_at: impl Fn(IVec3) -> Option<V>) -> Self {
+        Self::with_densities(width, voxel_at, |_| None)
+    }
+
+    /// Builds the field like [DensityField::new], but takes the densities `density_at` returns over those of the
+    /// voxels, for chunks that store their densities next to their voxels.
+    pub(super) fn with_densities<V: VoxelData>(
+        width: i32,
+        voxel_at: impl Fn(IVec3) -> Option<V>,
+        density_at: impl Fn(IVec3) -> Option<f32>,
+    ) -> Self {
+        let filled_width = width + 2 * FILLED_MARGIN;
+        let (filled, stored_densities): (Vec<bool>, Vec<Option<f32>>) = (0..filled_width.pow(3))
+            .map(|i| {
+                let position = grid_position(i, filled_width) - FILLED_MARGIN;
+                let voxel = voxel_at(position);
+                (
+                    voxel.is_some_and(|voxel| fills(&voxel)),
+                    density_at(position).or_else(|| voxel.and_then(|voxel| voxel.density())),
+                )
+            })
+            .unzip();
+
+        let mut field = Self {
+            width,
+            filled,
+            densities: Vec::new(),
+        };
+
+        let density_width = width + 2 * DENSITY_MARGIN;
+        field.densities = (0..density_width.pow(3))
+            .map(|i| {
+                let position = grid_position(i, density_width) - DENSITY_MARGIN;
+                let stored = stored_densities[grid_index(position + FILLED_MARGIN, filled_width)];
+
+                stored.unwrap_or_else(|| {
+                    let filled_around = box_positions(position - 1, position + 1)
+                        .filter(|neighbour| field.is_filled(*neighbour))
+                        .count();
+                    filled_around as f32 / 27.0
+                })
+            })
+            .collect();
+
+        field
+    }
+
+    pub(super) fn width(&self) -> i32 {
+        self.width
+    }
+
+    /// Whether the voxel at a position relative to the chunk is filled. Works up to [FILLED_MARGIN] voxels outside of
+    /// the chunk.
+    pub(super) fn is_filled(&self, position: IVec3) -> bool {
+        self.filled[grid_index(position + FILLED_MARGIN, self.width + 2 * FILLED_MARGIN)]
+    }
+
+    /// The density of the voxel at a position relative to the chunk. Works up to [DENSITY_MARGIN] voxels outside of
+    /// the chunk.
+    pub(super) fn density(&self, position: IVec3) -> f32 {
+        self.densities[grid_index(position + DENSITY_MARGIN, self.width + 2 * DENSITY_MARGIN)]
+    }
+
+    /// The direction the density falls off in the most at a voxel, which is the normal of a surface through it.
+    fn normal(&self, position: IVec3) -> Vec3 {
+        let slope = |axis: IVec3| self.density(position - axis) - self.density(position + axis);
+        Vec3::new(slope(IVec3::X), slope(IVec3::Y), slope(IVec3::Z)).normalize_or_zero()
+    }
+
+    /// Where the surface crosses the edge from the voxel at `filled` to the voxel at `empty` next to it, and the normal
+    /// of the surface there.
+    pub(super) fn edge_crossing(&self, filled: IVec3, empty: IVec3) -> (Vec3, Vec3) {
+        let (filled_density, empty_density) = (self.density(filled), self.density(empty));
+        let fraction = if filled_density > empty_density {
+            ((filled_density - SURFACE_DENSITY) / (filled_density - empty_density))
+                .clamp(MIN_EDGE_FRACTION, 1.0 - MIN_EDGE_FRACTION)
+        } else {
+            0.5
+        };
+
+        let position = filled.as_vec3().lerp(empty.as_vec3(), fraction);
+        let normal = self
+            .normal(filled)
+            .lerp(self.normal(empty), fraction)
+            .try_normalize()
+            .unwrap_or((empty - filled).as_vec3());
+        (position, normal)
+    }
+
+    /// Which [CELL_CORNERS] of the cell with its first corner at `position` are filled, as bit `i` for corner `i`.
+    pub(super) fn cell_case(&self, position: IVec3) -> usize {
+        CELL_CORNERS
+            .iter()
+            .enumerate()
+            .filter(|(_, corner)| self.is_filled(position + **corner))
+            .fold(0, |case, (i, _)| case | 1 << i)
+    }
+
+    /// The edges of the cell with its first corner at `position` that the surface crosses, as the voxel at their filled
+    /// end and the voxel at their empty end.
+    pub(super) fn crossed_edges(
+        &self,
+        position: IVec3,
+    ) -> impl Iterator<Item = (IVec3, IVec3)> + '_ {
+        CELL_EDGES.iter().filter_map(move |(start, end)| {
+            let (start, end) = (
+                position + CELL_CORNERS[*start],
+                position + CELL_CORNERS[*end],
+            );
+            match (self.is_filled(start), self.is_filled(end)) {
+                (true, false) => Some((start, end)),
+                (false, true) => Some((end, start)),
+                _ => None,
+            }
+        })
+    }
+}
+
+/// Whether a voxel fills smooth terrain. Only voxels drawn in the opaque section do; every other voxel is still drawn
+/// as a cube.
+pub(super) fn fills<V: VoxelData>(voxel: &V) -> bool {
+    voxel.mesh_section() == Some(ChunkMeshSection::Opaque)
+}
+
+fn grid_position(index: i32, width: i32) -> IVec3 {
+    IVec3::new(
+        index % width,
+        index / width % width,
+        index / (width * width),
+    )
+}
+
+fn grid_index(position: IVec3, width: i32) -> usize {
+    (position.x + position.y * width + position.z * width * width) as usize
+}
+
+/// The vertices and triangles of smooth terrain, as built by a smooth mesher. Positions are relative to the chunk, in
+/// voxels of the [DensityField].
+#[derive(Default)]
+pub(super) struct SmoothSurface {
+    pub(super) positions: Vec<Vec3>,
+    pub(super) normals: Vec<Vec3>,
+    pub(super) colors: Vec<[f32; 4]>,
+    pub(super) indices: Vec<u32>,
+}
+
+impl SmoothSurface {
+    /// Adds a vertex, returning its index.
+    pub(super) fn push_vertex(&mut self, position: Vec3, normal: Vec3, color: Color) -> u32 {
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.colors.push(color.as_linear_rgba_f32());
+        self.positions.len() as u32 - 1
+    }
+
+    /// Turns the surface into a chunk mesh, with its vertices scaled up by `scale` like those of
+    /// [VoxelChunk::generate_lod_mesh](super::generation::VoxelChunk::generate_lod_mesh).
+    ///
+    /// Smooth terrain is drawn with the voxel colors only, since its slopes don't fit voxel textures. It always has full
+    /// vertices, since packed vertices can only be on the corners of voxels.
+    pub(super) fn into_mesh(self, scale: u8) -> Mesh {
+        let vertex_count = self.positions.len();
+        let positions: Vec<Vec3> = self
+            .positions
+            .into_iter()
+            .map(|position| (position + 0.5) * scale as f32 - 0.5)
+            .collect();
+
+        Mesh::new(PrimitiveTopology::TriangleList)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![Vec2::ZERO; vertex_count])
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+            .with_inserted_attribute(ATTRIBUTE_OCCLUSION, vec![1.0; vertex_count])
+            .with_inserted_attribute(
+                ATTRIBUTE_TEXTURE_LAYER,
+                vec![NO_TEXTURE_LAYER; vertex_count],
+            )
+            .with_indices(Some(Indices::U32(self.indices)))
+    }
+}
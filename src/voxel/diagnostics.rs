@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+
+/// This plugin registers [Diagnostic]s for the voxel chunk pipeline, so they show up in
+/// `LogDiagnosticsPlugin` and other diagnostics UIs.
+pub(super) struct VoxelDiagnosticsPlugin;
+
+impl Plugin for VoxelDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VoxelPipelineStats>()
+            .register_diagnostic(Diagnostic::new(
+                Self::CHUNKS_GENERATED_PER_SEC,
+                "voxel/chunks_generated_per_sec",
+                20,
+            ))
+            .register_diagnostic(Diagnostic::new(
+                Self::MESHES_BUILT_PER_SEC,
+                "voxel/meshes_built_per_sec",
+                20,
+            ))
+            .register_diagnostic(
+                Diagnostic::new(Self::AVERAGE_MESH_TIME, "voxel/avg_mesh_time", 20)
+                    .with_suffix("ms"),
+            )
+            .register_diagnostic(Diagnostic::new(
+                Self::LOAD_QUEUE_LEN,
+                "voxel/load_queue_len",
+                1,
+            ))
+            .register_diagnostic(Diagnostic::new(
+                Self::UNLOAD_QUEUE_LEN,
+                "voxel/unload_queue_len",
+                1,
+            ))
+            .register_diagnostic(Diagnostic::new(
+                Self::RENDER_QUEUE_LEN,
+                "voxel/render_queue_len",
+                1,
+            ))
+            .register_diagnostic(
+                Diagnostic::new(Self::CHUNK_MEMORY, "voxel/chunk_memory", 1).with_suffix("MiB"),
+            )
+            .add_systems(Last, systems::voxel_diagnostics);
+    }
+}
+
+impl VoxelDiagnosticsPlugin {
+    pub(super) const CHUNKS_GENERATED_PER_SEC: DiagnosticId =
+        DiagnosticId::from_u128(138688938106362965851352986211783196214);
+    pub(super) const MESHES_BUILT_PER_SEC: DiagnosticId =
+        DiagnosticId::from_u128(844817361250067371191288069024620680);
+    pub(super) const AVERAGE_MESH_TIME: DiagnosticId =
+        DiagnosticId::from_u128(237305561021039438502944312798992105090);
+    pub(super) const LOAD_QUEUE_LEN: DiagnosticId =
+        DiagnosticId::from_u128(181857614755288725955759954100937704957);
+    pub(super) const UNLOAD_QUEUE_LEN: DiagnosticId =
+        DiagnosticId::from_u128(284947432875293826630487276589019179062);
+    pub(super) const RENDER_QUEUE_LEN: DiagnosticId =
+        DiagnosticId::from_u128(204405702757380699017863558043276750726);
+    pub(super) const CHUNK_MEMORY: DiagnosticId =
+        DiagnosticId::from_u128(96183034276581829017442735218846915723);
+}
+
+/// Counters filled in by the chunk pipeline systems during a frame. These are turned into
+/// [Diagnostic] measurements and reset at the end of every frame.
+#[derive(Resource, Default, Debug)]
+pub(super) struct VoxelPipelineStats {
+    pub(super) chunks_generated: u32,
+    pub(super) meshes_built: u32,
+    /// Total time spent building meshes this frame.
+    pub(super) mesh_time: Duration,
+}
+
+mod systems {
+    use crate::voxel::{generation::VoxelChunk, load::ChunkLoadQueue, render::ChunkRenderQueue};
+
+    use super::*;
+
+    pub(super) fn voxel_diagnostics(
+        mut diagnostics: Diagnostics,
+        mut stats: ResMut<VoxelPipelineStats>,
+        chunk_load_queue: Option<Res<ChunkLoadQueue>>,
+        chunk_render_queue: Option<Res<ChunkRenderQueue>>,
+        chunk_query: Query<&VoxelChunk>,
+        time: Res<Time<Real>>,
+    ) {
+        let delta_seconds = time.delta_seconds_f64();
+
+        if delta_seconds > 0.0 {
+            diagnostics.add_measurement(VoxelDiagnosticsPlugin::CHUNKS_GENERATED_PER_SEC, || {
+                stats.chunks_generated as f64 / delta_seconds
+            });
+            diagnostics.add_measurement(VoxelDiagnosticsPlugin::MESHES_BUILT_PER_SEC, || {
+                stats.meshes_built as f64 / delta_seconds
+            });
+        }
+
+        // Only measure the mesh time when meshes were actually built, so idle frames don't drag the average down.
+        if stats.meshes_built > 0 {
+            diagnostics.add_measurement(VoxelDiagnosticsPlugin::AVERAGE_MESH_TIME, || {
+                stats.mesh_time.as_secs_f64() * 1000.0 / stats.meshes_built as f64
+            });
+        }
+
+        // Without rendering there is no render queue, and clients of a server don't load chunks themselves.
+        if let Some(chunk_load_queue) = chunk_load_queue {
+            diagnostics.add_measurement(VoxelDiagnosticsPlugin::LOAD_QUEUE_LEN, || {
+                chunk_load_queue.load_len() as f64
+            });
+            diagnostics.add_measurement(VoxelDiagnosticsPlugin::UNLOAD_QUEUE_LEN, || {
+                chunk_load_queue.unload_len() as f64
+            });
+        }
+        if let Some(chunk_render_queue) = chunk_render_queue {
+            diagnostics.add_measurement(VoxelDiagnosticsPlugin::RENDER_QUEUE_LEN, || {
+                chunk_render_queue.len() as f64
+            });
+        }
+
+        diagnostics.add_measurement(VoxelDiagnosticsPlugin::CHUNK_MEMORY, || {
+            let bytes: usize = chunk_query.iter().map(VoxelChunk::voxel_memory).sum();
+            bytes as f64 / (1024.0 * 1024.0)
+        });
+
+        *stats = VoxelPipelineStats::default();
+    }
+}
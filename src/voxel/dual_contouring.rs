@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+
+use super::{
+    density::{DensityField, SmoothSurface},
+    world::box_positions,
+};
+
+/// How strongly the vertex of a cell is pulled towards the average of the crossings on its edges. Without it, the
+/// vertex of a flat or evenly curved surface could end up anywhere along it.
+const MASS_POINT_WEIGHT: f32 = 0.05;
+/// The other two axes of every axis, in the order that makes a right-handed frame with it.
+const SIDE_AXES: [(IVec3, IVec3); 3] = [
+    (IVec3::Y, IVec3::Z),
+    (IVec3::Z, IVec3::X),
+    (IVec3::X, IVec3::Y),
+];
+
+/// Meshes the surface of a [DensityField] with dual contouring. Every cell the surface goes through gets one vertex,
+/// where the planes through the crossings on its edges meet best, which lands on ridges and corners instead of cutting
+/// them off. The vertices of the 4 cells around every crossed edge are joined into a quad.
+///
+/// Every chunk joins the edges starting in it, which needs the vertices of the cells just before it too, so the surface
+/// runs on into the next chunk without gaps. `face_color` gives the color of the face of the filled voxel at a position,
+/// pointing in a direction.
+pub(super) fn dual_contouring(
+    field: &DensityField,
+    face_color: impl Fn(IVec3, IVec3) -> Color,
+) -> SmoothSurface {
+    let width = field.width();
+    let mut surface = SmoothSurface::default();
+
+    // The vertices of the cells from 1 before the chunk to the end of it.
+    let cells_width = width + 1;
+    let cell_index = |cell: IVec3| {
+        let cell = cell + 1;
+        (cell.x + cell.y * cells_width + cell.z * cells_width * cells_width) as usize
+    };
+    let mut cell_vertices = vec![None; cells_width.pow(3) as usize];
+    for cell in box_positions(IVec3::splat(-1), IVec3::splat(width - 1)) {
+        cell_vertices[cell_index(cell)] = cell_vertex(field, cell, &face_color)
+            .map(|(position, normal, color)| surface.push_vertex(position, normal, color));
+    }
+
+    for start in box_positions(IVec3::ZERO, IVec3::splat(width - 1)) {
+        for (axis, (side1, side2)) in [IVec3::X, IVec3::Y, IVec3::Z].into_iter().zip(SIDE_AXES) {
+            let start_filled = field.is_filled(start);
+            if start_filled == field.is_filled(start + axis) {
+                continue;
+            }
+
+            // Counter-clockwise around the edge, seen from where it points to.
+            let quad = [start - side1 - side2, start - side2, start, start - side1]
+                .map(|cell| cell_vertices[cell_index(cell)]);
+            let Some(mut quad) = quad.into_iter().collect::<Option<Vec<u32>>>() else {
+                continue;
+            };
+
+            // Faces point away from the filled end of the edge.
+            if !start_filled {
+                quad.reverse();
+            }
+            surface
+                .indices
+                .extend([quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+        }
+    }
+
+    surface
+}
+
+/// The vertex of the cell with its first corner at `cell`, and its normal and color, or [None] if the surface doesn't
+/// go through the cell.
+///
+/// The vertex is where the squared distances to the planes through the crossings are smallest, pulled slightly towards
+/// their average. It's kept inside the cell, so the surface never folds over itself.
+fn cell_vertex(
+    field: &DensityField,
+    cell: IVec3,
+    face_color: impl Fn(IVec3, IVec3) -> Color,
+) -> Option<(Vec3, Vec3, Color)> {
+    let crossings: Vec<(IVec3, IVec3, Vec3, Vec3)> = field
+        .crossed_edges(cell)
+        .map(|(filled, empty)| {
+            let (position, normal) = field.edge_crossing(filled, empty);
+            (filled, empty, position, normal)
+        })
+        .collect();
+    if crossings.is_empty() {
+        return None;
+    }
+
+    let mass_point = crossings
+        .iter()
+        .map(|(_, _, position, _)| *position)
+        .sum::<Vec3>()
+        / crossings.len() as f32;
+
+    // Solves for the offset from the mass point, which keeps the numbers small.
+    let mut normal_matrix = Mat3::from_diagonal(Vec3::splat(MASS_POINT_WEIGHT));
+    let mut target = Vec3::ZERO;
+    for (_, _, position, normal) in &crossings {
+        normal_matrix +=
+            Mat3::from_cols(*normal * normal.x, *normal * normal.y, *normal * normal.z);
+        target += *normal * normal.dot(*position - mass_point);
+    }
+    let position =
+        (mass_point + normal_matrix.inverse() * target).clamp(cell.as_vec3(), (cell + 1).as_vec3());
+
+    let normal = crossings
+        .iter()
+        .map(|(_, _, _, normal)| *normal)
+        .sum::<Vec3>()
+        .normalize_or_zero();
+
+    // The color of the voxel the surface faces away from most directly.
+    let (filled, empty, _, _) = crossings
+        .iter()
+        .max_by(|a, b| {
+            let facing = |(filled, empty, _, _): &&(IVec3, IVec3, Vec3, Vec3)| {
+                (*empty - *filled).as_vec3().dot(normal)
+            };
+            facing(a).total_cmp(&facing(b))
+        })
+        .expect("the surface goes through the cell");
+
+    Some((position, normal, face_color(*filled, *empty - *filled)))
+}
@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Voxel;
+
+/// This plugin applies [VoxelEdit]s to the world. It's part of the simulation, so when playing on a server,
+/// the edits of every player end up here on the server.
+pub(super) struct VoxelEditPlugin;
+
+impl Plugin for VoxelEditPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProtectedRegions>()
+            .add_event::<VoxelEdit>()
+            .add_event::<AppliedVoxelEdit>()
+            .add_event::<VoxelChanged>()
+            .add_systems(Update, systems::apply_voxel_edits);
+    }
+}
+
+/// Event sent when a player wants to change a voxel, like by breaking or placing it.
+///
+/// This is only a request. Where the world is simulated, it's applied by the [VoxelEditPlugin].
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(super) struct VoxelEdit {
+    /// The world voxel position of the voxel to change.
+    pub(super) voxel_pos: IVec3,
+    pub(super) voxel: Voxel,
+}
+
+/// Event sent once a [VoxelEdit] has actually changed the world. Edits of voxels in unloaded chunks are dropped,
+/// and don't get one.
+///
+/// On a client connected to a server, this is sent for the edits the server applied in the chunks the client has.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub(super) struct AppliedVoxelEdit(pub(super) VoxelEdit);
+
+/// Event sent wherever the world is shown once an edit has changed a voxel, with what the voxel was before. On a
+/// client connected to a server, it's sent once the client has applied the edit the server sent.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub(super) struct VoxelChanged {
+    /// The world voxel position of the changed voxel.
+    pub(super) voxel_pos: IVec3,
+    pub(super) previous: Voxel,
+    pub(super) voxel: Voxel,
+}
+
+/// Regions of the world that players connected to a server can't edit, like the area around the spawn. The host can
+/// still edit them.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub(super) struct ProtectedRegions(pub(super) Vec<ProtectedRegion>);
+
+impl ProtectedRegions {
+    /// Whether any of the regions contains the world voxel position.
+    pub(super) fn contains(&self, voxel_pos: IVec3) -> bool {
+        self.0.iter().any(|region| region.contains(voxel_pos))
+    }
+}
+
+/// A box of world voxel positions, including both corners.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct ProtectedRegion {
+    pub(super) min: IVec3,
+    pub(super) max: IVec3,
+}
+
+impl ProtectedRegion {
+    /// The region between two opposite corners, in any order.
+    pub(super) fn new(a: IVec3, b: IVec3) -> Self {
+        Self {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    pub(super) fn contains(&self, voxel_pos: IVec3) -> bool {
+        voxel_pos.cmpge(self.min).all() && voxel_pos.cmple(self.max).all()
+    }
+}
+
+mod systems {
+    use crate::voxel::{tick::BlockTickScheduler, world::VoxelWorld};
+
+    use super::*;
+
+    /// Sets the edited voxels, and lets the voxels around them react. Indestructible voxels stay as they are.
+    pub(super) fn apply_voxel_edits(
+        mut edits: EventReader<VoxelEdit>,
+        mut applied_edits: EventWriter<AppliedVoxelEdit>,
+        mut changes: EventWriter<VoxelChanged>,
+        mut voxel_world: VoxelWorld,
+        mut scheduler: ResMut<BlockTickScheduler>,
+    ) {
+        for edit in edits.read() {
+            let Some(previous) = voxel_world.get_block(edit.voxel_pos) else {
+                continue;
+            };
+            if previous.is_indestructible() {
+                continue;
+            }
+
+            if voxel_world.set_block(edit.voxel_pos, edit.voxel) {
+                scheduler.schedule(edit.voxel_pos, 1);
+                scheduler.schedule_neighbours(edit.voxel_pos, 1);
+                applied_edits.send(AppliedVoxelEdit(*edit));
+                changes.send(VoxelChanged {
+                    voxel_pos: edit.voxel_pos,
+                    previous,
+                    voxel: edit.voxel,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use super::tick::BlockTickSet;
+
+/// How powerful the explosion of a [Voxel::TNT](super::Voxel::TNT) is.
+const TNT_POWER: f32 = 4.0;
+/// How many game ticks it takes TNT to explode after being lit by fire or lava.
+const TNT_FUSE: u64 = 40;
+/// TNT caught in another explosion explodes a random amount of game ticks in this range later.
+const TNT_CHAIN_FUSE: std::ops::RangeInclusive<u64> = 5..=15;
+/// How much the radius of the destroyed sphere varies from voxel to voxel, as a fraction of the power.
+const EXPLOSION_NOISE: f32 = 0.3;
+/// Entities within this many times the power of an explosion are knocked back.
+const KNOCKBACK_RANGE: f32 = 2.0;
+const KNOCKBACK_STRENGTH: f32 = 10.0;
+
+/// This plugin is responsible for explosions. Explosions remove the voxels in a noisy sphere around them,
+/// and knock back entities with a [Velocity](super::physics::Velocity).
+///
+/// Explosions are caused through the [Explosions] system param. TNT explodes when it's lit by fire or lava.
+/// The particles are up to the [VoxelExplosionEffectsPlugin](super::explosion_effects::VoxelExplosionEffectsPlugin), and
+/// the sound to the [VoxelAudioPlugin](super::audio::VoxelAudioPlugin).
+pub(super) struct VoxelExplosionPlugin;
+
+impl Plugin for VoxelExplosionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Explosion>().add_systems(
+            FixedUpdate,
+            (systems::tick_tnt, systems::handle_explosions)
+                .chain()
+                .after(BlockTickSet),
+        );
+    }
+}
+
+/// Event sent when something explodes. Use [Explosions::explode] to send these.
+#[derive(Event, Debug, Clone, Copy)]
+pub(super) struct Explosion {
+    pub(super) center: Vec3,
+    /// The radius of the explosion, in voxels.
+    pub(super) power: f32,
+}
+
+/// System param for causing explosions.
+#[derive(SystemParam)]
+pub(super) struct Explosions<'w> {
+    explosions: EventWriter<'w, Explosion>,
+}
+
+impl Explosions<'_> {
+    /// Makes an explosion at `center`, destroying the voxels about `power` voxels around it.
+    pub(super) fn explode(&mut self, center: Vec3, power: f32) {
+        self.explosions.send(Explosion { center, power });
+    }
+}
+
+mod systems {
+    use rand::Rng;
+
+    use crate::voxel::{
+        cube_mesh::DIRECT_CUBE_NEIGHBOURS,
+        physics::Velocity,
+        tick::{BlockTick, BlockTickKind, BlockTickScheduler},
+        world::VoxelWorld,
+        Voxel,
+    };
+
+    use super::*;
+
+    /// Lights TNT next to fire or lava, and explodes lit TNT. The state of TNT is 1 while it's lit.
+    pub(super) fn tick_tnt(
+        mut block_ticks: EventReader<BlockTick>,
+        mut voxel_world: VoxelWorld,
+        mut scheduler: ResMut<BlockTickScheduler>,
+        mut explosions: Explosions,
+    ) {
+        for block_tick in block_ticks.read() {
+            if block_tick.kind != BlockTickKind::Scheduled {
+                continue;
+            }
+
+            let voxel_pos = block_tick.voxel_pos;
+            let Some(voxel) = voxel_world.get_block(voxel_pos) else {
+                continue;
+            };
+            if voxel.id != Voxel::TNT.id {
+                continue;
+            }
+
+            if voxel.state == 1 {
+                voxel_world.set_block(voxel_pos, Voxel::AIR);
+                explosions.explode(voxel_pos.as_vec3(), TNT_POWER);
+                continue;
+            }
+
+            let lit = DIRECT_CUBE_NEIGHBOURS.into_iter().any(|neighbour| {
+                voxel_world
+                    .get_block(voxel_pos + neighbour)
+                    .is_some_and(|voxel| voxel.id == Voxel::FIRE.id || voxel.id == Voxel::LAVA.id)
+            });
+
+            if lit {
+                voxel_world.set_block(voxel_pos, Voxel::TNT.with_state(1));
+                scheduler.schedule(voxel_pos, TNT_FUSE);
+            }
+        }
+    }
+
+    pub(super) fn handle_explosions(
+        mut explosions: EventReader<Explosion>,
+        mut voxel_world: VoxelWorld,
+        mut scheduler: ResMut<BlockTickScheduler>,
+        mut velocity_query: Query<(&Transform, &mut Velocity)>,
+    ) {
+        let mut rng = rand::thread_rng();
+
+        for explosion in explosions.read() {
+            let _span = info_span!("explosion", center = ?explosion.center).entered();
+
+            let center_voxel = explosion.center.round().as_ivec3();
+            let reach = explosion.power.ceil() as i32;
+            let mut changes = Vec::new();
+
+            for x in -reach..=reach {
+                for y in -reach..=reach {
+                    for z in -reach..=reach {
+                        let voxel_pos = center_voxel + IVec3::new(x, y, z);
+                        let radius = explosion.power * rng.gen_range((1.0 - EXPLOSION_NOISE)..=1.0);
+
+                        if voxel_pos.as_vec3().distance(explosion.center) > radius {
+                            continue;
+                        }
+
+                        match voxel_world.get_block(voxel_pos) {
+                            // TNT caught in the explosion is lit, instead of destroyed.
+                            Some(voxel) if voxel.id == Voxel::TNT.id => {
+                                if voxel.state == 0 {
+                                    scheduler.schedule(voxel_pos, rng.gen_range(TNT_CHAIN_FUSE));
+                                }
+                                changes.push((voxel_pos, Voxel::TNT.with_state(1)));
+                            }
+                            Some(voxel) if voxel != Voxel::AIR && !voxel.is_indestructible() => {
+                                changes.push((voxel_pos, Voxel::AIR));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+
+            voxel_world.set_blocks(changes.iter().copied());
+
+            // Let fluids flow into the hole. Lit TNT is skipped, as ticking it early would skip its fuse.
+            for (voxel_pos, voxel) in changes {
+                if voxel == Voxel::AIR {
+                    scheduler.schedule_neighbours(voxel_pos, 1);
+                }
+            }
+
+            for (transform, mut velocity) in &mut velocity_query {
+                let offset = transform.translation - explosion.center;
+                let distance = offset.length();
+
+                if distance < explosion.power * KNOCKBACK_RANGE {
+                    velocity.0 += offset.normalize_or_zero() * KNOCKBACK_STRENGTH * explosion.power
+                        / (1.0 + distance);
+                }
+            }
+        }
+    }
+}
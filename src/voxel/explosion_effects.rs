@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+
+use super::explosion::Explosion;
+
+const PARTICLES_PER_POWER: u32 = 8;
+const PARTICLE_LIFETIME: f32 = 1.0;
+const PARTICLE_SPEED: f32 = 8.0;
+const PARTICLE_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+
+/// This plugin spawns debris particles for every [Explosion]. Their sound is played by the
+/// [VoxelAudioPlugin](super::audio::VoxelAudioPlugin).
+pub(super) struct VoxelExplosionEffectsPlugin;
+
+impl Plugin for VoxelExplosionEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Explosion>()
+            .init_resource::<ExplosionAssets>()
+            .add_systems(
+                Update,
+                (
+                    systems::spawn_explosion_effects,
+                    systems::update_explosion_particles,
+                ),
+            );
+    }
+}
+
+/// Marker component for the debris particles of an explosion, holding how long they have left.
+#[derive(Component)]
+struct ExplosionParticle(Timer);
+
+#[derive(Resource)]
+struct ExplosionAssets {
+    particle_mesh: Handle<Mesh>,
+    particle_material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for ExplosionAssets {
+    fn from_world(world: &mut World) -> Self {
+        let particle_mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(shape::Cube::new(0.2).into());
+        let particle_material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(PARTICLE_COLOR.into());
+        Self {
+            particle_mesh,
+            particle_material,
+        }
+    }
+}
+
+mod systems {
+    use rand::Rng;
+
+    use crate::voxel::physics::{Gravity, TerrainCollider, Velocity};
+
+    use super::*;
+
+    pub(super) fn spawn_explosion_effects(
+        mut commands: Commands,
+        mut explosions: EventReader<Explosion>,
+        explosion_assets: Res<ExplosionAssets>,
+    ) {
+        let mut rng = rand::thread_rng();
+
+        for explosion in explosions.read() {
+            for _ in 0..(explosion.power * PARTICLES_PER_POWER as f32) as u32 {
+                let direction = Vec3::new(
+                    rng.gen_range(-1.0..=1.0),
+                    rng.gen_range(0.0..=1.0),
+                    rng.gen_range(-1.0..=1.0),
+                )
+                .normalize_or_zero();
+
+                commands.spawn((
+                    PbrBundle {
+                        mesh: explosion_assets.particle_mesh.clone(),
+                        material: explosion_assets.particle_material.clone(),
+                        transform: Transform::from_translation(explosion.center),
+                        ..default()
+                    },
+                    Velocity(direction * PARTICLE_SPEED * rng.gen_range(0.5..=1.5)),
+                    Gravity,
+                    TerrainCollider::default(),
+                    ExplosionParticle(Timer::from_seconds(PARTICLE_LIFETIME, TimerMode::Once)),
+                ));
+            }
+        }
+    }
+
+    /// Shrinks explosion particles, and despawns them once their time is up.
+    pub(super) fn update_explosion_particles(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut particle_query: Query<(Entity, &mut Transform, &mut ExplosionParticle)>,
+    ) {
+        for (entity, mut transform, mut particle) in &mut particle_query {
+            particle.0.tick(time.delta());
+
+            if particle.0.finished() {
+                commands.entity(entity).despawn();
+            } else {
+                transform.scale = Vec3::splat(particle.0.percent_left());
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+
+use super::tick::BlockTickSet;
+
+/// Fire burns out once its age reaches this.
+const MAX_FIRE_AGE: u8 = 15;
+/// Fire that doesn't have any flammable blocks around it burns out once its age reaches this.
+const MAX_UNFUELED_FIRE_AGE: u8 = 3;
+/// How many game ticks there are between the ticks of a fire, at minimum. A random amount up to
+/// [FIRE_TICK_JITTER] is added on top, so fires don't spread in lockstep.
+const FIRE_TICK_DELAY: u64 = 20;
+const FIRE_TICK_JITTER: u64 = 20;
+/// The chance of a flammable block next to a fire catching fire, every time the fire is ticked.
+const BURN_CHANCE: f64 = 0.3;
+/// The chance of fire spreading to air next to it, if that air is next to a flammable block.
+const SPREAD_CHANCE: f64 = 0.2;
+
+/// This plugin is responsible for fire spreading to, and burning away, [Flammable](super::registry::BlockTag::Flammable) blocks.
+///
+/// Fire stores its age in the [Voxel](super::Voxel) state, and is driven by scheduled block ticks.
+pub(super) struct VoxelFirePlugin;
+
+impl Plugin for VoxelFirePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FireBurning>()
+            .add_systems(FixedUpdate, systems::burn_fire.after(BlockTickSet));
+    }
+}
+
+/// Event sent every time a fire is ticked while it keeps burning.
+#[derive(Event, Debug, Clone, Copy)]
+pub(super) struct FireBurning {
+    /// The world voxel position of the fire.
+    pub(super) voxel_pos: IVec3,
+}
+
+mod systems {
+    use rand::Rng;
+
+    use crate::voxel::{
+        cube_mesh::DIRECT_CUBE_NEIGHBOURS,
+        registry::BlockTag,
+        tick::{BlockTick, BlockTickKind, BlockTickScheduler},
+        world::VoxelWorld,
+        Voxel,
+    };
+
+    use super::*;
+
+    pub(super) fn burn_fire(
+        mut block_ticks: EventReader<BlockTick>,
+        mut burning: EventWriter<FireBurning>,
+        mut voxel_world: VoxelWorld,
+        mut scheduler: ResMut<BlockTickScheduler>,
+    ) {
+        let mut rng = rand::thread_rng();
+
+        for block_tick in block_ticks.read() {
+            if block_tick.kind != BlockTickKind::Scheduled {
+                continue;
+            }
+
+            let voxel_pos = block_tick.voxel_pos;
+            let Some(voxel) = voxel_world.get_block(voxel_pos) else {
+                continue;
+            };
+            if voxel.id != Voxel::FIRE.id {
+                continue;
+            }
+
+            let is_flammable = |voxel_world: &VoxelWorld, pos: IVec3| {
+                voxel_world
+                    .get_block(pos)
+                    .is_some_and(|voxel| voxel.definition().has_tag(BlockTag::Flammable))
+            };
+
+            // Water puts out fire right away.
+            let extinguished = DIRECT_CUBE_NEIGHBOURS.into_iter().any(|neighbour| {
+                voxel_world
+                    .get_block(voxel_pos + neighbour)
+                    .is_some_and(|voxel| voxel.id == Voxel::WATER.id)
+            });
+            if extinguished {
+                voxel_world.set_block(voxel_pos, Voxel::AIR);
+                continue;
+            }
+
+            let mut fueled = false;
+            let mut ignited = Vec::new();
+
+            for neighbour in DIRECT_CUBE_NEIGHBOURS {
+                let neighbour_pos = voxel_pos + neighbour;
+
+                if is_flammable(&voxel_world, neighbour_pos) {
+                    fueled = true;
+
+                    if rng.gen_bool(BURN_CHANCE) {
+                        ignited.push(neighbour_pos);
+                    }
+                } else if voxel_world.get_block(neighbour_pos) == Some(Voxel::AIR)
+                    && DIRECT_CUBE_NEIGHBOURS
+                        .into_iter()
+                        .any(|offset| is_flammable(&voxel_world, neighbour_pos + offset))
+                    && rng.gen_bool(SPREAD_CHANCE)
+                {
+                    ignited.push(neighbour_pos);
+                }
+            }
+
+            for ignited_pos in ignited {
+                voxel_world.set_block(ignited_pos, Voxel::FIRE);
+                scheduler.schedule(
+                    ignited_pos,
+                    FIRE_TICK_DELAY + rng.gen_range(0..=FIRE_TICK_JITTER),
+                );
+            }
+
+            let age = voxel.state + 1;
+            let max_age = if fueled {
+                MAX_FIRE_AGE
+            } else {
+                MAX_UNFUELED_FIRE_AGE
+            };
+
+            if age >= max_age {
+                voxel_world.set_block(voxel_pos, Voxel::AIR);
+            } else {
+                voxel_world.set_block(voxel_pos, Voxel::FIRE.with_state(age));
+                burning.send(FireBurning { voxel_pos });
+                scheduler.schedule(
+                    voxel_pos,
+                    FIRE_TICK_DELAY + rng.gen_range(0..=FIRE_TICK_JITTER),
+                );
+            }
+        }
+    }
+}
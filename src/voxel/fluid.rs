@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+
+use super::{tick::BlockTickSet, Voxel};
+
+/// Fluid level of a fluid source. Flowing fluid has a level between 1 and [FLUID_FALLING_LEVEL].
+pub(super) const FLUID_SOURCE_LEVEL: u8 = 8;
+/// Fluid level of fluid falling down from above. This is the highest level a flowing fluid can have.
+const FLUID_FALLING_LEVEL: u8 = FLUID_SOURCE_LEVEL - 1;
+
+/// This plugin is responsible for simulating fluids, like water and lava.
+///
+/// Fluids store their level in the [Voxel](super::Voxel) state. Sources spread to neighbouring air with a decreasing
+/// level, and flowing fluid dries up again once nothing is feeding it. All of this is driven by scheduled block ticks,
+/// and configured by the [FluidDefinition](super::registry::FluidDefinition)s and
+/// [FLUID_INTERACTIONS](super::registry::FLUID_INTERACTIONS) in the block registry.
+pub(super) struct VoxelFluidPlugin;
+
+impl Plugin for VoxelFluidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FluidFlowed>()
+            .add_systems(FixedUpdate, systems::flow_fluids.after(BlockTickSet));
+    }
+}
+
+/// Event sent when a fluid flows into a voxel, down or to the side.
+#[derive(Event, Debug, Clone, Copy)]
+pub(super) struct FluidFlowed {
+    /// The world voxel position the fluid flowed into.
+    pub(super) voxel_pos: IVec3,
+    pub(super) fluid: Voxel,
+}
+
+/// How high the surface of a fluid voxel is, from 0.0 to 1.0.
+/// Fluid with more of the same fluid above it fills the entire voxel.
+pub(super) fn fluid_height(level: u8, fluid_above: bool) -> f32 {
+    if fluid_above {
+        1.0
+    } else {
+        level as f32 / (FLUID_SOURCE_LEVEL + 1) as f32
+    }
+}
+
+mod systems {
+    use crate::voxel::{
+        cube_mesh::DIRECT_CUBE_NEIGHBOURS,
+        registry::FLUID_INTERACTIONS,
+        tick::{BlockTick, BlockTickKind, BlockTickScheduler},
+        world::VoxelWorld,
+    };
+
+    use super::*;
+
+    const HORIZONTAL_NEIGHBOURS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+    pub(super) fn flow_fluids(
+        mut block_ticks: EventReader<BlockTick>,
+        mut flows: EventWriter<FluidFlowed>,
+        mut voxel_world: VoxelWorld,
+        mut scheduler: ResMut<BlockTickScheduler>,
+    ) {
+        for block_tick in block_ticks.read() {
+            if block_tick.kind != BlockTickKind::Scheduled {
+                continue;
+            }
+
+            let voxel_pos = block_tick.voxel_pos;
+            let Some(voxel) = voxel_world.get_block(voxel_pos) else {
+                continue;
+            };
+            let Some(fluid) = &voxel.definition().fluid else {
+                continue;
+            };
+
+            let mut changed = Vec::new();
+
+            if let Some(result) = interaction_result(&voxel_world, voxel_pos, voxel) {
+                voxel_world.set_block(voxel_pos, result);
+                changed.push(voxel_pos);
+            }
+
+            // Flowing fluid takes its level from the fluid feeding it, and dries up when there is none.
+            if changed.is_empty() && voxel.state < FLUID_SOURCE_LEVEL {
+                let level = expected_level(&voxel_world, voxel_pos, voxel, fluid.level_drop);
+
+                if level != voxel.state {
+                    let new_voxel = if level == 0 {
+                        Voxel::AIR
+                    } else {
+                        voxel.with_state(level)
+                    };
+
+                    voxel_world.set_block(voxel_pos, new_voxel);
+                    changed.push(voxel_pos);
+                }
+            }
+
+            // Only spread if the fluid didn't change, as it's evaluated again on the next tick anyway.
+            if changed.is_empty() {
+                let below = voxel_pos - IVec3::Y;
+
+                if can_flow_into(voxel_world.get_block(below), voxel, FLUID_FALLING_LEVEL) {
+                    voxel_world.set_block(below, voxel.with_state(FLUID_FALLING_LEVEL));
+                    changed.push(below);
+                    flows.send(FluidFlowed {
+                        voxel_pos: below,
+                        fluid: voxel,
+                    });
+                } else if voxel.state > fluid.level_drop {
+                    let level = (voxel.state - fluid.level_drop).min(FLUID_FALLING_LEVEL);
+
+                    for neighbour in HORIZONTAL_NEIGHBOURS {
+                        let neighbour_pos = voxel_pos + neighbour;
+
+                        if can_flow_into(voxel_world.get_block(neighbour_pos), voxel, level) {
+                            voxel_world.set_block(neighbour_pos, voxel.with_state(level));
+                            changed.push(neighbour_pos);
+                            flows.send(FluidFlowed {
+                                voxel_pos: neighbour_pos,
+                                fluid: voxel,
+                            });
+                        }
+                    }
+                }
+            }
+
+            for changed_pos in changed {
+                scheduler.schedule(changed_pos, fluid.flow_delay);
+                scheduler.schedule_neighbours(changed_pos, fluid.flow_delay);
+            }
+        }
+    }
+
+    /// What the fluid at `voxel_pos` turns into, if it's touching a block of one of the [FLUID_INTERACTIONS].
+    fn interaction_result(
+        voxel_world: &VoxelWorld,
+        voxel_pos: IVec3,
+        fluid: Voxel,
+    ) -> Option<Voxel> {
+        DIRECT_CUBE_NEIGHBOURS.into_iter().find_map(|neighbour| {
+            let neighbour_voxel = voxel_world.get_block(voxel_pos + neighbour)?;
+
+            let interaction = FLUID_INTERACTIONS.iter().find(|interaction| {
+                interaction.fluid == fluid.id && interaction.touching == neighbour_voxel.id
+            })?;
+
+            Some(if fluid.state == FLUID_SOURCE_LEVEL {
+                interaction.source_result
+            } else {
+                interaction.flowing_result
+            })
+        })
+    }
+
+    /// The level flowing fluid at `voxel_pos` should have, based on the same fluid around it.
+    fn expected_level(
+        voxel_world: &VoxelWorld,
+        voxel_pos: IVec3,
+        fluid: Voxel,
+        level_drop: u8,
+    ) -> u8 {
+        let fluid_level = |pos: IVec3| {
+            voxel_world
+                .get_block(pos)
+                .filter(|voxel| voxel.id == fluid.id)
+                .map(|voxel| voxel.state)
+        };
+
+        if fluid_level(voxel_pos + IVec3::Y).is_some() {
+            return FLUID_FALLING_LEVEL;
+        }
+
+        HORIZONTAL_NEIGHBOURS
+            .into_iter()
+            .filter_map(|neighbour| fluid_level(voxel_pos + neighbour))
+            .map(|level| level.saturating_sub(level_drop))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Whether fluid with the given level can flow into a voxel. Fluids only replace air, and the same fluid with a
+    /// lower level.
+    fn can_flow_into(voxel: Option<Voxel>, fluid: Voxel, level: u8) -> bool {
+        match voxel {
+            Some(voxel) if voxel == Voxel::AIR => true,
+            Some(voxel) if voxel.id == fluid.id => voxel.state < level,
+            _ => false,
+        }
+    }
+}
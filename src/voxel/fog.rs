@@ -0,0 +1,79 @@
+use bevy::{pbr::FogSettings, prelude::*};
+
+use super::{
+    generation::VoxelChunkWidth, horizon::HORIZON_RANGE, load::RenderDistance, noise::TerrainNoise,
+    underwater::CameraMedium,
+};
+
+/// How far into the render distance the fog starts, as a fraction of it.
+const FOG_START: f32 = 0.6;
+const UNDERWATER_FOG_COLOR: Color = Color::rgb(0.05, 0.2, 0.35);
+/// How quickly things fade into the fog under water. Higher sees less far.
+const UNDERWATER_FOG_DENSITY: f32 = 0.08;
+
+/// This plugin fogs the distance, so chunks streaming in at the edge of the [RenderDistance] fade in instead of
+/// popping in.
+///
+/// The fog follows the render distance of the camera, and takes the color of the sky, so it darkens with the time of
+/// day. Beyond the loaded chunks it thickens over the horizon, or at the render distance for clients without one.
+/// Under water, it's replaced by a dense blue fog.
+pub(super) struct VoxelFogPlugin;
+
+impl Plugin for VoxelFogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, systems::update_fog);
+    }
+}
+
+mod systems {
+    use super::*;
+
+    pub(super) fn update_fog(
+        mut commands: Commands,
+        camera_query: Query<(Entity, Ref<RenderDistance>), With<Camera3d>>,
+        clear_color: Res<ClearColor>,
+        camera_medium: Res<CameraMedium>,
+        chunk_width: Res<VoxelChunkWidth>,
+        terrain_noise: Option<Res<TerrainNoise>>,
+    ) {
+        let Ok((camera, render_distance)) = camera_query.get_single() else {
+            return;
+        };
+        if !render_distance.is_changed()
+            && !clear_color.is_changed()
+            && !camera_medium.is_changed()
+            && !chunk_width.is_changed()
+        {
+            return;
+        }
+
+        let fog = if *camera_medium == CameraMedium::Water {
+            FogSettings {
+                color: UNDERWATER_FOG_COLOR,
+                falloff: FogFalloff::Exponential {
+                    density: UNDERWATER_FOG_DENSITY,
+                },
+                ..default()
+            }
+        } else {
+            let render_radius = (render_distance.val * chunk_width.0 as u32) as f32;
+            // Only the noise can draw a horizon, see VoxelHorizonPlugin.
+            let end = if terrain_noise.is_some() {
+                ((render_distance.val + HORIZON_RANGE) * chunk_width.0 as u32) as f32
+            } else {
+                render_radius
+            };
+
+            FogSettings {
+                color: clear_color.0,
+                falloff: FogFalloff::Linear {
+                    start: render_radius * FOG_START,
+                    end,
+                },
+                ..default()
+            }
+        };
+
+        commands.entity(camera).insert(fog);
+    }
+}
@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use bevy_flycam::KeyBindings;
+use serde::{Deserialize, Serialize};
+
+use crate::console::RegisterConsoleCommand;
+
+use super::{
+    noclip::Noclip,
+    physics::{Gravity, PhysicsSet, TerrainCollider, Velocity},
+    spawn::EYE_HEIGHT,
+};
+
+/// How fast the player jumps up, in voxels per second. Enough to get on top of a voxel.
+const JUMP_SPEED: f32 = 7.5;
+
+/// This plugin holds the [GameMode], and switches the player between flying and walking with it. The
+/// `gamemode` console command shows or switches it. [Noclip] lets the player fly through the terrain in any game mode.
+pub(super) struct VoxelGameModePlugin;
+
+impl Plugin for VoxelGameModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .register_console_command(
+                "gamemode",
+                "Shows the game mode, or switches to `creative` or `survival`",
+            )
+            .add_systems(
+                Update,
+                (
+                    systems::switch_game_mode_on_command,
+                    systems::apply_game_mode_to_player,
+                    systems::toggle_flycam_flight.run_if(
+                        resource_changed::<GameMode>().or_else(resource_changed::<Noclip>()),
+                    ),
+                    systems::jump,
+                )
+                    .chain()
+                    .before(PhysicsSet),
+            );
+    }
+}
+
+/// How the player plays the world. It's saved with the world.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GameMode {
+    /// The player flies, breaks voxels right away, places blocks without using them up, and can't be hurt.
+    #[default]
+    Creative,
+    /// The player walks and falls, mines voxels for as long as their hardness, places blocks from the inventory, and
+    /// takes damage.
+    Survival,
+}
+
+impl GameMode {
+    /// The game mode with the given name, as typed into the `gamemode` command.
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "creative" => Some(GameMode::Creative),
+            "survival" => Some(GameMode::Survival),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_creative(&self) -> bool {
+        *self == GameMode::Creative
+    }
+}
+
+mod systems {
+    use crate::{
+        console::ConsoleCommand,
+        input::{ActionInput, InputAction},
+    };
+
+    use super::*;
+
+    pub(super) fn switch_game_mode_on_command(
+        mut commands: EventReader<ConsoleCommand>,
+        mut game_mode: ResMut<GameMode>,
+    ) {
+        for command in commands.read() {
+            if command.name != "gamemode" {
+                continue;
+            }
+
+            let Some(name) = command.args.first() else {
+                info!("Game mode: {:?}", *game_mode);
+                continue;
+            };
+            match GameMode::from_name(name) {
+                Some(new_game_mode) => {
+                    *game_mode = new_game_mode;
+                    info!("Switched to {new_game_mode:?}");
+                }
+                None => warn!("Unknown game mode `{name}`, expected `creative` or `survival`"),
+            }
+        }
+    }
+
+    /// Makes the player fall and land on the terrain in [GameMode::Survival], and stops it in [GameMode::Creative] or
+    /// with [Noclip]. The camera is the player.
+    pub(super) fn apply_game_mode_to_player(
+        mut commands: Commands,
+        game_mode: Res<GameMode>,
+        noclip: Res<Noclip>,
+        camera_query: Query<(Entity, Has<TerrainCollider>), With<Camera3d>>,
+    ) {
+        let flies = noclip.flies(&game_mode);
+
+        for (entity, walking) in &camera_query {
+            match (flies, walking) {
+                (false, false) => {
+                    commands.entity(entity).insert((
+                        Velocity::default(),
+                        Gravity,
+                        TerrainCollider {
+                            // The player stands on the bottom of the voxel their feet are in.
+                            height: EYE_HEIGHT + 0.5,
+                        },
+                    ));
+                }
+                (true, true) => {
+                    commands
+                        .entity(entity)
+                        .remove::<(Velocity, Gravity, TerrainCollider)>();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Unbinds flying up and down with the flycam while the player walks, and binds it again while they fly.
+    pub(super) fn toggle_flycam_flight(
+        game_mode: Res<GameMode>,
+        noclip: Res<Noclip>,
+        key_bindings: Option<ResMut<KeyBindings>>,
+        mut flight_keys: Local<Option<(KeyCode, KeyCode)>>,
+    ) {
+        let Some(mut key_bindings) = key_bindings else {
+            return;
+        };
+        let (ascend, descend) =
+            *flight_keys.get_or_insert((key_bindings.move_ascend, key_bindings.move_descend));
+
+        if noclip.flies(&game_mode) {
+            key_bindings.move_ascend = ascend;
+            key_bindings.move_descend = descend;
+        } else {
+            key_bindings.move_ascend = KeyCode::Unlabeled;
+            key_bindings.move_descend = KeyCode::Unlabeled;
+        }
+    }
+
+    /// Jumps while the player stands on the ground, which is whenever they're walking and not moving up or down.
+    pub(super) fn jump(
+        input: ActionInput,
+        mut player_query: Query<&mut Velocity, (With<Camera3d>, With<TerrainCollider>)>,
+    ) {
+        if !input.pressed(InputAction::Jump) {
+            return;
+        }
+
+        for mut velocity in &mut player_query {
+            if velocity.0.y == 0.0 {
+                velocity.0.y = JUMP_SPEED;
+            }
+        }
+    }
+}
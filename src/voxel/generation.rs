@@ -1,15 +1,39 @@
+use std::borrow::Cow;
+
 use bevy::{
+    math::Affine3A,
     prelude::*,
-    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    render::{
+        mesh::Indices,
+        primitives::{Aabb, Frustum},
+        render_resource::PrimitiveTopology,
+    },
     utils::hashbrown::HashMap,
 };
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::voxel::cube_mesh::CubeFace;
 
 use super::{
-    cube_mesh::DIRECT_CUBE_NEIGHBOURS, load::VoxelChunkLoadingPlugin, noise::TerrainNoise, Voxel,
-    VoxelChunkCoordinate,
+    chunk_material::{
+        pack_vertex, ChunkMaterial, ATTRIBUTE_OCCLUSION, ATTRIBUTE_PACKED_VERTEX,
+        ATTRIBUTE_TEXTURE_LAYER, NO_TEXTURE_LAYER,
+    },
+    color::VoxelMode,
+    cube_mesh::{vertex_occlusion, DIRECT_CUBE_NEIGHBOURS},
+    data::{DensityVoxel, VoxelData},
+    density::DensityField,
+    dual_contouring::dual_contouring,
+    load::{ChunkState, VoxelChunkLoadingPlugin},
+    marching_cubes::marching_cubes,
+    mesher::TerrainMesher,
+    noise::TerrainNoise,
+    shading::{bevel_patches, polygon_indices, smooth_normals, ChunkShading},
+    simplify::FaceQuads,
+    storage::ChunkStorage,
+    world::box_positions,
+    Voxel, VoxelChunkCoordinate,
 };
 
 /// Default value for [VoxelChunkWidth].
@@ -40,6 +64,15 @@ impl LocalVoxelPosition {
         Self { x, y, z }
     }
 
+    /// Creates a [LocalVoxelPosition] from an [IVec3]. The components are expected to be within the chunk width.
+    pub(super) fn from_ivec3(pos: IVec3) -> Self {
+        Self::new(pos.x as u8, pos.y as u8, pos.z as u8)
+    }
+
+    pub(super) fn as_ivec3(&self) -> IVec3 {
+        IVec3::new(self.x as i32, self.y as i32, self.z as i32)
+    }
+
     /// Calculates a [LocalVoxelPosition] from a given index in the 3-dimensional flat voxel array [VoxelChunk].voxels.
     /// This is calculated based on the chunk width.
     pub(super) fn from_index(index: usize, chunk_width: &VoxelChunkWidth) -> Self {
@@ -53,12 +86,10 @@ impl LocalVoxelPosition {
     }
 
     /// Calculates the index in the 3-dimensional flat voxel array [VoxelChunk].voxels based on the [LocalVoxelPosition]
-    pub(super) fn to_index(&self, chunk_width: &VoxelChunkWidth) -> usize {
-        let index = self.z as usize * chunk_width.0 as usize * chunk_width.0 as usize
+    pub(super) fn to_index(self, chunk_width: &VoxelChunkWidth) -> usize {
+        self.z as usize * chunk_width.0 as usize * chunk_width.0 as usize
             + self.y as usize * chunk_width.0 as usize
-            + self.x as usize;
-
-        index
+            + self.x as usize
     }
 }
 
@@ -85,43 +116,183 @@ impl VoxelChunkMap {
     }
 
     /// Gets a specific voxel from the map
-    fn get_voxel(
+    pub(super) fn get_voxel<V: VoxelData>(
         &self,
         chunk_position: &VoxelChunkPosition,
         local_voxel_position: &LocalVoxelPosition,
         chunk_width: &VoxelChunkWidth,
-        voxel_chunk_query: &Query<&VoxelChunk>,
-    ) -> Option<Voxel> {
-        let Some(chunk_entity) = self.0.get(chunk_position) else {
-            return None;
-        };
+        voxel_chunk_query: &Query<&VoxelChunk<V>>,
+    ) -> Option<V> {
+        let chunk_entity = self.0.get(chunk_position)?;
 
         let Ok(chunk) = voxel_chunk_query.get(*chunk_entity) else {
             return None;
         };
 
-        chunk
-            .voxels
-            .get(local_voxel_position.to_index(chunk_width))
-            .and_then(|v| Some(*v))
+        chunk.voxels.get(local_voxel_position.to_index(chunk_width))
+    }
+
+    /// The loaded chunks that are at least partly inside a sphere, given in world coordinates.
+    pub(super) fn chunks_in_sphere(
+        &self,
+        center: Vec3,
+        radius: f32,
+        chunk_width: &VoxelChunkWidth,
+    ) -> Vec<(VoxelChunkPosition, Entity)> {
+        let radius = radius.max(0.0);
+
+        self.chunks_in_box(
+            center - radius,
+            center + radius,
+            chunk_width,
+            |chunk_aabb| {
+                chunk_aabb.closest_point(center).distance_squared(center) <= radius * radius
+            },
+        )
+    }
+
+    /// The loaded chunks that are at least partly inside a box between two opposite corners, given in world
+    /// coordinates.
+    pub(super) fn chunks_in_aabb(
+        &self,
+        a: Vec3,
+        b: Vec3,
+        chunk_width: &VoxelChunkWidth,
+    ) -> Vec<(VoxelChunkPosition, Entity)> {
+        self.chunks_in_box(a.min(b), a.max(b), chunk_width, |_| true)
+    }
+
+    /// The loaded chunks that are at least partly inside the [Frustum] of a camera.
+    pub(super) fn chunks_in_frustum(
+        &self,
+        frustum: &Frustum,
+        chunk_width: &VoxelChunkWidth,
+    ) -> Vec<(VoxelChunkPosition, Entity)> {
+        let cw = chunk_width.0 as f32;
+        let aabb = Aabb::from_min_max(Vec3::splat(-0.5), Vec3::splat(cw - 0.5));
+
+        self.0
+            .iter()
+            .filter(|(chunk_pos, _)| {
+                let transform = Affine3A::from_translation(chunk_pos.0.as_vec3() * cw);
+                frustum.intersects_obb(&aabb, &transform, true, true)
+            })
+            .map(|(chunk_pos, entity)| (*chunk_pos, *entity))
+            .collect()
+    }
+
+    /// The loaded chunks overlapping the box between `min` and `max`, whose bounds pass `filter`.
+    ///
+    /// Small boxes look up every chunk position inside them, big ones go through all loaded chunks instead.
+    fn chunks_in_box(
+        &self,
+        min: Vec3,
+        max: Vec3,
+        chunk_width: &VoxelChunkWidth,
+        filter: impl Fn(ChunkBounds) -> bool,
+    ) -> Vec<(VoxelChunkPosition, Entity)> {
+        let min_chunk_pos = VoxelChunkPosition::from_world_pos(min + 0.5, chunk_width).0;
+        let max_chunk_pos = VoxelChunkPosition::from_world_pos(max + 0.5, chunk_width).0;
+        let box_size = (max_chunk_pos - min_chunk_pos + IVec3::ONE).as_i64vec3();
+
+        let overlaps = |chunk_pos: &VoxelChunkPosition| {
+            chunk_pos.0.cmpge(min_chunk_pos).all()
+                && chunk_pos.0.cmple(max_chunk_pos).all()
+                && filter(ChunkBounds::new(chunk_pos, chunk_width))
+        };
+
+        if box_size.x * box_size.y * box_size.z > self.0.len() as i64 {
+            return self
+                .0
+                .iter()
+                .filter(|(chunk_pos, _)| overlaps(chunk_pos))
+                .map(|(chunk_pos, entity)| (*chunk_pos, *entity))
+                .collect();
+        }
+
+        let mut chunks = Vec::new();
+        for z in min_chunk_pos.z..=max_chunk_pos.z {
+            for y in min_chunk_pos.y..=max_chunk_pos.y {
+                for x in min_chunk_pos.x..=max_chunk_pos.x {
+                    let chunk_pos = VoxelChunkPosition::new(x, y, z);
+                    if let Some(entity) = self.0.get(&chunk_pos) {
+                        if overlaps(&chunk_pos) {
+                            chunks.push((chunk_pos, *entity));
+                        }
+                    }
+                }
+            }
+        }
+
+        chunks
+    }
+}
+
+/// The world space box a chunk covers. Voxels are centered on their position, so a chunk starts half a voxel before
+/// its first voxel position.
+#[derive(Clone, Copy)]
+struct ChunkBounds {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl ChunkBounds {
+    fn new(chunk_pos: &VoxelChunkPosition, chunk_width: &VoxelChunkWidth) -> Self {
+        let min = chunk_pos.0.as_vec3() * chunk_width.0 as f32 - 0.5;
+
+        Self {
+            min,
+            max: min + chunk_width.0 as f32,
+        }
+    }
+
+    fn closest_point(&self, point: Vec3) -> Vec3 {
+        point.clamp(self.min, self.max)
     }
 }
 
 /// Decorative struct that represents a chunk position as an [IVec3].
 /// This is also a component used in [VoxelChunkBundle]
-#[derive(Component, Default, Debug, Eq, PartialEq, Hash, Copy, Clone, Reflect)]
+#[derive(
+    Component, Default, Debug, Eq, PartialEq, Hash, Copy, Clone, Reflect, Serialize, Deserialize,
+)]
 pub(super) struct VoxelChunkPosition(pub(super) IVec3);
 
 impl VoxelChunkPosition {
     pub(super) fn new(x: i32, y: i32, z: i32) -> Self {
         Self(IVec3::new(x, y, z))
     }
+
+    /// Splits a world voxel position into the position of the chunk containing it, and the local position inside that
+    /// chunk. Chunks are split off with euclidean division, so voxel -1 is the last voxel of chunk -1, not part of
+    /// chunk 0.
+    pub(super) fn world_to_local(
+        voxel_pos: IVec3,
+        chunk_width: &VoxelChunkWidth,
+    ) -> (Self, LocalVoxelPosition) {
+        let cw = IVec3::splat(chunk_width.0 as i32);
+
+        (
+            Self(voxel_pos.div_euclid(cw)),
+            LocalVoxelPosition::from_ivec3(voxel_pos.rem_euclid(cw)),
+        )
+    }
+
+    /// The world voxel position of a local position inside this chunk. The opposite of [Self::world_to_local].
+    pub(super) fn local_to_world(
+        &self,
+        local_voxel_pos: LocalVoxelPosition,
+        chunk_width: &VoxelChunkWidth,
+    ) -> IVec3 {
+        self.0 * chunk_width.0 as i32 + local_voxel_pos.as_ivec3()
+    }
 }
 
 impl VoxelChunkCoordinate for VoxelChunkPosition {
+    /// Voxels are centered on their positions, so a point is in the voxel it's [rounded](Vec3::round) to, like a point
+    /// at y -0.01 is in voxel 0, and in chunk 0.
     fn from_world_pos(world_pos: Vec3, chunk_width: &VoxelChunkWidth) -> Self {
-        VoxelChunkPosition::new(world_pos.x as i32, world_pos.y as i32, world_pos.z as i32)
-            / chunk_width.0 as i32
+        Self::world_to_local(world_pos.round().as_ivec3(), chunk_width).0
     }
 
     fn from_chunk_pos(chunk_pos: &VoxelChunkPosition, _chunk_width: &VoxelChunkWidth) -> Self {
@@ -137,14 +308,6 @@ impl VoxelChunkCoordinate for VoxelChunkPosition {
     }
 }
 
-impl std::ops::Div<i32> for VoxelChunkPosition {
-    type Output = VoxelChunkPosition;
-
-    fn div(self, rhs: i32) -> Self::Output {
-        Self::new(self.0.x / rhs, self.0.y / rhs, self.0.z / rhs)
-    }
-}
-
 impl std::ops::Mul<i32> for VoxelChunkPosition {
     type Output = VoxelChunkPosition;
 
@@ -153,7 +316,7 @@ impl std::ops::Mul<i32> for VoxelChunkPosition {
     }
 }
 
-impl<'a> std::ops::Mul<i32> for &'a VoxelChunkPosition {
+impl std::ops::Mul<i32> for &VoxelChunkPosition {
     type Output = VoxelChunkPosition;
 
     fn mul(self, rhs: i32) -> VoxelChunkPosition {
@@ -180,11 +343,25 @@ impl Default for VoxelChunkWidth {
 }
 
 /// The voxel chunk component.
-#[derive(Component, Default, Clone)]
-pub(super) struct VoxelChunk {
-    /// A 3 dimensional flat vector of all the voxels. Refer to [LocalVoxelPosition]'s methods to
-    /// find a specific voxel inside the vector.
-    voxels: Vec<Voxel>,
+///
+/// The chunks of the world hold [Voxel]s, but storing and meshing a chunk works for any [VoxelData].
+#[derive(Component, Clone)]
+pub(super) struct VoxelChunk<V: VoxelData = Voxel> {
+    /// All the voxels, 3 dimensionally flattened. Refer to [LocalVoxelPosition]'s methods to find the index of a
+    /// specific voxel.
+    voxels: ChunkStorage<V>,
+    /// The densities of smooth terrain, indexed like the voxels, if the chunk stores them. See
+    /// [VoxelConfig::density_chunks](super::VoxelConfig::density_chunks).
+    densities: Option<ChunkStorage<DensityVoxel>>,
+}
+
+impl<V: VoxelData> Default for VoxelChunk<V> {
+    fn default() -> Self {
+        Self {
+            voxels: ChunkStorage::Dense(Vec::new()),
+            densities: None,
+        }
+    }
 }
 
 impl VoxelChunk {
@@ -192,17 +369,29 @@ impl VoxelChunk {
         chunk_pos: &VoxelChunkPosition,
         chunk_width: &VoxelChunkWidth,
         terrain_noise: &TerrainNoise,
+        voxel_mode: VoxelMode,
     ) -> Self {
-        let range_size = chunk_width.0 as usize * chunk_width.0 as usize * chunk_width.0 as usize;
+        let _span = info_span!("generate_chunk", chunk_pos = ?chunk_pos.0).entered();
+
+        let cw = chunk_width.0 as usize;
+        let range_size = cw * cw * cw;
         let voxels = std::sync::Mutex::new(vec![Voxel::AIR; range_size]);
+        let origin = chunk_pos.0 * chunk_width.0 as i32;
+
+        // Blending the biomes is expensive, so it's done once per column instead of for every voxel.
+        let columns: Vec<_> = (0..cw * cw)
+            .into_par_iter()
+            .map(|i| terrain_noise.column(origin.x + (i % cw) as i32, origin.z + (i / cw) as i32))
+            .collect();
 
         (0..range_size).into_par_iter().for_each(|i| {
             let position = LocalVoxelPosition::from_index(i, chunk_width);
 
             let voxel = terrain_noise.get_voxel(
-                chunk_pos.0.x * chunk_width.0 as i32 + position.x as i32,
-                chunk_pos.0.y * chunk_width.0 as i32 + position.y as i32,
-                chunk_pos.0.z * chunk_width.0 as i32 + position.z as i32,
+                origin.x + position.x as i32,
+                origin.y + position.y as i32,
+                origin.z + position.z as i32,
+                &columns[position.z as usize * cw + position.x as usize],
             );
 
             loop {
@@ -213,86 +402,625 @@ impl VoxelChunk {
             }
         });
 
-        let voxels = voxels.into_inner().unwrap();
-        Self { voxels }
+        let mut voxels = voxels.into_inner().unwrap();
+        terrain_noise.apply_surface_rules(&mut voxels, origin, &columns, cw);
+        // Surface rules look at which blocks were generated, so the voxels are only turned into colors after them.
+        if voxel_mode != VoxelMode::Blocks {
+            for voxel in &mut voxels {
+                *voxel = voxel_mode.generated_voxel(*voxel);
+            }
+        }
+        Self::from_voxels(voxels)
+    }
+
+    /// Stores a [DensityVoxel] for every voxel of the chunk, or drops them again. The densities start out derived from
+    /// the voxels, see [DensityVoxel::from_chunk_voxels], so smooth terrain looks the same until they're changed.
+    /// Chunks that already store densities keep them.
+    pub(super) fn set_densities(&mut self, densities: bool, chunk_width: &VoxelChunkWidth) {
+        if !densities {
+            self.densities = None;
+        } else if self.densities.is_none() {
+            let voxels = DensityVoxel::from_chunk_voxels(&self.voxels(), chunk_width.0);
+            self.densities = Some(ChunkStorage::Dense(voxels));
+        }
+    }
+
+    /// Replaces the voxel at a local position in the chunk. A stored density is kept, but moved to the side of the
+    /// surface the new voxel is on, see [DensityVoxel::with_density].
+    ///
+    /// Note that this does not update the mesh. The chunk has to be pushed to the
+    /// [ChunkRenderQueue](super::render::ChunkRenderQueue) for that.
+    pub(super) fn set_voxel(
+        &mut self,
+        local_voxel_position: LocalVoxelPosition,
+        voxel: Voxel,
+        chunk_width: &VoxelChunkWidth,
+    ) {
+        let index = local_voxel_position.to_index(chunk_width);
+        self.voxels.set(index, voxel);
+
+        if let Some(densities) = &mut self.densities {
+            if let Some(old) = densities.get(index) {
+                densities.set(
+                    index,
+                    DensityVoxel::from_voxel(voxel).with_density(old.density()),
+                );
+            }
+        }
+    }
+}
+
+impl<V: VoxelData> VoxelChunk<V> {
+    /// Creates a chunk from all of its voxels, in the same order as [VoxelChunk::voxels].
+    pub(super) fn from_voxels(voxels: Vec<V>) -> Self {
+        Self {
+            voxels: ChunkStorage::Dense(voxels),
+            densities: None,
+        }
+    }
+
+    /// Finds the top-most solid voxel of every (x, z) column in the chunk.
+    ///
+    /// The returned vector is indexed by `z * chunk_width + x`, and holds the local y and the voxel.
+    pub(super) fn surface(&self, chunk_width: &VoxelChunkWidth) -> Vec<Option<(u8, V)>> {
+        let cw = chunk_width.0;
+        let mut surface = vec![None; cw as usize * cw as usize];
+
+        for z in 0..cw {
+            for x in 0..cw {
+                surface[z as usize * cw as usize + x as usize] = (0..cw).rev().find_map(|y| {
+                    let voxel = self.get_voxel(LocalVoxelPosition::new(x, y, z), chunk_width)?;
+                    voxel.is_solid().then_some((y, voxel))
+                });
+            }
+        }
+
+        surface
+    }
+
+    /// All the voxels of the chunk. Use [LocalVoxelPosition::from_index] to find the position of a voxel.
+    ///
+    /// Sparse chunks are expanded into a copy, so prefer [VoxelChunk::get_voxel] for looking up a few voxels.
+    pub(super) fn voxels(&self) -> Cow<'_, [V]> {
+        match &self.voxels {
+            ChunkStorage::Dense(voxels) => Cow::Borrowed(voxels),
+            sparse => Cow::Owned(sparse.to_vec()),
+        }
+    }
+
+    /// Moves the voxels into [sparse storage](ChunkStorage::Bricks), or back into a flat vector. Sparse chunks take a
+    /// fraction of the memory when they're mostly air or mostly solid, but are slower to look voxels up in.
+    pub(super) fn set_sparse(&mut self, sparse: bool, chunk_width: &VoxelChunkWidth) {
+        if sparse {
+            self.voxels.compact(chunk_width.0 as usize);
+            if let Some(densities) = &mut self.densities {
+                densities.compact(chunk_width.0 as usize);
+            }
+        } else {
+            self.voxels.expand();
+            if let Some(densities) = &mut self.densities {
+                densities.expand();
+            }
+        }
+    }
+
+    /// How many bytes the voxels of the chunk take up, along with their densities.
+    pub(super) fn voxel_memory(&self) -> usize {
+        self.voxels.heap_size() + self.densities.as_ref().map_or(0, ChunkStorage::heap_size)
     }
 
+    /// Gets the stored density of the voxel at a local position in the chunk, see [DensityVoxel::density]. Returns
+    /// `None` if the chunk doesn't store densities.
+    pub(super) fn density(
+        &self,
+        local_voxel_position: LocalVoxelPosition,
+        chunk_width: &VoxelChunkWidth,
+    ) -> Option<f32> {
+        let densities = self.densities.as_ref()?;
+        densities
+            .get(local_voxel_position.to_index(chunk_width))
+            .map(|voxel| voxel.density())
+    }
+
+    /// Gets the voxel at a local position in the chunk.
+    pub(super) fn get_voxel(
+        &self,
+        local_voxel_position: LocalVoxelPosition,
+        chunk_width: &VoxelChunkWidth,
+    ) -> Option<V> {
+        self.voxels.get(local_voxel_position.to_index(chunk_width))
+    }
+
+    /// Gets the voxel next to a voxel of this chunk. Neighbours outside of this chunk are looked up in the
+    /// neighbouring chunk instead, which returns `None` if that chunk isn't loaded.
+    fn neighbour_voxel(
+        &self,
+        chunk_pos: &VoxelChunkPosition,
+        local_voxel_pos: LocalVoxelPosition,
+        offset: IVec3,
+        chunk_width: &VoxelChunkWidth,
+        voxel_map: &VoxelChunkMap,
+        voxel_chunk_query: &Query<&VoxelChunk<V>>,
+    ) -> Option<V> {
+        let (neighbour_chunk_pos, neighbour_local_pos) = VoxelChunkPosition::world_to_local(
+            chunk_pos.local_to_world(local_voxel_pos, chunk_width) + offset,
+            chunk_width,
+        );
+
+        if neighbour_chunk_pos == *chunk_pos {
+            self.get_voxel(neighbour_local_pos, chunk_width)
+        } else {
+            voxel_map.get_voxel(
+                &neighbour_chunk_pos,
+                &neighbour_local_pos,
+                chunk_width,
+                voxel_chunk_query,
+            )
+        }
+    }
+
+    /// Gets the stored density of the voxel next to a voxel of this chunk, looking in the neighbouring chunk like
+    /// [neighbour_voxel](Self::neighbour_voxel). Returns `None` if that chunk isn't loaded or doesn't store densities.
+    fn neighbour_density(
+        &self,
+        chunk_pos: &VoxelChunkPosition,
+        local_voxel_pos: LocalVoxelPosition,
+        offset: IVec3,
+        chunk_width: &VoxelChunkWidth,
+        voxel_map: &VoxelChunkMap,
+        voxel_chunk_query: &Query<&VoxelChunk<V>>,
+    ) -> Option<f32> {
+        let (neighbour_chunk_pos, neighbour_local_pos) = VoxelChunkPosition::world_to_local(
+            chunk_pos.local_to_world(local_voxel_pos, chunk_width) + offset,
+            chunk_width,
+        );
+
+        if neighbour_chunk_pos == *chunk_pos {
+            self.density(neighbour_local_pos, chunk_width)
+        } else {
+            let neighbour_entity = voxel_map.0.get(&neighbour_chunk_pos)?;
+            let neighbour_chunk = voxel_chunk_query.get(*neighbour_entity).ok()?;
+            neighbour_chunk.density(neighbour_local_pos, chunk_width)
+        }
+    }
+
+    /// Generates the mesh of every voxel of the chunk that's drawn in the given [ChunkMeshSection].
+    ///
+    /// The tops of voxels are lowered by their [top_height](VoxelData::top_height). Besides positions, normals and
+    /// colors, every vertex gets a texture coordinate, its ambient occlusion and its texture layer, as the
+    /// [ChunkMaterial](super::chunk_material::ChunkMaterial) expects. With `packed`, each vertex is packed into a
+    /// single [ATTRIBUTE_PACKED_VERTEX] instead, which takes 8 bytes rather than 56.
+    ///
+    /// With a [smooth](TerrainMesher::is_smooth) mesher, the opaque section is smooth terrain instead, see
+    /// [mesh_smooth](Self::mesh_smooth). Otherwise the cubes are drawn with the given [ChunkShading].
     pub(super) fn generate_mesh(
         &self,
+        section: ChunkMeshSection,
+        packed: bool,
+        mesher: TerrainMesher,
+        shading: ChunkShading,
+        chunk_pos: &VoxelChunkPosition,
         chunk_width: &VoxelChunkWidth,
         voxel_map: &VoxelChunkMap,
-        voxel_chunk_query: &Query<&VoxelChunk>,
+        voxel_chunk_query: &Query<&VoxelChunk<V>>,
+    ) -> Mesh {
+        let _span = info_span!("mesh_chunk", chunk_pos = ?chunk_pos.0, ?section).entered();
+
+        self.mesh_voxels(
+            section,
+            packed,
+            mesher,
+            shading,
+            chunk_width,
+            1,
+            None,
+            |local_voxel_pos, offset| {
+                self.neighbour_voxel(
+                    chunk_pos,
+                    local_voxel_pos,
+                    offset,
+                    chunk_width,
+                    voxel_map,
+                    voxel_chunk_query,
+                )
+            },
+            |local_voxel_pos, offset| {
+                self.neighbour_density(
+                    chunk_pos,
+                    local_voxel_pos,
+                    offset,
+                    chunk_width,
+                    voxel_map,
+                    voxel_chunk_query,
+                )
+            },
+        )
+    }
+
+    /// Generates a coarser mesh of the chunk for level of detail `lod`, from voxel data downsampled by `2^lod` on every
+    /// axis, see [downsample](Self::downsample). The mesh covers the same space as the full mesh.
+    ///
+    /// Neighbouring chunks aren't looked at, so the faces on the border of the chunk are always drawn. These hide the
+    /// gaps between chunks of different levels of detail. With a `simplify_tolerance`, faces that look alike are merged
+    /// into larger quads, see [FaceQuads::merge].
+    pub(super) fn generate_lod_mesh(
+        &self,
+        section: ChunkMeshSection,
+        packed: bool,
+        mesher: TerrainMesher,
+        shading: ChunkShading,
+        lod: u8,
+        simplify_tolerance: Option<f32>,
+        chunk_pos: &VoxelChunkPosition,
+        chunk_width: &VoxelChunkWidth,
+    ) -> Mesh {
+        let _span = info_span!("mesh_chunk_lod", chunk_pos = ?chunk_pos.0, ?section, lod).entered();
+
+        let scale = 1 << lod;
+        let (lod_chunk, lod_width) = self.downsample(scale, chunk_width);
+        let width = lod_width.0 as i32;
+
+        lod_chunk.mesh_voxels(
+            section,
+            packed,
+            mesher,
+            shading,
+            &lod_width,
+            scale,
+            simplify_tolerance,
+            |local_voxel_pos, offset| {
+                let neighbour_pos = local_voxel_pos.as_ivec3() + offset;
+                if neighbour_pos.cmplt(IVec3::ZERO).any()
+                    || neighbour_pos.cmpge(IVec3::splat(width)).any()
+                {
+                    return None;
+                }
+
+                lod_chunk.get_voxel(LocalVoxelPosition::from_ivec3(neighbour_pos), &lod_width)
+            },
+            |_, _| None,
+        )
+    }
+
+    /// Downsamples the chunk by `factor` on every axis, returning the smaller chunk and its width. Every voxel of the
+    /// smaller chunk is the most common voxel of the cell of `factor`³ voxels it covers. Ties go to voxels that are
+    /// drawn, so thin surfaces don't vanish in the distance.
+    pub(super) fn downsample(
+        &self,
+        factor: u8,
+        chunk_width: &VoxelChunkWidth,
+    ) -> (VoxelChunk<V>, VoxelChunkWidth) {
+        let lod_width = VoxelChunkWidth(chunk_width.0 / factor);
+        if self.voxels.is_empty() {
+            return (VoxelChunk::default(), lod_width);
+        }
+
+        let mut voxels = Vec::with_capacity(self.voxels.len() / (factor as usize).pow(3));
+        let mut counts: Vec<(V, usize)> = Vec::new();
+
+        for i in 0..(lod_width.0 as usize).pow(3) {
+            let cell = LocalVoxelPosition::from_index(i, &lod_width).as_ivec3() * factor as i32;
+
+            counts.clear();
+            for offset in box_positions(IVec3::ZERO, IVec3::splat(factor as i32 - 1)) {
+                let Some(voxel) =
+                    self.get_voxel(LocalVoxelPosition::from_ivec3(cell + offset), chunk_width)
+                else {
+                    continue;
+                };
+
+                match counts.iter_mut().find(|(counted, _)| *counted == voxel) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((voxel, 1)),
+                }
+            }
+
+            let (voxel, _) = counts
+                .iter()
+                .max_by_key(|(voxel, count)| (*count, voxel.mesh_section().is_some()))
+                .expect("every cell has voxels");
+            voxels.push(*voxel);
+        }
+
+        (VoxelChunk::from_voxels(voxels), lod_width)
+    }
+
+    /// Meshes the voxels of the chunk, looking up the voxels next to them with `neighbour_voxel`, and the stored
+    /// densities of those with `neighbour_density` for smooth terrain. Vertices are scaled up by `scale`, for chunks
+    /// which are downsampled. Cubes are drawn with the given [ChunkShading], unless the
+    /// vertices are packed. With a `simplify_tolerance`, faces that look alike are merged into larger quads afterwards,
+    /// see [FaceQuads::merge].
+    pub(super) fn mesh_voxels(
+        &self,
+        section: ChunkMeshSection,
+        packed: bool,
+        mesher: TerrainMesher,
+        shading: ChunkShading,
+        chunk_width: &VoxelChunkWidth,
+        scale: u8,
+        simplify_tolerance: Option<f32>,
+        neighbour_voxel: impl Fn(LocalVoxelPosition, IVec3) -> Option<V>,
+        neighbour_density: impl Fn(LocalVoxelPosition, IVec3) -> Option<f32>,
     ) -> Mesh {
+        if mesher.is_smooth() && section == ChunkMeshSection::Opaque {
+            return self.mesh_smooth(
+                mesher,
+                chunk_width,
+                scale,
+                neighbour_voxel,
+                neighbour_density,
+            );
+        }
+        let shading = if packed { ChunkShading::FLAT } else { shading };
+
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
         let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut colors = Vec::new();
+        let mut occlusions = Vec::new();
+        let mut texture_layers = Vec::new();
+        // The voxel of every vertex, to tell which normals to smooth together.
+        let mut vertex_voxels = Vec::new();
         let mut vertices_pushed = 0;
 
         for (i, voxel) in self.voxels.iter().enumerate() {
-            if !voxel.is_solid() {
+            if voxel.mesh_section() != Some(section) {
                 continue;
             }
 
-            let local_voxel_pos = LocalVoxelPosition::from_index(i, &chunk_width);
+            let local_voxel_pos = LocalVoxelPosition::from_index(i, chunk_width);
+            let neighbour_voxel = |offset| neighbour_voxel(local_voxel_pos, offset);
 
-            let mut faces = Vec::new();
+            let height = voxel.top_height(neighbour_voxel(IVec3::Y));
+            // Faces are hidden by opaque voxels and voxels of the same kind. If there is no neighbour, the face is
+            // rendered. A lowered top, like that of a fluid, is below the voxel above it, so it's never hidden by
+            // opaque voxels.
+            let hidden = |neighbour: IVec3| {
+                neighbour_voxel(neighbour).is_some_and(|neighbour_voxel| {
+                    neighbour_voxel.is_same_kind(&voxel)
+                        || (neighbour_voxel.is_opaque() && !(height < 1.0 && neighbour == IVec3::Y))
+                })
+            };
+            let bevel = if height < 1.0 { 0.0 } else { shading.bevel() };
 
             for neighbour in DIRECT_CUBE_NEIGHBOURS {
-                let Some(x) = local_voxel_pos.x.checked_add_signed(neighbour.x as i8) else {
-                    continue;
-                };
-                let Some(y) = local_voxel_pos.y.checked_add_signed(neighbour.y as i8) else {
-                    continue;
-                };
-                let Some(z) = local_voxel_pos.z.checked_add_signed(neighbour.z as i8) else {
-                    continue;
-                };
-
                 let face = CubeFace::from_ivec3(neighbour);
+                let color = voxel.face_color(neighbour).as_linear_rgba_f32();
+                let texture_layer = voxel.texture_layer(neighbour).unwrap_or(NO_TEXTURE_LAYER);
 
-                // This looks kind of weird, but it's simply like this:
-                // - if there is a neighbour, and the neighbour isn't a solid voxel, render face. if there is no neighbour, render face.
-                if let Some(voxel) = voxel_map.get_voxel(
-                    &VoxelChunkPosition::new(0, 0, 0),
-                    &LocalVoxelPosition::new(x, y, z),
-                    &chunk_width,
-                    &voxel_chunk_query,
-                ) {
-                    if !voxel.is_solid() {
-                        faces.push(face);
-                    }
-                } else {
-                    faces.push(face);
+                if hidden(neighbour) {
+                    continue;
                 }
-            }
 
-            for face in faces {
                 for index in face.indices(vertices_pushed) {
                     indices.push(index);
                 }
 
-                for vertex in face.vertices() {
-                    let vertex_pos = Vec3::new(
-                        local_voxel_pos.x as f32,
-                        local_voxel_pos.y as f32,
-                        local_voxel_pos.z as f32,
-                    ) + vertex;
+                for mut vertex in face.vertices() {
+                    // A lowered top doesn't touch the voxels around it, so nothing occludes it.
+                    let occlusion = if height < 1.0 && matches!(face, CubeFace::Top) {
+                        1.0
+                    } else {
+                        let occludes = |offset| {
+                            neighbour_voxel(offset).is_some_and(|voxel: V| voxel.is_opaque())
+                        };
+                        let corner = (vertex * 2.0).as_ivec3();
+                        let (mut side1, mut side2) = (neighbour, neighbour);
+                        let mut axes = (0..3).filter(|axis| neighbour[*axis] == 0);
+                        let (axis1, axis2) = (axes.next().unwrap(), axes.next().unwrap());
+                        side1[axis1] = corner[axis1];
+                        side2[axis2] = corner[axis2];
+
+                        vertex_occlusion(
+                            occludes(side1),
+                            occludes(side2),
+                            occludes(side1 + side2 - neighbour),
+                        )
+                    };
 
-                    vertices.push(vertex_pos);
+                    if vertex.y > 0.0 {
+                        vertex.y = height - 0.5;
+                    }
+
+                    // A bevelled face is shrunk away from the edges it shares with the other drawn faces of the voxel.
+                    if bevel > 0.0 {
+                        for axis in (0..3).filter(|axis| neighbour[*axis] == 0) {
+                            let mut side = IVec3::ZERO;
+                            side[axis] = vertex[axis].signum() as i32;
+                            if !hidden(side) {
+                                vertex[axis] -= side[axis] as f32 * bevel;
+                            }
+                        }
+                    }
+
+                    let position = local_voxel_pos.as_ivec3().as_vec3() + vertex;
+                    vertices.push((position + 0.5) * scale as f32 - 0.5);
+                    uvs.push(face.uv(vertex));
+                    colors.push(color);
+                    occlusions.push(occlusion);
+                    texture_layers.push(texture_layer);
+                    vertex_voxels.push(voxel);
                     vertices_pushed += 1;
                 }
 
                 for normal in face.normals() {
                     normals.push(normal);
                 }
+
+                if bevel > 0.0 {
+                    for (polygon, normal) in bevel_patches(neighbour, bevel, |side| !hidden(side)) {
+                        indices.extend(polygon_indices(&polygon, normal, vertices_pushed));
+
+                        for vertex in polygon {
+                            let position = local_voxel_pos.as_ivec3().as_vec3() + vertex;
+                            vertices.push((position + 0.5) * scale as f32 - 0.5);
+                            normals.push(normal);
+                            uvs.push(face.uv(vertex));
+                            colors.push(color);
+                            occlusions.push(1.0);
+                            texture_layers.push(texture_layer);
+                            vertex_voxels.push(voxel);
+                            vertices_pushed += 1;
+                        }
+                    }
+                }
             }
         }
 
+        if shading.smooth_normals {
+            smooth_normals(&vertices, &mut normals, |a, b| {
+                vertex_voxels[a].is_same_kind(&vertex_voxels[b])
+            });
+        }
+
+        // Bevels aren't quads, so bevelled meshes can't be simplified.
+        if let Some(tolerance) = simplify_tolerance.filter(|_| shading.bevel() == 0.0) {
+            let merged = FaceQuads {
+                vertices,
+                normals,
+                uvs,
+                colors,
+                occlusions,
+                texture_layers,
+                indices,
+            }
+            .merge(scale, tolerance);
+
+            vertices = merged.vertices;
+            normals = merged.normals;
+            uvs = merged.uvs;
+            colors = merged.colors;
+            occlusions = merged.occlusions;
+            texture_layers = merged.texture_layers;
+            indices = merged.indices;
+        }
+
+        if packed {
+            let packed_vertices: Vec<[u32; 2]> = (0..vertices.len())
+                .map(|i| {
+                    let normal_id = DIRECT_CUBE_NEIGHBOURS
+                        .iter()
+                        .position(|neighbour| neighbour.as_vec3() == normals[i])
+                        .unwrap_or_default();
+                    let [r, g, b, a] = colors[i];
+
+                    pack_vertex(
+                        vertices[i],
+                        normal_id as u32,
+                        occlusions[i],
+                        Color::rgba_linear(r, g, b, a),
+                        texture_layers[i],
+                    )
+                })
+                .collect();
+
+            return Mesh::new(PrimitiveTopology::TriangleList)
+                .with_inserted_attribute(ATTRIBUTE_PACKED_VERTEX, packed_vertices)
+                .with_indices(Some(Indices::U32(indices)));
+        }
+
         Mesh::new(PrimitiveTopology::TriangleList)
             .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vertices)
             .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+            .with_inserted_attribute(ATTRIBUTE_OCCLUSION, occlusions)
+            .with_inserted_attribute(ATTRIBUTE_TEXTURE_LAYER, texture_layers)
             .with_indices(Some(Indices::U32(indices)))
     }
+
+    /// Meshes the opaque voxels of the chunk as smooth terrain, with the smooth `mesher` over the [DensityField] of
+    /// the chunk, which takes the stored densities of the chunk and its neighbours where they have any. Vertices get the color of the filled voxel next to them, as the face pointing towards the surface.
+    fn mesh_smooth(
+        &self,
+        mesher: TerrainMesher,
+        chunk_width: &VoxelChunkWidth,
+        scale: u8,
+        neighbour_voxel: impl Fn(LocalVoxelPosition, IVec3) -> Option<V>,
+        neighbour_density: impl Fn(LocalVoxelPosition, IVec3) -> Option<f32>,
+    ) -> Mesh {
+        let width = chunk_width.0 as i32;
+        let origin = LocalVoxelPosition::new(0, 0, 0);
+        let inside = |position: IVec3| {
+            position.cmpge(IVec3::ZERO).all() && position.cmplt(IVec3::splat(width)).all()
+        };
+        let voxel_at = |position: IVec3| {
+            if inside(position) {
+                self.get_voxel(LocalVoxelPosition::from_ivec3(position), chunk_width)
+            } else {
+                neighbour_voxel(origin, position)
+            }
+        };
+        let density_at = |position: IVec3| {
+            if inside(position) {
+                self.density(LocalVoxelPosition::from_ivec3(position), chunk_width)
+            } else {
+                neighbour_density(origin, position)
+            }
+        };
+        let field = DensityField::with_densities(width, voxel_at, density_at);
+        let face_color = |position, normal| {
+            voxel_at(position).map_or(Color::WHITE, |voxel| voxel.face_color(normal))
+        };
+
+        let surface = match mesher {
+            TerrainMesher::MarchingCubes => marching_cubes(&field, face_color),
+            TerrainMesher::DualContouring => dual_contouring(&field, face_color),
+            TerrainMesher::Cubes => unreachable!("cubes aren't smooth terrain"),
+        };
+        surface.into_mesh(scale)
+    }
+
+    /// Finds the light emitting voxels of the chunk, returning their local center and their combined
+    /// [light_emission](VoxelData::light_emission).
+    ///
+    /// Returns `None` if no voxels in the chunk emit light.
+    pub(super) fn light_source(&self, chunk_width: &VoxelChunkWidth) -> Option<(Vec3, u32)> {
+        let mut position_sum = Vec3::ZERO;
+        let mut emitters = 0;
+        let mut emission = 0;
+
+        for (i, voxel) in self.voxels.iter().enumerate() {
+            let light_emission = voxel.light_emission();
+            if light_emission == 0 {
+                continue;
+            }
+
+            position_sum += LocalVoxelPosition::from_index(i, chunk_width)
+                .as_ivec3()
+                .as_vec3();
+            emitters += 1;
+            emission += light_emission as u32;
+        }
+
+        (emitters > 0).then(|| (position_sum / emitters as f32, emission))
+    }
+}
+
+/// The parts a chunk mesh is split into, so each can be drawn with its own material.
+///
+/// The opaque section is the mesh of the chunk entity itself, the other sections are meshes of child entities with
+/// this component.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkMeshSection {
+    Opaque,
+    /// See-through voxels, like water. Drawn in the transparent pass.
+    Transparent,
+    /// Voxels that glow, like lava. These aren't affected by lighting.
+    Emissive,
+    /// Voxels with holes in them, like leaves. Drawn in the opaque pass, with the holes cut out by their alpha, so they
+    /// don't need sorting like transparent voxels.
+    Cutout,
+}
+
+impl ChunkMeshSection {
+    pub(super) const ALL: [Self; 4] = [
+        Self::Opaque,
+        Self::Transparent,
+        Self::Emissive,
+        Self::Cutout,
+    ];
 }
 
 /// This is the bundle used for a voxel chunk. This is used when spawning in chunks.
@@ -304,7 +1032,288 @@ pub(super) struct VoxelChunkBundle {
     pub(super) transform: Transform,
     pub(super) global_transform: GlobalTransform,
     pub(super) mesh: Handle<Mesh>,
-    pub(super) material: Handle<StandardMaterial>,
+    pub(super) material: Handle<ChunkMaterial>,
     pub(super) chunk: VoxelChunk,
     pub(super) chunk_pos: VoxelChunkPosition,
+    pub(super) state: ChunkState,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::*;
+    use crate::voxel::density::SURFACE_DENSITY;
+
+    const CHUNK_WIDTH: VoxelChunkWidth = VoxelChunkWidth(16);
+
+    /// A voxel type of a game with its own voxels, which are either empty or a glowing lamp.
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    struct Lamp(bool);
+
+    impl VoxelData for Lamp {
+        fn is_solid(&self) -> bool {
+            self.0
+        }
+
+        fn mesh_section(&self) -> Option<ChunkMeshSection> {
+            self.0.then_some(ChunkMeshSection::Emissive)
+        }
+
+        fn face_color(&self, _normal: IVec3) -> Color {
+            Color::YELLOW
+        }
+
+        fn light_emission(&self) -> u8 {
+            if self.0 {
+                15
+            } else {
+                0
+            }
+        }
+    }
+    /// The fixture holding a [GoldenChunk] for every chunk of [GOLDEN_CHUNKS], relative to the crate.
+    const GOLDEN_CHUNKS_PATH: &str = "src/voxel/fixtures/golden_chunks.ron";
+    /// The seeds and positions of the chunks pinned by the fixture. They cover the surface, the sea, the bedrock floor
+    /// and the sky of a few worlds.
+    const GOLDEN_CHUNKS: [(u32, IVec3); 11] = [
+        (0, IVec3::new(0, 0, 0)),
+        (0, IVec3::new(0, -4, 0)),
+        (1, IVec3::new(0, -1, 0)),
+        (1, IVec3::new(40, 0, 40)),
+        (1337, IVec3::new(0, 0, 0)),
+        (1337, IVec3::new(-20, 0, 31)),
+        (1337, IVec3::new(-20, -1, 31)),
+        (1337, IVec3::new(12, 1, -9)),
+        (0xdead_beef, IVec3::new(3, -1, -2)),
+        (0xdead_beef, IVec3::new(-20, -1, 31)),
+        (0xdead_beef, IVec3::new(40, 0, 40)),
+    ];
+
+    /// What the fixture pins down about a generated chunk.
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+    struct GoldenChunk {
+        seed: u32,
+        chunk_pos: IVec3,
+        /// An FNV-1a hash of the encoded voxels.
+        voxel_hash: u64,
+        /// The vertex count of every [ChunkMeshSection], in [ChunkMeshSection::ALL] order. Faces on the border of the
+        /// chunk are always drawn, so this doesn't depend on the neighbouring chunks.
+        vertex_counts: Vec<usize>,
+    }
+
+    /// A 64-bit FNV-1a hash, which unlike the hashers of the standard library is guaranteed to stay the same.
+    fn fnv1a(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+
+    /// Generates the chunk with the noise of a new world with the default preset, without any of the files that
+    /// override it.
+    fn golden_chunk(seed: u32, chunk_pos: IVec3) -> GoldenChunk {
+        let terrain_noise = TerrainNoise::new_world(seed);
+        let chunk = VoxelChunk::from_noise(
+            &VoxelChunkPosition(chunk_pos),
+            &CHUNK_WIDTH,
+            &terrain_noise,
+            VoxelMode::Blocks,
+        );
+        let voxels = bincode::serialize(&*chunk.voxels()).unwrap();
+
+        let neighbour_voxel = |local_pos: LocalVoxelPosition, offset: IVec3| {
+            let pos = local_pos.as_ivec3() + offset;
+            let inside =
+                pos.cmpge(IVec3::ZERO).all() && pos.cmplt(IVec3::splat(CHUNK_WIDTH.0 as i32)).all();
+            inside
+                .then(|| chunk.get_voxel(LocalVoxelPosition::from_ivec3(pos), &CHUNK_WIDTH))
+                .flatten()
+        };
+        let vertex_counts = ChunkMeshSection::ALL
+            .into_iter()
+            .map(|section| {
+                chunk
+                    .mesh_voxels(
+                        section,
+                        false,
+                        TerrainMesher::default(),
+                        ChunkShading::FLAT,
+                        &CHUNK_WIDTH,
+                        1,
+                        None,
+                        neighbour_voxel,
+                        |_, _| None,
+                    )
+                    .count_vertices()
+            })
+            .collect();
+
+        GoldenChunk {
+            seed,
+            chunk_pos,
+            voxel_hash: fnv1a(&voxels),
+            vertex_counts,
+        }
+    }
+
+    /// Catches unintended changes to the generator or the mesher. If a change is intended, run the tests with
+    /// `UPDATE_GOLDEN_CHUNKS=1` to write the new fixture, and commit it along with the change.
+    #[test]
+    fn generated_chunks_match_golden_fixture() {
+        let chunks: Vec<GoldenChunk> = GOLDEN_CHUNKS
+            .into_iter()
+            .map(|(seed, chunk_pos)| golden_chunk(seed, chunk_pos))
+            .collect();
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN_CHUNKS_PATH);
+
+        if std::env::var_os("UPDATE_GOLDEN_CHUNKS").is_some() {
+            let contents =
+                ron::ser::to_string_pretty(&chunks, ron::ser::PrettyConfig::default()).unwrap();
+            fs::write(&path, contents).unwrap();
+            return;
+        }
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let expected: Vec<GoldenChunk> = ron::from_str(&contents).unwrap();
+        assert_eq!(chunks.len(), expected.len(), "chunks in the fixture");
+        for (chunk, expected) in chunks.iter().zip(&expected) {
+            assert_eq!(
+                chunk, expected,
+                "chunk {} of seed {} changed, run with UPDATE_GOLDEN_CHUNKS=1 if that's intended",
+                expected.chunk_pos, expected.seed
+            );
+        }
+    }
+
+    #[test]
+    fn generation_is_deterministic() {
+        let (seed, chunk_pos) = GOLDEN_CHUNKS[0];
+        assert_eq!(golden_chunk(seed, chunk_pos), golden_chunk(seed, chunk_pos));
+    }
+
+    #[test]
+    fn world_to_local_floors_negative_positions() {
+        let cases = [
+            (
+                IVec3::new(0, 0, 0),
+                IVec3::new(0, 0, 0),
+                IVec3::new(0, 0, 0),
+            ),
+            (
+                IVec3::new(15, 16, 17),
+                IVec3::new(0, 1, 1),
+                IVec3::new(15, 0, 1),
+            ),
+            (
+                IVec3::new(-1, -1, -1),
+                IVec3::new(-1, -1, -1),
+                IVec3::new(15, 15, 15),
+            ),
+            (
+                IVec3::new(-16, -17, -32),
+                IVec3::new(-1, -2, -2),
+                IVec3::new(0, 15, 0),
+            ),
+            (
+                IVec3::new(-5, 5, -33),
+                IVec3::new(-1, 0, -3),
+                IVec3::new(11, 5, 15),
+            ),
+        ];
+
+        for (voxel_pos, chunk_pos, local_pos) in cases {
+            assert_eq!(
+                VoxelChunkPosition::world_to_local(voxel_pos, &CHUNK_WIDTH),
+                (
+                    VoxelChunkPosition(chunk_pos),
+                    LocalVoxelPosition::from_ivec3(local_pos)
+                ),
+                "voxel {voxel_pos}"
+            );
+        }
+    }
+
+    #[test]
+    fn local_to_world_round_trips() {
+        for x in -40..40 {
+            for y in [-33, -16, -1, 0, 1, 31] {
+                for z in [-17, -1, 0, 15] {
+                    let voxel_pos = IVec3::new(x, y, z);
+                    let (chunk_pos, local_pos) =
+                        VoxelChunkPosition::world_to_local(voxel_pos, &CHUNK_WIDTH);
+
+                    assert_eq!(chunk_pos.local_to_world(local_pos, &CHUNK_WIDTH), voxel_pos);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn from_world_pos_rounds_to_the_nearest_voxel() {
+        let cases = [
+            (Vec3::new(0.4, 0.0, 15.4), IVec3::new(0, 0, 0)),
+            (Vec3::new(-0.4, -0.01, 15.6), IVec3::new(0, 0, 1)),
+            (Vec3::new(-0.6, -16.4, -16.6), IVec3::new(-1, -1, -2)),
+            (Vec3::new(-16.4, 15.6, -31.9), IVec3::new(-1, 1, -2)),
+        ];
+
+        for (world_pos, chunk_pos) in cases {
+            assert_eq!(
+                VoxelChunkPosition::from_world_pos(world_pos, &CHUNK_WIDTH),
+                VoxelChunkPosition(chunk_pos),
+                "world position {world_pos}"
+            );
+            assert_eq!(
+                world_pos.as_chunk_pos(&CHUNK_WIDTH),
+                VoxelChunkPosition(chunk_pos)
+            );
+        }
+    }
+
+    #[test]
+    fn stored_densities_follow_edited_voxels() {
+        let mut chunk =
+            VoxelChunk::from_voxels(vec![Voxel::STONE; (CHUNK_WIDTH.0 as usize).pow(3)]);
+        let pos = LocalVoxelPosition::from_ivec3(IVec3::new(8, 8, 8));
+        assert_eq!(chunk.density(pos, &CHUNK_WIDTH), None);
+
+        chunk.set_densities(true, &CHUNK_WIDTH);
+        assert_eq!(chunk.density(pos, &CHUNK_WIDTH), Some(1.0));
+
+        // Emptying the voxel keeps as much of its density as it can, on the empty side of the surface.
+        chunk.set_voxel(pos, Voxel::AIR, &CHUNK_WIDTH);
+        assert_eq!(chunk.density(pos, &CHUNK_WIDTH), Some(SURFACE_DENSITY));
+
+        chunk.set_densities(false, &CHUNK_WIDTH);
+        assert_eq!(chunk.density(pos, &CHUNK_WIDTH), None);
+    }
+
+    #[test]
+    fn chunks_of_any_voxel_data_are_meshed_and_lit() {
+        let lamp_pos = LocalVoxelPosition::from_ivec3(IVec3::new(1, 2, 3));
+        let mut voxels =
+            vec![
+                Lamp(false);
+                CHUNK_WIDTH.0 as usize * CHUNK_WIDTH.0 as usize * CHUNK_WIDTH.0 as usize
+            ];
+        voxels[lamp_pos.to_index(&CHUNK_WIDTH)] = Lamp(true);
+        let chunk = VoxelChunk::from_voxels(voxels);
+
+        let mesh = chunk.mesh_voxels(
+            ChunkMeshSection::Emissive,
+            false,
+            TerrainMesher::default(),
+            ChunkShading::FLAT,
+            &CHUNK_WIDTH,
+            1,
+            None,
+            |_, _| None,
+            |_, _| None,
+        );
+        assert_eq!(mesh.count_vertices(), 24, "one cube of 6 faces");
+        assert_eq!(
+            chunk.light_source(&CHUNK_WIDTH),
+            Some((Vec3::new(1.0, 2.0, 3.0), 15))
+        );
+    }
 }
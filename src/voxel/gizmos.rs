@@ -1,6 +1,14 @@
 use bevy::prelude::*;
 
-const CHUNK_BORDER_COLOR: Color = Color::ORANGE;
+use super::{
+    cube_mesh::CubeFace,
+    generation::{VoxelChunkPosition, VoxelChunkWidth},
+    load::ChunkLoadQueue,
+    tick::BlockTick,
+    AddResourceInspector, VoxelChunkCoordinate,
+};
+
+const VOXEL_GRID_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.25);
 
 #[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
 pub(super) enum ChunkBorderState {
@@ -9,54 +17,589 @@ pub(super) enum ChunkBorderState {
     Disabled,
 }
 
+/// Whether the per-voxel grid of the chunk the camera is inside is drawn.
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(super) enum VoxelGridState {
+    Enabled,
+    #[default]
+    Disabled,
+}
+
+/// Whether chunks are outlined with a color based on their [ChunkState](super::load::ChunkState).
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(super) enum ChunkStateHeatmapState {
+    Enabled,
+    #[default]
+    Disabled,
+}
+
+/// Whether the load radius and unload margin of every [RenderDistance](super::load::RenderDistance) is drawn.
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(super) enum RenderDistanceGizmoState {
+    Enabled,
+    #[default]
+    Disabled,
+}
+
+/// Whether the vertex normals of the targeted chunk's mesh are drawn.
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(super) enum MeshNormalsState {
+    Enabled,
+    #[default]
+    Disabled,
+}
+
+/// Whether the chunk positions waiting in the [ChunkLoadQueue](super::load::ChunkLoadQueue) are outlined.
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(super) enum ChunkQueueGizmoState {
+    Enabled,
+    #[default]
+    Disabled,
+}
+
+/// Whether voxels are outlined when they receive a [BlockTick](super::tick::BlockTick).
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(super) enum BlockTickGizmoState {
+    Enabled,
+    #[default]
+    Disabled,
+}
+
+/// Configuration of the chunk border gizmos.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub(super) struct ChunkGizmoConfig {
+    pub(super) color: Color,
+    pub(super) selection: ChunkGizmoSelection,
+    /// Line width in pixels.
+    ///
+    /// NOTE: bevy only has a single global gizmo config, so this affects every gizmo while chunk borders are enabled.
+    pub(super) line_width: f32,
+}
+
+impl Default for ChunkGizmoConfig {
+    fn default() -> Self {
+        Self {
+            color: Color::ORANGE,
+            selection: ChunkGizmoSelection::All,
+            line_width: 2.0,
+        }
+    }
+}
+
+/// Which chunks get a border drawn around them.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum ChunkGizmoSelection {
+    /// Every loaded chunk.
+    All,
+    /// Chunks within this many chunks of the chunk the camera is inside.
+    Near(u32),
+    /// Only the chunk the camera is inside.
+    Current,
+}
+
 pub(super) struct VoxelGizmosPlugin;
 
 impl Plugin for VoxelGizmosPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_state::<ChunkBorderState>().add_systems(
-            Update,
-            (
-                systems::toggle_chunk_borders,
-                systems::chunk_borders.run_if(in_state(ChunkBorderState::Enabled)),
-            ),
-        );
+        app.add_state::<ChunkBorderState>()
+            .add_state::<VoxelGridState>()
+            .add_state::<ChunkStateHeatmapState>()
+            .add_state::<RenderDistanceGizmoState>()
+            .add_state::<MeshNormalsState>()
+            .add_state::<ChunkQueueGizmoState>()
+            .add_state::<BlockTickGizmoState>()
+            .init_resource::<ChunkGizmoConfig>()
+            .register_type::<ChunkGizmoConfig>()
+            .add_resource_inspector::<ChunkGizmoConfig>()
+            .add_systems(
+                Update,
+                (
+                    systems::toggle_chunk_borders,
+                    systems::toggle_voxel_grid,
+                    systems::toggle_chunk_state_heatmap,
+                    systems::toggle_chunk_wireframe,
+                    systems::toggle_render_distance_gizmo,
+                    systems::toggle_mesh_normals,
+                    systems::toggle_chunk_queue_gizmo,
+                    systems::toggle_block_tick_gizmo,
+                    systems::apply_gizmo_line_width.run_if(
+                        resource_changed::<ChunkGizmoConfig>()
+                            .or_else(state_changed::<ChunkBorderState>()),
+                    ),
+                    systems::chunk_borders.run_if(in_state(ChunkBorderState::Enabled)),
+                    systems::voxel_grid.run_if(in_state(VoxelGridState::Enabled)),
+                    systems::chunk_state_heatmap.run_if(in_state(ChunkStateHeatmapState::Enabled)),
+                    systems::render_distance_gizmo
+                        .run_if(in_state(RenderDistanceGizmoState::Enabled)),
+                    systems::mesh_normals.run_if(in_state(MeshNormalsState::Enabled)),
+                    // The load queue and block ticks only exist where the world is simulated, so not on clients
+                    // connected to a server.
+                    systems::chunk_queue_gizmo.run_if(
+                        in_state(ChunkQueueGizmoState::Enabled)
+                            .and_then(resource_exists::<ChunkLoadQueue>()),
+                    ),
+                    systems::block_tick_gizmo.run_if(
+                        in_state(BlockTickGizmoState::Enabled)
+                            .and_then(resource_exists::<Events<BlockTick>>()),
+                    ),
+                ),
+            );
     }
 }
 
+const QUEUED_CHUNK_COLOR: Color = Color::GRAY;
+const GENERATED_CHUNK_COLOR: Color = Color::YELLOW;
+const MESHED_CHUNK_COLOR: Color = Color::GREEN;
+const DIRTY_CHUNK_COLOR: Color = Color::RED;
+
+const LOAD_QUEUE_COLOR: Color = Color::rgba(0.0, 1.0, 1.0, 0.4);
+const UNLOAD_QUEUE_COLOR: Color = Color::rgba(1.0, 0.0, 1.0, 0.4);
+
+const SCHEDULED_TICK_COLOR: Color = Color::WHITE;
+const RANDOM_TICK_COLOR: Color = Color::LIME_GREEN;
+
+const LOAD_RADIUS_COLOR: Color = Color::CYAN;
+const UNLOAD_RADIUS_COLOR: Color = Color::PURPLE;
+
+/// Length of the vertex normal lines.
+const MESH_NORMAL_LENGTH: f32 = 0.3;
+/// Color of triangles whose winding order doesn't match their vertex normals.
+const WRONG_WINDING_COLOR: Color = Color::FUCHSIA;
+/// Color of normals that don't point along a single [CubeFace], like smoothed normals and those of smooth terrain.
+const OFF_AXIS_NORMAL_COLOR: Color = Color::WHITE;
+
+/// The color used for the normals of each [CubeFace], when drawing mesh normals.
+fn cube_face_color(face: &CubeFace) -> Color {
+    match face {
+        CubeFace::Top => Color::GREEN,
+        CubeFace::Bottom => Color::DARK_GREEN,
+        CubeFace::Left => Color::RED,
+        CubeFace::Right => Color::MAROON,
+        CubeFace::Front => Color::BLUE,
+        CubeFace::Back => Color::NAVY,
+    }
+}
+
+/// Returns a transform for a cuboid gizmo covering the chunk's AABB, scaled around its center.
+fn chunk_aabb_transform(
+    chunk_pos: &VoxelChunkPosition,
+    chunk_width: &VoxelChunkWidth,
+    scale: f32,
+) -> Transform {
+    // Voxels are centered on their position, so the chunk's AABB starts half a voxel before its world position.
+    let cw = chunk_width.0 as f32;
+    let center = chunk_pos.as_world_pos(chunk_width) + cw / 2.0 - 0.5;
+
+    Transform::from_translation(center).with_scale(Vec3::splat(cw * scale))
+}
+
 mod systems {
-    use bevy::{gizmos::gizmos::Gizmos, prelude::*};
+    use bevy::{
+        gizmos::gizmos::Gizmos, pbr::wireframe::Wireframe, prelude::*,
+        render::mesh::VertexAttributeValues,
+    };
 
-    use crate::voxel::{
-        generation::{VoxelChunk, VoxelChunkPosition, VoxelChunkWidth},
-        VoxelChunkCoordinate,
+    use crate::{
+        input::{ActionInput, InputAction},
+        voxel::{
+            generation::{VoxelChunk, VoxelChunkMap},
+            interaction::TargetedVoxel,
+            load::{ChunkState, RenderDistance},
+            tick::BlockTickKind,
+        },
     };
 
-    use super::{ChunkBorderState, CHUNK_BORDER_COLOR};
+    use super::*;
 
     pub(super) fn chunk_borders(
         mut gizmos: Gizmos,
         chunk_query: Query<&VoxelChunkPosition, With<VoxelChunk>>,
+        camera_query: Query<&Transform, With<Camera3d>>,
         chunk_width: Res<VoxelChunkWidth>,
+        config: Res<ChunkGizmoConfig>,
     ) {
+        let camera_chunk_pos = camera_query.get_single().ok().map(|camera_transform| {
+            VoxelChunkPosition::world_to_local(
+                camera_transform.translation.round().as_ivec3(),
+                &chunk_width,
+            )
+            .0
+        });
+
         for chunk_pos in &chunk_query {
+            let selected = match (config.selection, camera_chunk_pos) {
+                (ChunkGizmoSelection::All, _) => true,
+                (ChunkGizmoSelection::Near(radius), Some(camera_chunk_pos)) => {
+                    (chunk_pos.0 - camera_chunk_pos.0).abs().max_element() <= radius as i32
+                }
+                (ChunkGizmoSelection::Current, Some(camera_chunk_pos)) => {
+                    *chunk_pos == camera_chunk_pos
+                }
+                (_, None) => false,
+            };
+
+            if !selected {
+                continue;
+            }
+
             gizmos.cuboid(
-                Transform::from_translation(chunk_pos.as_world_pos(&chunk_width) / 2.0 - 0.5)
-                    .with_scale(Vec3::splat(chunk_width.0 as f32)),
-                CHUNK_BORDER_COLOR,
+                chunk_aabb_transform(chunk_pos, &chunk_width, 1.0),
+                config.color,
             )
         }
     }
 
+    pub(super) fn apply_gizmo_line_width(
+        config: Res<ChunkGizmoConfig>,
+        chunk_border_state: Res<State<ChunkBorderState>>,
+        mut gizmo_config: ResMut<GizmoConfig>,
+    ) {
+        gizmo_config.line_width = match **chunk_border_state {
+            ChunkBorderState::Enabled => config.line_width,
+            ChunkBorderState::Disabled => GizmoConfig::default().line_width,
+        };
+    }
+
     pub(super) fn toggle_chunk_borders(
-        input: Res<Input<KeyCode>>,
+        input: ActionInput,
         mut next_state: ResMut<NextState<ChunkBorderState>>,
         cur_state: Res<State<ChunkBorderState>>,
     ) {
-        if input.just_pressed(KeyCode::B) {
+        if input.just_pressed(InputAction::ToggleChunkBorders) {
             next_state.set(match **cur_state {
                 ChunkBorderState::Enabled => ChunkBorderState::Disabled,
                 ChunkBorderState::Disabled => ChunkBorderState::Enabled,
             })
         }
     }
+
+    /// Draws a line on every voxel boundary of the chunk the camera is currently inside.
+    pub(super) fn voxel_grid(
+        mut gizmos: Gizmos,
+        camera_query: Query<&Transform, With<Camera3d>>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let Ok(camera_transform) = camera_query.get_single() else {
+            return;
+        };
+
+        let (chunk_pos, _) = VoxelChunkPosition::world_to_local(
+            camera_transform.translation.round().as_ivec3(),
+            &chunk_width,
+        );
+
+        // Voxels are centered on their position, so the chunk starts half a voxel before its world position.
+        let min = chunk_pos.as_world_pos(&chunk_width) - 0.5;
+        let cw = chunk_width.0 as f32;
+
+        for i in 0..=chunk_width.0 {
+            for j in 0..=chunk_width.0 {
+                let (i, j) = (i as f32, j as f32);
+
+                gizmos.line(
+                    min + Vec3::new(0.0, i, j),
+                    min + Vec3::new(cw, i, j),
+                    VOXEL_GRID_COLOR,
+                );
+                gizmos.line(
+                    min + Vec3::new(i, 0.0, j),
+                    min + Vec3::new(i, cw, j),
+                    VOXEL_GRID_COLOR,
+                );
+                gizmos.line(
+                    min + Vec3::new(i, j, 0.0),
+                    min + Vec3::new(i, j, cw),
+                    VOXEL_GRID_COLOR,
+                );
+            }
+        }
+    }
+
+    pub(super) fn toggle_voxel_grid(
+        input: ActionInput,
+        mut next_state: ResMut<NextState<VoxelGridState>>,
+        cur_state: Res<State<VoxelGridState>>,
+    ) {
+        if input.just_pressed(InputAction::ToggleVoxelGrid) {
+            next_state.set(match **cur_state {
+                VoxelGridState::Enabled => VoxelGridState::Disabled,
+                VoxelGridState::Disabled => VoxelGridState::Enabled,
+            })
+        }
+    }
+
+    /// Draws a box inside every chunk, colored by how far along the loading pipeline it is.
+    pub(super) fn chunk_state_heatmap(
+        mut gizmos: Gizmos,
+        chunk_query: Query<(&VoxelChunkPosition, &ChunkState)>,
+        chunk_load_queue: Option<Res<ChunkLoadQueue>>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        // Slightly smaller than the chunk, so it doesn't overlap with the chunk borders.
+        const SCALE: f32 = 0.9;
+
+        for chunk_pos in chunk_load_queue
+            .iter()
+            .flat_map(|queue| queue.queued_loads())
+        {
+            gizmos.cuboid(
+                chunk_aabb_transform(chunk_pos, &chunk_width, SCALE),
+                QUEUED_CHUNK_COLOR,
+            );
+        }
+
+        for (chunk_pos, chunk_state) in &chunk_query {
+            let color = match chunk_state {
+                ChunkState::Generated => GENERATED_CHUNK_COLOR,
+                ChunkState::Meshed => MESHED_CHUNK_COLOR,
+                ChunkState::Dirty => DIRTY_CHUNK_COLOR,
+            };
+
+            gizmos.cuboid(chunk_aabb_transform(chunk_pos, &chunk_width, SCALE), color);
+        }
+    }
+
+    pub(super) fn toggle_chunk_state_heatmap(
+        input: ActionInput,
+        mut next_state: ResMut<NextState<ChunkStateHeatmapState>>,
+        cur_state: Res<State<ChunkStateHeatmapState>>,
+    ) {
+        if input.just_pressed(InputAction::ToggleChunkStateHeatmap) {
+            next_state.set(match **cur_state {
+                ChunkStateHeatmapState::Enabled => ChunkStateHeatmapState::Disabled,
+                ChunkStateHeatmapState::Disabled => ChunkStateHeatmapState::Enabled,
+            })
+        }
+    }
+
+    /// Toggles the [Wireframe] component of the chunk the camera is inside.
+    /// This is only visible while the global wireframe is disabled.
+    pub(super) fn toggle_chunk_wireframe(
+        mut commands: Commands,
+        input: ActionInput,
+        camera_query: Query<&Transform, With<Camera3d>>,
+        wireframe_query: Query<(), With<Wireframe>>,
+        voxel_chunk_map: Res<VoxelChunkMap>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        if !input.just_pressed(InputAction::ToggleChunkWireframe) {
+            return;
+        }
+
+        let Ok(camera_transform) = camera_query.get_single() else {
+            return;
+        };
+
+        let (chunk_pos, _) = VoxelChunkPosition::world_to_local(
+            camera_transform.translation.round().as_ivec3(),
+            &chunk_width,
+        );
+
+        let Some(chunk_entity) = voxel_chunk_map.0.get(&chunk_pos) else {
+            return;
+        };
+
+        if wireframe_query.contains(*chunk_entity) {
+            commands.entity(*chunk_entity).remove::<Wireframe>();
+        } else {
+            commands.entity(*chunk_entity).insert(Wireframe);
+        }
+    }
+
+    /// Draws a sphere for the load radius, and one for the load radius plus the unload margin, of every [RenderDistance].
+    ///
+    /// Chunks are loaded and unloaded based on the distance between chunk positions, so the spheres are centered
+    /// on the chunk the observer is inside, not the observer itself.
+    pub(super) fn render_distance_gizmo(
+        mut gizmos: Gizmos,
+        render_dist_query: Query<(&Transform, &RenderDistance)>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let cw = chunk_width.0 as f32;
+
+        for (transform, render_distance) in &render_dist_query {
+            let origin_chunk_pos = transform.translation.as_chunk_pos(&chunk_width);
+            let center = chunk_aabb_transform(&origin_chunk_pos, &chunk_width, 1.0).translation;
+
+            gizmos.sphere(
+                center,
+                Quat::IDENTITY,
+                render_distance.val as f32 * cw,
+                LOAD_RADIUS_COLOR,
+            );
+            gizmos.sphere(
+                center,
+                Quat::IDENTITY,
+                (render_distance.val + render_distance.unload_margin) as f32 * cw,
+                UNLOAD_RADIUS_COLOR,
+            );
+        }
+    }
+
+    pub(super) fn toggle_render_distance_gizmo(
+        input: ActionInput,
+        mut next_state: ResMut<NextState<RenderDistanceGizmoState>>,
+        cur_state: Res<State<RenderDistanceGizmoState>>,
+    ) {
+        if input.just_pressed(InputAction::ToggleRenderDistanceGizmo) {
+            next_state.set(match **cur_state {
+                RenderDistanceGizmoState::Enabled => RenderDistanceGizmoState::Disabled,
+                RenderDistanceGizmoState::Disabled => RenderDistanceGizmoState::Enabled,
+            })
+        }
+    }
+
+    /// Draws the vertex normals of the targeted chunk's mesh, colored by the [CubeFace] they belong to.
+    ///
+    /// Triangles whose winding order doesn't agree with their vertex normals are outlined as well,
+    /// since those are either culled, or lit from the wrong side.
+    pub(super) fn mesh_normals(
+        mut gizmos: Gizmos,
+        chunk_query: Query<(&Handle<Mesh>, &GlobalTransform)>,
+        meshes: Res<Assets<Mesh>>,
+        targeted_voxel: Res<TargetedVoxel>,
+        voxel_chunk_map: Res<VoxelChunkMap>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let Some(hit) = &targeted_voxel.0 else {
+            return;
+        };
+
+        let (chunk_pos, _) = VoxelChunkPosition::world_to_local(hit.voxel_pos, &chunk_width);
+        let Some((mesh_handle, transform)) = voxel_chunk_map
+            .0
+            .get(&chunk_pos)
+            .and_then(|entity| chunk_query.get(*entity).ok())
+        else {
+            return;
+        };
+        let Some(mesh) = meshes.get(mesh_handle) else {
+            return;
+        };
+
+        let (
+            Some(VertexAttributeValues::Float32x3(positions)),
+            Some(VertexAttributeValues::Float32x3(normals)),
+        ) = (
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+        )
+        else {
+            return;
+        };
+
+        let positions: Vec<Vec3> = positions
+            .iter()
+            .map(|position| transform.transform_point(Vec3::from(*position)))
+            .collect();
+        let normals: Vec<Vec3> = normals.iter().map(|normal| Vec3::from(*normal)).collect();
+
+        for (position, normal) in positions.iter().zip(&normals) {
+            let rounded = normal.round().as_ivec3();
+            let color = if rounded.abs().dot(IVec3::ONE) == 1 {
+                cube_face_color(&CubeFace::from_ivec3(rounded))
+            } else {
+                OFF_AXIS_NORMAL_COLOR
+            };
+
+            gizmos.ray(*position, *normal * MESH_NORMAL_LENGTH, color);
+        }
+
+        let Some(indices) = mesh.indices() else {
+            return;
+        };
+        let indices: Vec<usize> = indices.iter().collect();
+
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| positions[i]);
+
+            // Bevy treats counter-clockwise triangles as front facing.
+            let winding_normal = (b - a).cross(c - a);
+            if winding_normal.dot(normals[triangle[0]]) > 0.0 {
+                continue;
+            }
+
+            gizmos.linestrip([a, b, c, a], WRONG_WINDING_COLOR);
+        }
+    }
+
+    pub(super) fn toggle_mesh_normals(
+        input: ActionInput,
+        mut next_state: ResMut<NextState<MeshNormalsState>>,
+        cur_state: Res<State<MeshNormalsState>>,
+    ) {
+        if input.just_pressed(InputAction::ToggleMeshNormals) {
+            next_state.set(match **cur_state {
+                MeshNormalsState::Enabled => MeshNormalsState::Disabled,
+                MeshNormalsState::Disabled => MeshNormalsState::Enabled,
+            })
+        }
+    }
+
+    /// Outlines every chunk position in the load and unload queues, so chunks stuck in them are easy to spot.
+    pub(super) fn chunk_queue_gizmo(
+        mut gizmos: Gizmos,
+        chunk_load_queue: Res<ChunkLoadQueue>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        // Slightly smaller than the heatmap, so both can be enabled at once.
+        const SCALE: f32 = 0.8;
+
+        for chunk_pos in chunk_load_queue.queued_loads() {
+            gizmos.cuboid(
+                chunk_aabb_transform(chunk_pos, &chunk_width, SCALE),
+                LOAD_QUEUE_COLOR,
+            );
+        }
+
+        for chunk_pos in chunk_load_queue.queued_unloads() {
+            gizmos.cuboid(
+                chunk_aabb_transform(chunk_pos, &chunk_width, SCALE),
+                UNLOAD_QUEUE_COLOR,
+            );
+        }
+    }
+
+    pub(super) fn toggle_chunk_queue_gizmo(
+        input: ActionInput,
+        mut next_state: ResMut<NextState<ChunkQueueGizmoState>>,
+        cur_state: Res<State<ChunkQueueGizmoState>>,
+    ) {
+        if input.just_pressed(InputAction::ToggleChunkQueueGizmo) {
+            next_state.set(match **cur_state {
+                ChunkQueueGizmoState::Enabled => ChunkQueueGizmoState::Disabled,
+                ChunkQueueGizmoState::Disabled => ChunkQueueGizmoState::Enabled,
+            })
+        }
+    }
+
+    /// Outlines every voxel that was ticked since the last frame.
+    pub(super) fn block_tick_gizmo(mut gizmos: Gizmos, mut block_ticks: EventReader<BlockTick>) {
+        for block_tick in block_ticks.read() {
+            let color = match block_tick.kind {
+                BlockTickKind::Scheduled => SCHEDULED_TICK_COLOR,
+                BlockTickKind::Random => RANDOM_TICK_COLOR,
+            };
+
+            gizmos.cuboid(
+                Transform::from_translation(block_tick.voxel_pos.as_vec3())
+                    .with_scale(Vec3::splat(1.05)),
+                color,
+            );
+        }
+    }
+
+    pub(super) fn toggle_block_tick_gizmo(
+        input: ActionInput,
+        mut next_state: ResMut<NextState<BlockTickGizmoState>>,
+        cur_state: Res<State<BlockTickGizmoState>>,
+    ) {
+        if input.just_pressed(InputAction::ToggleBlockTickGizmo) {
+            next_state.set(match **cur_state {
+                BlockTickGizmoState::Enabled => BlockTickGizmoState::Disabled,
+                BlockTickGizmoState::Disabled => BlockTickGizmoState::Enabled,
+            })
+        }
+    }
 }
@@ -0,0 +1,747 @@
+use bevy::{
+    asset::load_internal_asset,
+    core_pipeline::{
+        core_3d::{self, Opaque3d},
+        tonemapping::{DebandDither, Tonemapping},
+    },
+    ecs::{
+        query::QueryItem,
+        system::{
+            lifetimeless::{Read, SRes},
+            SystemParamItem,
+        },
+    },
+    pbr::{MeshPipeline, MeshPipelineKey, RenderMaterials, SetMeshViewBindGroup},
+    prelude::*,
+    render::{
+        mesh::{Indices, MeshVertexBufferLayout, VertexAttributeValues},
+        primitives::{Aabb, Frustum},
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, ViewNode, ViewNodeRunner,
+        },
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItem, RenderCommand, RenderCommandResult,
+            RenderPhase, SetItemPipeline, TrackedRenderPass,
+        },
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        settings::WgpuFeatures,
+        view::ExtractedView,
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+
+use super::{
+    chunk_material::{ChunkMaterial, ATTRIBUTE_PACKED_VERTEX, CHUNK_SHADER_HANDLE},
+    generation::{ChunkMeshSection, VoxelChunk},
+    render::ChunkMaterials,
+    VoxelConfig,
+};
+
+const CHUNK_CULLING_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x766f_7865_6c5f_6375_6c6c_696e_675f_7368);
+const CHUNK_CULLING_NODE: &str = "voxel_chunk_culling";
+/// The size of a `DrawIndexedIndirect`, five words.
+const DRAW_INDEXED_INDIRECT_SIZE: u64 = 20;
+const CULLING_WORKGROUP_SIZE: u32 = 64;
+
+/// This plugin adds the experimental GPU culling path for chunks, turned on with [VoxelConfig::gpu_culling].
+///
+/// The opaque meshes of all chunks are merged into shared buffers, and every chunk gets an indirect draw. Every frame,
+/// a compute shader tests the bounds of every chunk against the view frustum and zeroes the draws of the chunks outside
+/// of it, and the chunks are drawn with a single multi-draw call, so submitting them doesn't scale with the number of
+/// loaded chunks. Adapters without multi-draw support get one indirect draw per chunk.
+///
+/// Only chunks meshed with [packed vertices](VoxelConfig::packed_vertices) take this path. Chunks on it don't cast
+/// shadows, and aren't occlusion culled. The other sections of the chunk are drawn as usual.
+pub(super) struct GpuChunkCullingPlugin;
+
+impl Plugin for GpuChunkCullingPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            CHUNK_CULLING_SHADER_HANDLE,
+            "shaders/chunk_culling.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.init_resource::<CulledChunkGeometry>()
+            .add_systems(PostUpdate, systems::update_culled_chunks);
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<CulledChunkBuffers>()
+            .init_resource::<CulledChunkViews>()
+            .init_resource::<SpecializedMeshPipelines<ChunkCullingPipeline>>()
+            .add_render_command::<Opaque3d, DrawCulledChunks>()
+            .add_systems(ExtractSchedule, systems::extract_culled_chunks)
+            .add_systems(
+                Render,
+                (
+                    systems::prepare_culled_chunk_buffers.in_set(RenderSet::PrepareAssets),
+                    systems::queue_culled_chunks.in_set(RenderSet::QueueMeshes),
+                    systems::prepare_culled_chunk_views.in_set(RenderSet::PrepareBindGroups),
+                ),
+            )
+            .add_render_graph_node::<ViewNodeRunner<ChunkCullingNode>>(
+                core_3d::graph::NAME,
+                CHUNK_CULLING_NODE,
+            )
+            .add_render_graph_edges(
+                core_3d::graph::NAME,
+                &[
+                    core_3d::graph::node::END_PREPASSES,
+                    CHUNK_CULLING_NODE,
+                    core_3d::graph::node::START_MAIN_PASS,
+                ],
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<ChunkCullingPipeline>();
+        }
+    }
+}
+
+/// Marker component for chunks whose opaque mesh is drawn by the GPU culling path. They don't have a
+/// [ChunkMaterial], so they aren't drawn the usual way.
+#[derive(Component)]
+struct GpuCulledChunk;
+
+/// The merged opaque meshes of the chunks on the GPU culling path, rebuilt whenever one of them changes.
+#[derive(Resource, Default, Clone)]
+struct CulledChunkGeometry {
+    vertices: Vec<[u32; 2]>,
+    /// The index into `chunks` of every vertex.
+    vertex_chunks: Vec<u32>,
+    /// The indices of every chunk, relative to its first vertex.
+    indices: Vec<u32>,
+    chunks: Vec<CulledChunk>,
+}
+
+#[derive(Clone, Copy)]
+struct CulledChunk {
+    origin: Vec3,
+    /// The bounds of the chunk, in world space.
+    min: Vec3,
+    max: Vec3,
+    first_index: u32,
+    index_count: u32,
+    base_vertex: u32,
+}
+
+/// What the render world needs from the main world. The geometry is only extracted in the frames it changed.
+#[derive(Resource)]
+struct ExtractedCulledChunks {
+    geometry: Option<CulledChunkGeometry>,
+    material: AssetId<ChunkMaterial>,
+}
+
+/// The buffers of the merged chunk geometry. [None] while no chunk takes the GPU culling path.
+#[derive(Resource, Default)]
+struct CulledChunkBuffers(Option<CulledChunkGeometryBuffers>);
+
+struct CulledChunkGeometryBuffers {
+    vertices: Buffer,
+    vertex_chunks: Buffer,
+    indices: Buffer,
+    chunks: Buffer,
+    /// Every draw with an instance count of one, copied into the draws of every view before culling them.
+    draws: Buffer,
+    chunk_count: u32,
+    chunks_bind_group: BindGroup,
+}
+
+/// The draws of every view, kept across frames so they're only reallocated when there are more chunks.
+#[derive(Resource, Default)]
+struct CulledChunkViews(HashMap<Entity, CulledChunkViewBuffers>);
+
+struct CulledChunkViewBuffers {
+    draws: Buffer,
+    capacity: u32,
+    frustum: Buffer,
+}
+
+/// The culling state of a view for this frame.
+#[derive(Component)]
+struct CulledChunkView {
+    draws: Buffer,
+    cull_bind_group: BindGroup,
+}
+
+#[derive(Resource)]
+struct ChunkCullingPipeline {
+    mesh_pipeline: MeshPipeline,
+    material_layout: BindGroupLayout,
+    chunks_layout: BindGroupLayout,
+    cull_layout: BindGroupLayout,
+    cull_pipeline: CachedComputePipelineId,
+    vertex_layout: MeshVertexBufferLayout,
+    multi_draw: bool,
+}
+
+impl FromWorld for ChunkCullingPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let storage_entry = |binding, visibility, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let chunks_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("culled_chunks_layout"),
+            entries: &[storage_entry(0, ShaderStages::VERTEX, true)],
+        });
+        let cull_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("chunk_culling_layout"),
+            entries: &[
+                storage_entry(0, ShaderStages::COMPUTE, true),
+                storage_entry(1, ShaderStages::COMPUTE, false),
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let material_layout = ChunkMaterial::bind_group_layout(render_device);
+        let multi_draw = render_device
+            .features()
+            .contains(WgpuFeatures::MULTI_DRAW_INDIRECT);
+        let vertex_layout = Mesh::new(PrimitiveTopology::TriangleList)
+            .with_inserted_attribute(ATTRIBUTE_PACKED_VERTEX, Vec::<[u32; 2]>::new())
+            .get_mesh_vertex_buffer_layout();
+
+        let cull_pipeline =
+            world
+                .resource::<PipelineCache>()
+                .queue_compute_pipeline(ComputePipelineDescriptor {
+                    label: Some("chunk_culling_pipeline".into()),
+                    layout: vec![cull_layout.clone()],
+                    push_constant_ranges: Vec::new(),
+                    shader: CHUNK_CULLING_SHADER_HANDLE,
+                    shader_defs: Vec::new(),
+                    entry_point: "cull".into(),
+                });
+
+        Self {
+            mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+            material_layout,
+            chunks_layout,
+            cull_layout,
+            cull_pipeline,
+            vertex_layout,
+            multi_draw,
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for ChunkCullingPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+
+        descriptor.label = Some("culled_chunk_pipeline".into());
+        descriptor.vertex.shader = CHUNK_SHADER_HANDLE;
+        descriptor.vertex.shader_defs.push("PACKED_VERTICES".into());
+        descriptor.vertex.shader_defs.push("GPU_CULLED".into());
+        descriptor.vertex.buffers = vec![
+            layout.get_layout(&[ATTRIBUTE_PACKED_VERTEX.at_shader_location(0)])?,
+            VertexBufferLayout {
+                array_stride: VertexFormat::Uint32.size(),
+                step_mode: VertexStepMode::Vertex,
+                attributes: vec![VertexAttribute {
+                    format: VertexFormat::Uint32,
+                    offset: 0,
+                    shader_location: 1,
+                }],
+            },
+        ];
+        if let Some(fragment) = &mut descriptor.fragment {
+            fragment.shader = CHUNK_SHADER_HANDLE;
+            fragment.shader_defs.push("PACKED_VERTICES".into());
+            fragment.shader_defs.push("GPU_CULLED".into());
+        }
+        // The view layout stays, the mesh layout is replaced by the material and chunks.
+        descriptor.layout.truncate(1);
+        descriptor.layout.push(self.material_layout.clone());
+        descriptor.layout.push(self.chunks_layout.clone());
+        descriptor.primitive.cull_mode = Some(Face::Back);
+
+        Ok(descriptor)
+    }
+}
+
+/// Culls the chunks of a view before the main pass draws them.
+#[derive(Default)]
+struct ChunkCullingNode;
+
+impl ViewNode for ChunkCullingNode {
+    type ViewQuery = &'static CulledChunkView;
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        view: QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some(buffers) = &world.resource::<CulledChunkBuffers>().0 else {
+            return Ok(());
+        };
+        let culling_pipeline = world.resource::<ChunkCullingPipeline>();
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_compute_pipeline(culling_pipeline.cull_pipeline)
+        else {
+            return Ok(());
+        };
+
+        let encoder = render_context.command_encoder();
+        encoder.copy_buffer_to_buffer(
+            &buffers.draws,
+            0,
+            &view.draws,
+            0,
+            buffers.chunk_count as u64 * DRAW_INDEXED_INDIRECT_SIZE,
+        );
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("chunk_culling_pass"),
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &view.cull_bind_group, &[]);
+        pass.dispatch_workgroups(buffers.chunk_count.div_ceil(CULLING_WORKGROUP_SIZE), 1, 1);
+
+        Ok(())
+    }
+}
+
+type DrawCulledChunks = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    DrawCulledChunkGeometry,
+);
+
+/// Draws the merged chunk geometry with the culled draws of the view.
+struct DrawCulledChunkGeometry;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawCulledChunkGeometry {
+    type Param = (
+        SRes<CulledChunkBuffers>,
+        SRes<ExtractedCulledChunks>,
+        SRes<RenderMaterials<ChunkMaterial>>,
+        SRes<ChunkCullingPipeline>,
+    );
+    type ViewWorldQuery = Read<CulledChunkView>;
+    type ItemWorldQuery = ();
+
+    fn render<'w>(
+        _item: &P,
+        view: &'w CulledChunkView,
+        _entity: (),
+        (buffers, extracted, materials, culling_pipeline): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(buffers) = &buffers.into_inner().0 else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(material) = materials.into_inner().0.get(&extracted.material) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(1, &material.bind_group, &[]);
+        pass.set_bind_group(2, &buffers.chunks_bind_group, &[]);
+        pass.set_vertex_buffer(0, buffers.vertices.slice(..));
+        pass.set_vertex_buffer(1, buffers.vertex_chunks.slice(..));
+        pass.set_index_buffer(buffers.indices.slice(..), 0, IndexFormat::Uint32);
+
+        if culling_pipeline.multi_draw {
+            pass.multi_draw_indexed_indirect(&view.draws, 0, buffers.chunk_count);
+        } else {
+            for chunk in 0..buffers.chunk_count as u64 {
+                pass.draw_indexed_indirect(&view.draws, chunk * DRAW_INDEXED_INDIRECT_SIZE);
+            }
+        }
+
+        RenderCommandResult::Success
+    }
+}
+
+/// The bytes of words, as the GPU reads them.
+fn words_to_bytes(words: impl IntoIterator<Item = u32>) -> Vec<u8> {
+    words.into_iter().flat_map(u32::to_le_bytes).collect()
+}
+
+/// The tonemapping bits of the [MeshPipelineKey], like the material pipeline sets them.
+fn tonemapping_pipeline_key(tonemapping: Tonemapping) -> MeshPipelineKey {
+    match tonemapping {
+        Tonemapping::None => MeshPipelineKey::TONEMAP_METHOD_NONE,
+        Tonemapping::Reinhard => MeshPipelineKey::TONEMAP_METHOD_REINHARD,
+        Tonemapping::ReinhardLuminance => MeshPipelineKey::TONEMAP_METHOD_REINHARD_LUMINANCE,
+        Tonemapping::AcesFitted => MeshPipelineKey::TONEMAP_METHOD_ACES_FITTED,
+        Tonemapping::AgX => MeshPipelineKey::TONEMAP_METHOD_AGX,
+        Tonemapping::SomewhatBoringDisplayTransform => {
+            MeshPipelineKey::TONEMAP_METHOD_SOMEWHAT_BORING_DISPLAY_TRANSFORM
+        }
+        Tonemapping::TonyMcMapface => MeshPipelineKey::TONEMAP_METHOD_TONY_MC_MAPFACE,
+        Tonemapping::BlenderFilmic => MeshPipelineKey::TONEMAP_METHOD_BLENDER_FILMIC,
+    }
+}
+
+mod systems {
+    use super::*;
+
+    type CulledChunkQuery<'w, 's> = Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static Aabb,
+            &'static Handle<Mesh>,
+            Has<GpuCulledChunk>,
+        ),
+        With<VoxelChunk>,
+    >;
+
+    type OpaqueViewQuery<'w, 's> = Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static ExtractedView,
+            Option<&'static Tonemapping>,
+            Option<&'static DebandDither>,
+            &'static mut RenderPhase<Opaque3d>,
+        ),
+    >;
+
+    /// Moves chunks with packed opaque meshes onto the GPU culling path while it's turned on, and back off it
+    /// otherwise, and rebuilds the merged geometry when any of their meshes changed.
+    pub(super) fn update_culled_chunks(
+        mut commands: Commands,
+        config: Res<VoxelConfig>,
+        chunk_materials: Res<ChunkMaterials>,
+        meshes: Res<Assets<Mesh>>,
+        chunk_query: CulledChunkQuery,
+        changed_query: Query<(), (With<GpuCulledChunk>, Changed<Handle<Mesh>>)>,
+        mut removed_chunks: RemovedComponents<GpuCulledChunk>,
+        mut geometry: ResMut<CulledChunkGeometry>,
+    ) {
+        // Raymarched chunks aren't drawn with their meshes at all.
+        let is_packed = |mesh: &Handle<Mesh>| {
+            config.gpu_culling
+                && !config.raymarch_chunks
+                && meshes
+                    .get(mesh)
+                    .is_some_and(|mesh| mesh.attribute(ATTRIBUTE_PACKED_VERTEX).is_some())
+        };
+
+        let mut changed = removed_chunks.read().count() > 0 || !changed_query.is_empty();
+        for (chunk_entity, _, _, mesh, culled) in &chunk_query {
+            let packed = is_packed(mesh);
+            if packed == culled {
+                continue;
+            }
+
+            changed = true;
+            if packed {
+                commands
+                    .entity(chunk_entity)
+                    .insert(GpuCulledChunk)
+                    .remove::<Handle<ChunkMaterial>>();
+            } else if config.raymarch_chunks {
+                // The raymarching gives the material back once it's turned off.
+                commands.entity(chunk_entity).remove::<GpuCulledChunk>();
+            } else {
+                commands
+                    .entity(chunk_entity)
+                    .remove::<GpuCulledChunk>()
+                    .insert(chunk_materials.get(ChunkMeshSection::Opaque));
+            }
+        }
+        if !changed {
+            return;
+        }
+
+        let _span = info_span!("merge_culled_chunks").entered();
+
+        let geometry = geometry.as_mut();
+        geometry.vertices.clear();
+        geometry.vertex_chunks.clear();
+        geometry.indices.clear();
+        geometry.chunks.clear();
+
+        for (_, transform, aabb, mesh, _) in &chunk_query {
+            let Some(mesh) = meshes.get(mesh).filter(|_| is_packed(mesh)) else {
+                continue;
+            };
+            let (Some(VertexAttributeValues::Uint32x2(vertices)), Some(Indices::U32(indices))) =
+                (mesh.attribute(ATTRIBUTE_PACKED_VERTEX), mesh.indices())
+            else {
+                continue;
+            };
+            if indices.is_empty() {
+                continue;
+            }
+
+            let chunk_index = geometry.chunks.len() as u32;
+            let origin = transform.translation;
+            geometry.chunks.push(CulledChunk {
+                origin,
+                min: origin + Vec3::from(aabb.min()),
+                max: origin + Vec3::from(aabb.max()),
+                first_index: geometry.indices.len() as u32,
+                index_count: indices.len() as u32,
+                base_vertex: geometry.vertices.len() as u32,
+            });
+            geometry.vertices.extend_from_slice(vertices);
+            let vertex_count = geometry.vertex_chunks.len() + vertices.len();
+            geometry.vertex_chunks.resize(vertex_count, chunk_index);
+            geometry.indices.extend_from_slice(indices);
+        }
+    }
+
+    pub(super) fn extract_culled_chunks(
+        mut commands: Commands,
+        geometry: Extract<Res<CulledChunkGeometry>>,
+        chunk_materials: Extract<Res<ChunkMaterials>>,
+    ) {
+        commands.insert_resource(ExtractedCulledChunks {
+            geometry: geometry.is_changed().then(|| geometry.clone()),
+            material: chunk_materials.get(ChunkMeshSection::Opaque).id(),
+        });
+    }
+
+    /// Uploads the merged chunk geometry when it changed.
+    pub(super) fn prepare_culled_chunk_buffers(
+        extracted: Res<ExtractedCulledChunks>,
+        mut buffers: ResMut<CulledChunkBuffers>,
+        culling_pipeline: Res<ChunkCullingPipeline>,
+        render_device: Res<RenderDevice>,
+    ) {
+        let Some(geometry) = &extracted.geometry else {
+            return;
+        };
+        if geometry.chunks.is_empty() {
+            buffers.0 = None;
+            return;
+        }
+
+        let buffer = |label, contents: Vec<u8>, usage| {
+            render_device.create_buffer_with_data(&BufferInitDescriptor {
+                label: Some(label),
+                contents: &contents,
+                usage,
+            })
+        };
+
+        let chunks = buffer(
+            "culled_chunks",
+            words_to_bytes(geometry.chunks.iter().flat_map(|chunk| {
+                [chunk.origin, chunk.min, chunk.max]
+                    .into_iter()
+                    .flat_map(|vector| vector.extend(0.0).to_array().map(f32::to_bits))
+            })),
+            BufferUsages::STORAGE,
+        );
+        let chunks_bind_group = render_device.create_bind_group(
+            "culled_chunks_bind_group",
+            &culling_pipeline.chunks_layout,
+            &BindGroupEntries::single(chunks.as_entire_binding()),
+        );
+
+        buffers.0 = Some(CulledChunkGeometryBuffers {
+            vertices: buffer(
+                "culled_chunk_vertices",
+                words_to_bytes(geometry.vertices.iter().flatten().copied()),
+                BufferUsages::VERTEX,
+            ),
+            vertex_chunks: buffer(
+                "culled_chunk_vertex_chunks",
+                words_to_bytes(geometry.vertex_chunks.iter().copied()),
+                BufferUsages::VERTEX,
+            ),
+            indices: buffer(
+                "culled_chunk_indices",
+                words_to_bytes(geometry.indices.iter().copied()),
+                BufferUsages::INDEX,
+            ),
+            draws: buffer(
+                "culled_chunk_draws",
+                words_to_bytes(geometry.chunks.iter().flat_map(|chunk| {
+                    [
+                        chunk.index_count,
+                        1,
+                        chunk.first_index,
+                        chunk.base_vertex,
+                        0,
+                    ]
+                })),
+                BufferUsages::COPY_SRC,
+            ),
+            chunks,
+            chunk_count: geometry.chunks.len() as u32,
+            chunks_bind_group,
+        });
+    }
+
+    /// Adds the merged chunk geometry to the opaque phase of every 3D view, as a single item.
+    pub(super) fn queue_culled_chunks(
+        buffers: Res<CulledChunkBuffers>,
+        draw_functions: Res<DrawFunctions<Opaque3d>>,
+        culling_pipeline: Res<ChunkCullingPipeline>,
+        mut pipelines: ResMut<SpecializedMeshPipelines<ChunkCullingPipeline>>,
+        pipeline_cache: Res<PipelineCache>,
+        msaa: Res<Msaa>,
+        mut view_query: OpaqueViewQuery,
+    ) {
+        if buffers.0.is_none() {
+            return;
+        }
+        let draw_function = draw_functions.read().id::<DrawCulledChunks>();
+
+        for (view_entity, view, tonemapping, dither, mut opaque_phase) in &mut view_query {
+            let mut key = MeshPipelineKey::from_msaa_samples(msaa.samples())
+                | MeshPipelineKey::from_hdr(view.hdr)
+                | MeshPipelineKey::from_primitive_topology(PrimitiveTopology::TriangleList);
+            if view.projection.w_axis.w == 1.0 {
+                key |= MeshPipelineKey::VIEW_PROJECTION_ORTHOGRAPHIC;
+            } else {
+                key |= MeshPipelineKey::VIEW_PROJECTION_PERSPECTIVE;
+            }
+            if !view.hdr {
+                if let Some(tonemapping) = tonemapping {
+                    key |=
+                        MeshPipelineKey::TONEMAP_IN_SHADER | tonemapping_pipeline_key(*tonemapping);
+                }
+                if let Some(DebandDither::Enabled) = dither {
+                    key |= MeshPipelineKey::DEBAND_DITHER;
+                }
+            }
+
+            let pipeline = match pipelines.specialize(
+                &pipeline_cache,
+                &culling_pipeline,
+                key,
+                &culling_pipeline.vertex_layout,
+            ) {
+                Ok(pipeline) => pipeline,
+                Err(error) => {
+                    error!("Failed to specialize the culled chunk pipeline: {error}");
+                    continue;
+                }
+            };
+
+            // The item draws every chunk at once, so it's drawn for the view itself.
+            opaque_phase.add(Opaque3d {
+                distance: 0.0,
+                pipeline,
+                entity: view_entity,
+                draw_function,
+                batch_range: 0..1,
+                dynamic_offset: None,
+            });
+        }
+    }
+
+    /// Gives every view its draws to cull, and writes its frustum.
+    pub(super) fn prepare_culled_chunk_views(
+        mut commands: Commands,
+        buffers: Res<CulledChunkBuffers>,
+        mut views: ResMut<CulledChunkViews>,
+        culling_pipeline: Res<ChunkCullingPipeline>,
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        view_query: Query<(Entity, &Frustum), With<RenderPhase<Opaque3d>>>,
+    ) {
+        let Some(buffers) = &buffers.0 else {
+            views.0.clear();
+            return;
+        };
+
+        views
+            .0
+            .retain(|view_entity, _| view_query.contains(*view_entity));
+
+        // Draws grow with some room, so streaming chunks in doesn't reallocate them every frame.
+        let create_draws = |capacity: u32| {
+            render_device.create_buffer(&BufferDescriptor {
+                label: Some("culled_chunk_view_draws"),
+                size: capacity as u64 * DRAW_INDEXED_INDIRECT_SIZE,
+                usage: BufferUsages::STORAGE | BufferUsages::INDIRECT | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let capacity = buffers.chunk_count.next_power_of_two();
+
+        for (view_entity, frustum) in &view_query {
+            let view_buffers =
+                views
+                    .0
+                    .entry(view_entity)
+                    .or_insert_with(|| CulledChunkViewBuffers {
+                        draws: create_draws(capacity),
+                        capacity,
+                        frustum: render_device.create_buffer(&BufferDescriptor {
+                            label: Some("culled_chunk_view_frustum"),
+                            size: 6 * 16,
+                            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                            mapped_at_creation: false,
+                        }),
+                    });
+            if view_buffers.capacity < buffers.chunk_count {
+                view_buffers.draws = create_draws(capacity);
+                view_buffers.capacity = capacity;
+            }
+
+            render_queue.write_buffer(
+                &view_buffers.frustum,
+                0,
+                &words_to_bytes(
+                    frustum
+                        .half_spaces
+                        .iter()
+                        .flat_map(|half_space| half_space.normal_d().to_array().map(f32::to_bits)),
+                ),
+            );
+
+            let cull_bind_group = render_device.create_bind_group(
+                "chunk_culling_bind_group",
+                &culling_pipeline.cull_layout,
+                &BindGroupEntries::sequential((
+                    buffers.chunks.as_entire_binding(),
+                    view_buffers.draws.as_entire_binding(),
+                    view_buffers.frustum.as_entire_binding(),
+                )),
+            );
+
+            commands.entity(view_entity).insert(CulledChunkView {
+                draws: view_buffers.draws.clone(),
+                cull_bind_group,
+            });
+        }
+    }
+}
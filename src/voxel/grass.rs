@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use super::tick::BlockTickSet;
+
+/// This plugin makes grass spread onto exposed dirt around it, and decay back to dirt when it's covered.
+/// This happens on random block ticks, so it's slow and spread out over the loaded chunks.
+pub(super) struct VoxelGrassPlugin;
+
+impl Plugin for VoxelGrassPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, systems::spread_grass.after(BlockTickSet));
+    }
+}
+
+mod systems {
+    use rand::Rng;
+
+    use crate::voxel::{
+        tick::{BlockTick, BlockTickKind},
+        world::VoxelWorld,
+        Voxel,
+    };
+
+    use super::*;
+
+    pub(super) fn spread_grass(
+        mut block_ticks: EventReader<BlockTick>,
+        mut voxel_world: VoxelWorld,
+    ) {
+        let mut rng = rand::thread_rng();
+
+        for block_tick in block_ticks.read() {
+            if block_tick.kind != BlockTickKind::Random {
+                continue;
+            }
+
+            let voxel_pos = block_tick.voxel_pos;
+            if voxel_world.get_block(voxel_pos) != Some(Voxel::GRASS) {
+                continue;
+            }
+
+            if !is_exposed(&voxel_world, voxel_pos) {
+                voxel_world.set_block(voxel_pos, Voxel::DIRT);
+                continue;
+            }
+
+            // Grass can spread to dirt one voxel around it horizontally, from one voxel above to three voxels below.
+            let target_pos = voxel_pos
+                + IVec3::new(
+                    rng.gen_range(-1..=1),
+                    rng.gen_range(-3..=1),
+                    rng.gen_range(-1..=1),
+                );
+
+            if voxel_world.get_block(target_pos) == Some(Voxel::DIRT)
+                && is_exposed(&voxel_world, target_pos)
+            {
+                voxel_world.set_block(target_pos, Voxel::GRASS);
+            }
+        }
+    }
+
+    /// Grass needs the voxel above it to be air. Unloaded voxels count as exposed, so grass at the top of the loaded
+    /// area doesn't decay.
+    fn is_exposed(voxel_world: &VoxelWorld, voxel_pos: IVec3) -> bool {
+        !voxel_world
+            .get_block(voxel_pos + IVec3::Y)
+            .is_some_and(|above| above != Voxel::AIR)
+    }
+}
@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+
+use super::{
+    game_mode::GameMode,
+    physics::{TerrainImpact, GRAVITY},
+};
+
+/// How much health the player has when it's full.
+const PLAYER_MAX_HEALTH: f32 = 20.0;
+/// How much health a heart of the hearts HUD stands for.
+const HEALTH_PER_HEART: f32 = 2.0;
+/// How far entities can fall without getting hurt, in voxels.
+const SAFE_FALL_HEIGHT: f32 = 3.0;
+/// How much damage a fall does for every voxel fallen past [SAFE_FALL_HEIGHT].
+const FALL_DAMAGE_PER_VOXEL: f32 = 1.0;
+const HEART_SIZE: f32 = 16.0;
+const FULL_HEART_COLOR: Color = Color::rgb(0.85, 0.1, 0.1);
+const HALF_HEART_COLOR: Color = Color::rgb(0.5, 0.1, 0.1);
+const EMPTY_HEART_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+
+/// This plugin gives the player [Health], and shows it as a row of hearts above the hotbar. The player can only be
+/// hurt outside of [GameMode::Creative], so the hearts are hidden there.
+///
+/// Entities with [Health] are hurt by sending [Damage], and a [Death] is sent once their health runs out. Entities
+/// with a [TerrainCollider](super::physics::TerrainCollider) and [Health] take fall damage when they hit the ground
+/// too fast.
+pub(super) struct VoxelHealthPlugin;
+
+impl Plugin for VoxelHealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Damage>()
+            .add_event::<Death>()
+            .add_systems(Startup, systems::setup_hearts_ui)
+            .add_systems(
+                Update,
+                (
+                    systems::add_player_health,
+                    systems::apply_fall_damage,
+                    systems::apply_damage,
+                    systems::log_deaths,
+                    systems::update_hearts_ui,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// How much more damage an entity can take before it dies.
+#[derive(Component, Debug, Clone, Copy)]
+pub(super) struct Health {
+    pub(super) current: f32,
+    pub(super) max: f32,
+}
+
+impl Health {
+    /// Full health of `max`.
+    pub(super) fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub(super) fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// Event for hurting an entity with [Health]. Damage to entities that are already dead is ignored.
+#[derive(Event, Debug, Clone, Copy)]
+pub(super) struct Damage {
+    pub(super) entity: Entity,
+    pub(super) amount: f32,
+    pub(super) cause: DamageCause,
+}
+
+/// What hurt an entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum DamageCause {
+    /// Hitting the ground too fast.
+    Fall,
+}
+
+/// Event sent when the [Health] of an entity runs out, with the cause of the damage that killed it.
+#[derive(Event, Debug, Clone, Copy)]
+pub(super) struct Death {
+    pub(super) entity: Entity,
+    pub(super) cause: DamageCause,
+}
+
+/// How much damage hitting the ground with `velocity` does. The speed is turned back into the height it takes to
+/// fall that fast, so every voxel fallen does the same damage.
+fn fall_damage(velocity: Vec3) -> f32 {
+    let fall_height = velocity.y * velocity.y / (2.0 * GRAVITY);
+    ((fall_height - SAFE_FALL_HEIGHT) * FALL_DAMAGE_PER_VOXEL)
+        .floor()
+        .max(0.0)
+}
+
+/// Marker component for the row of hearts of the hearts HUD.
+#[derive(Component)]
+struct HeartsUi;
+
+/// Marker component for a heart of the hearts HUD. Holds the index of the heart, from the left.
+#[derive(Component)]
+struct HeartUi(usize);
+
+mod systems {
+    use super::*;
+
+    /// The camera is the player.
+    pub(super) fn add_player_health(
+        mut commands: Commands,
+        camera_query: Query<Entity, (With<Camera3d>, Without<Health>)>,
+    ) {
+        for entity in &camera_query {
+            commands
+                .entity(entity)
+                .insert(Health::new(PLAYER_MAX_HEALTH));
+        }
+    }
+
+    pub(super) fn apply_fall_damage(
+        mut impacts: EventReader<TerrainImpact>,
+        mut damage: EventWriter<Damage>,
+        health_query: Query<(), With<Health>>,
+    ) {
+        for impact in impacts.read() {
+            let amount = fall_damage(impact.velocity);
+            if amount > 0.0 && health_query.contains(impact.entity) {
+                damage.send(Damage {
+                    entity: impact.entity,
+                    amount,
+                    cause: DamageCause::Fall,
+                });
+            }
+        }
+    }
+
+    pub(super) fn apply_damage(
+        mut damage: EventReader<Damage>,
+        mut deaths: EventWriter<Death>,
+        game_mode: Res<GameMode>,
+        mut health_query: Query<(&mut Health, Has<Camera3d>)>,
+    ) {
+        for damage in damage.read() {
+            let Ok((mut health, is_player)) = health_query.get_mut(damage.entity) else {
+                continue;
+            };
+            if health.is_dead() || (is_player && game_mode.is_creative()) {
+                continue;
+            }
+
+            health.current = (health.current - damage.amount).clamp(0.0, health.max);
+            if health.is_dead() {
+                deaths.send(Death {
+                    entity: damage.entity,
+                    cause: damage.cause,
+                });
+            }
+        }
+    }
+
+    pub(super) fn log_deaths(mut deaths: EventReader<Death>) {
+        for death in deaths.read() {
+            info!("{:?} died of {:?}", death.entity, death.cause);
+        }
+    }
+
+    pub(super) fn setup_hearts_ui(mut commands: Commands) {
+        let hearts = (PLAYER_MAX_HEALTH / HEALTH_PER_HEART).ceil() as usize;
+
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        // Just above the hotbar.
+                        bottom: Val::Px(60.0),
+                        width: Val::Percent(100.0),
+                        justify_content: JustifyContent::Center,
+                        column_gap: Val::Px(2.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                HeartsUi,
+            ))
+            .with_children(|parent| {
+                for i in 0..hearts {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Px(HEART_SIZE),
+                                height: Val::Px(HEART_SIZE),
+                                ..default()
+                            },
+                            background_color: FULL_HEART_COLOR.into(),
+                            ..default()
+                        },
+                        HeartUi(i),
+                    ));
+                }
+            });
+    }
+
+    /// Fills the hearts up to the player's health, with half a heart for health that doesn't fill a whole one.
+    pub(super) fn update_hearts_ui(
+        game_mode: Res<GameMode>,
+        player_query: Query<&Health, (With<Camera3d>, Changed<Health>)>,
+        mut hearts_query: Query<&mut Visibility, With<HeartsUi>>,
+        mut heart_query: Query<(&HeartUi, &mut BackgroundColor)>,
+    ) {
+        if game_mode.is_changed() {
+            for mut visibility in &mut hearts_query {
+                *visibility = if game_mode.is_creative() {
+                    Visibility::Hidden
+                } else {
+                    Visibility::Inherited
+                };
+            }
+        }
+
+        let Ok(health) = player_query.get_single() else {
+            return;
+        };
+
+        for (heart, mut background) in &mut heart_query {
+            let filled = health.current - heart.0 as f32 * HEALTH_PER_HEART;
+            *background = if filled >= HEALTH_PER_HEART {
+                FULL_HEART_COLOR
+            } else if filled > 0.0 {
+                HALF_HEART_COLOR
+            } else {
+                EMPTY_HEART_COLOR
+            }
+            .into();
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use bevy::{
+    pbr::NotShadowCaster,
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology, view::NoFrustumCulling},
+    utils::HashMap,
+};
+use rayon::prelude::*;
+
+use super::{
+    data::VoxelData, generation::VoxelChunkWidth, load::RenderDistance, noise::TerrainNoise,
+};
+
+/// How many voxels apart the heights of the horizon are sampled.
+const HORIZON_CELL_SIZE: i32 = 16;
+/// How far the horizon reaches past the render distance, in chunks.
+pub(super) const HORIZON_RANGE: u32 = 24;
+/// How many heights are sampled per frame, at most. Sampled heights are kept while they're in range, so every cell is
+/// only sampled once.
+const HORIZON_CELLS_PER_FRAME: usize = 256;
+/// How far the horizon is sunk below the terrain, so it doesn't poke through the loaded chunks at its inner edge.
+const HORIZON_DROP: f32 = 2.0;
+
+/// This plugin draws the terrain beyond the render distance as a coarse heightmap, so the world doesn't end at the
+/// edge of the loaded chunks.
+///
+/// The heights are sampled straight from the [TerrainNoise], see [TerrainNoise::surface_height], so edits and
+/// structures don't show up in the horizon. Clients that joined a server don't have the noise, so they have no horizon.
+pub(super) struct VoxelHorizonPlugin;
+
+impl Plugin for VoxelHorizonPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HorizonHeights>()
+            .add_systems(Startup, systems::spawn_horizon)
+            .add_systems(
+                Update,
+                (
+                    systems::sample_horizon_heights,
+                    systems::update_horizon_mesh,
+                )
+                    .chain()
+                    .run_if(resource_exists::<TerrainNoise>()),
+            );
+    }
+}
+
+/// The sampled heights of the horizon, keyed by their cell, which is [HORIZON_CELL_SIZE] voxels wide.
+#[derive(Resource, Default)]
+struct HorizonHeights {
+    /// The height of the top of the cell, and its color.
+    heights: HashMap<IVec2, (f32, Color)>,
+    /// Whether heights were added or removed since the mesh was last built.
+    changed: bool,
+}
+
+/// Marker component for the entity drawing the horizon.
+#[derive(Component)]
+struct Horizon;
+
+/// The area covered by the horizon around the camera, in voxels from the camera.
+struct HorizonRing {
+    center: Vec2,
+    inner_radius: f32,
+    outer_radius: f32,
+}
+
+impl HorizonRing {
+    fn new(camera_translation: Vec3, render_distance: &RenderDistance, chunk_width: u8) -> Self {
+        Self {
+            center: camera_translation.xz(),
+            inner_radius: (render_distance.val * chunk_width as u32) as f32,
+            outer_radius: ((render_distance.val + HORIZON_RANGE) * chunk_width as u32) as f32,
+        }
+    }
+
+    fn cell_distance(&self, cell: IVec2) -> f32 {
+        (cell.as_vec2() * HORIZON_CELL_SIZE as f32).distance(self.center)
+    }
+}
+
+/// Builds the mesh of the horizon. The empty mesh the horizon starts with still has every attribute, so it can be drawn
+/// before any heights are sampled.
+fn horizon_mesh(
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+) -> Mesh {
+    Mesh::new(PrimitiveTopology::TriangleList)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+        .with_indices(Some(Indices::U32(indices)))
+}
+
+mod systems {
+    use super::*;
+
+    pub(super) fn spawn_horizon(
+        mut commands: Commands,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+    ) {
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(horizon_mesh(Vec::new(), Vec::new(), Vec::new(), Vec::new())),
+                material: materials.add(StandardMaterial {
+                    perceptual_roughness: 1.0,
+                    ..default()
+                }),
+                ..default()
+            },
+            // The mesh is rebuilt as the camera moves, so its bounds would be out of date.
+            NoFrustumCulling,
+            NotShadowCaster,
+            Horizon,
+        ));
+    }
+
+    /// Samples the heights of the cells in range of the camera that haven't been sampled yet, closest first, and
+    /// forgets the heights that went out of range.
+    pub(super) fn sample_horizon_heights(
+        mut horizon_heights: ResMut<HorizonHeights>,
+        camera_query: Query<(&Transform, &RenderDistance), With<Camera3d>>,
+        terrain_noise: Res<TerrainNoise>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let _span = info_span!("sample_horizon_heights").entered();
+
+        // The world was regenerated, so the heights sampled so far are of the old terrain.
+        if terrain_noise.is_changed() && !terrain_noise.is_added() {
+            horizon_heights.heights.clear();
+            horizon_heights.changed = true;
+        }
+
+        let Ok((camera_transform, render_distance)) = camera_query.get_single() else {
+            return;
+        };
+        let ring = HorizonRing::new(camera_transform.translation, render_distance, chunk_width.0);
+        // Cells just inside the inner edge are sampled too, so the quads at the inner edge have all their corners.
+        let sample_range = (ring.inner_radius - 2.0 * HORIZON_CELL_SIZE as f32)..=ring.outer_radius;
+
+        let center_cell = (ring.center / HORIZON_CELL_SIZE as f32).round().as_ivec2();
+        let cell_radius = (ring.outer_radius / HORIZON_CELL_SIZE as f32).ceil() as i32;
+
+        let mut missing: Vec<(IVec2, f32)> = (-cell_radius..=cell_radius)
+            .flat_map(|z| (-cell_radius..=cell_radius).map(move |x| center_cell + IVec2::new(x, z)))
+            .filter(|cell| !horizon_heights.heights.contains_key(cell))
+            .map(|cell| (cell, ring.cell_distance(cell)))
+            .filter(|(_, distance)| sample_range.contains(distance))
+            .collect();
+        missing.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        missing.truncate(HORIZON_CELLS_PER_FRAME);
+
+        let sampled: Vec<_> = missing
+            .into_par_iter()
+            .map(|(cell, _)| {
+                let voxel_pos = cell * HORIZON_CELL_SIZE;
+                let (height, voxel) = terrain_noise.surface_height(voxel_pos.x, voxel_pos.y);
+                (cell, (height as f32 + 0.5, voxel.face_color(IVec3::Y)))
+            })
+            .collect();
+
+        let forget_distance = ring.outer_radius + 2.0 * HORIZON_CELL_SIZE as f32;
+        let heights_before = horizon_heights.heights.len();
+        horizon_heights
+            .heights
+            .retain(|cell, _| ring.cell_distance(*cell) <= forget_distance);
+
+        if !sampled.is_empty() || horizon_heights.heights.len() != heights_before {
+            horizon_heights.heights.extend(sampled);
+            horizon_heights.changed = true;
+        }
+    }
+
+    /// Rebuilds the horizon mesh when heights were sampled, or when the camera moved to another cell. Only the quads
+    /// outside of the render distance are part of it.
+    pub(super) fn update_horizon_mesh(
+        mut horizon_heights: ResMut<HorizonHeights>,
+        camera_query: Query<(&Transform, &RenderDistance), With<Camera3d>>,
+        horizon_query: Query<&Handle<Mesh>, With<Horizon>>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        chunk_width: Res<VoxelChunkWidth>,
+        mut last_camera_cell: Local<Option<(IVec2, u32)>>,
+    ) {
+        let Ok((camera_transform, render_distance)) = camera_query.get_single() else {
+            return;
+        };
+        let camera_cell = (camera_transform.translation.xz() / HORIZON_CELL_SIZE as f32)
+            .floor()
+            .as_ivec2();
+        let camera_cell = Some((camera_cell, render_distance.val));
+        if !horizon_heights.changed && *last_camera_cell == camera_cell {
+            return;
+        }
+        horizon_heights.changed = false;
+        *last_camera_cell = camera_cell;
+
+        let Some(mesh) = horizon_query
+            .get_single()
+            .ok()
+            .and_then(|handle| meshes.get_mut(handle))
+        else {
+            return;
+        };
+
+        let _span = info_span!("update_horizon_mesh").entered();
+
+        let ring = HorizonRing::new(camera_transform.translation, render_distance, chunk_width.0);
+        let heights = &horizon_heights.heights;
+        let height = |cell: IVec2| heights.get(&cell).map(|(height, _)| *height);
+
+        let mut vertex_indices = HashMap::new();
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut colors = Vec::new();
+        let mut indices = Vec::new();
+
+        for cell in heights.keys() {
+            let corners = [
+                *cell,
+                *cell + IVec2::X,
+                *cell + IVec2::Y,
+                *cell + IVec2::ONE,
+            ];
+            if corners.iter().any(|corner| !heights.contains_key(corner))
+                || ring.cell_distance(*cell) < ring.inner_radius
+            {
+                continue;
+            }
+
+            let [a, b, c, d] = corners.map(|corner| {
+                *vertex_indices.entry(corner).or_insert_with(|| {
+                    let (corner_height, color) = heights[&corner];
+                    let slope = |offset: IVec2| {
+                        height(corner + offset).unwrap_or(corner_height)
+                            - height(corner - offset).unwrap_or(corner_height)
+                    };
+                    let position = corner * HORIZON_CELL_SIZE;
+
+                    positions.push([
+                        position.x as f32,
+                        corner_height - HORIZON_DROP,
+                        position.y as f32,
+                    ]);
+                    normals.push(
+                        Vec3::new(
+                            -slope(IVec2::X),
+                            2.0 * HORIZON_CELL_SIZE as f32,
+                            -slope(IVec2::Y),
+                        )
+                        .normalize()
+                        .to_array(),
+                    );
+                    colors.push(color.as_linear_rgba_f32());
+                    positions.len() as u32 - 1
+                })
+            });
+
+            indices.extend([a, c, b, b, c, d]);
+        }
+
+        *mesh = horizon_mesh(positions, normals, colors, indices);
+    }
+}
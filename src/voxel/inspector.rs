@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_egui::EguiPlugin;
+
+use super::generation::VoxelChunkPosition;
+
+/// This plugin adds an egui panel for inspecting and editing a single chunk.
+/// The inspected chunk is the one targeted by the camera, unless a chunk has been pinned.
+pub(super) struct ChunkInspectorPlugin;
+
+impl Plugin for ChunkInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        app.init_resource::<ChunkInspector>()
+            .add_state::<ChunkInspectorState>()
+            .add_systems(
+                Update,
+                (
+                    systems::toggle_chunk_inspector,
+                    systems::chunk_inspector.run_if(in_state(ChunkInspectorState::Open)),
+                ),
+            );
+    }
+}
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(super) enum ChunkInspectorState {
+    Open,
+    #[default]
+    Closed,
+}
+
+/// State of the chunk inspector panel.
+#[derive(Resource, Default)]
+struct ChunkInspector {
+    /// When set, this chunk is inspected instead of the targeted one.
+    pinned: Option<VoxelChunkPosition>,
+    /// Index of the voxel being edited.
+    edit_index: usize,
+    /// The voxel id to write to [ChunkInspector::edit_index].
+    edit_id: u16,
+}
+
+mod systems {
+    use bevy_egui::{egui, EguiContexts};
+
+    use crate::{
+        input::{ActionInput, InputAction},
+        voxel::{
+            generation::{LocalVoxelPosition, VoxelChunk, VoxelChunkMap, VoxelChunkWidth},
+            interaction::TargetedVoxel,
+            load::ChunkState,
+            render::ChunkRenderQueue,
+            Voxel,
+        },
+    };
+
+    use super::*;
+
+    pub(super) fn toggle_chunk_inspector(
+        input: ActionInput,
+        mut next_state: ResMut<NextState<ChunkInspectorState>>,
+        cur_state: Res<State<ChunkInspectorState>>,
+    ) {
+        if input.just_pressed(InputAction::ToggleChunkInspector) {
+            next_state.set(match **cur_state {
+                ChunkInspectorState::Open => ChunkInspectorState::Closed,
+                ChunkInspectorState::Closed => ChunkInspectorState::Open,
+            })
+        }
+    }
+
+    pub(super) fn chunk_inspector(
+        mut contexts: EguiContexts,
+        mut inspector: ResMut<ChunkInspector>,
+        mut chunk_query: Query<(&mut VoxelChunk, &ChunkState, &Handle<Mesh>)>,
+        mut chunk_render_queue: ResMut<ChunkRenderQueue>,
+        meshes: Res<Assets<Mesh>>,
+        targeted_voxel: Res<TargetedVoxel>,
+        voxel_chunk_map: Res<VoxelChunkMap>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let targeted_chunk_pos = targeted_voxel
+            .0
+            .as_ref()
+            .map(|hit| VoxelChunkPosition::world_to_local(hit.voxel_pos, &chunk_width).0);
+
+        egui::Window::new("Chunk inspector").show(contexts.ctx_mut(), |ui| {
+            let Some(chunk_pos) = inspector.pinned.or(targeted_chunk_pos) else {
+                ui.label("Look at a chunk to inspect it.");
+                return;
+            };
+
+            ui.horizontal(|ui| {
+                ui.label(format!("Position: {:?}", chunk_pos.0));
+
+                if inspector.pinned.is_some() {
+                    if ui.button("Unpin").clicked() {
+                        inspector.pinned = None;
+                    }
+                } else if ui.button("Pin").clicked() {
+                    inspector.pinned = Some(chunk_pos);
+                }
+            });
+
+            let Some(chunk_entity) = voxel_chunk_map.0.get(&chunk_pos) else {
+                ui.label("Chunk is not loaded.");
+                return;
+            };
+            let Ok((mut chunk, chunk_state, mesh_handle)) = chunk_query.get_mut(*chunk_entity)
+            else {
+                ui.label("Chunk entity has no chunk components.");
+                return;
+            };
+
+            ui.label(format!("Entity: {chunk_entity:?}"));
+            ui.label(format!("State: {chunk_state:?}"));
+
+            ui.separator();
+            ui.heading("Mesh");
+
+            match meshes.get(mesh_handle) {
+                Some(mesh) => {
+                    ui.label(format!("Vertices: {}", mesh.count_vertices()));
+                    ui.label(format!(
+                        "Triangles: {}",
+                        mesh.indices().map_or(0, |indices| indices.len() / 3)
+                    ));
+                }
+                None => {
+                    ui.label("No mesh.");
+                }
+            }
+
+            ui.separator();
+            ui.heading("Voxels");
+
+            let mut histogram = BTreeMap::new();
+            for voxel in chunk.voxels().iter() {
+                *histogram.entry(voxel.id).or_insert(0usize) += 1;
+            }
+
+            egui::Grid::new("voxel_histogram").show(ui, |ui| {
+                ui.label("Id");
+                ui.label("Count");
+                ui.end_row();
+
+                for (id, count) in histogram {
+                    ui.label(id.to_string());
+                    ui.label(count.to_string());
+                    ui.end_row();
+                }
+            });
+
+            ui.separator();
+            ui.heading("Edit voxel");
+
+            let max_index = chunk.voxels().len() - 1;
+            ui.horizontal(|ui| {
+                ui.label("Index");
+                ui.add(egui::DragValue::new(&mut inspector.edit_index).clamp_range(0..=max_index));
+            });
+
+            let local_pos = LocalVoxelPosition::from_index(inspector.edit_index, &chunk_width);
+            let current_voxel = chunk.get_voxel(local_pos, &chunk_width);
+
+            ui.label(format!("Local position: {:?}", local_pos.as_ivec3()));
+            ui.label(format!(
+                "Current id: {}",
+                current_voxel.map_or("-".to_string(), |voxel| voxel.id.to_string())
+            ));
+
+            ui.horizontal(|ui| {
+                ui.label("New id");
+                ui.add(egui::DragValue::new(&mut inspector.edit_id));
+
+                if ui.button("Set").clicked() {
+                    chunk.set_voxel(local_pos, Voxel::new(inspector.edit_id), &chunk_width);
+                    chunk_render_queue.push_voxel_change(
+                        chunk_pos,
+                        local_pos,
+                        &voxel_chunk_map,
+                        &chunk_width,
+                    );
+                }
+            });
+        });
+    }
+}
@@ -0,0 +1,579 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::quick::ResourceInspectorPlugin;
+
+use super::{
+    color::VoxelMode,
+    edit::VoxelEdit,
+    game_mode::GameMode,
+    inventory::{Inventory, Item, ItemStack, HOTBAR_SLOTS},
+    item_drop::DropItem,
+    micro::{is_micro_voxel, MicroBlocks, MicroVoxelEdit, MICRO_BLOCK_WIDTH},
+    raycast::{raycast, VoxelRaycastHit},
+    tool::Tool,
+    Voxel,
+};
+
+/// How far away (in voxels) the player can break and place voxels.
+pub(super) const INTERACTION_REACH: f32 = 8.0;
+/// How long after breaking a voxel the next one starts being mined, in seconds, so holding the break button doesn't
+/// break a whole tunnel of soft voxels at once.
+const MINING_COOLDOWN: f32 = 0.2;
+const MINING_OVERLAY_MAX_ALPHA: f32 = 0.7;
+const HOTBAR_SLOT_SIZE: f32 = 40.0;
+const HOTBAR_SLOT_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+const HOTBAR_SELECTED_SLOT_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.8);
+
+/// This plugin is responsible for the player breaking and placing voxels, and the hotbar of voxels to place.
+///
+/// Breaking and placing sends [VoxelEdit]s, which are applied wherever the world is simulated. Voxels are mined by
+/// holding the break button for as long as their hardness, see [MiningProgress]. Broken blocks drop as items, see
+/// [DropItem], and placed blocks are taken from the selected hotbar slot of the [Inventory]. In [GameMode::Creative],
+/// voxels break right away, nothing drops, and placed blocks aren't used up. In a world of [VoxelMode::Colors],
+/// the [PlacementColor] is placed instead of the hotbar, and picked in its inspector window. With [MicroEditing]
+/// turned on, single micro voxels are broken and placed instead, see [MicroBlock](super::micro::MicroBlock). Micro
+/// voxels are too small to count, so they aren't taken from or added to the inventory.
+pub(super) struct VoxelInteractionPlugin;
+
+impl Plugin for VoxelInteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<VoxelEdit>()
+            .add_event::<MicroVoxelEdit>()
+            .add_event::<DropItem>()
+            .init_resource::<VoxelMode>()
+            .init_resource::<MicroBlocks>()
+            .init_resource::<MicroEditing>()
+            .init_resource::<PlacementColor>()
+            .register_type::<PlacementColor>()
+            .add_plugins(
+                ResourceInspectorPlugin::<PlacementColor>::default()
+                    .run_if(resource_equals(VoxelMode::Colors)),
+            )
+            .init_resource::<Hotbar>()
+            .init_resource::<TargetedVoxel>()
+            .init_resource::<TargetedMicroVoxel>()
+            .init_resource::<MiningProgress>()
+            .add_systems(
+                Startup,
+                (systems::setup_hotbar_ui, systems::spawn_mining_overlay),
+            )
+            .add_systems(
+                Update,
+                (
+                    systems::select_hotbar_slot,
+                    systems::update_hotbar_ui.run_if(
+                        resource_changed::<Hotbar>().or_else(resource_changed::<Inventory>()),
+                    ),
+                    systems::toggle_micro_editing,
+                    (
+                        systems::update_targeted_voxel,
+                        // Don't interact with the world while the cursor is used for menus.
+                        (
+                            systems::break_and_place_voxels,
+                            systems::mine_targeted_voxel,
+                        )
+                            .run_if(systems::cursor_grabbed),
+                        systems::update_mining_overlay,
+                    )
+                        .chain(),
+                    systems::targeted_micro_voxel_gizmo.run_if(resource_equals(MicroEditing(true))),
+                ),
+            );
+    }
+}
+
+/// Which slot of the hotbar is selected. The hotbar is the first [HOTBAR_SLOTS] slots of the [Inventory].
+#[derive(Resource, Default)]
+pub(super) struct Hotbar {
+    selected: usize,
+}
+
+impl Hotbar {
+    /// The voxel of the block in the selected slot, if it holds a block.
+    pub(super) fn selected_voxel(&self, inventory: &Inventory) -> Option<Voxel> {
+        inventory
+            .slot(self.selected)
+            .and_then(|stack| match stack.item {
+                Item::Block(voxel) => Some(voxel),
+                Item::Tool(_) => None,
+            })
+    }
+
+    /// The tool in the selected slot, if it holds a tool.
+    pub(super) fn selected_tool(&self, inventory: &Inventory) -> Option<Tool> {
+        inventory
+            .slot(self.selected)
+            .and_then(|stack| match stack.item {
+                Item::Tool(tool) => Some(tool),
+                Item::Block(_) => None,
+            })
+    }
+}
+
+/// The color the player places in a world of [VoxelMode::Colors].
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub(super) struct PlacementColor {
+    color: Color,
+}
+
+impl Default for PlacementColor {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(0.8, 0.35, 0.2),
+        }
+    }
+}
+
+/// The solid voxel the camera is currently looking at, within [INTERACTION_REACH].
+#[derive(Resource, Default, Debug)]
+pub(super) struct TargetedVoxel(pub(super) Option<VoxelRaycastHit>);
+
+/// Whether the player breaks and places single micro voxels instead of whole voxels.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct MicroEditing(pub(super) bool);
+
+/// The solid micro voxel the camera is currently looking at while [MicroEditing], within [INTERACTION_REACH]. The
+/// hit is in world micro positions, see [MicroBlocks::micro_voxel].
+#[derive(Resource, Default, Debug)]
+struct TargetedMicroVoxel(Option<VoxelRaycastHit>);
+
+/// How far the voxel targeted while the break button is held has been mined, from 0.0 to 1.0.
+#[derive(Resource, Default, Debug)]
+pub(super) struct MiningProgress {
+    voxel_pos: Option<IVec3>,
+    progress: f32,
+    /// How long until the next voxel can be mined, in seconds.
+    cooldown: f32,
+}
+
+/// Marker component for the entity covering the voxel being mined.
+#[derive(Component)]
+struct MiningOverlay;
+
+/// Marker component for a slot in the hotbar UI. Holds the index of the slot.
+#[derive(Component)]
+struct HotbarSlotUi(usize);
+
+/// Marker component for the item shown in a slot of the hotbar UI. Holds the index of the slot.
+#[derive(Component)]
+struct HotbarItemUi(usize);
+
+/// Marker component for the item count shown in a slot of the hotbar UI. Holds the index of the slot.
+#[derive(Component)]
+struct HotbarCountUi(usize);
+
+mod systems {
+    use bevy::{
+        pbr::NotShadowCaster,
+        window::{CursorGrabMode, PrimaryWindow},
+    };
+
+    use crate::{
+        input::{ActionInput, InputAction},
+        voxel::{
+            generation::{VoxelChunk, VoxelChunkMap, VoxelChunkPosition, VoxelChunkWidth},
+            world::VoxelWorld,
+        },
+    };
+
+    use super::*;
+
+    pub(super) fn setup_hotbar_ui(mut commands: Commands) {
+        commands
+            .spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(10.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    column_gap: Val::Px(4.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                for i in 0..HOTBAR_SLOTS {
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Px(HOTBAR_SLOT_SIZE),
+                                    height: Val::Px(HOTBAR_SLOT_SIZE),
+                                    padding: UiRect::all(Val::Px(6.0)),
+                                    ..default()
+                                },
+                                background_color: HOTBAR_SLOT_COLOR.into(),
+                                ..default()
+                            },
+                            HotbarSlotUi(i),
+                        ))
+                        .with_children(|parent| {
+                            parent
+                                .spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            width: Val::Percent(100.0),
+                                            height: Val::Percent(100.0),
+                                            justify_content: JustifyContent::FlexEnd,
+                                            align_items: AlignItems::FlexEnd,
+                                            ..default()
+                                        },
+                                        ..default()
+                                    },
+                                    HotbarItemUi(i),
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn((
+                                        TextBundle::from_section(
+                                            "",
+                                            TextStyle {
+                                                font_size: 14.0,
+                                                color: Color::WHITE,
+                                                ..default()
+                                            },
+                                        ),
+                                        HotbarCountUi(i),
+                                    ));
+                                });
+                        });
+                }
+            });
+    }
+
+    pub(super) fn update_hotbar_ui(
+        hotbar: Res<Hotbar>,
+        inventory: Res<Inventory>,
+        mut slot_query: Query<(&HotbarSlotUi, &mut BackgroundColor), Without<HotbarItemUi>>,
+        mut item_query: Query<(&HotbarItemUi, &mut BackgroundColor), Without<HotbarSlotUi>>,
+        mut count_query: Query<(&HotbarCountUi, &mut Text)>,
+    ) {
+        for (slot, mut background_color) in &mut slot_query {
+            *background_color = if slot.0 == hotbar.selected {
+                HOTBAR_SELECTED_SLOT_COLOR.into()
+            } else {
+                HOTBAR_SLOT_COLOR.into()
+            };
+        }
+
+        for (item, mut background_color) in &mut item_query {
+            *background_color = inventory
+                .slot(item.0)
+                .map_or(Color::NONE, |stack| stack.item.color())
+                .into();
+        }
+
+        for (count, mut text) in &mut count_query {
+            text.sections[0].value = inventory
+                .slot(count.0)
+                .map_or(String::new(), |stack| stack.count.to_string());
+        }
+    }
+
+    pub(super) fn select_hotbar_slot(input: ActionInput, mut hotbar: ResMut<Hotbar>) {
+        let mut selected = hotbar.selected;
+
+        for slot in 0..HOTBAR_SLOTS {
+            if input.just_pressed(InputAction::HotbarSlot(slot as u8)) {
+                selected = slot;
+            }
+        }
+
+        if input.just_pressed(InputAction::HotbarNext) {
+            selected = (selected + 1) % HOTBAR_SLOTS;
+        }
+        if input.just_pressed(InputAction::HotbarPrevious) {
+            selected = (selected + HOTBAR_SLOTS - 1) % HOTBAR_SLOTS;
+        }
+
+        if selected != hotbar.selected {
+            hotbar.selected = selected;
+        }
+    }
+
+    pub(super) fn cursor_grabbed(window_query: Query<&Window, With<PrimaryWindow>>) -> bool {
+        window_query
+            .get_single()
+            .is_ok_and(|window| window.cursor.grab_mode != CursorGrabMode::None)
+    }
+
+    pub(super) fn toggle_micro_editing(
+        input: ActionInput,
+        mut micro_editing: ResMut<MicroEditing>,
+    ) {
+        if input.just_pressed(InputAction::ToggleMicroEditing) {
+            micro_editing.0 = !micro_editing.0;
+        }
+    }
+
+    pub(super) fn update_targeted_voxel(
+        mut targeted_voxel: ResMut<TargetedVoxel>,
+        mut targeted_micro_voxel: ResMut<TargetedMicroVoxel>,
+        micro_editing: Res<MicroEditing>,
+        micro_blocks: Res<MicroBlocks>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+        chunk_query: Query<&VoxelChunk>,
+        voxel_chunk_map: Res<VoxelChunkMap>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let Ok(camera_transform) = camera_query.get_single() else {
+            targeted_voxel.0 = None;
+            targeted_micro_voxel.0 = None;
+            return;
+        };
+
+        let get_voxel = |voxel_pos: IVec3| {
+            let (chunk_pos, local_pos) =
+                VoxelChunkPosition::world_to_local(voxel_pos, &chunk_width);
+            let entity = voxel_chunk_map.0.get(&chunk_pos)?;
+
+            chunk_query
+                .get(*entity)
+                .ok()?
+                .get_voxel(local_pos, &chunk_width)
+        };
+
+        targeted_voxel.0 = raycast(
+            camera_transform.translation,
+            camera_transform.forward(),
+            INTERACTION_REACH,
+            |voxel_pos| get_voxel(voxel_pos).is_some_and(|v| v.is_solid()),
+        );
+
+        // Micro voxels are raycast in a grid scaled up by the micro block width, where micro voxel (0, 0, 0) is
+        // centered on the first micro voxel of voxel (0, 0, 0).
+        targeted_micro_voxel.0 = micro_editing
+            .0
+            .then(|| {
+                let micro_width = MICRO_BLOCK_WIDTH as f32;
+                raycast(
+                    (camera_transform.translation + 0.5) * micro_width - 0.5,
+                    camera_transform.forward(),
+                    INTERACTION_REACH * micro_width,
+                    |micro_pos| {
+                        micro_blocks
+                            .micro_voxel(micro_pos, &chunk_width, get_voxel)
+                            .is_some_and(|v| v.is_solid())
+                    },
+                )
+            })
+            .flatten();
+    }
+
+    /// Places voxels, and breaks micro voxels while [MicroEditing]. Whole voxels are mined instead, see
+    /// [mine_targeted_voxel].
+    pub(super) fn break_and_place_voxels(
+        input: ActionInput,
+        hotbar: Res<Hotbar>,
+        mut inventory: ResMut<Inventory>,
+        voxel_mode: Res<VoxelMode>,
+        game_mode: Res<GameMode>,
+        placement_color: Res<PlacementColor>,
+        micro_editing: Res<MicroEditing>,
+        targeted_voxel: Res<TargetedVoxel>,
+        targeted_micro_voxel: Res<TargetedMicroVoxel>,
+        mut edits: EventWriter<VoxelEdit>,
+        mut micro_edits: EventWriter<MicroVoxelEdit>,
+    ) {
+        let (voxel, place) = if input.just_pressed(InputAction::BreakBlock) {
+            if !micro_editing.0 {
+                return;
+            }
+            (Voxel::AIR, false)
+        } else if input.just_pressed(InputAction::PlaceBlock) {
+            let voxel = match *voxel_mode {
+                VoxelMode::Blocks => hotbar.selected_voxel(&inventory),
+                VoxelMode::Colors => Some(Voxel::from_color(placement_color.color)),
+            };
+            let Some(voxel) = voxel else {
+                return;
+            };
+            (voxel, true)
+        } else {
+            return;
+        };
+
+        if micro_editing.0 {
+            if !is_micro_voxel(voxel) {
+                return;
+            }
+            let Some(hit) = &targeted_micro_voxel.0 else {
+                return;
+            };
+            let micro_pos = if place {
+                let Some(adjacent_pos) = hit.adjacent_pos() else {
+                    return;
+                };
+                adjacent_pos
+            } else {
+                hit.voxel_pos
+            };
+
+            micro_edits.send(MicroVoxelEdit {
+                voxel_pos: micro_pos.div_euclid(IVec3::splat(MICRO_BLOCK_WIDTH)),
+                micro_pos: micro_pos.rem_euclid(IVec3::splat(MICRO_BLOCK_WIDTH)),
+                voxel,
+            });
+            return;
+        }
+
+        let Some(voxel_pos) = targeted_voxel
+            .0
+            .as_ref()
+            .and_then(VoxelRaycastHit::adjacent_pos)
+        else {
+            return;
+        };
+
+        if *voxel_mode == VoxelMode::Blocks && !game_mode.is_creative() {
+            inventory.take_one(hotbar.selected);
+        }
+
+        edits.send(VoxelEdit { voxel_pos, voxel });
+    }
+
+    /// Mines the targeted voxel while the break button is held, breaking it once it's been mined for as long as its
+    /// [hardness](Voxel::hardness), divided by the [mining speed](Tool::mining_speed) of the selected tool. In
+    /// [GameMode::Creative] every voxel breaks right away. Mining starts over whenever another voxel is targeted, and
+    /// after a voxel breaks the next one only starts being mined after [MINING_COOLDOWN].
+    pub(super) fn mine_targeted_voxel(
+        time: Res<Time>,
+        input: ActionInput,
+        voxel_world: VoxelWorld,
+        voxel_mode: Res<VoxelMode>,
+        game_mode: Res<GameMode>,
+        micro_editing: Res<MicroEditing>,
+        targeted_voxel: Res<TargetedVoxel>,
+        hotbar: Res<Hotbar>,
+        mut inventory: ResMut<Inventory>,
+        mut mining: ResMut<MiningProgress>,
+        mut edits: EventWriter<VoxelEdit>,
+        mut drops: EventWriter<DropItem>,
+    ) {
+        mining.cooldown = (mining.cooldown - time.delta_seconds()).max(0.0);
+
+        let target = targeted_voxel
+            .0
+            .as_ref()
+            .filter(|_| !micro_editing.0 && input.pressed(InputAction::BreakBlock))
+            .map(|hit| hit.voxel_pos);
+        if target != mining.voxel_pos {
+            mining.voxel_pos = target;
+            mining.progress = 0.0;
+        }
+
+        let Some(voxel_pos) = target else {
+            return;
+        };
+        let Some(voxel) = voxel_world.get_block(voxel_pos) else {
+            return;
+        };
+        if mining.cooldown > 0.0 || voxel.is_indestructible() {
+            return;
+        }
+
+        let tool = hotbar
+            .selected_tool(&inventory)
+            .filter(|_| !game_mode.is_creative());
+        let hardness = if game_mode.is_creative() {
+            0.0
+        } else {
+            voxel.hardness() / tool.map_or(1.0, |tool| tool.mining_speed(voxel))
+        };
+        mining.progress += if hardness > 0.0 {
+            time.delta_seconds() / hardness
+        } else {
+            1.0
+        };
+        if mining.progress < 1.0 {
+            return;
+        }
+
+        mining.progress = 0.0;
+        mining.cooldown = MINING_COOLDOWN;
+        // Blocks that break right away don't need a tool, so they don't wear it either.
+        if tool.is_some() && hardness > 0.0 {
+            inventory.wear_tool(hotbar.selected);
+        }
+
+        // A micro block is made of the micro voxels in it, not a block of its own.
+        if *voxel_mode == VoxelMode::Blocks
+            && !game_mode.is_creative()
+            && voxel != Voxel::MICRO_BLOCK
+        {
+            drops.send(DropItem {
+                position: voxel_pos.as_vec3(),
+                stack: ItemStack::new(Item::broken_block(voxel), 1),
+            });
+        }
+
+        edits.send(VoxelEdit {
+            voxel_pos,
+            voxel: Voxel::AIR,
+        });
+    }
+
+    /// Covers the voxel being mined with an overlay that gets darker the further it's mined.
+    pub(super) fn update_mining_overlay(
+        mining: Res<MiningProgress>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+        mut overlay_query: Query<
+            (&mut Transform, &mut Visibility, &Handle<StandardMaterial>),
+            With<MiningOverlay>,
+        >,
+    ) {
+        for (mut transform, mut visibility, material) in &mut overlay_query {
+            let Some(voxel_pos) = mining.voxel_pos.filter(|_| mining.progress > 0.0) else {
+                *visibility = Visibility::Hidden;
+                continue;
+            };
+
+            *visibility = Visibility::Visible;
+            transform.translation = voxel_pos.as_vec3();
+            if let Some(material) = materials.get_mut(material) {
+                material
+                    .base_color
+                    .set_a(mining.progress.min(1.0) * MINING_OVERLAY_MAX_ALPHA);
+            }
+        }
+    }
+
+    pub(super) fn spawn_mining_overlay(
+        mut commands: Commands,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+    ) {
+        commands.spawn((
+            PbrBundle {
+                // Slightly larger than a voxel, so it isn't hidden by the faces of the voxel.
+                mesh: meshes.add(shape::Cube::new(1.01).into()),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgba(0.0, 0.0, 0.0, 0.0),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                }),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            NotShadowCaster,
+            MiningOverlay,
+        ));
+    }
+
+    /// Outlines the micro voxel the camera is looking at while [MicroEditing].
+    pub(super) fn targeted_micro_voxel_gizmo(
+        mut gizmos: Gizmos,
+        targeted_micro_voxel: Res<TargetedMicroVoxel>,
+    ) {
+        let Some(hit) = &targeted_micro_voxel.0 else {
+            return;
+        };
+
+        let micro_width = MICRO_BLOCK_WIDTH as f32;
+        gizmos.cuboid(
+            Transform::from_translation((hit.voxel_pos.as_vec3() + 0.5) / micro_width - 0.5)
+                .with_scale(Vec3::splat(1.0 / micro_width)),
+            Color::WHITE,
+        );
+    }
+}
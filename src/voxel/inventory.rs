@@ -0,0 +1,378 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiPlugin};
+use serde::{Deserialize, Serialize};
+
+use super::{registry, tool::Tool, Voxel};
+
+/// Amount of slots in the [Inventory], in rows of [HOTBAR_SLOTS].
+pub(super) const INVENTORY_SLOTS: usize = 36;
+/// Amount of slots in the hotbar, which are the first slots of the [Inventory].
+pub(super) const HOTBAR_SLOTS: usize = 9;
+/// The most items a slot holds, of items that stack at all, see [Item::max_stack_size].
+pub(super) const MAX_STACK_SIZE: u16 = 64;
+const INVENTORY_SLOT_SIZE: f32 = 40.0;
+
+/// This plugin holds the player's [Inventory], and the inventory screen for moving items between its slots.
+///
+/// Broken blocks are picked up into the inventory, see [VoxelItemDropPlugin](super::item_drop::VoxelItemDropPlugin), and
+/// placing a block takes it from the hotbar, see [VoxelInteractionPlugin](super::interaction::VoxelInteractionPlugin).
+pub(super) struct VoxelInventoryPlugin;
+
+impl Plugin for VoxelInventoryPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        app.init_resource::<Inventory>()
+            .add_state::<InventoryScreenState>()
+            .add_systems(
+                Update,
+                (
+                    systems::toggle_inventory_screen,
+                    systems::inventory_screen.run_if(in_state(InventoryScreenState::Open)),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                OnExit(InventoryScreenState::Open),
+                systems::put_back_held_stack,
+            );
+    }
+}
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(super) enum InventoryScreenState {
+    Open,
+    #[default]
+    Closed,
+}
+
+/// Anything that can be held in an [Inventory].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(super) enum Item {
+    /// A block, placed as this voxel.
+    Block(Voxel),
+    /// A tool, which mines some blocks faster. See [Tool].
+    Tool(Tool),
+}
+
+impl Item {
+    /// The block a broken voxel drops. Its state, like whether TNT is lit, is dropped with it.
+    pub(super) fn broken_block(voxel: Voxel) -> Self {
+        Item::Block(Voxel::new(voxel.id()))
+    }
+
+    /// The item with the given [name](Item::name), if there is one.
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        registry::block_id(name)
+            .map(|id| Item::Block(Voxel::new(id)))
+            .or_else(|| Tool::from_name(name).map(Item::Tool))
+    }
+
+    pub(super) fn name(&self) -> &'static str {
+        match self {
+            Item::Block(voxel) => voxel.name(),
+            Item::Tool(tool) => tool.name(),
+        }
+    }
+
+    /// The most of the item a slot holds. Tools wear out one by one, so they don't stack.
+    pub(super) fn max_stack_size(&self) -> u16 {
+        match self {
+            Item::Block(_) => MAX_STACK_SIZE,
+            Item::Tool(_) => 1,
+        }
+    }
+
+    /// The color the item is shown with in the hotbar and the inventory screen.
+    pub(super) fn color(&self) -> Color {
+        match self {
+            Item::Block(voxel) => voxel.color(),
+            Item::Tool(tool) => tool.tier.color(),
+        }
+    }
+}
+
+/// A number of the same [Item], in a single slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct ItemStack {
+    pub(super) item: Item,
+    pub(super) count: u16,
+    /// How many blocks the tool in the stack has broken. Always 0 for items that aren't tools.
+    pub(super) wear: u16,
+}
+
+impl ItemStack {
+    pub(super) fn new(item: Item, count: u16) -> Self {
+        Self {
+            item,
+            count,
+            wear: 0,
+        }
+    }
+
+    /// How many more blocks the tool in the stack can break, if it holds a tool.
+    pub(super) fn durability_left(&self) -> Option<u16> {
+        match self.item {
+            Item::Tool(tool) => Some(tool.tier.durability().saturating_sub(self.wear)),
+            Item::Block(_) => None,
+        }
+    }
+}
+
+/// The items the player carries. The first [HOTBAR_SLOTS] slots are the hotbar.
+#[derive(Resource, Debug)]
+pub(super) struct Inventory {
+    slots: [Option<ItemStack>; INVENTORY_SLOTS],
+    /// The stack picked up on the inventory screen, until it's put down in a slot.
+    held: Option<ItemStack>,
+}
+
+impl Inventory {
+    pub(super) fn slot(&self, slot: usize) -> Option<ItemStack> {
+        self.slots.get(slot).copied().flatten()
+    }
+
+    /// Adds the items of a stack, filling up the stacks of the item first and then empty slots, hotbar first. Returns
+    /// how many of them didn't fit.
+    pub(super) fn add(&mut self, added_stack: ItemStack) -> u16 {
+        let item = added_stack.item;
+        let max_stack_size = item.max_stack_size();
+        let mut count = added_stack.count;
+
+        for stack in self.slots.iter_mut().flatten() {
+            if stack.item == item {
+                let added = count.min(max_stack_size.saturating_sub(stack.count));
+                stack.count += added;
+                count -= added;
+            }
+        }
+
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if count == 0 {
+                break;
+            }
+
+            let added = count.min(max_stack_size);
+            *slot = Some(ItemStack {
+                count: added,
+                ..added_stack
+            });
+            count -= added;
+        }
+
+        count
+    }
+
+    /// Takes a single item out of a slot, emptying it if it was the last one.
+    pub(super) fn take_one(&mut self, slot: usize) -> Option<Item> {
+        let stack = self.slots.get_mut(slot)?.as_mut()?;
+        let item = stack.item;
+
+        stack.count -= 1;
+        if stack.count == 0 {
+            self.slots[slot] = None;
+        }
+
+        Some(item)
+    }
+
+    /// Wears down the tool in a slot by a block, and breaks it once it's worn out. Does nothing if the slot doesn't
+    /// hold a tool.
+    pub(super) fn wear_tool(&mut self, slot: usize) {
+        let Some(stack) = self.slots.get_mut(slot) else {
+            return;
+        };
+
+        if let Some(tool_stack) = stack
+            .as_mut()
+            .filter(|stack| matches!(stack.item, Item::Tool(_)))
+        {
+            tool_stack.wear += 1;
+            if tool_stack.durability_left() == Some(0) {
+                *stack = None;
+            }
+        }
+    }
+
+    /// Adds a stack to the held stack, if nothing is held or it's the same item and there's room for it. Returns
+    /// whether it was added.
+    pub(super) fn pick_up(&mut self, stack: ItemStack) -> bool {
+        match &mut self.held {
+            None => self.held = Some(stack),
+            Some(held)
+                if held.item == stack.item
+                    && held.count + stack.count <= stack.item.max_stack_size() =>
+            {
+                held.count += stack.count;
+            }
+            Some(_) => return false,
+        }
+
+        true
+    }
+
+    /// Clicks a slot of the inventory on the inventory screen, see [Inventory::click_stack].
+    fn click_slot(&mut self, slot: usize, secondary: bool) {
+        let mut stack = self.slots[slot].take();
+        self.click_stack(&mut stack, secondary);
+        self.slots[slot] = stack;
+    }
+
+    /// Clicks a slot holding `stack` on the inventory screen, which can be a slot outside of the inventory, like one of
+    /// the crafting grid.
+    ///
+    /// Clicking picks up the stack of the slot, or puts the held stack down in it. A held stack of the same item is
+    /// added to the slot's stack as far as it fits, and any other stack is swapped with it. Secondary clicking puts a
+    /// single item of the held stack down, or picks up half of the slot's stack when nothing is held.
+    pub(super) fn click_stack(&mut self, stack: &mut Option<ItemStack>, secondary: bool) {
+        let held = &mut self.held;
+        let fits = |stack: &ItemStack| stack.item.max_stack_size().saturating_sub(stack.count);
+
+        match (stack.as_mut(), held.as_mut(), secondary) {
+            (Some(slot_stack), Some(held_stack), false) if slot_stack.item == held_stack.item => {
+                let moved = held_stack.count.min(fits(slot_stack));
+                slot_stack.count += moved;
+                held_stack.count -= moved;
+            }
+            (_, _, false) => std::mem::swap(stack, held),
+            (Some(slot_stack), None, true) => {
+                let picked = slot_stack.count.div_ceil(2);
+                *held = Some(ItemStack {
+                    count: picked,
+                    ..*slot_stack
+                });
+                slot_stack.count -= picked;
+            }
+            (None, Some(held_stack), true) => {
+                *stack = Some(ItemStack {
+                    count: 1,
+                    ..*held_stack
+                });
+                held_stack.count -= 1;
+            }
+            (Some(slot_stack), Some(held_stack), true)
+                if slot_stack.item == held_stack.item && fits(slot_stack) > 0 =>
+            {
+                slot_stack.count += 1;
+                held_stack.count -= 1;
+            }
+            _ => {}
+        }
+
+        // Stacks that ran out are gone.
+        for stack in [stack, held] {
+            if stack.is_some_and(|stack| stack.count == 0) {
+                *stack = None;
+            }
+        }
+    }
+}
+
+/// Adds a button showing a slot holding `stack` to the inventory screen, with its name when hovered. Tools show how
+/// many more blocks they can break instead of their count.
+pub(super) fn slot_button(ui: &mut egui::Ui, stack: Option<ItemStack>) -> egui::Response {
+    let (text, fill) = match stack {
+        Some(stack) => {
+            let [r, g, b, a] = stack.item.color().as_rgba_u8();
+            (
+                stack.durability_left().unwrap_or(stack.count).to_string(),
+                egui::Color32::from_rgba_unmultiplied(r, g, b, a),
+            )
+        }
+        None => (String::new(), egui::Color32::from_black_alpha(150)),
+    };
+
+    let response = ui.add(
+        egui::Button::new(egui::RichText::new(text).strong())
+            .fill(fill)
+            .min_size(egui::vec2(INVENTORY_SLOT_SIZE, INVENTORY_SLOT_SIZE)),
+    );
+    match stack {
+        Some(stack) => response.on_hover_text(stack.item.name()),
+        None => response,
+    }
+}
+
+impl Default for Inventory {
+    /// A full stack of a few blocks in the hotbar, so there's something to build with.
+    fn default() -> Self {
+        let mut slots = [None; INVENTORY_SLOTS];
+        let blocks = [
+            Voxel::STONE,
+            Voxel::WATER,
+            Voxel::LAVA,
+            Voxel::WOOD,
+            Voxel::FIRE,
+            Voxel::DIRT,
+            Voxel::TNT,
+            Voxel::SAND,
+            Voxel::LEAVES,
+        ];
+        for (slot, block) in slots.iter_mut().zip(blocks) {
+            *slot = Some(ItemStack::new(Item::Block(block), MAX_STACK_SIZE));
+        }
+
+        Self { slots, held: None }
+    }
+}
+
+mod systems {
+    use bevy_egui::EguiContexts;
+
+    use crate::input::{ActionInput, InputAction};
+
+    use super::*;
+
+    pub(super) fn toggle_inventory_screen(
+        input: ActionInput,
+        mut next_state: ResMut<NextState<InventoryScreenState>>,
+        cur_state: Res<State<InventoryScreenState>>,
+    ) {
+        if input.just_pressed(InputAction::ToggleInventory) {
+            next_state.set(match **cur_state {
+                InventoryScreenState::Open => InventoryScreenState::Closed,
+                InventoryScreenState::Closed => InventoryScreenState::Open,
+            })
+        }
+    }
+
+    /// Shows every slot of the inventory, with the hotbar in the bottom row like on the screen. Clicking slots moves
+    /// stacks around, see [Inventory::click_stack].
+    pub(super) fn inventory_screen(mut contexts: EguiContexts, mut inventory: ResMut<Inventory>) {
+        egui::Window::new("Inventory")
+            .collapsible(false)
+            .resizable(false)
+            .show(contexts.ctx_mut(), |ui| {
+                let rows = INVENTORY_SLOTS / HOTBAR_SLOTS;
+                egui::Grid::new("inventory_slots").show(ui, |ui| {
+                    for row in (0..rows).rev() {
+                        for slot in row * HOTBAR_SLOTS..(row + 1) * HOTBAR_SLOTS {
+                            let response = slot_button(ui, inventory.slot(slot));
+                            if response.clicked() {
+                                inventory.click_slot(slot, false);
+                            } else if response.secondary_clicked() {
+                                inventory.click_slot(slot, true);
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+
+                ui.separator();
+                match inventory.held {
+                    Some(held) => ui.label(format!("Holding {} {}", held.count, held.item.name())),
+                    None => ui.label("Click a slot to pick up its items"),
+                };
+            });
+    }
+
+    /// Puts the stack held when the inventory screen is closed back into the inventory. It came out of a slot, so
+    /// there's always room for it.
+    pub(super) fn put_back_held_stack(mut inventory: ResMut<Inventory>) {
+        if let Some(held) = inventory.held.take() {
+            inventory.add(held);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+
+use super::inventory::{Inventory, ItemStack};
+
+const DROPPED_ITEM_SIZE: f32 = 0.25;
+/// How fast dropped items spin around, in radians per second.
+const DROPPED_ITEM_SPIN: f32 = 2.0;
+/// How fast dropped items are tossed up when they're dropped, in voxels per second.
+const DROP_SPEED: f32 = 3.0;
+/// How close the player has to get to a dropped item to pick it up, in voxels.
+const PICKUP_RADIUS: f32 = 2.0;
+/// How close dropped items of the same item have to be to merge into one, in voxels.
+const MERGE_RADIUS: f32 = 1.0;
+/// How long dropped items lie around before they vanish, in seconds.
+const DROPPED_ITEM_LIFETIME: f32 = 300.0;
+
+/// This plugin spawns a spinning item for every [DropItem], which falls to the ground and is picked up into the
+/// [Inventory] once the player touches it. Dropped items of the same item next to each other merge into one stack.
+pub(super) struct VoxelItemDropPlugin;
+
+impl Plugin for VoxelItemDropPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DropItem>()
+            .init_resource::<DroppedItemAssets>()
+            .add_systems(
+                Update,
+                (
+                    systems::spawn_dropped_items,
+                    systems::spin_dropped_items,
+                    systems::merge_dropped_items,
+                    systems::pick_up_dropped_items,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Drops a stack of items into the world, at a position in world coordinates.
+#[derive(Event, Debug, Clone, Copy)]
+pub(super) struct DropItem {
+    pub(super) position: Vec3,
+    pub(super) stack: ItemStack,
+}
+
+/// A stack of items lying in the world, holding how long it has left.
+#[derive(Component)]
+struct DroppedItem {
+    stack: ItemStack,
+    lifetime: Timer,
+}
+
+#[derive(Resource)]
+struct DroppedItemAssets {
+    mesh: Handle<Mesh>,
+}
+
+impl FromWorld for DroppedItemAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(shape::Cube::new(DROPPED_ITEM_SIZE).into());
+
+        Self { mesh }
+    }
+}
+
+mod systems {
+    use rand::Rng;
+
+    use crate::voxel::physics::{Gravity, TerrainCollider, Velocity};
+
+    use super::*;
+
+    pub(super) fn spawn_dropped_items(
+        mut commands: Commands,
+        mut drops: EventReader<DropItem>,
+        assets: Res<DroppedItemAssets>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+    ) {
+        let mut rng = rand::thread_rng();
+
+        for drop in drops.read() {
+            let toss = Vec3::new(rng.gen_range(-0.5..=0.5), 1.0, rng.gen_range(-0.5..=0.5));
+
+            commands.spawn((
+                PbrBundle {
+                    mesh: assets.mesh.clone(),
+                    material: materials.add(drop.stack.item.color().into()),
+                    transform: Transform::from_translation(drop.position),
+                    ..default()
+                },
+                Velocity(toss * DROP_SPEED),
+                Gravity,
+                TerrainCollider::default(),
+                DroppedItem {
+                    stack: drop.stack,
+                    lifetime: Timer::from_seconds(DROPPED_ITEM_LIFETIME, TimerMode::Once),
+                },
+            ));
+        }
+    }
+
+    /// Spins dropped items, and despawns them once their time is up.
+    pub(super) fn spin_dropped_items(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut item_query: Query<(Entity, &mut Transform, &mut DroppedItem)>,
+    ) {
+        for (entity, mut transform, mut dropped_item) in &mut item_query {
+            if dropped_item.lifetime.tick(time.delta()).finished() {
+                commands.entity(entity).despawn();
+                continue;
+            }
+
+            transform.rotate_y(DROPPED_ITEM_SPIN * time.delta_seconds());
+        }
+    }
+
+    /// Moves the items of dropped items into other dropped items of the same item close to them, as far as they fit
+    /// in a stack, and despawns the ones left empty.
+    pub(super) fn merge_dropped_items(
+        mut commands: Commands,
+        mut item_query: Query<(Entity, &Transform, &mut DroppedItem)>,
+    ) {
+        let mut combinations = item_query.iter_combinations_mut();
+        while let Some([(_, transform_a, mut a), (entity_b, transform_b, mut b)]) =
+            combinations.fetch_next()
+        {
+            if a.stack.count == 0
+                || b.stack.count == 0
+                || a.stack.item != b.stack.item
+                || transform_a.translation.distance(transform_b.translation) > MERGE_RADIUS
+            {
+                continue;
+            }
+
+            let moved = b
+                .stack
+                .count
+                .min(a.stack.item.max_stack_size().saturating_sub(a.stack.count));
+            a.stack.count += moved;
+            b.stack.count -= moved;
+            // The merged item lies around as long as the newer of the two would have.
+            if b.lifetime.elapsed() < a.lifetime.elapsed() {
+                a.lifetime = b.lifetime.clone();
+            }
+
+            if b.stack.count == 0 {
+                commands.entity(entity_b).despawn();
+            }
+        }
+    }
+
+    /// Adds dropped items close to the camera to the [Inventory]. Items that don't fit stay where they are.
+    pub(super) fn pick_up_dropped_items(
+        mut commands: Commands,
+        mut inventory: ResMut<Inventory>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+        mut item_query: Query<(Entity, &Transform, &mut DroppedItem), Without<Camera3d>>,
+    ) {
+        let Ok(camera_transform) = camera_query.get_single() else {
+            return;
+        };
+
+        for (entity, transform, mut dropped_item) in &mut item_query {
+            if dropped_item.stack.count == 0
+                || transform.translation.distance(camera_transform.translation) > PICKUP_RADIUS
+            {
+                continue;
+            }
+
+            let left = inventory.add(dropped_item.stack);
+            if left == 0 {
+                commands.entity(entity).despawn();
+            } else if left != dropped_item.stack.count {
+                dropped_item.stack.count = left;
+            }
+        }
+    }
+}
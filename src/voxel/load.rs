@@ -2,53 +2,163 @@ use std::collections::VecDeque;
 
 use bevy::prelude::*;
 
-use super::generation::{
-    VoxelChunk, VoxelChunkBundle, VoxelChunkMap, VoxelChunkPosition, VoxelChunkWidth,
+use super::{
+    color::VoxelMode,
+    generation::{
+        VoxelChunk, VoxelChunkBundle, VoxelChunkMap, VoxelChunkPosition, VoxelChunkWidth,
+    },
+    preset::TerrainPreset,
 };
-use bevy_inspector_egui::quick::ResourceInspectorPlugin;
 
+/// This plugin loads and unloads chunks around every [RenderDistance], generating them from the terrain noise.
+///
+/// Chunks are only spawned with their voxels. Meshing them is up to the
+/// [VoxelChunkRenderingPlugin](super::render::VoxelChunkRenderingPlugin), so this also runs without rendering.
 pub(super) struct VoxelChunkLoadingPlugin;
 
 impl Plugin for VoxelChunkLoadingPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<ChunkRenderQueue>()
-            .init_resource::<ChunkLoadQueue>()
-            .register_type::<ChunkRenderQueue>()
+        app.init_resource::<ChunkLoadQueue>()
+            .init_resource::<VoxelMode>()
             .register_type::<ChunkLoadQueue>()
-            .add_plugins((
-                ResourceInspectorPlugin::<ChunkRenderQueue>::default(),
-                ResourceInspectorPlugin::<ChunkLoadQueue>::default(),
-            ))
             .add_systems(
                 Update,
                 (
+                    systems::apply_vertical_range,
                     systems::enqueue_chunks_in_render_distance,
                     systems::unload_chunks_out_of_render_distance,
                     systems::handle_chunk_unloading,
                     systems::handle_chunk_loading,
-                    systems::handle_chunk_rendering,
                 )
                     .chain(),
             );
     }
 }
 
+/// This plugin adds the chunk lifecycle events, [ChunkLoaded], [ChunkGenerated], [ChunkMeshed] and [ChunkUnloaded].
+/// Both the simulation and the presentation send some of them, so it's shared between them.
+pub(super) struct ChunkLifecyclePlugin;
+
+impl Plugin for ChunkLifecyclePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ChunkLoaded>()
+            .add_event::<ChunkGenerated>()
+            .add_event::<ChunkMeshed>()
+            .add_event::<ChunkUnloaded>();
+    }
+}
+
+/// Sent when a chunk is spawned with its voxels, whether they were generated, loaded from the save, or received from
+/// a server.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkLoaded {
+    /// The position of the chunk, in chunks.
+    pub chunk_pos: IVec3,
+    pub entity: Entity,
+}
+
+/// Sent along with the [ChunkLoaded] of a chunk whose voxels were generated from the terrain noise, rather than
+/// loaded from the save. Only sent where the world is simulated.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkGenerated {
+    /// The position of the chunk, in chunks.
+    pub chunk_pos: IVec3,
+    pub entity: Entity,
+}
+
+/// Sent every time a chunk got a new mesh, so after it's loaded and after its voxels change. Only sent where chunks
+/// are rendered.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkMeshed {
+    /// The position of the chunk, in chunks.
+    pub chunk_pos: IVec3,
+    pub entity: Entity,
+}
+
+/// Sent when a chunk is despawned. Its entity doesn't exist anymore by the time this is read.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkUnloaded {
+    /// The position of the chunk, in chunks.
+    pub chunk_pos: IVec3,
+    pub entity: Entity,
+}
+
+/// Loads the chunks around the entity it's on, like a player or a camera.
+///
+/// Any number of entities can have one. The loaded chunks are the union of what they all need: a chunk is loaded
+/// once it's within `val` chunks of any of them, and only unloaded once it's further than `val + unload_margin`
+/// chunks from every one of them, each using its own distances.
+///
+/// Every frame, each entity enqueues at most its budget of the closest chunks it's missing. Entities with a higher
+/// priority enqueue first, so their chunks load first, and chunks several of them need count against the budget of
+/// the one with the highest priority.
 #[derive(Component)]
-pub(crate) struct RenderDistance {
+pub struct RenderDistance {
     pub(crate) val: u32,
     pub(crate) unload_margin: u32,
+    /// How many times further chunks are loaded above and below than around, set from the
+    /// [TerrainPreset::vertical_range].
+    pub(crate) vertical_range: f32,
+    pub(crate) priority: i32,
+    /// How many chunks can be enqueued for loading per frame. [None] enqueues every missing chunk at once.
+    pub(crate) budget: Option<u32>,
 }
 
 impl RenderDistance {
-    pub(crate) fn new(val: u32, unload_margin: u32) -> Self {
-        Self { val, unload_margin }
+    pub fn new(val: u32, unload_margin: u32) -> Self {
+        Self {
+            val,
+            unload_margin,
+            vertical_range: 1.0,
+            priority: 0,
+            budget: None,
+        }
+    }
+
+    /// Entities with a higher priority get their chunks loaded first. The default is 0.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Limits how many chunks the entity enqueues for loading per frame, closest first. Useful for observers that
+    /// matter less, like a map marker, so they don't hold up the chunks of the player.
+    pub fn with_budget(mut self, budget: u32) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// How far away a chunk `offset` chunks away counts as, with the vertical distance shrunk by the vertical range.
+    pub(crate) fn chunk_distance(&self, offset: IVec3) -> f32 {
+        let offset = offset.as_vec3();
+        Vec3::new(offset.x, offset.y / self.vertical_range, offset.z).length()
+    }
+
+    /// Whether the chunk `offset` chunks away should stay loaded for this entity.
+    pub(crate) fn keeps(&self, offset: IVec3) -> bool {
+        self.chunk_distance(offset) <= (self.val + self.unload_margin) as f32
     }
 }
 
+/// Where a spawned chunk is in the loading pipeline. Chunks that are still in the [ChunkLoadQueue] don't have an entity yet.
+///
+/// Generation happens in the same frame a chunk is taken out of the [ChunkLoadQueue], so there is no
+/// separate "generating" state.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ChunkState {
+    /// The voxels have been generated, but the chunk hasn't been meshed yet.
+    #[default]
+    Generated,
+    /// The chunk has an up to date mesh.
+    Meshed,
+    /// The chunk has a mesh, but the voxels have changed since, and it's waiting in the [ChunkRenderQueue].
+    Dirty,
+}
+
 /// This is the queue responsible for loading in voxel chunk entities.
 ///
 /// It should be noted that chunks are just loaded in as entitites, but are not rendered.
-/// Rendering is handled by [ChunkRenderQueue]
+/// Rendering is handled by [ChunkRenderQueue](super::render::ChunkRenderQueue)
 #[derive(Resource, Default, Clone, Reflect)]
 pub(super) struct ChunkLoadQueue {
     /// Chunks to be loaded.
@@ -63,6 +173,30 @@ pub(super) enum ChunkLoadQueueInput {
 }
 
 impl ChunkLoadQueue {
+    /// The chunks waiting to be loaded.
+    pub(super) fn queued_loads(&self) -> impl Iterator<Item = &VoxelChunkPosition> {
+        self.load.iter()
+    }
+
+    /// The chunks waiting to be unloaded.
+    pub(super) fn queued_unloads(&self) -> impl Iterator<Item = &VoxelChunkPosition> {
+        self.unload.iter().map(|(chunk_pos, _)| chunk_pos)
+    }
+
+    /// Forgets every chunk waiting to be loaded or unloaded.
+    pub(super) fn clear(&mut self) {
+        self.load.clear();
+        self.unload.clear();
+    }
+
+    pub(super) fn load_len(&self) -> usize {
+        self.load.len()
+    }
+
+    pub(super) fn unload_len(&self) -> usize {
+        self.unload.len()
+    }
+
     pub(super) fn push_chunk(&mut self, input: ChunkLoadQueueInput) {
         match input {
             ChunkLoadQueueInput::Load(pos) => self.load.push_back(pos),
@@ -73,34 +207,49 @@ impl ChunkLoadQueue {
     }
 }
 
-/// This is the queue responsible for rendering chunks / creating the meshes.
-#[derive(Resource, Default, Reflect)]
-pub(super) struct ChunkRenderQueue {
-    /// Chunks to be rendered.
-    queue: VecDeque<Entity>,
-}
-
-impl ChunkRenderQueue {
-    pub(super) fn push_chunk(&mut self, entity: Entity) {
-        self.queue.push_back(entity);
-    }
-}
-
 mod systems {
-    use crate::voxel::{noise::TerrainNoise, VoxelChunkCoordinate};
+    use crate::voxel::{
+        color::VoxelMode, diagnostics::VoxelPipelineStats, noise::TerrainNoise,
+        persistence::WorldSave, VoxelChunkCoordinate, VoxelConfig,
+    };
 
     use super::*;
 
+    /// Also runs when a [RenderDistance] is replaced, like the server does with the one of every player whenever
+    /// they move.
+    pub(super) fn apply_vertical_range(
+        mut render_dist_query: Query<&mut RenderDistance, Changed<RenderDistance>>,
+        preset: Res<TerrainPreset>,
+    ) {
+        for mut render_distance in &mut render_dist_query {
+            if render_distance.vertical_range != preset.vertical_range() {
+                render_distance.vertical_range = preset.vertical_range();
+            }
+        }
+    }
+
     pub(super) fn enqueue_chunks_in_render_distance(
         render_dist_query: Query<(&Transform, &RenderDistance)>,
         chunk_width: Res<VoxelChunkWidth>,
         mut chunk_load_queue: ResMut<ChunkLoadQueue>,
         voxel_chunk_map: Res<VoxelChunkMap>,
     ) {
-        for (transform, render_distance) in render_dist_query.iter() {
+        let _span = info_span!("enqueue_chunks").entered();
+
+        let mut observers: Vec<_> = render_dist_query.iter().collect();
+        observers.sort_by_key(|(_, render_distance)| std::cmp::Reverse(render_distance.priority));
+
+        for (transform, render_distance) in observers {
             let origin_chunk_pos = transform.translation.as_chunk_pos(&chunk_width);
-            let min_bound = origin_chunk_pos.0 - render_distance.val as i32;
-            let max_bound = origin_chunk_pos.0 + render_distance.val as i32;
+            let mut missing_chunks = Vec::new();
+            let vertical_val = (render_distance.val as f32 * render_distance.vertical_range) as i32;
+            let bound = IVec3::new(
+                render_distance.val as i32,
+                vertical_val,
+                render_distance.val as i32,
+            );
+            let min_bound = origin_chunk_pos.0 - bound;
+            let max_bound = origin_chunk_pos.0 + bound;
 
             for x in min_bound.x..=max_bound.x {
                 for y in min_bound.y..=max_bound.y {
@@ -113,14 +262,24 @@ mod systems {
                             continue;
                         }
 
-                        let distance = (*chunk_pos - origin_chunk_pos).0.abs();
+                        let distance =
+                            render_distance.chunk_distance((*chunk_pos - origin_chunk_pos).0);
 
-                        if distance.as_vec3().length() <= render_distance.val as f32 {
-                            chunk_load_queue.push_chunk(ChunkLoadQueueInput::Load(*chunk_pos));
+                        if distance <= render_distance.val as f32 {
+                            missing_chunks.push((distance, *chunk_pos));
                         }
                     }
                 }
             }
+
+            missing_chunks.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+            let budget = render_distance
+                .budget
+                .map_or(usize::MAX, |budget| budget as usize);
+
+            for (_, chunk_pos) in missing_chunks.into_iter().take(budget) {
+                chunk_load_queue.push_chunk(ChunkLoadQueueInput::Load(chunk_pos));
+            }
         }
     }
 
@@ -130,58 +289,91 @@ mod systems {
         mut chunk_load_queue: ResMut<ChunkLoadQueue>,
         voxel_chunk_map: Res<VoxelChunkMap>,
     ) {
-        for (chunk_pos, entity) in voxel_chunk_map.0.iter() {
-            if render_dist_query
-                .iter()
-                .all(|(transform, render_distance)| {
-                    let origin_chunk_pos = transform.translation.as_chunk_pos(&chunk_width);
+        let _span = info_span!("enqueue_unloads").entered();
 
-                    let distance = (*chunk_pos - origin_chunk_pos).0.abs();
+        let observers: Vec<_> = render_dist_query
+            .iter()
+            .map(|(transform, render_distance)| {
+                (
+                    transform.translation.as_chunk_pos(&chunk_width),
+                    render_distance,
+                )
+            })
+            .collect();
 
-                    distance.as_vec3().length()
-                        > (render_distance.val + render_distance.unload_margin) as f32
-                })
+        for (chunk_pos, entity) in voxel_chunk_map.0.iter() {
+            // Every observer keeps chunks within its own margin, so a chunk only goes once none of them keeps it.
+            let kept = observers.iter().any(|(origin_chunk_pos, render_distance)| {
+                render_distance.keeps((*chunk_pos - *origin_chunk_pos).0)
+            });
+
+            if !kept
+                && !chunk_load_queue
+                    .queued_unloads()
+                    .any(|queued| queued == chunk_pos)
             {
                 chunk_load_queue.push_chunk(ChunkLoadQueueInput::Unload((*chunk_pos, *entity)));
             }
         }
     }
 
-    /// This system is responsible for empyting the [ChunkLoadQueue] resource, by loading in chunks.
+    /// This system is responsible for empyting the [ChunkLoadQueue] resource, by loading in up to
+    /// [VoxelConfig::chunk_loads_per_frame] chunks every frame.
     pub(super) fn handle_chunk_loading(
         mut commands: Commands,
-        mut materials: ResMut<Assets<StandardMaterial>>,
+        config: Res<VoxelConfig>,
         mut chunk_load_queue: ResMut<ChunkLoadQueue>,
-        mut chunk_render_queue: ResMut<ChunkRenderQueue>,
         mut voxel_map: ResMut<VoxelChunkMap>,
         chunk_width: Res<VoxelChunkWidth>,
         terrain_noise: Res<TerrainNoise>,
+        voxel_mode: Res<VoxelMode>,
+        world_save: Option<Res<WorldSave>>,
+        mut stats: ResMut<VoxelPipelineStats>,
+        mut loaded_chunks: EventWriter<ChunkLoaded>,
+        mut generated_chunks: EventWriter<ChunkGenerated>,
     ) {
-        loop {
-            // TODO: this could lead to performance issues. Needs to be changed to something where it loads a variable
-            // amount of chunks every frame, instead of ALL of them.
+        let _span = info_span!("chunk_load_queue").entered();
+
+        for _ in 0..config.chunk_loads_per_frame {
             let Some(chunk_pos) = chunk_load_queue.load.front() else {
                 break;
             };
-
-            let chunk = VoxelChunk::from_noise(chunk_pos, &chunk_width, &terrain_noise);
+            // Chunks that changed before are loaded as they were saved, the rest is generated again.
+            let saved_chunk = world_save
+                .as_ref()
+                .and_then(|world_save| world_save.load_chunk(*chunk_pos, &chunk_width));
+            let generated = saved_chunk.is_none();
+            let mut chunk = saved_chunk.unwrap_or_else(|| {
+                stats.chunks_generated += 1;
+                VoxelChunk::from_noise(chunk_pos, &chunk_width, &terrain_noise, *voxel_mode)
+            });
+            // The densities are filled in right away, so the first mesh of the chunk is already drawn from them.
+            chunk.set_densities(config.density_chunks, &chunk_width);
 
             let chunk_entity = commands
                 .spawn(VoxelChunkBundle {
                     transform: Transform::from_translation(chunk_pos.as_world_pos(&chunk_width)),
-                    material: materials.add(Color::GREEN.into()),
                     chunk,
                     chunk_pos: *chunk_pos,
                     ..default()
                 })
                 .id();
 
-            if let Err(_) = voxel_map.insert_chunk(*chunk_pos, chunk_entity) {
+            if voxel_map.insert_chunk(*chunk_pos, chunk_entity).is_err() {
                 commands.entity(chunk_entity).despawn();
                 break;
             }
 
-            chunk_render_queue.push_chunk(chunk_entity);
+            if generated {
+                generated_chunks.send(ChunkGenerated {
+                    chunk_pos: chunk_pos.0,
+                    entity: chunk_entity,
+                });
+            }
+            loaded_chunks.send(ChunkLoaded {
+                chunk_pos: chunk_pos.0,
+                entity: chunk_entity,
+            });
 
             chunk_load_queue.load.pop_front();
         }
@@ -191,47 +383,34 @@ mod systems {
         mut commands: Commands,
         mut chunk_load_queue: ResMut<ChunkLoadQueue>,
         mut voxel_chunk_map: ResMut<VoxelChunkMap>,
+        mut world_save: Option<ResMut<WorldSave>>,
+        chunk_query: Query<Ref<VoxelChunk>>,
+        mut unloaded_chunks: EventWriter<ChunkUnloaded>,
     ) {
-        loop {
-            let Some((chunk_pos, chunk_entity)) = chunk_load_queue.unload.front() else {
-                break;
-            };
+        let _span = info_span!("chunk_unload_queue").entered();
 
+        while let Some((chunk_pos, chunk_entity)) = chunk_load_queue.unload.front() {
             let Some(entity_commands) = commands.get_entity(*chunk_entity) else {
                 break;
             };
 
+            if let (Some(world_save), Ok(chunk)) =
+                (world_save.as_mut(), chunk_query.get(*chunk_entity))
+            {
+                // The chunk could have changed since the changes were last tracked.
+                if chunk.is_changed() && !chunk.is_added() {
+                    world_save.mark_changed(*chunk_pos);
+                }
+                world_save.save_chunk_if_changed(*chunk_pos, &chunk);
+            }
+
             entity_commands.despawn_recursive();
             voxel_chunk_map.0.remove(chunk_pos);
+            unloaded_chunks.send(ChunkUnloaded {
+                chunk_pos: chunk_pos.0,
+                entity: *chunk_entity,
+            });
             chunk_load_queue.unload.pop_front();
         }
     }
-
-    pub(super) fn handle_chunk_rendering(
-        mut commands: Commands,
-        mut meshes: ResMut<Assets<Mesh>>,
-        mut chunk_render_queue: ResMut<ChunkRenderQueue>,
-        chunk_width: Res<VoxelChunkWidth>,
-        chunk_query: Query<&VoxelChunk>,
-        voxel_chunk_map: Res<VoxelChunkMap>,
-    ) {
-        loop {
-            let Some(chunk_entity) = chunk_render_queue.queue.front() else {
-                break;
-            };
-            let Ok(chunk) = chunk_query.get(*chunk_entity) else {
-                break;
-            };
-
-            let mesh = chunk.generate_mesh(&chunk_width, &voxel_chunk_map, &chunk_query);
-
-            if let Some(mut chunk_commands) = commands.get_entity(*chunk_entity) {
-                chunk_commands.insert(meshes.add(mesh));
-            } else {
-                break;
-            };
-
-            chunk_render_queue.queue.pop_front();
-        }
-    }
 }
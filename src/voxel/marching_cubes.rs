@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+
+use super::{
+    density::{DensityField, SmoothSurface, CELL_CORNERS, CELL_EDGES},
+    world::box_positions,
+};
+
+/// Meshes the surface of a [DensityField] with marching cubes. Every cell is filled in with triangles between the
+/// crossings of the surface on its edges, looked up by which of its corners are filled.
+///
+/// The cells run between the centers of voxels, and every chunk meshes the cells starting in it, so the surface runs on
+/// into the next chunk without gaps. `face_color` gives the color of the face of the filled voxel at a position,
+/// pointing in a direction.
+pub(super) fn marching_cubes(
+    field: &DensityField,
+    face_color: impl Fn(IVec3, IVec3) -> Color,
+) -> SmoothSurface {
+    let mut surface = SmoothSurface::default();
+
+    for cell in box_positions(IVec3::ZERO, IVec3::splat(field.width() - 1)) {
+        let triangles = CELL_TRIANGLES[field.cell_case(cell)];
+        if triangles.is_empty() {
+            continue;
+        }
+
+        // Triangles of the same cell share the vertices on its edges.
+        let mut edge_vertices = [None; 12];
+        for edge in triangles.iter().flatten() {
+            let index = *edge_vertices[*edge as usize].get_or_insert_with(|| {
+                let (start, end) = CELL_EDGES[*edge as usize];
+                let (mut filled, mut empty) =
+                    (cell + CELL_CORNERS[start], cell + CELL_CORNERS[end]);
+                if !field.is_filled(filled) {
+                    std::mem::swap(&mut filled, &mut empty);
+                }
+
+                let (position, normal) = field.edge_crossing(filled, empty);
+                surface.push_vertex(position, normal, face_color(filled, empty - filled))
+            });
+            surface.indices.push(index);
+        }
+    }
+
+    surface
+}
+
+/// The triangles of the surface through a marching cubes cell, by the [CELL_EDGES] their corners are on, for every
+/// combination of filled [CELL_CORNERS]. Bit `i` of the index is set if corner `i` is filled.
+///
+/// Triangles wind counter-clockwise seen from the empty side. Faces of the cell with two filled corners diagonal from
+/// each other keep the filled corners apart, on both cells sharing the face, so the surface has no holes.
+#[rustfmt::skip]
+pub(super) const CELL_TRIANGLES: [&[[u8; 3]]; 256] = [
+    &[],
+    &[[0, 4, 8]],
+    &[[0, 9, 5]],
+    &[[4, 8, 9], [4, 9, 5]],
+    &[[1, 10, 4]],
+    &[[0, 1, 10], [0, 10, 8]],
+    &[[0, 9, 5], [1, 10, 4]],
+    &[[1, 10, 8], [1, 8, 9], [1, 9, 5]],
+    &[[1, 5, 11]],
+    &[[0, 4, 8], [1, 5, 11]],
+    &[[0, 9, 11], [0, 11, 1]],
+    &[[1, 4, 8], [1, 8, 9], [1, 9, 11]],
+    &[[4, 5, 11], [4, 11, 10]],
+    &[[0, 5, 11], [0, 11, 10], [0, 10, 8]],
+    &[[0, 9, 11], [0, 11, 10], [0, 10, 4]],
+    &[[8, 9, 11], [8, 11, 10]],
+    &[[2, 8, 6]],
+    &[[0, 4, 6], [0, 6, 2]],
+    &[[0, 9, 5], [2, 8, 6]],
+    &[[2, 9, 5], [2, 5, 4], [2, 4, 6]],
+    &[[1, 10, 4], [2, 8, 6]],
+    &[[0, 1, 10], [0, 10, 6], [0, 6, 2]],
+    &[[0, 9, 5], [1, 10, 4], [2, 8, 6]],
+    &[[1, 10, 6], [1, 6, 2], [1, 2, 9], [1, 9, 5]],
+    &[[1, 5, 11], [2, 8, 6]],
+    &[[0, 4, 6], [0, 6, 2], [1, 5, 11]],
+    &[[0, 9, 11], [0, 11, 1], [2, 8, 6]],
+    &[[1, 4, 6], [1, 6, 2], [1, 2, 9], [1, 9, 11]],
+    &[[2, 8, 6], [4, 5, 11], [4, 11, 10]],
+    &[[0, 5, 11], [0, 11, 10], [0, 10, 6], [0, 6, 2]],
+    &[[0, 9, 11], [0, 11, 10], [0, 10, 4], [2, 8, 6]],
+    &[[2, 9, 11], [2, 11, 10], [2, 10, 6]],
+    &[[2, 7, 9]],
+    &[[0, 4, 8], [2, 7, 9]],
+    &[[0, 2, 7], [0, 7, 5]],
+    &[[2, 7, 5], [2, 5, 4], [2, 4, 8]],
+    &[[1, 10, 4], [2, 7, 9]],
+    &[[0, 1, 10], [0, 10, 8], [2, 7, 9]],
+    &[[0, 2, 7], [0, 7, 5], [1, 10, 4]],
+    &[[1, 10, 8], [1, 8, 2], [1, 2, 7], [1, 7, 5]],
+    &[[1, 5, 11], [2, 7, 9]],
+    &[[0, 4, 8], [1, 5, 11], [2, 7, 9]],
+    &[[0, 2, 7], [0, 7, 11], [0, 11, 1]],
+    &[[1, 4, 8], [1, 8, 2], [1, 2, 7], [1, 7, 11]],
+    &[[2, 7, 9], [4, 5, 11], [4, 11, 10]],
+    &[[0, 5, 11], [0, 11, 10], [0, 10, 8], [2, 7, 9]],
+    &[[0, 2, 7], [0, 7, 11], [0, 11, 10], [0, 10, 4]],
+    &[[2, 7, 11], [2, 11, 10], [2, 10, 8]],
+    &[[6, 7, 9], [6, 9, 8]],
+    &[[0, 4, 6], [0, 6, 7], [0, 7, 9]],
+    &[[0, 8, 6], [0, 6, 7], [0, 7, 5]],
+    &[[4, 6, 7], [4, 7, 5]],
+    &[[1, 10, 4], [6, 7, 9], [6, 9, 8]],
+    &[[0, 1, 10], [0, 10, 6], [0, 6, 7], [0, 7, 9]],
+    &[[0, 8, 6], [0, 6, 7], [0, 7, 5], [1, 10, 4]],
+    &[[1, 10, 6], [1, 6, 7], [1, 7, 5]],
+    &[[1, 5, 11], [6, 7, 9], [6, 9, 8]],
+    &[[0, 4, 6], [0, 6, 7], [0, 7, 9], [1, 5, 11]],
+    &[[0, 8, 6], [0, 6, 7], [0, 7, 11], [0, 11, 1]],
+    &[[1, 4, 6], [1, 6, 7], [1, 7, 11]],
+    &[[4, 5, 11], [4, 11, 10], [6, 7, 9], [6, 9, 8]],
+    &[[0, 5, 11], [0, 11, 10], [0, 10, 6], [0, 6, 7], [0, 7, 9]],
+    &[[0, 8, 6], [0, 6, 7], [0, 7, 11], [0, 11, 10], [0, 10, 4]],
+    &[[6, 7, 11], [6, 11, 10]],
+    &[[3, 6, 10]],
+    &[[0, 4, 8], [3, 6, 10]],
+    &[[0, 9, 5], [3, 6, 10]],
+    &[[3, 6, 10], [4, 8, 9], [4, 9, 5]],
+    &[[1, 3, 6], [1, 6, 4]],
+    &[[0, 1, 3], [0, 3, 6], [0, 6, 8]],
+    &[[0, 9, 5], [1, 3, 6], [1, 6, 4]],
+    &[[1, 3, 6], [1, 6, 8], [1, 8, 9], [1, 9, 5]],
+    &[[1, 5, 11], [3, 6, 10]],
+    &[[0, 4, 8], [1, 5, 11], [3, 6, 10]],
+    &[[0, 9, 11], [0, 11, 1], [3, 6, 10]],
+    &[[1, 4, 8], [1, 8, 9], [1, 9, 11], [3, 6, 10]],
+    &[[3, 6, 4], [3, 4, 5], [3, 5, 11]],
+    &[[0, 5, 11], [0, 11, 3], [0, 3, 6], [0, 6, 8]],
+    &[[0, 9, 11], [0, 11, 3], [0, 3, 6], [0, 6, 4]],
+    &[[3, 6, 8], [3, 8, 9], [3, 9, 11]],
+    &[[2, 8, 10], [2, 10, 3]],
+    &[[0, 4, 10], [0, 10, 3], [0, 3, 2]],
+    &[[0, 9, 5], [2, 8, 10], [2, 10, 3]],
+    &[[2, 9, 5], [2, 5, 4], [2, 4, 10], [2, 10, 3]],
+    &[[1, 3, 2], [1, 2, 8], [1, 8, 4]],
+    &[[0, 1, 3], [0, 3, 2]],
+    &[[0, 9, 5], [1, 3, 2], [1, 2, 8], [1, 8, 4]],
+    &[[1, 3, 2], [1, 2, 9], [1, 9, 5]],
+    &[[1, 5, 11], [2, 8, 10], [2, 10, 3]],
+    &[[0, 4, 10], [0, 10, 3], [0, 3, 2], [1, 5, 11]],
+    &[[0, 9, 11], [0, 11, 1], [2, 8, 10], [2, 10, 3]],
+    &[[4, 10, 3], [4, 3, 2], [4, 2, 9], [4, 9, 11], [4, 11, 1]],
+    &[[2, 8, 4], [2, 4, 5], [2, 5, 11], [2, 11, 3]],
+    &[[0, 5, 11], [0, 11, 3], [0, 3, 2]],
+    &[[11, 3, 2], [11, 2, 8], [11, 8, 4], [11, 4, 0], [11, 0, 9]],
+    &[[2, 9, 11], [2, 11, 3]],
+    &[[2, 7, 9], [3, 6, 10]],
+    &[[0, 4, 8], [2, 7, 9], [3, 6, 10]],
+    &[[0, 2, 7], [0, 7, 5], [3, 6, 10]],
+    &[[2, 7, 5], [2, 5, 4], [2, 4, 8], [3, 6, 10]],
+    &[[1, 3, 6], [1, 6, 4], [2, 7, 9]],
+    &[[0, 1, 3], [0, 3, 6], [0, 6, 8], [2, 7, 9]],
+    &[[0, 2, 7], [0, 7, 5], [1, 3, 6], [1, 6, 4]],
+    &[[1, 3, 6], [1, 6, 8], [1, 8, 2], [1, 2, 7], [1, 7, 5]],
+    &[[1, 5, 11], [2, 7, 9], [3, 6, 10]],
+    &[[0, 4, 8], [1, 5, 11], [2, 7, 9], [3, 6, 10]],
+    &[[0, 2, 7], [0, 7, 11], [0, 11, 1], [3, 6, 10]],
+    &[[1, 4, 8], [1, 8, 2], [1, 2, 7], [1, 7, 11], [3, 6, 10]],
+    &[[2, 7, 9], [3, 6, 4], [3, 4, 5], [3, 5, 11]],
+    &[[0, 5, 11], [0, 11, 3], [0, 3, 6], [0, 6, 8], [2, 7, 9]],
+    &[[0, 2, 7], [0, 7, 11], [0, 11, 3], [0, 3, 6], [0, 6, 4]],
+    &[[11, 3, 6], [11, 6, 8], [11, 8, 2], [11, 2, 7]],
+    &[[3, 7, 9], [3, 9, 8], [3, 8, 10]],
+    &[[0, 4, 10], [0, 10, 3], [0, 3, 7], [0, 7, 9]],
+    &[[0, 8, 10], [0, 10, 3], [0, 3, 7], [0, 7, 5]],
+    &[[3, 7, 5], [3, 5, 4], [3, 4, 10]],
+    &[[1, 3, 7], [1, 7, 9], [1, 9, 8], [1, 8, 4]],
+    &[[0, 1, 3], [0, 3, 7], [0, 7, 9]],
+    &[[8, 4, 1], [8, 1, 3], [8, 3, 7], [8, 7, 5], [8, 5, 0]],
+    &[[1, 3, 7], [1, 7, 5]],
+    &[[1, 5, 11], [3, 7, 9], [3, 9, 8], [3, 8, 10]],
+    &[[0, 4, 10], [0, 10, 3], [0, 3, 7], [0, 7, 9], [1, 5, 11]],
+    &[[0, 8, 10], [0, 10, 3], [0, 3, 7], [0, 7, 11], [0, 11, 1]],
+    &[[4, 10, 3], [4, 3, 7], [4, 7, 11], [4, 11, 1]],
+    &[[3, 7, 9], [3, 9, 8], [3, 8, 4], [3, 4, 5], [3, 5, 11]],
+    &[[0, 5, 11], [0, 11, 3], [0, 3, 7], [0, 7, 9]],
+    &[[0, 8, 4], [3, 7, 11]],
+    &[[3, 7, 11]],
+    &[[3, 11, 7]],
+    &[[0, 4, 8], [3, 11, 7]],
+    &[[0, 9, 5], [3, 11, 7]],
+    &[[3, 11, 7], [4, 8, 9], [4, 9, 5]],
+    &[[1, 10, 4], [3, 11, 7]],
+    &[[0, 1, 10], [0, 10, 8], [3, 11, 7]],
+    &[[0, 9, 5], [1, 10, 4], [3, 11, 7]],
+    &[[1, 10, 8], [1, 8, 9], [1, 9, 5], [3, 11, 7]],
+    &[[1, 5, 7], [1, 7, 3]],
+    &[[0, 4, 8], [1, 5, 7], [1, 7, 3]],
+    &[[0, 9, 7], [0, 7, 3], [0, 3, 1]],
+    &[[1, 4, 8], [1, 8, 9], [1, 9, 7], [1, 7, 3]],
+    &[[3, 10, 4], [3, 4, 5], [3, 5, 7]],
+    &[[0, 5, 7], [0, 7, 3], [0, 3, 10], [0, 10, 8]],
+    &[[0, 9, 7], [0, 7, 3], [0, 3, 10], [0, 10, 4]],
+    &[[3, 10, 8], [3, 8, 9], [3, 9, 7]],
+    &[[2, 8, 6], [3, 11, 7]],
+    &[[0, 4, 6], [0, 6, 2], [3, 11, 7]],
+    &[[0, 9, 5], [2, 8, 6], [3, 11, 7]],
+    &[[2, 9, 5], [2, 5, 4], [2, 4, 6], [3, 11, 7]],
+    &[[1, 10, 4], [2, 8, 6], [3, 11, 7]],
+    &[[0, 1, 10], [0, 10, 6], [0, 6, 2], [3, 11, 7]],
+    &[[0, 9, 5], [1, 10, 4], [2, 8, 6], [3, 11, 7]],
+    &[[1, 10, 6], [1, 6, 2], [1, 2, 9], [1, 9, 5], [3, 11, 7]],
+    &[[1, 5, 7], [1, 7, 3], [2, 8, 6]],
+    &[[0, 4, 6], [0, 6, 2], [1, 5, 7], [1, 7, 3]],
+    &[[0, 9, 7], [0, 7, 3], [0, 3, 1], [2, 8, 6]],
+    &[[1, 4, 6], [1, 6, 2], [1, 2, 9], [1, 9, 7], [1, 7, 3]],
+    &[[2, 8, 6], [3, 10, 4], [3, 4, 5], [3, 5, 7]],
+    &[[0, 5, 7], [0, 7, 3], [0, 3, 10], [0, 10, 6], [0, 6, 2]],
+    &[[0, 9, 7], [0, 7, 3], [0, 3, 10], [0, 10, 4], [2, 8, 6]],
+    &[[9, 7, 3], [9, 3, 10], [9, 10, 6], [9, 6, 2]],
+    &[[2, 3, 11], [2, 11, 9]],
+    &[[0, 4, 8], [2, 3, 11], [2, 11, 9]],
+    &[[0, 2, 3], [0, 3, 11], [0, 11, 5]],
+    &[[2, 3, 11], [2, 11, 5], [2, 5, 4], [2, 4, 8]],
+    &[[1, 10, 4], [2, 3, 11], [2, 11, 9]],
+    &[[0, 1, 10], [0, 10, 8], [2, 3, 11], [2, 11, 9]],
+    &[[0, 2, 3], [0, 3, 11], [0, 11, 5], [1, 10, 4]],
+    &[[8, 2, 3], [8, 3, 11], [8, 11, 5], [8, 5, 1], [8, 1, 10]],
+    &[[1, 5, 9], [1, 9, 2], [1, 2, 3]],
+    &[[0, 4, 8], [1, 5, 9], [1, 9, 2], [1, 2, 3]],
+    &[[0, 2, 3], [0, 3, 1]],
+    &[[1, 4, 8], [1, 8, 2], [1, 2, 3]],
+    &[[2, 3, 10], [2, 10, 4], [2, 4, 5], [2, 5, 9]],
+    &[[5, 9, 2], [5, 2, 3], [5, 3, 10], [5, 10, 8], [5, 8, 0]],
+    &[[0, 2, 3], [0, 3, 10], [0, 10, 4]],
+    &[[2, 3, 10], [2, 10, 8]],
+    &[[3, 11, 9], [3, 9, 8], [3, 8, 6]],
+    &[[0, 4, 6], [0, 6, 3], [0, 3, 11], [0, 11, 9]],
+    &[[0, 8, 6], [0, 6, 3], [0, 3, 11], [0, 11, 5]],
+    &[[3, 11, 5], [3, 5, 4], [3, 4, 6]],
+    &[[1, 10, 4], [3, 11, 9], [3, 9, 8], [3, 8, 6]],
+    &[[0, 1, 10], [0, 10, 6], [0, 6, 3], [0, 3, 11], [0, 11, 9]],
+    &[[0, 8, 6], [0, 6, 3], [0, 3, 11], [0, 11, 5], [1, 10, 4]],
+    &[[6, 3, 11], [6, 11, 5], [6, 5, 1], [6, 1, 10]],
+    &[[1, 5, 9], [1, 9, 8], [1, 8, 6], [1, 6, 3]],
+    &[[6, 3, 1], [6, 1, 5], [6, 5, 9], [6, 9, 0], [6, 0, 4]],
+    &[[0, 8, 6], [0, 6, 3], [0, 3, 1]],
+    &[[1, 4, 6], [1, 6, 3]],
+    &[[3, 10, 4], [3, 4, 5], [3, 5, 9], [3, 9, 8], [3, 8, 6]],
+    &[[0, 5, 9], [3, 10, 6]],
+    &[[0, 8, 6], [0, 6, 3], [0, 3, 10], [0, 10, 4]],
+    &[[3, 10, 6]],
+    &[[6, 10, 11], [6, 11, 7]],
+    &[[0, 4, 8], [6, 10, 11], [6, 11, 7]],
+    &[[0, 9, 5], [6, 10, 11], [6, 11, 7]],
+    &[[4, 8, 9], [4, 9, 5], [6, 10, 11], [6, 11, 7]],
+    &[[1, 11, 7], [1, 7, 6], [1, 6, 4]],
+    &[[0, 1, 11], [0, 11, 7], [0, 7, 6], [0, 6, 8]],
+    &[[0, 9, 5], [1, 11, 7], [1, 7, 6], [1, 6, 4]],
+    &[[1, 11, 7], [1, 7, 6], [1, 6, 8], [1, 8, 9], [1, 9, 5]],
+    &[[1, 5, 7], [1, 7, 6], [1, 6, 10]],
+    &[[0, 4, 8], [1, 5, 7], [1, 7, 6], [1, 6, 10]],
+    &[[0, 9, 7], [0, 7, 6], [0, 6, 10], [0, 10, 1]],
+    &[[1, 4, 8], [1, 8, 9], [1, 9, 7], [1, 7, 6], [1, 6, 10]],
+    &[[4, 5, 7], [4, 7, 6]],
+    &[[0, 5, 7], [0, 7, 6], [0, 6, 8]],
+    &[[0, 9, 7], [0, 7, 6], [0, 6, 4]],
+    &[[6, 8, 9], [6, 9, 7]],
+    &[[2, 8, 10], [2, 10, 11], [2, 11, 7]],
+    &[[0, 4, 10], [0, 10, 11], [0, 11, 7], [0, 7, 2]],
+    &[[0, 9, 5], [2, 8, 10], [2, 10, 11], [2, 11, 7]],
+    &[[2, 9, 5], [2, 5, 4], [2, 4, 10], [2, 10, 11], [2, 11, 7]],
+    &[[1, 11, 7], [1, 7, 2], [1, 2, 8], [1, 8, 4]],
+    &[[0, 1, 11], [0, 11, 7], [0, 7, 2]],
+    &[[0, 9, 5], [1, 11, 7], [1, 7, 2], [1, 2, 8], [1, 8, 4]],
+    &[[1, 11, 7], [1, 7, 2], [1, 2, 9], [1, 9, 5]],
+    &[[1, 5, 7], [1, 7, 2], [1, 2, 8], [1, 8, 10]],
+    &[[10, 1, 5], [10, 5, 7], [10, 7, 2], [10, 2, 0], [10, 0, 4]],
+    &[[7, 2, 8], [7, 8, 10], [7, 10, 1], [7, 1, 0], [7, 0, 9]],
+    &[[1, 4, 10], [2, 9, 7]],
+    &[[2, 8, 4], [2, 4, 5], [2, 5, 7]],
+    &[[0, 5, 7], [0, 7, 2]],
+    &[[7, 2, 8], [7, 8, 4], [7, 4, 0], [7, 0, 9]],
+    &[[2, 9, 7]],
+    &[[2, 6, 10], [2, 10, 11], [2, 11, 9]],
+    &[[0, 4, 8], [2, 6, 10], [2, 10, 11], [2, 11, 9]],
+    &[[0, 2, 6], [0, 6, 10], [0, 10, 11], [0, 11, 5]],
+    &[[2, 6, 10], [2, 10, 11], [2, 11, 5], [2, 5, 4], [2, 4, 8]],
+    &[[1, 11, 9], [1, 9, 2], [1, 2, 6], [1, 6, 4]],
+    &[[1, 11, 9], [1, 9, 2], [1, 2, 6], [1, 6, 8], [1, 8, 0]],
+    &[[2, 6, 4], [2, 4, 1], [2, 1, 11], [2, 11, 5], [2, 5, 0]],
+    &[[1, 11, 5], [2, 6, 8]],
+    &[[1, 5, 9], [1, 9, 2], [1, 2, 6], [1, 6, 10]],
+    &[[0, 4, 8], [1, 5, 9], [1, 9, 2], [1, 2, 6], [1, 6, 10]],
+    &[[0, 2, 6], [0, 6, 10], [0, 10, 1]],
+    &[[1, 4, 8], [1, 8, 2], [1, 2, 6], [1, 6, 10]],
+    &[[2, 6, 4], [2, 4, 5], [2, 5, 9]],
+    &[[5, 9, 2], [5, 2, 6], [5, 6, 8], [5, 8, 0]],
+    &[[0, 2, 6], [0, 6, 4]],
+    &[[2, 6, 8]],
+    &[[8, 10, 11], [8, 11, 9]],
+    &[[0, 4, 10], [0, 10, 11], [0, 11, 9]],
+    &[[0, 8, 10], [0, 10, 11], [0, 11, 5]],
+    &[[4, 10, 11], [4, 11, 5]],
+    &[[1, 11, 9], [1, 9, 8], [1, 8, 4]],
+    &[[0, 1, 11], [0, 11, 9]],
+    &[[8, 4, 1], [8, 1, 11], [8, 11, 5], [8, 5, 0]],
+    &[[1, 11, 5]],
+    &[[1, 5, 9], [1, 9, 8], [1, 8, 10]],
+    &[[10, 1, 5], [10, 5, 9], [10, 9, 0], [10, 0, 4]],
+    &[[0, 8, 10], [0, 10, 1]],
+    &[[1, 4, 10]],
+    &[[4, 5, 9], [4, 9, 8]],
+    &[[0, 5, 9]],
+    &[[0, 8, 4]],
+    &[],
+];
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How the terrain of a world is meshed. It's saved with the world like the
+/// [TerrainPreset](super::preset::TerrainPreset), and sent to clients joining it.
+///
+/// Either way the world is made of the same voxels, generated from the same noise, so only how it looks changes.
+/// Players still collide with the voxels, which smooth terrain is up to half a voxel off from.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerrainMesher {
+    /// Every voxel is drawn as a cube.
+    #[default]
+    Cubes,
+    /// Opaque voxels are drawn as smooth rolling terrain, with marching cubes over a density derived from which voxels
+    /// are filled, see [DensityField](super::density::DensityField). Its slopes don't fit voxel textures, so they're
+    /// drawn with the voxel colors only. Every other voxel is still drawn as a cube.
+    MarchingCubes,
+    /// Smooth terrain like [TerrainMesher::MarchingCubes], but with dual contouring, which keeps ridges and corners
+    /// sharp, and needs about half as many triangles for the same surface.
+    DualContouring,
+}
+
+impl TerrainMesher {
+    const ALL: [TerrainMesher; 3] = [
+        TerrainMesher::Cubes,
+        TerrainMesher::MarchingCubes,
+        TerrainMesher::DualContouring,
+    ];
+
+    /// The mesher with the given name, see [TerrainMesher::name]. The error lists the names there are.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|mesher| mesher.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|mesher| mesher.name()).collect();
+                format!(
+                    "Unknown mesher {name:?}, expected one of {}",
+                    names.join(", ")
+                )
+            })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TerrainMesher::Cubes => "cubes",
+            TerrainMesher::MarchingCubes => "marching_cubes",
+            TerrainMesher::DualContouring => "dual_contouring",
+        }
+    }
+
+    /// Whether opaque voxels are drawn as smooth terrain, rather than cubes.
+    pub(super) fn is_smooth(&self) -> bool {
+        match self {
+            TerrainMesher::Cubes => false,
+            TerrainMesher::MarchingCubes | TerrainMesher::DualContouring => true,
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    data::VoxelData,
+    generation::{
+        ChunkMeshSection, LocalVoxelPosition, VoxelChunk, VoxelChunkMap, VoxelChunkPosition,
+        VoxelChunkWidth,
+    },
+    mesher::TerrainMesher,
+    render::ChunkMeshingSet,
+    shading::ChunkShading,
+    Voxel,
+};
+
+/// How many micro voxels wide a [MicroBlock] is.
+pub(super) const MICRO_BLOCK_WIDTH: i32 = 8;
+
+/// This plugin applies [MicroVoxelEdit]s to the world, and forgets the [MicroBlocks] of chunks that are unloaded. Like
+/// the [VoxelEditPlugin](super::edit::VoxelEditPlugin), it's part of the simulation.
+///
+/// A voxel is subdivided into a [MicroBlock] by the first micro edit in it, and turns back into a whole voxel once all
+/// of its micro voxels are the same again. Meanwhile the chunk holds a [Voxel::MICRO_BLOCK] in its place, which
+/// collides and is targeted like any solid voxel, so breaking it as a whole still works.
+pub(super) struct VoxelMicroBlockPlugin;
+
+impl Plugin for VoxelMicroBlockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MicroBlocks>()
+            .add_event::<MicroVoxelEdit>()
+            .add_event::<AppliedMicroVoxelEdit>()
+            .add_systems(
+                Update,
+                (
+                    systems::apply_micro_voxel_edits,
+                    systems::forget_unloaded_micro_blocks,
+                ),
+            );
+    }
+}
+
+/// This plugin draws the [MicroBlocks] of every chunk meshed at full detail, as child entities of the chunk. Micro
+/// blocks are specific to [Voxel]s, so they're drawn apart from the
+/// [VoxelChunkRenderingPlugin](super::render::VoxelChunkRenderingPlugin).
+pub(super) struct VoxelMicroBlockRenderingPlugin;
+
+impl Plugin for VoxelMicroBlockRenderingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MicroBlocks>()
+            .add_systems(Update, systems::mesh_micro_blocks.after(ChunkMeshingSet));
+    }
+}
+
+/// A voxel subdivided into [MICRO_BLOCK_WIDTH]³ micro voxels, for builds with more detail than whole voxels.
+///
+/// Only voxels drawn in the opaque section can be micro voxels, since every micro block is meshed on its own and
+/// drawn with the opaque material.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct MicroBlock {
+    /// Indexed like the voxels of a chunk [MICRO_BLOCK_WIDTH] wide.
+    voxels: Box<[Voxel]>,
+}
+
+impl MicroBlock {
+    /// Subdivides a voxel, filling the micro block with it. See [is_micro_voxel] for the voxels that can be subdivided.
+    pub(super) fn subdivide(voxel: Voxel) -> Self {
+        Self {
+            voxels: vec![voxel; MICRO_BLOCK_WIDTH.pow(3) as usize].into_boxed_slice(),
+        }
+    }
+
+    /// A micro block of the given micro voxels. Returns [None] if there aren't exactly [MICRO_BLOCK_WIDTH]³ of them, or
+    /// some can't be micro voxels.
+    pub(super) fn from_voxels(voxels: Vec<Voxel>) -> Option<Self> {
+        (voxels.len() == MICRO_BLOCK_WIDTH.pow(3) as usize
+            && voxels.iter().all(|voxel| is_micro_voxel(*voxel)))
+        .then(|| Self {
+            voxels: voxels.into_boxed_slice(),
+        })
+    }
+
+    pub(super) fn voxels(&self) -> &[Voxel] {
+        &self.voxels
+    }
+
+    /// Gets the micro voxel at a position in the micro block, from 0 to [MICRO_BLOCK_WIDTH] - 1 on every axis.
+    pub(super) fn get(&self, micro_pos: IVec3) -> Option<Voxel> {
+        micro_index(micro_pos).map(|index| self.voxels[index])
+    }
+
+    /// Replaces the micro voxel at a position in the micro block. Positions outside of it are ignored.
+    pub(super) fn set(&mut self, micro_pos: IVec3, voxel: Voxel) {
+        if let Some(index) = micro_index(micro_pos) {
+            self.voxels[index] = voxel;
+        }
+    }
+
+    /// The voxel every micro voxel is, if they're all the same.
+    pub(super) fn uniform_voxel(&self) -> Option<Voxel> {
+        let first = self.voxels[0];
+        self.voxels
+            .iter()
+            .all(|voxel| *voxel == first)
+            .then_some(first)
+    }
+
+    /// The micro voxels as a chunk [MICRO_BLOCK_WIDTH] wide, to mesh them like one.
+    pub(super) fn to_chunk(&self) -> VoxelChunk {
+        VoxelChunk::from_voxels(self.voxels.to_vec())
+    }
+
+    /// Meshes the micro voxels of the micro block of the voxel at `voxel_pos`, in micro voxels from its first micro
+    /// voxel. Faces against the micro voxels of the voxels around it are hidden like within the micro block.
+    ///
+    /// Micro blocks are always meshed as flat cubes with full vertices, whatever the chunks are meshed with.
+    pub(super) fn generate_mesh(
+        &self,
+        voxel_pos: IVec3,
+        micro_blocks: &MicroBlocks,
+        chunk_width: &VoxelChunkWidth,
+        voxel_map: &VoxelChunkMap,
+        voxel_chunk_query: &Query<&VoxelChunk>,
+    ) -> Mesh {
+        let world_voxel = |voxel_pos: IVec3| {
+            let (chunk_pos, local_pos) = VoxelChunkPosition::world_to_local(voxel_pos, chunk_width);
+            voxel_map.get_voxel(&chunk_pos, &local_pos, chunk_width, voxel_chunk_query)
+        };
+
+        self.to_chunk().mesh_voxels(
+            ChunkMeshSection::Opaque,
+            false,
+            TerrainMesher::Cubes,
+            ChunkShading::FLAT,
+            &VoxelChunkWidth(MICRO_BLOCK_WIDTH as u8),
+            1,
+            None,
+            |local_pos, offset| {
+                let micro_pos = local_pos.as_ivec3() + offset;
+                self.get(micro_pos).or_else(|| {
+                    micro_blocks.micro_voxel(
+                        voxel_pos * MICRO_BLOCK_WIDTH + micro_pos,
+                        chunk_width,
+                        world_voxel,
+                    )
+                })
+            },
+            |_, _| None,
+        )
+    }
+}
+
+/// Whether a voxel can be a micro voxel, and be subdivided into micro voxels. See [MicroBlock].
+pub(super) fn is_micro_voxel(voxel: Voxel) -> bool {
+    voxel == Voxel::AIR || voxel.mesh_section() == Some(ChunkMeshSection::Opaque)
+}
+
+fn micro_index(micro_pos: IVec3) -> Option<usize> {
+    (micro_pos.cmpge(IVec3::ZERO).all() && micro_pos.cmplt(IVec3::splat(MICRO_BLOCK_WIDTH)).all())
+        .then(|| {
+            LocalVoxelPosition::from_ivec3(micro_pos)
+                .to_index(&VoxelChunkWidth(MICRO_BLOCK_WIDTH as u8))
+        })
+}
+
+/// The [MicroBlock]s of the loaded chunks, by the world voxel position of the voxel they subdivide. Only chunks with
+/// micro blocks have an entry, so whole voxels don't take up any more memory.
+///
+/// A micro block only counts while its voxel is a [Voxel::MICRO_BLOCK]. Replacing the voxel, like by breaking it as a
+/// whole or blowing it up, leaves the micro block behind until the voxel is subdivided again.
+#[derive(Resource, Default, Debug)]
+pub(super) struct MicroBlocks(HashMap<VoxelChunkPosition, HashMap<IVec3, MicroBlock>>);
+
+impl MicroBlocks {
+    pub(super) fn get(
+        &self,
+        voxel_pos: IVec3,
+        chunk_width: &VoxelChunkWidth,
+    ) -> Option<&MicroBlock> {
+        let (chunk_pos, _) = VoxelChunkPosition::world_to_local(voxel_pos, chunk_width);
+        self.0.get(&chunk_pos)?.get(&voxel_pos)
+    }
+
+    /// Replaces the micro block of a voxel, or removes it with [None].
+    pub(super) fn set(
+        &mut self,
+        voxel_pos: IVec3,
+        micro_block: Option<MicroBlock>,
+        chunk_width: &VoxelChunkWidth,
+    ) {
+        let (chunk_pos, _) = VoxelChunkPosition::world_to_local(voxel_pos, chunk_width);
+
+        match micro_block {
+            Some(micro_block) => {
+                self.0
+                    .entry(chunk_pos)
+                    .or_default()
+                    .insert(voxel_pos, micro_block);
+            }
+            None => {
+                if let Some(chunk) = self.0.get_mut(&chunk_pos) {
+                    chunk.remove(&voxel_pos);
+                    if chunk.is_empty() {
+                        self.0.remove(&chunk_pos);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The micro blocks in a chunk, with the world voxel positions of their voxels.
+    pub(super) fn chunk(
+        &self,
+        chunk_pos: VoxelChunkPosition,
+    ) -> impl Iterator<Item = (IVec3, &MicroBlock)> + '_ {
+        self.0
+            .get(&chunk_pos)
+            .into_iter()
+            .flat_map(|chunk| chunk.iter().map(|(voxel_pos, block)| (*voxel_pos, block)))
+    }
+
+    /// Forgets the micro blocks of a chunk, like when it's unloaded.
+    pub(super) fn remove_chunk(&mut self, chunk_pos: VoxelChunkPosition) {
+        self.0.remove(&chunk_pos);
+    }
+
+    /// The micro voxel at a world micro position, which is the world voxel position times [MICRO_BLOCK_WIDTH] plus the
+    /// position in the micro block. Whole voxels are the same at every micro position in them.
+    pub(super) fn micro_voxel(
+        &self,
+        micro_pos: IVec3,
+        chunk_width: &VoxelChunkWidth,
+        get_voxel: impl Fn(IVec3) -> Option<Voxel>,
+    ) -> Option<Voxel> {
+        let voxel_pos = micro_pos.div_euclid(IVec3::splat(MICRO_BLOCK_WIDTH));
+        let voxel = get_voxel(voxel_pos)?;
+        if voxel != Voxel::MICRO_BLOCK {
+            return Some(voxel);
+        }
+
+        self.get(voxel_pos, chunk_width)?
+            .get(micro_pos.rem_euclid(IVec3::splat(MICRO_BLOCK_WIDTH)))
+    }
+}
+
+/// Event sent when a player wants to change a single micro voxel. Like a [VoxelEdit](super::edit::VoxelEdit), it's
+/// only a request, applied where the world is simulated.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(super) struct MicroVoxelEdit {
+    /// The world voxel position of the voxel the micro voxel is in.
+    pub(super) voxel_pos: IVec3,
+    /// The position of the micro voxel in the voxel, see [MicroBlock::get].
+    pub(super) micro_pos: IVec3,
+    pub(super) voxel: Voxel,
+}
+
+/// Marker component for the child entities of a chunk drawing its [MicroBlock]s, one for every micro block.
+#[derive(Component)]
+struct MicroBlockMesh;
+
+/// Event sent once a [MicroVoxelEdit] has actually changed the world.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub(super) struct AppliedMicroVoxelEdit(pub(super) MicroVoxelEdit);
+
+mod systems {
+    use crate::voxel::{
+        load::{ChunkMeshed, ChunkUnloaded},
+        render::{ChunkLod, ChunkMaterials},
+        world::VoxelWorld,
+    };
+
+    use super::*;
+
+    /// Sets the edited micro voxels, subdividing whole voxels on the way, and merges micro blocks that became uniform
+    /// back into whole voxels. Indestructible voxels, and voxels that can't be micro voxels, like fluids, can't
+    /// be subdivided.
+    pub(super) fn apply_micro_voxel_edits(
+        mut edits: EventReader<MicroVoxelEdit>,
+        mut applied_edits: EventWriter<AppliedMicroVoxelEdit>,
+        mut voxel_world: VoxelWorld,
+        mut micro_blocks: ResMut<MicroBlocks>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        for edit in edits.read() {
+            if !is_micro_voxel(edit.voxel) {
+                continue;
+            }
+            let Some(current) = voxel_world.get_block(edit.voxel_pos) else {
+                continue;
+            };
+            if current.is_indestructible()
+                || (current != Voxel::MICRO_BLOCK && !is_micro_voxel(current))
+            {
+                continue;
+            }
+
+            let mut micro_block = match micro_blocks.get(edit.voxel_pos, &chunk_width) {
+                Some(micro_block) if current == Voxel::MICRO_BLOCK => micro_block.clone(),
+                // The micro block went missing, like when its save couldn't be read.
+                None if current == Voxel::MICRO_BLOCK => MicroBlock::subdivide(Voxel::AIR),
+                _ => MicroBlock::subdivide(current),
+            };
+            if !micro_block
+                .get(edit.micro_pos)
+                .is_some_and(|voxel| voxel != edit.voxel)
+            {
+                continue;
+            }
+            micro_block.set(edit.micro_pos, edit.voxel);
+
+            match micro_block.uniform_voxel() {
+                Some(voxel) => {
+                    micro_blocks.set(edit.voxel_pos, None, &chunk_width);
+                    voxel_world.set_block(edit.voxel_pos, voxel);
+                }
+                None => {
+                    micro_blocks.set(edit.voxel_pos, Some(micro_block), &chunk_width);
+                    if current == Voxel::MICRO_BLOCK {
+                        voxel_world.remesh_block(edit.voxel_pos);
+                    } else {
+                        voxel_world.set_block(edit.voxel_pos, Voxel::MICRO_BLOCK);
+                    }
+                }
+            }
+
+            applied_edits.send(AppliedMicroVoxelEdit(*edit));
+        }
+    }
+
+    pub(super) fn forget_unloaded_micro_blocks(
+        mut unloaded_chunks: EventReader<ChunkUnloaded>,
+        mut micro_blocks: ResMut<MicroBlocks>,
+    ) {
+        for unloaded in unloaded_chunks.read() {
+            micro_blocks.remove_chunk(VoxelChunkPosition(unloaded.chunk_pos));
+        }
+    }
+
+    /// Replaces the micro block meshes of every chunk that was just meshed. Chunks meshed with less detail don't get
+    /// any.
+    pub(super) fn mesh_micro_blocks(
+        mut commands: Commands,
+        mut meshed_chunks: EventReader<ChunkMeshed>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        chunk_query: Query<(&VoxelChunk, &VoxelChunkPosition, &ChunkLod, &Children)>,
+        micro_mesh_query: Query<(), With<MicroBlockMesh>>,
+        voxel_chunk_query: Query<&VoxelChunk>,
+        voxel_chunk_map: Res<VoxelChunkMap>,
+        micro_blocks: Res<MicroBlocks>,
+        chunk_materials: Res<ChunkMaterials>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        for meshed in meshed_chunks.read() {
+            let Ok((chunk, chunk_pos, chunk_lod, children)) = chunk_query.get(meshed.entity) else {
+                continue;
+            };
+
+            for micro_mesh_entity in children
+                .iter()
+                .filter(|child| micro_mesh_query.contains(**child))
+            {
+                commands.entity(*micro_mesh_entity).despawn_recursive();
+            }
+            if chunk_lod.0 != 0 {
+                continue;
+            }
+
+            let chunk_origin = chunk_pos.0 * chunk_width.0 as i32;
+            let micro_scale = 1.0 / MICRO_BLOCK_WIDTH as f32;
+
+            for (voxel_pos, micro_block) in micro_blocks.chunk(*chunk_pos) {
+                let (_, local_pos) = VoxelChunkPosition::world_to_local(voxel_pos, &chunk_width);
+                if chunk.get_voxel(local_pos, &chunk_width) != Some(Voxel::MICRO_BLOCK) {
+                    continue;
+                }
+
+                let mesh = micro_block.generate_mesh(
+                    voxel_pos,
+                    &micro_blocks,
+                    &chunk_width,
+                    &voxel_chunk_map,
+                    &voxel_chunk_query,
+                );
+                // The micro voxels are centered on their position in the micro block, like voxels in a chunk, so the
+                // first one is half a micro voxel in from the corner of the voxel.
+                let translation = (voxel_pos - chunk_origin).as_vec3() - 0.5 + micro_scale / 2.0;
+                let micro_mesh_entity = commands
+                    .spawn((
+                        MaterialMeshBundle {
+                            mesh: meshes.add(mesh),
+                            material: chunk_materials.get(ChunkMeshSection::Opaque),
+                            transform: Transform::from_translation(translation)
+                                .with_scale(Vec3::splat(micro_scale)),
+                            ..default()
+                        },
+                        MicroBlockMesh,
+                    ))
+                    .id();
+                commands.entity(meshed.entity).add_child(micro_mesh_entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, utils::hashbrown::HashMap};
+
+use super::{generation::VoxelChunkPosition, Voxel};
+
+/// How many voxels (and pixels) wide the minimap is.
+const MINIMAP_SIZE: u32 = 128;
+/// How big the minimap is drawn on screen, in logical pixels.
+const MINIMAP_DISPLAY_SIZE: f32 = 192.0;
+/// Color used for columns that aren't loaded, or don't have any solid voxels.
+const MINIMAP_EMPTY_COLOR: [u8; 4] = [0, 0, 0, 160];
+/// Color of the pixel marking the camera position.
+const MINIMAP_MARKER_COLOR: Color = Color::RED;
+
+/// This plugin draws a top-down map of the loaded chunks in the corner of the screen.
+pub(super) struct VoxelMinimapPlugin;
+
+impl Plugin for VoxelMinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MinimapColumns>()
+            .add_systems(Startup, systems::setup_minimap)
+            .add_systems(
+                Update,
+                (systems::update_minimap_columns, systems::draw_minimap).chain(),
+            );
+    }
+}
+
+/// The minimap image, and the state needed to know when it has to be redrawn.
+#[derive(Resource)]
+struct Minimap {
+    image: Handle<Image>,
+    /// The world voxel column the minimap was last centered on.
+    center: Option<IVec2>,
+    /// Set when the surface of any column changed since the last redraw.
+    dirty: bool,
+}
+
+/// The surface of a single chunk, as returned by [VoxelChunk::surface](super::generation::VoxelChunk::surface).
+type ChunkSurface = Vec<Option<(u8, Voxel)>>;
+
+/// The surface of a single chunk column. Indexed by `z * chunk_width + x`, holding the world y and the voxel.
+type ColumnSurface = Vec<Option<(i32, Voxel)>>;
+
+/// Cache of the top-most solid voxels of the loaded chunks.
+///
+/// This is updated incrementally, so only chunks that were loaded, changed or unloaded are scanned again.
+#[derive(Resource, Default)]
+struct MinimapColumns {
+    /// The surface of every loaded chunk, keyed by chunk column (x, z) and then by the chunk y.
+    chunk_surfaces: HashMap<IVec2, BTreeMap<i32, ChunkSurface>>,
+    /// The combined surface of every chunk column.
+    columns: HashMap<IVec2, ColumnSurface>,
+    /// Used to find the position of a chunk after its entity has been despawned.
+    chunk_entities: HashMap<Entity, VoxelChunkPosition>,
+}
+
+impl MinimapColumns {
+    /// Recomputes the combined surface of a chunk column from the chunk surfaces in it.
+    fn rebuild_column(&mut self, column: IVec2, chunk_width: u8) {
+        let Some(chunks) = self.chunk_surfaces.get(&column) else {
+            self.columns.remove(&column);
+            return;
+        };
+
+        let mut surface = vec![None; chunk_width as usize * chunk_width as usize];
+
+        for (i, column_voxel) in surface.iter_mut().enumerate() {
+            *column_voxel = chunks.iter().rev().find_map(|(chunk_y, chunk_surface)| {
+                chunk_surface[i].map(|(y, voxel)| (chunk_y * chunk_width as i32 + y as i32, voxel))
+            });
+        }
+
+        self.columns.insert(column, surface);
+    }
+
+    /// Gets the top-most solid voxel at a world (x, z) column.
+    fn get(&self, x: i32, z: i32, chunk_width: u8) -> Option<(i32, Voxel)> {
+        let cw = chunk_width as i32;
+        let column = IVec2::new(x.div_euclid(cw), z.div_euclid(cw));
+        let index = (z.rem_euclid(cw) * cw + x.rem_euclid(cw)) as usize;
+
+        self.columns.get(&column)?[index]
+    }
+}
+
+mod systems {
+    use bevy::{
+        render::{
+            render_resource::{Extent3d, TextureDimension, TextureFormat},
+            texture::ImageSampler,
+        },
+        utils::hashbrown::HashSet,
+    };
+
+    use crate::voxel::generation::{VoxelChunk, VoxelChunkWidth};
+
+    use super::*;
+
+    pub(super) fn setup_minimap(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: MINIMAP_SIZE,
+                height: MINIMAP_SIZE,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &MINIMAP_EMPTY_COLOR,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        image.sampler = ImageSampler::nearest();
+
+        let image = images.add(image);
+
+        commands.spawn(ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                right: Val::Px(10.0),
+                width: Val::Px(MINIMAP_DISPLAY_SIZE),
+                height: Val::Px(MINIMAP_DISPLAY_SIZE),
+                ..default()
+            },
+            image: UiImage::new(image.clone()),
+            ..default()
+        });
+
+        commands.insert_resource(Minimap {
+            image,
+            center: None,
+            dirty: true,
+        });
+    }
+
+    pub(super) fn update_minimap_columns(
+        mut columns: ResMut<MinimapColumns>,
+        mut minimap: ResMut<Minimap>,
+        mut removed_chunks: RemovedComponents<VoxelChunk>,
+        chunk_query: Query<(Entity, &VoxelChunk, &VoxelChunkPosition), Changed<VoxelChunk>>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let mut changed_columns = HashSet::new();
+
+        for entity in removed_chunks.read() {
+            let Some(chunk_pos) = columns.chunk_entities.remove(&entity) else {
+                continue;
+            };
+            let column = chunk_pos.0.xz();
+
+            if let Some(chunks) = columns.chunk_surfaces.get_mut(&column) {
+                chunks.remove(&chunk_pos.0.y);
+
+                if chunks.is_empty() {
+                    columns.chunk_surfaces.remove(&column);
+                }
+            }
+
+            changed_columns.insert(column);
+        }
+
+        for (entity, chunk, chunk_pos) in &chunk_query {
+            let column = chunk_pos.0.xz();
+            let surface = chunk.surface(&chunk_width);
+
+            columns.chunk_entities.insert(entity, *chunk_pos);
+            columns
+                .chunk_surfaces
+                .entry(column)
+                .or_default()
+                .insert(chunk_pos.0.y, surface);
+
+            changed_columns.insert(column);
+        }
+
+        for column in changed_columns.iter() {
+            columns.rebuild_column(*column, chunk_width.0);
+        }
+
+        if !changed_columns.is_empty() {
+            minimap.dirty = true;
+        }
+    }
+
+    pub(super) fn draw_minimap(
+        mut minimap: ResMut<Minimap>,
+        mut images: ResMut<Assets<Image>>,
+        columns: Res<MinimapColumns>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let Ok(camera_transform) = camera_query.get_single() else {
+            return;
+        };
+
+        let camera_pos = camera_transform.translation;
+        let center = camera_pos.xz().round().as_ivec2();
+
+        if !minimap.dirty && minimap.center == Some(center) {
+            return;
+        }
+
+        let Some(image) = images.get_mut(&minimap.image) else {
+            return;
+        };
+
+        let half_size = MINIMAP_SIZE as i32 / 2;
+
+        for py in 0..MINIMAP_SIZE as i32 {
+            for px in 0..MINIMAP_SIZE as i32 {
+                let x = center.x - half_size + px;
+                let z = center.y - half_size + py;
+
+                let color = match columns.get(x, z, chunk_width.0) {
+                    Some((height, voxel)) => {
+                        // Columns above the camera are drawn brighter, and columns below it darker.
+                        let shade = (1.0 + (height as f32 - camera_pos.y) / 64.0).clamp(0.3, 1.5);
+                        let color = voxel.color();
+
+                        Color::rgb(color.r() * shade, color.g() * shade, color.b() * shade)
+                            .as_rgba_u8()
+                    }
+                    None => MINIMAP_EMPTY_COLOR,
+                };
+
+                let offset = (py as usize * MINIMAP_SIZE as usize + px as usize) * 4;
+                image.data[offset..offset + 4].copy_from_slice(&color);
+            }
+        }
+
+        let marker_offset = (half_size as usize * MINIMAP_SIZE as usize + half_size as usize) * 4;
+        image.data[marker_offset..marker_offset + 4]
+            .copy_from_slice(&MINIMAP_MARKER_COLOR.as_rgba_u8());
+
+        minimap.center = Some(center);
+        minimap.dirty = false;
+    }
+}
@@ -0,0 +1,434 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+    data::VoxelData,
+    generation::VoxelChunkPosition,
+    pathfinding::{PathRequest, PathResponse, WalkLimits},
+    physics::PhysicsSet,
+    world::VoxelWorld,
+    Voxel,
+};
+
+/// How often mobs try to spawn, in seconds.
+const MOB_SPAWN_INTERVAL: f32 = 1.0;
+/// How many spots are tried for a mob to spawn on, every [MOB_SPAWN_INTERVAL].
+const MOB_SPAWN_ATTEMPTS: usize = 8;
+/// Mobs spawn at least this far from the player, so they don't pop up in view, in voxels.
+const MIN_SPAWN_DISTANCE: f32 = 16.0;
+/// Mobs spawn in the loaded chunks within this distance from the player, in voxels.
+const MAX_SPAWN_DISTANCE: f32 = 64.0;
+/// Mobs further than this from the player despawn, in voxels.
+const DESPAWN_DISTANCE: f32 = 96.0;
+/// The most mobs that can be in a single chunk. No more spawn in a chunk that has this many.
+const MAX_MOBS_PER_CHUNK: usize = 4;
+/// The most mobs that can be around at once.
+const MAX_MOBS: usize = 48;
+/// How far up the sky is looked for, to tell whether a voxel is lit by it, in voxels.
+const SKY_SCAN_HEIGHT: i32 = 64;
+/// How far the light of voxels emitting light reaches, in voxels. The light drops by one for every voxel.
+const BLOCK_LIGHT_RADIUS: i32 = 7;
+const MAX_LIGHT_LEVEL: u8 = 15;
+const MOB_SIZE: f32 = 0.8;
+const MOB_MAX_HEALTH: f32 = 10.0;
+/// How fast mobs walk, in voxels per second.
+const MOB_SPEED: f32 = 1.5;
+/// How fast mobs jump up onto a voxel in their way, in voxels per second.
+const MOB_JUMP_SPEED: f32 = 7.5;
+/// How long mobs keep walking in one direction, or standing still, in seconds.
+const WANDER_TIME: std::ops::Range<f32> = 2.0..6.0;
+/// How far away mobs pick spots to walk to, in voxels.
+const WANDER_RADIUS: i32 = 10;
+/// How close a mob has to get to the center of a voxel on its path to move on to the next one, in voxels.
+const WAYPOINT_RADIUS: f32 = 0.2;
+/// How mobs get around the terrain. They're less than a voxel tall.
+const MOB_WALK_LIMITS: WalkLimits = WalkLimits {
+    height: 1,
+    step_up: 1,
+    max_fall: 3,
+};
+
+/// This plugin spawns mobs on the loaded terrain around the player, and makes them wander around. Wandering mobs walk
+/// to spots around them along paths from the [VoxelPathfindingPlugin](super::pathfinding::VoxelPathfindingPlugin).
+///
+/// Every [MOB_SPAWN_INTERVAL], a few random columns of the chunks around the player are searched for a spot to spawn
+/// on: a solid voxel with room for a mob above it, lit as brightly as the [MobKind] likes. Chunks with
+/// [MAX_MOBS_PER_CHUNK] mobs get no more. Mobs despawn once the player is far enough away, or when they die.
+pub(super) struct VoxelMobPlugin;
+
+impl Plugin for VoxelMobPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MobAssets>()
+            .insert_resource(MobSpawnTimer(Timer::from_seconds(
+                MOB_SPAWN_INTERVAL,
+                TimerMode::Repeating,
+            )))
+            .add_systems(
+                Update,
+                (
+                    systems::spawn_mobs,
+                    systems::receive_paths,
+                    systems::wander,
+                    systems::despawn_far_mobs,
+                    systems::despawn_dead_mobs,
+                )
+                    .chain()
+                    .before(PhysicsSet),
+            );
+    }
+}
+
+/// A creature walking around the world.
+#[derive(Component, Debug)]
+pub(super) struct Mob {
+    pub(super) kind: MobKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(super) enum MobKind {
+    /// Roams the surface during the day.
+    Pig,
+    /// Lurks in caves, and on the surface at night.
+    Slime,
+}
+
+impl MobKind {
+    const ALL: [MobKind; 2] = [MobKind::Pig, MobKind::Slime];
+
+    /// The light levels the mob spawns at.
+    fn spawn_light(&self) -> std::ops::RangeInclusive<u8> {
+        match self {
+            MobKind::Pig => 9..=MAX_LIGHT_LEVEL,
+            MobKind::Slime => 0..=7,
+        }
+    }
+
+    /// Whether the mob hops along instead of walking.
+    fn hops(&self) -> bool {
+        *self == MobKind::Slime
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            MobKind::Pig => Color::rgb(0.95, 0.6, 0.65),
+            MobKind::Slime => Color::rgb(0.35, 0.8, 0.3),
+        }
+    }
+}
+
+/// Where a mob is walking. It picks a new spot to walk to once its timer runs out.
+#[derive(Component, Debug)]
+struct Wander {
+    /// The direction the mob walks in, on the ground. Zero while it stands still.
+    heading: Vec2,
+    /// The world voxel positions left to walk through, towards the spot the mob is walking to. While it's empty, the
+    /// mob walks in its heading.
+    path: Vec<IVec3>,
+    timer: Timer,
+}
+
+#[derive(Resource)]
+struct MobSpawnTimer(Timer);
+
+#[derive(Resource)]
+struct MobAssets {
+    mesh: Handle<Mesh>,
+    materials: HashMap<MobKind, Handle<StandardMaterial>>,
+}
+
+impl FromWorld for MobAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(shape::Cube::new(MOB_SIZE).into());
+        let mut material_assets = world.resource_mut::<Assets<StandardMaterial>>();
+        let materials = MobKind::ALL
+            .into_iter()
+            .map(|kind| (kind, material_assets.add(kind.color().into())))
+            .collect();
+
+        Self { mesh, materials }
+    }
+}
+
+/// Whether a mob can spawn standing in a world voxel position: the voxel below it is solid, and it and the voxel above
+/// it are empty.
+fn is_spawnable(voxel_world: &VoxelWorld, voxel_pos: IVec3) -> bool {
+    let is_empty = |voxel_pos: IVec3| voxel_world.get_block(voxel_pos) == Some(Voxel::AIR);
+
+    voxel_world
+        .get_block(voxel_pos - IVec3::Y)
+        .is_some_and(|voxel| voxel.is_solid())
+        && is_empty(voxel_pos)
+        && is_empty(voxel_pos + IVec3::Y)
+}
+
+/// How brightly a voxel is lit, from 0 to [MAX_LIGHT_LEVEL]. It's the brightest of the sky, if nothing solid is above
+/// the voxel, and the voxels emitting light around it, whose light drops by one for every voxel it travels.
+fn light_level(voxel_world: &VoxelWorld, voxel_pos: IVec3, daylight: f32) -> u8 {
+    // Unloaded voxels above count as open sky.
+    let under_sky = (1..=SKY_SCAN_HEIGHT).all(|dy| {
+        !voxel_world
+            .get_block(voxel_pos + IVec3::Y * dy)
+            .is_some_and(|voxel| voxel.is_solid())
+    });
+    let sky_light = if under_sky {
+        (MAX_LIGHT_LEVEL as f32 * daylight).round() as u8
+    } else {
+        0
+    };
+
+    let radius = IVec3::splat(BLOCK_LIGHT_RADIUS);
+    let block_light = voxel_world
+        .iter_region(voxel_pos - radius, voxel_pos + radius)
+        .filter_map(|(emitter_pos, voxel)| {
+            let offset = (emitter_pos - voxel_pos).abs();
+            let distance = offset.x + offset.y + offset.z;
+            voxel.light_emission().checked_sub(distance as u8)
+        })
+        .max()
+        .unwrap_or(0);
+
+    sky_light.max(block_light)
+}
+
+mod systems {
+    use rand::{seq::SliceRandom, Rng};
+
+    use crate::{
+        sky::TimeOfDay,
+        voxel::{
+            generation::VoxelChunkWidth,
+            health::{Death, Health},
+            physics::{Gravity, TerrainCollider, Velocity},
+        },
+    };
+
+    use super::*;
+
+    /// The camera is the player.
+    pub(super) fn spawn_mobs(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut spawn_timer: ResMut<MobSpawnTimer>,
+        voxel_world: VoxelWorld,
+        chunk_width: Res<VoxelChunkWidth>,
+        assets: Res<MobAssets>,
+        time_of_day: Option<Res<TimeOfDay>>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+        mob_query: Query<&Transform, With<Mob>>,
+    ) {
+        if !spawn_timer.0.tick(time.delta()).just_finished() {
+            return;
+        }
+        let Ok(camera_transform) = camera_query.get_single() else {
+            return;
+        };
+
+        let mut mobs_per_chunk: HashMap<VoxelChunkPosition, usize> = HashMap::new();
+        for transform in &mob_query {
+            let (chunk_pos, _) = VoxelChunkPosition::world_to_local(
+                transform.translation.round().as_ivec3(),
+                &chunk_width,
+            );
+            *mobs_per_chunk.entry(chunk_pos).or_default() += 1;
+        }
+        let mut mob_count = mob_query.iter().count();
+
+        let chunks = voxel_world.chunks_in_sphere(camera_transform.translation, MAX_SPAWN_DISTANCE);
+        let daylight = time_of_day.map_or(1.0, |time_of_day| time_of_day.daylight());
+        let width = chunk_width.0 as i32;
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..MOB_SPAWN_ATTEMPTS {
+            if mob_count >= MAX_MOBS {
+                return;
+            }
+            let Some((chunk_pos, _)) = chunks.choose(&mut rng) else {
+                return;
+            };
+            let chunk_mobs = mobs_per_chunk
+                .entry(VoxelChunkPosition(*chunk_pos))
+                .or_default();
+            if *chunk_mobs >= MAX_MOBS_PER_CHUNK {
+                continue;
+            }
+
+            // The highest spot with room for a mob in a random column of the chunk.
+            let corner = *chunk_pos * width;
+            let (x, z) = (rng.gen_range(0..width), rng.gen_range(0..width));
+            let Some(voxel_pos) = (0..width)
+                .rev()
+                .map(|y| corner + IVec3::new(x, y, z))
+                .find(|voxel_pos| is_spawnable(&voxel_world, *voxel_pos))
+            else {
+                continue;
+            };
+
+            let position = voxel_pos.as_vec3() + Vec3::Y * (MOB_SIZE / 2.0 - 0.5);
+            let distance = position.distance(camera_transform.translation);
+            if !(MIN_SPAWN_DISTANCE..=MAX_SPAWN_DISTANCE).contains(&distance) {
+                continue;
+            }
+
+            let light = light_level(&voxel_world, voxel_pos, daylight);
+            let kinds: Vec<MobKind> = MobKind::ALL
+                .into_iter()
+                .filter(|kind| kind.spawn_light().contains(&light))
+                .collect();
+            let Some(kind) = kinds.choose(&mut rng).copied() else {
+                continue;
+            };
+
+            commands.spawn((
+                PbrBundle {
+                    mesh: assets.mesh.clone(),
+                    material: assets.materials[&kind].clone(),
+                    transform: Transform::from_translation(position),
+                    ..default()
+                },
+                Mob { kind },
+                Wander {
+                    heading: Vec2::ZERO,
+                    path: Vec::new(),
+                    timer: Timer::from_seconds(rng.gen_range(WANDER_TIME), TimerMode::Once),
+                },
+                Health::new(MOB_MAX_HEALTH),
+                Velocity::default(),
+                Gravity,
+                TerrainCollider {
+                    height: MOB_SIZE / 2.0,
+                },
+            ));
+            *chunk_mobs += 1;
+            mob_count += 1;
+        }
+    }
+
+    /// Walks mobs along the paths found for them. Mobs without a path to the spot they picked walk in a random
+    /// direction instead.
+    pub(super) fn receive_paths(
+        mut responses: EventReader<PathResponse>,
+        mut mob_query: Query<&mut Wander>,
+    ) {
+        let mut rng = rand::thread_rng();
+
+        for response in responses.read() {
+            let Ok(mut wander) = mob_query.get_mut(response.entity) else {
+                continue;
+            };
+
+            match &response.path {
+                Some(path) => wander.path = path.clone(),
+                None => {
+                    wander.heading = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU))
+                }
+            }
+        }
+    }
+
+    /// Walks mobs along their path or in their heading, and picks a new spot to walk to now and then. Mobs jump onto
+    /// single voxels in their way, and turn around at anything higher. Mobs that [hop](MobKind::hops) jump all the
+    /// way.
+    pub(super) fn wander(
+        time: Res<Time>,
+        voxel_world: VoxelWorld,
+        mut path_requests: EventWriter<PathRequest>,
+        mut mob_query: Query<(Entity, &Mob, &Transform, &mut Wander, &mut Velocity)>,
+    ) {
+        let mut rng = rand::thread_rng();
+
+        for (entity, mob, transform, mut wander, mut velocity) in &mut mob_query {
+            let feet = (transform.translation - Vec3::Y * (MOB_SIZE / 2.0))
+                .round()
+                .as_ivec3();
+
+            if wander.timer.tick(time.delta()).finished() {
+                wander.heading = Vec2::ZERO;
+                wander.path.clear();
+                // Mobs stand still about a third of the time.
+                if !rng.gen_bool(1.0 / 3.0) {
+                    let offset = IVec3::new(
+                        rng.gen_range(-WANDER_RADIUS..=WANDER_RADIUS),
+                        0,
+                        rng.gen_range(-WANDER_RADIUS..=WANDER_RADIUS),
+                    );
+                    // The spot is on whatever is in the column there, a few voxels up or down.
+                    let goal = (-MOB_WALK_LIMITS.max_fall..=MOB_WALK_LIMITS.max_fall)
+                        .rev()
+                        .map(|dy| feet + offset + IVec3::Y * dy)
+                        .find(|voxel_pos| is_spawnable(&voxel_world, *voxel_pos))
+                        .unwrap_or(feet + offset);
+                    path_requests.send(PathRequest {
+                        entity,
+                        start: feet,
+                        goal,
+                        limits: MOB_WALK_LIMITS,
+                    });
+                }
+                wander.timer = Timer::from_seconds(rng.gen_range(WANDER_TIME), TimerMode::Once);
+            }
+
+            if let Some(waypoint) = wander.path.first().copied() {
+                let to_waypoint = (waypoint.as_vec3() - transform.translation).xz();
+                if to_waypoint.length() < WAYPOINT_RADIUS {
+                    wander.path.remove(0);
+                }
+                wander.heading = if wander.path.is_empty() {
+                    Vec2::ZERO
+                } else {
+                    to_waypoint.normalize_or_zero()
+                };
+            }
+
+            if wander.heading != Vec2::ZERO {
+                let ahead = (transform.translation + wander.heading.extend(0.0).xzy() * MOB_SIZE)
+                    .round()
+                    .as_ivec3();
+                let ahead = IVec3::new(ahead.x, feet.y, ahead.z);
+                let is_solid = |voxel_pos: IVec3| {
+                    voxel_world
+                        .get_block(voxel_pos)
+                        .is_some_and(|voxel| voxel.is_solid())
+                };
+
+                if is_solid(ahead + IVec3::Y) {
+                    wander.heading = -wander.heading;
+                } else if (is_solid(ahead) || mob.kind.hops()) && velocity.0.y == 0.0 {
+                    velocity.0.y = MOB_JUMP_SPEED;
+                }
+            }
+
+            let walk = wander.heading * MOB_SPEED;
+            velocity.0.x = walk.x;
+            velocity.0.z = walk.y;
+        }
+    }
+
+    pub(super) fn despawn_far_mobs(
+        mut commands: Commands,
+        camera_query: Query<&Transform, With<Camera3d>>,
+        mob_query: Query<(Entity, &Transform), With<Mob>>,
+    ) {
+        let Ok(camera_transform) = camera_query.get_single() else {
+            return;
+        };
+
+        for (entity, transform) in &mob_query {
+            if transform.translation.distance(camera_transform.translation) > DESPAWN_DISTANCE {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    pub(super) fn despawn_dead_mobs(
+        mut commands: Commands,
+        mut deaths: EventReader<Death>,
+        mob_query: Query<(), With<Mob>>,
+    ) {
+        for death in deaths.read() {
+            if mob_query.contains(death.entity) {
+                commands.entity(death.entity).despawn();
+            }
+        }
+    }
+}
@@ -1,44 +1,514 @@
+mod audio;
+pub mod bench;
+mod biome;
+mod chunk_material;
+pub mod color;
+mod crafting;
 mod cube_mesh;
+pub mod data;
+mod density;
+mod diagnostics;
+mod dual_contouring;
+mod edit;
+mod explosion;
+mod explosion_effects;
+mod fire;
+mod fluid;
+mod fog;
+pub(crate) mod game_mode;
 mod generation;
 mod gizmos;
-pub(crate) mod load;
+mod gpu_culling;
+mod grass;
+mod health;
+mod horizon;
+#[cfg(feature = "debug")]
+mod inspector;
+mod interaction;
+mod inventory;
+mod item_drop;
+pub mod load;
+mod marching_cubes;
+pub mod mesher;
+mod micro;
+mod minimap;
+mod mob;
+pub mod net;
+pub(crate) mod noclip;
 mod noise;
+mod noise_layer;
+mod pathfinding;
+pub mod persistence;
+mod physics;
+mod precipitation;
+pub mod preset;
+pub mod raycast;
+mod raymarch;
+mod regenerate;
+mod registry;
+mod render;
+pub mod replay;
+mod river;
+mod sand;
+mod shading;
+mod shadows;
+mod simplify;
+mod spawn;
+mod storage;
+mod surface;
+mod tick;
+mod tool;
+mod underwater;
+mod void;
+pub(crate) mod weather;
+pub mod world;
 
-use bevy::{app::Plugin, math::Vec3};
+use bevy::{
+    app::Plugin,
+    math::Vec3,
+    prelude::{Reflect, ReflectResource, Resource},
+    render::color::Color,
+};
+use bevy_inspector_egui::quick::ResourceInspectorPlugin;
+use serde::{Deserialize, Serialize};
 
 use self::{
-    generation::{VoxelChunkPosition, VoxelChunkWidth, VoxelTerrainGeneratorPlugin},
+    audio::VoxelAudioPlugin,
+    crafting::VoxelCraftingPlugin,
+    diagnostics::VoxelDiagnosticsPlugin,
+    edit::VoxelEditPlugin,
+    explosion::VoxelExplosionPlugin,
+    explosion_effects::VoxelExplosionEffectsPlugin,
+    fire::VoxelFirePlugin,
+    fluid::VoxelFluidPlugin,
+    fog::VoxelFogPlugin,
+    game_mode::VoxelGameModePlugin,
+    generation::{VoxelChunkMap, VoxelChunkPosition, VoxelChunkWidth, VoxelTerrainGeneratorPlugin},
     gizmos::VoxelGizmosPlugin,
-    noise::VoxelTerrainNoisePlugin,
+    grass::VoxelGrassPlugin,
+    health::VoxelHealthPlugin,
+    horizon::VoxelHorizonPlugin,
+    interaction::VoxelInteractionPlugin,
+    inventory::VoxelInventoryPlugin,
+    item_drop::VoxelItemDropPlugin,
+    load::{ChunkLifecyclePlugin, ChunkLoadQueue},
+    micro::{VoxelMicroBlockPlugin, VoxelMicroBlockRenderingPlugin},
+    minimap::VoxelMinimapPlugin,
+    mob::VoxelMobPlugin,
+    net::{NetworkMode, VoxelNetworkPlugin},
+    noclip::VoxelNoclipPlugin,
+    noise::{TerrainParameters, VoxelTerrainNoisePlugin},
+    pathfinding::VoxelPathfindingPlugin,
+    persistence::VoxelPersistencePlugin,
+    physics::VoxelPhysicsPlugin,
+    precipitation::VoxelPrecipitationPlugin,
+    regenerate::VoxelRegenerationPlugin,
+    registry::{BlockDefinition, BlockTag},
+    render::VoxelChunkRenderingPlugin,
+    replay::VoxelReplayPlugin,
+    sand::VoxelSandPlugin,
+    shadows::VoxelShadowPlugin,
+    spawn::VoxelSpawnPlugin,
+    storage::ChunkStoragePlugin,
+    tick::VoxelTickPlugin,
+    underwater::VoxelUnderwaterPlugin,
+    void::VoxelVoidPlugin,
+    weather::VoxelWeatherPlugin,
 };
 
-pub(crate) struct VoxelPlugin;
+/// The complete voxel game. Which parts of it are added depends on the [NetworkMode] resource, which has to be
+/// inserted before this plugin is added.
+///
+/// Playing offline or hosting adds both the [VoxelServerPlugin] and the [VoxelClientPlugin]. Joining a server
+/// only adds the [VoxelClientPlugin], since the world is simulated on the server.
+#[derive(Default)]
+pub struct VoxelPlugin {
+    config: VoxelConfig,
+}
+
+impl VoxelPlugin {
+    /// Panics if the config is invalid, see [VoxelConfig].
+    pub fn new(config: VoxelConfig) -> Self {
+        config.validate();
+        Self { config }
+    }
+}
 
 impl Plugin for VoxelPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_plugins((
+        self.config.insert(app);
+
+        let network_mode = app
+            .world
+            .get_resource::<NetworkMode>()
+            .cloned()
+            .unwrap_or_default();
+
+        if !matches!(network_mode, NetworkMode::Client { .. }) {
+            // The inspectors need a window, so they can't be part of the server plugin itself.
+            app.add_plugins(VoxelServerPlugin)
+                .add_resource_inspector::<ChunkLoadQueue>()
+                .add_resource_inspector::<TerrainParameters>()
+                .add_resource_inspector::<VoxelConfig>();
+        }
+
+        app.add_plugins((VoxelClientPlugin, VoxelNetworkPlugin));
+    }
+}
+
+/// The world without anything to draw it, for the dedicated server. This hosts a server if the [NetworkMode]
+/// resource is [NetworkMode::Host].
+#[derive(Default)]
+pub struct VoxelDedicatedServerPlugin {
+    config: VoxelConfig,
+}
+
+impl VoxelDedicatedServerPlugin {
+    /// Panics if the config is invalid, see [VoxelConfig].
+    pub fn new(config: VoxelConfig) -> Self {
+        config.validate();
+        Self { config }
+    }
+}
+
+impl Plugin for VoxelDedicatedServerPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        self.config.insert(app);
+        app.add_plugins((VoxelServerPlugin, VoxelNetworkPlugin));
+    }
+}
+
+/// The knobs of the voxel world, given to the [VoxelPlugin] or [VoxelDedicatedServerPlugin].
+///
+/// The config is a resource too, and the budgets are read from it every frame, so they can be tweaked live in its
+/// inspector. The chunk width, seed and debug tools are only read when the app is built.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Resource)]
+pub struct VoxelConfig {
+    /// How many voxels wide, high and deep a chunk is. Has to be a power of two, and at most [MAX_CHUNK_WIDTH].
+    /// Clients and servers have to use the same width, since chunks are sent as a whole.
+    ///
+    /// Wider chunks mean fewer entities and draw calls, but every edit remeshes a larger chunk. Chunk meshes use 32-bit
+    /// indices, since a chunk wider than 16 voxels can have more vertices than 16-bit indices can address. Chunks are
+    /// saved by their voxel count, so changing the width of an existing world regenerates its saved chunks.
+    pub chunk_width: u8,
+    /// The seed of new worlds, or [None] for a random one. Saved worlds keep their own seed.
+    pub seed: Option<u32>,
+    /// The render distance of players who haven't picked one yet, in chunks.
+    pub render_distance: u32,
+    /// The furthest render distance a server loads and sends chunks around a connected player at, in chunks. Players
+    /// who pick a larger one get this.
+    pub max_player_render_distance: u32,
+    /// How many chunks are loaded or generated from the load queue every frame, at most.
+    pub chunk_loads_per_frame: usize,
+    /// How many chunks are meshed from the render queue every frame, at most.
+    pub chunk_meshes_per_frame: usize,
+    /// How many voxels of every loaded chunk are picked for a random tick every game tick.
+    pub random_ticks_per_chunk: u32,
+    /// How many chunks the falling sand simulation goes through every frame, at most.
+    pub sand_chunks_per_frame: usize,
+    /// How many whole chunks the server sends to every client per frame, at most. Sending too many at once fills up the channel.
+    pub chunk_sends_per_frame: usize,
+    /// Whether chunk meshes pack every vertex into 8 bytes, rather than 56. This saves a lot of GPU memory at large
+    /// render distances, but fluid surfaces snap to sixteenths of a voxel, colors lose some precision, and only 1023
+    /// texture layers can be used. Only chunks meshed after changing this are affected.
+    pub packed_vertices: bool,
+    /// Experimental: draws the opaque meshes of chunks with packed vertices through the GPU culling path, which culls
+    /// them in a compute shader and draws them all at once. See [GpuChunkCullingPlugin](gpu_culling::GpuChunkCullingPlugin).
+    pub gpu_culling: bool,
+    /// Experimental: draws the opaque voxels of chunks by raymarching through them, rather than with their meshes.
+    /// Can be turned on and off while playing, to compare the two. See
+    /// [VoxelRaymarchPlugin](raymarch::VoxelRaymarchPlugin).
+    pub raymarch_chunks: bool,
+    /// Stores the voxels of chunks in bricks of 8x8x8 voxels, where bricks of a single kind of voxel are stored as just
+    /// that voxel. Mostly-air and mostly-solid chunks take a fraction of the memory, at the cost of slower lookups.
+    pub sparse_chunks: bool,
+    /// Stores a [density](data::DensityVoxel) for every voxel of the chunks, which the smooth
+    /// [TerrainMesher](mesher::TerrainMesher)s draw instead of deriving them from which voxels are filled. The densities
+    /// start out derived, so the terrain looks the same until they're changed. They aren't saved or sent to clients, so
+    /// loaded and received chunks start over from their voxels.
+    pub density_chunks: bool,
+    /// Whether the inspector windows of the voxel plugins are shown, along with the chunk inspector of the `debug`
+    /// feature.
+    pub debug_tools: bool,
+}
+
+/// The widest a chunk can be. A chunk this wide already has 262,144 voxels.
+pub const MAX_CHUNK_WIDTH: u8 = 64;
+
+impl Default for VoxelConfig {
+    fn default() -> Self {
+        Self {
+            chunk_width: VoxelChunkWidth::default().0,
+            seed: None,
+            render_distance: 5,
+            max_player_render_distance: 16,
+            chunk_loads_per_frame: 16,
+            chunk_meshes_per_frame: 8,
+            random_ticks_per_chunk: 3,
+            sand_chunks_per_frame: 8,
+            chunk_sends_per_frame: 4,
+            packed_vertices: false,
+            gpu_culling: false,
+            raymarch_chunks: false,
+            sparse_chunks: false,
+            density_chunks: false,
+            debug_tools: true,
+        }
+    }
+}
+
+impl VoxelConfig {
+    fn validate(&self) {
+        assert!(
+            self.chunk_width.is_power_of_two(),
+            "The chunk width has to be a power of two, but it's {}",
+            self.chunk_width
+        );
+        assert!(
+            self.chunk_width <= MAX_CHUNK_WIDTH,
+            "The chunk width can be at most {MAX_CHUNK_WIDTH}, but it's {}",
+            self.chunk_width
+        );
+    }
+
+    /// Inserts the config as resources, before the plugins initialize them with their defaults.
+    fn insert(&self, app: &mut bevy::prelude::App) {
+        app.insert_resource(*self)
+            .register_type::<VoxelConfig>()
+            .insert_resource(VoxelChunkWidth(self.chunk_width));
+    }
+}
+
+/// Whether the debug tools are turned on in the [VoxelConfig] of the app. Apps without a config have them.
+// `Option::is_none_or` needs a newer Rust than the game supports.
+#[allow(unknown_lints, clippy::unnecessary_map_or)]
+fn debug_tools_enabled(app: &bevy::prelude::App) -> bool {
+    app.world
+        .get_resource::<VoxelConfig>()
+        .map_or(true, |config| config.debug_tools)
+}
+
+pub(crate) trait AddResourceInspector {
+    /// Adds an inspector window for the resource, unless the debug tools are turned off in the [VoxelConfig].
+    fn add_resource_inspector<T: Resource + Reflect>(&mut self) -> &mut Self;
+}
+
+impl AddResourceInspector for bevy::prelude::App {
+    fn add_resource_inspector<T: Resource + Reflect>(&mut self) -> &mut Self {
+        if debug_tools_enabled(self) {
+            self.add_plugins(ResourceInspectorPlugin::<T>::default());
+        }
+        self
+    }
+}
+
+/// The authoritative side of the game. It owns the [VoxelChunkMap], generates and simulates the world,
+/// and applies the [VoxelEdit](edit::VoxelEdit)s of every player.
+///
+/// Nothing in here draws anything, which is left to the [VoxelClientPlugin].
+pub(crate) struct VoxelServerPlugin;
+
+impl Plugin for VoxelServerPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<VoxelConfig>().add_plugins((
             VoxelTerrainGeneratorPlugin,
             VoxelTerrainNoisePlugin,
-            VoxelGizmosPlugin,
+            VoxelTickPlugin,
+            VoxelFluidPlugin,
+            VoxelFirePlugin,
+            VoxelGrassPlugin,
+            VoxelExplosionPlugin,
+            VoxelSandPlugin,
+            VoxelWeatherPlugin,
+            VoxelEditPlugin,
+            VoxelMicroBlockPlugin,
+            VoxelVoidPlugin,
+            VoxelPersistencePlugin,
+            VoxelRegenerationPlugin,
         ));
+
+        add_shared_plugins(app);
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-struct Voxel {
+/// The presentation side of the game. It meshes and draws the chunks it's given, and turns player input into
+/// [VoxelEdit](edit::VoxelEdit)s.
+///
+/// It doesn't care whether the chunks are generated locally by the [VoxelServerPlugin], or received from a server.
+pub(crate) struct VoxelClientPlugin;
+
+impl Plugin for VoxelClientPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<VoxelChunkWidth>()
+            .init_resource::<VoxelChunkMap>()
+            .add_plugins((
+                VoxelChunkRenderingPlugin::<Voxel>::default(),
+                VoxelMicroBlockRenderingPlugin,
+                VoxelGizmosPlugin,
+                VoxelMinimapPlugin,
+                VoxelInteractionPlugin,
+                (
+                    VoxelGameModePlugin,
+                    VoxelInventoryPlugin,
+                    VoxelItemDropPlugin,
+                    VoxelCraftingPlugin,
+                    VoxelHealthPlugin,
+                    VoxelSpawnPlugin,
+                    VoxelMobPlugin,
+                    VoxelPathfindingPlugin,
+                ),
+                VoxelExplosionEffectsPlugin,
+                VoxelPrecipitationPlugin,
+                VoxelUnderwaterPlugin,
+                VoxelHorizonPlugin,
+                VoxelFogPlugin,
+                VoxelShadowPlugin,
+                VoxelAudioPlugin,
+                VoxelReplayPlugin,
+                VoxelNoclipPlugin,
+            ));
+
+        #[cfg(feature = "debug")]
+        if debug_tools_enabled(app) {
+            app.add_plugins(inspector::ChunkInspectorPlugin);
+        }
+
+        add_shared_plugins(app);
+    }
+}
+
+/// Adds the plugins both sides need, unless the other side already added them.
+fn add_shared_plugins(app: &mut bevy::prelude::App) {
+    if !app.is_plugin_added::<ChunkLifecyclePlugin>() {
+        app.add_plugins(ChunkLifecyclePlugin);
+    }
+
+    if !app.is_plugin_added::<VoxelDiagnosticsPlugin>() {
+        app.add_plugins(VoxelDiagnosticsPlugin);
+    }
+
+    if !app.is_plugin_added::<VoxelPhysicsPlugin>() {
+        app.add_plugins(VoxelPhysicsPlugin);
+    }
+
+    if !app.is_plugin_added::<ChunkStoragePlugin>() {
+        app.add_plugins(ChunkStoragePlugin);
+    }
+}
+
+/// Voxel ids with this bit set are [colored voxels](Voxel::from_color).
+const COLORED_VOXEL_FLAG: u16 = 1 << 15;
+
+/// A single voxel of the world. What kind of block it is depends on its id, see the constants for the blocks of the
+/// game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Voxel {
     id: u16,
+    /// Extra per-voxel data, whose meaning depends on the id. For fluids, this is the fluid level.
+    state: u8,
 }
 
 impl Voxel {
-    const AIR: Self = Self::new(0);
-    const STONE: Self = Self::new(1);
+    pub const AIR: Self = Self::new(0);
+    pub const STONE: Self = Self::new(1);
+    /// A water source. See [fluid](self::fluid) for how the state of fluids is used.
+    pub const WATER: Self = Self::new(2).with_state(fluid::FLUID_SOURCE_LEVEL);
+    /// A lava source.
+    pub const LAVA: Self = Self::new(3).with_state(fluid::FLUID_SOURCE_LEVEL);
+    pub const OBSIDIAN: Self = Self::new(4);
+    /// Fire. The state is the age of the fire, see [fire](self::fire).
+    pub const FIRE: Self = Self::new(5);
+    pub const WOOD: Self = Self::new(6);
+    pub const DIRT: Self = Self::new(7);
+    pub const GRASS: Self = Self::new(8);
+    /// TNT. The state is 1 while it's lit, see [explosion](self::explosion).
+    pub const TNT: Self = Self::new(9);
+    pub const SAND: Self = Self::new(10);
+    /// A thin layer of snow. The state is how many layers there are, see [weather](self::weather).
+    pub const SNOW_LAYER: Self = Self::new(11);
+    /// The floor of the world, see [TerrainNoise::bedrock_level](self::noise::TerrainNoise::bedrock_level).
+    pub const BEDROCK: Self = Self::new(12);
+    /// A full block of snow, unlike the [Voxel::SNOW_LAYER].
+    pub const SNOW: Self = Self::new(13);
+    pub const GRAVEL: Self = Self::new(14);
+    /// Leaves, which can be seen through where their texture has holes.
+    pub const LEAVES: Self = Self::new(15);
+    /// A voxel subdivided into micro voxels, which are kept next to the chunk, see [micro](self::micro).
+    pub const MICRO_BLOCK: Self = Self::new(16);
 
-    const fn new(id: u16) -> Self {
-        Self { id }
+    pub const fn new(id: u16) -> Self {
+        Self { id, state: 0 }
     }
 
-    fn is_solid(&self) -> bool {
-        self.id != Self::AIR.id
+    /// A solid voxel of any color, for worlds in [VoxelMode::Colors](color::VoxelMode::Colors). Its id and state
+    /// hold the color instead of a kind of block, with 7 bits for each of red, green and blue.
+    pub fn from_color(color: Color) -> Self {
+        let [r, g, b, _] = color.as_rgba_u8().map(|channel| channel as u32 >> 1);
+        let packed = r << 14 | g << 7 | b;
+        Self {
+            id: COLORED_VOXEL_FLAG | (packed >> 8) as u16,
+            state: packed as u8,
+        }
+    }
+
+    /// Whether the voxel holds a color, rather than a kind of block. See [Voxel::from_color].
+    pub fn is_colored(&self) -> bool {
+        self.id & COLORED_VOXEL_FLAG != 0
+    }
+
+    pub const fn with_state(self, state: u8) -> Self {
+        Self { state, ..self }
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn state(&self) -> u8 {
+        self.state
+    }
+
+    /// The [BlockDefinition] of the voxel's id.
+    fn definition(&self) -> &'static BlockDefinition {
+        registry::block_definition(self.id)
+    }
+
+    /// The name of the kind of block the voxel is, see [BlockDefinition::name].
+    pub fn name(&self) -> &'static str {
+        self.definition().name
+    }
+
+    pub fn is_solid(&self) -> bool {
+        self.definition().solid
+    }
+
+    pub fn is_fluid(&self) -> bool {
+        self.definition().fluid.is_some()
+    }
+
+    /// How long the voxel takes to mine, in seconds, see [BlockDefinition::hardness].
+    pub fn hardness(&self) -> f32 {
+        self.definition().hardness
+    }
+
+    pub fn is_indestructible(&self) -> bool {
+        self.definition().has_tag(BlockTag::Indestructible)
+    }
+
+    /// The color of the voxel. This is used for its mesh, and in flat views, like the minimap.
+    pub fn color(&self) -> Color {
+        if !self.is_colored() {
+            return self.definition().color;
+        }
+
+        let packed = ((self.id & !COLORED_VOXEL_FLAG) as u32) << 8 | self.state as u32;
+        // Spreads the 7 bits of a channel over all 8, so white stays white.
+        let channel = |shift: u32| {
+            let channel = (packed >> shift & 0x7f) as u8;
+            channel << 1 | channel >> 6
+        };
+        Color::rgb_u8(channel(14), channel(7), channel(0))
     }
 }
 
@@ -0,0 +1,188 @@
+use bevy::{prelude::*, ui::UiSystem};
+
+use super::interpolation::NetworkedPlayer;
+
+/// The size of the head of an avatar, in voxels. The camera of the player is in its middle.
+const HEAD_SIZE: f32 = 0.5;
+/// The size of the body of an avatar, in voxels. It hangs below the head.
+const BODY_SIZE: Vec3 = Vec3::new(0.6, 1.2, 0.3);
+/// How far above the head of an avatar its name tag is, in voxels.
+const NAME_TAG_HEIGHT: f32 = 0.6;
+/// Name tags of players further away than this, in voxels, are hidden.
+const NAME_TAG_DISTANCE: f32 = 64.0;
+const NAME_TAG_FONT_SIZE: f32 = 16.0;
+const NAME_TAG_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.5);
+
+/// This plugin gives every [NetworkedPlayer] a simple avatar, a head that looks where they look on top of an upright
+/// body, and a name tag that always faces the camera.
+pub(super) struct PlayerAvatarPlugin;
+
+impl Plugin for PlayerAvatarPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AvatarAssets>()
+            .add_systems(
+                Update,
+                (systems::spawn_avatars, systems::pose_avatars).chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    systems::update_name_tags,
+                    systems::despawn_orphaned_name_tags,
+                )
+                    .before(UiSystem::Layout),
+            );
+    }
+}
+
+/// Marker component for the body of an avatar, which only turns around the vertical axis.
+#[derive(Component)]
+struct AvatarBody;
+
+/// The name of a [NetworkedPlayer], drawn on the UI above their avatar.
+#[derive(Component)]
+struct NameTag {
+    player: Entity,
+}
+
+#[derive(Resource)]
+struct AvatarAssets {
+    head_mesh: Handle<Mesh>,
+    body_mesh: Handle<Mesh>,
+}
+
+impl FromWorld for AvatarAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+
+        Self {
+            head_mesh: meshes.add(shape::Cube::new(HEAD_SIZE).into()),
+            body_mesh: meshes.add(shape::Box::new(BODY_SIZE.x, BODY_SIZE.y, BODY_SIZE.z).into()),
+        }
+    }
+}
+
+/// Every player gets their own color, so they can be told apart from afar.
+fn player_color(player_id: u64) -> Color {
+    let hue = (player_id.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32) % 360;
+    Color::hsl(hue as f32, 0.6, 0.5)
+}
+
+mod systems {
+    use super::*;
+
+    pub(super) fn spawn_avatars(
+        mut commands: Commands,
+        avatar_assets: Res<AvatarAssets>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+        player_query: Query<(Entity, &NetworkedPlayer), Added<NetworkedPlayer>>,
+    ) {
+        for (entity, player) in &player_query {
+            let material = materials.add(player_color(player.id).into());
+
+            commands.entity(entity).with_children(|parent| {
+                parent.spawn(PbrBundle {
+                    mesh: avatar_assets.head_mesh.clone(),
+                    material: material.clone(),
+                    ..default()
+                });
+                parent.spawn((
+                    PbrBundle {
+                        mesh: avatar_assets.body_mesh.clone(),
+                        material,
+                        ..default()
+                    },
+                    AvatarBody,
+                ));
+            });
+
+            commands.spawn((
+                TextBundle::from_section(
+                    player.name.clone(),
+                    TextStyle {
+                        font_size: NAME_TAG_FONT_SIZE,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    padding: UiRect::horizontal(Val::Px(4.0)),
+                    ..default()
+                })
+                .with_background_color(NAME_TAG_BACKGROUND),
+                NameTag { player: entity },
+            ));
+        }
+    }
+
+    /// Players look up and down with their head only. The body stays upright below it, facing where they look.
+    pub(super) fn pose_avatars(
+        player_query: Query<(&Transform, &Children), With<NetworkedPlayer>>,
+        mut body_query: Query<&mut Transform, (With<AvatarBody>, Without<NetworkedPlayer>)>,
+    ) {
+        for (player_transform, children) in &player_query {
+            let (yaw, ..) = player_transform.rotation.to_euler(EulerRot::YXZ);
+            let rotation = player_transform.rotation.inverse() * Quat::from_rotation_y(yaw);
+
+            for child in children {
+                if let Ok(mut body_transform) = body_query.get_mut(*child) {
+                    body_transform.rotation = rotation;
+                    body_transform.translation =
+                        rotation * Vec3::Y * -(HEAD_SIZE + BODY_SIZE.y) / 2.0;
+                }
+            }
+        }
+    }
+
+    /// Moves every name tag above the head of its player on the screen, and hides it when the player is off screen
+    /// or too far away.
+    pub(super) fn update_name_tags(
+        camera_query: Query<(&Camera, &Transform), With<Camera3d>>,
+        player_query: Query<(&Transform, &NetworkedPlayer)>,
+        mut name_tag_query: Query<(&NameTag, &Node, &mut Style, &mut Visibility, &mut Text)>,
+    ) {
+        let Ok((camera, camera_transform)) = camera_query.get_single() else {
+            return;
+        };
+        let camera_transform = GlobalTransform::from(*camera_transform);
+
+        for (name_tag, node, mut style, mut visibility, mut text) in &mut name_tag_query {
+            let Ok((player_transform, player)) = player_query.get(name_tag.player) else {
+                continue;
+            };
+
+            let tag_pos = player_transform.translation + Vec3::Y * NAME_TAG_HEIGHT;
+            let screen_pos = (tag_pos.distance(camera_transform.translation())
+                <= NAME_TAG_DISTANCE)
+                .then(|| camera.world_to_viewport(&camera_transform, tag_pos))
+                .flatten();
+
+            let Some(screen_pos) = screen_pos else {
+                *visibility = Visibility::Hidden;
+                continue;
+            };
+
+            *visibility = Visibility::Inherited;
+            // The size is from the last layout, which is good enough to center the tag.
+            style.left = Val::Px(screen_pos.x - node.size().x / 2.0);
+            style.top = Val::Px(screen_pos.y - node.size().y);
+
+            if text.sections[0].value != player.name {
+                text.sections[0].value.clone_from(&player.name);
+            }
+        }
+    }
+
+    pub(super) fn despawn_orphaned_name_tags(
+        mut commands: Commands,
+        name_tag_query: Query<(Entity, &NameTag)>,
+        player_query: Query<(), With<NetworkedPlayer>>,
+    ) {
+        for (entity, name_tag) in &name_tag_query {
+            if !player_query.contains(name_tag.player) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
@@ -0,0 +1,521 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+use bevy_renet::{
+    client_connected, client_just_disconnected,
+    renet::{
+        transport::{ClientAuthentication, NetcodeClientTransport},
+        ConnectionConfig, RenetClient,
+    },
+    transport::NetcodeClientPlugin,
+    RenetClientPlugin,
+};
+
+use crate::{
+    chat::{ChatLine, OutgoingChatMessage},
+    voxel::{
+        edit::{AppliedVoxelEdit, VoxelChanged},
+        micro::MicroBlocks,
+        Voxel,
+    },
+};
+
+use super::{
+    avatar::PlayerAvatarPlugin,
+    interpolation::{PlayerInterpolationPlugin, PlayerSnapshotsReceived},
+    prediction::{PlayerAcknowledged, PlayerPredictionPlugin},
+    protocol::{name_to_user_data, EditRejection, PROTOCOL_ID},
+    PlayerName,
+};
+
+/// The revision of a chunk received from the server. See [ServerMessage](super::protocol::ServerMessage) for how
+/// revisions are used.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+struct ChunkRevision(u32);
+
+/// Sent for every [ServerMessage::EditRejected](super::protocol::ServerMessage::EditRejected) received.
+#[derive(Event, Debug, Clone, Copy)]
+struct EditRejected {
+    voxel_pos: IVec3,
+    voxel: Option<Voxel>,
+    reason: EditRejection,
+}
+
+/// This plugin connects to the server at [VoxelClientNetworkPlugin::server_addr]. It spawns the chunks the server
+/// sends, and sends the movement of the camera and the [VoxelEdit](crate::voxel::edit::VoxelEdit)s and
+/// [MicroVoxelEdit](crate::voxel::micro::MicroVoxelEdit)s of the player back to the server.
+pub(super) struct VoxelClientNetworkPlugin {
+    pub(super) server_addr: SocketAddr,
+}
+
+impl Plugin for VoxelClientNetworkPlugin {
+    fn build(&self, app: &mut App) {
+        let socket = match UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], 0))) {
+            Ok(socket) => socket,
+            Err(err) => {
+                error!("Couldn't open a socket to connect to the server: {err}");
+                return;
+            }
+        };
+
+        let current_time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        // Good enough to tell the players on a server apart.
+        let client_id = current_time.as_millis() as u64;
+        let authentication = ClientAuthentication::Unsecure {
+            protocol_id: PROTOCOL_ID,
+            client_id,
+            server_addr: self.server_addr,
+            user_data: app
+                .world
+                .get_resource::<PlayerName>()
+                .map(|name| name_to_user_data(&name.0)),
+        };
+
+        let transport = match NetcodeClientTransport::new(current_time, authentication, socket) {
+            Ok(transport) => transport,
+            Err(err) => {
+                error!("Couldn't connect to {}: {err}", self.server_addr);
+                return;
+            }
+        };
+        info!("Connecting to {}", self.server_addr);
+
+        app.add_plugins((
+            RenetClientPlugin,
+            NetcodeClientPlugin,
+            PlayerPredictionPlugin,
+            PlayerInterpolationPlugin {
+                local_player_id: client_id,
+            },
+            PlayerAvatarPlugin,
+        ))
+        .add_event::<AppliedVoxelEdit>()
+        .add_event::<VoxelChanged>()
+        .add_event::<EditRejected>()
+        .init_resource::<MicroBlocks>()
+        .add_event::<ChatLine>()
+        .add_event::<OutgoingChatMessage>()
+        .insert_resource(RenetClient::new(ConnectionConfig::default()))
+        .insert_resource(transport)
+        .add_systems(
+            Update,
+            (
+                (
+                    systems::receive_server_messages,
+                    systems::apply_replicated_edits,
+                    systems::correct_rejected_edits,
+                )
+                    .chain(),
+                systems::send_voxel_edits,
+                systems::send_micro_voxel_edits,
+                systems::send_chat_messages,
+            )
+                .run_if(client_connected()),
+        )
+        .add_systems(
+            Update,
+            (
+                systems::log_disconnect.run_if(client_just_disconnected()),
+                super::systems::log_transport_errors,
+            ),
+        );
+    }
+}
+
+mod systems {
+    use bevy::utils::HashMap;
+    use bevy_renet::renet::DefaultChannel;
+
+    use crate::voxel::{
+        color::VoxelMode,
+        edit::VoxelEdit,
+        generation::{
+            LocalVoxelPosition, VoxelChunk, VoxelChunkBundle, VoxelChunkMap, VoxelChunkPosition,
+            VoxelChunkWidth,
+        },
+        load::{ChunkLoaded, ChunkUnloaded},
+        mesher::TerrainMesher,
+        micro::{MicroBlock, MicroVoxelEdit, MICRO_BLOCK_WIDTH},
+        net::{
+            fragment::FragmentAssembler,
+            protocol::{decode, encode, ClientMessage, ServerMessage},
+        },
+        render::ChunkRenderQueue,
+        weather::Weather,
+        world::VoxelWorld,
+        VoxelChunkCoordinate,
+    };
+
+    use super::*;
+
+    const NEIGHBOUR_OFFSETS: [IVec3; 6] = [
+        IVec3::X,
+        IVec3::NEG_X,
+        IVec3::Y,
+        IVec3::NEG_Y,
+        IVec3::Z,
+        IVec3::NEG_Z,
+    ];
+
+    fn send(client: &mut RenetClient, message: &ClientMessage) {
+        client.send_message(message.channel(), encode(message));
+    }
+
+    /// Applies the changes of a [ServerMessage::ChunkDelta] to a chunk at `base_revision`.
+    ///
+    /// Returns false, without changing anything, if the chunk is at another revision or a change is outside of it.
+    fn apply_delta(
+        chunk: &mut VoxelChunk,
+        revision: &mut ChunkRevision,
+        base_revision: u32,
+        changes: &[(u32, Voxel)],
+        chunk_width: &VoxelChunkWidth,
+    ) -> bool {
+        if revision.0 != base_revision
+            || changes
+                .iter()
+                .any(|(index, _)| *index as usize >= chunk.voxels().len())
+        {
+            return false;
+        }
+
+        for (index, voxel) in changes {
+            chunk.set_voxel(
+                LocalVoxelPosition::from_index(*index as usize, chunk_width),
+                *voxel,
+                chunk_width,
+            );
+        }
+        revision.0 += 1;
+
+        true
+    }
+
+    pub(super) fn receive_server_messages(
+        mut commands: Commands,
+        mut client: ResMut<RenetClient>,
+        mut voxel_chunk_map: ResMut<VoxelChunkMap>,
+        mut chunk_query: Query<(&mut VoxelChunk, &mut ChunkRevision)>,
+        mut chunk_render_queue: ResMut<ChunkRenderQueue>,
+        mut micro_blocks: ResMut<MicroBlocks>,
+        chunk_width: Res<VoxelChunkWidth>,
+        mut next_weather: ResMut<NextState<Weather>>,
+        // Settings of the world the server sends when connecting.
+        (mut mesher, mut voxel_mode): (ResMut<TerrainMesher>, ResMut<VoxelMode>),
+        mut applied_edits: EventWriter<AppliedVoxelEdit>,
+        mut rejected_edits: EventWriter<EditRejected>,
+        mut acknowledgements: EventWriter<PlayerAcknowledged>,
+        mut snapshots: EventWriter<PlayerSnapshotsReceived>,
+        mut chat_lines: EventWriter<ChatLine>,
+        mut fragments: Local<FragmentAssembler>,
+        (mut loaded_chunks, mut unloaded_chunks): (
+            EventWriter<ChunkLoaded>,
+            EventWriter<ChunkUnloaded>,
+        ),
+    ) {
+        let voxel_count = chunk_width.0 as usize * chunk_width.0 as usize * chunk_width.0 as usize;
+
+        // Whole chunks are only spawned once every message has been read, so the deltas that follow them in the same
+        // frame can still be applied to them.
+        let mut received: HashMap<VoxelChunkPosition, (VoxelChunk, ChunkRevision)> = HashMap::new();
+
+        let mut messages = Vec::new();
+        for channel in [DefaultChannel::Unreliable, DefaultChannel::ReliableOrdered] {
+            let channel = u8::from(channel);
+            while let Some(bytes) = client.receive_message(channel) {
+                match decode::<ServerMessage>(&bytes) {
+                    Some(ServerMessage::Fragment {
+                        index,
+                        count,
+                        bytes,
+                    }) => {
+                        if let Some(bytes) = fragments.push(index, count, &bytes) {
+                            messages.extend(decode::<ServerMessage>(&bytes));
+                        }
+                    }
+                    Some(message) => messages.push(message),
+                    None => {}
+                }
+            }
+        }
+
+        for message in messages {
+            match message {
+                ServerMessage::Chunk {
+                    chunk_pos,
+                    revision,
+                    payload,
+                } => {
+                    let Some(voxels) = payload.decode(voxel_count) else {
+                        warn!("Ignoring malformed chunk {:?}", chunk_pos.0);
+                        continue;
+                    };
+
+                    received.insert(
+                        chunk_pos,
+                        (VoxelChunk::from_voxels(voxels), ChunkRevision(revision)),
+                    );
+                }
+                ServerMessage::ChunkDelta {
+                    chunk_pos,
+                    base_revision,
+                    changes,
+                } => {
+                    let applied = if let Some((chunk, revision)) = received.get_mut(&chunk_pos) {
+                        apply_delta(chunk, revision, base_revision, &changes, &chunk_width)
+                    } else if let Some((mut chunk, mut revision)) = voxel_chunk_map
+                        .0
+                        .get(&chunk_pos)
+                        .and_then(|chunk_entity| chunk_query.get_mut(*chunk_entity).ok())
+                    {
+                        let applied = apply_delta(
+                            &mut chunk,
+                            &mut revision,
+                            base_revision,
+                            &changes,
+                            &chunk_width,
+                        );
+
+                        if applied {
+                            for (index, _) in &changes {
+                                chunk_render_queue.push_voxel_change(
+                                    chunk_pos,
+                                    LocalVoxelPosition::from_index(*index as usize, &chunk_width),
+                                    &voxel_chunk_map,
+                                    &chunk_width,
+                                );
+                            }
+                        }
+
+                        applied
+                    } else {
+                        false
+                    };
+
+                    if !applied {
+                        debug!(
+                            "Out of sync with chunk {:?}, requesting it again",
+                            chunk_pos.0
+                        );
+                        send(&mut client, &ClientMessage::RequestChunk(chunk_pos));
+                    }
+                }
+                ServerMessage::VoxelEdit(edit) => {
+                    applied_edits.send(AppliedVoxelEdit(edit));
+                }
+                ServerMessage::MicroBlock { voxel_pos, payload } => {
+                    let micro_block = match payload {
+                        Some(payload) => {
+                            let Some(micro_block) = payload
+                                .decode(MICRO_BLOCK_WIDTH.pow(3) as usize)
+                                .and_then(MicroBlock::from_voxels)
+                            else {
+                                warn!("Ignoring malformed micro block at {voxel_pos}");
+                                continue;
+                            };
+                            Some(micro_block)
+                        }
+                        None => None,
+                    };
+                    micro_blocks.set(voxel_pos, micro_block, &chunk_width);
+
+                    let (chunk_pos, local_pos) =
+                        VoxelChunkPosition::world_to_local(voxel_pos, &chunk_width);
+                    chunk_render_queue.push_voxel_change(
+                        chunk_pos,
+                        local_pos,
+                        &voxel_chunk_map,
+                        &chunk_width,
+                    );
+                }
+                ServerMessage::UnloadChunk(chunk_pos) => {
+                    received.remove(&chunk_pos);
+                    micro_blocks.remove_chunk(chunk_pos);
+
+                    if let Some(chunk_entity) = voxel_chunk_map.0.remove(&chunk_pos) {
+                        commands.entity(chunk_entity).despawn_recursive();
+                        unloaded_chunks.send(ChunkUnloaded {
+                            chunk_pos: chunk_pos.0,
+                            entity: chunk_entity,
+                        });
+                    }
+                }
+                ServerMessage::Weather(weather) => {
+                    next_weather.set(weather);
+                }
+                ServerMessage::TerrainMesher(new_mesher) => {
+                    mesher.set_if_neq(new_mesher);
+                }
+                ServerMessage::VoxelMode(new_voxel_mode) => {
+                    voxel_mode.set_if_neq(new_voxel_mode);
+                }
+                ServerMessage::Chat(line) => {
+                    chat_lines.send(line);
+                }
+                ServerMessage::PlayerAck {
+                    sequence,
+                    translation,
+                } => {
+                    acknowledgements.send(PlayerAcknowledged {
+                        sequence,
+                        translation,
+                    });
+                }
+                ServerMessage::PlayerSnapshots {
+                    server_time,
+                    players,
+                } => {
+                    snapshots.send(PlayerSnapshotsReceived {
+                        server_time,
+                        players,
+                    });
+                }
+                ServerMessage::EditRejected {
+                    voxel_pos,
+                    voxel,
+                    reason,
+                } => {
+                    rejected_edits.send(EditRejected {
+                        voxel_pos,
+                        voxel,
+                        reason,
+                    });
+                }
+                // Fragments are put back together as they're received.
+                ServerMessage::Fragment { .. } => {}
+            }
+        }
+
+        for (chunk_pos, (chunk, revision)) in received {
+            let existing = voxel_chunk_map.0.get(&chunk_pos).copied();
+
+            let Some((chunk_entity, (mut existing_chunk, mut existing_revision))) = existing
+                .and_then(|chunk_entity| {
+                    Some((chunk_entity, chunk_query.get_mut(chunk_entity).ok()?))
+                })
+            else {
+                let chunk_entity = commands
+                    .spawn((
+                        VoxelChunkBundle {
+                            transform: Transform::from_translation(
+                                chunk_pos.as_world_pos(&chunk_width),
+                            ),
+                            chunk,
+                            chunk_pos,
+                            ..default()
+                        },
+                        revision,
+                    ))
+                    .id();
+                voxel_chunk_map.0.insert(chunk_pos, chunk_entity);
+                loaded_chunks.send(ChunkLoaded {
+                    chunk_pos: chunk_pos.0,
+                    entity: chunk_entity,
+                });
+                continue;
+            };
+
+            *existing_chunk = chunk;
+            *existing_revision = revision;
+
+            // Anything could have changed, including the voxels on the edges, which affect the neighbours as well.
+            chunk_render_queue.push_chunk(chunk_entity);
+            for offset in NEIGHBOUR_OFFSETS {
+                if let Some(neighbour_entity) = voxel_chunk_map
+                    .0
+                    .get(&VoxelChunkPosition(chunk_pos.0 + offset))
+                {
+                    chunk_render_queue.push_chunk(*neighbour_entity);
+                }
+            }
+        }
+    }
+
+    /// Applies the edits the server sent, the same way local edits are applied, so the edited chunks are remeshed
+    /// right away.
+    pub(super) fn apply_replicated_edits(
+        mut applied_edits: EventReader<AppliedVoxelEdit>,
+        mut changes: EventWriter<VoxelChanged>,
+        mut voxel_world: VoxelWorld,
+    ) {
+        for AppliedVoxelEdit(edit) in applied_edits.read() {
+            let Some(previous) = voxel_world.get_block(edit.voxel_pos) else {
+                continue;
+            };
+
+            if voxel_world.set_block(edit.voxel_pos, edit.voxel) {
+                changes.send(VoxelChanged {
+                    voxel_pos: edit.voxel_pos,
+                    previous,
+                    voxel: edit.voxel,
+                });
+            }
+        }
+    }
+
+    /// Puts back what the server has at the voxels it didn't let the player edit, and tells the player why.
+    pub(super) fn correct_rejected_edits(
+        mut rejected_edits: EventReader<EditRejected>,
+        mut voxel_world: VoxelWorld,
+        mut chat_lines: EventWriter<ChatLine>,
+    ) {
+        for rejected in rejected_edits.read() {
+            if let Some(voxel) = rejected.voxel {
+                if voxel_world.get_block(rejected.voxel_pos) != Some(voxel) {
+                    voxel_world.set_block(rejected.voxel_pos, voxel);
+                }
+            }
+
+            let IVec3 { x, y, z } = rejected.voxel_pos;
+            chat_lines.send(ChatLine {
+                sender: None,
+                text: format!(
+                    "Couldn't change the voxel at {x}, {y}, {z}: {}",
+                    rejected.reason
+                ),
+            });
+        }
+    }
+
+    /// Forwards the edits of the player to the server. They aren't applied here, the server sends the changed chunks
+    /// back instead.
+    pub(super) fn send_voxel_edits(
+        mut client: ResMut<RenetClient>,
+        mut edits: EventReader<VoxelEdit>,
+    ) {
+        for edit in edits.read() {
+            send(&mut client, &ClientMessage::Edit(*edit));
+        }
+    }
+
+    /// Forwards the micro edits of the player to the server, like [send_voxel_edits].
+    pub(super) fn send_micro_voxel_edits(
+        mut client: ResMut<RenetClient>,
+        mut edits: EventReader<MicroVoxelEdit>,
+    ) {
+        for edit in edits.read() {
+            send(&mut client, &ClientMessage::MicroEdit(*edit));
+        }
+    }
+
+    pub(super) fn send_chat_messages(
+        mut client: ResMut<RenetClient>,
+        mut outgoing: EventReader<OutgoingChatMessage>,
+    ) {
+        for OutgoingChatMessage(text) in outgoing.read() {
+            send(&mut client, &ClientMessage::Chat(text.clone()));
+        }
+    }
+
+    pub(super) fn log_disconnect(transport: Res<NetcodeClientTransport>) {
+        match transport.disconnect_reason() {
+            Some(reason) => error!("Disconnected from the server: {reason}"),
+            None => error!("Disconnected from the server"),
+        }
+    }
+}
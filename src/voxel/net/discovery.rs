@@ -0,0 +1,221 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use super::protocol::{decode, encode, PROTOCOL_ID};
+
+/// The port servers listen on for [LanServerBrowser] queries. Only one server per machine can be discovered.
+pub const DISCOVERY_PORT: u16 = 5001;
+/// How often the [LanServerBrowser] asks for servers, while it's searching.
+const QUERY_INTERVAL: Duration = Duration::from_secs(2);
+/// Servers that haven't answered for this long are removed from the [LanServerBrowser].
+const SERVER_TIMEOUT: Duration = Duration::from_secs(6);
+/// Larger packets are ignored. Answers are small, and queries are just the protocol id.
+const MAX_PACKET_BYTES: usize = 512;
+
+/// This plugin lets a server be found on the local network. It answers the broadcast queries of the
+/// [LanServerBrowser] on [DISCOVERY_PORT].
+pub(super) struct LanDiscoveryPlugin {
+    /// The port the game itself is hosted on, which is sent to the browsers.
+    pub(super) port: u16,
+    pub(super) name: String,
+}
+
+impl Plugin for LanDiscoveryPlugin {
+    fn build(&self, app: &mut App) {
+        let socket = UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT)))
+            .and_then(|socket| socket.set_nonblocking(true).map(|_| socket));
+        let socket = match socket {
+            Ok(socket) => socket,
+            Err(err) => {
+                warn!("Couldn't listen on port {DISCOVERY_PORT}, the server can't be found on the local network: {err}");
+                return;
+            }
+        };
+
+        app.insert_resource(DiscoveryResponder {
+            socket,
+            port: self.port,
+            name: self.name.clone(),
+        })
+        .add_systems(Update, systems::answer_discovery_queries);
+    }
+}
+
+/// This plugin adds the [LanServerBrowser].
+pub struct LanServerBrowserPlugin;
+
+impl Plugin for LanServerBrowserPlugin {
+    fn build(&self, app: &mut App) {
+        let socket = UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)))
+            .and_then(|socket| socket.set_broadcast(true).map(|_| socket))
+            .and_then(|socket| socket.set_nonblocking(true).map(|_| socket));
+        let socket = match socket {
+            Ok(socket) => Some(socket),
+            Err(err) => {
+                warn!("Couldn't open a socket to search for servers on the local network: {err}");
+                None
+            }
+        };
+
+        app.insert_resource(LanServerBrowser {
+            socket,
+            searching: false,
+            last_query: None,
+            servers: HashMap::new(),
+        })
+        .add_systems(
+            Update,
+            (
+                systems::send_discovery_queries,
+                systems::receive_discovery_answers,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// What a server on the local network says about itself.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LanServerInfo {
+    /// The name of the hosting player, or of the dedicated server.
+    pub name: String,
+    pub players: usize,
+    pub max_players: usize,
+    /// The port the game is hosted on.
+    port: u16,
+}
+
+/// A server found by the [LanServerBrowser].
+#[derive(Debug, Clone)]
+pub struct LanServer {
+    /// Where to connect to.
+    pub addr: SocketAddr,
+    pub info: LanServerInfo,
+    /// When the server last answered, in seconds since the game started.
+    last_seen: f64,
+}
+
+/// Searches for servers on the local network, by broadcasting a query every few seconds that servers with the
+/// [LanDiscoveryPlugin] answer.
+///
+/// It only searches while [LanServerBrowser::set_searching] is on, like while a menu listing the servers is open.
+#[derive(Resource)]
+pub struct LanServerBrowser {
+    socket: Option<UdpSocket>,
+    searching: bool,
+    /// When the last query was sent, in seconds since the game started.
+    last_query: Option<f64>,
+    servers: HashMap<SocketAddr, LanServer>,
+}
+
+impl LanServerBrowser {
+    /// Starts or stops searching. Starting sends a query right away.
+    pub fn set_searching(&mut self, searching: bool) {
+        self.searching = searching;
+        self.last_query = None;
+    }
+
+    /// The servers that answered recently, sorted by name.
+    pub fn servers(&self) -> Vec<&LanServer> {
+        let mut servers: Vec<&LanServer> = self.servers.values().collect();
+        servers.sort_by(|a, b| a.info.name.cmp(&b.info.name).then(a.addr.cmp(&b.addr)));
+        servers
+    }
+}
+
+#[derive(Resource)]
+struct DiscoveryResponder {
+    socket: UdpSocket,
+    port: u16,
+    name: String,
+}
+
+mod systems {
+    use bevy_renet::renet::RenetServer;
+
+    use crate::voxel::net::server::MAX_CLIENTS;
+
+    use super::*;
+
+    pub(super) fn answer_discovery_queries(
+        responder: Res<DiscoveryResponder>,
+        server: Option<Res<RenetServer>>,
+    ) {
+        let mut buf = [0; MAX_PACKET_BYTES];
+
+        while let Ok((len, addr)) = responder.socket.recv_from(&mut buf) {
+            if buf[..len] != PROTOCOL_ID.to_le_bytes() {
+                continue;
+            }
+
+            let info = LanServerInfo {
+                name: responder.name.clone(),
+                players: server
+                    .as_ref()
+                    .map_or(0, |server| server.connected_clients()),
+                max_players: MAX_CLIENTS,
+                port: responder.port,
+            };
+            if let Err(err) = responder.socket.send_to(&encode(&info), addr) {
+                debug!("Couldn't answer the discovery query of {addr}: {err}");
+            }
+        }
+    }
+
+    pub(super) fn send_discovery_queries(time: Res<Time>, mut browser: ResMut<LanServerBrowser>) {
+        let now = time.elapsed_seconds_f64();
+
+        browser
+            .servers
+            .retain(|_, server| now - server.last_seen < SERVER_TIMEOUT.as_secs_f64());
+
+        if !browser.searching
+            || browser
+                .last_query
+                .is_some_and(|last_query| now - last_query < QUERY_INTERVAL.as_secs_f64())
+        {
+            return;
+        }
+        browser.last_query = Some(now);
+
+        let Some(socket) = &browser.socket else {
+            return;
+        };
+        let broadcast_addr = SocketAddr::from((Ipv4Addr::BROADCAST, DISCOVERY_PORT));
+        if let Err(err) = socket.send_to(&PROTOCOL_ID.to_le_bytes(), broadcast_addr) {
+            debug!("Couldn't search for servers on the local network: {err}");
+        }
+    }
+
+    pub(super) fn receive_discovery_answers(
+        time: Res<Time>,
+        mut browser: ResMut<LanServerBrowser>,
+    ) {
+        let mut buf = [0; MAX_PACKET_BYTES];
+        let mut answers = Vec::new();
+
+        if let Some(socket) = &browser.socket {
+            while let Ok((len, addr)) = socket.recv_from(&mut buf) {
+                if let Some(info) = decode::<LanServerInfo>(&buf[..len]) {
+                    answers.push((SocketAddr::new(addr.ip(), info.port), info));
+                }
+            }
+        }
+
+        for (addr, info) in answers {
+            browser.servers.insert(
+                addr,
+                LanServer {
+                    addr,
+                    info,
+                    last_seen: time.elapsed_seconds_f64(),
+                },
+            );
+        }
+    }
+}
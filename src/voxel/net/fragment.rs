@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use bevy_renet::renet::DefaultChannel;
+
+use super::protocol::{encode, ServerMessage};
+
+/// Encoded messages larger than this, in bytes, are split into [ServerMessage::Fragment]s, so every packet stays well
+/// within the usual MTU of 1500 bytes, headers included.
+const MAX_FRAGMENT_BYTES: usize = 1024;
+/// Messages split into more fragments than this are dropped by the [FragmentAssembler]. Even a chunk of random voxels
+/// needs far fewer.
+const MAX_FRAGMENTS: u16 = 1024;
+
+/// Encodes a message, split into [ServerMessage::Fragment]s if it doesn't fit into one packet. The returned packets
+/// have to be sent right after each other, on the channel of the message.
+///
+/// Only reliable ordered messages are split, as the fragments have to arrive, in order. Others are always sent whole.
+pub(super) fn encode_fragmented(message: &ServerMessage) -> Vec<Vec<u8>> {
+    let bytes = encode(message);
+    if bytes.len() <= MAX_FRAGMENT_BYTES
+        || !matches!(message.channel(), DefaultChannel::ReliableOrdered)
+    {
+        return vec![bytes];
+    }
+
+    let count = bytes.len().div_ceil(MAX_FRAGMENT_BYTES) as u16;
+    bytes
+        .chunks(MAX_FRAGMENT_BYTES)
+        .enumerate()
+        .map(|(index, part)| {
+            encode(&ServerMessage::Fragment {
+                index: index as u16,
+                count,
+                bytes: part.to_vec(),
+            })
+        })
+        .collect()
+}
+
+/// Puts the [ServerMessage::Fragment]s of a message back together. Fragments arrive in order, without other messages
+/// in between, so only one message is assembled at a time.
+#[derive(Default, Debug)]
+pub(super) struct FragmentAssembler {
+    /// How many fragments the message being assembled has.
+    count: u16,
+    /// How many of them have been added.
+    received: u16,
+    bytes: Vec<u8>,
+}
+
+impl FragmentAssembler {
+    /// Adds the next fragment of a message. Returns the encoded message once its last fragment is added.
+    pub(super) fn push(&mut self, index: u16, count: u16, bytes: &[u8]) -> Option<Vec<u8>> {
+        if index == 0 {
+            self.count = count;
+            self.received = 0;
+            self.bytes.clear();
+        }
+
+        if count > MAX_FRAGMENTS || count != self.count || index != self.received {
+            warn!("Ignoring fragment {index} of {count}, it doesn't follow the previous one");
+            self.count = 0;
+            self.received = 0;
+            self.bytes.clear();
+            return None;
+        }
+
+        self.received += 1;
+        self.bytes.extend_from_slice(bytes);
+
+        (self.received == self.count).then(|| std::mem::take(&mut self.bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voxel::{generation::VoxelChunkPosition, Voxel};
+
+    use super::{super::protocol::decode, *};
+
+    /// A delta changing `changes` voxels, which encodes to about 10 bytes per change.
+    fn delta(changes: u32) -> ServerMessage {
+        ServerMessage::ChunkDelta {
+            chunk_pos: VoxelChunkPosition(IVec3::new(-3, 1, 7)),
+            base_revision: 42,
+            changes: (0..changes).map(|index| (index, Voxel::STONE)).collect(),
+        }
+    }
+
+    /// The index, count and bytes of an encoded [ServerMessage::Fragment].
+    fn fragment(packet: &[u8]) -> (u16, u16, Vec<u8>) {
+        match decode(packet) {
+            Some(ServerMessage::Fragment {
+                index,
+                count,
+                bytes,
+            }) => (index, count, bytes),
+            other => panic!("expected a fragment, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn small_messages_are_sent_whole() {
+        let message = delta(4);
+
+        assert_eq!(encode_fragmented(&message), vec![encode(&message)]);
+    }
+
+    #[test]
+    fn large_messages_are_split_and_reassembled() {
+        let message = delta(1000);
+        let bytes = encode(&message);
+        assert!(bytes.len() > MAX_FRAGMENT_BYTES * 2);
+
+        let packets = encode_fragmented(&message);
+        assert_eq!(packets.len(), bytes.len().div_ceil(MAX_FRAGMENT_BYTES));
+
+        let mut assembler = FragmentAssembler::default();
+        for (i, packet) in packets.iter().enumerate() {
+            let (index, count, part) = fragment(packet);
+            assert!(part.len() <= MAX_FRAGMENT_BYTES);
+
+            let assembled = assembler.push(index, count, &part);
+            if i + 1 < packets.len() {
+                assert_eq!(assembled, None);
+            } else {
+                assert_eq!(assembled.as_ref(), Some(&bytes));
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_order_fragments_reset_the_assembler() {
+        let fragments: Vec<_> = encode_fragmented(&delta(1000))
+            .iter()
+            .map(|packet| fragment(packet))
+            .collect();
+        let push = |assembler: &mut FragmentAssembler, i: usize| {
+            let (index, count, part) = &fragments[i];
+            assembler.push(*index, *count, part)
+        };
+
+        let mut assembler = FragmentAssembler::default();
+        assert_eq!(push(&mut assembler, 0), None);
+        assert_eq!(push(&mut assembler, 2), None);
+        // The message is dropped, so the rest of it doesn't assemble either.
+        for i in 1..fragments.len() {
+            assert_eq!(push(&mut assembler, i), None);
+        }
+
+        // The next message starting from its first fragment assembles again.
+        let assembled = (0..fragments.len())
+            .filter_map(|i| push(&mut assembler, i))
+            .last();
+        assert_eq!(assembled, Some(encode(&delta(1000))));
+    }
+
+    #[test]
+    fn foreign_fragments_reset_the_assembler() {
+        let message = encode_fragmented(&delta(1000));
+        let other = encode_fragmented(&delta(2000));
+        let (index, count, part) = fragment(&message[0]);
+        let (other_index, other_count, other_part) = fragment(&other[1]);
+        assert_ne!(count, other_count);
+
+        let mut assembler = FragmentAssembler::default();
+        assert_eq!(assembler.push(index, count, &part), None);
+        assert_eq!(assembler.push(other_index, other_count, &other_part), None);
+
+        // The fragment after the first one no longer follows anything.
+        let (index, count, part) = fragment(&message[1]);
+        assert_eq!(assembler.push(index, count, &part), None);
+    }
+
+    #[test]
+    fn messages_with_too_many_fragments_are_dropped() {
+        let mut assembler = FragmentAssembler::default();
+
+        assert_eq!(assembler.push(0, MAX_FRAGMENTS + 1, &[1, 2, 3]), None);
+        assert_eq!(assembler.push(0, 1, &[1, 2, 3]), Some(vec![1, 2, 3]));
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashSet};
+
+use super::protocol::PlayerSnapshot;
+
+/// How far in the past other players are drawn, in seconds. Snapshots are sent every 50 milliseconds, so this leaves
+/// room for one to arrive late or get lost.
+const INTERPOLATION_DELAY: f64 = 0.15;
+/// How much every received snapshot moves the estimate of the server clock. Smooths out the jitter of the network.
+const CLOCK_SMOOTHING: f64 = 0.1;
+
+/// This plugin spawns an entity for every other player in the
+/// [ServerMessage::PlayerSnapshots](super::protocol::ServerMessage::PlayerSnapshots), and despawns it once they're no
+/// longer in them.
+///
+/// Snapshots only arrive a few times a second, so moving the players to the latest one would make them stutter.
+/// Instead, players are drawn [INTERPOLATION_DELAY] seconds in the past, in between the two snapshots around that
+/// time.
+pub(super) struct PlayerInterpolationPlugin {
+    /// The player on this side, who isn't spawned.
+    pub(super) local_player_id: u64,
+}
+
+impl Plugin for PlayerInterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ServerClock {
+            local_player_id: self.local_player_id,
+            offset: None,
+            latest_server_time: f64::NEG_INFINITY,
+        })
+        .add_event::<PlayerSnapshotsReceived>()
+        .add_systems(
+            Update,
+            (systems::receive_snapshots, systems::interpolate_players).chain(),
+        );
+    }
+}
+
+/// Sent for every [ServerMessage::PlayerSnapshots](super::protocol::ServerMessage::PlayerSnapshots) received. The
+/// host sends these to itself.
+#[derive(Event, Debug, Clone)]
+pub(super) struct PlayerSnapshotsReceived {
+    pub(super) server_time: f64,
+    pub(super) players: Vec<PlayerSnapshot>,
+}
+
+/// Another player in the game, moved by the snapshots the server sends.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub(super) struct NetworkedPlayer {
+    pub(super) id: u64,
+    pub(super) name: String,
+}
+
+/// The snapshots of a [NetworkedPlayer] that haven't been passed yet, oldest first, by their server time.
+#[derive(Component, Default, Debug)]
+struct SnapshotBuffer(VecDeque<(f64, Vec3, Quat)>);
+
+/// Estimates the clock of the server.
+#[derive(Resource, Debug)]
+struct ServerClock {
+    local_player_id: u64,
+    /// How far ahead the server clock is of the local one, in seconds. [None] until the first snapshot.
+    offset: Option<f64>,
+    /// The server time of the latest snapshot. Older snapshots arriving late are ignored.
+    latest_server_time: f64,
+}
+
+mod systems {
+    use super::*;
+
+    pub(super) fn receive_snapshots(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut clock: ResMut<ServerClock>,
+        mut snapshot_events: EventReader<PlayerSnapshotsReceived>,
+        mut player_query: Query<(Entity, &mut NetworkedPlayer, &mut SnapshotBuffer)>,
+    ) {
+        let mut latest = None;
+        for snapshots in snapshot_events.read() {
+            if snapshots.server_time <= clock.latest_server_time {
+                continue;
+            }
+            clock.latest_server_time = snapshots.server_time;
+
+            let sample = snapshots.server_time - time.elapsed_seconds_f64();
+            clock.offset = Some(match clock.offset {
+                Some(offset) => offset + (sample - offset) * CLOCK_SMOOTHING,
+                None => sample,
+            });
+
+            latest = Some(snapshots);
+        }
+
+        // Every snapshot has every player, so only the latest one is needed to know who is still there.
+        let Some(snapshots) = latest else {
+            return;
+        };
+
+        let mut seen = HashSet::new();
+        for snapshot in &snapshots.players {
+            if snapshot.id == clock.local_player_id {
+                continue;
+            }
+            seen.insert(snapshot.id);
+
+            let entry = (
+                snapshots.server_time,
+                snapshot.translation,
+                snapshot.rotation,
+            );
+            match player_query
+                .iter_mut()
+                .find(|(_, player, _)| player.id == snapshot.id)
+            {
+                Some((_, mut player, mut buffer)) => {
+                    buffer.0.push_back(entry);
+
+                    if player.name != snapshot.name {
+                        player.name.clone_from(&snapshot.name);
+                    }
+                }
+                None => {
+                    commands.spawn((
+                        NetworkedPlayer {
+                            id: snapshot.id,
+                            name: snapshot.name.clone(),
+                        },
+                        SnapshotBuffer(VecDeque::from([entry])),
+                        SpatialBundle::from_transform(
+                            Transform::from_translation(snapshot.translation)
+                                .with_rotation(snapshot.rotation),
+                        ),
+                    ));
+                }
+            }
+        }
+
+        for (entity, player, _) in &player_query {
+            if !seen.contains(&player.id) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+
+    pub(super) fn interpolate_players(
+        time: Res<Time>,
+        clock: Res<ServerClock>,
+        mut player_query: Query<(&mut Transform, &mut SnapshotBuffer)>,
+    ) {
+        let Some(offset) = clock.offset else {
+            return;
+        };
+        let render_time = time.elapsed_seconds_f64() + offset - INTERPOLATION_DELAY;
+
+        for (mut transform, mut buffer) in &mut player_query {
+            // Keep the last snapshot before the render time, to interpolate from.
+            while buffer
+                .0
+                .get(1)
+                .is_some_and(|(time, ..)| *time <= render_time)
+            {
+                buffer.0.pop_front();
+            }
+
+            let Some((from_time, from_translation, from_rotation)) = buffer.0.front().copied()
+            else {
+                continue;
+            };
+
+            // Without a newer snapshot, the player stays where they were last seen.
+            let Some((to_time, to_translation, to_rotation)) = buffer.0.get(1).copied() else {
+                transform.translation = from_translation;
+                transform.rotation = from_rotation;
+                continue;
+            };
+
+            let t = ((render_time - from_time) / (to_time - from_time)).clamp(0.0, 1.0) as f32;
+            transform.translation = from_translation.lerp(to_translation, t);
+            transform.rotation = from_rotation.slerp(to_rotation, t);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Networking between a server, which simulates the world, and the clients playing in it.
+//!
+//! The server is authoritative. It owns the [VoxelChunkMap](super::generation::VoxelChunkMap), generates and
+//! simulates the chunks, and streams them to every client around where they are. Clients only draw what they
+//! receive, and send what they want to do, like [VoxelEdit](super::edit::VoxelEdit)s and where they moved, back to
+//! the server.
+
+mod avatar;
+mod client;
+mod discovery;
+mod fragment;
+mod interpolation;
+mod payload;
+mod prediction;
+mod protocol;
+mod server;
+mod validation;
+
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use bevy::prelude::*;
+
+pub use self::{
+    discovery::{
+        LanServer, LanServerBrowser, LanServerBrowserPlugin, LanServerInfo, DISCOVERY_PORT,
+    },
+    protocol::DEFAULT_PORT,
+};
+
+use self::{
+    avatar::PlayerAvatarPlugin, client::VoxelClientNetworkPlugin,
+    interpolation::PlayerInterpolationPlugin, protocol::HOST_PLAYER_ID,
+    server::VoxelServerNetworkPlugin,
+};
+
+use super::VoxelClientPlugin;
+
+/// How this instance of the game takes part in a networked game.
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq)]
+pub enum NetworkMode {
+    /// Single player. Nothing is sent over the network.
+    #[default]
+    Offline,
+    /// Plays, and lets other players join on the given port.
+    Host { port: u16 },
+    /// Joins the server at the given address.
+    Client { server_addr: SocketAddr },
+}
+
+impl NetworkMode {
+    /// Joins the server at an address like `localhost` or `192.168.0.2:5000`, or plays offline if it can't be
+    /// resolved.
+    pub fn connect(addr: &str) -> Self {
+        match resolve_server_addr(addr) {
+            Some(server_addr) => NetworkMode::Client { server_addr },
+            None => {
+                warn!("Couldn't resolve server address {addr}, playing offline instead");
+                NetworkMode::Offline
+            }
+        }
+    }
+}
+
+/// The name other players see in the chat.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct PlayerName(pub String);
+
+impl Default for PlayerName {
+    fn default() -> Self {
+        Self("Player".to_string())
+    }
+}
+
+/// Resolves a server address like `localhost` or `192.168.0.2:5000`, using [DEFAULT_PORT] if it has no port.
+fn resolve_server_addr(addr: &str) -> Option<SocketAddr> {
+    let addr = if addr.contains(':') {
+        addr.to_string()
+    } else {
+        format!("{addr}:{DEFAULT_PORT}")
+    };
+
+    addr.to_socket_addrs().ok()?.next()
+}
+
+/// This plugin adds the server or client side of the networking, depending on the [NetworkMode] resource.
+/// When playing offline it does nothing.
+///
+/// A host that plays as well, with the [VoxelClientPlugin] added before this, also shows the other players.
+pub(super) struct VoxelNetworkPlugin;
+
+impl Plugin for VoxelNetworkPlugin {
+    fn build(&self, app: &mut App) {
+        let network_mode = app
+            .world
+            .get_resource::<NetworkMode>()
+            .cloned()
+            .unwrap_or_default();
+
+        match network_mode {
+            NetworkMode::Offline => {}
+            NetworkMode::Host { port } => {
+                app.add_plugins(VoxelServerNetworkPlugin { port });
+
+                if app.is_plugin_added::<VoxelClientPlugin>() {
+                    app.add_plugins((
+                        PlayerInterpolationPlugin {
+                            local_player_id: HOST_PLAYER_ID,
+                        },
+                        PlayerAvatarPlugin,
+                    ));
+                }
+            }
+            NetworkMode::Client { server_addr } => {
+                app.add_plugins(VoxelClientNetworkPlugin { server_addr });
+            }
+        }
+    }
+}
+
+mod systems {
+    use bevy_renet::renet::transport::NetcodeTransportError;
+
+    use super::*;
+
+    pub(super) fn log_transport_errors(mut errors: EventReader<NetcodeTransportError>) {
+        for error in errors.read() {
+            error!("Network error: {error}");
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::voxel::Voxel;
+
+/// The voxels of a whole chunk, the way they're sent over the network.
+///
+/// Most chunks only contain a handful of different voxels, so instead of sending every voxel, the chunk is split into
+/// a palette of its distinct voxels, and an index into that palette for every voxel. The indices are packed with as
+/// few bits as the palette needs, and the packed bytes are compressed with LZ4, since chunks tend to have long runs and
+/// repeating layers of the same voxels, like air or stone. A chunk of a single voxel takes no bits per index, and only
+/// sends its palette.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct ChunkPayload {
+    /// Every distinct voxel in the chunk, in the order they first appear.
+    palette: Vec<Voxel>,
+    /// How many bits every packed palette index takes up.
+    bits_per_index: u8,
+    /// The packed palette indices, compressed with LZ4.
+    compressed: Vec<u8>,
+}
+
+impl ChunkPayload {
+    /// Packs all the voxels of a chunk, in the order of [VoxelChunk::voxels](crate::voxel::generation::VoxelChunk::voxels).
+    pub(super) fn encode(voxels: &[Voxel]) -> Self {
+        let mut palette = Vec::new();
+        let mut palette_indices = HashMap::new();
+        // Chunks of colored voxels can have more distinct voxels than fit in a u16.
+        let indices: Vec<u32> = voxels
+            .iter()
+            .map(|voxel| {
+                *palette_indices.entry(*voxel).or_insert_with(|| {
+                    palette.push(*voxel);
+                    (palette.len() - 1) as u32
+                })
+            })
+            .collect();
+
+        let bits_per_index = bits_for(palette.len());
+
+        let mut packed = Vec::with_capacity(indices.len() * bits_per_index as usize / 8 + 1);
+        let mut buffer = 0u32;
+        let mut buffered_bits = 0;
+        for index in indices {
+            buffer |= index << buffered_bits;
+            buffered_bits += bits_per_index as u32;
+
+            while buffered_bits >= 8 {
+                packed.push(buffer as u8);
+                buffer >>= 8;
+                buffered_bits -= 8;
+            }
+        }
+        if buffered_bits > 0 {
+            packed.push(buffer as u8);
+        }
+
+        Self {
+            palette,
+            bits_per_index,
+            compressed: if packed.is_empty() {
+                Vec::new()
+            } else {
+                lz4_flex::compress(&packed)
+            },
+        }
+    }
+
+    /// Unpacks the voxels of the chunk. Returns [None] if the payload doesn't hold exactly `voxel_count` valid voxels.
+    pub(super) fn decode(&self, voxel_count: usize) -> Option<Vec<Voxel>> {
+        if self.bits_per_index > 24 || self.bits_per_index != bits_for(self.palette.len()) {
+            return None;
+        }
+
+        if self.bits_per_index == 0 {
+            let voxel = *self.palette.first()?;
+            return self.compressed.is_empty().then(|| vec![voxel; voxel_count]);
+        }
+
+        // The size is known up front, so a malicious payload can't make this allocate more.
+        let packed_len = (voxel_count * self.bits_per_index as usize).div_ceil(8);
+        let packed = lz4_flex::decompress(&self.compressed, packed_len).ok()?;
+        // Anything else means the payload was made for a differently sized chunk.
+        if packed.len() != packed_len {
+            return None;
+        }
+
+        let mut packed = packed.into_iter();
+        let mask = (1u32 << self.bits_per_index) - 1;
+
+        let mut voxels = Vec::with_capacity(voxel_count);
+        let mut buffer = 0u32;
+        let mut buffered_bits = 0;
+        while voxels.len() < voxel_count {
+            while buffered_bits < self.bits_per_index as u32 {
+                buffer |= (packed.next()? as u32) << buffered_bits;
+                buffered_bits += 8;
+            }
+
+            voxels.push(*self.palette.get((buffer & mask) as usize)?);
+            buffer >>= self.bits_per_index;
+            buffered_bits -= self.bits_per_index as u32;
+        }
+
+        Some(voxels)
+    }
+}
+
+/// How many bits are needed to index a palette of `palette_len` voxels.
+fn bits_for(palette_len: usize) -> u8 {
+    if palette_len <= 1 {
+        0
+    } else {
+        (usize::BITS - (palette_len - 1).leading_zeros()) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// As many voxels as a chunk 16 voxels wide has.
+    const VOXEL_COUNT: usize = 16 * 16 * 16;
+
+    fn round_trip(voxels: &[Voxel]) -> ChunkPayload {
+        let payload = ChunkPayload::encode(voxels);
+        assert_eq!(payload.decode(voxels.len()).as_deref(), Some(voxels));
+        payload
+    }
+
+    #[test]
+    fn chunks_of_a_single_voxel_only_send_the_palette() {
+        let payload = round_trip(&[Voxel::STONE; VOXEL_COUNT]);
+
+        assert_eq!(payload.palette, vec![Voxel::STONE]);
+        assert_eq!(payload.bits_per_index, 0);
+        assert!(payload.compressed.is_empty());
+    }
+
+    #[test]
+    fn layered_chunks_round_trip() {
+        let layers = [
+            Voxel::BEDROCK,
+            Voxel::STONE,
+            Voxel::DIRT,
+            Voxel::GRASS,
+            Voxel::AIR,
+        ];
+        let voxels: Vec<_> = (0..VOXEL_COUNT)
+            .map(|index| layers[(index / 256).min(layers.len() - 1)])
+            .collect();
+
+        let payload = round_trip(&voxels);
+
+        assert_eq!(payload.palette, layers);
+        assert_eq!(payload.bits_per_index, 3);
+    }
+
+    #[test]
+    fn palettes_wider_than_a_byte_round_trip() {
+        let voxels: Vec<_> = (0..VOXEL_COUNT)
+            .map(|index| Voxel::new((index * 7 % 300) as u16))
+            .collect();
+
+        let payload = round_trip(&voxels);
+
+        assert_eq!(payload.palette.len(), 300);
+        assert_eq!(payload.bits_per_index, 9);
+    }
+
+    #[test]
+    fn payloads_of_a_different_size_are_rejected() {
+        let voxels: Vec<_> = (0..VOXEL_COUNT)
+            .map(|index| [Voxel::AIR, Voxel::STONE][index % 2])
+            .collect();
+        let payload = ChunkPayload::encode(&voxels);
+
+        assert_eq!(payload.decode(VOXEL_COUNT / 2), None);
+        assert_eq!(payload.decode(VOXEL_COUNT * 2), None);
+    }
+
+    #[test]
+    fn payloads_with_a_mismatched_palette_are_rejected() {
+        let mut payload = ChunkPayload::encode(&[Voxel::STONE; VOXEL_COUNT]);
+        payload.compressed = vec![0];
+        assert_eq!(payload.decode(VOXEL_COUNT), None);
+
+        payload.palette.clear();
+        payload.compressed.clear();
+        assert_eq!(payload.decode(VOXEL_COUNT), None);
+    }
+}
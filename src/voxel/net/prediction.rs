@@ -0,0 +1,153 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{prelude::*, time::common_conditions::on_timer, transform::TransformSystem};
+use bevy_renet::client_connected;
+
+/// How often the movement of the player is sent to the server.
+const INPUT_INTERVAL: Duration = Duration::from_millis(50);
+/// How much of the remaining correction is applied every second.
+const CORRECTION_RATE: f32 = 10.0;
+/// Corrections shorter than this, in voxels, are ignored, so rounding errors don't make the camera wobble.
+const MIN_CORRECTION: f32 = 0.01;
+/// Corrections longer than this, in voxels, are applied at once instead of smoothly, like when the server moved the
+/// player somewhere else entirely.
+const MAX_SMOOTH_CORRECTION: f32 = 4.0;
+
+/// This plugin predicts the movement of the player on a client.
+///
+/// The camera moves right away, and how far it moved is sent to the server as inputs. Every
+/// [ServerMessage::PlayerAck](super::protocol::ServerMessage::PlayerAck) says where the server put the player after an
+/// input. Replaying the inputs the server hasn't applied yet on top of that gives where the player should be now. If
+/// the camera is somewhere else, it's moved there smoothly, instead of snapping back to where the server was a round
+/// trip ago.
+pub(super) struct PlayerPredictionPlugin;
+
+impl Plugin for PlayerPredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PredictedMovement>()
+            .add_event::<PlayerAcknowledged>()
+            .add_systems(
+                Update,
+                systems::send_player_input
+                    .run_if(client_connected().and_then(on_timer(INPUT_INTERVAL))),
+            )
+            .add_systems(
+                PostUpdate,
+                systems::reconcile_movement
+                    .run_if(client_connected())
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Sent when the server acknowledges the inputs of the player, up to and including `sequence`.
+#[derive(Event, Debug, Clone, Copy)]
+pub(super) struct PlayerAcknowledged {
+    pub(super) sequence: u32,
+    /// Where the server put the player.
+    pub(super) translation: Vec3,
+}
+
+#[derive(Resource, Default, Debug)]
+struct PredictedMovement {
+    /// The sequence of the last sent input.
+    sequence: u32,
+    /// The sequence of the last acknowledged input. Acknowledgements can arrive out of order, older ones are ignored.
+    acknowledged: u32,
+    /// Where the camera was at the end of the last frame.
+    last_translation: Option<Vec3>,
+    /// How far the player moved since the last input was sent.
+    unsent_movement: Vec3,
+    /// The sequence and movement of every input the server hasn't acknowledged yet, oldest first.
+    pending_inputs: VecDeque<(u32, Vec3)>,
+    /// How far the camera still has to be moved to get to where the player should be.
+    correction: Vec3,
+}
+
+mod systems {
+    use bevy_renet::renet::RenetClient;
+
+    use crate::voxel::{
+        load::RenderDistance,
+        net::protocol::{encode, ClientMessage},
+    };
+
+    use super::*;
+
+    pub(super) fn send_player_input(
+        mut client: ResMut<RenetClient>,
+        mut predicted: ResMut<PredictedMovement>,
+        camera_query: Query<(&Transform, &RenderDistance), With<Camera3d>>,
+    ) {
+        let Ok((transform, render_distance)) = camera_query.get_single() else {
+            return;
+        };
+
+        predicted.sequence += 1;
+        let sequence = predicted.sequence;
+        let movement = std::mem::take(&mut predicted.unsent_movement);
+        predicted.pending_inputs.push_back((sequence, movement));
+
+        let message = ClientMessage::PlayerInput {
+            sequence,
+            movement,
+            rotation: transform.rotation,
+            render_distance: render_distance.val,
+            unload_margin: render_distance.unload_margin,
+        };
+        client.send_message(message.channel(), encode(&message));
+    }
+
+    /// Records how far the player moved this frame, and moves the camera towards where the server says it should be.
+    pub(super) fn reconcile_movement(
+        time: Res<Time>,
+        mut predicted: ResMut<PredictedMovement>,
+        mut acknowledgements: EventReader<PlayerAcknowledged>,
+        mut camera_query: Query<&mut Transform, With<Camera3d>>,
+    ) {
+        let Ok(mut transform) = camera_query.get_single_mut() else {
+            return;
+        };
+
+        // Whatever moved the camera since the last frame, like the flycam, is the player moving.
+        if let Some(last_translation) = predicted.last_translation {
+            predicted.unsent_movement += transform.translation - last_translation;
+        }
+
+        let latest = acknowledgements
+            .read()
+            .filter(|ack| ack.sequence >= predicted.acknowledged)
+            .max_by_key(|ack| ack.sequence)
+            .copied();
+        if let Some(ack) = latest {
+            predicted.acknowledged = ack.sequence;
+            predicted
+                .pending_inputs
+                .retain(|(sequence, _)| *sequence > ack.sequence);
+
+            let predicted_translation = ack.translation
+                + predicted
+                    .pending_inputs
+                    .iter()
+                    .map(|(_, movement)| *movement)
+                    .sum::<Vec3>()
+                + predicted.unsent_movement;
+            let error = predicted_translation - transform.translation;
+
+            predicted.correction = if error.length() < MIN_CORRECTION {
+                Vec3::ZERO
+            } else if error.length() > MAX_SMOOTH_CORRECTION {
+                transform.translation = predicted_translation;
+                Vec3::ZERO
+            } else {
+                error
+            };
+        }
+
+        let step = predicted.correction * (CORRECTION_RATE * time.delta_seconds()).min(1.0);
+        transform.translation += step;
+        predicted.correction -= step;
+
+        predicted.last_translation = Some(transform.translation);
+    }
+}
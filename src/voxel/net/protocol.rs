@@ -0,0 +1,239 @@
+use bevy::prelude::*;
+use bevy_renet::renet::{transport::NETCODE_USER_DATA_BYTES, DefaultChannel};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    chat::ChatLine,
+    voxel::{
+        color::VoxelMode, edit::VoxelEdit, generation::VoxelChunkPosition, mesher::TerrainMesher,
+        micro::MicroVoxelEdit, weather::Weather, Voxel,
+    },
+};
+
+use super::payload::ChunkPayload;
+
+/// Identifies this game on the wire, so other programs and incompatible versions can't connect.
+/// Bump the last digits whenever the messages change.
+pub(super) const PROTOCOL_ID: u64 = 0x766f_7865_6c00_000d;
+/// The port servers listen on, unless another one is given.
+pub const DEFAULT_PORT: u16 = 5000;
+
+/// Messages sent from a client to the server.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) enum ClientMessage {
+    /// How far the player moved since their last input, where they look, and how far around them they want to see.
+    ///
+    /// The client moves the player right away, and the server applies the same movement, within how fast players can
+    /// move. The server answers with a [ServerMessage::PlayerAck], which the client reconciles its prediction with.
+    PlayerInput {
+        /// Goes up by one with every input, starting at 1.
+        sequence: u32,
+        movement: Vec3,
+        rotation: Quat,
+        render_distance: u32,
+        unload_margin: u32,
+    },
+    /// The player wants to change a voxel.
+    Edit(VoxelEdit),
+    /// The player wants to change a micro voxel. Rejected micro edits are answered with a
+    /// [ServerMessage::EditRejected] for the voxel they're in.
+    MicroEdit(MicroVoxelEdit),
+    /// The client couldn't apply a [ServerMessage::ChunkDelta], and needs the whole chunk again.
+    RequestChunk(VoxelChunkPosition),
+    /// The player typed a message into the chat.
+    Chat(String),
+}
+
+impl ClientMessage {
+    pub(super) fn channel(&self) -> DefaultChannel {
+        match self {
+            // Inputs are movement since the last one, so every input has to arrive, in order.
+            ClientMessage::PlayerInput { .. }
+            | ClientMessage::Edit(_)
+            | ClientMessage::MicroEdit(_)
+            | ClientMessage::RequestChunk(_)
+            | ClientMessage::Chat(_) => DefaultChannel::ReliableOrdered,
+        }
+    }
+}
+
+/// Messages sent from the server to a client.
+///
+/// Every chunk has a revision, which goes up by one whenever it changes. A client first gets a whole
+/// [ServerMessage::Chunk], and after that only [ServerMessage::ChunkDelta]s with the voxels that changed. All of these
+/// are sent in order on the same reliable channel, so a delta always applies to the revision the client has. If it
+/// doesn't anyway, the client asks for the whole chunk again with [ClientMessage::RequestChunk].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) enum ServerMessage {
+    /// All the voxels of a chunk. Sent when the chunk comes close enough to the player, or when a lot of it changed at
+    /// once.
+    Chunk {
+        chunk_pos: VoxelChunkPosition,
+        revision: u32,
+        payload: ChunkPayload,
+    },
+    /// The voxels of a chunk that changed since `base_revision`, by their index in
+    /// [VoxelChunk::voxels](crate::voxel::generation::VoxelChunk::voxels). Applying them makes it `base_revision + 1`.
+    ChunkDelta {
+        chunk_pos: VoxelChunkPosition,
+        base_revision: u32,
+        changes: Vec<(u32, Voxel)>,
+    },
+    /// A voxel edit the server applied, in a chunk the client has. The change is part of the next
+    /// [ServerMessage::ChunkDelta] as well, but this lets clients apply it the same way as a local edit, and react to
+    /// it.
+    VoxelEdit(VoxelEdit),
+    /// The micro voxels of a voxel, in a chunk the client has, or [None] if it's no longer subdivided. Sent after every
+    /// chunk, for the micro blocks in it, and whenever a micro block changes.
+    MicroBlock {
+        voxel_pos: IVec3,
+        payload: Option<ChunkPayload>,
+    },
+    /// The chunk is too far away from the player, and should be despawned.
+    UnloadChunk(VoxelChunkPosition),
+    /// The weather changed.
+    Weather(Weather),
+    /// How the terrain of the world is meshed. Sent when the client connects, before any chunks.
+    TerrainMesher(TerrainMesher),
+    /// What the voxels of the world hold. Sent when the client connects, before any chunks.
+    VoxelMode(VoxelMode),
+    /// A line to add to the chat.
+    Chat(ChatLine),
+    /// Where the server put the player, after applying their inputs up to and including `sequence`.
+    PlayerAck { sequence: u32, translation: Vec3 },
+    /// Where every player is, at `server_time` seconds since the server started. Clients draw the other players
+    /// slightly in the past, in between the last two snapshots.
+    PlayerSnapshots {
+        server_time: f64,
+        players: Vec<PlayerSnapshot>,
+    },
+    /// The server didn't apply a [ClientMessage::Edit] of this client. `voxel` is what's really at `voxel_pos`, or
+    /// [None] if the server doesn't have it loaded.
+    EditRejected {
+        voxel_pos: IVec3,
+        voxel: Option<Voxel>,
+        reason: EditRejection,
+    },
+    /// Part of an encoded message too large for one packet, like a whole chunk. See
+    /// [encode_fragmented](super::fragment::encode_fragmented).
+    Fragment {
+        index: u16,
+        count: u16,
+        bytes: Vec<u8>,
+    },
+}
+
+impl ServerMessage {
+    pub(super) fn channel(&self) -> DefaultChannel {
+        match self {
+            // Chunks and their unloading have to arrive in order, or a client could keep a chunk it was told to
+            // unload.
+            ServerMessage::Chunk { .. }
+            | ServerMessage::ChunkDelta { .. }
+            | ServerMessage::VoxelEdit(_)
+            | ServerMessage::MicroBlock { .. }
+            | ServerMessage::UnloadChunk(_)
+            | ServerMessage::Weather(_)
+            | ServerMessage::TerrainMesher(_)
+            | ServerMessage::VoxelMode(_)
+            | ServerMessage::EditRejected { .. }
+            | ServerMessage::Fragment { .. } => DefaultChannel::ReliableOrdered,
+            ServerMessage::Chat(_) => DefaultChannel::ReliableOrdered,
+            // Only the latest players matter, so lost messages don't need to be sent again.
+            ServerMessage::PlayerAck { .. } | ServerMessage::PlayerSnapshots { .. } => {
+                DefaultChannel::Unreliable
+            }
+        }
+    }
+}
+
+/// Why the server didn't apply a [ClientMessage::Edit].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum EditRejection {
+    /// The voxel is further away than players can reach.
+    OutOfReach,
+    /// A solid voxel is in between the player and the voxel.
+    Obstructed,
+    /// The player edits faster than allowed.
+    RateLimited,
+    /// The voxel is in one of the [ProtectedRegions](crate::voxel::edit::ProtectedRegions).
+    Protected,
+    /// The chunk of the voxel isn't loaded on the server.
+    Unloaded,
+    /// There is nothing to break, or no room to place the voxel, like when another player was faster.
+    Outdated,
+    /// The voxel can't be broken, like bedrock.
+    Indestructible,
+}
+
+impl std::fmt::Display for EditRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EditRejection::OutOfReach => "it's out of reach",
+            EditRejection::Obstructed => "something is in the way",
+            EditRejection::RateLimited => "too many changes at once",
+            EditRejection::Protected => "it's protected",
+            EditRejection::Unloaded => "it isn't loaded",
+            EditRejection::Outdated => "it changed in the meantime",
+            EditRejection::Indestructible => "it can't be broken",
+        })
+    }
+}
+
+/// A player in a [ServerMessage::PlayerSnapshots].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) struct PlayerSnapshot {
+    /// The client id of the player, or [HOST_PLAYER_ID] for the player hosting the server.
+    pub(super) id: u64,
+    pub(super) name: String,
+    pub(super) translation: Vec3,
+    pub(super) rotation: Quat,
+}
+
+/// The player id of the player hosting the server. Clients get random ids, which are never 0.
+pub(super) const HOST_PLAYER_ID: u64 = 0;
+
+/// Longer player names are cut off, in bytes.
+const MAX_NAME_BYTES: usize = 32;
+
+/// Packs the name of the player into the user data clients connect with.
+pub(super) fn name_to_user_data(name: &str) -> [u8; NETCODE_USER_DATA_BYTES] {
+    let mut end = name.len().min(MAX_NAME_BYTES);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let mut user_data = [0; NETCODE_USER_DATA_BYTES];
+    user_data[..end].copy_from_slice(&name.as_bytes()[..end]);
+    user_data
+}
+
+/// Unpacks the name of the player from the user data of a client. Returns [None] if it has no name.
+pub(super) fn name_from_user_data(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> Option<String> {
+    let bytes = &user_data[..MAX_NAME_BYTES];
+    let len = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+    let name = String::from_utf8_lossy(&bytes[..len]);
+    let name: String = name.chars().filter(|char| !char.is_control()).collect();
+    let name = name.trim();
+
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Serializes a message to send it.
+pub(super) fn encode(message: &impl Serialize) -> Vec<u8> {
+    bincode::serialize(message).expect("network messages can always be serialized")
+}
+
+/// Deserializes a received message. Messages that can't be read are logged and ignored.
+pub(super) fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Option<T> {
+    match bincode::deserialize(bytes) {
+        Ok(message) => Some(message),
+        Err(err) => {
+            warn!("Ignoring malformed network message: {err}");
+            None
+        }
+    }
+}
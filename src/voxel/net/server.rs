@@ -0,0 +1,771 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, SystemTime},
+};
+
+use bevy::{prelude::*, time::common_conditions::on_timer, utils::HashSet};
+use bevy_renet::{
+    renet::{
+        transport::{NetcodeServerTransport, ServerAuthentication, ServerConfig},
+        ClientId, ConnectionConfig, RenetServer,
+    },
+    transport::NetcodeServerPlugin,
+    RenetServerPlugin,
+};
+
+use crate::{
+    chat::ChatLine,
+    console::RegisterConsoleCommand,
+    voxel::{generation::VoxelChunkPosition, weather::Weather, Voxel},
+};
+
+use super::{
+    discovery::LanDiscoveryPlugin, protocol::PROTOCOL_ID, validation::EditValidationPlugin,
+    PlayerName,
+};
+
+/// How many players can be connected at once.
+pub(super) const MAX_CLIENTS: usize = 8;
+/// When more voxels than this change in a chunk at once, the whole chunk is sent instead of a delta.
+const MAX_DELTA_CHANGES: usize = 512;
+/// The fastest players can move, in voxels per second. The flycam moves at 12, the rest is slack for network jitter.
+const MAX_PLAYER_SPEED: f32 = 16.0;
+/// How many seconds of movement players can save up, while their inputs are delayed.
+const MAX_MOVEMENT_BUDGET_SECONDS: f32 = 1.0;
+/// How many voxels players can edit per second, on average.
+const MAX_EDITS_PER_SECOND: f32 = 10.0;
+/// How many edits players can save up, to make a few quickly after a pause.
+const MAX_EDIT_BURST: f32 = 20.0;
+/// How often every client is told where all the players are.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// This plugin hosts a server on [VoxelServerNetworkPlugin::port]. Every connected client gets a [RemotePlayer]
+/// entity with a [RenderDistance](crate::voxel::load::RenderDistance), so chunks load around them just like around
+/// the local player, and the loaded chunks are streamed to them.
+pub(super) struct VoxelServerNetworkPlugin {
+    pub(super) port: u16,
+}
+
+impl Plugin for VoxelServerNetworkPlugin {
+    fn build(&self, app: &mut App) {
+        let socket = match UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], self.port))) {
+            Ok(socket) => socket,
+            Err(err) => {
+                error!("Couldn't host a server on port {}: {err}", self.port);
+                return;
+            }
+        };
+
+        let server_config = ServerConfig {
+            current_time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default(),
+            max_clients: MAX_CLIENTS,
+            protocol_id: PROTOCOL_ID,
+            public_addresses: socket.local_addr().into_iter().collect(),
+            authentication: ServerAuthentication::Unsecure,
+        };
+
+        let transport = match NetcodeServerTransport::new(server_config, socket) {
+            Ok(transport) => transport,
+            Err(err) => {
+                error!("Couldn't host a server on port {}: {err}", self.port);
+                return;
+            }
+        };
+        info!("Hosting a server on port {}", self.port);
+
+        let name = app
+            .world
+            .get_resource::<PlayerName>()
+            .map_or_else(|| "Dedicated server".to_string(), |name| name.0.clone());
+
+        app.add_plugins((
+            RenetServerPlugin,
+            NetcodeServerPlugin,
+            LanDiscoveryPlugin {
+                port: self.port,
+                name,
+            },
+            EditValidationPlugin,
+        ))
+        .insert_resource(RenetServer::new(ConnectionConfig::default()))
+        .insert_resource(transport)
+        .add_systems(
+            Update,
+            (
+                systems::handle_server_events,
+                systems::receive_client_messages,
+                systems::broadcast_voxel_edits,
+                systems::broadcast_micro_blocks,
+                systems::send_changed_chunks,
+                systems::send_chunks,
+                systems::send_weather.run_if(state_changed::<Weather>()),
+                systems::broadcast_chat_lines,
+                systems::acknowledge_player_inputs,
+                systems::broadcast_player_snapshots.run_if(on_timer(SNAPSHOT_INTERVAL)),
+            )
+                .chain(),
+        )
+        .add_event::<ChatLine>()
+        .register_console_command("kick", "Disconnects the client with the given id")
+        .register_console_command("say", "Sends a message to every player")
+        .add_systems(
+            Update,
+            (
+                (systems::kick_clients, systems::say).before(systems::broadcast_chat_lines),
+                super::systems::log_transport_errors,
+            ),
+        );
+    }
+}
+
+/// A player connected to the server, seen from the server.
+#[derive(Component)]
+pub(super) struct RemotePlayer {
+    client_id: ClientId,
+    name: String,
+    /// The chunks the client has been sent, and not been told to unload since. The client always has the latest
+    /// [ReplicatedChunk::revision] of these, as every change is sent to it.
+    sent_chunks: HashSet<VoxelChunkPosition>,
+    /// The sequence of the last applied input.
+    last_input: u32,
+    /// The sequence of the last input the client was told about.
+    acknowledged_input: u32,
+    /// How far the player can still move, in voxels. Goes up with time, and down with every input.
+    movement_budget: f32,
+    /// How many voxels the player can still edit. Goes up with time, and down with every edit.
+    edit_budget: f32,
+}
+
+/// What clients have been sent of a chunk. Changes to the chunk are sent as the difference to this.
+///
+/// Only chunks that have been sent to at least one client have this.
+#[derive(Component)]
+struct ReplicatedChunk {
+    revision: u32,
+    voxels: Vec<Voxel>,
+}
+
+mod systems {
+    use bevy_renet::renet::{DefaultChannel, ServerEvent};
+
+    use crate::{
+        console::ConsoleCommand,
+        voxel::{
+            color::VoxelMode,
+            edit::{AppliedVoxelEdit, ProtectedRegions, VoxelEdit},
+            generation::{VoxelChunk, VoxelChunkMap, VoxelChunkWidth},
+            load::RenderDistance,
+            mesher::TerrainMesher,
+            micro::{AppliedMicroVoxelEdit, MicroBlock, MicroBlocks, MicroVoxelEdit},
+            net::{
+                fragment::encode_fragmented,
+                interpolation::PlayerSnapshotsReceived,
+                payload::ChunkPayload,
+                protocol::{
+                    decode, encode, name_from_user_data, ClientMessage, EditRejection,
+                    PlayerSnapshot, ServerMessage, HOST_PLAYER_ID,
+                },
+                validation::{validate_edit, validate_micro_edit, validate_render_distance},
+            },
+            world::VoxelWorld,
+            VoxelChunkCoordinate, VoxelConfig,
+        },
+    };
+
+    use super::*;
+
+    fn send(server: &mut RenetServer, client_id: ClientId, message: &ServerMessage) {
+        let channel = u8::from(message.channel());
+        for packet in encode_fragmented(message) {
+            server.send_message(client_id, channel, packet);
+        }
+    }
+
+    fn micro_block_message(voxel_pos: IVec3, micro_block: Option<&MicroBlock>) -> ServerMessage {
+        ServerMessage::MicroBlock {
+            voxel_pos,
+            payload: micro_block.map(|micro_block| ChunkPayload::encode(micro_block.voxels())),
+        }
+    }
+
+    /// Spawns a [RemotePlayer] for every client that connects, and despawns it once they disconnect.
+    pub(super) fn handle_server_events(
+        mut commands: Commands,
+        mut server_events: EventReader<ServerEvent>,
+        mut server: ResMut<RenetServer>,
+        transport: Res<NetcodeServerTransport>,
+        player_query: Query<(Entity, &RemotePlayer)>,
+        weather: Option<Res<State<Weather>>>,
+        mesher: Res<TerrainMesher>,
+        voxel_mode: Res<VoxelMode>,
+        mut chat_lines: EventWriter<ChatLine>,
+    ) {
+        for event in server_events.read() {
+            match event {
+                ServerEvent::ClientConnected { client_id } => {
+                    let name = transport
+                        .user_data(*client_id)
+                        .and_then(|user_data| name_from_user_data(&user_data))
+                        .unwrap_or_else(|| format!("Player {client_id}"));
+                    info!("{name} connected as client {client_id}");
+                    chat_lines.send(ChatLine {
+                        sender: None,
+                        text: format!("{name} joined the game"),
+                    });
+
+                    // The player only gets a render distance, and chunks, once it says where it is.
+                    commands.spawn((
+                        RemotePlayer {
+                            client_id: *client_id,
+                            name,
+                            sent_chunks: HashSet::new(),
+                            last_input: 0,
+                            acknowledged_input: 0,
+                            movement_budget: 0.0,
+                            edit_budget: MAX_EDIT_BURST,
+                        },
+                        TransformBundle::default(),
+                    ));
+
+                    send(
+                        &mut server,
+                        *client_id,
+                        &ServerMessage::TerrainMesher(*mesher),
+                    );
+                    send(
+                        &mut server,
+                        *client_id,
+                        &ServerMessage::VoxelMode(*voxel_mode),
+                    );
+                    if let Some(weather) = &weather {
+                        send(
+                            &mut server,
+                            *client_id,
+                            &ServerMessage::Weather(*weather.get()),
+                        );
+                    }
+                }
+                ServerEvent::ClientDisconnected { client_id, reason } => {
+                    info!("Client {client_id} disconnected: {reason}");
+
+                    for (entity, player) in &player_query {
+                        if player.client_id == *client_id {
+                            commands.entity(entity).despawn();
+                            chat_lines.send(ChatLine {
+                                sender: None,
+                                text: format!("{} left the game", player.name),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub(super) fn receive_client_messages(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut server: ResMut<RenetServer>,
+        mut player_query: Query<(Entity, &mut RemotePlayer, &mut Transform)>,
+        mut edits: EventWriter<VoxelEdit>,
+        mut micro_edits: EventWriter<MicroVoxelEdit>,
+        mut chat_lines: EventWriter<ChatLine>,
+        voxel_world: VoxelWorld,
+        protected_regions: Res<ProtectedRegions>,
+        config: Res<VoxelConfig>,
+    ) {
+        for (_, mut player, _) in &mut player_query {
+            player.movement_budget = (player.movement_budget
+                + MAX_PLAYER_SPEED * time.delta_seconds())
+            .min(MAX_PLAYER_SPEED * MAX_MOVEMENT_BUDGET_SECONDS);
+            player.edit_budget = (player.edit_budget + MAX_EDITS_PER_SECOND * time.delta_seconds())
+                .min(MAX_EDIT_BURST);
+        }
+
+        for client_id in server.clients_id() {
+            for channel in [DefaultChannel::Unreliable, DefaultChannel::ReliableOrdered] {
+                let channel = u8::from(channel);
+                while let Some(bytes) = server.receive_message(client_id, channel) {
+                    let Some(message) = decode::<ClientMessage>(&bytes) else {
+                        continue;
+                    };
+
+                    match message {
+                        ClientMessage::PlayerInput {
+                            sequence,
+                            movement,
+                            rotation,
+                            render_distance,
+                            unload_margin,
+                        } => {
+                            let Some((entity, mut player, mut transform)) = player_query
+                                .iter_mut()
+                                .find(|(_, player, _)| player.client_id == client_id)
+                            else {
+                                continue;
+                            };
+
+                            if sequence <= player.last_input
+                                || !movement.is_finite()
+                                || !rotation.is_finite()
+                            {
+                                continue;
+                            }
+
+                            // Players can't move faster than they could have since their last input.
+                            let movement = movement.clamp_length_max(player.movement_budget);
+                            player.movement_budget -= movement.length();
+                            player.last_input = sequence;
+
+                            transform.translation += movement;
+                            transform.rotation = rotation.normalize();
+                            commands.entity(entity).insert(validate_render_distance(
+                                render_distance,
+                                unload_margin,
+                                config.max_player_render_distance,
+                            ));
+                        }
+                        ClientMessage::Edit(edit) => {
+                            let Some((_, mut player, transform)) = player_query
+                                .iter_mut()
+                                .find(|(_, player, _)| player.client_id == client_id)
+                            else {
+                                continue;
+                            };
+
+                            let result = if player.edit_budget < 1.0 {
+                                Err(EditRejection::RateLimited)
+                            } else {
+                                player.edit_budget -= 1.0;
+                                validate_edit(
+                                    &edit,
+                                    transform.translation,
+                                    &voxel_world,
+                                    &protected_regions,
+                                )
+                            };
+
+                            match result {
+                                Ok(()) => edits.send(edit),
+                                Err(reason) => {
+                                    debug!(
+                                        "Rejected the edit of {} at {}: {reason}",
+                                        player.name, edit.voxel_pos
+                                    );
+                                    // Tell the client what's really there, in case it already shows the edit.
+                                    send(
+                                        &mut server,
+                                        client_id,
+                                        &ServerMessage::EditRejected {
+                                            voxel_pos: edit.voxel_pos,
+                                            voxel: voxel_world.get_block(edit.voxel_pos),
+                                            reason,
+                                        },
+                                    );
+                                }
+                            }
+                        }
+                        ClientMessage::MicroEdit(edit) => {
+                            let Some((_, mut player, transform)) = player_query
+                                .iter_mut()
+                                .find(|(_, player, _)| player.client_id == client_id)
+                            else {
+                                continue;
+                            };
+
+                            let result = if player.edit_budget < 1.0 {
+                                Err(EditRejection::RateLimited)
+                            } else {
+                                player.edit_budget -= 1.0;
+                                validate_micro_edit(
+                                    &edit,
+                                    transform.translation,
+                                    &voxel_world,
+                                    &protected_regions,
+                                )
+                            };
+
+                            match result {
+                                Ok(()) => micro_edits.send(edit),
+                                Err(reason) => {
+                                    debug!(
+                                        "Rejected the micro edit of {} at {}: {reason}",
+                                        player.name, edit.voxel_pos
+                                    );
+                                    send(
+                                        &mut server,
+                                        client_id,
+                                        &ServerMessage::EditRejected {
+                                            voxel_pos: edit.voxel_pos,
+                                            voxel: voxel_world.get_block(edit.voxel_pos),
+                                            reason,
+                                        },
+                                    );
+                                }
+                            }
+                        }
+                        ClientMessage::Chat(text) => {
+                            let Some((_, player, _)) = player_query
+                                .iter()
+                                .find(|(_, player, _)| player.client_id == client_id)
+                            else {
+                                continue;
+                            };
+
+                            if let Some(line) = ChatLine::new(Some(player.name.clone()), &text) {
+                                chat_lines.send(line);
+                            }
+                        }
+                        ClientMessage::RequestChunk(chunk_pos) => {
+                            // Forgetting that the chunk was sent makes `send_chunks` send it again.
+                            if let Some((_, mut player, _)) = player_query
+                                .iter_mut()
+                                .find(|(_, player, _)| player.client_id == client_id)
+                            {
+                                player.sent_chunks.remove(&chunk_pos);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends the applied edits to every client that has the edited chunk. This runs before [send_changed_chunks], so
+    /// clients get the edit before the delta that contains it.
+    pub(super) fn broadcast_voxel_edits(
+        mut server: ResMut<RenetServer>,
+        mut applied_edits: EventReader<AppliedVoxelEdit>,
+        player_query: Query<&RemotePlayer>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        for AppliedVoxelEdit(edit) in applied_edits.read() {
+            let (chunk_pos, _) = VoxelChunkPosition::world_to_local(edit.voxel_pos, &chunk_width);
+
+            for player in &player_query {
+                if player.sent_chunks.contains(&chunk_pos) {
+                    send(
+                        &mut server,
+                        player.client_id,
+                        &ServerMessage::VoxelEdit(*edit),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Sends the micro blocks changed by micro edits to every client that has their chunk. Like
+    /// [broadcast_voxel_edits], this runs before [send_changed_chunks], so a voxel that was just subdivided is never
+    /// drawn without its micro block.
+    pub(super) fn broadcast_micro_blocks(
+        mut server: ResMut<RenetServer>,
+        mut applied_edits: EventReader<AppliedMicroVoxelEdit>,
+        player_query: Query<&RemotePlayer>,
+        micro_blocks: Res<MicroBlocks>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let changed: HashSet<IVec3> = applied_edits
+            .read()
+            .map(|AppliedMicroVoxelEdit(edit)| edit.voxel_pos)
+            .collect();
+
+        for voxel_pos in changed {
+            let (chunk_pos, _) = VoxelChunkPosition::world_to_local(voxel_pos, &chunk_width);
+            let message = micro_block_message(voxel_pos, micro_blocks.get(voxel_pos, &chunk_width));
+
+            for player in &player_query {
+                if player.sent_chunks.contains(&chunk_pos) {
+                    send(&mut server, player.client_id, &message);
+                }
+            }
+        }
+    }
+
+    /// Sends what changed in every chunk to the clients that have it, as a [ServerMessage::ChunkDelta], or as a whole
+    /// chunk if too much of it changed.
+    pub(super) fn send_changed_chunks(
+        mut server: ResMut<RenetServer>,
+        mut player_query: Query<&mut RemotePlayer>,
+        mut chunk_query: Query<
+            (&VoxelChunkPosition, &VoxelChunk, &mut ReplicatedChunk),
+            Changed<VoxelChunk>,
+        >,
+    ) {
+        for (chunk_pos, chunk, mut replicated) in &mut chunk_query {
+            let voxels = chunk.voxels();
+            let changes: Vec<(u32, Voxel)> = voxels
+                .iter()
+                .zip(&replicated.voxels)
+                .enumerate()
+                .filter(|(_, (voxel, old_voxel))| voxel != old_voxel)
+                .map(|(i, (voxel, _))| (i as u32, *voxel))
+                .collect();
+            if changes.is_empty() {
+                continue;
+            }
+
+            let message = if changes.len() > MAX_DELTA_CHANGES {
+                ServerMessage::Chunk {
+                    chunk_pos: *chunk_pos,
+                    revision: replicated.revision + 1,
+                    payload: ChunkPayload::encode(&voxels),
+                }
+            } else {
+                ServerMessage::ChunkDelta {
+                    chunk_pos: *chunk_pos,
+                    base_revision: replicated.revision,
+                    changes,
+                }
+            };
+            let channel = u8::from(message.channel());
+            let packets = encode_fragmented(&message);
+            let size = packets.iter().map(Vec::len).sum();
+
+            replicated.revision += 1;
+            replicated.voxels.copy_from_slice(&voxels);
+
+            for mut player in &mut player_query {
+                if !player.sent_chunks.contains(chunk_pos) {
+                    continue;
+                }
+
+                if server.can_send_message(player.client_id, channel, size) {
+                    for packet in &packets {
+                        server.send_message(player.client_id, channel, packet.clone());
+                    }
+                } else {
+                    // The client is too far behind. Forget the chunk was sent, so it's sent again by `send_chunks`
+                    // once there is room.
+                    player.sent_chunks.remove(chunk_pos);
+                }
+            }
+        }
+    }
+
+    /// Sends the closest loaded chunks every client doesn't have yet, and tells them to unload the chunks that are
+    /// out of their render distance.
+    pub(super) fn send_chunks(
+        mut commands: Commands,
+        mut server: ResMut<RenetServer>,
+        mut player_query: Query<(&mut RemotePlayer, &Transform, &RenderDistance)>,
+        chunk_query: Query<(&VoxelChunk, Option<&ReplicatedChunk>)>,
+        voxel_chunk_map: Res<VoxelChunkMap>,
+        micro_blocks: Res<MicroBlocks>,
+        chunk_width: Res<VoxelChunkWidth>,
+        config: Res<VoxelConfig>,
+    ) {
+        let _span = info_span!("send_chunks").entered();
+
+        for (mut player, transform, render_distance) in &mut player_query {
+            let client_id = player.client_id;
+            let origin_chunk_pos = transform.translation.as_chunk_pos(&chunk_width);
+            let distance_to = |chunk_pos: &VoxelChunkPosition| {
+                render_distance.chunk_distance((*chunk_pos - origin_chunk_pos).0)
+            };
+
+            let unloads: Vec<VoxelChunkPosition> = player
+                .sent_chunks
+                .iter()
+                .filter(|chunk_pos| {
+                    !voxel_chunk_map.0.contains_key(*chunk_pos)
+                        || !render_distance.keeps((**chunk_pos - origin_chunk_pos).0)
+                })
+                .copied()
+                .collect();
+
+            for chunk_pos in unloads {
+                player.sent_chunks.remove(&chunk_pos);
+                send(
+                    &mut server,
+                    client_id,
+                    &ServerMessage::UnloadChunk(chunk_pos),
+                );
+            }
+
+            let mut missing: Vec<(&VoxelChunkPosition, &Entity)> = voxel_chunk_map
+                .0
+                .iter()
+                .filter(|(chunk_pos, _)| {
+                    !player.sent_chunks.contains(*chunk_pos)
+                        && distance_to(chunk_pos) <= render_distance.val as f32
+                })
+                .collect();
+            missing.sort_by(|(a, _), (b, _)| distance_to(a).total_cmp(&distance_to(b)));
+
+            for (chunk_pos, chunk_entity) in missing.into_iter().take(config.chunk_sends_per_frame)
+            {
+                let Ok((chunk, replicated)) = chunk_query.get(*chunk_entity) else {
+                    continue;
+                };
+
+                // Send what the other clients have, so the same deltas apply to every client.
+                let message = match replicated {
+                    Some(replicated) => ServerMessage::Chunk {
+                        chunk_pos: *chunk_pos,
+                        revision: replicated.revision,
+                        payload: ChunkPayload::encode(&replicated.voxels),
+                    },
+                    None => ServerMessage::Chunk {
+                        chunk_pos: *chunk_pos,
+                        revision: 0,
+                        payload: ChunkPayload::encode(&chunk.voxels()),
+                    },
+                };
+                let channel = u8::from(message.channel());
+                let packets = encode_fragmented(&message);
+
+                if !server.can_send_message(client_id, channel, packets.iter().map(Vec::len).sum())
+                {
+                    break;
+                }
+
+                for packet in packets {
+                    server.send_message(client_id, channel, packet);
+                }
+                for (voxel_pos, micro_block) in micro_blocks.chunk(*chunk_pos) {
+                    send(
+                        &mut server,
+                        client_id,
+                        &micro_block_message(voxel_pos, Some(micro_block)),
+                    );
+                }
+                player.sent_chunks.insert(*chunk_pos);
+
+                if replicated.is_none() {
+                    commands.entity(*chunk_entity).insert(ReplicatedChunk {
+                        revision: 0,
+                        voxels: chunk.voxels().into_owned(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Disconnects the client given to the `kick` console command.
+    pub(super) fn kick_clients(
+        mut commands: EventReader<ConsoleCommand>,
+        mut server: ResMut<RenetServer>,
+    ) {
+        for command in commands.read() {
+            if command.name != "kick" {
+                continue;
+            }
+
+            let Some(client_id) = command.args.first().and_then(|id| id.parse().ok()) else {
+                warn!("Usage: kick <client id>");
+                continue;
+            };
+            let client_id = ClientId::from_raw(client_id);
+
+            if server.is_connected(client_id) {
+                server.disconnect(client_id);
+                info!("Kicked client {client_id}");
+            } else {
+                warn!("There is no client {client_id}");
+            }
+        }
+    }
+
+    /// Sends the `say` console command to every player, as a message of the server.
+    pub(super) fn say(
+        mut commands: EventReader<ConsoleCommand>,
+        mut chat_lines: EventWriter<ChatLine>,
+    ) {
+        for command in commands.read() {
+            if command.name != "say" {
+                continue;
+            }
+
+            match ChatLine::new(None, &command.args.join(" ")) {
+                Some(line) => chat_lines.send(line),
+                None => warn!("Usage: say <message>"),
+            }
+        }
+    }
+
+    /// Sends every new chat line to every client, and logs it, so the chat can be followed on a dedicated server.
+    pub(super) fn broadcast_chat_lines(
+        mut server: ResMut<RenetServer>,
+        mut chat_lines: EventReader<ChatLine>,
+    ) {
+        for line in chat_lines.read() {
+            match &line.sender {
+                Some(sender) => info!("<{sender}> {}", line.text),
+                None => info!("{}", line.text),
+            }
+
+            let message = ServerMessage::Chat(line.clone());
+            server.broadcast_message(message.channel(), encode(&message));
+        }
+    }
+
+    pub(super) fn send_weather(mut server: ResMut<RenetServer>, weather: Res<State<Weather>>) {
+        let message = ServerMessage::Weather(*weather.get());
+        server.broadcast_message(message.channel(), encode(&message));
+    }
+
+    /// Tells every client that sent inputs this frame where they ended up.
+    pub(super) fn acknowledge_player_inputs(
+        mut server: ResMut<RenetServer>,
+        mut player_query: Query<(&mut RemotePlayer, &Transform)>,
+    ) {
+        for (mut player, transform) in &mut player_query {
+            if player.acknowledged_input == player.last_input {
+                continue;
+            }
+            player.acknowledged_input = player.last_input;
+
+            send(
+                &mut server,
+                player.client_id,
+                &ServerMessage::PlayerAck {
+                    sequence: player.last_input,
+                    translation: transform.translation,
+                },
+            );
+        }
+    }
+
+    /// Tells every client where all the players are, including the host. The host gets the snapshot as well, to move
+    /// the other players the same way clients do.
+    pub(super) fn broadcast_player_snapshots(
+        time: Res<Time>,
+        mut server: ResMut<RenetServer>,
+        player_query: Query<(&RemotePlayer, &Transform)>,
+        host_query: Query<&Transform, (With<Camera3d>, With<RenderDistance>)>,
+        host_name: Option<Res<PlayerName>>,
+        local_snapshots: Option<ResMut<Events<PlayerSnapshotsReceived>>>,
+    ) {
+        let host_name = host_name.map_or_else(|| PlayerName::default().0, |name| name.0.clone());
+        let players: Vec<PlayerSnapshot> = host_query
+            .get_single()
+            .map(|transform| (HOST_PLAYER_ID, host_name, transform))
+            .into_iter()
+            .chain(player_query.iter().map(|(player, transform)| {
+                (player.client_id.raw(), player.name.clone(), transform)
+            }))
+            .map(|(id, name, transform)| PlayerSnapshot {
+                id,
+                name,
+                translation: transform.translation,
+                rotation: transform.rotation,
+            })
+            .collect();
+        let server_time = time.elapsed_seconds_f64();
+
+        if let Some(mut local_snapshots) = local_snapshots {
+            local_snapshots.send(PlayerSnapshotsReceived {
+                server_time,
+                players: players.clone(),
+            });
+        }
+
+        let message = ServerMessage::PlayerSnapshots {
+            server_time,
+            players,
+        };
+        server.broadcast_message(message.channel(), encode(&message));
+    }
+}
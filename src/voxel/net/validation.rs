@@ -0,0 +1,226 @@
+use bevy::prelude::*;
+
+use crate::{
+    console::RegisterConsoleCommand,
+    voxel::{
+        edit::{ProtectedRegion, ProtectedRegions, VoxelEdit},
+        interaction::INTERACTION_REACH,
+        load::RenderDistance,
+        micro::{is_micro_voxel, MicroVoxelEdit, MICRO_BLOCK_WIDTH},
+        raycast::raycast,
+        world::VoxelWorld,
+        Voxel,
+    },
+};
+
+use super::protocol::EditRejection;
+
+/// How much further than [INTERACTION_REACH] players connected to a server can edit, in voxels. The server only
+/// knows where the last input of a player put them, which lags behind where they see themselves.
+const REACH_TOLERANCE: f32 = 2.0;
+/// The most chunks past their render distance the server keeps loaded around a player, like the settings allow.
+const MAX_UNLOAD_MARGIN: u32 = 8;
+
+/// This plugin adds the console commands to manage the [ProtectedRegions], which [validate_edit] checks the edits of
+/// clients against.
+pub(super) struct EditValidationPlugin;
+
+impl Plugin for EditValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProtectedRegions>()
+            .register_console_command(
+                "protect",
+                "Protects the box between two corners from players",
+            )
+            .register_console_command(
+                "unprotect",
+                "Removes the protected region with the given number",
+            )
+            .register_console_command("regions", "Lists the protected regions")
+            .add_systems(Update, systems::manage_protected_regions);
+    }
+}
+
+/// Checks whether a player with their eye at `eye` may make the edit. Clients can send anything, so the server runs
+/// this on every [ClientMessage::Edit](super::protocol::ClientMessage::Edit) before applying it.
+///
+/// The player has to be able to reach the voxel, without anything solid in between, like with the raycast of the
+/// [VoxelInteractionPlugin](crate::voxel::interaction::VoxelInteractionPlugin). Breaking needs a solid voxel, and
+/// placing needs room for it.
+pub(super) fn validate_edit(
+    edit: &VoxelEdit,
+    eye: Vec3,
+    voxel_world: &VoxelWorld,
+    protected_regions: &ProtectedRegions,
+) -> Result<(), EditRejection> {
+    if protected_regions.contains(edit.voxel_pos) {
+        return Err(EditRejection::Protected);
+    }
+
+    let Some(current) = voxel_world.get_block(edit.voxel_pos) else {
+        return Err(EditRejection::Unloaded);
+    };
+
+    if current.is_indestructible() {
+        return Err(EditRejection::Indestructible);
+    }
+
+    let target = edit.voxel_pos.as_vec3();
+    let distance = eye.distance(target);
+    if distance > INTERACTION_REACH + REACH_TOLERANCE {
+        return Err(EditRejection::OutOfReach);
+    }
+
+    let breaking = edit.voxel == Voxel::AIR;
+    if breaking != current.is_solid() {
+        return Err(EditRejection::Outdated);
+    }
+
+    let obstruction = raycast(eye, target - eye, distance, |voxel_pos| {
+        voxel_pos != edit.voxel_pos
+            && voxel_world
+                .get_block(voxel_pos)
+                .is_some_and(|voxel| voxel.is_solid())
+    });
+    if obstruction.is_some() {
+        return Err(EditRejection::Obstructed);
+    }
+
+    Ok(())
+}
+
+/// Checks whether a player with their eye at `eye` may make the micro edit, like [validate_edit] does for whole
+/// voxels. Only voxels that can be subdivided, see [is_micro_voxel], can be edited, and only with micro voxels.
+///
+/// Nothing in between the player and the micro voxel is checked, since micro voxels can be reached through the gaps
+/// between the micro voxels in front of them.
+pub(super) fn validate_micro_edit(
+    edit: &MicroVoxelEdit,
+    eye: Vec3,
+    voxel_world: &VoxelWorld,
+    protected_regions: &ProtectedRegions,
+) -> Result<(), EditRejection> {
+    if protected_regions.contains(edit.voxel_pos) {
+        return Err(EditRejection::Protected);
+    }
+
+    let Some(current) = voxel_world.get_block(edit.voxel_pos) else {
+        return Err(EditRejection::Unloaded);
+    };
+
+    if current.is_indestructible() {
+        return Err(EditRejection::Indestructible);
+    }
+
+    let micro_width = MICRO_BLOCK_WIDTH as f32;
+    let target = edit.voxel_pos.as_vec3() + (edit.micro_pos.as_vec3() + 0.5) / micro_width - 0.5;
+    if eye.distance(target) > INTERACTION_REACH + REACH_TOLERANCE {
+        return Err(EditRejection::OutOfReach);
+    }
+
+    if !is_micro_voxel(edit.voxel) || (current != Voxel::MICRO_BLOCK && !is_micro_voxel(current)) {
+        return Err(EditRejection::Outdated);
+    }
+
+    Ok(())
+}
+
+/// The [RenderDistance] the server loads chunks around a player with, for the render distance and unload margin of
+/// their [ClientMessage::PlayerInput](super::protocol::ClientMessage::PlayerInput). Both are capped, since every chunk
+/// in range is generated and streamed to the player.
+pub(super) fn validate_render_distance(
+    render_distance: u32,
+    unload_margin: u32,
+    max_render_distance: u32,
+) -> RenderDistance {
+    RenderDistance::new(
+        render_distance.min(max_render_distance),
+        unload_margin.min(MAX_UNLOAD_MARGIN),
+    )
+}
+
+mod systems {
+    use crate::console::ConsoleCommand;
+
+    use super::*;
+
+    /// Runs the `protect`, `unprotect` and `regions` console commands.
+    pub(super) fn manage_protected_regions(
+        mut commands: EventReader<ConsoleCommand>,
+        mut protected_regions: ResMut<ProtectedRegions>,
+    ) {
+        for command in commands.read() {
+            match command.name.as_str() {
+                "protect" => {
+                    let coords: Vec<i32> = command
+                        .args
+                        .iter()
+                        .filter_map(|arg| arg.parse().ok())
+                        .collect();
+                    let [x1, y1, z1, x2, y2, z2] = coords[..] else {
+                        warn!("Usage: protect <x1> <y1> <z1> <x2> <y2> <z2>");
+                        continue;
+                    };
+
+                    let region =
+                        ProtectedRegion::new(IVec3::new(x1, y1, z1), IVec3::new(x2, y2, z2));
+                    protected_regions.0.push(region);
+                    info!(
+                        "Protected region {} from {} to {}",
+                        protected_regions.0.len(),
+                        region.min,
+                        region.max
+                    );
+                }
+                "unprotect" => {
+                    let index = command
+                        .args
+                        .first()
+                        .and_then(|number| number.parse::<usize>().ok())
+                        .and_then(|number| number.checked_sub(1))
+                        .filter(|index| *index < protected_regions.0.len());
+                    let Some(index) = index else {
+                        warn!("Usage: unprotect <number>, see regions for the numbers");
+                        continue;
+                    };
+
+                    let region = protected_regions.0.remove(index);
+                    info!(
+                        "Removed the protected region from {} to {}",
+                        region.min, region.max
+                    );
+                }
+                "regions" => {
+                    if protected_regions.0.is_empty() {
+                        info!("There are no protected regions");
+                    }
+                    for (i, region) in protected_regions.0.iter().enumerate() {
+                        info!("{}: from {} to {}", i + 1, region.min, region.max);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_distance_is_capped() {
+        let render_distance = validate_render_distance(u32::MAX, u32::MAX, 16);
+
+        assert_eq!(render_distance.val, 16);
+        assert_eq!(render_distance.unload_margin, MAX_UNLOAD_MARGIN);
+    }
+
+    #[test]
+    fn render_distance_within_the_cap_is_kept() {
+        let render_distance = validate_render_distance(6, 2, 16);
+
+        assert_eq!(render_distance.val, 6);
+        assert_eq!(render_distance.unload_margin, 2);
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+use super::game_mode::GameMode;
+
+/// This plugin keeps the flying camera out of solid voxels, unless [Noclip] is turned on. Walking players are kept out
+/// of them by their [TerrainCollider](super::physics::TerrainCollider) instead.
+pub(super) struct VoxelNoclipPlugin;
+
+impl Plugin for VoxelNoclipPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Noclip>()
+            .add_systems(Update, systems::toggle_noclip)
+            .add_systems(
+                PostUpdate,
+                // After the camera moved this frame, but before it's drawn there.
+                systems::keep_camera_out_of_voxels.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Whether the player flies through the terrain, like in [GameMode::Creative] but without being stopped by solid
+/// voxels, whatever the game mode. Toggled with [InputAction::ToggleNoclip](crate::input::InputAction::ToggleNoclip),
+/// and not saved with the world.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Noclip(pub(crate) bool);
+
+impl Noclip {
+    /// Whether the player flies in the game mode, instead of walking.
+    pub(crate) fn flies(&self, game_mode: &GameMode) -> bool {
+        self.0 || game_mode.is_creative()
+    }
+}
+
+mod systems {
+    use crate::{
+        input::{ActionInput, InputAction},
+        voxel::{
+            generation::{VoxelChunk, VoxelChunkMap, VoxelChunkPosition, VoxelChunkWidth},
+            physics::TerrainCollider,
+        },
+    };
+
+    use super::*;
+
+    pub(super) fn toggle_noclip(input: ActionInput, mut noclip: ResMut<Noclip>) {
+        if input.just_pressed(InputAction::ToggleNoclip) {
+            noclip.0 = !noclip.0;
+            info!("Noclip {}", if noclip.0 { "on" } else { "off" });
+        }
+    }
+
+    /// Moves the camera back to where it last was outside of solid voxels when it moves into one, unless [Noclip] is
+    /// on. The last free position is tracked with noclip on as well, so turning it off inside the terrain moves the
+    /// camera back to where it went in.
+    pub(super) fn keep_camera_out_of_voxels(
+        noclip: Res<Noclip>,
+        mut camera_query: Query<&mut Transform, (With<Camera3d>, Without<TerrainCollider>)>,
+        chunk_query: Query<&VoxelChunk>,
+        voxel_chunk_map: Res<VoxelChunkMap>,
+        chunk_width: Res<VoxelChunkWidth>,
+        mut free_position: Local<Option<Vec3>>,
+    ) {
+        let Ok(mut transform) = camera_query.get_single_mut() else {
+            return;
+        };
+
+        let (chunk_pos, local_pos) = VoxelChunkPosition::world_to_local(
+            transform.translation.round().as_ivec3(),
+            &chunk_width,
+        );
+        let in_voxel = voxel_chunk_map
+            .0
+            .get(&chunk_pos)
+            .and_then(|entity| chunk_query.get(*entity).ok())
+            .and_then(|chunk| chunk.get_voxel(local_pos, &chunk_width))
+            .is_some_and(|voxel| voxel.is_solid());
+
+        if !in_voxel {
+            *free_position = Some(transform.translation);
+        } else if let (false, Some(free_position)) = (noclip.0, *free_position) {
+            transform.translation = free_position;
+        }
+    }
+}
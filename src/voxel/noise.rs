@@ -1,38 +1,436 @@
-use bevy::{app::Plugin, ecs::system::Resource};
-use noise::{Fbm, NoiseFn, Simplex};
+use bevy::prelude::*;
+use noise::{Fbm, MultiFractal, NoiseFn, Simplex};
 use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use super::Voxel;
+use crate::console::RegisterConsoleCommand;
 
+use super::{
+    biome::{BiomeBlend, BiomeMap, DEFAULT_BLEND_RADIUS},
+    noise_layer::{NoiseLayer, NoiseNode},
+    preset::TerrainPreset,
+    river::RiverMap,
+    surface::{SurfaceContext, SurfaceRules, MAX_SURFACE_DEPTH},
+    Voxel, VoxelConfig,
+};
+
+/// The highest y of the bedrock floor of new worlds. Everything at and below it is bedrock.
+pub(super) const DEFAULT_BEDROCK_LEVEL: i32 = -64;
+/// The height new worlds are filled with water up to. Worlds without a sea still fill their rivers up to it.
+pub(super) const DEFAULT_SEA_LEVEL: i32 = 0;
+/// The highest a column is scanned from by [TerrainNoise::surface_height].
+const SURFACE_SCAN_TOP: i32 = 256;
+const SURFACE_SCAN_STEP: i32 = 8;
+/// Where the [SurfaceRules] are loaded from, relative to the working directory.
+const SURFACE_RULES_PATH: &str = "surface.ron";
+
+/// This plugin adds the [TerrainNoise]. Its [NoiseLayer]s are loaded from the file of the [TerrainPreset], and its
+/// [SurfaceRules] from [SURFACE_RULES_PATH]. Both apply to every chunk generated from then on.
 pub(super) struct VoxelTerrainNoisePlugin;
 
 impl Plugin for VoxelTerrainNoisePlugin {
-    fn build(&self, app: &mut bevy::prelude::App) {
-        app.init_resource::<TerrainNoise>();
+    fn build(&self, app: &mut App) {
+        let seed = app
+            .world
+            .get_resource::<VoxelConfig>()
+            .and_then(|config| config.seed);
+        if let Some(seed) = seed {
+            app.insert_resource(TerrainNoise::new_world(seed));
+        }
+
+        app.init_resource::<TerrainNoise>()
+            .init_resource::<TerrainPreset>()
+            .register_console_command("seed", "Shows the seed of the world")
+            .add_systems(Update, systems::show_seed);
+    }
+
+    /// Other plugins can replace the [TerrainNoise] and [TerrainPreset], like to continue a saved world, so the preset
+    /// is only applied once every plugin is built.
+    fn finish(&self, app: &mut App) {
+        let preset = *app.world.resource::<TerrainPreset>();
+        if let Some(terrain_noise) = app.world.remove_resource::<TerrainNoise>() {
+            app.insert_resource(terrain_noise.with_preset(preset));
+        }
     }
 }
 
 #[derive(Resource)]
-pub(super) struct TerrainNoise(Fbm<Simplex>);
+pub(super) struct TerrainNoise {
+    noise: NoiseNode,
+    seed: u32,
+    domain_warp: Option<(DomainWarp, [Fbm<Simplex>; 3])>,
+    biomes: BiomeMap,
+    rivers: Option<RiverMap>,
+    bedrock_level: Option<i32>,
+    sea_level: Option<i32>,
+    surface_rules: SurfaceRules,
+    /// The noise of the [SurfaceCondition::Noise](super::surface::SurfaceCondition::Noise) conditions.
+    surface_noise: Simplex,
+}
+
+/// What's the same for every voxel in a column of the terrain. See [TerrainNoise::column].
+#[derive(Debug, Clone, Copy)]
+pub(super) struct TerrainColumn {
+    biome: BiomeBlend,
+    /// The floor of the river valley the column is in, if any. See [RiverMap::floor].
+    river_floor: Option<f64>,
+}
+
+/// Warps the coordinates the terrain is sampled at with another noise, one per axis. This bends the terrain into
+/// cliffs and overhangs, where the plain noise only makes round blobs.
+///
+/// It's saved with the world, so it can be tuned in its `level.ron`.
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(super) struct DomainWarp {
+    /// How far the coordinates are moved at most, in voxels.
+    pub(super) strength: f64,
+    /// How quickly the warp changes over the world. Lower is smoother.
+    pub(super) frequency: f64,
+}
+
+impl Default for DomainWarp {
+    fn default() -> Self {
+        Self {
+            strength: 24.0,
+            frequency: 0.01,
+        }
+    }
+}
+
+/// The parameters of the [TerrainNoise], to tweak in their inspector. Changes only apply once the world is
+/// regenerated, see [VoxelRegenerationPlugin](super::regenerate::VoxelRegenerationPlugin).
+#[derive(Resource, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Resource)]
+pub(super) struct TerrainParameters {
+    pub(super) seed: u32,
+    pub(super) domain_warp: Option<DomainWarp>,
+    /// How far the biomes are blended at their borders, in voxels.
+    pub(super) biome_blend_radius: u32,
+    pub(super) bedrock_level: Option<i32>,
+    /// Presets without a sea ignore this.
+    pub(super) sea_level: Option<i32>,
+}
+
+impl FromWorld for TerrainParameters {
+    fn from_world(world: &mut World) -> Self {
+        Self::of(world.resource::<TerrainNoise>())
+    }
+}
+
+impl TerrainParameters {
+    /// The parameters the noise was made with.
+    pub(super) fn of(terrain_noise: &TerrainNoise) -> Self {
+        Self {
+            seed: terrain_noise.seed(),
+            domain_warp: terrain_noise.domain_warp(),
+            biome_blend_radius: terrain_noise.biome_blend_radius(),
+            bedrock_level: terrain_noise.bedrock_level(),
+            sea_level: terrain_noise.sea_level(),
+        }
+    }
+}
 
 impl TerrainNoise {
+    pub(super) fn new(seed: u32) -> Self {
+        Self {
+            noise: NoiseNode::new(&NoiseLayer::default(), seed),
+            seed,
+            domain_warp: None,
+            biomes: BiomeMap::new(seed, DEFAULT_BLEND_RADIUS),
+            rivers: Some(RiverMap::new(seed)),
+            bedrock_level: None,
+            sea_level: None,
+            surface_rules: SurfaceRules::default(),
+            surface_noise: Simplex::new(seed ^ 0x6a09_e667),
+        }
+    }
+
+    /// Shapes the terrain with the given layers, instead of the default ones.
+    pub(super) fn with_layers(mut self, layers: &NoiseLayer) -> Self {
+        self.noise = NoiseNode::new(layers, self.seed);
+        self
+    }
+
+    /// The noise with the given parameters, with the preset applied, see [TerrainNoise::with_preset].
+    pub(super) fn from_parameters(parameters: &TerrainParameters, preset: TerrainPreset) -> Self {
+        Self::new(parameters.seed)
+            .with_domain_warp(parameters.domain_warp)
+            .with_biome_blend_radius(parameters.biome_blend_radius)
+            .with_bedrock_level(parameters.bedrock_level)
+            .with_sea_level(parameters.sea_level)
+            .with_preset(preset)
+    }
+
+    /// Shapes the terrain with the noise layers of the preset, and picks its surface with the [SurfaceRules]. Both are
+    /// loaded from their files, so changes to them apply whenever this is called.
+    pub(super) fn with_preset(self, preset: TerrainPreset) -> Self {
+        let layers = NoiseLayer::load(preset.layers_path(), preset.default_layers());
+        let sea_level = self.sea_level.filter(|_| preset.has_sea());
+
+        self.with_layers(&layers)
+            .with_rivers(preset.has_rivers())
+            .with_sea_level(sea_level)
+            .with_surface_rules(SurfaceRules::load(SURFACE_RULES_PATH))
+    }
+
     pub(super) fn rand() -> Self {
-        let mut rng = rand::thread_rng();
+        Self::new_world(rand::thread_rng().gen())
+    }
+
+    /// The noise of a new world, with everything new worlds have, unlike worlds saved before it existed.
+    pub(super) fn new_world(seed: u32) -> Self {
+        Self::new(seed)
+            .with_domain_warp(Some(DomainWarp::default()))
+            .with_bedrock_level(Some(DEFAULT_BEDROCK_LEVEL))
+            .with_sea_level(Some(DEFAULT_SEA_LEVEL))
+    }
+
+    /// Turns the [DomainWarp] on, or off with [None].
+    pub(super) fn with_domain_warp(mut self, domain_warp: Option<DomainWarp>) -> Self {
+        self.domain_warp = domain_warp.map(|domain_warp| {
+            // Every axis gets its own noise, or the terrain would only be warped along the diagonal.
+            let axis_noise = |axis: u32| Fbm::new(self.seed.wrapping_add(axis + 1)).set_octaves(3);
+            (domain_warp, [axis_noise(0), axis_noise(1), axis_noise(2)])
+        });
+        self
+    }
+
+    /// Blends the biomes within `radius` voxels of every column, 0 for hard borders between them.
+    pub(super) fn with_biome_blend_radius(mut self, radius: u32) -> Self {
+        self.biomes = BiomeMap::new(self.seed, radius);
+        self
+    }
+
+    /// Carves rivers into the terrain, or leaves it whole.
+    pub(super) fn with_rivers(mut self, rivers: bool) -> Self {
+        self.rivers = rivers.then(|| RiverMap::new(self.seed));
+        self
+    }
+
+    /// Turns everything at and below `bedrock_level` into bedrock, or leaves the world bottomless with [None].
+    pub(super) fn with_bedrock_level(mut self, bedrock_level: Option<i32>) -> Self {
+        self.bedrock_level = bedrock_level;
+        self
+    }
+
+    /// Fills everything below `sea_level` that isn't solid with water, or only the rivers with [None].
+    pub(super) fn with_sea_level(mut self, sea_level: Option<i32>) -> Self {
+        self.sea_level = sea_level;
+        self
+    }
+
+    /// Picks the voxels at the surface with the given rules, instead of the default ones.
+    pub(super) fn with_surface_rules(mut self, surface_rules: SurfaceRules) -> Self {
+        self.surface_rules = surface_rules;
+        self
+    }
 
-        Self(Fbm::new(rng.gen::<u32>()))
+    /// The seed the terrain is generated from. The same seed always generates the same terrain.
+    pub(super) fn seed(&self) -> u32 {
+        self.seed
     }
 
-    pub(super) fn get_voxel(&self, x: i32, y: i32, z: i32) -> Voxel {
-        let scalar = 0.01;
-        let noise_value = self
-            .0
-            .get([x as f64 * scalar, y as f64 * scalar, z as f64 * scalar]);
+    pub(super) fn domain_warp(&self) -> Option<DomainWarp> {
+        self.domain_warp
+            .as_ref()
+            .map(|(domain_warp, _)| *domain_warp)
+    }
+
+    /// The highest y of the bedrock floor, if the world has one.
+    pub(super) fn bedrock_level(&self) -> Option<i32> {
+        self.bedrock_level
+    }
+
+    /// The height the world is filled with water up to, if it has a sea.
+    pub(super) fn sea_level(&self) -> Option<i32> {
+        self.sea_level
+    }
+
+    /// The height rivers are filled with water up to, which is the sea level if there is a sea.
+    fn water_level(&self) -> i32 {
+        self.sea_level.unwrap_or(DEFAULT_SEA_LEVEL)
+    }
+
+    pub(super) fn biome_blend_radius(&self) -> u32 {
+        self.biomes.blend_radius()
+    }
+
+    /// What's the same for every voxel in the column at the given world voxel position, to be passed to
+    /// [TerrainNoise::get_voxel] for the voxels in it.
+    pub(super) fn column(&self, x: i32, z: i32) -> TerrainColumn {
+        TerrainColumn {
+            biome: self.biomes.blend(x, z),
+            river_floor: self
+                .rivers
+                .as_ref()
+                .and_then(|rivers| rivers.floor(x, z))
+                .map(|floor| floor + self.water_level() as f64),
+        }
+    }
+
+    /// The height of the highest solid voxel of the column at the given world voxel position, and the voxel its top
+    /// would be, for drawing the terrain from afar. Columns under water return the water level and water instead.
+    ///
+    /// This scans down the column in steps of [SURFACE_SCAN_STEP] voxels, so thin overhangs can be missed, and the
+    /// surface rules aren't applied, so it's the surface voxel of the biome.
+    pub(super) fn surface_height(&self, x: i32, z: i32) -> (i32, Voxel) {
+        let column = self.column(x, z);
+        let bottom = self.bedrock_level.unwrap_or(DEFAULT_BEDROCK_LEVEL);
 
-        if noise_value < 0.0 {
-            Voxel::STONE
-        } else {
-            Voxel::AIR
+        let mut y = SURFACE_SCAN_TOP;
+        while y > bottom && !self.is_solid(x, y, z, &column) {
+            y -= SURFACE_SCAN_STEP;
         }
+        while self.is_solid(x, y + 1, z, &column) && y < SURFACE_SCAN_TOP {
+            y += 1;
+        }
+
+        if self.get_voxel(x, y + 1, z, &column) == Voxel::WATER {
+            return (self.water_level(), Voxel::WATER);
+        }
+
+        (y, column.biome.biome.settings().surface)
+    }
+
+    /// Whether the voxel at the given world voxel position was carved away by a river.
+    fn is_carved(&self, y: i32, column: &TerrainColumn) -> bool {
+        column.river_floor.is_some_and(|floor| y as f64 > floor)
+    }
+
+    /// Whether the terrain is solid at the given world voxel position.
+    fn is_solid(&self, x: i32, y: i32, z: i32, column: &TerrainColumn) -> bool {
+        !self.is_carved(y, column) && self.is_solid_uncarved(x, y, z, column)
+    }
+
+    /// Whether the terrain noise is solid at the given world voxel position, before rivers are carved.
+    fn is_solid_uncarved(&self, x: i32, y: i32, z: i32, column: &TerrainColumn) -> bool {
+        self.density(x, y, z, column) < 0.0
+    }
+
+    /// The terrain noise at the given world voxel position. It's solid below 0.
+    fn density(&self, x: i32, y: i32, z: i32, column: &TerrainColumn) -> f64 {
+        let mut point = [x as f64, y as f64, z as f64];
+
+        if let Some((domain_warp, axis_noise)) = &self.domain_warp {
+            let warp_point = point.map(|coordinate| coordinate * domain_warp.frequency);
+            for (coordinate, noise) in point.iter_mut().zip(axis_noise) {
+                *coordinate += noise.get(warp_point) * domain_warp.strength;
+            }
+        }
+
+        self.noise.get(point, &column.biome)
+    }
+
+    /// How steep the terrain is at the given world voxel position, in how many voxels it rises per voxel sideways.
+    /// Found from how quickly the terrain noise changes around it.
+    fn slope(&self, x: i32, y: i32, z: i32, column: &TerrainColumn) -> f64 {
+        let density = |dx: i32, dy: i32, dz: i32| self.density(x + dx, y + dy, z + dz, column);
+        let horizontal =
+            (density(1, 0, 0) - density(-1, 0, 0)).hypot(density(0, 0, 1) - density(0, 0, -1));
+        let vertical = (density(0, 1, 0) - density(0, -1, 0)).abs();
+
+        horizontal / vertical.max(f64::EPSILON)
+    }
+
+    /// The voxel of the base terrain at the given world voxel position, in the [TerrainNoise::column] `column`. The
+    /// terrain itself is all stone, until [TerrainNoise::apply_surface_rules] picks the voxels at its surface.
+    pub(super) fn get_voxel(&self, x: i32, y: i32, z: i32, column: &TerrainColumn) -> Voxel {
+        if self
+            .bedrock_level
+            .is_some_and(|bedrock_level| y <= bedrock_level)
+        {
+            return Voxel::BEDROCK;
+        }
+
+        if !self.is_solid(x, y, z, column) {
+            // The sea fills everything below it. Without a sea, rivers are still filled with water where they cut
+            // into the terrain.
+            let water = y <= self.water_level()
+                && (self.sea_level.is_some()
+                    || self.is_carved(y, column) && self.is_solid_uncarved(x, y, z, column));
+            return if water { Voxel::WATER } else { Voxel::AIR };
+        }
+
+        Voxel::STONE
+    }
+
+    /// Replaces the stone at the surface of a generated chunk with the voxels the [SurfaceRules] pick. `voxels` are
+    /// the voxels of the chunk from [TerrainNoise::get_voxel], `origin` is the world voxel position of its first voxel,
+    /// and `columns` are the [TerrainNoise::column]s of the chunk, indexed by `z * chunk_width + x`.
+    pub(super) fn apply_surface_rules(
+        &self,
+        voxels: &mut [Voxel],
+        origin: IVec3,
+        columns: &[TerrainColumn],
+        chunk_width: usize,
+    ) {
+        let cw = chunk_width;
+        let uses_slope = self.surface_rules.uses_slope();
+
+        // Voxels are ordered x, then y, then z, so every z is one slice of the chunk.
+        voxels
+            .par_chunks_mut(cw * cw)
+            .enumerate()
+            .for_each(|(local_z, slice)| {
+                for local_x in 0..cw {
+                    let column = &columns[local_z * cw + local_x];
+                    let settings = column.biome.biome.settings();
+                    let (x, z) = (origin.x + local_x as i32, origin.z + local_z as i32);
+
+                    // The surface of the voxels at the top of the chunk can start in the chunk above, so the walk
+                    // down the column starts above the chunk.
+                    let mut above = Voxel::AIR;
+                    let mut depth = None;
+                    let (mut underwater, mut slope) = (false, 0.0);
+                    for local_y in (0..cw + MAX_SURFACE_DEPTH as usize).rev() {
+                        let y = origin.y + local_y as i32;
+                        let voxel = if local_y < cw {
+                            slice[local_y * cw + local_x]
+                        } else {
+                            self.get_voxel(x, y, z, column)
+                        };
+
+                        if voxel != Voxel::STONE {
+                            above = voxel;
+                            depth = None;
+                            continue;
+                        }
+
+                        let voxel_depth = depth.map_or(0, |depth| depth + 1);
+                        depth = Some(voxel_depth);
+                        // Every voxel of a surface is as underwater and steep as its top voxel.
+                        if voxel_depth == 0 {
+                            underwater = above.id == Voxel::WATER.id;
+                            slope = if uses_slope {
+                                self.slope(x, y, z, column)
+                            } else {
+                                0.0
+                            };
+                        }
+
+                        if local_y >= cw || voxel_depth >= MAX_SURFACE_DEPTH {
+                            continue;
+                        }
+
+                        let context = SurfaceContext {
+                            x,
+                            y,
+                            z,
+                            depth: voxel_depth,
+                            filler_depth: column.biome.filler_depth.round() as i32,
+                            biome: column.biome.biome,
+                            sea_level: self.sea_level,
+                            underwater,
+                            slope,
+                            noise: &self.surface_noise,
+                        };
+                        if let Some(voxel) = self.surface_rules.pick(&context, &settings) {
+                            slice[local_y * cw + local_x] = voxel;
+                        }
+                    }
+                }
+            });
     }
 }
 
@@ -41,3 +439,20 @@ impl Default for TerrainNoise {
         Self::rand()
     }
 }
+
+mod systems {
+    use crate::console::ConsoleCommand;
+
+    use super::*;
+
+    pub(super) fn show_seed(
+        mut commands: EventReader<ConsoleCommand>,
+        terrain_noise: Res<TerrainNoise>,
+    ) {
+        for command in commands.read() {
+            if command.name == "seed" {
+                info!("Seed: {}", terrain_noise.seed());
+            }
+        }
+    }
+}
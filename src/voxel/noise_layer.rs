@@ -0,0 +1,328 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use noise::{Billow, Fbm, MultiFractal, NoiseFn, RidgedMulti, Simplex};
+use serde::{Deserialize, Serialize};
+
+use super::biome::BiomeBlend;
+
+/// A layer of the noise the terrain is shaped by. Layers are leaves, like [NoiseLayer::Fbm], or combine the layers
+/// inside them, so together they form a tree. Where the whole tree is below 0, the terrain is solid.
+///
+/// The tree is loaded from a RON file by the [VoxelTerrainNoisePlugin](super::noise::VoxelTerrainNoisePlugin), so the
+/// terrain can be reshaped without recompiling. For example:
+///
+/// ```ron
+/// Add([
+///     Fbm((octaves: 6, frequency: 0.01)),
+///     Scale(Ridged((octaves: 4, frequency: 0.004)), 0.5),
+/// ])
+/// ```
+///
+/// Plain 3D noise makes the same blobs everywhere. To get oceans, plains and mountains instead, the default layers
+/// sample a few large noises per [NoiseLayer::Column], map them to a surface height with [NoiseLayer::Spline]s, and
+/// make everything below that height solid, by adding the [NoiseLayer::Height] above it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) enum NoiseLayer {
+    /// Fractal simplex noise. Roughly between -1 and 1.
+    Fbm(Fractal),
+    /// Like [NoiseLayer::Fbm], but folded into sharp ridges, like mountain ranges.
+    Ridged(Fractal),
+    /// Like [NoiseLayer::Fbm], but folded into round bumps, like hills.
+    Billow(Fractal),
+    Constant(f64),
+    /// The height, in voxels.
+    Height,
+    /// How far the [Biome](super::biome::Biome)s around the column raise the surface, in voxels.
+    BiomeHeight,
+    /// How much of the mountains the [Biome](super::biome::Biome)s around the column keep, usually between 0 and 2.
+    BiomeAmplitude,
+    /// The layer, sampled at height 0, so it's the same for the whole column of voxels.
+    Column(Box<NoiseLayer>),
+    /// The layer, mapped through a smooth curve through the `(input, output)` points. Below the first and above the
+    /// last point, the output stays at theirs. Without points, the layer is passed through as it is.
+    Spline(Box<NoiseLayer>, Vec<(f64, f64)>),
+    /// The layer, multiplied by the factor.
+    Scale(Box<NoiseLayer>, f64),
+    /// The layer, plus the offset.
+    Offset(Box<NoiseLayer>, f64),
+    /// The sum of the layers.
+    Add(Vec<NoiseLayer>),
+    /// The product of the layers.
+    Multiply(Vec<NoiseLayer>),
+    /// The lowest of the layers, which is solid wherever any of them is.
+    Min(Vec<NoiseLayer>),
+    /// The highest of the layers, which is only solid where all of them are.
+    Max(Vec<NoiseLayer>),
+}
+
+/// The settings of a fractal [NoiseLayer], which adds `octaves` layers of simplex noise together, every one more
+/// detailed and fainter than the one before.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub(super) struct Fractal {
+    pub(super) octaves: usize,
+    /// How quickly the first octave changes over the world. Lower is larger features.
+    pub(super) frequency: f64,
+    /// How much faster every octave changes than the one before.
+    pub(super) lacunarity: f64,
+    /// How much fainter every octave is than the one before.
+    pub(super) persistence: f64,
+}
+
+impl Default for Fractal {
+    fn default() -> Self {
+        Self {
+            octaves: Fbm::<Simplex>::DEFAULT_OCTAVE_COUNT,
+            frequency: 0.01,
+            lacunarity: Fbm::<Simplex>::DEFAULT_LACUNARITY,
+            persistence: Fbm::<Simplex>::DEFAULT_PERSISTENCE,
+        }
+    }
+}
+
+impl Default for NoiseLayer {
+    fn default() -> Self {
+        Self::terrain(1.0)
+    }
+}
+
+impl NoiseLayer {
+    /// Terrain shaped by three large noises per column: continentalness, which decides between oceans and land,
+    /// peaks, which raises mountain ranges, and erosion, which flattens them again. The biomes raise or flatten it
+    /// further, and some fractal noise on top makes cliffs and overhangs.
+    ///
+    /// The mountains are `amplitude` times as high as usual.
+    pub(super) fn terrain(amplitude: f64) -> Self {
+        let column = |layer: NoiseLayer| Box::new(NoiseLayer::Column(Box::new(layer)));
+        let continentalness = NoiseLayer::Spline(
+            column(NoiseLayer::Fbm(Fractal {
+                octaves: 4,
+                frequency: 0.0015,
+                ..default()
+            })),
+            vec![
+                (-1.0, -48.0),
+                (-0.4, -24.0),
+                (-0.15, -4.0),
+                (0.0, 4.0),
+                (0.3, 12.0),
+                (1.0, 28.0),
+            ],
+        );
+        let peaks = NoiseLayer::Spline(
+            column(NoiseLayer::Ridged(Fractal {
+                octaves: 4,
+                frequency: 0.003,
+                ..default()
+            })),
+            [(-1.0, 0.0), (0.0, 4.0), (0.5, 32.0), (1.0, 72.0)]
+                .map(|(peaks, height)| (peaks, height * amplitude))
+                .to_vec(),
+        );
+        let erosion = NoiseLayer::Spline(
+            column(NoiseLayer::Fbm(Fractal {
+                octaves: 3,
+                frequency: 0.002,
+                ..default()
+            })),
+            vec![(-1.0, 1.0), (-0.2, 0.7), (0.2, 0.3), (1.0, 0.05)],
+        );
+        let surface_height = NoiseLayer::Add(vec![
+            continentalness,
+            NoiseLayer::Multiply(vec![peaks, erosion, NoiseLayer::BiomeAmplitude]),
+            NoiseLayer::BiomeHeight,
+        ]);
+
+        // Every 32 voxels above the surface height count as much as the whole detail noise.
+        Self::Add(vec![
+            NoiseLayer::Scale(Box::new(NoiseLayer::Height), 1.0 / 32.0),
+            NoiseLayer::Scale(Box::new(surface_height), -1.0 / 32.0),
+            NoiseLayer::Scale(
+                Box::new(NoiseLayer::Fbm(Fractal {
+                    octaves: 4,
+                    frequency: 0.02,
+                    ..default()
+                })),
+                0.4,
+            ),
+        ])
+    }
+
+    /// Loads the layers from `path`, writing `default` to it if it doesn't exist yet, so they can be edited. Falls back
+    /// to `default` if the file is invalid.
+    pub(super) fn load(path: impl AsRef<Path>, default: Self) -> Self {
+        let path = path.as_ref();
+        let Ok(contents) = fs::read_to_string(path) else {
+            default.save(path);
+            return default;
+        };
+
+        ron::from_str(&contents).unwrap_or_else(|err| {
+            warn!("Failed to parse {}: {err}", path.display());
+            default
+        })
+    }
+
+    fn save(&self, path: &Path) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                error!("Failed to serialize the terrain noise layers: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = fs::write(path, contents) {
+            error!("Failed to write {}: {err}", path.display());
+        }
+    }
+}
+
+/// A [NoiseLayer] tree with its noise functions created, ready to be sampled.
+pub(super) enum NoiseNode {
+    Fbm(Fbm<Simplex>),
+    Ridged(RidgedMulti<Simplex>),
+    Billow(Billow<Simplex>),
+    Constant(f64),
+    Height,
+    BiomeHeight,
+    BiomeAmplitude,
+    Column(Box<NoiseNode>),
+    Spline(Box<NoiseNode>, Vec<(f64, f64)>),
+    Scale(Box<NoiseNode>, f64),
+    Offset(Box<NoiseNode>, f64),
+    Add(Vec<NoiseNode>),
+    Multiply(Vec<NoiseNode>),
+    Min(Vec<NoiseNode>),
+    Max(Vec<NoiseNode>),
+}
+
+impl NoiseNode {
+    /// Creates the noise functions of the layers. Every leaf gets its own seed, derived from the seed of the world, so
+    /// two leaves with the same settings still differ.
+    pub(super) fn new(layer: &NoiseLayer, seed: u32) -> Self {
+        let mut leaves = 0;
+        Self::build(layer, seed, &mut leaves)
+    }
+
+    fn build(layer: &NoiseLayer, seed: u32, leaves: &mut u32) -> Self {
+        let children = |layers: &[NoiseLayer], leaves: &mut u32| {
+            layers
+                .iter()
+                .map(|layer| Self::build(layer, seed, leaves))
+                .collect()
+        };
+
+        match layer {
+            NoiseLayer::Fbm(fractal) => Self::Fbm(fractal.apply(Fbm::new(leaf_seed(seed, leaves)))),
+            NoiseLayer::Ridged(fractal) => {
+                Self::Ridged(fractal.apply(RidgedMulti::new(leaf_seed(seed, leaves))))
+            }
+            NoiseLayer::Billow(fractal) => {
+                Self::Billow(fractal.apply(Billow::new(leaf_seed(seed, leaves))))
+            }
+            NoiseLayer::Constant(value) => Self::Constant(*value),
+            NoiseLayer::Height => Self::Height,
+            NoiseLayer::BiomeHeight => Self::BiomeHeight,
+            NoiseLayer::BiomeAmplitude => Self::BiomeAmplitude,
+            NoiseLayer::Column(layer) => Self::Column(Box::new(Self::build(layer, seed, leaves))),
+            NoiseLayer::Spline(layer, points) => {
+                let mut points = points.clone();
+                points.retain(|(input, output)| input.is_finite() && output.is_finite());
+                points.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+                points.dedup_by(|(a, _), (b, _)| a == b);
+
+                Self::Spline(Box::new(Self::build(layer, seed, leaves)), points)
+            }
+            NoiseLayer::Scale(layer, factor) => {
+                Self::Scale(Box::new(Self::build(layer, seed, leaves)), *factor)
+            }
+            NoiseLayer::Offset(layer, offset) => {
+                Self::Offset(Box::new(Self::build(layer, seed, leaves)), *offset)
+            }
+            NoiseLayer::Add(layers) => Self::Add(children(layers, leaves)),
+            NoiseLayer::Multiply(layers) => Self::Multiply(children(layers, leaves)),
+            NoiseLayer::Min(layers) => Self::Min(children(layers, leaves)),
+            NoiseLayer::Max(layers) => Self::Max(children(layers, leaves)),
+        }
+    }
+
+    /// Samples the layers at a world position, in the given column.
+    pub(super) fn get(&self, point: [f64; 3], column: &BiomeBlend) -> f64 {
+        match self {
+            NoiseNode::Fbm(noise) => noise.get(point),
+            NoiseNode::Ridged(noise) => noise.get(point),
+            NoiseNode::Billow(noise) => noise.get(point),
+            NoiseNode::Constant(value) => *value,
+            NoiseNode::Height => point[1],
+            NoiseNode::BiomeHeight => column.height,
+            NoiseNode::BiomeAmplitude => column.amplitude,
+            NoiseNode::Column(node) => node.get([point[0], 0.0, point[2]], column),
+            NoiseNode::Spline(node, points) => sample_spline(points, node.get(point, column)),
+            NoiseNode::Scale(node, factor) => node.get(point, column) * factor,
+            NoiseNode::Offset(node, offset) => node.get(point, column) + offset,
+            NoiseNode::Add(nodes) => nodes.iter().map(|node| node.get(point, column)).sum(),
+            NoiseNode::Multiply(nodes) => {
+                nodes.iter().map(|node| node.get(point, column)).product()
+            }
+            NoiseNode::Min(nodes) => nodes
+                .iter()
+                .map(|node| node.get(point, column))
+                .fold(f64::INFINITY, f64::min),
+            NoiseNode::Max(nodes) => nodes
+                .iter()
+                .map(|node| node.get(point, column))
+                .fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+/// The seed of the next leaf. The first leaf gets the seed of the world itself.
+fn leaf_seed(seed: u32, leaves: &mut u32) -> u32 {
+    let leaf_seed = seed.wrapping_add(leaves.wrapping_mul(0x9e37_79b9));
+    *leaves += 1;
+    leaf_seed
+}
+
+/// Samples a Catmull-Rom spline through the points, which are sorted by their input, at `input`.
+fn sample_spline(points: &[(f64, f64)], input: f64) -> f64 {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return input;
+    };
+    if input <= first.0 {
+        return first.1;
+    }
+    if input >= last.0 {
+        return last.1;
+    }
+
+    let i = points.partition_point(|(point_input, _)| *point_input <= input) - 1;
+    let (x0, y0) = points[i];
+    let (x1, y1) = points[i + 1];
+    // The slope at every point goes from its previous point to its next one, or to itself at the ends.
+    let slope = |i: usize| {
+        let (before_x, before_y) = points[i.saturating_sub(1)];
+        let (after_x, after_y) = points[(i + 1).min(points.len() - 1)];
+        (after_y - before_y) / (after_x - before_x)
+    };
+
+    let width = x1 - x0;
+    let t = (input - x0) / width;
+    let (t2, t3) = (t * t, t * t * t);
+
+    (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+        + (t3 - 2.0 * t2 + t) * width * slope(i)
+        + (-2.0 * t3 + 3.0 * t2) * y1
+        + (t3 - t2) * width * slope(i + 1)
+}
+
+impl Fractal {
+    fn apply<T: MultiFractal>(&self, noise: T) -> T {
+        noise
+            .set_octaves(self.octaves)
+            .set_frequency(self.frequency)
+            .set_lacunarity(self.lacunarity)
+            .set_persistence(self.persistence)
+    }
+}
@@ -0,0 +1,375 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+    utils::HashMap,
+};
+
+use super::world::VoxelWorld;
+
+/// How far around the start and the goal of a path the terrain is searched, in voxels, so paths can go around things
+/// in the way.
+const SEARCH_MARGIN: i32 = 8;
+/// The widest region a path is searched in, in voxels. Requests for paths further than this are answered with no path
+/// right away.
+const MAX_SEARCH_WIDTH: i32 = 96;
+/// The most cells a search visits before it gives up, so unreachable goals don't search the whole region.
+const MAX_VISITED_CELLS: usize = 8192;
+/// The four directions a walker can step in.
+const STEPS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+/// This plugin finds paths over the terrain for [PathRequest]s, answering each with a [PathResponse].
+///
+/// The voxels around the path are copied out of the loaded chunks when the request is read, and the path is searched
+/// for with A* on the async compute task pool, so long searches don't hold up the frame.
+pub(super) struct VoxelPathfindingPlugin;
+
+impl Plugin for VoxelPathfindingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PathRequest>()
+            .add_event::<PathResponse>()
+            .init_resource::<PathTasks>()
+            .add_systems(
+                Update,
+                (systems::start_path_searches, systems::finish_path_searches).chain(),
+            );
+    }
+}
+
+/// Event for asking for a path for an entity, between two world voxel positions a walker stands in.
+#[derive(Event, Debug, Clone, Copy)]
+pub(super) struct PathRequest {
+    pub(super) entity: Entity,
+    pub(super) start: IVec3,
+    pub(super) goal: IVec3,
+    pub(super) limits: WalkLimits,
+}
+
+/// Event sent with the path found for a [PathRequest], a few frames after it.
+#[derive(Event, Debug, Clone)]
+pub(super) struct PathResponse {
+    pub(super) entity: Entity,
+    /// The world voxel positions the walker stands in along the way, from the one after the start up to the goal.
+    /// [None] if there's no path, or it would lead through chunks that aren't loaded.
+    pub(super) path: Option<Vec<IVec3>>,
+}
+
+/// How a walker gets around.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct WalkLimits {
+    /// How many voxels tall the walker is.
+    pub(super) height: i32,
+    /// How many voxels the walker can step or jump up at once.
+    pub(super) step_up: i32,
+    /// How many voxels the walker can drop down at once.
+    pub(super) max_fall: i32,
+}
+
+/// The searches that haven't finished yet, with the entity each is for.
+#[derive(Resource, Default)]
+struct PathTasks(Vec<(Entity, Task<Option<Vec<IVec3>>>)>);
+
+/// A copy of which voxels of a region are solid, to search paths in off the main thread. Voxels outside of the region,
+/// or in chunks that weren't loaded, are unknown, and never walked through.
+struct WalkGrid {
+    min: IVec3,
+    size: IVec3,
+    cells: Vec<Option<bool>>,
+}
+
+impl WalkGrid {
+    /// Copies the voxels between two opposite corners out of the loaded chunks.
+    fn from_world(voxel_world: &VoxelWorld, min: IVec3, max: IVec3) -> Self {
+        let size = max - min + IVec3::ONE;
+        let mut grid = Self {
+            min,
+            size,
+            cells: vec![None; (size.x * size.y * size.z) as usize],
+        };
+
+        for (voxel_pos, voxel) in voxel_world.iter_region(min, max) {
+            if let Some(index) = grid.index(voxel_pos) {
+                grid.cells[index] = Some(voxel.is_solid());
+            }
+        }
+
+        grid
+    }
+
+    fn index(&self, voxel_pos: IVec3) -> Option<usize> {
+        let local = voxel_pos - self.min;
+        if local.cmplt(IVec3::ZERO).any() || local.cmpge(self.size).any() {
+            return None;
+        }
+
+        Some((local.x + local.z * self.size.x + local.y * self.size.x * self.size.z) as usize)
+    }
+
+    fn is_solid(&self, voxel_pos: IVec3) -> bool {
+        self.index(voxel_pos)
+            .and_then(|index| self.cells[index])
+            .unwrap_or(false)
+    }
+
+    fn is_empty(&self, voxel_pos: IVec3) -> bool {
+        self.index(voxel_pos)
+            .and_then(|index| self.cells[index])
+            .is_some_and(|solid| !solid)
+    }
+
+    /// Whether a walker can stand in a voxel: it's on solid ground, with room for the walker above.
+    fn is_walkable(&self, voxel_pos: IVec3, limits: &WalkLimits) -> bool {
+        self.is_solid(voxel_pos - IVec3::Y)
+            && (0..limits.height).all(|dy| self.is_empty(voxel_pos + IVec3::Y * dy))
+    }
+
+    /// The voxels a walker standing in `voxel_pos` can get to in one step: next to it on the same level, up to
+    /// [WalkLimits::step_up] voxels higher with room to jump, or down to [WalkLimits::max_fall] voxels lower.
+    fn neighbours(&self, voxel_pos: IVec3, limits: &WalkLimits) -> Vec<IVec3> {
+        let mut neighbours = Vec::new();
+
+        for step in STEPS {
+            let next = voxel_pos + step;
+
+            // Jumping up needs room above the walker's head.
+            let up = (1..=limits.step_up)
+                .take_while(|dy| self.is_empty(voxel_pos + IVec3::Y * (limits.height - 1 + dy)))
+                .map(|dy| next + IVec3::Y * dy)
+                .find(|candidate| self.is_walkable(*candidate, limits));
+            if let Some(up) = up {
+                neighbours.push(up);
+                continue;
+            }
+
+            // Walking off an edge needs room to walk into before falling.
+            if !(0..limits.height).all(|dy| self.is_empty(next + IVec3::Y * dy)) {
+                continue;
+            }
+            if let Some(down) = (0..=limits.max_fall)
+                .map(|dy| next - IVec3::Y * dy)
+                .take_while(|candidate| self.is_empty(*candidate))
+                .find(|candidate| self.is_walkable(*candidate, limits))
+            {
+                neighbours.push(down);
+            }
+        }
+
+        neighbours
+    }
+}
+
+/// Finds the shortest walk from `start` to `goal` with A*, in world voxel positions a walker stands in. The path leaves
+/// the start out and ends with the goal. Returns [None] if the goal can't be reached within the grid.
+fn find_path(
+    grid: &WalkGrid,
+    start: IVec3,
+    goal: IVec3,
+    limits: &WalkLimits,
+) -> Option<Vec<IVec3>> {
+    if !grid.is_walkable(goal, limits) {
+        return None;
+    }
+    if start == goal {
+        return Some(Vec::new());
+    }
+
+    let heuristic = |voxel_pos: IVec3| {
+        let offset = (goal - voxel_pos).abs();
+        offset.x + offset.y + offset.z
+    };
+
+    let mut open = BinaryHeap::from([Reverse((heuristic(start), start.to_array()))]);
+    let mut came_from: HashMap<IVec3, IVec3> = HashMap::new();
+    let mut costs = HashMap::from([(start, 0)]);
+
+    while let Some(Reverse((_, current))) = open.pop() {
+        let current = IVec3::from_array(current);
+        if current == goal {
+            let mut path = vec![goal];
+            while let Some(previous) = came_from
+                .get(&path[path.len() - 1])
+                .filter(|previous| **previous != start)
+            {
+                path.push(*previous);
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        if costs.len() > MAX_VISITED_CELLS {
+            return None;
+        }
+
+        let cost = costs[&current];
+        for next in grid.neighbours(current, limits) {
+            // Climbing and falling take a bit longer than walking.
+            let next_cost = cost + 1 + (next.y - current.y).abs();
+            if costs.get(&next).is_some_and(|known| *known <= next_cost) {
+                continue;
+            }
+
+            costs.insert(next, next_cost);
+            came_from.insert(next, current);
+            open.push(Reverse((next_cost + heuristic(next), next.to_array())));
+        }
+    }
+
+    None
+}
+
+mod systems {
+    use super::*;
+
+    pub(super) fn start_path_searches(
+        mut requests: EventReader<PathRequest>,
+        mut responses: EventWriter<PathResponse>,
+        mut tasks: ResMut<PathTasks>,
+        voxel_world: VoxelWorld,
+    ) {
+        let task_pool = AsyncComputeTaskPool::get();
+
+        for request in requests.read() {
+            let limits = request.limits;
+            let margin = IVec3::new(
+                SEARCH_MARGIN,
+                limits.max_fall.max(limits.step_up) + limits.height,
+                SEARCH_MARGIN,
+            );
+            let min = request.start.min(request.goal) - margin;
+            let max = request.start.max(request.goal) + margin;
+            if (max - min).cmpgt(IVec3::splat(MAX_SEARCH_WIDTH)).any() {
+                responses.send(PathResponse {
+                    entity: request.entity,
+                    path: None,
+                });
+                continue;
+            }
+
+            let grid = WalkGrid::from_world(&voxel_world, min, max);
+            let (start, goal) = (request.start, request.goal);
+            let task = task_pool.spawn(async move { find_path(&grid, start, goal, &limits) });
+            tasks.0.push((request.entity, task));
+        }
+    }
+
+    pub(super) fn finish_path_searches(
+        mut tasks: ResMut<PathTasks>,
+        mut responses: EventWriter<PathResponse>,
+    ) {
+        let (finished, searching): (Vec<_>, Vec<_>) = std::mem::take(&mut tasks.0)
+            .into_iter()
+            .partition(|(_, task)| task.is_finished());
+        tasks.0 = searching;
+
+        for (entity, task) in finished {
+            responses.send(PathResponse {
+                entity,
+                path: block_on(task),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: WalkLimits = WalkLimits {
+        height: 2,
+        step_up: 1,
+        max_fall: 3,
+    };
+
+    /// A grid between two opposite corners, with the voxels `is_solid` returns true for solid.
+    fn grid(min: IVec3, max: IVec3, is_solid: impl Fn(IVec3) -> bool) -> WalkGrid {
+        let size = max - min + IVec3::ONE;
+        let mut grid = WalkGrid {
+            min,
+            size,
+            cells: vec![None; (size.x * size.y * size.z) as usize],
+        };
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    let voxel_pos = IVec3::new(x, y, z);
+                    let index = grid.index(voxel_pos).unwrap();
+                    grid.cells[index] = Some(is_solid(voxel_pos));
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// A corridor along x, one voxel wide, so paths can't go around anything in it.
+    fn corridor(length: i32, height: i32, is_solid: impl Fn(IVec3) -> bool) -> WalkGrid {
+        grid(IVec3::ZERO, IVec3::new(length - 1, height - 1, 0), is_solid)
+    }
+
+    #[test]
+    fn walks_straight_over_flat_ground() {
+        let grid = grid(IVec3::new(-4, 0, -4), IVec3::new(4, 3, 4), |pos| pos.y == 0);
+
+        let path = find_path(&grid, IVec3::new(-2, 1, 0), IVec3::new(1, 1, 0), &LIMITS);
+
+        assert_eq!(
+            path,
+            Some(vec![
+                IVec3::new(-1, 1, 0),
+                IVec3::new(0, 1, 0),
+                IVec3::new(1, 1, 0)
+            ])
+        );
+    }
+
+    #[test]
+    fn steps_up_only_with_room_above() {
+        let step = IVec3::new(1, 1, 0);
+        let ceiling = IVec3::new(0, 3, 0);
+        let (start, goal) = (IVec3::new(0, 1, 0), IVec3::new(2, 1, 0));
+
+        let open = corridor(3, 5, |pos| pos.y == 0 || pos == step);
+        assert_eq!(
+            find_path(&open, start, goal, &LIMITS),
+            Some(vec![IVec3::new(1, 2, 0), goal])
+        );
+
+        let covered = corridor(3, 5, |pos| pos.y == 0 || pos == step || pos == ceiling);
+        assert_eq!(find_path(&covered, start, goal, &LIMITS), None);
+    }
+
+    #[test]
+    fn does_not_fall_further_than_max_fall() {
+        // The start is on a pillar four voxels above the ground next to it.
+        let grid = corridor(2, 8, |pos| pos.y == 0 || (pos.x == 0 && pos.y <= 4));
+        let (start, goal) = (IVec3::new(0, 5, 0), IVec3::new(1, 1, 0));
+
+        assert_eq!(find_path(&grid, start, goal, &LIMITS), None);
+
+        let limits = WalkLimits {
+            max_fall: 4,
+            ..LIMITS
+        };
+        assert_eq!(find_path(&grid, start, goal, &limits), Some(vec![goal]));
+    }
+
+    #[test]
+    fn finds_no_path_to_unreachable_goals() {
+        // A wall too high to jump over cuts the corridor in two.
+        let grid = corridor(3, 5, |pos| pos.y == 0 || (pos.x == 1 && pos.y <= 3));
+        let start = IVec3::new(0, 1, 0);
+
+        assert_eq!(find_path(&grid, start, IVec3::new(2, 1, 0), &LIMITS), None);
+        // Goals in the air can't be stood in at all.
+        assert_eq!(find_path(&grid, start, IVec3::new(0, 3, 0), &LIMITS), None);
+    }
+
+    #[test]
+    fn path_to_the_start_is_empty() {
+        let grid = corridor(3, 3, |pos| pos.y == 0);
+        let start = IVec3::new(1, 1, 0);
+
+        assert_eq!(find_path(&grid, start, start, &LIMITS), Some(Vec::new()));
+    }
+}
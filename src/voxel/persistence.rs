@@ -0,0 +1,468 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::console::RegisterConsoleCommand;
+
+use super::{
+    biome::DEFAULT_BLEND_RADIUS,
+    color::VoxelMode,
+    edit::ProtectedRegions,
+    game_mode::GameMode,
+    generation::{VoxelChunk, VoxelChunkMap, VoxelChunkPosition, VoxelChunkWidth},
+    mesher::TerrainMesher,
+    micro::{MicroBlock, MicroBlocks},
+    noise::{DomainWarp, TerrainNoise},
+    preset::TerrainPreset,
+    spawn::WorldSpawn,
+    Voxel,
+};
+
+/// Where the world is saved by default, relative to the working directory.
+const WORLD_DIR: &str = "world";
+/// The file in the [WorldDir] holding the [Level].
+const LEVEL_FILE: &str = "level.ron";
+/// The directory in the [WorldDir] holding a file for every changed chunk.
+const CHUNKS_DIR: &str = "chunks";
+/// The directory in the [WorldDir] holding a file with the [MicroBlock]s of every chunk that has any.
+const MICRO_DIR: &str = "micro";
+
+/// This plugin saves the world to disk, and loads it again. The world is its seed, and every chunk that changed since
+/// it was generated. Unchanged chunks are generated from the seed again, so they aren't saved.
+///
+/// Changed chunks are saved when they're unloaded, when the game exits, and with the `save` console command. Micro
+/// blocks are small and rarely edited, so the micro blocks of a chunk are saved right away whenever one of them changes.
+///
+/// The world is saved in the [WorldDir], which has to be inserted before this plugin is added to save it somewhere else.
+pub(super) struct VoxelPersistencePlugin;
+
+impl Plugin for VoxelPersistencePlugin {
+    fn build(&self, app: &mut App) {
+        let world_dir = app
+            .world
+            .get_resource::<WorldDir>()
+            .cloned()
+            .unwrap_or_default();
+        let world_save = WorldSave::new(&world_dir.0);
+
+        // Continue the saved world, instead of generating a new one.
+        if let Some(level) = world_save.load_level() {
+            info!(
+                "Loading the world from {} with seed {}",
+                world_dir.0.display(),
+                level.seed
+            );
+            app.insert_resource(
+                TerrainNoise::new(level.seed)
+                    .with_domain_warp(level.domain_warp)
+                    .with_biome_blend_radius(level.biome_blend_radius)
+                    .with_bedrock_level(level.bedrock_level)
+                    .with_sea_level(level.sea_level),
+            )
+            .insert_resource(level.protected_regions)
+            .insert_resource(level.spawn)
+            .insert_resource(level.game_mode)
+            .insert_resource(level.preset)
+            .insert_resource(level.mesher)
+            .insert_resource(level.voxel_mode);
+        }
+
+        app.insert_resource(world_save)
+            .init_resource::<ProtectedRegions>()
+            .init_resource::<WorldSpawn>()
+            .init_resource::<GameMode>()
+            .init_resource::<TerrainMesher>()
+            .init_resource::<VoxelMode>()
+            .init_resource::<MicroBlocks>()
+            .register_console_command("save", "Saves every changed chunk")
+            .add_systems(Startup, systems::save_level)
+            .add_systems(
+                Update,
+                (
+                    systems::track_changed_chunks,
+                    systems::save_on_command,
+                    systems::save_level.run_if(
+                        resource_changed::<ProtectedRegions>()
+                            .or_else(resource_changed::<WorldSpawn>())
+                            .or_else(resource_changed::<GameMode>())
+                            .or_else(resource_changed::<TerrainNoise>()),
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (systems::load_micro_blocks, systems::save_micro_blocks),
+            )
+            .add_systems(Last, systems::save_on_exit);
+    }
+}
+
+/// The directory the world is saved in, and loaded from.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct WorldDir(pub PathBuf);
+
+impl Default for WorldDir {
+    fn default() -> Self {
+        Self(PathBuf::from(WORLD_DIR))
+    }
+}
+
+/// What's saved about the world, besides its chunks.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Level {
+    seed: u32,
+    /// Worlds from before there were presets were all generated with the default one.
+    #[serde(default)]
+    preset: TerrainPreset,
+    /// Worlds from before there were meshers were all drawn as cubes.
+    #[serde(default)]
+    mesher: TerrainMesher,
+    /// Worlds from before there were colored voxels were all made of blocks.
+    #[serde(default)]
+    voxel_mode: VoxelMode,
+    /// Worlds from before there was a domain warp don't have one, so their terrain stays the same.
+    #[serde(default)]
+    domain_warp: Option<DomainWarp>,
+    /// How far the biomes are blended at their borders, in voxels.
+    #[serde(default = "default_biome_blend_radius")]
+    biome_blend_radius: u32,
+    /// Worlds from before there was bedrock don't have a floor, so their terrain stays the same.
+    #[serde(default)]
+    bedrock_level: Option<i32>,
+    /// Worlds from before there was a sea don't have one either.
+    #[serde(default)]
+    sea_level: Option<i32>,
+    #[serde(default)]
+    protected_regions: ProtectedRegions,
+    /// Worlds from before there was a spawn point get one where new worlds do.
+    #[serde(default)]
+    spawn: WorldSpawn,
+    /// Worlds from before there were game modes were all played in creative.
+    #[serde(default)]
+    game_mode: GameMode,
+}
+
+fn default_biome_blend_radius() -> u32 {
+    DEFAULT_BLEND_RADIUS
+}
+
+/// The directory the world is saved in, and the loaded chunks that changed since they were last saved.
+#[derive(Resource, Debug)]
+pub(super) struct WorldSave {
+    dir: PathBuf,
+    changed_chunks: HashSet<VoxelChunkPosition>,
+}
+
+impl WorldSave {
+    fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            changed_chunks: HashSet::new(),
+        }
+    }
+
+    fn chunk_path(&self, chunk_pos: VoxelChunkPosition) -> PathBuf {
+        let IVec3 { x, y, z } = chunk_pos.0;
+        self.dir.join(CHUNKS_DIR).join(format!("{x}_{y}_{z}.bin"))
+    }
+
+    fn micro_path(&self, chunk_pos: VoxelChunkPosition) -> PathBuf {
+        let IVec3 { x, y, z } = chunk_pos.0;
+        self.dir.join(MICRO_DIR).join(format!("{x}_{y}_{z}.bin"))
+    }
+
+    fn load_level(&self) -> Option<Level> {
+        let path = self.dir.join(LEVEL_FILE);
+        let contents = fs::read_to_string(&path).ok()?;
+
+        ron::from_str(&contents)
+            .map_err(|err| warn!("Failed to parse {}: {err}", path.display()))
+            .ok()
+    }
+
+    fn save_level(&self, level: Level) {
+        let path = self.dir.join(LEVEL_FILE);
+        let result = ron::ser::to_string_pretty(&level, ron::ser::PrettyConfig::default())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            .and_then(|contents| write_file(&path, contents.as_bytes()));
+
+        if let Err(err) = result {
+            error!("Failed to write {}: {err}", path.display());
+        }
+    }
+
+    /// Loads a saved chunk. Returns [None] if the chunk was never saved, or the file can't be read.
+    pub(super) fn load_chunk(
+        &self,
+        chunk_pos: VoxelChunkPosition,
+        chunk_width: &VoxelChunkWidth,
+    ) -> Option<VoxelChunk> {
+        let path = self.chunk_path(chunk_pos);
+        let bytes = fs::read(&path).ok()?;
+
+        let voxels: Vec<Voxel> = match bincode::deserialize(&bytes) {
+            Ok(voxels) => voxels,
+            Err(err) => {
+                warn!("Failed to read {}: {err}", path.display());
+                return None;
+            }
+        };
+
+        let voxel_count = chunk_width.0 as usize * chunk_width.0 as usize * chunk_width.0 as usize;
+        if voxels.len() != voxel_count {
+            warn!(
+                "{} has {} voxels, expected {voxel_count}",
+                path.display(),
+                voxels.len()
+            );
+            return None;
+        }
+
+        Some(VoxelChunk::from_voxels(voxels))
+    }
+
+    /// Saves the chunk if it changed since it was last saved.
+    pub(super) fn save_chunk_if_changed(
+        &mut self,
+        chunk_pos: VoxelChunkPosition,
+        chunk: &VoxelChunk,
+    ) {
+        if !self.changed_chunks.remove(&chunk_pos) {
+            return;
+        }
+
+        let path = self.chunk_path(chunk_pos);
+        let result = bincode::serialize(&*chunk.voxels())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            .and_then(|bytes| write_file(&path, &bytes));
+
+        if let Err(err) = result {
+            error!("Failed to write {}: {err}", path.display());
+        }
+    }
+
+    /// Loads the saved micro blocks of a chunk, by the world voxel position of their voxels. Micro blocks that can't be
+    /// read are left out.
+    fn load_micro_blocks(&self, chunk_pos: VoxelChunkPosition) -> Vec<(IVec3, MicroBlock)> {
+        let path = self.micro_path(chunk_pos);
+        let Ok(bytes) = fs::read(&path) else {
+            return Vec::new();
+        };
+
+        let micro_blocks: Vec<(IVec3, Vec<Voxel>)> = match bincode::deserialize(&bytes) {
+            Ok(micro_blocks) => micro_blocks,
+            Err(err) => {
+                warn!("Failed to read {}: {err}", path.display());
+                return Vec::new();
+            }
+        };
+
+        micro_blocks
+            .into_iter()
+            .filter_map(|(voxel_pos, voxels)| {
+                let micro_block = MicroBlock::from_voxels(voxels);
+                if micro_block.is_none() {
+                    warn!(
+                        "{} has an invalid micro block at {voxel_pos}",
+                        path.display()
+                    );
+                }
+                Some((voxel_pos, micro_block?))
+            })
+            .collect()
+    }
+
+    /// Saves the micro blocks of a chunk, replacing the ones saved before. The file is removed once there are none.
+    fn save_micro_blocks<'a>(
+        &self,
+        chunk_pos: VoxelChunkPosition,
+        micro_blocks: impl Iterator<Item = (IVec3, &'a MicroBlock)>,
+    ) {
+        let path = self.micro_path(chunk_pos);
+        let micro_blocks: Vec<(IVec3, &[Voxel])> = micro_blocks
+            .map(|(voxel_pos, micro_block)| (voxel_pos, micro_block.voxels()))
+            .collect();
+
+        let result = if micro_blocks.is_empty() {
+            match fs::remove_file(&path) {
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                result => result,
+            }
+        } else {
+            bincode::serialize(&micro_blocks)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+                .and_then(|bytes| write_file(&path, &bytes))
+        };
+
+        if let Err(err) = result {
+            error!("Failed to write {}: {err}", path.display());
+        }
+    }
+
+    /// Marks a chunk as changed, so it's saved the next time.
+    pub(super) fn mark_changed(&mut self, chunk_pos: VoxelChunkPosition) {
+        self.changed_chunks.insert(chunk_pos);
+    }
+
+    /// Deletes every saved chunk and its micro blocks, and forgets the chunks that changed since they were saved.
+    pub(super) fn delete_chunks(&mut self) {
+        self.changed_chunks.clear();
+
+        for dir in [CHUNKS_DIR, MICRO_DIR] {
+            let path = self.dir.join(dir);
+            match fs::remove_dir_all(&path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                    error!("Failed to delete {}: {err}", path.display());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Writes a file, creating its directory if it doesn't exist yet.
+fn write_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, contents)
+}
+
+mod systems {
+    use bevy::app::AppExit;
+
+    use crate::{
+        console::ConsoleCommand,
+        voxel::{load::ChunkLoaded, micro::AppliedMicroVoxelEdit, render::ChunkRenderQueue},
+    };
+
+    use super::*;
+
+    /// Saves the seed right away, so chunks saved later always go with the seed they were generated from. Runs again
+    /// whenever the [ProtectedRegions], the [WorldSpawn], the [GameMode] or the [TerrainNoise] change.
+    pub(super) fn save_level(
+        world_save: Res<WorldSave>,
+        terrain_noise: Res<TerrainNoise>,
+        preset: Res<TerrainPreset>,
+        mesher: Res<TerrainMesher>,
+        voxel_mode: Res<VoxelMode>,
+        protected_regions: Res<ProtectedRegions>,
+        spawn: Res<WorldSpawn>,
+        game_mode: Res<GameMode>,
+    ) {
+        world_save.save_level(Level {
+            seed: terrain_noise.seed(),
+            preset: *preset,
+            mesher: *mesher,
+            voxel_mode: *voxel_mode,
+            domain_warp: terrain_noise.domain_warp(),
+            biome_blend_radius: terrain_noise.biome_blend_radius(),
+            bedrock_level: terrain_noise.bedrock_level(),
+            sea_level: terrain_noise.sea_level(),
+            protected_regions: protected_regions.clone(),
+            spawn: spawn.clone(),
+            game_mode: *game_mode,
+        });
+    }
+
+    pub(super) fn track_changed_chunks(
+        mut world_save: ResMut<WorldSave>,
+        chunk_query: Query<(&VoxelChunkPosition, Ref<VoxelChunk>), Changed<VoxelChunk>>,
+    ) {
+        for (chunk_pos, chunk) in &chunk_query {
+            // Newly loaded chunks count as changed as well, but they're the same as what was generated or saved.
+            if !chunk.is_added() {
+                world_save.mark_changed(*chunk_pos);
+            }
+        }
+    }
+
+    pub(super) fn save_on_command(
+        mut commands: EventReader<ConsoleCommand>,
+        mut world_save: ResMut<WorldSave>,
+        chunk_query: Query<(&VoxelChunkPosition, &VoxelChunk)>,
+    ) {
+        if !commands.read().any(|command| command.name == "save") {
+            return;
+        }
+
+        save_changed_chunks(&mut world_save, &chunk_query);
+        info!("Saved the world");
+    }
+
+    pub(super) fn save_on_exit(
+        exit: EventReader<AppExit>,
+        mut world_save: ResMut<WorldSave>,
+        chunk_query: Query<(&VoxelChunkPosition, &VoxelChunk)>,
+    ) {
+        if !exit.is_empty() {
+            save_changed_chunks(&mut world_save, &chunk_query);
+        }
+    }
+
+    fn save_changed_chunks(
+        world_save: &mut WorldSave,
+        chunk_query: &Query<(&VoxelChunkPosition, &VoxelChunk)>,
+    ) {
+        for (chunk_pos, chunk) in chunk_query {
+            world_save.save_chunk_if_changed(*chunk_pos, chunk);
+        }
+    }
+
+    /// Loads the saved micro blocks of every loaded chunk, and remeshes the chunk with them if it's drawn here.
+    pub(super) fn load_micro_blocks(
+        mut loaded_chunks: EventReader<ChunkLoaded>,
+        world_save: Res<WorldSave>,
+        mut micro_blocks: ResMut<MicroBlocks>,
+        mut chunk_render_queue: Option<ResMut<ChunkRenderQueue>>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        for loaded in loaded_chunks.read() {
+            let saved = world_save.load_micro_blocks(VoxelChunkPosition(loaded.chunk_pos));
+            if saved.is_empty() {
+                continue;
+            }
+
+            for (voxel_pos, micro_block) in saved {
+                micro_blocks.set(voxel_pos, Some(micro_block), &chunk_width);
+            }
+            if let Some(chunk_render_queue) = chunk_render_queue.as_mut() {
+                chunk_render_queue.push_chunk(loaded.entity);
+            }
+        }
+    }
+
+    /// Saves the micro blocks of every chunk a micro block changed in. Micro blocks whose voxel was replaced since are
+    /// left out.
+    pub(super) fn save_micro_blocks(
+        mut applied_edits: EventReader<AppliedMicroVoxelEdit>,
+        world_save: Res<WorldSave>,
+        micro_blocks: Res<MicroBlocks>,
+        voxel_chunk_map: Res<VoxelChunkMap>,
+        chunk_query: Query<&VoxelChunk>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let changed_chunks: HashSet<VoxelChunkPosition> = applied_edits
+            .read()
+            .map(|edit| VoxelChunkPosition::world_to_local(edit.0.voxel_pos, &chunk_width).0)
+            .collect();
+
+        for chunk_pos in changed_chunks {
+            world_save.save_micro_blocks(
+                chunk_pos,
+                micro_blocks.chunk(chunk_pos).filter(|(voxel_pos, _)| {
+                    let (_, local_pos) =
+                        VoxelChunkPosition::world_to_local(*voxel_pos, &chunk_width);
+                    voxel_chunk_map.get_voxel(&chunk_pos, &local_pos, &chunk_width, &chunk_query)
+                        == Some(Voxel::MICRO_BLOCK)
+                }),
+            );
+        }
+    }
+}
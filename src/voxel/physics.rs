@@ -0,0 +1,147 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{noise::TerrainNoise, world::VoxelWorld};
+
+/// Downwards acceleration of entities with [Gravity], in voxels per second squared.
+pub(super) const GRAVITY: f32 = 20.0;
+/// How many voxels apart the heights of the [HeightmapProxy] are sampled.
+const PROXY_CELL_SIZE: i32 = 4;
+/// How many heights the [HeightmapProxy] keeps before it's cleared, so it doesn't grow without bound as entities roam.
+const MAX_PROXY_CELLS: usize = 16384;
+
+/// This plugin moves entities with a [Velocity], pulls entities with [Gravity] down, and stops entities with a
+/// [TerrainCollider] on the ground, sending a [TerrainImpact] when they land.
+pub(super) struct VoxelPhysicsPlugin;
+
+impl Plugin for VoxelPhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HeightmapProxy>()
+            .add_event::<TerrainImpact>()
+            .add_systems(
+                Update,
+                (
+                    systems::apply_gravity,
+                    systems::apply_velocity,
+                    systems::collide_with_terrain,
+                )
+                    .chain()
+                    .in_set(PhysicsSet),
+            );
+    }
+}
+
+/// The systems moving entities. Systems changing the [Velocity] of entities should run before this set, in [Update].
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct PhysicsSet;
+
+/// How fast an entity moves, in voxels per second.
+#[derive(Component, Default, Debug, Clone, Copy)]
+pub(super) struct Velocity(pub(super) Vec3);
+
+/// Marker component for entities that fall.
+#[derive(Component, Default)]
+pub(super) struct Gravity;
+
+/// Marker component for entities that come to rest on the ground instead of moving through it.
+///
+/// In loaded chunks they land on solid voxels. Elsewhere they land on the [HeightmapProxy], so they don't fall out of
+/// the world when they end up past the loaded chunks.
+#[derive(Component, Default)]
+pub(super) struct TerrainCollider {
+    /// How far above the bottom of the entity its translation is, like the eyes of a player above their feet.
+    pub(super) height: f32,
+}
+
+/// Event sent when an entity with a [TerrainCollider] falls onto the ground, with the velocity it hit the ground with.
+#[derive(Event, Debug, Clone, Copy)]
+pub(super) struct TerrainImpact {
+    pub(super) entity: Entity,
+    pub(super) velocity: Vec3,
+}
+
+/// A coarse heightmap of the terrain, sampled from the [TerrainNoise] for where chunks aren't loaded, see
+/// [TerrainNoise::surface_height]. Every cell is [PROXY_CELL_SIZE] voxels wide, and has the height of the ground at
+/// its center. Like the horizon, it doesn't know about edits and structures, and water counts as ground.
+///
+/// Clients that joined a server don't have the noise, so they have no proxy.
+#[derive(Resource, Default)]
+pub(super) struct HeightmapProxy {
+    heights: HashMap<IVec2, f32>,
+}
+
+impl HeightmapProxy {
+    /// The height of the ground below a position, in the world.
+    fn ground_height(&mut self, terrain_noise: &TerrainNoise, position: Vec3) -> f32 {
+        let cell = (position.xz() / PROXY_CELL_SIZE as f32).floor().as_ivec2();
+
+        if self.heights.len() >= MAX_PROXY_CELLS {
+            self.heights.clear();
+        }
+
+        *self.heights.entry(cell).or_insert_with(|| {
+            let center = cell * PROXY_CELL_SIZE + PROXY_CELL_SIZE / 2;
+            let (height, _) = terrain_noise.surface_height(center.x, center.y);
+            // The top of the highest voxel, which is centered on its position.
+            height as f32 + 0.5
+        })
+    }
+}
+
+mod systems {
+    use super::*;
+
+    pub(super) fn apply_gravity(time: Res<Time>, mut query: Query<&mut Velocity, With<Gravity>>) {
+        for mut velocity in &mut query {
+            velocity.0.y -= GRAVITY * time.delta_seconds();
+        }
+    }
+
+    pub(super) fn apply_velocity(time: Res<Time>, mut query: Query<(&mut Transform, &Velocity)>) {
+        for (mut transform, velocity) in &mut query {
+            transform.translation += velocity.0 * time.delta_seconds();
+        }
+    }
+
+    /// Puts entities with a [TerrainCollider] that ended up in the ground back on top of it, and stops them.
+    pub(super) fn collide_with_terrain(
+        voxel_world: VoxelWorld,
+        terrain_noise: Option<Res<TerrainNoise>>,
+        mut heightmap_proxy: ResMut<HeightmapProxy>,
+        mut impacts: EventWriter<TerrainImpact>,
+        mut collider_query: Query<(Entity, &mut Transform, &mut Velocity, &TerrainCollider)>,
+    ) {
+        // A new world has different terrain.
+        if terrain_noise
+            .as_ref()
+            .is_some_and(|terrain_noise| terrain_noise.is_changed())
+        {
+            heightmap_proxy.heights.clear();
+        }
+
+        for (entity, mut transform, mut velocity, collider) in &mut collider_query {
+            let bottom = transform.translation - Vec3::Y * collider.height;
+            // Voxels are centered on their position, like they're drawn.
+            let voxel_pos = bottom.round().as_ivec3();
+
+            let ground = match voxel_world.get_block(voxel_pos) {
+                Some(voxel) if voxel.is_solid() => voxel_pos.y as f32 + 0.5,
+                Some(_) => continue,
+                None => match &terrain_noise {
+                    Some(terrain_noise) => heightmap_proxy.ground_height(terrain_noise, bottom),
+                    None => continue,
+                },
+            };
+
+            if bottom.y < ground {
+                transform.translation.y = ground + collider.height;
+                if velocity.0.y < 0.0 {
+                    impacts.send(TerrainImpact {
+                        entity,
+                        velocity: velocity.0,
+                    });
+                }
+                velocity.0 = Vec3::ZERO;
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+
+use super::weather::Weather;
+
+/// Weather particles spawn in a square of this many voxels around the camera, horizontally.
+const PARTICLE_RADIUS: f32 = 16.0;
+/// Weather particles spawn this many voxels above the camera.
+const PARTICLE_HEIGHT: Range<f32> = 8.0..16.0;
+const RAIN_PARTICLES_PER_SECOND: f32 = 300.0;
+const RAIN_SPEED: f32 = 20.0;
+const RAIN_LIFETIME: f32 = 1.5;
+const RAIN_COLOR: Color = Color::rgba(0.6, 0.7, 0.9, 0.6);
+const SNOW_PARTICLES_PER_SECOND: f32 = 60.0;
+const SNOW_SPEED: f32 = 2.0;
+const SNOW_LIFETIME: f32 = 10.0;
+
+/// This plugin spawns rain and snow particles around the camera, depending on the [Weather].
+pub(super) struct VoxelPrecipitationPlugin;
+
+impl Plugin for VoxelPrecipitationPlugin {
+    fn build(&self, app: &mut App) {
+        // The weather is decided where the world is simulated. On a client of a server, it's received instead.
+        if !app.world.contains_resource::<State<Weather>>() {
+            app.add_state::<Weather>();
+        }
+
+        app.init_resource::<WeatherAssets>().add_systems(
+            Update,
+            (
+                systems::spawn_weather_particles,
+                systems::update_weather_particles,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Marker component for rain drops and snow flakes, holding how long they have left.
+#[derive(Component)]
+struct WeatherParticle(Timer);
+
+#[derive(Resource)]
+struct WeatherAssets {
+    rain_mesh: Handle<Mesh>,
+    rain_material: Handle<StandardMaterial>,
+    snow_mesh: Handle<Mesh>,
+    snow_material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for WeatherAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let rain_mesh = meshes.add(shape::Box::new(0.03, 0.5, 0.03).into());
+        let snow_mesh = meshes.add(shape::Cube::new(0.1).into());
+
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let rain_material = materials.add(StandardMaterial {
+            base_color: RAIN_COLOR,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        });
+        let snow_material = materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            unlit: true,
+            ..default()
+        });
+
+        Self {
+            rain_mesh,
+            rain_material,
+            snow_mesh,
+            snow_material,
+        }
+    }
+}
+
+mod systems {
+    use rand::Rng;
+
+    use crate::voxel::physics::Velocity;
+
+    use super::*;
+
+    pub(super) fn spawn_weather_particles(
+        mut commands: Commands,
+        time: Res<Time>,
+        weather: Res<State<Weather>>,
+        weather_assets: Res<WeatherAssets>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+    ) {
+        let (per_second, mesh, material, lifetime) = match weather.get() {
+            Weather::Clear => return,
+            Weather::Rain => (
+                RAIN_PARTICLES_PER_SECOND,
+                &weather_assets.rain_mesh,
+                &weather_assets.rain_material,
+                RAIN_LIFETIME,
+            ),
+            Weather::Snow => (
+                SNOW_PARTICLES_PER_SECOND,
+                &weather_assets.snow_mesh,
+                &weather_assets.snow_material,
+                SNOW_LIFETIME,
+            ),
+        };
+
+        let mut rng = rand::thread_rng();
+
+        // Spawn the fraction of a particle that's left over with the matching chance, so the rate stays the same
+        // at any frame rate.
+        let count = per_second * time.delta_seconds();
+        let count = count as u32 + rng.gen_bool(count.fract() as f64) as u32;
+
+        for camera_transform in &camera_query {
+            for _ in 0..count {
+                let translation = camera_transform.translation
+                    + Vec3::new(
+                        rng.gen_range(-PARTICLE_RADIUS..PARTICLE_RADIUS),
+                        rng.gen_range(PARTICLE_HEIGHT),
+                        rng.gen_range(-PARTICLE_RADIUS..PARTICLE_RADIUS),
+                    );
+                let velocity = match weather.get() {
+                    Weather::Snow => Vec3::new(
+                        rng.gen_range(-0.5..0.5),
+                        -SNOW_SPEED,
+                        rng.gen_range(-0.5..0.5),
+                    ),
+                    _ => Vec3::NEG_Y * RAIN_SPEED,
+                };
+
+                commands.spawn((
+                    PbrBundle {
+                        mesh: mesh.clone(),
+                        material: material.clone(),
+                        transform: Transform::from_translation(translation),
+                        ..default()
+                    },
+                    Velocity(velocity),
+                    WeatherParticle(Timer::from_seconds(lifetime, TimerMode::Once)),
+                ));
+            }
+        }
+    }
+
+    pub(super) fn update_weather_particles(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut particle_query: Query<(Entity, &mut WeatherParticle)>,
+    ) {
+        for (entity, mut particle) in &mut particle_query {
+            if particle.0.tick(time.delta()).finished() {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::noise_layer::{Fractal, NoiseLayer};
+
+/// How many times higher the mountains of the [TerrainPreset::Amplified] terrain are.
+const AMPLIFIED_AMPLITUDE: f64 = 4.0;
+/// The height everything below is solid in the [TerrainPreset::Flat] terrain, in voxels.
+const FLAT_HEIGHT: f64 = 8.0;
+
+/// The kind of terrain a new world is generated with. It's saved with the world, so a saved world keeps its preset,
+/// whatever preset the game is started with.
+///
+/// Every preset has its own noise layers file, see [TerrainPreset::layers_path].
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerrainPreset {
+    /// Oceans, plains and mountains, with rivers running through them.
+    #[default]
+    Default,
+    /// Islands floating in the sky, far above the bedrock floor.
+    FloatingIslands,
+    /// The default terrain, with its mountains stretched far into the sky, and chunks loaded much further up and down
+    /// to see them.
+    Amplified,
+    /// Solid ground at the same height everywhere, without any hills, rivers or sea, for building and testing.
+    Flat,
+}
+
+impl TerrainPreset {
+    const ALL: [TerrainPreset; 4] = [
+        TerrainPreset::Default,
+        TerrainPreset::FloatingIslands,
+        TerrainPreset::Amplified,
+        TerrainPreset::Flat,
+    ];
+
+    /// The preset with the given name, see [TerrainPreset::name]. The error lists the names there are.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|preset| preset.name()).collect();
+                format!(
+                    "Unknown preset {name:?}, expected one of {}",
+                    names.join(", ")
+                )
+            })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TerrainPreset::Default => "default",
+            TerrainPreset::FloatingIslands => "floating_islands",
+            TerrainPreset::Amplified => "amplified",
+            TerrainPreset::Flat => "flat",
+        }
+    }
+
+    /// Where the noise layers of the preset are loaded from, relative to the working directory.
+    pub(super) fn layers_path(&self) -> &'static str {
+        match self {
+            TerrainPreset::Default => "terrain.ron",
+            TerrainPreset::FloatingIslands => "terrain_floating_islands.ron",
+            TerrainPreset::Amplified => "terrain_amplified.ron",
+            TerrainPreset::Flat => "terrain_flat.ron",
+        }
+    }
+
+    /// The noise layers of the preset, until they're changed in its [TerrainPreset::layers_path].
+    pub(super) fn default_layers(&self) -> NoiseLayer {
+        match self {
+            TerrainPreset::Default => NoiseLayer::default(),
+            TerrainPreset::FloatingIslands => floating_islands(),
+            TerrainPreset::Amplified => NoiseLayer::terrain(AMPLIFIED_AMPLITUDE),
+            TerrainPreset::Flat => NoiseLayer::Offset(Box::new(NoiseLayer::Height), -FLAT_HEIGHT),
+        }
+    }
+
+    /// Rivers are carved down to the water level, which would cut right through anything floating, and into flat
+    /// ground.
+    pub(super) fn has_rivers(&self) -> bool {
+        match self {
+            TerrainPreset::Default | TerrainPreset::Amplified => true,
+            TerrainPreset::FloatingIslands | TerrainPreset::Flat => false,
+        }
+    }
+
+    /// The sea would drown the lower islands, and fill the sky below them with water.
+    pub(super) fn has_sea(&self) -> bool {
+        match self {
+            TerrainPreset::Default | TerrainPreset::Amplified => true,
+            TerrainPreset::FloatingIslands | TerrainPreset::Flat => false,
+        }
+    }
+
+    /// How many times further chunks are loaded above and below a [RenderDistance](super::load::RenderDistance) than
+    /// around it.
+    pub(super) fn vertical_range(&self) -> f32 {
+        match self {
+            TerrainPreset::Default | TerrainPreset::FloatingIslands | TerrainPreset::Flat => 1.0,
+            TerrainPreset::Amplified => 2.0,
+        }
+    }
+}
+
+/// Plain 3D noise, only solid in a band of heights. The noise has to be far below 0 to be solid, so only scattered
+/// islands are left, and nothing at all above or below the band.
+fn floating_islands() -> NoiseLayer {
+    NoiseLayer::Add(vec![
+        NoiseLayer::Fbm(Fractal {
+            octaves: 4,
+            frequency: 0.012,
+            ..default()
+        }),
+        // The band is thickest in the middle, and thins out towards its top and bottom.
+        NoiseLayer::Spline(
+            Box::new(NoiseLayer::Height),
+            vec![
+                (20.0, 2.0),
+                (50.0, 0.35),
+                (80.0, 0.25),
+                (110.0, 0.35),
+                (140.0, 2.0),
+            ],
+        ),
+        // Some columns have more islands than others.
+        NoiseLayer::Scale(
+            Box::new(NoiseLayer::Column(Box::new(NoiseLayer::Fbm(Fractal {
+                octaves: 2,
+                frequency: 0.004,
+                ..default()
+            })))),
+            0.2,
+        ),
+    ])
+}
@@ -0,0 +1,209 @@
+use bevy::prelude::*;
+
+pub use super::cube_mesh::CubeFace;
+
+/// The result of a successful [raycast].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxelRaycastHit {
+    /// The world voxel position of the voxel that was hit.
+    pub voxel_pos: IVec3,
+    /// The normal of the face that was hit. This is zero if the ray started inside a solid voxel.
+    pub normal: IVec3,
+    /// The face the ray entered the voxel through, or [None] if the ray started inside it.
+    pub face: Option<CubeFace>,
+    /// Where the ray entered the voxel, in world coordinates.
+    pub point: Vec3,
+    /// How far along the ray the voxel was entered.
+    pub distance: f32,
+    /// Every voxel the ray went through before it hit, in order, starting with the one it started in.
+    pub traversed: Vec<IVec3>,
+}
+
+impl VoxelRaycastHit {
+    /// The voxel in front of the face that was hit, where a voxel would be placed against it. [None] if the ray
+    /// started inside the voxel, since there's no face to place against.
+    pub fn adjacent_pos(&self) -> Option<IVec3> {
+        (self.normal != IVec3::ZERO).then(|| self.voxel_pos + self.normal)
+    }
+}
+
+/// Casts a ray through the voxel grid, returning the first voxel for which `is_solid` returns true.
+///
+/// Voxels are centered on their world voxel position, meaning voxel (0, 0, 0) spans from -0.5 to 0.5 on every axis.
+/// [VoxelWorld::raycast](super::world::VoxelWorld::raycast) casts against the solid voxels of the loaded world.
+pub fn raycast(
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    mut is_solid: impl FnMut(IVec3) -> bool,
+) -> Option<VoxelRaycastHit> {
+    let direction = direction.normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return None;
+    }
+
+    // Shift the origin, so the voxel cells line up with the integer grid.
+    let shifted_origin = origin + 0.5;
+    let mut voxel_pos = shifted_origin.floor().as_ivec3();
+    let mut normal = IVec3::ZERO;
+    let mut distance = 0.0;
+    let mut traversed = Vec::new();
+
+    let step = IVec3::new(
+        direction.x.signum() as i32,
+        direction.y.signum() as i32,
+        direction.z.signum() as i32,
+    );
+    // Distance along the ray it takes to cross a whole voxel on each axis.
+    let t_delta = direction.recip().abs();
+    // Distance along the ray to the next voxel boundary on each axis.
+    let mut t_max = Vec3::from_array(std::array::from_fn(|axis| {
+        if direction[axis] > 0.0 {
+            (voxel_pos[axis] as f32 + 1.0 - shifted_origin[axis]) * t_delta[axis]
+        } else if direction[axis] < 0.0 {
+            (shifted_origin[axis] - voxel_pos[axis] as f32) * t_delta[axis]
+        } else {
+            f32::INFINITY
+        }
+    }));
+
+    loop {
+        if is_solid(voxel_pos) {
+            return Some(VoxelRaycastHit {
+                voxel_pos,
+                normal,
+                face: (normal != IVec3::ZERO).then(|| CubeFace::from_ivec3(normal)),
+                point: origin + direction * distance,
+                distance,
+                traversed,
+            });
+        }
+
+        traversed.push(voxel_pos);
+
+        let axis = if t_max.x < t_max.y && t_max.x < t_max.z {
+            0
+        } else if t_max.y < t_max.z {
+            1
+        } else {
+            2
+        };
+
+        if t_max[axis] > max_distance {
+            return None;
+        }
+
+        distance = t_max[axis];
+        voxel_pos[axis] += step[axis];
+        normal = IVec3::ZERO;
+        normal[axis] = -step[axis];
+        t_max[axis] += t_delta[axis];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-4),
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn axis_aligned_ray_hits_the_face_towards_it() {
+        let hit = raycast(Vec3::ZERO, Vec3::X, 10.0, |pos| pos == IVec3::new(3, 0, 0)).unwrap();
+
+        assert_eq!(hit.voxel_pos, IVec3::new(3, 0, 0));
+        assert_eq!(hit.normal, IVec3::NEG_X);
+        assert_eq!(hit.face, Some(CubeFace::Left));
+        assert_near(hit.point, Vec3::new(2.5, 0.0, 0.0));
+        assert_eq!(hit.distance, 2.5);
+        assert_eq!(
+            hit.traversed,
+            vec![IVec3::ZERO, IVec3::new(1, 0, 0), IVec3::new(2, 0, 0)]
+        );
+        assert_eq!(hit.adjacent_pos(), Some(IVec3::new(2, 0, 0)));
+    }
+
+    #[test]
+    fn ray_down_hits_the_top_of_the_ground() {
+        let hit = raycast(Vec3::new(0.2, 5.0, -0.3), Vec3::NEG_Y, 10.0, |pos| {
+            pos.y <= 0
+        })
+        .unwrap();
+
+        assert_eq!(hit.voxel_pos, IVec3::ZERO);
+        assert_eq!(hit.face, Some(CubeFace::Top));
+        assert_eq!(hit.distance, 4.5);
+        assert_eq!(hit.adjacent_pos(), Some(IVec3::Y));
+    }
+
+    #[test]
+    fn diagonal_ray_steps_through_every_voxel_it_crosses() {
+        let target = IVec3::new(3, 1, 0);
+        let hit = raycast(Vec3::ZERO, Vec3::new(1.0, 0.5, 0.0), 10.0, |pos| {
+            pos == target
+        })
+        .unwrap();
+
+        assert_eq!(hit.voxel_pos, target);
+        assert_eq!(hit.face, Some(CubeFace::Left));
+        assert_near(hit.point, Vec3::new(2.5, 1.25, 0.0));
+        assert!((hit.distance - Vec2::new(2.5, 1.25).length()).abs() < 1e-4);
+        assert_eq!(
+            hit.traversed,
+            vec![
+                IVec3::ZERO,
+                IVec3::new(1, 0, 0),
+                IVec3::new(1, 1, 0),
+                IVec3::new(2, 1, 0)
+            ]
+        );
+        assert_eq!(hit.adjacent_pos(), Some(IVec3::new(2, 1, 0)));
+    }
+
+    #[test]
+    fn negative_coordinates_round_to_the_voxel_they_are_in() {
+        let origin = Vec3::new(-3.2, -7.0, -10.4);
+        let hit = raycast(origin, Vec3::NEG_Z, 10.0, |pos| pos.z <= -14).unwrap();
+
+        assert_eq!(hit.voxel_pos, IVec3::new(-3, -7, -14));
+        assert_eq!(hit.face, Some(CubeFace::Back));
+        assert_near(hit.point, Vec3::new(-3.2, -7.0, -13.5));
+        assert_eq!(hit.traversed[0], IVec3::new(-3, -7, -10));
+        assert_eq!(hit.traversed.len(), 4);
+        assert_eq!(hit.adjacent_pos(), Some(IVec3::new(-3, -7, -13)));
+    }
+
+    #[test]
+    fn voxels_past_max_distance_are_missed() {
+        let is_solid = |pos: IVec3| pos == IVec3::new(5, 0, 0);
+
+        assert_eq!(raycast(Vec3::ZERO, Vec3::X, 4.0, is_solid), None);
+        assert_eq!(
+            raycast(Vec3::ZERO, Vec3::X, 4.5, is_solid).map(|hit| hit.distance),
+            Some(4.5)
+        );
+        assert_eq!(raycast(Vec3::ZERO, Vec3::X, 10.0, |_| false), None);
+    }
+
+    #[test]
+    fn ray_starting_inside_a_voxel_hits_it_without_a_face() {
+        let hit = raycast(Vec3::new(0.4, 0.0, 0.0), Vec3::Y, 10.0, |_| true).unwrap();
+
+        assert_eq!(hit.voxel_pos, IVec3::ZERO);
+        assert_eq!(hit.normal, IVec3::ZERO);
+        assert_eq!(hit.face, None);
+        assert_eq!(hit.distance, 0.0);
+        assert!(hit.traversed.is_empty());
+        assert_eq!(hit.adjacent_pos(), None);
+    }
+
+    #[test]
+    fn ray_without_a_direction_hits_nothing() {
+        assert_eq!(raycast(Vec3::ZERO, Vec3::ZERO, 10.0, |_| true), None);
+    }
+}
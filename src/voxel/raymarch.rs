@@ -0,0 +1,193 @@
+use bevy::{
+    asset::load_internal_asset,
+    pbr::{MaterialPipeline, MaterialPipelineKey, NotShadowCaster},
+    prelude::*,
+    reflect::TypePath,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_resource::{
+            AsBindGroup, Extent3d, Face, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError, TextureDimension, TextureFormat,
+        },
+    },
+};
+
+use super::{
+    chunk_material::ChunkMaterial,
+    data::VoxelData,
+    generation::{ChunkMeshSection, LocalVoxelPosition, VoxelChunk, VoxelChunkWidth},
+    render::ChunkMaterials,
+    world::box_positions,
+    VoxelConfig,
+};
+
+const RAYMARCH_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x766f_7865_6c5f_7261_796d_6172_6368_0000);
+
+/// This plugin adds the experimental raymarching renderer, turned on with [VoxelConfig::raymarch_chunks].
+///
+/// Every chunk gets a 3D texture of the colors of its voxels, and a box around it drawn with the [RaymarchMaterial].
+/// The box walks the ray of every pixel through the voxels in the texture, so chunks are drawn without meshing them
+/// at all. The opaque mesh of the chunk isn't drawn meanwhile, but it's still built, so switching back is instant.
+///
+/// Only the opaque voxels are raymarched, the other sections are drawn as usual. Raymarched chunks don't cast shadows,
+/// and their voxels have no texture or ambient occlusion.
+pub(super) struct VoxelRaymarchPlugin;
+
+impl Plugin for VoxelRaymarchPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            RAYMARCH_SHADER_HANDLE,
+            "shaders/raymarch.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_plugins(MaterialPlugin::<RaymarchMaterial> {
+            prepass_enabled: false,
+            ..default()
+        })
+        .add_systems(Update, systems::update_raymarched_chunks);
+    }
+}
+
+/// The material of the box around a raymarched chunk.
+#[derive(Asset, AsBindGroup, TypePath, Debug, Clone)]
+struct RaymarchMaterial {
+    /// The world position of the center of the first voxel of the chunk, with the width of the chunk in w.
+    #[uniform(0)]
+    origin: Vec4,
+    /// The color of every voxel of the chunk, with an alpha of 1.0 if it's filled.
+    #[texture(1, dimension = "3d")]
+    voxels: Handle<Image>,
+}
+
+impl Material for RaymarchMaterial {
+    fn fragment_shader() -> ShaderRef {
+        RAYMARCH_SHADER_HANDLE.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // The back faces are drawn, so the box is still drawn with the camera inside of it.
+        descriptor.primitive.cull_mode = Some(Face::Front);
+        Ok(())
+    }
+}
+
+/// A chunk drawn by raymarching, and the box it's drawn with, which is a child of it.
+#[derive(Component)]
+struct RaymarchedChunk {
+    volume: Entity,
+    voxels: Handle<Image>,
+}
+
+/// Builds the 3D texture of a chunk for the [RaymarchMaterial].
+fn voxel_image(chunk: &VoxelChunk, chunk_width: &VoxelChunkWidth) -> Image {
+    let width = chunk_width.0 as u32;
+    let mut data = Vec::with_capacity(width.pow(3) as usize * 4);
+
+    // Texels go along x first, then y, then z.
+    for position in box_positions(IVec3::ZERO, IVec3::splat(width as i32 - 1)) {
+        let texel = chunk
+            .get_voxel(LocalVoxelPosition::from_ivec3(position), chunk_width)
+            .filter(|voxel| voxel.mesh_section() == Some(ChunkMeshSection::Opaque))
+            .map_or([0; 4], |voxel| {
+                let [r, g, b, _] = voxel.face_color(IVec3::Y).as_rgba_u8();
+                [r, g, b, u8::MAX]
+            });
+        data.extend(texel);
+    }
+
+    Image::new(
+        Extent3d {
+            width,
+            height: width,
+            depth_or_array_layers: width,
+        },
+        TextureDimension::D3,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+mod systems {
+    use super::*;
+
+    /// Moves chunks onto raymarching while it's turned on, and back onto their meshes otherwise, and updates the
+    /// textures of raymarched chunks whose voxels changed.
+    pub(super) fn update_raymarched_chunks(
+        mut commands: Commands,
+        config: Res<VoxelConfig>,
+        chunk_width: Res<VoxelChunkWidth>,
+        chunk_materials: Res<ChunkMaterials>,
+        chunk_query: Query<(
+            Entity,
+            &Transform,
+            Ref<VoxelChunk>,
+            Option<&RaymarchedChunk>,
+        )>,
+        mut images: ResMut<Assets<Image>>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<RaymarchMaterial>>,
+        mut volume_mesh: Local<Option<Handle<Mesh>>>,
+    ) {
+        let width = chunk_width.0 as f32;
+
+        for (chunk_entity, transform, chunk, raymarched) in &chunk_query {
+            match (config.raymarch_chunks, raymarched) {
+                (true, None) => {
+                    let voxels = images.add(voxel_image(&chunk, &chunk_width));
+                    let material = materials.add(RaymarchMaterial {
+                        origin: transform.translation.extend(width),
+                        voxels: voxels.clone(),
+                    });
+                    let mesh = volume_mesh
+                        .get_or_insert_with(|| {
+                            meshes.add(shape::Box::new(width, width, width).into())
+                        })
+                        .clone();
+
+                    // The voxels of the chunk are centered on their local position, so the box is offset by half a
+                    // voxel.
+                    let volume = commands
+                        .spawn((
+                            MaterialMeshBundle {
+                                mesh,
+                                material,
+                                transform: Transform::from_translation(Vec3::splat(
+                                    width / 2.0 - 0.5,
+                                )),
+                                ..default()
+                            },
+                            NotShadowCaster,
+                        ))
+                        .id();
+
+                    commands
+                        .entity(chunk_entity)
+                        .add_child(volume)
+                        .insert(RaymarchedChunk { volume, voxels })
+                        .remove::<Handle<ChunkMaterial>>();
+                }
+                (true, Some(raymarched)) if chunk.is_changed() => {
+                    if let Some(image) = images.get_mut(&raymarched.voxels) {
+                        *image = voxel_image(&chunk, &chunk_width);
+                    }
+                }
+                (false, Some(raymarched)) => {
+                    commands.entity(raymarched.volume).despawn_recursive();
+                    commands
+                        .entity(chunk_entity)
+                        .remove::<RaymarchedChunk>()
+                        .insert(chunk_materials.get(ChunkMeshSection::Opaque));
+                }
+                _ => {}
+            }
+        }
+    }
+}
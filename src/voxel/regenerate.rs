@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+use crate::console::RegisterConsoleCommand;
+
+use super::{
+    generation::VoxelChunkMap,
+    load::{ChunkLoadQueue, ChunkUnloaded},
+    noise::{DomainWarp, TerrainNoise, TerrainParameters},
+    persistence::WorldSave,
+    preset::TerrainPreset,
+};
+
+/// This plugin regenerates the world with the `regenerate` console command, to try out new [TerrainParameters]
+/// without restarting the game. The noise layers and surface rules are loaded from their files again too.
+///
+/// Every chunk is despawned, and streamed in again from the new [TerrainNoise]. Saved chunks are deleted, since their
+/// edits were made to the old terrain. Players that joined a server keep the chunks they were already sent.
+pub(super) struct VoxelRegenerationPlugin;
+
+impl Plugin for VoxelRegenerationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TerrainParameters>()
+            .register_type::<DomainWarp>()
+            .register_type::<Option<DomainWarp>>()
+            .register_type::<Option<i32>>()
+            .register_console_command(
+                "regenerate",
+                "Regenerates the world with the terrain parameters",
+            )
+            .add_systems(Startup, systems::init_terrain_parameters)
+            // Chunks are despawned before anything in Update can stream them in again.
+            .add_systems(PreUpdate, systems::regenerate_on_command);
+    }
+}
+
+mod systems {
+    use crate::console::ConsoleCommand;
+
+    use super::*;
+
+    pub(super) fn init_terrain_parameters(
+        mut commands: Commands,
+        terrain_noise: Res<TerrainNoise>,
+    ) {
+        commands.insert_resource(TerrainParameters::of(&terrain_noise));
+    }
+
+    pub(super) fn regenerate_on_command(
+        mut commands: Commands,
+        mut console_commands: EventReader<ConsoleCommand>,
+        parameters: Res<TerrainParameters>,
+        preset: Res<TerrainPreset>,
+        mut terrain_noise: ResMut<TerrainNoise>,
+        mut chunk_load_queue: ResMut<ChunkLoadQueue>,
+        mut voxel_chunk_map: ResMut<VoxelChunkMap>,
+        mut world_save: Option<ResMut<WorldSave>>,
+        mut unloaded_chunks: EventWriter<ChunkUnloaded>,
+    ) {
+        if !console_commands
+            .read()
+            .any(|command| command.name == "regenerate")
+        {
+            return;
+        }
+
+        *terrain_noise = TerrainNoise::from_parameters(&parameters, *preset);
+
+        let chunk_count = voxel_chunk_map.0.len();
+        for (chunk_pos, chunk_entity) in voxel_chunk_map.0.drain() {
+            if let Some(entity_commands) = commands.get_entity(chunk_entity) {
+                entity_commands.despawn_recursive();
+            }
+            unloaded_chunks.send(ChunkUnloaded {
+                chunk_pos: chunk_pos.0,
+                entity: chunk_entity,
+            });
+        }
+        chunk_load_queue.clear();
+
+        if let Some(world_save) = world_save.as_mut() {
+            world_save.delete_chunks();
+        }
+
+        info!(
+            "Regenerated the world with seed {}, unloading {chunk_count} chunks",
+            parameters.seed
+        );
+    }
+}
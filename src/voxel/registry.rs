@@ -0,0 +1,357 @@
+use bevy::render::color::Color;
+
+use super::{audio::BlockSoundSet, generation::ChunkMeshSection, Voxel};
+
+/// Everything there is to know about a kind of block. Definitions are looked up by voxel id in [BLOCK_REGISTRY].
+pub(super) struct BlockDefinition {
+    /// The name of the block, as shown to players and used to refer to it in data like recipes.
+    pub(super) name: &'static str,
+    /// The color of the block, used for its mesh and in flat views like the minimap.
+    pub(super) color: Color,
+    /// Solid blocks hide the faces of the voxels around them, and can be targeted.
+    pub(super) solid: bool,
+    /// Which part of the chunk mesh the block is drawn in. `None` means the block isn't drawn at all.
+    pub(super) mesh_section: Option<ChunkMeshSection>,
+    /// How much light the block emits, from 0 to 15.
+    pub(super) light_emission: u8,
+    /// How long the block takes to mine, in seconds. Blocks with a hardness of 0.0 break right away.
+    pub(super) hardness: f32,
+    pub(super) fluid: Option<FluidDefinition>,
+    /// The sounds the block makes when it's broken, placed or walked on. `None` means it's silent.
+    pub(super) sounds: Option<BlockSoundSet>,
+    /// The name of the sound the block plays on a loop while the listener is close, as the `block.ambient.<name>` sound
+    /// event.
+    /// `None` means it doesn't play one.
+    pub(super) ambient_sound: Option<&'static str>,
+    pub(super) tags: &'static [BlockTag],
+}
+
+impl BlockDefinition {
+    pub(super) fn has_tag(&self, tag: BlockTag) -> bool {
+        self.tags.contains(&tag)
+    }
+}
+
+/// Tags are used to group blocks that behave the same in some way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum BlockTag {
+    /// The block can catch fire, and burn away.
+    Flammable,
+    /// The block falls and piles up in the [falling sand simulation](super::sand).
+    Powder,
+    /// The block falls and spreads out in the [falling sand simulation](super::sand).
+    Liquid,
+    /// The block can't be broken, neither by players nor by explosions.
+    Indestructible,
+    /// The block is mined faster with a [pickaxe](super::tool::ToolKind::Pickaxe).
+    Rock,
+    /// The block is mined faster with a [shovel](super::tool::ToolKind::Shovel).
+    Soil,
+    /// The block is mined faster with an [axe](super::tool::ToolKind::Axe).
+    Wood,
+}
+
+/// How a fluid block flows. See [fluid](super::fluid) for the simulation itself.
+pub(super) struct FluidDefinition {
+    /// How many game ticks it takes the fluid to flow one voxel.
+    pub(super) flow_delay: u64,
+    /// How much the fluid level drops for every voxel the fluid flows horizontally.
+    pub(super) level_drop: u8,
+}
+
+/// What happens when a fluid touches another block.
+pub(super) struct FluidInteraction {
+    /// The id of the fluid.
+    pub(super) fluid: u16,
+    /// The id of the block the fluid has to touch.
+    pub(super) touching: u16,
+    /// What a source of the fluid turns into.
+    pub(super) source_result: Voxel,
+    /// What flowing fluid turns into.
+    pub(super) flowing_result: Voxel,
+}
+
+/// Every block, indexed by voxel id.
+const BLOCK_REGISTRY: &[BlockDefinition] = &[
+    // Air
+    BlockDefinition {
+        name: "air",
+        color: Color::NONE,
+        solid: false,
+        mesh_section: None,
+        light_emission: 0,
+        hardness: 0.0,
+        fluid: None,
+        sounds: None,
+        ambient_sound: None,
+        tags: &[],
+    },
+    // Stone
+    BlockDefinition {
+        name: "stone",
+        color: Color::GRAY,
+        solid: true,
+        mesh_section: Some(ChunkMeshSection::Opaque),
+        light_emission: 0,
+        hardness: 1.5,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Stone),
+        ambient_sound: None,
+        tags: &[BlockTag::Rock],
+    },
+    // Water
+    BlockDefinition {
+        name: "water",
+        color: Color::rgba(0.1, 0.3, 0.9, 0.6),
+        solid: false,
+        mesh_section: Some(ChunkMeshSection::Transparent),
+        light_emission: 0,
+        hardness: 0.0,
+        fluid: Some(FluidDefinition {
+            flow_delay: 5,
+            level_drop: 1,
+        }),
+        sounds: Some(BlockSoundSet::Liquid),
+        ambient_sound: Some("water"),
+        tags: &[BlockTag::Liquid],
+    },
+    // Lava
+    BlockDefinition {
+        name: "lava",
+        color: Color::rgb(1.0, 0.35, 0.0),
+        solid: false,
+        mesh_section: Some(ChunkMeshSection::Emissive),
+        light_emission: 15,
+        hardness: 0.0,
+        fluid: Some(FluidDefinition {
+            flow_delay: 30,
+            level_drop: 2,
+        }),
+        sounds: Some(BlockSoundSet::Liquid),
+        ambient_sound: Some("lava"),
+        tags: &[],
+    },
+    // Obsidian
+    BlockDefinition {
+        name: "obsidian",
+        color: Color::rgb(0.15, 0.05, 0.2),
+        solid: true,
+        mesh_section: Some(ChunkMeshSection::Opaque),
+        light_emission: 0,
+        hardness: 10.0,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Stone),
+        ambient_sound: None,
+        tags: &[BlockTag::Rock],
+    },
+    // Fire
+    BlockDefinition {
+        name: "fire",
+        color: Color::rgb(1.0, 0.75, 0.1),
+        solid: false,
+        mesh_section: Some(ChunkMeshSection::Emissive),
+        light_emission: 15,
+        hardness: 0.0,
+        fluid: None,
+        sounds: None,
+        ambient_sound: Some("fire"),
+        tags: &[],
+    },
+    // Wood
+    BlockDefinition {
+        name: "wood",
+        color: Color::rgb(0.55, 0.35, 0.15),
+        solid: true,
+        mesh_section: Some(ChunkMeshSection::Opaque),
+        light_emission: 0,
+        hardness: 1.0,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Wood),
+        ambient_sound: None,
+        tags: &[BlockTag::Flammable, BlockTag::Wood],
+    },
+    // Dirt
+    BlockDefinition {
+        name: "dirt",
+        color: Color::rgb(0.45, 0.3, 0.15),
+        solid: true,
+        mesh_section: Some(ChunkMeshSection::Opaque),
+        light_emission: 0,
+        hardness: 0.5,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Dirt),
+        ambient_sound: None,
+        tags: &[BlockTag::Soil],
+    },
+    // Grass
+    BlockDefinition {
+        name: "grass",
+        color: Color::rgb(0.3, 0.6, 0.2),
+        solid: true,
+        mesh_section: Some(ChunkMeshSection::Opaque),
+        light_emission: 0,
+        hardness: 0.6,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Grass),
+        ambient_sound: None,
+        tags: &[BlockTag::Soil],
+    },
+    // TNT
+    BlockDefinition {
+        name: "tnt",
+        color: Color::rgb(0.8, 0.1, 0.1),
+        solid: true,
+        mesh_section: Some(ChunkMeshSection::Opaque),
+        light_emission: 0,
+        hardness: 0.0,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Grass),
+        ambient_sound: None,
+        tags: &[],
+    },
+    // Sand
+    BlockDefinition {
+        name: "sand",
+        color: Color::rgb(0.85, 0.8, 0.55),
+        solid: true,
+        mesh_section: Some(ChunkMeshSection::Opaque),
+        light_emission: 0,
+        hardness: 0.5,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Sand),
+        ambient_sound: None,
+        tags: &[BlockTag::Powder, BlockTag::Soil],
+    },
+    // Snow layer
+    BlockDefinition {
+        name: "snow_layer",
+        color: Color::rgb(0.95, 0.95, 1.0),
+        solid: false,
+        mesh_section: Some(ChunkMeshSection::Opaque),
+        light_emission: 0,
+        hardness: 0.1,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Snow),
+        ambient_sound: None,
+        tags: &[BlockTag::Soil],
+    },
+    // Bedrock
+    BlockDefinition {
+        name: "bedrock",
+        color: Color::rgb(0.12, 0.12, 0.12),
+        solid: true,
+        mesh_section: Some(ChunkMeshSection::Opaque),
+        light_emission: 0,
+        hardness: f32::INFINITY,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Stone),
+        ambient_sound: None,
+        tags: &[BlockTag::Indestructible],
+    },
+    // Snow
+    BlockDefinition {
+        name: "snow",
+        color: Color::rgb(0.95, 0.97, 1.0),
+        solid: true,
+        mesh_section: Some(ChunkMeshSection::Opaque),
+        light_emission: 0,
+        hardness: 0.2,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Snow),
+        ambient_sound: None,
+        tags: &[BlockTag::Soil],
+    },
+    // Gravel
+    BlockDefinition {
+        name: "gravel",
+        color: Color::rgb(0.5, 0.48, 0.45),
+        solid: true,
+        mesh_section: Some(ChunkMeshSection::Opaque),
+        light_emission: 0,
+        hardness: 0.6,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Gravel),
+        ambient_sound: None,
+        tags: &[BlockTag::Powder, BlockTag::Soil],
+    },
+    // Leaves
+    BlockDefinition {
+        name: "leaves",
+        color: Color::rgb(0.2, 0.45, 0.15),
+        solid: true,
+        mesh_section: Some(ChunkMeshSection::Cutout),
+        light_emission: 0,
+        hardness: 0.2,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Leaves),
+        ambient_sound: None,
+        tags: &[BlockTag::Flammable],
+    },
+    // Micro block. Its micro voxels are meshed on their own, so it isn't drawn itself.
+    BlockDefinition {
+        name: "micro_block",
+        color: Color::GRAY,
+        solid: true,
+        mesh_section: None,
+        light_emission: 0,
+        hardness: 0.5,
+        fluid: None,
+        sounds: Some(BlockSoundSet::Stone),
+        ambient_sound: None,
+        tags: &[],
+    },
+];
+
+/// Used for voxel ids that aren't in the [BLOCK_REGISTRY], so they stand out.
+const UNKNOWN_BLOCK: BlockDefinition = BlockDefinition {
+    name: "unknown",
+    color: Color::FUCHSIA,
+    solid: true,
+    mesh_section: Some(ChunkMeshSection::Opaque),
+    light_emission: 0,
+    hardness: 1.0,
+    fluid: None,
+    sounds: Some(BlockSoundSet::Stone),
+    ambient_sound: None,
+    tags: &[],
+};
+
+/// Used for [colored voxels](Voxel::from_color), whose color is in the voxel rather than its definition.
+const COLORED_BLOCK: BlockDefinition = BlockDefinition {
+    name: "colored",
+    color: Color::NONE,
+    solid: true,
+    mesh_section: Some(ChunkMeshSection::Opaque),
+    light_emission: 0,
+    hardness: 0.0,
+    fluid: None,
+    sounds: Some(BlockSoundSet::Stone),
+    ambient_sound: None,
+    tags: &[],
+};
+
+/// Every fluid interaction. When a fluid is ticked while touching a block of a matching interaction, it turns into
+/// the result.
+pub(super) const FLUID_INTERACTIONS: &[FluidInteraction] = &[FluidInteraction {
+    fluid: Voxel::LAVA.id,
+    touching: Voxel::WATER.id,
+    source_result: Voxel::OBSIDIAN,
+    flowing_result: Voxel::STONE,
+}];
+
+/// Looks up the definition of a voxel id.
+pub(super) fn block_definition(id: u16) -> &'static BlockDefinition {
+    if Voxel::new(id).is_colored() {
+        return &COLORED_BLOCK;
+    }
+
+    BLOCK_REGISTRY.get(id as usize).unwrap_or(&UNKNOWN_BLOCK)
+}
+
+/// Looks up the voxel id of a block by its [name](BlockDefinition::name).
+pub(super) fn block_id(name: &str) -> Option<u16> {
+    BLOCK_REGISTRY
+        .iter()
+        .position(|definition| definition.name == name)
+        .map(|id| id as u16)
+}
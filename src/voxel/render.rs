@@ -0,0 +1,505 @@
+use std::{collections::VecDeque, marker::PhantomData};
+
+use bevy::{prelude::*, render::primitives::Aabb};
+
+use super::{
+    chunk_material::{ChunkMaterial, ChunkMaterialPlugin},
+    data::VoxelData,
+    diagnostics::VoxelPipelineStats,
+    generation::{
+        ChunkMeshSection, LocalVoxelPosition, VoxelChunk, VoxelChunkMap, VoxelChunkPosition,
+        VoxelChunkWidth,
+    },
+    gpu_culling::GpuChunkCullingPlugin,
+    load::{ChunkMeshed, ChunkState},
+    mesher::TerrainMesher,
+    raymarch::VoxelRaymarchPlugin,
+    shading::ChunkShading,
+    AddResourceInspector, Voxel, VoxelChunkCoordinate, VoxelConfig,
+};
+
+/// This plugin is responsible for meshing chunks of `V`. It gives every new chunk its materials, mesh section children
+/// and light, and remeshes chunks pushed to the [ChunkRenderQueue]. Meshes and lights only go through [VoxelData], so
+/// this works for any voxel type; the [MicroBlock](super::micro::MicroBlock)s of the game's [Voxel]s are drawn by the
+/// [VoxelMicroBlockRenderingPlugin](super::micro::VoxelMicroBlockRenderingPlugin).
+///
+/// It doesn't care where chunks come from, so it works the same for generated chunks and chunks received from a server.
+pub(super) struct VoxelChunkRenderingPlugin<V: VoxelData = Voxel>(PhantomData<V>);
+
+impl<V: VoxelData> Default for VoxelChunkRenderingPlugin<V> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<V: VoxelData> Plugin for VoxelChunkRenderingPlugin<V> {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ChunkMaterialPlugin,
+            GpuChunkCullingPlugin,
+            VoxelRaymarchPlugin,
+        ))
+        .init_resource::<ChunkRenderQueue>()
+        .init_resource::<ChunkMaterials>()
+        .init_resource::<VoxelPipelineStats>()
+        .init_resource::<ChunkLodSettings>()
+        .init_resource::<TerrainMesher>()
+        .init_resource::<ChunkShading>()
+        .register_type::<ChunkRenderQueue>()
+        .register_type::<ChunkLodSettings>()
+        .register_type::<ChunkShading>()
+        .add_resource_inspector::<ChunkRenderQueue>()
+        .add_resource_inspector::<ChunkLodSettings>()
+        .add_resource_inspector::<ChunkShading>()
+        .add_systems(
+            Update,
+            (
+                systems::attach_chunk_render_components::<V>,
+                systems::update_chunk_lods,
+                systems::update_chunk_shading::<V>,
+                systems::mark_dirty_chunks,
+                systems::handle_chunk_rendering::<V>,
+            )
+                .chain()
+                .in_set(ChunkMeshingSet),
+        )
+        .add_systems(Update, systems::flicker_emissive_material);
+    }
+}
+
+/// The systems meshing the chunks of the [ChunkRenderQueue].
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct ChunkMeshingSet;
+
+/// How bright the light of a chunk is, per [light_emission](super::registry::BlockDefinition::light_emission) of the
+/// voxels in it.
+const CHUNK_LIGHT_INTENSITY_PER_EMISSION: f32 = 20.0;
+const MAX_CHUNK_LIGHT_INTENSITY: f32 = 4000.0;
+const CHUNK_LIGHT_COLOR: Color = Color::rgb(1.0, 0.6, 0.3);
+/// How many times a second the emissive voxels flicker to a new brightness. Every change uploads the material to the GPU
+/// again, so it isn't changed every frame.
+const FLICKER_RATE: f32 = 15.0;
+
+/// The materials used for the [ChunkMeshSection]s of every chunk. Voxel colors come from the vertex colors of the
+/// meshes, so these are all white.
+#[derive(Resource)]
+pub(super) struct ChunkMaterials {
+    opaque: Handle<ChunkMaterial>,
+    transparent: Handle<ChunkMaterial>,
+    emissive: Handle<ChunkMaterial>,
+    cutout: Handle<ChunkMaterial>,
+}
+
+impl ChunkMaterials {
+    pub(super) fn get(&self, section: ChunkMeshSection) -> Handle<ChunkMaterial> {
+        match section {
+            ChunkMeshSection::Opaque => self.opaque.clone(),
+            ChunkMeshSection::Transparent => self.transparent.clone(),
+            ChunkMeshSection::Emissive => self.emissive.clone(),
+            ChunkMeshSection::Cutout => self.cutout.clone(),
+        }
+    }
+}
+
+impl FromWorld for ChunkMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<ChunkMaterial>>();
+
+        Self {
+            opaque: materials.add(ChunkMaterial::default()),
+            transparent: materials.add(ChunkMaterial {
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            emissive: materials.add(ChunkMaterial {
+                unlit: true,
+                ..default()
+            }),
+            // Both sides of a leaf show through its holes.
+            cutout: materials.add(ChunkMaterial {
+                alpha_mode: AlphaMode::Mask(0.5),
+                cull_mode: None,
+                ..default()
+            }),
+        }
+    }
+}
+
+/// When distant chunks are meshed with less detail. Chunks further away than the first distance are meshed at half
+/// the resolution, and chunks further away than the second distance at a quarter.
+///
+/// To keep chunks on the edge of a distance from switching back and forth, a chunk only switches to a coarser level
+/// of detail `hysteresis` chunks past the distance, and back `hysteresis` chunks before it.
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub(super) struct ChunkLodSettings {
+    pub(super) enabled: bool,
+    /// The distances, in chunks from the camera, where each coarser level of detail starts.
+    pub(super) distances: [u32; 2],
+    pub(super) hysteresis: u32,
+    /// The level of detail from which on the meshes of chunks are simplified, by merging faces that look alike into
+    /// larger quads. Levels past the last one are never reached, so this turns simplifying off.
+    pub(super) simplify_from: u8,
+    /// How much the colors and ambient occlusion of faces may differ for them to be merged, see
+    /// [FaceQuads::merge](super::simplify::FaceQuads::merge). 0.0 only merges faces that look exactly the same.
+    pub(super) simplify_tolerance: f32,
+}
+
+impl Default for ChunkLodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            distances: [8, 16],
+            hysteresis: 1,
+            simplify_from: 2,
+            simplify_tolerance: 0.05,
+        }
+    }
+}
+
+impl ChunkLodSettings {
+    /// The tolerance to simplify the meshes of chunks at level of detail `lod` with, if they're simplified.
+    fn simplify_tolerance(&self, lod: u8) -> Option<f32> {
+        (lod > 0 && lod >= self.simplify_from).then_some(self.simplify_tolerance.max(0.0))
+    }
+
+    /// The level of detail a chunk `distance` chunks away should switch to, from its `current` level of detail.
+    fn lod(&self, current: u8, distance: f32) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+
+        let mut lod = current.min(self.distances.len() as u8);
+        while let Some(start) = self.distances.get(lod as usize) {
+            if distance <= (start + self.hysteresis) as f32 {
+                break;
+            }
+            lod += 1;
+        }
+        while lod > 0
+            && distance < self.distances[lod as usize - 1].saturating_sub(self.hysteresis) as f32
+        {
+            lod -= 1;
+        }
+
+        lod
+    }
+}
+
+/// The level of detail a chunk is meshed at. Level 0 is the full chunk, and every level halves the resolution, see
+/// [VoxelChunk::generate_lod_mesh].
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct ChunkLod(pub(super) u8);
+
+/// Marker component for the child entity of a chunk holding a [PointLight], which is lit when the chunk has light
+/// emitting voxels in it.
+///
+/// This is a rough approximation of light, as all the light of a chunk comes from the center of its emitting voxels.
+#[derive(Component)]
+pub(super) struct ChunkLight;
+
+/// This is the queue responsible for rendering chunks / creating the meshes.
+#[derive(Resource, Default, Reflect)]
+pub(super) struct ChunkRenderQueue {
+    /// Chunks to be rendered.
+    queue: VecDeque<Entity>,
+}
+
+impl ChunkRenderQueue {
+    /// Pushes a chunk to the queue, unless it's already waiting in it.
+    pub(super) fn push_chunk(&mut self, entity: Entity) {
+        if !self.queue.contains(&entity) {
+            self.queue.push_back(entity);
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Pushes the chunk containing a changed voxel to the queue.
+    ///
+    /// Voxels on the edge of a chunk affect the faces of the neighbouring chunks as well,
+    /// so those are pushed too.
+    pub(super) fn push_voxel_change(
+        &mut self,
+        chunk_pos: VoxelChunkPosition,
+        local_pos: LocalVoxelPosition,
+        voxel_chunk_map: &VoxelChunkMap,
+        chunk_width: &VoxelChunkWidth,
+    ) {
+        if let Some(chunk_entity) = voxel_chunk_map.0.get(&chunk_pos) {
+            self.push_chunk(*chunk_entity);
+        }
+
+        let local_pos = local_pos.as_ivec3();
+        for axis in 0..3 {
+            let mut offset = IVec3::ZERO;
+
+            if local_pos[axis] == 0 {
+                offset[axis] = -1;
+            } else if local_pos[axis] == chunk_width.0 as i32 - 1 {
+                offset[axis] = 1;
+            } else {
+                continue;
+            }
+
+            if let Some(neighbour_entity) = voxel_chunk_map
+                .0
+                .get(&VoxelChunkPosition(chunk_pos.0 + offset))
+            {
+                self.push_chunk(*neighbour_entity);
+            }
+        }
+    }
+}
+
+mod systems {
+    use bevy::utils::Instant;
+
+    use super::*;
+
+    /// Gives newly spawned chunks everything they need to be drawn, and pushes them to the [ChunkRenderQueue].
+    pub(super) fn attach_chunk_render_components<V: VoxelData>(
+        mut commands: Commands,
+        mut chunk_render_queue: ResMut<ChunkRenderQueue>,
+        chunk_query: Query<Entity, Added<VoxelChunk<V>>>,
+        chunk_materials: Res<ChunkMaterials>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        // Packed chunk meshes have no positions to compute bounds from, and the bounds of a remeshed chunk aren't
+        // recomputed, so every section is bounded by the whole chunk instead.
+        let aabb = Aabb::from_min_max(Vec3::splat(-0.5), Vec3::splat(chunk_width.0 as f32 - 0.5));
+
+        for chunk_entity in &chunk_query {
+            commands
+                .entity(chunk_entity)
+                .insert((
+                    chunk_materials.get(ChunkMeshSection::Opaque),
+                    aabb,
+                    ChunkLod::default(),
+                ))
+                .with_children(|parent| {
+                    for section in ChunkMeshSection::ALL {
+                        if section != ChunkMeshSection::Opaque {
+                            parent.spawn((
+                                MaterialMeshBundle::<ChunkMaterial> {
+                                    material: chunk_materials.get(section),
+                                    ..default()
+                                },
+                                section,
+                                aabb,
+                            ));
+                        }
+                    }
+
+                    parent.spawn((
+                        PointLightBundle {
+                            point_light: PointLight {
+                                color: CHUNK_LIGHT_COLOR,
+                                range: chunk_width.0 as f32 * 1.5,
+                                ..default()
+                            },
+                            visibility: Visibility::Hidden,
+                            ..default()
+                        },
+                        ChunkLight,
+                    ));
+                });
+
+            chunk_render_queue.push_chunk(chunk_entity);
+        }
+    }
+
+    /// Switches chunks to the level of detail for their distance to the camera, and pushes the chunks that switched to
+    /// the [ChunkRenderQueue] to be remeshed. Once the settings change, every chunk with less detail is remeshed, as it
+    /// could be simplified differently.
+    pub(super) fn update_chunk_lods(
+        lod_settings: Res<ChunkLodSettings>,
+        mut chunk_render_queue: ResMut<ChunkRenderQueue>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+        mut chunk_query: Query<(Entity, &VoxelChunkPosition, &mut ChunkLod)>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let Ok(camera_transform) = camera_query.get_single() else {
+            return;
+        };
+        let camera_chunk_pos =
+            VoxelChunkPosition::from_world_pos(camera_transform.translation, &chunk_width);
+        // A chunk can't be downsampled to less than a voxel.
+        let max_lod = chunk_width.0.trailing_zeros() as u8;
+
+        for (chunk_entity, chunk_pos, mut chunk_lod) in &mut chunk_query {
+            let distance = (chunk_pos.0 - camera_chunk_pos.0).as_vec3().length();
+            let lod = lod_settings.lod(chunk_lod.0, distance).min(max_lod);
+
+            if lod != chunk_lod.0 {
+                chunk_lod.0 = lod;
+                chunk_render_queue.push_chunk(chunk_entity);
+            } else if lod > 0 && lod_settings.is_changed() {
+                chunk_render_queue.push_chunk(chunk_entity);
+            }
+        }
+    }
+
+    /// Remeshes every chunk when the [ChunkShading] changes.
+    pub(super) fn update_chunk_shading<V: VoxelData>(
+        shading: Res<ChunkShading>,
+        mut chunk_render_queue: ResMut<ChunkRenderQueue>,
+        chunk_query: Query<Entity, With<VoxelChunk<V>>>,
+    ) {
+        if !shading.is_changed() {
+            return;
+        }
+
+        for chunk_entity in &chunk_query {
+            chunk_render_queue.push_chunk(chunk_entity);
+        }
+    }
+
+    /// Marks meshed chunks that have been pushed to the [ChunkRenderQueue] again as [ChunkState::Dirty].
+    pub(super) fn mark_dirty_chunks(
+        chunk_render_queue: Res<ChunkRenderQueue>,
+        mut chunk_state_query: Query<&mut ChunkState>,
+    ) {
+        for chunk_entity in chunk_render_queue.queue.iter() {
+            if let Ok(mut chunk_state) = chunk_state_query.get_mut(*chunk_entity) {
+                if *chunk_state == ChunkState::Meshed {
+                    *chunk_state = ChunkState::Dirty;
+                }
+            }
+        }
+    }
+
+    /// Meshes up to [VoxelConfig::chunk_meshes_per_frame] chunks from the [ChunkRenderQueue] every frame.
+    pub(super) fn handle_chunk_rendering<V: VoxelData>(
+        mut commands: Commands,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut chunk_render_queue: ResMut<ChunkRenderQueue>,
+        // How the chunks are meshed.
+        (config, mesher, shading, lod_settings): (
+            Res<VoxelConfig>,
+            Res<TerrainMesher>,
+            Res<ChunkShading>,
+            Res<ChunkLodSettings>,
+        ),
+        chunk_width: Res<VoxelChunkWidth>,
+        chunk_query: Query<(&VoxelChunk<V>, &VoxelChunkPosition, &ChunkLod, &Children)>,
+        section_query: Query<&ChunkMeshSection>,
+        light_query: Query<(), With<ChunkLight>>,
+        voxel_chunk_query: Query<&VoxelChunk<V>>,
+        voxel_chunk_map: Res<VoxelChunkMap>,
+        mut stats: ResMut<VoxelPipelineStats>,
+        mut meshed_chunks: EventWriter<ChunkMeshed>,
+    ) {
+        let _span = info_span!("chunk_render_queue").entered();
+
+        let mut meshed = 0;
+        while meshed < config.chunk_meshes_per_frame {
+            let Some(chunk_entity) = chunk_render_queue.queue.front() else {
+                break;
+            };
+            // Chunks can be unloaded while they wait in the queue.
+            if commands.get_entity(*chunk_entity).is_none() {
+                chunk_render_queue.queue.pop_front();
+                continue;
+            }
+            let Ok((chunk, chunk_pos, chunk_lod, children)) = chunk_query.get(*chunk_entity) else {
+                break;
+            };
+
+            let mesh_start = Instant::now();
+            for section in ChunkMeshSection::ALL {
+                let mesh = if chunk_lod.0 == 0 {
+                    chunk.generate_mesh(
+                        section,
+                        config.packed_vertices,
+                        *mesher,
+                        *shading,
+                        chunk_pos,
+                        &chunk_width,
+                        &voxel_chunk_map,
+                        &voxel_chunk_query,
+                    )
+                } else {
+                    chunk.generate_lod_mesh(
+                        section,
+                        config.packed_vertices,
+                        *mesher,
+                        *shading,
+                        chunk_lod.0,
+                        lod_settings.simplify_tolerance(chunk_lod.0),
+                        chunk_pos,
+                        &chunk_width,
+                    )
+                };
+                let mesh = meshes.add(mesh);
+
+                if section == ChunkMeshSection::Opaque {
+                    commands.entity(*chunk_entity).insert(mesh);
+                } else if let Some(section_entity) = children
+                    .iter()
+                    .find(|child| section_query.get(**child) == Ok(&section))
+                {
+                    commands.entity(*section_entity).insert(mesh);
+                }
+            }
+
+            stats.mesh_time += mesh_start.elapsed();
+            stats.meshes_built += 1;
+            meshed += 1;
+
+            if let Some(light_entity) = children.iter().find(|child| light_query.contains(**child))
+            {
+                let mut light_commands = commands.entity(*light_entity);
+
+                match chunk.light_source(&chunk_width) {
+                    Some((position, emission)) => light_commands.insert((
+                        Transform::from_translation(position),
+                        PointLight {
+                            color: CHUNK_LIGHT_COLOR,
+                            intensity: (emission as f32 * CHUNK_LIGHT_INTENSITY_PER_EMISSION)
+                                .min(MAX_CHUNK_LIGHT_INTENSITY),
+                            range: chunk_width.0 as f32 * 1.5,
+                            ..default()
+                        },
+                        Visibility::Visible,
+                    )),
+                    None => light_commands.insert(Visibility::Hidden),
+                };
+            }
+
+            commands.entity(*chunk_entity).insert(ChunkState::Meshed);
+            meshed_chunks.send(ChunkMeshed {
+                chunk_pos: chunk_pos.0,
+                entity: *chunk_entity,
+            });
+
+            chunk_render_queue.queue.pop_front();
+        }
+    }
+
+    /// Makes the emissive voxels, like fire and lava, flicker. There are no voxel textures to animate, so this changes
+    /// the brightness of the whole emissive material instead.
+    pub(super) fn flicker_emissive_material(
+        time: Res<Time>,
+        chunk_materials: Res<ChunkMaterials>,
+        mut materials: ResMut<Assets<ChunkMaterial>>,
+    ) {
+        let t = (time.elapsed_seconds() * FLICKER_RATE).floor() / FLICKER_RATE;
+        let brightness = 0.9 + 0.1 * (t * 7.0).sin() * (t * 13.0).sin();
+        let color = Color::rgb(brightness, brightness, brightness);
+
+        // Getting the material mutably marks it as changed, even if nothing is written to it.
+        let handle = chunk_materials.get(ChunkMeshSection::Emissive);
+        if !materials
+            .get(&handle)
+            .is_some_and(|material| material.base_color != color)
+        {
+            return;
+        }
+        if let Some(material) = materials.get_mut(handle) {
+            material.base_color = color;
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, transform::TransformSystem};
+use serde::{Deserialize, Serialize};
+
+use crate::console::RegisterConsoleCommand;
+
+use super::{edit::VoxelEdit, noise::TerrainNoise};
+
+/// Where replays are saved and loaded from, relative to the working directory.
+const REPLAY_DIR: &str = "replays";
+/// The name of replays recorded without one.
+const DEFAULT_REPLAY_NAME: &str = "replay";
+
+/// This plugin records the path of the camera and every [VoxelEdit] to a [Replay], and plays replays back. Playing one
+/// moves the camera along the recorded path and sends the recorded edits in the same order, at the same time since
+/// the start, which makes them handy for demos and for reproducing streaming bugs.
+///
+/// `record <name>` starts recording, and `record` again stops and saves it to [REPLAY_DIR]. `replay <name>` plays it,
+/// and `replay` stops playing early. Insert [PlayReplay] before this plugin is added to play a replay right away.
+///
+/// The camera and the chunks it loads follow the replay exactly. Edits of voxels in unloaded chunks are dropped, like
+/// they would be when made by hand, so replays should be played in the world they were recorded in.
+pub(super) struct VoxelReplayPlugin;
+
+impl Plugin for VoxelReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let state = match app.world.remove_resource::<PlayReplay>() {
+            Some(PlayReplay(name)) => ReplayState::play(&name).unwrap_or_default(),
+            None => ReplayState::Idle,
+        };
+
+        app.insert_resource(state)
+            .add_event::<VoxelEdit>()
+            .register_console_command(
+                "record",
+                "Starts recording a replay with the given name, or saves the one being recorded",
+            )
+            .register_console_command(
+                "replay",
+                "Plays the replay with the given name, or stops the one playing",
+            )
+            .add_systems(Update, systems::handle_replay_commands)
+            .add_systems(
+                PostUpdate,
+                (systems::record_frame, systems::play_frame)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// The name of a replay in [REPLAY_DIR] to play as soon as the game starts.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct PlayReplay(pub String);
+
+/// A recorded camera path, with the edits made along the way.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct Replay {
+    /// The seed of the world it was recorded in, if it was recorded where the world is generated.
+    seed: Option<u32>,
+    frames: Vec<ReplayFrame>,
+}
+
+/// What happened in a single frame of a [Replay].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ReplayFrame {
+    /// Seconds since the recording started.
+    time: f64,
+    translation: Vec3,
+    rotation: Quat,
+    /// The edits sent in the frame, in the order they were sent.
+    edits: Vec<VoxelEdit>,
+}
+
+impl Replay {
+    fn path(name: &str) -> PathBuf {
+        Path::new(REPLAY_DIR).join(format!("{name}.replay"))
+    }
+
+    fn load(name: &str) -> Option<Self> {
+        let path = Self::path(name);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                warn!("Failed to read {}: {err}", path.display());
+                return None;
+            }
+        };
+
+        match bincode::deserialize(&bytes) {
+            Ok(replay) => Some(replay),
+            Err(err) => {
+                warn!("Failed to read {}: {err}", path.display());
+                None
+            }
+        }
+    }
+
+    fn save(&self, name: &str) -> io::Result<PathBuf> {
+        let path = Self::path(name);
+        let bytes = bincode::serialize(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        fs::create_dir_all(REPLAY_DIR)?;
+        fs::write(&path, bytes)?;
+        Ok(path)
+    }
+}
+
+/// Whether a [Replay] is being recorded or played.
+#[derive(Resource, Debug, Default)]
+enum ReplayState {
+    #[default]
+    Idle,
+    Recording {
+        name: String,
+        /// When the recording started, in seconds since the app started.
+        started_at: Option<f64>,
+        replay: Replay,
+    },
+    Playing {
+        /// When playing started, in seconds since the app started. Set on the first frame played.
+        started_at: Option<f64>,
+        replay: Replay,
+        /// The index of the first frame whose edits weren't sent yet.
+        next_frame: usize,
+    },
+}
+
+impl ReplayState {
+    fn play(name: &str) -> Option<Self> {
+        let replay = Replay::load(name)?;
+        info!("Playing replay {name}, {} frames long", replay.frames.len());
+
+        Some(Self::Playing {
+            started_at: None,
+            replay,
+            next_frame: 0,
+        })
+    }
+}
+
+mod systems {
+    use crate::console::ConsoleCommand;
+
+    use super::*;
+
+    pub(super) fn handle_replay_commands(
+        mut commands: EventReader<ConsoleCommand>,
+        mut state: ResMut<ReplayState>,
+        terrain_noise: Option<Res<TerrainNoise>>,
+    ) {
+        for command in commands.read() {
+            let name = command.args.first().map(String::as_str);
+
+            match (command.name.as_str(), &*state) {
+                ("record", ReplayState::Recording { name, replay, .. }) => {
+                    match replay.save(name) {
+                        Ok(path) => info!("Saved the replay to {}", path.display()),
+                        Err(err) => error!("Failed to save replay {name}: {err}"),
+                    }
+                    *state = ReplayState::Idle;
+                }
+                ("record", ReplayState::Idle) => {
+                    let name = name.unwrap_or(DEFAULT_REPLAY_NAME).to_string();
+                    info!("Recording replay {name}");
+                    *state = ReplayState::Recording {
+                        name,
+                        started_at: None,
+                        replay: Replay {
+                            seed: terrain_noise.as_ref().map(|noise| noise.seed()),
+                            frames: Vec::new(),
+                        },
+                    };
+                }
+                ("replay", ReplayState::Playing { .. }) => {
+                    info!("Stopped playing the replay");
+                    *state = ReplayState::Idle;
+                }
+                ("replay", ReplayState::Idle) => {
+                    let Some(name) = name else {
+                        warn!("Usage: replay <name>");
+                        continue;
+                    };
+                    if let Some(playing) = ReplayState::play(name) {
+                        *state = playing;
+                    }
+                }
+                ("record", ReplayState::Playing { .. }) => {
+                    warn!("Can't record while a replay is playing");
+                }
+                ("replay", ReplayState::Recording { .. }) => {
+                    warn!("Can't play a replay while recording one");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Records where the camera is, and the edits sent this frame.
+    pub(super) fn record_frame(
+        time: Res<Time>,
+        mut state: ResMut<ReplayState>,
+        mut edits: EventReader<VoxelEdit>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+    ) {
+        let ReplayState::Recording {
+            started_at, replay, ..
+        } = &mut *state
+        else {
+            edits.clear();
+            return;
+        };
+        let Ok(camera_transform) = camera_query.get_single() else {
+            return;
+        };
+
+        let now = time.elapsed_seconds_f64();
+        let started_at = *started_at.get_or_insert(now);
+        replay.frames.push(ReplayFrame {
+            time: now - started_at,
+            translation: camera_transform.translation,
+            rotation: camera_transform.rotation,
+            edits: edits.read().copied().collect(),
+        });
+    }
+
+    /// Sends the edits of every frame up to the current time, and moves the camera between the frames around it. This
+    /// runs after the camera is moved by the player, so the replay wins.
+    pub(super) fn play_frame(
+        time: Res<Time>,
+        terrain_noise: Option<Res<TerrainNoise>>,
+        mut state: ResMut<ReplayState>,
+        mut edits: EventWriter<VoxelEdit>,
+        mut camera_query: Query<&mut Transform, With<Camera3d>>,
+    ) {
+        let ReplayState::Playing {
+            started_at,
+            replay,
+            next_frame,
+        } = &mut *state
+        else {
+            return;
+        };
+        let Ok(mut camera_transform) = camera_query.get_single_mut() else {
+            return;
+        };
+
+        let now = time.elapsed_seconds_f64();
+        if started_at.is_none() {
+            if let (Some(seed), Some(terrain_noise)) = (replay.seed, &terrain_noise) {
+                if seed != terrain_noise.seed() {
+                    warn!(
+                        "The replay was recorded in a world with seed {seed}, not {}, so its edits may not line up",
+                        terrain_noise.seed()
+                    );
+                }
+            }
+        }
+        let elapsed = now - *started_at.get_or_insert(now);
+
+        while let Some(frame) = replay.frames.get(*next_frame) {
+            if frame.time > elapsed {
+                break;
+            }
+            edits.send_batch(frame.edits.iter().copied());
+            *next_frame += 1;
+        }
+
+        let previous = next_frame.checked_sub(1).and_then(|i| replay.frames.get(i));
+        match (previous, replay.frames.get(*next_frame)) {
+            (Some(previous), Some(next)) => {
+                let t = ((elapsed - previous.time) / (next.time - previous.time)) as f32;
+                camera_transform.translation = previous.translation.lerp(next.translation, t);
+                camera_transform.rotation = previous.rotation.slerp(next.rotation, t);
+            }
+            (None, Some(first)) => {
+                camera_transform.translation = first.translation;
+                camera_transform.rotation = first.rotation;
+            }
+            (Some(last), None) => {
+                camera_transform.translation = last.translation;
+                camera_transform.rotation = last.rotation;
+                info!("Finished playing the replay");
+                *state = ReplayState::Idle;
+            }
+            (None, None) => {
+                *state = ReplayState::Idle;
+            }
+        }
+    }
+}
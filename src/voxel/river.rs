@@ -0,0 +1,47 @@
+use noise::{NoiseFn, Simplex};
+
+/// How quickly rivers wind over the world. Lower makes longer, straighter rivers.
+const RIVER_FREQUENCY: f64 = 0.003;
+/// How wide the water of rivers is, in how close the river noise has to be to 0.
+const CHANNEL_WIDTH: f64 = 0.035;
+/// How wide rivers are with their banks, in how close the river noise has to be to 0.
+const VALLEY_WIDTH: f64 = 0.12;
+/// How deep rivers are in their middle, in voxels below the water level.
+const CHANNEL_DEPTH: f64 = 4.0;
+/// How high the banks of rivers rise above the water level at the edge of the valley, in voxels.
+const BANK_HEIGHT: f64 = 24.0;
+
+/// Carves rivers into the terrain, where a noise crosses 0. Those lines never end, and wind around the world.
+///
+/// Every river is a channel of water at the water level of the terrain, in a valley whose banks slope up from the water.
+/// Everything above the valley floor is carved away, so rivers cut through hills and mountains as canyons.
+pub(super) struct RiverMap {
+    noise: Simplex,
+}
+
+impl RiverMap {
+    pub(super) fn new(seed: u32) -> Self {
+        Self {
+            noise: Simplex::new(seed ^ 0x2c1b_3f6d),
+        }
+    }
+
+    /// The height of the river valley floor at a column relative to the water level, above which the terrain is carved
+    /// away. [None] outside of river valleys.
+    pub(super) fn floor(&self, x: i32, z: i32) -> Option<f64> {
+        let distance = self
+            .noise
+            .get([x as f64 * RIVER_FREQUENCY, z as f64 * RIVER_FREQUENCY])
+            .abs();
+
+        if distance < CHANNEL_WIDTH {
+            Some(-CHANNEL_DEPTH * (1.0 - distance / CHANNEL_WIDTH))
+        } else if distance < VALLEY_WIDTH {
+            let t = (distance - CHANNEL_WIDTH) / (VALLEY_WIDTH - CHANNEL_WIDTH);
+            // Smoothstep, so the banks flatten out towards the water and the surrounding terrain.
+            Some(BANK_HEIGHT * t * t * (3.0 - 2.0 * t))
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::input::InputMap;
+
+use super::{generation::VoxelChunkPosition, VoxelConfig};
+
+/// This plugin adds an optional falling sand simulation. While it's enabled, [Powder](super::registry::BlockTag::Powder)
+/// voxels fall and pile up, and [Liquid](super::registry::BlockTag::Liquid) voxels fall and spread out, every frame.
+///
+/// This is a simple cellular automaton, separate from the block ticks. Only chunks that changed are simulated,
+/// and only [VoxelConfig::sand_chunks_per_frame] of them every frame.
+pub(super) struct VoxelSandPlugin;
+
+impl Plugin for VoxelSandPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<FallingSandState>()
+            .init_resource::<FallingSandSimulation>()
+            .add_systems(
+                Update,
+                (
+                    // The dedicated server has no input.
+                    systems::toggle_falling_sand.run_if(resource_exists::<InputMap>()),
+                    (
+                        systems::queue_changed_chunks,
+                        systems::simulate_falling_sand,
+                    )
+                        .chain()
+                        .run_if(in_state(FallingSandState::Enabled)),
+                ),
+            )
+            .add_systems(OnExit(FallingSandState::Enabled), systems::clear_queue);
+    }
+}
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub(super) enum FallingSandState {
+    Enabled,
+    #[default]
+    Disabled,
+}
+
+#[derive(Resource, Default)]
+pub(super) struct FallingSandSimulation {
+    /// Chunks waiting to be simulated.
+    queue: VecDeque<VoxelChunkPosition>,
+    /// The same chunks as the queue, to quickly check if a chunk is queued.
+    queued: HashSet<VoxelChunkPosition>,
+}
+
+mod systems {
+    use rand::seq::SliceRandom;
+
+    use crate::{
+        input::{ActionInput, InputAction},
+        voxel::{
+            generation::{LocalVoxelPosition, VoxelChunk, VoxelChunkWidth},
+            registry::BlockTag,
+            world::VoxelWorld,
+            Voxel,
+        },
+    };
+
+    use super::*;
+
+    const DIAGONALS: [IVec3; 4] = [
+        IVec3::new(1, -1, 0),
+        IVec3::new(-1, -1, 0),
+        IVec3::new(0, -1, 1),
+        IVec3::new(0, -1, -1),
+    ];
+    const HORIZONTAL_NEIGHBOURS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+    pub(super) fn toggle_falling_sand(
+        input: ActionInput,
+        mut next_state: ResMut<NextState<FallingSandState>>,
+        cur_state: Res<State<FallingSandState>>,
+    ) {
+        if input.just_pressed(InputAction::ToggleFallingSand) {
+            next_state.set(match **cur_state {
+                FallingSandState::Enabled => FallingSandState::Disabled,
+                FallingSandState::Disabled => FallingSandState::Enabled,
+            })
+        }
+    }
+
+    /// Queues every chunk that changed since the last frame. This includes newly loaded chunks, and the chunks the
+    /// simulation itself changed, so moving voxels keep being simulated until they settle.
+    pub(super) fn queue_changed_chunks(
+        mut simulation: ResMut<FallingSandSimulation>,
+        chunk_query: Query<&VoxelChunkPosition, Changed<VoxelChunk>>,
+    ) {
+        for chunk_pos in &chunk_query {
+            if simulation.queued.insert(*chunk_pos) {
+                simulation.queue.push_back(*chunk_pos);
+            }
+        }
+    }
+
+    pub(super) fn simulate_falling_sand(
+        mut simulation: ResMut<FallingSandSimulation>,
+        mut voxel_world: VoxelWorld,
+        chunk_width: Res<VoxelChunkWidth>,
+        config: Res<VoxelConfig>,
+    ) {
+        let _span = info_span!("simulate_falling_sand").entered();
+
+        let mut rng = rand::thread_rng();
+        let voxel_count = chunk_width.0 as usize * chunk_width.0 as usize * chunk_width.0 as usize;
+
+        for _ in 0..config.sand_chunks_per_frame {
+            let Some(chunk_pos) = simulation.queue.pop_front() else {
+                break;
+            };
+            simulation.queued.remove(&chunk_pos);
+
+            let Some(chunk) = voxel_world.get_chunk(chunk_pos) else {
+                continue;
+            };
+
+            // Go through the chunk bottom up, so a falling column moves down together, instead of only the bottom
+            // voxel moving.
+            let mut cells: Vec<(IVec3, Voxel)> = (0..voxel_count)
+                .filter_map(|i| {
+                    let local_pos = LocalVoxelPosition::from_index(i, &chunk_width);
+                    let voxel = chunk.get_voxel(local_pos, &chunk_width)?;
+                    let definition = voxel.definition();
+
+                    (definition.has_tag(BlockTag::Powder) || definition.has_tag(BlockTag::Liquid))
+                        .then(|| (chunk_pos.local_to_world(local_pos, &chunk_width), voxel))
+                })
+                .collect();
+            cells.sort_by_key(|(voxel_pos, _)| voxel_pos.y);
+
+            let mut moved = HashSet::new();
+
+            for (voxel_pos, voxel) in cells {
+                if moved.contains(&voxel_pos) {
+                    continue;
+                }
+
+                let liquid = voxel.definition().has_tag(BlockTag::Liquid);
+
+                // Powder sinks through liquids, but liquids only move into air.
+                let can_move_into = |voxel_world: &VoxelWorld, target_pos: IVec3| {
+                    voxel_world.get_block(target_pos).is_some_and(|target| {
+                        target == Voxel::AIR
+                            || (!liquid && target.definition().has_tag(BlockTag::Liquid))
+                    })
+                };
+
+                let mut targets = vec![voxel_pos - IVec3::Y];
+
+                let mut diagonals = DIAGONALS;
+                diagonals.shuffle(&mut rng);
+                targets.extend(diagonals.map(|offset| voxel_pos + offset));
+
+                if liquid {
+                    let mut sideways = HORIZONTAL_NEIGHBOURS;
+                    sideways.shuffle(&mut rng);
+                    targets.extend(sideways.map(|offset| voxel_pos + offset));
+                }
+
+                let Some(target_pos) = targets
+                    .into_iter()
+                    .find(|target_pos| can_move_into(&voxel_world, *target_pos))
+                else {
+                    continue;
+                };
+
+                let Some(target) = voxel_world.get_block(target_pos) else {
+                    continue;
+                };
+
+                voxel_world.set_block(target_pos, voxel);
+                voxel_world.set_block(voxel_pos, target);
+                moved.insert(target_pos);
+            }
+        }
+    }
+
+    pub(super) fn clear_queue(mut simulation: ResMut<FallingSandSimulation>) {
+        simulation.queue.clear();
+        simulation.queued.clear();
+    }
+}
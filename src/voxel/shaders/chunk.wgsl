@@ -0,0 +1,148 @@
+// The shader of `ChunkMaterial`. Colors, ambient occlusion and texture layers come from the vertices of the chunk mesh.
+//
+// With `GPU_CULLED`, it draws the merged chunk meshes of the GPU culling path instead, which have no mesh bindings.
+
+#ifdef GPU_CULLED
+#import bevy_pbr::mesh_types::MESH_FLAGS_SHADOW_RECEIVER_BIT
+#import voxel::chunk_vertex::CulledChunk
+#else
+#import bevy_pbr::{
+    mesh_bindings::mesh,
+    mesh_functions::{get_model_matrix, mesh_position_local_to_world, mesh_normal_local_to_world},
+}
+#endif
+#import bevy_pbr::{
+    mesh_view_bindings::view,
+    view_transformations::position_world_to_clip,
+    pbr_types::{pbr_input_new, STANDARD_MATERIAL_FLAGS_FOG_ENABLED_BIT},
+    pbr_functions::{calculate_view, prepare_world_normal, apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#import bevy_render::instance_index::get_instance_index
+#import voxel::chunk_vertex::{ChunkVertex, unpack_vertex, NO_TEXTURE_LAYER}
+
+const FLAGS_UNLIT: u32 = 1u;
+const FLAGS_DOUBLE_SIDED: u32 = 2u;
+const FLAGS_TEXTURED: u32 = 4u;
+
+struct ChunkMaterial {
+    base_color: vec4<f32>,
+    flags: u32,
+    alpha_cutoff: f32,
+};
+
+@group(1) @binding(0) var<uniform> material: ChunkMaterial;
+@group(1) @binding(1) var array_texture: texture_2d_array<f32>;
+@group(1) @binding(2) var array_texture_sampler: sampler;
+
+#ifdef GPU_CULLED
+@group(2) @binding(0) var<storage, read> culled_chunks: array<CulledChunk>;
+#endif
+
+#ifdef PACKED_VERTICES
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) packed: vec2<u32>,
+#ifdef GPU_CULLED
+    // The index of the chunk in the `culled_chunks`.
+    @location(1) chunk: u32,
+#endif
+};
+#else
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) color: vec4<f32>,
+    @location(4) occlusion: f32,
+    @location(5) texture_layer: u32,
+};
+#endif
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) color: vec4<f32>,
+    @location(4) occlusion: f32,
+    @location(5) @interpolate(flat) texture_layer: u32,
+    @location(6) @interpolate(flat) instance_index: u32,
+};
+
+@vertex
+fn vertex(in: Vertex) -> VertexOutput {
+#ifdef PACKED_VERTICES
+    let vertex = unpack_vertex(in.packed);
+#else
+    let vertex = ChunkVertex(in.position, in.normal, in.uv, in.color, in.occlusion, in.texture_layer);
+#endif
+    var out: VertexOutput;
+
+#ifdef GPU_CULLED
+    // Chunks are only ever translated.
+    out.world_position = vec4<f32>(vertex.position + culled_chunks[in.chunk].origin.xyz, 1.0);
+    out.world_normal = vertex.normal;
+#else
+    let model = get_model_matrix(in.instance_index);
+    out.world_position = mesh_position_local_to_world(model, vec4<f32>(vertex.position, 1.0));
+    out.world_normal = mesh_normal_local_to_world(vertex.normal, get_instance_index(in.instance_index));
+#endif
+    out.position = position_world_to_clip(out.world_position.xyz);
+    out.uv = vertex.uv;
+    out.color = vertex.color;
+    out.occlusion = vertex.occlusion;
+    out.texture_layer = vertex.texture_layer;
+    out.instance_index = get_instance_index(in.instance_index);
+
+    return out;
+}
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> @location(0) vec4<f32> {
+    // Sampling has to happen in uniform control flow, so untextured faces sample the first layer and ignore it.
+    let textured = (material.flags & FLAGS_TEXTURED) != 0u && in.texture_layer != NO_TEXTURE_LAYER;
+    // Texture coordinates can run over several voxels, like along packed vertices or simplified faces, so they're
+    // wrapped per voxel.
+    let uv = fract(in.uv);
+    let texel = textureSample(array_texture, array_texture_sampler, uv, select(0u, in.texture_layer, textured));
+    var color = material.base_color * in.color * select(vec4<f32>(1.0), texel, textured);
+    color = vec4<f32>(color.rgb * in.occlusion, color.a);
+#ifdef MAY_DISCARD
+    // Cut out the holes of masked voxels, like leaves.
+    if color.a < material.alpha_cutoff {
+        discard;
+    }
+    color.a = 1.0;
+#endif
+
+    var pbr_input = pbr_input_new();
+    pbr_input.material.base_color = color;
+    pbr_input.material.flags |= STANDARD_MATERIAL_FLAGS_FOG_ENABLED_BIT;
+#ifdef GPU_CULLED
+    pbr_input.flags = MESH_FLAGS_SHADOW_RECEIVER_BIT;
+#else
+    pbr_input.flags = mesh[in.instance_index].flags;
+#endif
+    pbr_input.frag_coord = in.position;
+    pbr_input.world_position = in.world_position;
+    pbr_input.is_orthographic = view.projection[3].w == 1.0;
+    pbr_input.V = calculate_view(in.world_position, pbr_input.is_orthographic);
+    pbr_input.world_normal = prepare_world_normal(
+        in.world_normal,
+        (material.flags & FLAGS_DOUBLE_SIDED) != 0u,
+        is_front,
+    );
+    pbr_input.N = normalize(pbr_input.world_normal);
+    pbr_input.occlusion = vec3<f32>(in.occlusion);
+
+    var out_color = color;
+    if (material.flags & FLAGS_UNLIT) == 0u {
+        out_color = apply_pbr_lighting(pbr_input);
+    }
+
+    return main_pass_post_lighting_processing(pbr_input, out_color);
+}
@@ -0,0 +1,43 @@
+// Frustum culls the chunks of the GPU culling path. Every chunk has an indexed indirect draw, which is skipped by setting
+// its instance count to zero when the bounds of the chunk are outside of the view.
+
+#import voxel::chunk_vertex::CulledChunk
+
+struct DrawIndexedIndirect {
+    index_count: u32,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+};
+
+// The planes of the view frustum, with their normals pointing inwards and the distance from the origin in `w`.
+struct Frustum {
+    planes: array<vec4<f32>, 6>,
+};
+
+@group(0) @binding(0) var<storage, read> chunks: array<CulledChunk>;
+@group(0) @binding(1) var<storage, read_write> draws: array<DrawIndexedIndirect>;
+@group(0) @binding(2) var<uniform> frustum: Frustum;
+
+@compute @workgroup_size(64)
+fn cull(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= arrayLength(&chunks) {
+        return;
+    }
+
+    let chunk = chunks[index];
+    let center = (chunk.min.xyz + chunk.max.xyz) * 0.5;
+    let half_extents = (chunk.max.xyz - chunk.min.xyz) * 0.5;
+
+    for (var i = 0u; i < 6u; i += 1u) {
+        let plane = frustum.planes[i];
+        // How far the corner of the bounds furthest along the normal is inside the plane.
+        let distance = dot(plane.xyz, center) + plane.w + dot(abs(plane.xyz), half_extents);
+        if distance < 0.0 {
+            draws[index].instance_count = 0u;
+            return;
+        }
+    }
+}
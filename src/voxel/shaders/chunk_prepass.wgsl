@@ -0,0 +1,37 @@
+// The prepass vertex shader of chunks with packed vertices, like for shadows. Unpacked chunks use the default one.
+
+#import bevy_pbr::{
+    mesh_functions::{get_model_matrix, mesh_position_local_to_clip, mesh_position_local_to_world},
+    prepass_io::VertexOutput,
+}
+#import bevy_render::instance_index::get_instance_index
+#import voxel::chunk_vertex::unpack_position
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) packed: vec2<u32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let model = get_model_matrix(vertex.instance_index);
+    let position = vec4<f32>(unpack_position(vertex.packed), 1.0);
+    out.position = mesh_position_local_to_clip(model, position);
+#ifdef DEPTH_CLAMP_ORTHO
+    out.clip_position_unclamped = out.position;
+    out.position.z = min(out.position.z, 1.0);
+#endif
+
+    out.world_position = mesh_position_local_to_world(model, position);
+#ifdef MOTION_VECTOR_PREPASS
+    out.previous_world_position = out.world_position;
+#endif
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = get_instance_index(vertex.instance_index);
+#endif
+
+    return out;
+}
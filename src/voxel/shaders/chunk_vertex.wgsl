@@ -0,0 +1,82 @@
+// The vertices of chunk meshes, and how packed vertices are decoded. The packing is done by `pack_vertex`.
+
+#define_import_path voxel::chunk_vertex
+
+const NO_TEXTURE_LAYER: u32 = 0xffffffffu;
+const PACKED_NO_TEXTURE_LAYER: u32 = 0x3ffu;
+
+// A chunk of the GPU culling path, with its bounds in world space. The `w` components are unused.
+struct CulledChunk {
+    origin: vec4<f32>,
+    min: vec4<f32>,
+    max: vec4<f32>,
+};
+
+struct ChunkVertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    uv: vec2<f32>,
+    color: vec4<f32>,
+    occlusion: f32,
+    texture_layer: u32,
+};
+
+// The face normals by id, in the same order as `DIRECT_CUBE_NEIGHBOURS`.
+fn normal_from_id(id: u32) -> vec3<f32> {
+    var normals = array<vec3<f32>, 6>(
+        vec3<f32>(0.0, 1.0, 0.0),
+        vec3<f32>(0.0, -1.0, 0.0),
+        vec3<f32>(-1.0, 0.0, 0.0),
+        vec3<f32>(1.0, 0.0, 0.0),
+        vec3<f32>(0.0, 0.0, -1.0),
+        vec3<f32>(0.0, 0.0, 1.0),
+    );
+    return normals[min(id, 5u)];
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    return select(
+        pow((color + 0.055) / 1.055, vec3<f32>(2.4)),
+        color / 12.92,
+        color <= vec3<f32>(0.04045),
+    );
+}
+
+// Only the position is needed to draw the depth of a chunk, like for shadows.
+fn unpack_position(packed: vec2<u32>) -> vec3<f32> {
+    return vec3<f32>(
+        f32(packed.x & 0x7fu) - 0.5,
+        f32((packed.x >> 14u) & 0x7ffu) / 16.0 - 0.5,
+        f32((packed.x >> 7u) & 0x7fu) - 0.5,
+    );
+}
+
+fn unpack_vertex(packed: vec2<u32>) -> ChunkVertex {
+    var vertex: ChunkVertex;
+
+    vertex.position = unpack_position(packed);
+    vertex.normal = normal_from_id((packed.x >> 25u) & 0x7u);
+    vertex.occlusion = f32((packed.x >> 28u) & 0x3u) / 3.0;
+
+    // Texture coordinates run along the chunk, and are wrapped per voxel in the fragment shader.
+    let p = vertex.position;
+    if vertex.normal.y != 0.0 {
+        vertex.uv = vec2<f32>(p.x + 0.5, p.z + 0.5);
+    } else if vertex.normal.x != 0.0 {
+        vertex.uv = vec2<f32>(p.z + 0.5, 0.5 - p.y);
+    } else {
+        vertex.uv = vec2<f32>(p.x + 0.5, 0.5 - p.y);
+    }
+
+    let color = vec3<f32>(
+        f32(packed.y & 0x3fu),
+        f32((packed.y >> 6u) & 0x3fu),
+        f32((packed.y >> 12u) & 0x3fu),
+    ) / 63.0;
+    vertex.color = vec4<f32>(srgb_to_linear(color), f32((packed.y >> 18u) & 0xfu) / 15.0);
+
+    let texture_layer = packed.y >> 22u;
+    vertex.texture_layer = select(texture_layer, NO_TEXTURE_LAYER, texture_layer == PACKED_NO_TEXTURE_LAYER);
+
+    return vertex;
+}
@@ -0,0 +1,104 @@
+// The shader of `RaymarchMaterial`. Instead of drawing a mesh of the voxels, every pixel of the box around a chunk
+// walks the ray from the camera through the voxels of the chunk, one voxel at a time, until it hits a filled one.
+
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_types::MESH_FLAGS_SHADOW_RECEIVER_BIT,
+    mesh_view_bindings::view,
+    pbr_types::{pbr_input_new, STANDARD_MATERIAL_FLAGS_FOG_ENABLED_BIT},
+    pbr_functions::{calculate_view, apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+
+struct RaymarchChunk {
+    // The world position of the center of the first voxel of the chunk, and the width of the chunk in w.
+    origin: vec4<f32>,
+};
+
+@group(1) @binding(0) var<uniform> chunk: RaymarchChunk;
+// The color of every voxel of the chunk, with an alpha of 1.0 if it's filled.
+@group(1) @binding(1) var voxels: texture_3d<f32>;
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    // The depth of the hit voxel, rather than of the box.
+    @builtin(frag_depth) depth: f32,
+};
+
+fn shade(in: VertexOutput, color: vec3<f32>, world_position: vec3<f32>, normal: vec3<f32>) -> FragmentOutput {
+    let clip_position = view.view_proj * vec4<f32>(world_position, 1.0);
+    let depth = clip_position.z / clip_position.w;
+
+    var pbr_input = pbr_input_new();
+    pbr_input.material.base_color = vec4<f32>(color, 1.0);
+    pbr_input.material.flags |= STANDARD_MATERIAL_FLAGS_FOG_ENABLED_BIT;
+    pbr_input.flags = MESH_FLAGS_SHADOW_RECEIVER_BIT;
+    pbr_input.frag_coord = vec4<f32>(in.position.xy, depth, 1.0);
+    pbr_input.world_position = vec4<f32>(world_position, 1.0);
+    pbr_input.is_orthographic = view.projection[3].w == 1.0;
+    pbr_input.V = calculate_view(pbr_input.world_position, pbr_input.is_orthographic);
+    pbr_input.world_normal = normal;
+    pbr_input.N = normal;
+
+    var out: FragmentOutput;
+    out.color = main_pass_post_lighting_processing(pbr_input, apply_pbr_lighting(pbr_input));
+    out.depth = depth;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    let width = i32(chunk.origin.w);
+    // In voxels of the chunk, where voxel (x, y, z) covers x..x + 1 on every axis.
+    let ray_origin = view.world_position - chunk.origin.xyz + 0.5;
+    var direction = normalize(in.world_position.xyz - view.world_position);
+    // Keeps the divisions below finite.
+    direction = select(direction, vec3<f32>(1e-6), abs(direction) < vec3<f32>(1e-6));
+    let inverse_direction = 1.0 / direction;
+
+    // Where the ray enters the chunk, or where the camera is if it's inside of it. The back faces of the box are
+    // drawn, so this works either way.
+    let near = min(-ray_origin * inverse_direction, (f32(width) - ray_origin) * inverse_direction);
+    let entry = max(max(max(near.x, near.y), near.z), 0.0);
+    var normal = -sign(direction) * select(vec3<f32>(0.0), vec3<f32>(1.0), near == vec3<f32>(entry));
+    if entry == 0.0 {
+        normal = -direction;
+    }
+
+    let start = ray_origin + direction * entry;
+    var cell = clamp(vec3<i32>(floor(start)), vec3<i32>(0), vec3<i32>(width - 1));
+    let cell_step = vec3<i32>(sign(direction));
+    let cell_distance = abs(inverse_direction);
+    var next_crossing = (vec3<f32>(cell) + max(sign(direction), vec3<f32>(0.0)) - ray_origin) * inverse_direction;
+    var distance = entry;
+
+    // A ray crosses at most 3 times the width of the chunk before it leaves it.
+    for (var i = 0; i < 3 * width; i += 1) {
+        let voxel = textureLoad(voxels, cell, 0);
+        if voxel.a > 0.5 {
+            return shade(in, voxel.rgb, view.world_position + direction * distance, normal);
+        }
+
+        if next_crossing.x < next_crossing.y && next_crossing.x < next_crossing.z {
+            cell.x += cell_step.x;
+            distance = next_crossing.x;
+            next_crossing.x += cell_distance.x;
+            normal = vec3<f32>(-f32(cell_step.x), 0.0, 0.0);
+        } else if next_crossing.y < next_crossing.z {
+            cell.y += cell_step.y;
+            distance = next_crossing.y;
+            next_crossing.y += cell_distance.y;
+            normal = vec3<f32>(0.0, -f32(cell_step.y), 0.0);
+        } else {
+            cell.z += cell_step.z;
+            distance = next_crossing.z;
+            next_crossing.z += cell_distance.z;
+            normal = vec3<f32>(0.0, 0.0, -f32(cell_step.z));
+        }
+
+        if any(cell < vec3<i32>(0)) || any(cell >= vec3<i32>(width)) {
+            break;
+        }
+    }
+
+    discard;
+}
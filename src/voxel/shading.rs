@@ -0,0 +1,152 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::cube_mesh::DIRECT_CUBE_NEIGHBOURS;
+
+/// How many steps per voxel vertex positions are rounded to, to find the vertices that share a corner.
+const CORNER_PRECISION: f32 = 64.0;
+/// The widest a bevel can be, in voxels. Any wider and the bevels of opposite edges of a voxel would meet.
+const MAX_BEVEL: f32 = 0.25;
+
+/// A stylized look for cube meshes, without switching to a smooth [TerrainMesher](super::mesher::TerrainMesher).
+///
+/// Chunks with [packed vertices](super::VoxelConfig::packed_vertices) can only have the normals of the six faces and
+/// vertices on the corners of voxels, so they're always drawn flat.
+#[derive(Resource, Reflect, Default, Debug, Clone, Copy, PartialEq)]
+#[reflect(Resource)]
+pub(super) struct ChunkShading {
+    /// Averages the normals of the faces of voxels of the same kind where they meet, so the edges of blocks are shaded
+    /// softly.
+    pub(super) smooth_normals: bool,
+    /// Cuts off the outer edges of voxels, in voxels. 0.0 leaves them sharp. Lowered tops, like those of fluids, are
+    /// never bevelled.
+    pub(super) bevel: f32,
+}
+
+impl ChunkShading {
+    /// Flat shading with sharp edges, like the chunks are drawn by default.
+    pub(super) const FLAT: Self = Self {
+        smooth_normals: false,
+        bevel: 0.0,
+    };
+
+    /// The bevel to cut the edges of voxels off with, kept from getting so wide that the bevels of a voxel overlap.
+    pub(super) fn bevel(&self) -> f32 {
+        self.bevel.clamp(0.0, MAX_BEVEL)
+    }
+}
+
+/// The faces that fill the gaps between the faces of a voxel, once the faces are shrunk by `bevel` at the edges they
+/// share with the other faces of the voxel that are drawn. Every patch is a polygon around the corner of the voxel it
+/// fills, with its normal, relative to the center of the voxel.
+///
+/// This only returns the patches along the face facing `normal` that it's responsible for, so every patch is returned
+/// once for all the faces of the voxel. `drawn` tells whether the face of the voxel facing a direction is drawn.
+pub(super) fn bevel_patches(
+    normal: IVec3,
+    bevel: f32,
+    drawn: impl Fn(IVec3) -> bool,
+) -> Vec<(Vec<Vec3>, Vec3)> {
+    let face_index = |direction: IVec3| {
+        DIRECT_CUBE_NEIGHBOURS
+            .iter()
+            .position(|neighbour| *neighbour == direction)
+            .unwrap_or_default()
+    };
+    let n = normal.as_vec3();
+    let sides: Vec<IVec3> = DIRECT_CUBE_NEIGHBOURS
+        .into_iter()
+        .filter(|side| side.dot(normal) == 0 && drawn(*side))
+        .collect();
+    let mut patches = Vec::new();
+
+    // The strips along the edges this face shares with the faces after it.
+    for side in sides
+        .iter()
+        .filter(|side| face_index(**side) > face_index(normal))
+    {
+        let d = side.as_vec3();
+        let along = IVec3::ONE - normal.abs() - side.abs();
+
+        let strip = [-1, 1].map(|sign| {
+            let end = along * sign;
+            let corner = (n + d + end.as_vec3()) * 0.5;
+            let inset = if drawn(end) {
+                end.as_vec3() * bevel
+            } else {
+                Vec3::ZERO
+            };
+            (corner - d * bevel - inset, corner - n * bevel - inset)
+        });
+
+        patches.push((
+            vec![strip[0].0, strip[1].0, strip[1].1, strip[0].1],
+            (n + d).normalize(),
+        ));
+    }
+
+    // The corners this face shares with two faces after it.
+    for (i, first) in sides.iter().enumerate() {
+        for second in &sides[i + 1..] {
+            if first.abs() == second.abs()
+                || face_index(*first) < face_index(normal)
+                || face_index(*second) < face_index(normal)
+            {
+                continue;
+            }
+
+            let (d1, d2) = (first.as_vec3(), second.as_vec3());
+            let corner = (n + d1 + d2) * 0.5;
+            patches.push((
+                vec![
+                    corner - (d1 + d2) * bevel,
+                    corner - (n + d2) * bevel,
+                    corner - (n + d1) * bevel,
+                ],
+                (n + d1 + d2).normalize(),
+            ));
+        }
+    }
+
+    patches
+}
+
+/// The indices of the triangles of a convex polygon, from `first_index` on, wound to face `normal`.
+pub(super) fn polygon_indices(polygon: &[Vec3], normal: Vec3, first_index: u32) -> Vec<u32> {
+    let facing = (polygon[1] - polygon[0])
+        .cross(polygon[2] - polygon[0])
+        .dot(normal)
+        >= 0.0;
+
+    (1..polygon.len() as u32 - 1)
+        .flat_map(|i| if facing { [0, i, i + 1] } else { [0, i + 1, i] })
+        .map(|index| index + first_index)
+        .collect()
+}
+
+/// Averages the normals of the vertices in the same place whose voxels are the same kind, as told by `same_kind`
+/// with the indices of two vertices.
+pub(super) fn smooth_normals(
+    vertices: &[Vec3],
+    normals: &mut [Vec3],
+    same_kind: impl Fn(usize, usize) -> bool,
+) {
+    let mut corners: HashMap<IVec3, Vec<usize>> = HashMap::new();
+    for (i, vertex) in vertices.iter().enumerate() {
+        corners
+            .entry((*vertex * CORNER_PRECISION).round().as_ivec3())
+            .or_default()
+            .push(i);
+    }
+
+    let face_normals = normals.to_vec();
+    for sharing in corners.values() {
+        for i in sharing {
+            let sum: Vec3 = sharing
+                .iter()
+                .filter(|j| same_kind(*i, **j))
+                .map(|j| face_normals[*j])
+                .sum();
+            normals[*i] = sum.try_normalize().unwrap_or(face_normals[*i]);
+        }
+    }
+}
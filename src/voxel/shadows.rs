@@ -0,0 +1,146 @@
+use bevy::{
+    pbr::{CascadeShadowConfig, CascadeShadowConfigBuilder, NotShadowCaster},
+    prelude::*,
+};
+
+use super::{
+    generation::{ChunkMeshSection, VoxelChunkPosition, VoxelChunkWidth},
+    render::ChunkLod,
+    AddResourceInspector, VoxelChunkCoordinate,
+};
+
+/// This plugin configures the shadows of the sun for voxel terrain.
+///
+/// Shadows over every loaded chunk would cost more than the rest of the frame, so only chunks close to the camera cast
+/// shadows, and the shadow cascades are fitted to that distance. See [ChunkShadowSettings].
+pub(super) struct VoxelShadowPlugin;
+
+impl Plugin for VoxelShadowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkShadowSettings>()
+            .register_type::<ChunkShadowSettings>()
+            .add_resource_inspector::<ChunkShadowSettings>()
+            .add_systems(
+                Update,
+                (
+                    systems::configure_sun_shadows,
+                    systems::update_chunk_shadow_casters,
+                ),
+            );
+    }
+}
+
+/// How the sun casts shadows on the terrain.
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub(super) struct ChunkShadowSettings {
+    pub(super) enabled: bool,
+    /// How many chunks from the camera chunks cast shadows. The shadows end at this distance too.
+    pub(super) distance: u32,
+    /// Whether chunks meshed at a coarser [ChunkLod] cast shadows. Their shadows don't match their meshes at full
+    /// detail, and they're usually far away anyway.
+    pub(super) lod_casts_shadows: bool,
+    pub(super) cascades: usize,
+    /// Voxel faces are all axis aligned, so they need less bias against shadow acne than curved surfaces.
+    pub(super) depth_bias: f32,
+    pub(super) normal_bias: f32,
+}
+
+impl Default for ChunkShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            distance: 4,
+            lod_casts_shadows: false,
+            cascades: 3,
+            depth_bias: 0.02,
+            normal_bias: 0.6,
+        }
+    }
+}
+
+impl ChunkShadowSettings {
+    /// Whether a chunk `distance` chunks away at the given level of detail casts shadows.
+    fn casts_shadows(&self, distance: f32, lod: ChunkLod) -> bool {
+        self.enabled && distance <= self.distance as f32 && (lod.0 == 0 || self.lod_casts_shadows)
+    }
+}
+
+mod systems {
+    use super::*;
+
+    /// Applies the [ChunkShadowSettings] to the sun, and fits its shadow cascades to the shadow distance.
+    pub(super) fn configure_sun_shadows(
+        shadow_settings: Res<ChunkShadowSettings>,
+        chunk_width: Res<VoxelChunkWidth>,
+        mut light_query: Query<(&mut DirectionalLight, &mut CascadeShadowConfig)>,
+    ) {
+        if !shadow_settings.is_changed() && !chunk_width.is_changed() {
+            return;
+        }
+
+        let chunk_width = chunk_width.0 as f32;
+        let maximum_distance = shadow_settings.distance.max(1) as f32 * chunk_width;
+
+        for (mut light, mut cascade_config) in &mut light_query {
+            light.shadows_enabled = shadow_settings.enabled;
+            light.shadow_depth_bias = shadow_settings.depth_bias;
+            light.shadow_normal_bias = shadow_settings.normal_bias;
+
+            *cascade_config = CascadeShadowConfigBuilder {
+                num_cascades: shadow_settings.cascades.max(1),
+                minimum_distance: 0.1,
+                maximum_distance,
+                // The first cascade covers the chunk around the camera, where shadows are looked at closest.
+                first_cascade_far_bound: chunk_width.min(maximum_distance),
+                overlap_proportion: 0.2,
+            }
+            .build();
+        }
+    }
+
+    /// Turns shadow casting of chunks on or off, depending on their distance to the camera and their level of detail.
+    pub(super) fn update_chunk_shadow_casters(
+        mut commands: Commands,
+        shadow_settings: Res<ChunkShadowSettings>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+        chunk_query: Query<(
+            Entity,
+            &VoxelChunkPosition,
+            &ChunkLod,
+            &Children,
+            Has<NotShadowCaster>,
+        )>,
+        section_query: Query<(), With<ChunkMeshSection>>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let Ok(camera_transform) = camera_query.get_single() else {
+            return;
+        };
+        let camera_chunk_pos =
+            VoxelChunkPosition::from_world_pos(camera_transform.translation, &chunk_width);
+
+        for (chunk_entity, chunk_pos, chunk_lod, children, not_shadow_caster) in &chunk_query {
+            let distance = (chunk_pos.0 - camera_chunk_pos.0).as_vec3().length();
+            let casts_shadows = shadow_settings.casts_shadows(distance, *chunk_lod);
+            if casts_shadows != not_shadow_caster {
+                continue;
+            }
+
+            // The opaque section is drawn by the chunk itself, the other sections by its children.
+            let section_entities = std::iter::once(chunk_entity).chain(
+                children
+                    .iter()
+                    .copied()
+                    .filter(|child| section_query.contains(*child)),
+            );
+            for entity in section_entities {
+                if casts_shadows {
+                    commands.entity(entity).remove::<NotShadowCaster>();
+                } else {
+                    commands.entity(entity).insert(NotShadowCaster);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use bevy::{prelude::*, utils::HashSet};
+use std::collections::BTreeMap;
+
+use super::cube_mesh::CubeFace;
+
+/// How far off a position can be from the voxel grid and still count as on it.
+const GRID_EPSILON: f32 = 1e-3;
+
+/// The faces of a cube mesh, as built by [VoxelChunk::mesh_voxels](super::generation::VoxelChunk::mesh_voxels). Every
+/// face is a quad of 4 vertices in a row, with 6 indices.
+pub(super) struct FaceQuads {
+    pub(super) vertices: Vec<Vec3>,
+    pub(super) normals: Vec<Vec3>,
+    pub(super) uvs: Vec<[f32; 2]>,
+    pub(super) colors: Vec<[f32; 4]>,
+    pub(super) occlusions: Vec<f32>,
+    pub(super) texture_layers: Vec<u32>,
+    pub(super) indices: Vec<u32>,
+}
+
+/// A face of a single voxel that can be merged with the faces next to it.
+#[derive(Clone, Copy)]
+struct Cell {
+    quad: usize,
+    color: [f32; 4],
+    occlusion: f32,
+}
+
+/// The faces that lie in the same plane, face the same way and have the same texture, so they can be merged. The
+/// plane is given by its axis, whether it faces along or against it, and its position along the axis.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Slice {
+    normal: [i32; 3],
+    depth: i32,
+    texture_layer: u32,
+}
+
+impl FaceQuads {
+    /// Merges neighbouring faces that look alike into larger quads, like greedy meshing, so distant terrain takes far
+    /// fewer triangles. Vertices are scaled up by `scale` like those of the mesh, see
+    /// [generate_lod_mesh](super::generation::VoxelChunk::generate_lod_mesh).
+    ///
+    /// Faces look alike when their colors and ambient occlusion differ by at most `tolerance`, and the merged quad gets
+    /// their average. A tolerance of 0.0 only merges faces that look exactly the same, and loses nothing. Faces with
+    /// occlusion that varies by more than `tolerance` across them, and faces that aren't whole faces of a voxel, like
+    /// lowered tops, are kept as they are.
+    pub(super) fn merge(self, scale: u8, tolerance: f32) -> Self {
+        let scale = scale as f32;
+        let quad_count = self.vertices.len() / 4;
+
+        let mut slices: BTreeMap<Slice, BTreeMap<(i32, i32), Cell>> = BTreeMap::new();
+        let mut kept = Vec::new();
+
+        for quad in 0..quad_count {
+            let first = quad * 4;
+            let corners = &self.vertices[first..first + 4];
+            let normal = self.normals[first].round().as_ivec3();
+            let Some(axis) = (0..3).find(|axis| normal[*axis] != 0) else {
+                kept.push(quad);
+                continue;
+            };
+            // Smoothed normals would be lost in a merged quad.
+            if self.normals[first..first + 4]
+                .iter()
+                .any(|vertex_normal| *vertex_normal != normal.as_vec3())
+            {
+                kept.push(quad);
+                continue;
+            }
+            let (u_axis, v_axis) = plane_axes(axis);
+
+            // Positions in voxels of the mesh, with the corners of voxels on whole numbers.
+            let grid = |vertex: Vec3| (vertex + 0.5) / scale;
+            let min = corners.iter().map(|v| grid(*v)).fold(Vec3::MAX, Vec3::min);
+            let max = corners.iter().map(|v| grid(*v)).fold(Vec3::MIN, Vec3::max);
+
+            let on_grid = |value: f32| (value - value.round()).abs() < GRID_EPSILON;
+            let whole_face = (max[u_axis] - min[u_axis] - 1.0).abs() < GRID_EPSILON
+                && (max[v_axis] - min[v_axis] - 1.0).abs() < GRID_EPSILON
+                && (max[axis] - min[axis]).abs() < GRID_EPSILON
+                && on_grid(min[axis])
+                && on_grid(min[u_axis])
+                && on_grid(min[v_axis]);
+
+            let occlusions = &self.occlusions[first..first + 4];
+            let (lowest, highest) = occlusions
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(lowest, highest), occlusion| {
+                    (lowest.min(*occlusion), highest.max(*occlusion))
+                });
+
+            if !whole_face || highest - lowest > tolerance {
+                kept.push(quad);
+                continue;
+            }
+
+            slices
+                .entry(Slice {
+                    normal: normal.to_array(),
+                    depth: min[axis].round() as i32,
+                    texture_layer: self.texture_layers[first],
+                })
+                .or_default()
+                .insert(
+                    (min[v_axis].round() as i32, min[u_axis].round() as i32),
+                    Cell {
+                        quad,
+                        color: self.colors[first],
+                        occlusion: occlusions.iter().sum::<f32>() / 4.0,
+                    },
+                );
+        }
+
+        let mut merged = FaceQuads {
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            colors: Vec::new(),
+            occlusions: Vec::new(),
+            texture_layers: Vec::new(),
+            indices: Vec::new(),
+        };
+
+        for quad in kept {
+            merged.copy_quad(&self, quad);
+        }
+
+        for (slice, cells) in &slices {
+            let normal = IVec3::from_array(slice.normal);
+            let axis = (0..3).find(|axis| normal[*axis] != 0).unwrap_or_default();
+            let (u_axis, v_axis) = plane_axes(axis);
+            let mut merged_cells = HashSet::new();
+
+            for (&(v, u), seed) in cells {
+                if merged_cells.contains(&(v, u)) {
+                    continue;
+                }
+
+                let fits = |position: (i32, i32)| {
+                    !merged_cells.contains(&position)
+                        && cells
+                            .get(&position)
+                            .is_some_and(|cell| looks_alike(seed, cell, tolerance))
+                };
+
+                let mut width = 1;
+                while fits((v, u + width)) {
+                    width += 1;
+                }
+                let mut height = 1;
+                while (0..width).all(|i| fits((v + height, u + i))) {
+                    height += 1;
+                }
+
+                if width == 1 && height == 1 {
+                    merged_cells.insert((v, u));
+                    merged.copy_quad(&self, seed.quad);
+                    continue;
+                }
+
+                let mut color = Vec4::ZERO;
+                let mut occlusion = 0.0;
+                for position in (0..height).flat_map(|j| (0..width).map(move |i| (v + j, u + i))) {
+                    let cell = cells[&position];
+                    color += Vec4::from_array(cell.color);
+                    occlusion += cell.occlusion;
+                    merged_cells.insert(position);
+                }
+                let count = (width * height) as f32;
+
+                let mut min = IVec3::ZERO;
+                min[axis] = slice.depth;
+                min[u_axis] = u;
+                min[v_axis] = v;
+                let mut size = IVec3::ZERO;
+                size[u_axis] = width;
+                size[v_axis] = height;
+
+                merged.push_quad(
+                    CubeFace::from_ivec3(normal),
+                    min.as_vec3(),
+                    size.as_vec3(),
+                    scale,
+                    (color / count).to_array(),
+                    occlusion / count,
+                    slice.texture_layer,
+                );
+            }
+        }
+
+        merged
+    }
+
+    /// Copies a quad of `other` over as it is.
+    fn copy_quad(&mut self, other: &FaceQuads, quad: usize) {
+        let first = quad * 4;
+        let base = self.vertices.len() as u32;
+
+        self.vertices.extend(&other.vertices[first..first + 4]);
+        self.normals.extend(&other.normals[first..first + 4]);
+        self.uvs.extend(&other.uvs[first..first + 4]);
+        self.colors.extend(&other.colors[first..first + 4]);
+        self.occlusions.extend(&other.occlusions[first..first + 4]);
+        self.texture_layers
+            .extend(&other.texture_layers[first..first + 4]);
+        self.indices.extend(
+            other.indices[quad * 6..quad * 6 + 6]
+                .iter()
+                .map(|index| index - first as u32 + base),
+        );
+    }
+
+    /// Adds a quad covering `size` voxels of the mesh from the voxel corner `min`, with the winding of `face`.
+    fn push_quad(
+        &mut self,
+        face: CubeFace,
+        min: Vec3,
+        size: Vec3,
+        scale: f32,
+        color: [f32; 4],
+        occlusion: f32,
+        texture_layer: u32,
+    ) {
+        self.indices
+            .extend(face.indices(self.vertices.len() as u32));
+
+        for (vertex, normal) in face.vertices().into_iter().zip(face.normals()) {
+            // The corners of a face are half a voxel from its center, so they pick the side of the quad they're on.
+            let corner = min + Vec3::select(vertex.cmpgt(Vec3::ZERO), size, Vec3::ZERO);
+            let position = corner - 0.5;
+
+            self.vertices.push(corner * scale - 0.5);
+            self.normals.push(normal);
+            // Texture coordinates run along the quad, so the texture repeats once for every voxel of the mesh.
+            self.uvs.push(match face {
+                CubeFace::Top | CubeFace::Bottom => [position.x + 0.5, position.z + 0.5],
+                CubeFace::Left | CubeFace::Right => [position.z + 0.5, 0.5 - position.y],
+                CubeFace::Front | CubeFace::Back => [position.x + 0.5, 0.5 - position.y],
+            });
+            self.colors.push(color);
+            self.occlusions.push(occlusion);
+            self.texture_layers.push(texture_layer);
+        }
+    }
+}
+
+/// The two axes spanning the plane of faces along `axis`.
+fn plane_axes(axis: usize) -> (usize, usize) {
+    match axis {
+        0 => (1, 2),
+        1 => (0, 2),
+        _ => (0, 1),
+    }
+}
+
+fn looks_alike(a: &Cell, b: &Cell, tolerance: f32) -> bool {
+    (a.occlusion - b.occlusion).abs() <= tolerance
+        && a.color
+            .iter()
+            .zip(b.color)
+            .all(|(a, b)| (a - b).abs() <= tolerance)
+}
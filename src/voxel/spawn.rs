@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::console::RegisterConsoleCommand;
+
+use super::{
+    health::{Death, Health},
+    noise::TerrainNoise,
+    physics::Velocity,
+};
+
+/// How far above the voxel the player stands in their eyes are, in voxels.
+pub(super) const EYE_HEIGHT: f32 = 1.5;
+
+/// This plugin puts the player at the [WorldSpawn] when the world starts, and brings them back there with full
+/// [Health] when they die. The chunks around the spawn point load like they do around any
+/// [RenderDistance](super::load::RenderDistance), so they're reloaded once the player is back.
+///
+/// The `setspawn` console command moves the spawn point to where the player stands.
+pub(super) struct VoxelSpawnPlugin;
+
+impl Plugin for VoxelSpawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldSpawn>()
+            .register_console_command("setspawn", "Moves the spawn point to where you stand")
+            .add_systems(
+                Update,
+                (
+                    systems::find_world_spawn
+                        .run_if(|spawn: Res<WorldSpawn>| spawn.position.is_none()),
+                    systems::move_new_player_to_spawn,
+                    systems::set_spawn_on_command,
+                    systems::respawn_dead_player,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Where the player starts, and comes back after dying. It's saved with the world.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub(super) struct WorldSpawn {
+    /// The world voxel position the player stands in, or [None] until it's found, see [WorldSpawn::on_surface].
+    position: Option<IVec3>,
+}
+
+impl WorldSpawn {
+    /// The spawn point of a new world: standing on the terrain at the center of the world.
+    fn on_surface(terrain_noise: &TerrainNoise) -> Self {
+        let (height, _) = terrain_noise.surface_height(0, 0);
+        Self {
+            position: Some(IVec3::new(0, height + 1, 0)),
+        }
+    }
+}
+
+/// Where the eyes of a player standing in `voxel_pos` are.
+fn eye_position(voxel_pos: IVec3) -> Vec3 {
+    voxel_pos.as_vec3() + Vec3::Y * EYE_HEIGHT
+}
+
+/// The world voxel position a player with their eyes at `eye_position` stands in.
+fn standing_position(eye_position: Vec3) -> IVec3 {
+    (eye_position - Vec3::Y * EYE_HEIGHT).round().as_ivec3()
+}
+
+mod systems {
+    use crate::console::ConsoleCommand;
+
+    use super::*;
+
+    /// Finds the spawn point of a world that doesn't have one yet. Clients that joined a server don't have the
+    /// [TerrainNoise], so they spawn where they first appeared.
+    pub(super) fn find_world_spawn(
+        mut spawn: ResMut<WorldSpawn>,
+        terrain_noise: Option<Res<TerrainNoise>>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+    ) {
+        if let Some(terrain_noise) = terrain_noise {
+            *spawn = WorldSpawn::on_surface(&terrain_noise);
+        } else if let Ok(transform) = camera_query.get_single() {
+            spawn.position = Some(standing_position(transform.translation));
+        }
+    }
+
+    /// The camera is the player.
+    pub(super) fn move_new_player_to_spawn(
+        spawn: Res<WorldSpawn>,
+        mut camera_query: Query<&mut Transform, Added<Camera3d>>,
+    ) {
+        let Some(position) = spawn.position else {
+            return;
+        };
+
+        for mut transform in &mut camera_query {
+            transform.translation = eye_position(position);
+        }
+    }
+
+    pub(super) fn set_spawn_on_command(
+        mut commands: EventReader<ConsoleCommand>,
+        mut spawn: ResMut<WorldSpawn>,
+        camera_query: Query<&Transform, With<Camera3d>>,
+    ) {
+        if !commands.read().any(|command| command.name == "setspawn") {
+            return;
+        }
+        let Ok(transform) = camera_query.get_single() else {
+            return;
+        };
+
+        let position = standing_position(transform.translation);
+        spawn.position = Some(position);
+        info!("Set the spawn point to {position}");
+    }
+
+    pub(super) fn respawn_dead_player(
+        mut deaths: EventReader<Death>,
+        spawn: Res<WorldSpawn>,
+        mut camera_query: Query<
+            (Entity, &mut Transform, &mut Health, Option<&mut Velocity>),
+            With<Camera3d>,
+        >,
+    ) {
+        let Ok((player, mut transform, mut health, velocity)) = camera_query.get_single_mut()
+        else {
+            return;
+        };
+        if !deaths.read().any(|death| death.entity == player) {
+            return;
+        }
+
+        *health = Health::new(health.max);
+        if let Some(mut velocity) = velocity {
+            velocity.0 = Vec3::ZERO;
+        }
+        if let Some(position) = spawn.position {
+            transform.translation = eye_position(position);
+            info!("Respawned at {position}");
+        }
+    }
+}
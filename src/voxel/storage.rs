@@ -0,0 +1,351 @@
+use bevy::prelude::*;
+
+use super::{
+    generation::{VoxelChunk, VoxelChunkWidth},
+    VoxelConfig,
+};
+
+/// How many voxels wide the bricks of [ChunkStorage::Bricks] are. Chunks whose width isn't a multiple of this get the
+/// widest bricks that fit them evenly instead.
+const BRICK_WIDTH: usize = 8;
+
+/// This plugin moves chunks between dense and sparse storage, following [VoxelConfig::sparse_chunks], and adds or drops
+/// their densities, following [VoxelConfig::density_chunks].
+///
+/// Chunks are always created dense, since that's how they're generated, received and loaded. They're compacted at the
+/// end of the frame they're added or changed in, and when the config changes.
+pub(super) struct ChunkStoragePlugin;
+
+impl Plugin for ChunkStoragePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Last, systems::update_chunk_storage);
+    }
+}
+
+/// How the voxels of a [VoxelChunk] are stored. Both are indexed the same, see
+/// [LocalVoxelPosition](super::generation::LocalVoxelPosition) for how indices map to positions.
+#[derive(Clone, Debug)]
+pub(super) enum ChunkStorage<V> {
+    /// Every voxel in a flat vector. Empty for chunks without voxels.
+    Dense(Vec<V>),
+    /// Bricks of up to 8³ voxels, where a brick of only one kind of voxel, like air or stone, is stored as that voxel. This
+    /// takes a fraction of the memory for chunks that are mostly air or mostly solid, but every access looks up its
+    /// brick first.
+    Bricks {
+        width: usize,
+        brick_width: usize,
+        bricks: Vec<Brick<V>>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub(super) enum Brick<V> {
+    Uniform(V),
+    /// Every voxel of the brick, indexed like a chunk as wide as the brick.
+    Mixed(Box<[V]>),
+}
+
+impl<V: Copy + PartialEq> ChunkStorage<V> {
+    /// How many voxels there are.
+    pub(super) fn len(&self) -> usize {
+        match self {
+            ChunkStorage::Dense(voxels) => voxels.len(),
+            ChunkStorage::Bricks { width, .. } => width.pow(3),
+        }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(super) fn get(&self, index: usize) -> Option<V> {
+        match self {
+            ChunkStorage::Dense(voxels) => voxels.get(index).copied(),
+            ChunkStorage::Bricks {
+                width,
+                brick_width,
+                bricks,
+            } => {
+                if index >= width.pow(3) {
+                    return None;
+                }
+
+                let (brick, offset) = brick_index(index, *width, *brick_width);
+                match &bricks[brick] {
+                    Brick::Uniform(voxel) => Some(*voxel),
+                    Brick::Mixed(voxels) => Some(voxels[offset]),
+                }
+            }
+        }
+    }
+
+    /// Replaces the voxel at `index`, if there is one. A uniform brick is split up when a different voxel is put in it.
+    pub(super) fn set(&mut self, index: usize, voxel: V) {
+        match self {
+            ChunkStorage::Dense(voxels) => {
+                if let Some(old_voxel) = voxels.get_mut(index) {
+                    *old_voxel = voxel;
+                }
+            }
+            ChunkStorage::Bricks {
+                width,
+                brick_width,
+                bricks,
+            } => {
+                if index >= width.pow(3) {
+                    return;
+                }
+
+                let (brick, offset) = brick_index(index, *width, *brick_width);
+                let brick = &mut bricks[brick];
+                if let Brick::Uniform(uniform) = brick {
+                    if *uniform == voxel {
+                        return;
+                    }
+                    *brick = Brick::Mixed(vec![*uniform; brick_width.pow(3)].into_boxed_slice());
+                }
+                if let Brick::Mixed(voxels) = brick {
+                    voxels[offset] = voxel;
+                }
+            }
+        }
+    }
+
+    /// Every voxel, in the order of their indices.
+    pub(super) fn iter(&self) -> impl Iterator<Item = V> + '_ {
+        (0..self.len()).map(|i| self.get(i).expect("the index is in the chunk"))
+    }
+
+    /// Every voxel in a flat vector, like [ChunkStorage::Dense] holds them.
+    pub(super) fn to_vec(&self) -> Vec<V> {
+        match self {
+            ChunkStorage::Dense(voxels) => voxels.clone(),
+            ChunkStorage::Bricks { .. } => self.iter().collect(),
+        }
+    }
+
+    /// Moves the voxels into bricks, of a chunk `width` voxels wide, and merges bricks that became uniform since.
+    pub(super) fn compact(&mut self, width: usize) {
+        if self.is_empty() {
+            return;
+        }
+
+        match self {
+            ChunkStorage::Dense(voxels) => {
+                // `usize::is_multiple_of` needs a newer Rust than the game supports.
+                #[allow(unknown_lints, clippy::manual_is_multiple_of)]
+                let brick_width = (1..=BRICK_WIDTH)
+                    .rev()
+                    .find(|brick_width| width % brick_width == 0)
+                    .expect("every width is a multiple of 1");
+                let bricks_wide = width / brick_width;
+                let bricks = (0..bricks_wide.pow(3))
+                    .map(|brick| {
+                        let brick_voxels: Vec<V> = (0..brick_width.pow(3))
+                            .map(|offset| voxels[voxel_index(brick, offset, width, brick_width)])
+                            .collect();
+                        Brick::new(brick_voxels)
+                    })
+                    .collect();
+
+                *self = ChunkStorage::Bricks {
+                    width,
+                    brick_width,
+                    bricks,
+                };
+            }
+            ChunkStorage::Bricks { bricks, .. } => {
+                for brick in bricks {
+                    if let Brick::Mixed(voxels) = brick {
+                        if voxels.iter().all(|voxel| *voxel == voxels[0]) {
+                            *brick = Brick::Uniform(voxels[0]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Moves the voxels back into a flat vector.
+    pub(super) fn expand(&mut self) {
+        if let ChunkStorage::Bricks { .. } = self {
+            *self = ChunkStorage::Dense(self.to_vec());
+        }
+    }
+
+    /// How many bytes the voxels take up on the heap.
+    pub(super) fn heap_size(&self) -> usize {
+        let voxel_size = std::mem::size_of::<V>();
+        match self {
+            ChunkStorage::Dense(voxels) => voxels.capacity() * voxel_size,
+            ChunkStorage::Bricks { bricks, .. } => bricks
+                .iter()
+                .map(|brick| {
+                    std::mem::size_of::<Brick<V>>()
+                        + match brick {
+                            Brick::Uniform(_) => 0,
+                            Brick::Mixed(voxels) => voxels.len() * voxel_size,
+                        }
+                })
+                .sum(),
+        }
+    }
+}
+
+impl<V: Copy + PartialEq> Brick<V> {
+    fn new(voxels: Vec<V>) -> Self {
+        if voxels.iter().all(|voxel| *voxel == voxels[0]) {
+            Brick::Uniform(voxels[0])
+        } else {
+            Brick::Mixed(voxels.into_boxed_slice())
+        }
+    }
+}
+
+/// The brick a voxel of the chunk is in, and its index in the brick.
+fn brick_index(index: usize, width: usize, brick_width: usize) -> (usize, usize) {
+    let (x, y, z) = (
+        index % width,
+        index / width % width,
+        index / (width * width),
+    );
+    let bricks_wide = width / brick_width;
+
+    let brick =
+        x / brick_width + (y / brick_width) * bricks_wide + (z / brick_width) * bricks_wide.pow(2);
+    let offset =
+        x % brick_width + (y % brick_width) * brick_width + (z % brick_width) * brick_width.pow(2);
+    (brick, offset)
+}
+
+/// The index in the chunk of a voxel of a brick, the other way around from [brick_index].
+fn voxel_index(brick: usize, offset: usize, width: usize, brick_width: usize) -> usize {
+    let bricks_wide = width / brick_width;
+    let x = brick % bricks_wide * brick_width + offset % brick_width;
+    let y = brick / bricks_wide % bricks_wide * brick_width + offset / brick_width % brick_width;
+    let z = brick / bricks_wide.pow(2) * brick_width + offset / brick_width.pow(2);
+    x + y * width + z * width * width
+}
+
+mod systems {
+    use super::*;
+
+    /// Compacts chunks that were added or changed while sparse storage is turned on, and expands them again once it's
+    /// turned off. Chunks without densities get them while density storage is turned on. Neither changes the voxels,
+    /// so it doesn't count as a change of the chunk.
+    pub(super) fn update_chunk_storage(
+        config: Res<VoxelConfig>,
+        chunk_width: Res<VoxelChunkWidth>,
+        mut chunk_query: Query<&mut VoxelChunk>,
+    ) {
+        for mut chunk in &mut chunk_query {
+            if !config.is_changed() && !chunk.is_changed() {
+                continue;
+            }
+
+            let chunk = chunk.bypass_change_detection();
+            chunk.set_densities(config.density_chunks, &chunk_width);
+            chunk.set_sparse(config.sparse_chunks, &chunk_width);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 16;
+
+    /// A chunk where every voxel is its own index, so no two bricks are alike.
+    fn indexed_voxels(width: usize) -> Vec<u32> {
+        (0..width.pow(3) as u32).collect()
+    }
+
+    #[test]
+    fn bricks_round_trip_every_voxel() {
+        for width in [WIDTH, 12, 5] {
+            let voxels = indexed_voxels(width);
+            let mut storage = ChunkStorage::Dense(voxels.clone());
+            storage.compact(width);
+
+            assert!(matches!(storage, ChunkStorage::Bricks { .. }));
+            assert_eq!(storage.to_vec(), voxels, "width {width}");
+            for (index, voxel) in voxels.iter().enumerate() {
+                assert_eq!(
+                    storage.get(index),
+                    Some(*voxel),
+                    "index {index} of width {width}"
+                );
+            }
+            assert_eq!(storage.get(voxels.len()), None);
+        }
+    }
+
+    #[test]
+    fn uneven_widths_get_the_widest_bricks_that_fit() {
+        for (width, expected_brick_width) in [(WIDTH, 8), (12, 6), (5, 5), (7, 7)] {
+            let mut storage = ChunkStorage::Dense(indexed_voxels(width));
+            storage.compact(width);
+
+            let ChunkStorage::Bricks { brick_width, .. } = storage else {
+                panic!("width {width} wasn't compacted");
+            };
+            assert_eq!(brick_width, expected_brick_width, "width {width}");
+        }
+    }
+
+    #[test]
+    fn set_voxels_across_brick_boundaries() {
+        let mut dense = indexed_voxels(WIDTH);
+        let mut storage = ChunkStorage::Dense(dense.clone());
+        storage.compact(WIDTH);
+
+        // The voxels on either side of the brick boundaries on every axis, and the corners of the chunk.
+        let positions = [7, 8, 15]
+            .into_iter()
+            .flat_map(|x| [0, 7, 8].into_iter().map(move |y| (x, y)))
+            .flat_map(|(x, y)| [7, 8, 15].into_iter().map(move |z| (x, y, z)));
+        for (x, y, z) in positions {
+            let index = x + y * WIDTH + z * WIDTH * WIDTH;
+            dense[index] = u32::MAX - index as u32;
+            storage.set(index, dense[index]);
+        }
+
+        assert_eq!(storage.to_vec(), dense);
+    }
+
+    #[test]
+    fn uniform_bricks_are_split_and_merged_again() {
+        let mut storage = ChunkStorage::Dense(vec![0_u32; WIDTH.pow(3)]);
+        let dense_size = storage.heap_size();
+        storage.compact(WIDTH);
+
+        let uniform = |storage: &ChunkStorage<u32>| match storage {
+            ChunkStorage::Bricks { bricks, .. } => bricks
+                .iter()
+                .filter(|brick| matches!(brick, Brick::Uniform(_)))
+                .count(),
+            ChunkStorage::Dense(_) => 0,
+        };
+        assert_eq!(uniform(&storage), 8);
+        assert!(storage.heap_size() < dense_size);
+
+        // Setting a voxel to what it already is leaves the brick uniform.
+        storage.set(0, 0);
+        assert_eq!(uniform(&storage), 8);
+
+        storage.set(9, 1);
+        assert_eq!(uniform(&storage), 7);
+        assert_eq!(storage.get(9), Some(1));
+        assert_eq!(storage.get(8), Some(0));
+
+        storage.set(9, 0);
+        storage.compact(WIDTH);
+        assert_eq!(uniform(&storage), 8);
+
+        storage.expand();
+        assert!(
+            matches!(&storage, ChunkStorage::Dense(voxels) if voxels == &vec![0; WIDTH.pow(3)])
+        );
+    }
+}
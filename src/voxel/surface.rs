@@ -0,0 +1,255 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use noise::{NoiseFn, Simplex};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    biome::{Biome, BiomeSettings},
+    Voxel,
+};
+
+/// How far below the surface the rules apply, in voxels. Everything deeper stays stone.
+pub(super) const MAX_SURFACE_DEPTH: i32 = 8;
+
+/// The rules picking the voxels at the surface of the terrain, after its shape is generated. For every voxel near the
+/// surface, the first rule whose conditions all match picks what it becomes. Voxels no rule matches stay stone.
+///
+/// The rules are loaded from a RON file by the [VoxelTerrainNoisePlugin](super::noise::VoxelTerrainNoisePlugin), like
+/// the [NoiseLayer](super::noise_layer::NoiseLayer)s. For example, snow on everything above 50, and grass elsewhere:
+///
+/// ```ron
+/// [
+///     (when: [Top, Above(50)], then: Snow),
+///     (when: [Top], then: Grass),
+///     (when: [Filler], then: Dirt),
+/// ]
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub(super) struct SurfaceRules(pub(super) Vec<SurfaceRule>);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct SurfaceRule {
+    /// Every condition has to match. No conditions always match.
+    pub(super) when: Vec<SurfaceCondition>,
+    pub(super) then: SurfaceBlock,
+}
+
+/// Something about a voxel near the surface a [SurfaceRule] can check.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) enum SurfaceCondition {
+    /// The voxel is the top one, with nothing solid right above it.
+    Top,
+    /// The voxel is within the filler depth of its biome, counting the top one.
+    Filler,
+    /// The voxel is at most this many voxels below the top one.
+    Depth(i32),
+    /// The voxel is at or above this height.
+    Above(i32),
+    /// The voxel is below this height.
+    Below(i32),
+    /// The voxel is at most `below` voxels below the sea level, and at most `above` voxels above it. Never matches in
+    /// worlds without a sea.
+    NearSea { below: i32, above: i32 },
+    /// The surface is covered by water, like at the bottom of the sea or a river.
+    Underwater,
+    /// The surface is at least this steep, in how many voxels it rises per voxel sideways.
+    Slope(f64),
+    /// The voxel is in one of these biomes.
+    Biome(Vec<Biome>),
+    /// A noise over the columns of the world is at least `min` at the voxel. Higher `min` makes smaller patches, and
+    /// lower `frequency` larger ones.
+    Noise { frequency: f64, min: f64 },
+    /// The condition doesn't match.
+    Not(Box<SurfaceCondition>),
+    /// Any of the conditions match.
+    Any(Vec<SurfaceCondition>),
+}
+
+/// What a [SurfaceRule] turns a voxel into.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SurfaceBlock {
+    Stone,
+    Dirt,
+    Grass,
+    Sand,
+    Gravel,
+    Snow,
+    /// The [surface](BiomeSettings::surface) voxel of the biome.
+    BiomeSurface,
+    /// The [filler](BiomeSettings::filler) voxel of the biome.
+    BiomeFiller,
+}
+
+impl SurfaceBlock {
+    fn voxel(self, settings: &BiomeSettings) -> Voxel {
+        match self {
+            SurfaceBlock::Stone => Voxel::STONE,
+            SurfaceBlock::Dirt => Voxel::DIRT,
+            SurfaceBlock::Grass => Voxel::GRASS,
+            SurfaceBlock::Sand => Voxel::SAND,
+            SurfaceBlock::Gravel => Voxel::GRAVEL,
+            SurfaceBlock::Snow => Voxel::SNOW,
+            SurfaceBlock::BiomeSurface => settings.surface,
+            SurfaceBlock::BiomeFiller => settings.filler,
+        }
+    }
+}
+
+/// Everything the [SurfaceCondition]s check about a voxel.
+pub(super) struct SurfaceContext<'a> {
+    pub(super) x: i32,
+    pub(super) y: i32,
+    pub(super) z: i32,
+    /// How far below the top voxel of its surface the voxel is, 0 for the top voxel itself.
+    pub(super) depth: i32,
+    pub(super) filler_depth: i32,
+    pub(super) biome: Biome,
+    pub(super) sea_level: Option<i32>,
+    pub(super) underwater: bool,
+    pub(super) slope: f64,
+    pub(super) noise: &'a Simplex,
+}
+
+impl SurfaceCondition {
+    fn matches(&self, context: &SurfaceContext) -> bool {
+        match self {
+            SurfaceCondition::Top => context.depth == 0,
+            SurfaceCondition::Filler => context.depth < context.filler_depth,
+            SurfaceCondition::Depth(depth) => context.depth <= *depth,
+            SurfaceCondition::Above(height) => context.y >= *height,
+            SurfaceCondition::Below(height) => context.y < *height,
+            SurfaceCondition::NearSea { below, above } => {
+                context.sea_level.is_some_and(|sea_level| {
+                    (sea_level - below..=sea_level + above).contains(&context.y)
+                })
+            }
+            SurfaceCondition::Underwater => context.underwater,
+            SurfaceCondition::Slope(slope) => context.slope >= *slope,
+            SurfaceCondition::Biome(biomes) => biomes.contains(&context.biome),
+            SurfaceCondition::Noise { frequency, min } => {
+                let point = [context.x as f64 * frequency, context.z as f64 * frequency];
+                context.noise.get(point) >= *min
+            }
+            SurfaceCondition::Not(condition) => !condition.matches(context),
+            SurfaceCondition::Any(conditions) => conditions
+                .iter()
+                .any(|condition| condition.matches(context)),
+        }
+    }
+}
+
+impl SurfaceRules {
+    /// The voxel the first matching rule picks, or [None] if no rule matches.
+    pub(super) fn pick(&self, context: &SurfaceContext, settings: &BiomeSettings) -> Option<Voxel> {
+        self.0
+            .iter()
+            .find(|rule| rule.when.iter().all(|condition| condition.matches(context)))
+            .map(|rule| rule.then.voxel(settings))
+    }
+
+    /// Whether any rule checks the slope, which is expensive to find.
+    pub(super) fn uses_slope(&self) -> bool {
+        fn uses_slope(condition: &SurfaceCondition) -> bool {
+            match condition {
+                SurfaceCondition::Slope(_) => true,
+                SurfaceCondition::Not(condition) => uses_slope(condition),
+                SurfaceCondition::Any(conditions) => conditions.iter().any(uses_slope),
+                _ => false,
+            }
+        }
+
+        self.0.iter().flat_map(|rule| &rule.when).any(uses_slope)
+    }
+
+    /// Loads the rules from `path`, writing the default rules to it if it doesn't exist yet, so they can be edited.
+    /// Falls back to the default rules if the file is invalid.
+    pub(super) fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let Ok(contents) = fs::read_to_string(path) else {
+            let rules = Self::default();
+            rules.save(path);
+            return rules;
+        };
+
+        ron::from_str(&contents).unwrap_or_else(|err| {
+            warn!("Failed to parse {}: {err}", path.display());
+            Self::default()
+        })
+    }
+
+    fn save(&self, path: &Path) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                error!("Failed to serialize the surface rules: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = fs::write(path, contents) {
+            error!("Failed to write {}: {err}", path.display());
+        }
+    }
+}
+
+impl Default for SurfaceRules {
+    /// Bare rock on cliffs, snow on peaks, sand and gravel under water and along the shore, and the surface of the
+    /// biome everywhere else.
+    fn default() -> Self {
+        let rule = |when: Vec<SurfaceCondition>, then: SurfaceBlock| SurfaceRule { when, then };
+
+        Self(vec![
+            rule(vec![SurfaceCondition::Slope(1.5)], SurfaceBlock::Stone),
+            rule(
+                vec![SurfaceCondition::Top, SurfaceCondition::Above(64)],
+                SurfaceBlock::Snow,
+            ),
+            // The highlands are cold enough for snow further down.
+            rule(
+                vec![
+                    SurfaceCondition::Top,
+                    SurfaceCondition::Above(40),
+                    SurfaceCondition::Biome(vec![Biome::Highlands]),
+                ],
+                SurfaceBlock::Snow,
+            ),
+            rule(
+                vec![
+                    SurfaceCondition::Underwater,
+                    SurfaceCondition::Filler,
+                    SurfaceCondition::Noise {
+                        frequency: 0.04,
+                        min: 0.2,
+                    },
+                ],
+                SurfaceBlock::Gravel,
+            ),
+            rule(
+                vec![SurfaceCondition::Underwater, SurfaceCondition::Filler],
+                SurfaceBlock::Sand,
+            ),
+            rule(
+                vec![
+                    SurfaceCondition::NearSea { below: 3, above: 2 },
+                    SurfaceCondition::Filler,
+                ],
+                SurfaceBlock::Sand,
+            ),
+            rule(
+                vec![
+                    SurfaceCondition::Top,
+                    SurfaceCondition::Above(24),
+                    SurfaceCondition::Noise {
+                        frequency: 0.06,
+                        min: 0.5,
+                    },
+                ],
+                SurfaceBlock::Gravel,
+            ),
+            rule(vec![SurfaceCondition::Top], SurfaceBlock::BiomeSurface),
+            rule(vec![SurfaceCondition::Filler], SurfaceBlock::BiomeFiller),
+        ])
+    }
+}
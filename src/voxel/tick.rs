@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, utils::HashSet};
+
+use super::VoxelConfig;
+
+/// How many game ticks happen every second. Block ticks run in [FixedUpdate], at this rate.
+const TICKS_PER_SECOND: f64 = 20.0;
+
+/// This plugin is responsible for ticking voxels. This is what drives world simulation, like fluids.
+///
+/// Voxels can either request a tick a number of game ticks later through the [BlockTickScheduler],
+/// or be picked at random, as every loaded chunk gets [VoxelConfig::random_ticks_per_chunk] random ticks every game
+/// tick.
+/// Ticks are sent as [BlockTick] events, which systems react to after the [BlockTickSet].
+pub(super) struct VoxelTickPlugin;
+
+impl Plugin for VoxelTickPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_hz(TICKS_PER_SECOND))
+            .init_resource::<BlockTickScheduler>()
+            .add_event::<BlockTick>()
+            .add_systems(
+                FixedUpdate,
+                (systems::scheduled_block_ticks, systems::random_block_ticks)
+                    .chain()
+                    .in_set(BlockTickSet),
+            );
+    }
+}
+
+/// The systems sending [BlockTick] events. Systems reacting to block ticks should run after this set, in [FixedUpdate].
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct BlockTickSet;
+
+/// Event sent when a voxel is ticked.
+#[derive(Event, Debug, Clone, Copy)]
+pub(super) struct BlockTick {
+    /// The world voxel position of the ticked voxel.
+    pub(super) voxel_pos: IVec3,
+    pub(super) kind: BlockTickKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum BlockTickKind {
+    /// The tick was requested through [BlockTickScheduler::schedule].
+    Scheduled,
+    /// The voxel was picked at random. Only solid voxels get random ticks.
+    Random,
+}
+
+/// Keeps track of the current game tick, and the ticks voxels have requested.
+#[derive(Resource, Default)]
+pub(super) struct BlockTickScheduler {
+    /// The amount of game ticks that have passed.
+    current_tick: u64,
+    /// Voxel positions to tick, keyed by the game tick they should be ticked at.
+    scheduled: BTreeMap<u64, HashSet<IVec3>>,
+}
+
+impl BlockTickScheduler {
+    /// Requests a tick of the voxel at `voxel_pos`, `delay` game ticks from now.
+    /// A delay of 0 ticks the voxel on the next game tick.
+    ///
+    /// Scheduling the same voxel for the same tick multiple times only ticks it once.
+    pub(super) fn schedule(&mut self, voxel_pos: IVec3, delay: u64) {
+        let tick = self.current_tick + delay.max(1);
+        self.scheduled.entry(tick).or_default().insert(voxel_pos);
+    }
+
+    /// Schedules a tick for every direct neighbour of `voxel_pos`. This should be called when a voxel changes,
+    /// so the voxels around it can react.
+    pub(super) fn schedule_neighbours(&mut self, voxel_pos: IVec3, delay: u64) {
+        for neighbour in super::cube_mesh::DIRECT_CUBE_NEIGHBOURS {
+            self.schedule(voxel_pos + neighbour, delay);
+        }
+    }
+}
+
+mod systems {
+    use rand::Rng;
+
+    use crate::voxel::generation::{
+        LocalVoxelPosition, VoxelChunk, VoxelChunkPosition, VoxelChunkWidth,
+    };
+
+    use super::*;
+
+    /// Advances the game tick, and sends the ticks that were scheduled for it.
+    pub(super) fn scheduled_block_ticks(
+        mut scheduler: ResMut<BlockTickScheduler>,
+        mut block_ticks: EventWriter<BlockTick>,
+    ) {
+        let _span = info_span!("scheduled_block_ticks").entered();
+
+        scheduler.current_tick += 1;
+        let current_tick = scheduler.current_tick;
+
+        while let Some(entry) = scheduler.scheduled.first_entry() {
+            if *entry.key() > current_tick {
+                break;
+            }
+
+            block_ticks.send_batch(entry.remove().into_iter().map(|voxel_pos| BlockTick {
+                voxel_pos,
+                kind: BlockTickKind::Scheduled,
+            }));
+        }
+    }
+
+    pub(super) fn random_block_ticks(
+        config: Res<VoxelConfig>,
+        mut block_ticks: EventWriter<BlockTick>,
+        chunk_query: Query<(&VoxelChunk, &VoxelChunkPosition)>,
+        chunk_width: Res<VoxelChunkWidth>,
+    ) {
+        let _span = info_span!("random_block_ticks").entered();
+
+        let mut rng = rand::thread_rng();
+        let voxel_count = chunk_width.0 as usize * chunk_width.0 as usize * chunk_width.0 as usize;
+
+        for (chunk, chunk_pos) in &chunk_query {
+            for _ in 0..config.random_ticks_per_chunk {
+                let local_pos =
+                    LocalVoxelPosition::from_index(rng.gen_range(0..voxel_count), &chunk_width);
+
+                if !chunk
+                    .get_voxel(local_pos, &chunk_width)
+                    .is_some_and(|voxel| voxel.is_solid())
+                {
+                    continue;
+                }
+
+                block_ticks.send(BlockTick {
+                    voxel_pos: chunk_pos.local_to_world(local_pos, &chunk_width),
+                    kind: BlockTickKind::Random,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use bevy::render::color::Color;
+use serde::{Deserialize, Serialize};
+
+use super::{registry::BlockTag, Voxel};
+
+/// The names of every tool, by [ToolTier] and then [ToolKind].
+const TOOL_NAMES: [[&str; 3]; 3] = [
+    ["wooden_pickaxe", "wooden_shovel", "wooden_axe"],
+    ["stone_pickaxe", "stone_shovel", "stone_axe"],
+    ["obsidian_pickaxe", "obsidian_shovel", "obsidian_axe"],
+];
+
+/// An item that mines the blocks of its [ToolKind] faster, and wears out a little with every block it breaks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(super) struct Tool {
+    pub(super) kind: ToolKind,
+    pub(super) tier: ToolTier,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(super) enum ToolKind {
+    Pickaxe,
+    Shovel,
+    Axe,
+}
+
+/// What a tool is made of. Better materials mine faster and last longer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(super) enum ToolTier {
+    Wooden,
+    Stone,
+    Obsidian,
+}
+
+impl Tool {
+    const KINDS: [ToolKind; 3] = [ToolKind::Pickaxe, ToolKind::Shovel, ToolKind::Axe];
+    const TIERS: [ToolTier; 3] = [ToolTier::Wooden, ToolTier::Stone, ToolTier::Obsidian];
+
+    /// The tool with the given [name](Tool::name), if there is one.
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        Self::TIERS
+            .into_iter()
+            .flat_map(|tier| Self::KINDS.map(|kind| Tool { kind, tier }))
+            .find(|tool| tool.name() == name)
+    }
+
+    pub(super) fn name(&self) -> &'static str {
+        TOOL_NAMES[self.tier as usize][self.kind as usize]
+    }
+
+    /// How many times faster the tool mines a voxel than an empty hand.
+    pub(super) fn mining_speed(&self, voxel: Voxel) -> f32 {
+        if voxel.definition().has_tag(self.kind.tag()) {
+            self.tier.speed()
+        } else {
+            1.0
+        }
+    }
+}
+
+impl ToolKind {
+    /// The tag of the blocks the tool mines faster.
+    fn tag(&self) -> BlockTag {
+        match self {
+            ToolKind::Pickaxe => BlockTag::Rock,
+            ToolKind::Shovel => BlockTag::Soil,
+            ToolKind::Axe => BlockTag::Wood,
+        }
+    }
+}
+
+impl ToolTier {
+    fn speed(&self) -> f32 {
+        match self {
+            ToolTier::Wooden => 2.0,
+            ToolTier::Stone => 4.0,
+            ToolTier::Obsidian => 8.0,
+        }
+    }
+
+    /// How many blocks a tool of the tier breaks before it's worn out.
+    pub(super) fn durability(&self) -> u16 {
+        match self {
+            ToolTier::Wooden => 60,
+            ToolTier::Stone => 130,
+            ToolTier::Obsidian => 1500,
+        }
+    }
+
+    /// The color tools of the tier are shown with, which is the color of the block they're made of.
+    pub(super) fn color(&self) -> Color {
+        match self {
+            ToolTier::Wooden => Voxel::WOOD,
+            ToolTier::Stone => Voxel::STONE,
+            ToolTier::Obsidian => Voxel::OBSIDIAN,
+        }
+        .color()
+    }
+}
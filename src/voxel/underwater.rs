@@ -0,0 +1,123 @@
+use bevy::{prelude::*, render::render_resource::Face};
+
+use super::{
+    chunk_material::ChunkMaterial, generation::ChunkMeshSection, render::ChunkMaterials,
+    world::VoxelWorld, Voxel,
+};
+
+/// The color laid over the view under water, on top of the fog.
+const UNDERWATER_TINT: Color = Color::rgba(0.0, 0.15, 0.3, 0.25);
+/// How far the camera sees under water, in voxels. Nothing further is drawn, since the fog hides it anyway.
+const UNDERWATER_VIEW_DISTANCE: f32 = 48.0;
+
+/// This plugin changes how the world looks while the camera is under water, like below the sea level or in a river.
+///
+/// The view is tinted blue and doesn't reach as far, and the water surface is drawn from below as well, since usually
+/// the back faces of the transparent voxels are culled, which would hide the surface from below. The fog is turned blue
+/// by the [VoxelFogPlugin](super::fog::VoxelFogPlugin), and the sounds are muffled by the
+/// [VoxelAudioPlugin](super::audio::VoxelAudioPlugin), which both read the [CameraMedium].
+pub(super) struct VoxelUnderwaterPlugin;
+
+impl Plugin for VoxelUnderwaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraMedium>()
+            .add_systems(Startup, systems::spawn_underwater_tint)
+            .add_systems(
+                Update,
+                (
+                    systems::detect_camera_medium,
+                    systems::apply_underwater_effects,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// What the camera is in, detected every frame from the voxel at its position.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum CameraMedium {
+    #[default]
+    Air,
+    Water,
+}
+
+/// Marker component for the overlay tinting the view under water.
+#[derive(Component)]
+struct UnderwaterTint;
+
+mod systems {
+    use super::*;
+
+    pub(super) fn spawn_underwater_tint(mut commands: Commands) {
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                background_color: UNDERWATER_TINT.into(),
+                visibility: Visibility::Hidden,
+                // Below every other UI node, so the hotbar isn't tinted.
+                z_index: ZIndex::Global(-1),
+                ..default()
+            },
+            UnderwaterTint,
+        ));
+    }
+
+    /// Looks up the voxel the camera is in. Chunks that aren't loaded count as air.
+    pub(super) fn detect_camera_medium(
+        camera_query: Query<&Transform, With<Camera3d>>,
+        voxel_world: VoxelWorld,
+        mut camera_medium: ResMut<CameraMedium>,
+    ) {
+        let Ok(transform) = camera_query.get_single() else {
+            return;
+        };
+
+        let medium = match voxel_world.get_block(transform.translation.round().as_ivec3()) {
+            Some(voxel) if voxel.id == Voxel::WATER.id => CameraMedium::Water,
+            _ => CameraMedium::Air,
+        };
+        camera_medium.set_if_neq(medium);
+    }
+
+    pub(super) fn apply_underwater_effects(
+        camera_medium: Res<CameraMedium>,
+        mut camera_query: Query<&mut Projection, With<Camera3d>>,
+        mut tint_query: Query<&mut Visibility, With<UnderwaterTint>>,
+        chunk_materials: Res<ChunkMaterials>,
+        mut materials: ResMut<Assets<ChunkMaterial>>,
+    ) {
+        if !camera_medium.is_changed() {
+            return;
+        }
+        let underwater = *camera_medium == CameraMedium::Water;
+
+        if let Some(material) =
+            materials.get_mut(chunk_materials.get(ChunkMeshSection::Transparent))
+        {
+            material.cull_mode = if underwater { None } else { Some(Face::Back) };
+        }
+
+        for mut visibility in &mut tint_query {
+            *visibility = if underwater {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+
+        if let Ok(mut projection) = camera_query.get_single_mut() {
+            if let Projection::Perspective(perspective) = projection.as_mut() {
+                perspective.far = if underwater {
+                    UNDERWATER_VIEW_DISTANCE
+                } else {
+                    PerspectiveProjection::default().far
+                };
+            }
+        }
+    }
+}
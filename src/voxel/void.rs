@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+
+use super::{load::RenderDistance, noise::TerrainNoise, world::VoxelWorld};
+
+/// How far above the bedrock floor to look for room for a player, in voxels.
+const MAX_CLIMB: i32 = 256;
+
+/// This plugin keeps players out of the bedrock floor. A player that ends up in or below it, like by flying through
+/// it, is teleported back on top of it, to the first spot with room for them.
+///
+/// Worlds without bedrock are bottomless, so players can go as deep as they want there.
+pub(super) struct VoxelVoidPlugin;
+
+impl Plugin for VoxelVoidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, systems::teleport_out_of_bedrock);
+    }
+}
+
+/// The lowest world voxel position above `bedrock_level` in the column of `voxel_pos`, with room for a player: the
+/// voxel and the one above it aren't solid. Unloaded voxels count as room, as there's nothing to check them with.
+fn find_room_above(voxel_world: &VoxelWorld, voxel_pos: IVec3, bedrock_level: i32) -> IVec3 {
+    let is_free = |y: i32| {
+        !voxel_world
+            .get_block(IVec3::new(voxel_pos.x, y, voxel_pos.z))
+            .is_some_and(|voxel| voxel.is_solid())
+    };
+
+    let y = (bedrock_level + 1..bedrock_level + MAX_CLIMB)
+        .find(|&y| is_free(y) && is_free(y + 1))
+        .unwrap_or(bedrock_level + MAX_CLIMB);
+
+    IVec3::new(voxel_pos.x, y, voxel_pos.z)
+}
+
+mod systems {
+    use super::*;
+
+    /// Every entity with a [RenderDistance] is a player, on the server as well.
+    pub(super) fn teleport_out_of_bedrock(
+        mut player_query: Query<&mut Transform, With<RenderDistance>>,
+        terrain_noise: Res<TerrainNoise>,
+        voxel_world: VoxelWorld,
+    ) {
+        let Some(bedrock_level) = terrain_noise.bedrock_level() else {
+            return;
+        };
+
+        for mut transform in &mut player_query {
+            let voxel_pos = transform.translation.round().as_ivec3();
+            if voxel_pos.y > bedrock_level {
+                continue;
+            }
+
+            let room = find_room_above(&voxel_world, voxel_pos, bedrock_level);
+            // The translation is the eye, so it goes in the center of the upper voxel of the room.
+            transform.translation.y = room.y as f32 + 1.0;
+            info!(
+                "Teleported a player at {} out of the bedrock, to y {}",
+                voxel_pos, room.y
+            );
+        }
+    }
+}
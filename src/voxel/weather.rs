@@ -0,0 +1,209 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input::InputMap;
+
+use super::tick::BlockTickSet;
+
+/// How long the weather lasts before it changes, in seconds.
+const WEATHER_DURATION: Range<f32> = 120.0..300.0;
+/// Snow layers can stack this high, at which point they're a full voxel.
+const MAX_SNOW_LAYERS: u8 = 8;
+/// How far up a voxel checks for something covering it, before snow can land on it.
+const SNOW_SKY_CHECK_HEIGHT: i32 = 32;
+/// The chance for a snow layer to melt on a random tick, when it isn't snowing.
+const SNOW_MELT_CHANCE: f64 = 0.2;
+
+/// This plugin is responsible for the [Weather]. The weather changes every few minutes. Rain and snow particles are
+/// up to the [VoxelPrecipitationPlugin](super::precipitation::VoxelPrecipitationPlugin).
+///
+/// Snow piles up on exposed surfaces as thin [snow layers](super::Voxel::SNOW_LAYER) on random block ticks,
+/// and melts again once it stops snowing.
+pub(super) struct VoxelWeatherPlugin;
+
+impl Plugin for VoxelWeatherPlugin {
+    fn build(&self, app: &mut App) {
+        // The precipitation plugin needs the weather state as well.
+        if !app.world.contains_resource::<State<Weather>>() {
+            app.add_state::<Weather>();
+        }
+
+        app.init_resource::<WeatherTimer>()
+            .add_systems(
+                Update,
+                (
+                    systems::change_weather,
+                    // The dedicated server has no input.
+                    systems::cycle_weather.run_if(resource_exists::<InputMap>()),
+                ),
+            )
+            .add_systems(FixedUpdate, systems::accumulate_snow.after(BlockTickSet));
+    }
+}
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub(crate) enum Weather {
+    #[default]
+    Clear,
+    Rain,
+    Snow,
+}
+
+impl Weather {
+    const ALL: [Weather; 3] = [Weather::Clear, Weather::Rain, Weather::Snow];
+
+    /// How much the clouds darken the sky and light, from 0.0 for a clear sky to 1.0 for complete darkness.
+    pub(crate) fn overcast(&self) -> f32 {
+        match self {
+            Weather::Clear => 0.0,
+            Weather::Rain => 0.6,
+            Weather::Snow => 0.3,
+        }
+    }
+
+    /// How hard it rains, from 0.0 for no rain to 1.0 for a downpour.
+    pub(crate) fn rain_intensity(&self) -> f32 {
+        match self {
+            Weather::Rain => 1.0,
+            Weather::Clear | Weather::Snow => 0.0,
+        }
+    }
+}
+
+/// How high a snow layer is drawn, based on how many layers it has.
+pub(super) fn snow_layer_height(layers: u8) -> f32 {
+    layers.min(MAX_SNOW_LAYERS) as f32 / MAX_SNOW_LAYERS as f32
+}
+
+/// Counts down until the weather changes.
+#[derive(Resource)]
+struct WeatherTimer(Timer);
+
+impl Default for WeatherTimer {
+    fn default() -> Self {
+        Self(random_weather_timer())
+    }
+}
+
+fn random_weather_timer() -> Timer {
+    use rand::Rng;
+
+    Timer::from_seconds(
+        rand::thread_rng().gen_range(WEATHER_DURATION),
+        TimerMode::Once,
+    )
+}
+
+mod systems {
+    use rand::{seq::SliceRandom, Rng};
+
+    use crate::{
+        input::{ActionInput, InputAction},
+        voxel::{
+            tick::{BlockTick, BlockTickKind},
+            world::VoxelWorld,
+            Voxel,
+        },
+    };
+
+    use super::*;
+
+    /// Picks a different random weather once the [WeatherTimer] runs out.
+    pub(super) fn change_weather(
+        time: Res<Time>,
+        mut timer: ResMut<WeatherTimer>,
+        weather: Res<State<Weather>>,
+        mut next_weather: ResMut<NextState<Weather>>,
+    ) {
+        if !timer.0.tick(time.delta()).finished() {
+            return;
+        }
+
+        let others: Vec<Weather> = Weather::ALL
+            .into_iter()
+            .filter(|other| other != weather.get())
+            .collect();
+        if let Some(new_weather) = others.choose(&mut rand::thread_rng()) {
+            next_weather.set(*new_weather);
+        }
+        timer.0 = random_weather_timer();
+    }
+
+    /// Switches to the next weather by hand, and restarts the [WeatherTimer].
+    pub(super) fn cycle_weather(
+        input: ActionInput,
+        mut timer: ResMut<WeatherTimer>,
+        weather: Res<State<Weather>>,
+        mut next_weather: ResMut<NextState<Weather>>,
+    ) {
+        if !input.just_pressed(InputAction::CycleWeather) {
+            return;
+        }
+
+        let current = Weather::ALL
+            .iter()
+            .position(|other| other == weather.get())
+            .unwrap_or(0);
+        let new_weather = Weather::ALL[(current + 1) % Weather::ALL.len()];
+        info!("Weather: {new_weather:?}");
+
+        next_weather.set(new_weather);
+        timer.0 = random_weather_timer();
+    }
+
+    /// Adds a snow layer on top of randomly ticked voxels that are exposed to the sky while it's snowing,
+    /// and melts them while it isn't.
+    pub(super) fn accumulate_snow(
+        mut block_ticks: EventReader<BlockTick>,
+        mut voxel_world: VoxelWorld,
+        weather: Res<State<Weather>>,
+    ) {
+        let mut rng = rand::thread_rng();
+        let snowing = *weather.get() == Weather::Snow;
+
+        for block_tick in block_ticks.read() {
+            if block_tick.kind != BlockTickKind::Random {
+                continue;
+            }
+
+            let above_pos = block_tick.voxel_pos + IVec3::Y;
+            let Some(above) = voxel_world.get_block(above_pos) else {
+                continue;
+            };
+
+            if !snowing {
+                if above.id == Voxel::SNOW_LAYER.id && rng.gen_bool(SNOW_MELT_CHANCE) {
+                    let melted = if above.state > 1 {
+                        Voxel::SNOW_LAYER.with_state(above.state - 1)
+                    } else {
+                        Voxel::AIR
+                    };
+                    voxel_world.set_block(above_pos, melted);
+                }
+                continue;
+            }
+
+            if !is_exposed_to_sky(&voxel_world, above_pos) {
+                continue;
+            }
+
+            if above == Voxel::AIR {
+                voxel_world.set_block(above_pos, Voxel::SNOW_LAYER.with_state(1));
+            } else if above.id == Voxel::SNOW_LAYER.id && above.state < MAX_SNOW_LAYERS {
+                voxel_world.set_block(above_pos, Voxel::SNOW_LAYER.with_state(above.state + 1));
+            }
+        }
+    }
+
+    /// Checks that there is nothing but air above `voxel_pos`, up to [SNOW_SKY_CHECK_HEIGHT] voxels.
+    /// Unloaded voxels count as air.
+    fn is_exposed_to_sky(voxel_world: &VoxelWorld, voxel_pos: IVec3) -> bool {
+        (1..=SNOW_SKY_CHECK_HEIGHT).all(|height| {
+            !voxel_world
+                .get_block(voxel_pos + IVec3::Y * height)
+                .is_some_and(|voxel| voxel != Voxel::AIR)
+        })
+    }
+}
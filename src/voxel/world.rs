@@ -0,0 +1,266 @@
+use std::borrow::Cow;
+
+use bevy::{ecs::system::SystemParam, prelude::*, render::primitives::Frustum, utils::HashMap};
+
+use super::{
+    color::VoxelMode,
+    generation::{
+        LocalVoxelPosition, VoxelChunk, VoxelChunkMap, VoxelChunkPosition, VoxelChunkWidth,
+    },
+    noise::TerrainNoise,
+    raycast::{raycast, VoxelRaycastHit},
+    render::ChunkRenderQueue,
+    Voxel,
+};
+
+/// System param for reading and writing voxels by their world voxel position, without having to deal with chunks.
+/// The world voxel position of a point in the world is its [rounded](Vec3::round) position, as voxels are centered on
+/// their positions.
+///
+/// Only loaded chunks can be read and written. Voxels in other chunks read as [None], and writes to them are
+/// dropped, as the chunk would be generated or loaded from the save as it was anyway.
+///
+/// Changed chunks are saved and sent to clients like any other change, and remeshed when chunks are rendered.
+/// Without rendering, like on a dedicated server, there is no [ChunkRenderQueue].
+///
+/// ```ignore
+/// fn place_block_below(camera_query: Query<&Transform, With<Camera3d>>, mut voxel_world: VoxelWorld) {
+///     let below = camera_query.single().translation.round().as_ivec3() - IVec3::Y;
+///     if voxel_world.get_block(below) == Some(Voxel::AIR) {
+///         voxel_world.set_block(below, Voxel::STONE);
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct VoxelWorld<'w, 's> {
+    chunk_query: Query<'w, 's, &'static mut VoxelChunk>,
+    chunk_render_queue: Option<ResMut<'w, ChunkRenderQueue>>,
+    voxel_chunk_map: Res<'w, VoxelChunkMap>,
+    chunk_width: Res<'w, VoxelChunkWidth>,
+    /// Only the server has it, to generate chunks with.
+    terrain_noise: Option<Res<'w, TerrainNoise>>,
+    voxel_mode: Option<Res<'w, VoxelMode>>,
+}
+
+impl VoxelWorld<'_, '_> {
+    /// Gets the voxel at a world voxel position. Returns `None` if the chunk containing it isn't loaded.
+    pub fn get_block(&self, voxel_pos: IVec3) -> Option<Voxel> {
+        let (chunk_pos, local_pos) =
+            VoxelChunkPosition::world_to_local(voxel_pos, &self.chunk_width);
+        let chunk_entity = self.voxel_chunk_map.0.get(&chunk_pos)?;
+
+        self.chunk_query
+            .get(*chunk_entity)
+            .ok()?
+            .get_voxel(local_pos, &self.chunk_width)
+    }
+
+    /// The positions and entities of the loaded chunks that are at least partly inside a sphere, given in world
+    /// coordinates. Chunk positions are in chunks, multiply them by the chunk width for their world position.
+    pub fn chunks_in_sphere(&self, center: Vec3, radius: f32) -> Vec<(IVec3, Entity)> {
+        Self::chunk_positions(self.voxel_chunk_map.chunks_in_sphere(
+            center,
+            radius,
+            &self.chunk_width,
+        ))
+    }
+
+    /// The positions and entities of the loaded chunks that are at least partly inside a box between two opposite
+    /// corners, given in world coordinates.
+    pub fn chunks_in_aabb(&self, a: Vec3, b: Vec3) -> Vec<(IVec3, Entity)> {
+        Self::chunk_positions(self.voxel_chunk_map.chunks_in_aabb(a, b, &self.chunk_width))
+    }
+
+    /// The positions and entities of the loaded chunks that a camera with this [Frustum] can at least partly see.
+    pub fn chunks_in_frustum(&self, frustum: &Frustum) -> Vec<(IVec3, Entity)> {
+        Self::chunk_positions(
+            self.voxel_chunk_map
+                .chunks_in_frustum(frustum, &self.chunk_width),
+        )
+    }
+
+    fn chunk_positions(chunks: Vec<(VoxelChunkPosition, Entity)>) -> Vec<(IVec3, Entity)> {
+        chunks
+            .into_iter()
+            .map(|(chunk_pos, entity)| (chunk_pos.0, entity))
+            .collect()
+    }
+
+    /// Gets a loaded chunk, for reading many of its voxels at once.
+    pub(super) fn get_chunk(&self, chunk_pos: VoxelChunkPosition) -> Option<&VoxelChunk> {
+        let chunk_entity = self.voxel_chunk_map.0.get(&chunk_pos)?;
+        self.chunk_query.get(*chunk_entity).ok()
+    }
+
+    /// Casts a ray against the solid voxels of the loaded chunks, see [raycast]. Voxels in chunks that aren't loaded
+    /// are passed through.
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+    ) -> Option<VoxelRaycastHit> {
+        raycast(origin, direction, max_distance, |voxel_pos| {
+            self.get_block(voxel_pos)
+                .is_some_and(|voxel| voxel.is_solid())
+        })
+    }
+
+    /// Iterates over the voxels in the box between two opposite corners, including both, with their world voxel
+    /// positions. Voxels in chunks that aren't loaded are skipped.
+    ///
+    /// The voxels are visited chunk by chunk, so every chunk is only looked up once.
+    pub fn iter_region(&self, a: IVec3, b: IVec3) -> impl Iterator<Item = (IVec3, Voxel)> + '_ {
+        self.region_voxels(a, b, false)
+    }
+
+    /// Like [VoxelWorld::iter_region], but the voxels in chunks that aren't loaded are generated, as they would be
+    /// without any edits. Clients can't generate chunks, so they still skip them.
+    ///
+    /// Generating a chunk is slow, so this is meant for small regions, or regions that are mostly loaded.
+    pub fn iter_region_generating(
+        &self,
+        a: IVec3,
+        b: IVec3,
+    ) -> impl Iterator<Item = (IVec3, Voxel)> + '_ {
+        self.region_voxels(a, b, true)
+    }
+
+    fn region_voxels(
+        &self,
+        a: IVec3,
+        b: IVec3,
+        generate: bool,
+    ) -> impl Iterator<Item = (IVec3, Voxel)> + '_ {
+        let (min, max) = (a.min(b), a.max(b));
+        let (min_chunk_pos, _) = VoxelChunkPosition::world_to_local(min, &self.chunk_width);
+        let (max_chunk_pos, _) = VoxelChunkPosition::world_to_local(max, &self.chunk_width);
+        let last_local_pos = IVec3::splat(self.chunk_width.0 as i32 - 1);
+
+        box_positions(min_chunk_pos.0, max_chunk_pos.0)
+            .filter_map(move |chunk_pos| {
+                let chunk_pos = VoxelChunkPosition(chunk_pos);
+                let chunk = match self.get_chunk(chunk_pos) {
+                    Some(chunk) => Cow::Borrowed(chunk),
+                    None if generate => Cow::Owned(VoxelChunk::from_noise(
+                        &chunk_pos,
+                        &self.chunk_width,
+                        self.terrain_noise.as_deref()?,
+                        self.voxel_mode.as_deref().copied().unwrap_or_default(),
+                    )),
+                    None => return None,
+                };
+
+                let origin = chunk_pos.0 * self.chunk_width.0 as i32;
+                let voxels = box_positions(
+                    (min - origin).max(IVec3::ZERO),
+                    (max - origin).min(last_local_pos),
+                )
+                .filter_map(move |local_pos| {
+                    let voxel = chunk
+                        .get_voxel(LocalVoxelPosition::from_ivec3(local_pos), &self.chunk_width)?;
+                    Some((origin + local_pos, voxel))
+                });
+
+                Some(voxels)
+            })
+            .flatten()
+    }
+
+    /// Replaces the voxel at a world voxel position, and pushes the affected chunks to the [ChunkRenderQueue].
+    ///
+    /// Returns false if the chunk containing the voxel isn't loaded, in which case nothing happens.
+    pub fn set_block(&mut self, voxel_pos: IVec3, voxel: Voxel) -> bool {
+        let (chunk_pos, local_pos) =
+            VoxelChunkPosition::world_to_local(voxel_pos, &self.chunk_width);
+        let Some(chunk_entity) = self.voxel_chunk_map.0.get(&chunk_pos) else {
+            return false;
+        };
+        let Ok(mut chunk) = self.chunk_query.get_mut(*chunk_entity) else {
+            return false;
+        };
+
+        chunk.set_voxel(local_pos, voxel, &self.chunk_width);
+        if let Some(chunk_render_queue) = &mut self.chunk_render_queue {
+            chunk_render_queue.push_voxel_change(
+                chunk_pos,
+                local_pos,
+                &self.voxel_chunk_map,
+                &self.chunk_width,
+            );
+        }
+
+        true
+    }
+
+    /// Pushes the chunks around a voxel to the [ChunkRenderQueue], like [VoxelWorld::set_block] does, for changes the
+    /// chunk doesn't hold itself, like those of [micro blocks](super::micro::MicroBlock).
+    pub(super) fn remesh_block(&mut self, voxel_pos: IVec3) {
+        let (chunk_pos, local_pos) =
+            VoxelChunkPosition::world_to_local(voxel_pos, &self.chunk_width);
+
+        if let Some(chunk_render_queue) = &mut self.chunk_render_queue {
+            chunk_render_queue.push_voxel_change(
+                chunk_pos,
+                local_pos,
+                &self.voxel_chunk_map,
+                &self.chunk_width,
+            );
+        }
+    }
+
+    /// Replaces many voxels at once. Voxels in chunks that aren't loaded are skipped.
+    ///
+    /// Use this over [VoxelWorld::set_block] for big edits, since every affected chunk is only looked up once.
+    /// Returns how many voxels were set.
+    pub fn set_blocks(&mut self, voxels: impl IntoIterator<Item = (IVec3, Voxel)>) -> usize {
+        let mut chunks: HashMap<VoxelChunkPosition, Vec<(LocalVoxelPosition, Voxel)>> =
+            HashMap::new();
+
+        for (voxel_pos, voxel) in voxels {
+            let (chunk_pos, local_pos) =
+                VoxelChunkPosition::world_to_local(voxel_pos, &self.chunk_width);
+            chunks
+                .entry(chunk_pos)
+                .or_default()
+                .push((local_pos, voxel));
+        }
+
+        let mut count = 0;
+
+        for (chunk_pos, changes) in chunks {
+            let Some(chunk_entity) = self.voxel_chunk_map.0.get(&chunk_pos) else {
+                continue;
+            };
+            let Ok(mut chunk) = self.chunk_query.get_mut(*chunk_entity) else {
+                continue;
+            };
+
+            for (local_pos, voxel) in &changes {
+                chunk.set_voxel(*local_pos, *voxel, &self.chunk_width);
+            }
+
+            count += changes.len();
+
+            if let Some(chunk_render_queue) = &mut self.chunk_render_queue {
+                for (local_pos, _) in changes {
+                    chunk_render_queue.push_voxel_change(
+                        chunk_pos,
+                        local_pos,
+                        &self.voxel_chunk_map,
+                        &self.chunk_width,
+                    );
+                }
+            }
+        }
+
+        count
+    }
+}
+
+/// Every position in the box between `min` and `max`, including both, in the order voxels are stored in a chunk.
+pub(super) fn box_positions(min: IVec3, max: IVec3) -> impl Iterator<Item = IVec3> {
+    (min.z..=max.z).flat_map(move |z| {
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+    })
+}